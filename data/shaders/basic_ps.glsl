@@ -1,11 +1,23 @@
 #version 150 core
+#include <globals>
+#include <section>
+#include <fade>
+#include <fog>
 
 in vec2 v_TexCoord;
 in vec4 v_Color;
+in vec3 v_World;
+flat in float v_Fade;
 out vec4 Target0;
 
 uniform sampler2D t_Map;
 
 void main() {
+    fade(v_Fade);
+    if (section(v_World)) {
+        Target0 = vec4(u_CapColor.rgb, 1.0);
+        return;
+    }
     Target0 = v_Color * texture(t_Map, v_TexCoord);
+    Target0.rgb = fog(Target0.rgb, v_World);
 }
@@ -1,21 +1,40 @@
 #version 150 core
 #include <globals>
+#include <displacement>
+
+// Set to 1 by the pipelines of meshes with morph targets.
+#ifndef DISPLACEMENT
+#define DISPLACEMENT 0
+#endif
 
 in vec4 a_Position;
 in vec4 a_Normal;
 in vec2 a_TexCoord;
+in vec4 a_Color;
 out vec2 v_TexCoord;
 out vec4 v_Color;
+out vec3 v_World;
+flat out float v_Fade;
 
 in vec4 i_World0;
 in vec4 i_World1;
 in vec4 i_World2;
 in vec4 i_Color;
 in vec4 i_UvRange;
+in float i_Fade;
 
 void main() {
     mat4 m_World = transpose(mat4(i_World0, i_World1, i_World2, vec4(0.0, 0.0, 0.0, 1.0)));
     v_TexCoord = mix(i_UvRange.xy, i_UvRange.zw, a_TexCoord);
-    v_Color = i_Color;
-    gl_Position = u_ViewProj * m_World * a_Position;
+    vec4 position = a_Position;
+#if DISPLACEMENT
+    vec3 normal = a_Normal.xyz;
+    vec3 tangent = vec3(0.0);
+    displace(position.xyz, normal, tangent);
+#endif
+    vec4 world = m_World * position;
+    v_Color = i_Color * a_Color;
+    v_World = world.xyz;
+    v_Fade = i_Fade;
+    gl_Position = u_ViewProj * world;
 }
@@ -0,0 +1,33 @@
+#version 150 core
+#include <post>
+
+in vec2 v_TexCoord;
+out vec4 Target0;
+
+// u_Params.w selects the pass:
+// 0.0: bright pass, x: threshold
+// 1.0: blur, xy: direction in texels
+// 2.0: composite of t_Extra over t_Input, x: intensity
+
+const float WEIGHTS[5] = float[](0.2270270270, 0.1945945946, 0.1216216216, 0.0540540541, 0.0162162162);
+
+void main() {
+    if (u_Params.w < 0.5) {
+        vec4 color = texture(t_Input, v_TexCoord);
+        float brightness = max(max(color.r, color.g), color.b);
+        float contribution = max(brightness - u_Params.x, 0.0) / max(brightness, 0.0001);
+        Target0 = vec4(color.rgb * contribution, 1.0);
+    } else if (u_Params.w < 1.5) {
+        vec2 step = u_Params.xy * u_Resolution.zw;
+        vec3 sum = texture(t_Input, v_TexCoord).rgb * WEIGHTS[0];
+        for (int i = 1; i < 5; ++i) {
+            sum += texture(t_Input, v_TexCoord + float(i) * step).rgb * WEIGHTS[i];
+            sum += texture(t_Input, v_TexCoord - float(i) * step).rgb * WEIGHTS[i];
+        }
+        Target0 = vec4(sum, 1.0);
+    } else {
+        vec4 color = texture(t_Input, v_TexCoord);
+        vec3 bloom = texture(t_Extra, v_TexCoord).rgb;
+        Target0 = vec4(color.rgb + u_Params.x * bloom, color.a);
+    }
+}
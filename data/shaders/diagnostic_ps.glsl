@@ -0,0 +1,45 @@
+#version 150 core
+
+#define MODE_OVERDRAW   0
+#define MODE_MIP_LEVEL  1
+#define MODE_NORMALS    2
+
+in vec3 v_Normal;
+in vec2 v_TexCoord;
+in vec2 v_MeshCoord;
+flat in vec4 v_MatParams;
+out vec4 Target0;
+
+uniform sampler2D t_Map;
+
+// colors of mipmap levels 0 (or magnified) to 4 and above
+const vec3 MIP_COLORS[5] = vec3[5](
+    vec3(0.0, 0.0, 1.0),
+    vec3(0.0, 1.0, 1.0),
+    vec3(0.0, 1.0, 0.0),
+    vec3(1.0, 1.0, 0.0),
+    vec3(1.0, 0.0, 0.0)
+);
+
+void main() {
+    int mode = int(v_MatParams.x + 0.5);
+    if (mode == MODE_OVERDRAW) {
+        // accumulated with additive blending
+        Target0 = vec4(0.1, 0.04, 0.01, 0.0);
+    } else if (mode == MODE_MIP_LEVEL) {
+        vec2 texels = v_TexCoord * vec2(textureSize(t_Map, 0));
+        float rho = max(length(dFdx(texels)), length(dFdy(texels)));
+        float level = clamp(log2(max(rho, 1e-6)), 0.0, 4.0);
+        int lower = int(floor(level));
+        int upper = min(lower + 1, 4);
+        Target0 = vec4(mix(MIP_COLORS[lower], MIP_COLORS[upper], fract(level)), 1.0);
+    } else if (mode == MODE_NORMALS) {
+        Target0 = vec4(normalize(v_Normal) * 0.5 + 0.5, 1.0);
+    } else {
+        // checkerboard of 8x8 cells tinted by the texture co-ordinates
+        vec2 cell = floor(v_MeshCoord * 8.0);
+        float checker = mod(cell.x + cell.y, 2.0);
+        vec3 tint = vec3(fract(v_MeshCoord), 1.0);
+        Target0 = vec4(tint * mix(0.35, 1.0, checker), 1.0);
+    }
+}
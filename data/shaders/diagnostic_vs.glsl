@@ -0,0 +1,28 @@
+#version 150 core
+#include <globals>
+
+in vec4 a_Position;
+in vec4 a_Normal;
+in vec2 a_TexCoord;
+out vec3 v_Normal;
+out vec2 v_TexCoord;
+out vec2 v_MeshCoord;
+flat out vec4 v_MatParams;
+
+in vec4 i_World0;
+in vec4 i_World1;
+in vec4 i_World2;
+in vec4 i_Normal0;
+in vec4 i_Normal1;
+in vec4 i_Normal2;
+in vec4 i_MatParams;
+in vec4 i_UvRange;
+
+void main() {
+    mat4 m_World = transpose(mat4(i_World0, i_World1, i_World2, vec4(0.0, 0.0, 0.0, 1.0)));
+    v_Normal = normalize(transpose(mat3(i_Normal0.xyz, i_Normal1.xyz, i_Normal2.xyz)) * a_Normal.xyz);
+    v_TexCoord = mix(i_UvRange.xy, i_UvRange.zw, a_TexCoord);
+    v_MeshCoord = a_TexCoord;
+    v_MatParams = i_MatParams;
+    gl_Position = u_ViewProj * m_World * a_Position;
+}
@@ -0,0 +1,33 @@
+// Morph target displacements of the vertices.
+
+#ifndef MAX_TARGETS
+#define MAX_TARGETS 8U
+#endif
+
+// Toggles displacement contributions to `a_Position/a_Normal/a_Tangent`.
+struct DisplacementContribution {
+    // position: 1.0 if morph target weights should influence a_Position
+    // normal: 1.0 if morph target weights should influence a_Normal
+    // tangent: 1.0 if morph target weights should influence a_Tangent
+    // weight: The weight to be applied.
+    float position, normal, tangent, weight;
+};
+
+layout(std140) uniform b_DisplacementContributions {
+    DisplacementContribution u_DisplacementContributions[MAX_TARGETS];
+};
+
+uniform sampler2D u_Displacements;
+
+// Adds the weighted displacements of the morph targets to the attributes of
+// the vertex.
+void displace(inout vec3 position, inout vec3 normal, inout vec3 tangent) {
+    uint num_targets = uvec2(textureSize(u_Displacements, 0)).y / 3U;
+    for (uint i = 0U; i < min(num_targets, MAX_TARGETS); ++i) {
+        DisplacementContribution disp = u_DisplacementContributions[i];
+        if (disp.weight == 0.0) continue;
+        position += disp.position * disp.weight * texelFetch(u_Displacements, ivec2(gl_VertexID, 3U*i+0U), 0).xyz;
+        normal   += disp.normal   * disp.weight * texelFetch(u_Displacements, ivec2(gl_VertexID, 3U*i+1U), 0).xyz;
+        tangent  += disp.tangent  * disp.weight * texelFetch(u_Displacements, ivec2(gl_VertexID, 3U*i+2U), 0).xyz;
+    }
+}
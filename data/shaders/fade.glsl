@@ -0,0 +1,21 @@
+// Dithered fade-out of distant and small meshes.
+
+// Ordered 4x4 dither thresholds.
+const float DITHER[16] = float[](
+    0.5, 8.5, 2.5, 10.5,
+    12.5, 4.5, 14.5, 6.5,
+    3.5, 11.5, 1.5, 9.5,
+    15.5, 7.5, 13.5, 5.5
+);
+
+// Discards the fragments of a mesh faded to `amount`, where `1.0` is fully
+// drawn, in a screen space pattern keeping that share of the fragments.
+void fade(float amount) {
+    if (amount >= 1.0) {
+        return;
+    }
+    ivec2 p = ivec2(gl_FragCoord.xy) % 4;
+    if (amount * 16.0 <= DITHER[p.y * 4 + p.x]) {
+        discard;
+    }
+}
@@ -0,0 +1,26 @@
+// Scene fog, requires <globals>.
+
+// Set to 0 by the pipelines of meshes opting out of the fog.
+#ifndef FOG
+#define FOG 1
+#endif
+
+// Blends `color` into the fog color by the distance of `world` from the camera.
+vec3 fog(vec3 color, vec3 world) {
+#if FOG
+    if (u_FogParams.x < 0.5) {
+        return color;
+    }
+    float distance = length((u_View * vec4(world, 1.0)).xyz);
+    float visibility;
+    if (u_FogParams.x < 1.5) {
+        visibility = (u_FogParams.z - distance) / max(u_FogParams.z - u_FogParams.y, 0.0001);
+    } else {
+        float depth = u_FogParams.y * distance;
+        visibility = exp(-depth * depth);
+    }
+    return mix(u_FogColor.rgb, color, clamp(visibility, 0.0, 1.0));
+#else
+    return color;
+#endif
+}
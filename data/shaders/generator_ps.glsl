@@ -0,0 +1,73 @@
+#version 150 core
+#extension GL_ARB_shader_bit_encoding : enable
+
+layout(std140) uniform b_GeneratorParams {
+    // x: number of vertices, y: width of the target in texels
+    uvec4 u_Size;
+    // parameters of the generator
+    vec4 u_Params;
+};
+
+// optional input of the generator, white if none is given
+uniform sampler2D t_Input;
+
+out uvec4 Target0;
+
+struct GeneratedVertex {
+    vec3 position;
+    vec3 normal;
+    // w: handedness of the bitangent
+    vec4 tangent;
+    vec2 uv;
+    vec4 color;
+};
+
+GeneratedVertex generated_vertex() {
+    return GeneratedVertex(
+        vec3(0.0),
+        vec3(0.0, 1.0, 0.0),
+        vec4(1.0, 0.0, 0.0, 1.0),
+        vec2(0.0),
+        vec4(1.0)
+    );
+}
+
+// provided by the generator
+GeneratedVertex generate(int index);
+
+// packs four normalized values as signed bytes, like `I8Norm`
+uint pack_snorm(vec4 value) {
+    ivec4 bytes = ivec4(round(clamp(value, -1.0, 1.0) * 127.0)) & 0xFF;
+    return uint(bytes.x | (bytes.y << 8) | (bytes.z << 16) | (bytes.w << 24));
+}
+
+void main() {
+    // vertices are laid out as 5 consecutive texels, in rows of `u_Size.y` texels
+    ivec2 coord = ivec2(gl_FragCoord.xy);
+    int texel = coord.y * int(u_Size.y) + coord.x;
+    int index = texel / 5;
+    if (index >= int(u_Size.x)) {
+        Target0 = uvec4(0u);
+        return;
+    }
+
+    GeneratedVertex vertex = generate(index);
+    int chunk = texel - index * 5;
+    if (chunk == 0) {
+        Target0 = floatBitsToUint(vec4(vertex.position, 1.0));
+    } else if (chunk == 1) {
+        Target0 = uvec4(
+            floatBitsToUint(vertex.uv),
+            pack_snorm(vec4(vertex.normal, 0.0)),
+            pack_snorm(vertex.tangent)
+        );
+    } else if (chunk == 2) {
+        // joint indices
+        Target0 = uvec4(0u);
+    } else if (chunk == 3) {
+        // joint weights
+        Target0 = floatBitsToUint(vec4(1.0));
+    } else {
+        Target0 = floatBitsToUint(vertex.color);
+    }
+}
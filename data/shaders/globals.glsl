@@ -2,5 +2,23 @@ layout(std140) uniform b_Globals {
     mat4 u_ViewProj;
     mat4 u_InverseProj;
     mat4 u_View;
+    // x: environment intensity, y: environment rotation about the Y axis,
+    // z: 1.0 if the environment map lights PBR materials
+    vec4 u_EnvParams;
+    // rgb: ambient light color premultiplied by its intensity
+    vec4 u_Ambient;
+    // xyz: section plane normal, w: negated distance from the origin
+    vec4 u_ClipPlane;
+    // rgb: section cap color, a: 1.0 if caps are enabled
+    vec4 u_CapColor;
+    // x: shadow depth bias, y: shadow filter kernel radius in texels
+    vec4 u_ShadowParams;
+    // rgb: fog color
+    vec4 u_FogColor;
+    // x: 0.0 without fog, 1.0 for linear fog with y: near and z: far distances,
+    // 2.0 for exponential squared fog with y: density
+    vec4 u_FogParams;
+    // xy: size of the render target in pixels
+    vec4 u_Resolution;
     uint u_NumLights;
 };
@@ -1,4 +1,10 @@
 #version 150 core
+#include <lights>
+#include <globals>
+#include <section>
+#include <fade>
+#include <fog>
+#include <shadow>
 
 in vec4 v_ResultColor;
 flat in vec4 v_ResultColorFlat;
@@ -6,6 +12,9 @@ flat in float v_Smooth;
 in vec4 v_LightEval[2];
 flat in vec4 v_LightEvalFlat[2];
 in vec4 v_ShadowCoord[2];
+in vec3 v_World;
+flat in float v_Fade;
+in float v_Alpha;
 
 out vec4 Target0;
 
@@ -13,15 +22,21 @@ uniform sampler2DShadow t_Shadow0;
 uniform sampler2DShadow t_Shadow1;
 
 void main() {
+    fade(v_Fade);
+    if (section(v_World)) {
+        Target0 = vec4(u_CapColor.rgb, 1.0);
+        return;
+    }
     Target0 = mix(v_ResultColorFlat, v_ResultColor, v_Smooth);
+#if SHADOWS
     if (v_ShadowCoord[0].w != 0.0) {
-        vec3 coord = v_ShadowCoord[0].xyz / v_ShadowCoord[0].w;
-        float shadow = texture(t_Shadow0, 0.5 * coord + 0.5);
+        float shadow = sample_shadow(t_Shadow0, v_ShadowCoord[0]);
         Target0 += shadow * mix(v_LightEvalFlat[0], v_LightEval[0], v_Smooth);
     }
     if (v_ShadowCoord[1].w != 0.0) {
-        vec3 coord = v_ShadowCoord[1].xyz / v_ShadowCoord[1].w;
-        float shadow = texture(t_Shadow1, 0.5 * coord + 0.5);
+        float shadow = sample_shadow(t_Shadow1, v_ShadowCoord[1]);
         Target0 += shadow * mix(v_LightEvalFlat[1], v_LightEval[1], v_Smooth);
     }
+#endif
+    Target0 = vec4(fog(Target0.rgb, v_World), v_Alpha);
 }
@@ -1,6 +1,12 @@
 #version 150 core
 #include <lights>
 #include <globals>
+#include <displacement>
+
+// Set to 1 by the pipelines of meshes with morph targets.
+#ifndef DISPLACEMENT
+#define DISPLACEMENT 0
+#endif
 
 #define MAX_SHADOWS 2
 
@@ -12,23 +18,37 @@ flat out float v_Smooth;
 out vec4 v_LightEval[MAX_SHADOWS];
 flat out vec4 v_LightEvalFlat[MAX_SHADOWS];
 out vec4 v_ShadowCoord[MAX_SHADOWS];
+out vec3 v_World;
+out float v_Alpha;
+flat out float v_Fade;
 
 in vec4 i_World0;
 in vec4 i_World1;
 in vec4 i_World2;
+in vec4 i_Normal0;
+in vec4 i_Normal1;
+in vec4 i_Normal2;
 in vec4 i_MatParams;
 in vec4 i_Color;
 in vec4 i_UvRange;
+in float i_Fade;
 
 void main() {
     mat4 m_World = transpose(mat4(i_World0, i_World1, i_World2, vec4(0.0, 0.0, 0.0, 1.0)));
-    vec4 world = m_World * a_Position;
-    vec3 normal = normalize(mat3(m_World) * a_Normal.xyz);
+    vec4 position = a_Position;
+    vec3 local_normal = a_Normal.xyz;
+#if DISPLACEMENT
+    vec3 tangent = vec3(0.0);
+    displace(position.xyz, local_normal, tangent);
+#endif
+    vec4 world = m_World * position;
+    v_World = world.xyz;
+    vec3 normal = normalize(transpose(mat3(i_Normal0.xyz, i_Normal1.xyz, i_Normal2.xyz)) * local_normal);
     for(int i=0; i<MAX_SHADOWS; ++i) {
         v_ShadowCoord[i] = vec4(0.0);
         v_LightEval[i] = v_LightEvalFlat[i] = vec4(0.0);
     }
-    v_ResultColor = vec4(0.0);
+    v_ResultColor = u_Ambient * i_Color;
     v_Smooth = i_MatParams.x;
 
     for(uint i=0U; i < min(MAX_LIGHTS, u_NumLights); ++i) {
@@ -44,16 +64,20 @@ void main() {
         v_ResultColor += light.intensity.x * i_Color * irradiance; //ambient
         vec4 color = light.intensity.y * max(0.0, dot_nl) * i_Color * light.color;
         // compute shadow coordinates
+#if SHADOWS
         int shadow_index = light.shadow_params[0];
         if (0 <= shadow_index && shadow_index < MAX_SHADOWS) {
             v_ShadowCoord[shadow_index] = light.projection * world;
             v_LightEval[shadow_index] = color;
             v_LightEvalFlat[shadow_index] = color;
-        } else {
-            v_ResultColor += color;
+            continue;
         }
+#endif
+        v_ResultColor += color;
     }
 
     v_ResultColorFlat = v_ResultColor;
+    v_Alpha = i_Color.a;
+    v_Fade = i_Fade;
     gl_Position = u_ViewProj * world;
 }
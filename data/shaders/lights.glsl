@@ -1,4 +1,12 @@
+#ifndef MAX_LIGHTS
 #define MAX_LIGHTS  4U
+#endif
+
+// Set to 0 by the pipelines drawing without shadow maps, leaving all the
+// lights unshadowed.
+#ifndef SHADOWS
+#define SHADOWS 1
+#endif
 
 struct Light {
     mat4 projection;
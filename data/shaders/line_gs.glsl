@@ -0,0 +1,57 @@
+#version 150 core
+#include <globals>
+
+// Expands each line segment into a screen space quad.
+
+layout(lines) in;
+layout(triangle_strip, max_vertices = 4) out;
+
+in vec4 l_Color[];
+in vec3 l_World[];
+in float l_Distance[];
+flat in vec4 l_Params[];
+flat in float l_Fade[];
+out vec4 v_Color;
+out vec3 v_World;
+out float v_Distance;
+flat out vec4 v_Params;
+flat out float v_Fade;
+
+void emit(vec4 position, vec2 offset, float t) {
+    gl_Position = position + vec4(offset * position.w, 0.0, 0.0);
+    v_Color = mix(l_Color[0], l_Color[1], t);
+    v_World = mix(l_World[0], l_World[1], t);
+    v_Distance = mix(l_Distance[0], l_Distance[1], t);
+    v_Params = l_Params[0];
+    v_Fade = l_Fade[0];
+    EmitVertex();
+}
+
+void main() {
+    // clip the segment against the near plane, so that both ends project
+    vec4 p0 = gl_in[0].gl_Position;
+    vec4 p1 = gl_in[1].gl_Position;
+    float d0 = p0.z + p0.w;
+    float d1 = p1.z + p1.w;
+    if (d0 < 0.0 && d1 < 0.0) {
+        return;
+    }
+    float t0 = d0 < 0.0 ? d0 / (d0 - d1) : 0.0;
+    float t1 = d1 < 0.0 ? d0 / (d0 - d1) : 1.0;
+    vec4 c0 = mix(p0, p1, t0);
+    vec4 c1 = mix(p0, p1, t1);
+
+    // x: width in pixels
+    vec2 half_size = 0.5 * u_Resolution.xy;
+    vec2 dir = c1.xy / c1.w * half_size - c0.xy / c0.w * half_size;
+    dir = length(dir) > 1e-6 ? normalize(dir) : vec2(1.0, 0.0);
+    // ends are extended by half the width, closing the joints of strips
+    vec2 along = 0.5 * l_Params[0].x * dir / half_size;
+    vec2 across = 0.5 * l_Params[0].x * vec2(-dir.y, dir.x) / half_size;
+
+    emit(c0, -along + across, t0);
+    emit(c0, -along - across, t0);
+    emit(c1, along + across, t1);
+    emit(c1, along - across, t1);
+    EndPrimitive();
+}
@@ -0,0 +1,24 @@
+#version 150 core
+#include <globals>
+#include <section>
+#include <fade>
+#include <fog>
+
+in vec4 v_Color;
+in vec3 v_World;
+in float v_Distance;
+// x: width in pixels, y: dash size, z: gap size, w: 1.0 for dashed lines
+flat in vec4 v_Params;
+flat in float v_Fade;
+out vec4 Target0;
+
+void main() {
+    fade(v_Fade);
+    // lines have no inside, so the section caps don't apply
+    section(v_World);
+    if (v_Params.w > 0.0 && mod(v_Distance, v_Params.y + v_Params.z) > v_Params.y) {
+        discard;
+    }
+    Target0 = v_Color;
+    Target0.rgb = fog(Target0.rgb, v_World);
+}
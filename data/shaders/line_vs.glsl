@@ -0,0 +1,29 @@
+#version 150 core
+#include <globals>
+
+in vec4 a_Position;
+in vec2 a_TexCoord;
+in vec4 a_Color;
+out vec4 l_Color;
+out vec3 l_World;
+out float l_Distance;
+flat out vec4 l_Params;
+flat out float l_Fade;
+
+in vec4 i_World0;
+in vec4 i_World1;
+in vec4 i_World2;
+in vec4 i_Color;
+in vec4 i_MatParams;
+in float i_Fade;
+
+void main() {
+    mat4 m_World = transpose(mat4(i_World0, i_World1, i_World2, vec4(0.0, 0.0, 0.0, 1.0)));
+    vec4 world = m_World * a_Position;
+    l_Color = i_Color * a_Color;
+    l_World = world.xyz;
+    l_Distance = a_TexCoord.x;
+    l_Params = i_MatParams;
+    l_Fade = i_Fade;
+    gl_Position = u_ViewProj * world;
+}
@@ -1,6 +1,9 @@
 #version 150 core
 #include <lights>
 #include <globals>
+#include <section>
+#include <fade>
+#include <fog>
 
 const int BASE_COLOR_MAP          = 1 << 0;
 const int NORMAL_MAP              = 1 << 1;
@@ -14,6 +17,9 @@ uniform sampler2D u_NormalSampler;
 uniform sampler2D u_EmissiveSampler;
 uniform sampler2D u_MetallicRoughnessSampler;
 uniform sampler2D u_OcclusionSampler;
+uniform samplerCube u_EnvironmentSampler;
+uniform samplerCube u_ProbeSampler0;
+uniform samplerCube u_ProbeSampler1;
 
 layout(std140) uniform b_PbrParams {
     vec4 u_BaseColorFactor;
@@ -25,9 +31,22 @@ layout(std140) uniform b_PbrParams {
     int u_PbrFlags;
 };
 
+// Reflection probes around the mesh, with the bounds of their volume in
+// world space, `min.w` set if the probe is used, `max.w` the blend distance,
+// and `center.w` the intensity
+layout(std140) uniform b_Probes {
+    vec4 u_ProbeMin0;
+    vec4 u_ProbeMax0;
+    vec4 u_ProbeCenter0;
+    vec4 u_ProbeMin1;
+    vec4 u_ProbeMax1;
+    vec4 u_ProbeCenter1;
+};
+
 in vec3 v_Position;
 in vec2 v_TexCoord;
 in mat3 v_Tbn;
+flat in float v_Fade;
 
 out vec4 Target0;
 
@@ -48,6 +67,11 @@ struct PbrInfo {
 const float PI = 3.141592653589793;
 const float MIN_ROUGHNESS = 0.04;
 
+// Last mipmap level of a cube map, sampled for fully rough surfaces
+float max_lod(samplerCube map) {
+    return log2(float(textureSize(map, 0).x));
+}
+
 float smith(float ndotv, float r) {
     float tan_sq = (1.0 - ndotv * ndotv) / max((ndotv * ndotv), 0.00001);
     return 2.0 / (1.0 + sqrt(1.0 + r * r * tan_sq));
@@ -80,11 +104,49 @@ float ggx(PbrInfo pbr) {
     return roughness_sq / (PI * f * f);
 }
 
+// Analytic approximation of the pre-integrated specular BRDF for image-based lighting,
+// from "Physically Based Shading on Mobile" by Brian Karis
+vec3 environment_brdf(vec3 specular_color, float roughness, float ndotv) {
+    const vec4 c0 = vec4(-1.0, -0.0275, -0.572, 0.022);
+    const vec4 c1 = vec4(1.0, 0.0425, 1.04, -0.04);
+    vec4 r = roughness * c0 + c1;
+    float a004 = min(r.x * r.x, exp2(-9.28 * ndotv)) * r.x + r.y;
+    vec2 ab = vec2(-1.04, 1.04) * a004 + r.zw;
+    return specular_color * ab.x + ab.y;
+}
+
+// Weight of a probe at the fragment, fading out over the blend distance
+// towards the faces of its volume
+float probe_weight(vec4 box_min, vec4 box_max) {
+    if (box_min.w == 0.0) {
+        return 0.0;
+    }
+    vec3 inside = min(v_Position - box_min.xyz, box_max.xyz - v_Position);
+    float distance = min(min(inside.x, inside.y), inside.z);
+    return clamp(distance / max(box_max.w, 0.0001), 0.0, 1.0);
+}
+
+// Direction from the probe center to the point where `dir`, cast from the
+// fragment, leaves the probe volume, so that reflections line up with the
+// faces of the volume
+vec3 box_project(vec3 dir, vec4 box_min, vec4 box_max, vec4 center) {
+    vec3 first = (box_max.xyz - v_Position) / dir;
+    vec3 second = (box_min.xyz - v_Position) / dir;
+    vec3 furthest = max(first, second);
+    float distance = min(min(furthest.x, furthest.y), furthest.z);
+    return v_Position + dir * distance - center.xyz;
+}
+
 bool available(int flag) {
     return (u_PbrFlags & flag) == flag;
 }
 
 void main() {
+    fade(v_Fade);
+    if (section(v_Position)) {
+        Target0 = vec4(u_CapColor.rgb, 1.0);
+        return;
+    }
     vec3 v = normalize(u_Camera - v_Position);
 
     vec3 n;
@@ -131,7 +193,7 @@ void main() {
     // material roughness by squaring the perceptual roughness
     float alpha_roughness = perceptual_roughness * perceptual_roughness;
 
-    vec3 color = vec3(0.0);
+    vec3 color = u_Ambient.rgb * diffuse_color;
     for (uint i = 0U; i < min(MAX_LIGHTS, u_NumLights); ++i) {
         Light light = u_Lights[i];
         vec3 l = normalize(light.dir.xyz);
@@ -164,6 +226,44 @@ void main() {
         color += ndotl * light.intensity.y * light.color.rgb * (diffuse_contrib + spec_contrib);
     }
 
+    // image-based lighting from the reflection probes around the fragment, the
+    // first one taking precedence, and from the environment map for the rest
+    float weight0 = probe_weight(u_ProbeMin0, u_ProbeMax0);
+    float weight1 = min(probe_weight(u_ProbeMin1, u_ProbeMax1), 1.0 - weight0);
+    float env_weight = u_EnvParams.z > 0.0 ? 1.0 - weight0 - weight1 : 0.0;
+    if (weight0 + weight1 + env_weight > 0.0) {
+        vec3 reflection = reflect(-v, n);
+        vec3 irradiance = vec3(0.0);
+        vec3 radiance = vec3(0.0);
+        if (weight0 > 0.0) {
+            vec3 dir = box_project(reflection, u_ProbeMin0, u_ProbeMax0, u_ProbeCenter0);
+            float scale = weight0 * u_ProbeCenter0.w;
+            float lod = max_lod(u_ProbeSampler0);
+            irradiance += scale * textureLod(u_ProbeSampler0, n, lod).rgb;
+            radiance += scale * textureLod(u_ProbeSampler0, dir, perceptual_roughness * lod).rgb;
+        }
+        if (weight1 > 0.0) {
+            vec3 dir = box_project(reflection, u_ProbeMin1, u_ProbeMax1, u_ProbeCenter1);
+            float scale = weight1 * u_ProbeCenter1.w;
+            float lod = max_lod(u_ProbeSampler1);
+            irradiance += scale * textureLod(u_ProbeSampler1, n, lod).rgb;
+            radiance += scale * textureLod(u_ProbeSampler1, dir, perceptual_roughness * lod).rgb;
+        }
+        // the environment map is rotated as the skybox
+        if (env_weight > 0.0) {
+            float c = cos(u_EnvParams.y);
+            float s = sin(u_EnvParams.y);
+            mat3 env_rotation = mat3(c, 0.0, s, 0.0, 1.0, 0.0, -s, 0.0, c);
+            float scale = env_weight * u_EnvParams.x;
+            float lod = max_lod(u_EnvironmentSampler);
+            irradiance += scale * textureLod(u_EnvironmentSampler, env_rotation * n, lod).rgb;
+            radiance += scale * textureLod(u_EnvironmentSampler, env_rotation * reflection, perceptual_roughness * lod).rgb;
+        }
+        float ndotv = clamp(dot(n, v), 0.001, 1.0);
+        vec3 specular = environment_brdf(specular_color, perceptual_roughness, ndotv);
+        color += irradiance * diffuse_color + radiance * specular;
+    }
+
     if (available(OCCLUSION_MAP)) {
         float ao = texture(u_OcclusionSampler, v_TexCoord).r;
         color = mix(color, color * ao, u_OcclusionStrength);
@@ -174,5 +274,5 @@ void main() {
         color += emissive;
     }
 
-    Target0 = vec4(color, base_color.a);
+    Target0 = vec4(fog(color, v_Position), base_color.a);
 }
@@ -1,6 +1,14 @@
 #version 150 core
-#define MAX_TARGETS 8U
 #include <globals>
+#include <displacement>
+
+// Feature toggles, overridable with `source::Defines`.
+#ifndef SKINNING
+#define SKINNING 1
+#endif
+#ifndef DISPLACEMENT
+#define DISPLACEMENT 1
+#endif
 
 const int DISPLACEMENT_BUFFER = 1 << 5;
 
@@ -14,23 +22,15 @@ in vec4 a_JointWeights;
 out vec3 v_Position;
 out vec2 v_TexCoord;
 out mat3 v_Tbn;
+flat out float v_Fade;
 
 in vec4 i_World0;
 in vec4 i_World1;
 in vec4 i_World2;
-
-// Toggles displacement contributions to `a_Position/a_Normal/a_Tangent`.
-struct DisplacementContribution {
-    // position: 1.0 if morph target weights should influence a_Position
-    // normal: 1.0 if morph target weights should influence a_Normal
-    // tangent: 1.0 if morph target weights should influence a_Tangent
-    // weight: The weight to be applied.
-    float position, normal, tangent, weight;
-};
-
-layout(std140) uniform b_DisplacementContributions {
-    DisplacementContribution u_DisplacementContributions[MAX_TARGETS];
-};
+in vec4 i_Normal0;
+in vec4 i_Normal1;
+in vec4 i_Normal2;
+in float i_Fade;
 
 layout(std140) uniform b_PbrParams {
     vec4 u_BaseColorFactor;
@@ -43,7 +43,6 @@ layout(std140) uniform b_PbrParams {
 };
 
 uniform samplerBuffer b_JointTransforms;
-uniform sampler2D u_Displacements;
 
 //TODO: store each join transform in 3 vectors, similar to `i_WorldX`
 
@@ -73,29 +72,30 @@ void main() {
     vec3 local_normal = a_Normal.xyz;
     vec3 local_tangent = a_Tangent.xyz;
 
+#if DISPLACEMENT
     if (available(DISPLACEMENT_BUFFER)) {
-        uint num_targets = uvec2(textureSize(u_Displacements, 0)).y / 3U;
-        for (uint i = 0U; i < min(num_targets, MAX_TARGETS); ++i) {
-            DisplacementContribution disp = u_DisplacementContributions[i];
-            if (disp.weight == 0.0) continue;
-            local_position += disp.position * disp.weight * texelFetch(u_Displacements, ivec2(gl_VertexID, 3U*i+0U), 0).xyz;
-            local_normal   += disp.normal   * disp.weight * texelFetch(u_Displacements, ivec2(gl_VertexID, 3U*i+1U), 0).xyz;
-            local_tangent  += disp.tangent  * disp.weight * texelFetch(u_Displacements, ivec2(gl_VertexID, 3U*i+2U), 0).xyz;
-        }
+        displace(local_position, local_normal, local_tangent);
     }
+#endif
 
     mat4 mx_world = transpose(mat4(i_World0, i_World1, i_World2, vec4(0.0, 0.0, 0.0, 1.0)));
     mat4 mx_mvp = u_ViewProj * mx_world;
+#if SKINNING
     mat4 mx_skin = compute_skin_transform();
+#else
+    mat4 mx_skin = mat4(1.0);
+#endif
 
     vec4 world_position = mx_world * vec4(local_position, a_Position.w);
-    vec3 world_normal = mat3(mx_world) * normalize(local_normal);
-    vec3 world_tangent = mat3(mx_world) * normalize(local_tangent);
+    mat3 mx_normal = transpose(mat3(i_Normal0.xyz, i_Normal1.xyz, i_Normal2.xyz));
+    vec3 world_normal = normalize(mx_normal * local_normal);
+    vec3 world_tangent = normalize(mat3(mx_world) * local_tangent);
     vec3 world_bitangent = cross(world_normal, world_tangent) * a_Tangent.w;
 
     v_Tbn = mat3(world_tangent, world_bitangent, world_normal);
     v_Position = world_position.xyz / world_position.w;
     v_TexCoord = a_TexCoord;
 
+    v_Fade = i_Fade;
     gl_Position = mx_mvp * mx_skin * vec4(local_position, a_Position.w);
 }
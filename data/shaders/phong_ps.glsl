@@ -1,9 +1,17 @@
 #version 150 core
 #include <lights>
 #include <globals>
+#include <section>
+#include <fade>
+#include <fog>
+#include <shadow>
 
 in vec3 v_World;
+flat in float v_Fade;
 in vec3 v_Normal;
+in vec4 v_Tangent;
+in vec2 v_TexCoord;
+in vec2 v_MeshCoord;
 in vec3 v_Half[MAX_LIGHTS];
 in vec4 v_ShadowCoord[MAX_LIGHTS];
 
@@ -12,42 +20,61 @@ in vec4 v_Color;
 
 out vec4 Target0;
 
+uniform sampler2D t_Map;
+uniform sampler2D t_NormalMap;
 uniform sampler2DShadow t_Shadow0;
 uniform sampler2DShadow t_Shadow1;
 
 void main() {
-    vec4 color = vec4(0.0);
+    fade(v_Fade);
+    if (section(v_World)) {
+        Target0 = vec4(u_CapColor.rgb, 1.0);
+        return;
+    }
+    vec4 albedo = v_Color * texture(t_Map, v_TexCoord);
+    vec4 color = u_Ambient * albedo;
     vec3 normal = normalize(v_Normal);
+    // perturb the normal in tangent space, flat normal maps leave it unchanged
+    vec3 tangent = v_Tangent.xyz - normal * dot(normal, v_Tangent.xyz);
+    if (dot(tangent, tangent) > 1e-6) {
+        tangent = normalize(tangent);
+        vec3 bitangent = cross(normal, tangent) * (v_Tangent.w < 0.0 ? -1.0 : 1.0);
+        vec3 mapped = texture(t_NormalMap, v_MeshCoord).xyz * 2.0 - 1.0;
+        normal = normalize(mat3(tangent, bitangent, normal) * mapped);
+    }
     float glossiness = v_MatParams.x;
+    vec4 specular = vec4(v_MatParams.yzw, 0.0);
     for(uint i=0U; i < min(MAX_LIGHTS, u_NumLights); ++i) {
         Light light = u_Lights[i];
-        vec4 lit_space = v_ShadowCoord[i];
         float shadow = 1.0;
+#if SHADOWS
+        vec4 lit_space = v_ShadowCoord[i];
         if (light.shadow_params[0] == 0) {
-            shadow = texture(t_Shadow0, 0.5 * lit_space.xyz / lit_space.w + 0.5);
+            shadow = sample_shadow(t_Shadow0, lit_space);
         }
         if (light.shadow_params[0] == 1) {
-            shadow = texture(t_Shadow1, 0.5 * lit_space.xyz / lit_space.w + 0.5);
+            shadow = sample_shadow(t_Shadow1, lit_space);
         }
         if (shadow == 0.0) {
             continue;
         }
+#endif
         vec3 dir = light.pos.xyz - light.pos.w * v_World.xyz;
         float dot_nl = dot(normal, normalize(dir));
         // hemisphere light test
         if (dot(light.color_back, light.color_back) > 0.0) {
             vec4 irradiance = mix(light.color_back, light.color, dot_nl*0.5 + 0.5);
-            color += shadow * light.intensity.x * v_Color * irradiance;
+            color += shadow * light.intensity.x * albedo * irradiance;
         } else {
             float kd = light.intensity.x + light.intensity.y * max(0.0, dot_nl);
-            color += shadow * kd * v_Color * light.color;
+            color += shadow * kd * albedo * light.color;
         }
         if (dot_nl > 0.0 && glossiness > 0.0) {
             float ks = dot(normal, normalize(v_Half[i]));
             if (ks > 0.0) {
-                color += shadow * pow(ks, glossiness) * light.color;
+                color += shadow * pow(ks, glossiness) * specular * light.color;
             }
         }
     }
-    Target0 = color;
+    Target0 = vec4(fog(color.rgb, v_World), albedo.a);
 }
@@ -1,34 +1,61 @@
 #version 150 core
 #include <lights>
 #include <globals>
+#include <displacement>
+
+// Set to 1 by the pipelines of meshes with morph targets.
+#ifndef DISPLACEMENT
+#define DISPLACEMENT 0
+#endif
 
 in vec4 a_Position;
 in vec4 a_Normal;
+in vec2 a_TexCoord;
+in vec4 a_Tangent;
 out vec3 v_World;
 out vec3 v_Normal;
+out vec4 v_Tangent;
+out vec2 v_TexCoord;
+out vec2 v_MeshCoord;
 out vec3 v_Half[MAX_LIGHTS];
 out vec4 v_ShadowCoord[MAX_LIGHTS];
 out vec4 v_MatParams;
 out vec4 v_Color;
+flat out float v_Fade;
 
 in vec4 i_World0;
 in vec4 i_World1;
 in vec4 i_World2;
+in vec4 i_Normal0;
+in vec4 i_Normal1;
+in vec4 i_Normal2;
 in vec4 i_MatParams;
 in vec4 i_Color;
+in vec4 i_UvRange;
+in float i_Fade;
 
 void main() {
     mat4 m_World = transpose(mat4(i_World0, i_World1, i_World2, vec4(0.0, 0.0, 0.0, 1.0)));
-    vec4 world = m_World * a_Position;
+    vec4 position = a_Position;
+    vec3 normal = a_Normal.xyz;
+    vec3 tangent = a_Tangent.xyz;
+#if DISPLACEMENT
+    displace(position.xyz, normal, tangent);
+#endif
+    vec4 world = m_World * position;
     v_World = world.xyz;
-    v_Normal = normalize(mat3(m_World) * a_Normal.xyz);
+    v_Normal = normalize(transpose(mat3(i_Normal0.xyz, i_Normal1.xyz, i_Normal2.xyz)) * normal);
+    v_Tangent = vec4(normalize(mat3(m_World) * tangent), a_Tangent.w);
     for(uint i=0U; i < min(MAX_LIGHTS, u_NumLights); ++i) {
         Light light = u_Lights[i];
         vec3 dir = light.pos.xyz - light.pos.w * world.xyz;
         v_Half[i] = normalize(v_Normal + normalize(dir));
         v_ShadowCoord[i] = light.projection * world;
     }
+    v_TexCoord = mix(i_UvRange.xy, i_UvRange.zw, a_TexCoord);
+    v_MeshCoord = a_TexCoord;
     v_Color = i_Color;
     v_MatParams = i_MatParams;
+    v_Fade = i_Fade;
     gl_Position = u_ViewProj * world;
 }
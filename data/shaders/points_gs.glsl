@@ -0,0 +1,43 @@
+#version 150 core
+#include <globals>
+
+// Expands each point into a quad facing the camera.
+
+layout(points) in;
+layout(triangle_strip, max_vertices = 4) out;
+
+in vec4 p_Color[];
+flat in float p_Size[];
+flat in vec4 p_UvRange[];
+flat in float p_Fade[];
+out vec4 v_Color;
+out vec3 v_World;
+out vec2 v_TexCoord;
+flat out float v_Fade;
+
+void emit(vec3 world, vec2 corner) {
+    gl_Position = u_ViewProj * vec4(world, 1.0);
+    v_Color = p_Color[0];
+    v_World = world;
+    v_TexCoord = mix(p_UvRange[0].xy, p_UvRange[0].zw, corner);
+    v_Fade = p_Fade[0];
+    EmitVertex();
+}
+
+void main() {
+    // points are sized in world units, so that they shrink with the distance
+    float half_size = 0.5 * p_Size[0];
+    if (half_size <= 0.0) {
+        return;
+    }
+    vec3 center = gl_in[0].gl_Position.xyz;
+    // the camera axes are the rows of the view rotation
+    vec3 right = half_size * vec3(u_View[0][0], u_View[1][0], u_View[2][0]);
+    vec3 up = half_size * vec3(u_View[0][1], u_View[1][1], u_View[2][1]);
+
+    emit(center - right - up, vec2(0.0, 0.0));
+    emit(center + right - up, vec2(1.0, 0.0));
+    emit(center - right + up, vec2(0.0, 1.0));
+    emit(center + right + up, vec2(1.0, 1.0));
+    EndPrimitive();
+}
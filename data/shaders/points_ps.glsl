@@ -0,0 +1,25 @@
+#version 150 core
+#include <globals>
+#include <section>
+#include <fade>
+#include <fog>
+
+in vec4 v_Color;
+in vec3 v_World;
+in vec2 v_TexCoord;
+flat in float v_Fade;
+out vec4 Target0;
+
+uniform sampler2D t_Map;
+
+void main() {
+    fade(v_Fade);
+    // points have no inside, so the section caps don't apply
+    section(v_World);
+    Target0 = v_Color * texture(t_Map, v_TexCoord);
+    // fully transparent texels are cut out, partial transparency needs blending
+    if (Target0.a < 1.0 / 255.0) {
+        discard;
+    }
+    Target0.rgb = fog(Target0.rgb, v_World);
+}
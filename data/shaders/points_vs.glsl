@@ -0,0 +1,29 @@
+#version 150 core
+#include <globals>
+
+in vec4 a_Position;
+in vec4 a_Color;
+out vec4 p_Color;
+flat out float p_Size;
+flat out vec4 p_UvRange;
+flat out float p_Fade;
+
+in vec4 i_World0;
+in vec4 i_World1;
+in vec4 i_World2;
+in vec4 i_Color;
+in vec4 i_MatParams;
+in vec4 i_UvRange;
+in float i_Fade;
+
+void main() {
+    mat4 m_World = transpose(mat4(i_World0, i_World1, i_World2, vec4(0.0, 0.0, 0.0, 1.0)));
+    p_Color = i_Color * a_Color;
+    // x: size in world units, scaled by the w component of the position,
+    // which is 1.0 for geometries and the particle size for particle systems
+    p_Size = i_MatParams.x * a_Position.w;
+    p_UvRange = i_UvRange;
+    p_Fade = i_Fade;
+    // projected by the geometry shader, once expanded
+    gl_Position = m_World * vec4(a_Position.xyz, 1.0);
+}
@@ -0,0 +1,10 @@
+uniform sampler2D t_Input;
+// second input of the passes combining two images
+uniform sampler2D t_Extra;
+
+layout(std140) uniform b_PostParams {
+    // xy: size of the input in pixels, zw: size of a texel
+    vec4 u_Resolution;
+    // parameters of the effect
+    vec4 u_Params;
+};
@@ -0,0 +1,9 @@
+#version 150 core
+
+void main() {
+    vec2 pos = gl_VertexID==0 ? vec2(1.0, -1.0) :
+               gl_VertexID==1 ? vec2(-1.0, -1.0) :
+               gl_VertexID==2 ? vec2(1.0, 1.0) :
+                                vec2(-1.0, 1.0) ;
+    gl_Position = vec4(pos, 0.0, 1.0);
+}
@@ -0,0 +1,10 @@
+// Scene section plane, requires <globals>.
+
+// Discards the fragment if it lies on the clipped side of the section plane.
+// Returns `true` if the fragment should be drawn as part of a cap.
+bool section(vec3 world) {
+    if (dot(vec4(world, 1.0), u_ClipPlane) < 0.0) {
+        discard;
+    }
+    return !gl_FrontFacing && u_CapColor.a > 0.0;
+}
@@ -0,0 +1,21 @@
+// Shadow map sampling, requires <globals>.
+
+// Returns the fraction of light reaching a fragment with the given light space
+// coordinates, filtered over a (2 * radius + 1)^2 texel kernel.
+float sample_shadow(sampler2DShadow map, vec4 coord) {
+    vec3 p = 0.5 * coord.xyz / coord.w + 0.5;
+    p.z -= u_ShadowParams.x;
+    int radius = int(u_ShadowParams.y);
+    if (radius <= 0) {
+        return texture(map, p);
+    }
+    vec2 texel = 1.0 / vec2(textureSize(map, 0));
+    float sum = 0.0;
+    for (int y = -radius; y <= radius; ++y) {
+        for (int x = -radius; x <= radius; ++x) {
+            sum += texture(map, vec3(p.xy + vec2(x, y) * texel, p.z));
+        }
+    }
+    float size = float(2 * radius + 1);
+    return sum / (size * size);
+}
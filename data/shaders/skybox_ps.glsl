@@ -1,4 +1,5 @@
 #version 150 core
+#include <globals>
 
 uniform samplerCube t_Input;
 
@@ -6,5 +7,5 @@ in vec3 v_TexCoord;
 out vec4 Target0;
 
 void main() {
-    Target0 = texture(t_Input, v_TexCoord);
+    Target0 = u_EnvParams.x * texture(t_Input, v_TexCoord);
 }
@@ -14,7 +14,11 @@ void main() {
     mat3 inverseView = transpose(mat3(u_View));
     vec3 unprojected = (u_InverseProj * a_Position).xyz;
 
-    v_TexCoord = inverseView * unprojected;
+    float c = cos(u_EnvParams.y);
+    float s = sin(u_EnvParams.y);
+    mat3 envRotation = mat3(c, 0.0, s, 0.0, 1.0, 0.0, -s, 0.0, c);
+
+    v_TexCoord = envRotation * inverseView * unprojected;
 
     gl_Position = a_Position;
 }
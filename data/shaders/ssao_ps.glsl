@@ -0,0 +1,77 @@
+#version 150 core
+
+in vec2 v_TexCoord;
+out vec4 Target0;
+
+uniform sampler2D t_Input;
+uniform sampler2D t_Depth;
+// ambient occlusion of the frame, in the red channel
+uniform sampler2D t_Occlusion;
+
+layout(std140) uniform b_SsaoParams {
+    mat4 u_Projection;
+    mat4 u_InverseProjection;
+    // xy: size of the target in pixels, zw: size of a texel
+    vec4 u_Resolution;
+    // w selects the pass:
+    // 0.0: occlusion, x: radius, y: bias
+    // 1.0: composite of t_Occlusion over t_Input, x: intensity
+    vec4 u_Params;
+};
+
+const int SAMPLES = 16;
+const float GOLDEN_ANGLE = 2.3999632;
+
+// Returns the view space position of the pixel at `uv`.
+vec3 view_position(vec2 uv) {
+    float depth = texture(t_Depth, uv).r;
+    vec4 p = u_InverseProjection * vec4(vec3(uv, depth) * 2.0 - 1.0, 1.0);
+    return p.xyz / p.w;
+}
+
+// Returns a pseudo-random number in [0, 1) for the pixel at `p`.
+float noise(vec2 p) {
+    return fract(sin(dot(p, vec2(12.9898, 78.233))) * 43758.5453);
+}
+
+float occlusion() {
+    // derivatives are taken before any branch, to be defined for all pixels
+    vec3 position = view_position(v_TexCoord);
+    vec3 normal = normalize(cross(dFdx(position), dFdy(position)));
+    if (texture(t_Depth, v_TexCoord).r >= 1.0) {
+        // background
+        return 1.0;
+    }
+    vec3 tangent = normalize(cross(normal, abs(normal.z) < 0.999 ? vec3(0.0, 0.0, 1.0) : vec3(1.0, 0.0, 0.0)));
+    vec3 bitangent = cross(normal, tangent);
+
+    float radius = u_Params.x;
+    // the sample spiral is rotated per pixel, the noise is removed by the blur
+    float angle = 6.2831853 * noise(gl_FragCoord.xy);
+    float occluded = 0.0;
+    for (int i = 0; i < SAMPLES; ++i) {
+        float t = (float(i) + 0.5) / float(SAMPLES);
+        float a = angle + float(i) * GOLDEN_ANGLE;
+        vec3 dir = vec3(cos(a) * sqrt(t), sin(a) * sqrt(t), sqrt(1.0 - t));
+        // samples are denser close to the pixel
+        float scale = radius * mix(0.1, 1.0, t * t);
+        vec3 point = position + (tangent * dir.x + bitangent * dir.y + normal * dir.z) * scale;
+        vec4 clip = u_Projection * vec4(point, 1.0);
+        vec2 uv = clip.xy / clip.w * 0.5 + 0.5;
+        float scene_z = view_position(uv).z;
+        // surfaces far in front of the pixel don't occlude it
+        float range = smoothstep(0.0, 1.0, radius / max(abs(position.z - scene_z), 0.0001));
+        occluded += (scene_z >= point.z + u_Params.y ? 1.0 : 0.0) * range;
+    }
+    return 1.0 - occluded / float(SAMPLES);
+}
+
+void main() {
+    if (u_Params.w < 0.5) {
+        Target0 = vec4(vec3(occlusion()), 1.0);
+    } else {
+        vec4 color = texture(t_Input, v_TexCoord);
+        float ao = texture(t_Occlusion, v_TexCoord).r;
+        Target0 = vec4(color.rgb * max(mix(1.0, ao, u_Params.x), 0.0), color.a);
+    }
+}
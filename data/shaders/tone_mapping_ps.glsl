@@ -0,0 +1,20 @@
+#version 150 core
+#include <post>
+
+in vec2 v_TexCoord;
+out vec4 Target0;
+
+// u_Params: x: exposure, y: 0.0 for Reinhard, 1.0 for ACES
+
+void main() {
+    vec4 color = texture(t_Input, v_TexCoord);
+    vec3 x = color.rgb * u_Params.x;
+    vec3 mapped;
+    if (u_Params.y < 0.5) {
+        mapped = x / (1.0 + x);
+    } else {
+        // fit of the ACES filmic curve by Krzysztof Narkowicz
+        mapped = clamp((x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14), 0.0, 1.0);
+    }
+    Target0 = vec4(mapped, color.a);
+}
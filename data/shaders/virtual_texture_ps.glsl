@@ -0,0 +1,103 @@
+#version 150 core
+#include <globals>
+#include <section>
+#include <fade>
+#include <fog>
+#include <shadow>
+
+in vec4 v_ResultColor;
+in vec4 v_LightEval[2];
+in vec4 v_ShadowCoord[2];
+in vec3 v_World;
+in vec2 v_TexCoord;
+// width and height of the finest level, tile size and number of levels
+flat in vec4 v_Layout;
+flat in float v_Fade;
+in float v_Alpha;
+
+out vec4 Target0;
+
+// the tile cache
+uniform sampler2D t_Map;
+// the indirection table, one mip level per level of the virtual texture
+uniform sampler2D t_NormalMap;
+uniform sampler2DShadow t_Shadow0;
+uniform sampler2DShadow t_Shadow1;
+
+// Set to the downscale factor of the feedback target by the pipeline of the
+// feedback pass.
+#ifndef FEEDBACK
+#define FEEDBACK 0
+#endif
+
+vec4 sample_virtual(vec2 uv) {
+    float tile_size = v_Layout.z;
+    int levels = int(v_Layout.w);
+    // texels of the finest level, from the top-left corner like the tiles
+    vec2 texel = vec2(uv.x, 1.0 - uv.y) * v_Layout.xy;
+    vec2 footprint = max(abs(dFdx(texel)), abs(dFdy(texel)));
+    float lod = log2(max(max(footprint.x, footprint.y), 1.0));
+    int level = clamp(int(lod), 0, levels - 1);
+    vec2 tile = floor(texel / (tile_size * exp2(float(level))));
+    ivec2 table_size = textureSize(t_NormalMap, level);
+    ivec2 position = clamp(ivec2(tile), ivec2(0), table_size - 1);
+    // page position, level of the finest resident tile covering it, and
+    // whether there is one
+    vec4 entry = floor(texelFetch(t_NormalMap, position, level) * 255.0 + 0.5);
+    if (entry.a == 0.0) {
+        return vec4(0.5, 0.5, 0.5, 1.0);
+    }
+    float span = tile_size * exp2(entry.z);
+    // stay half a texel away from the neighbouring pages of the cache
+    vec2 offset = clamp(mod(texel, span) / span * tile_size, vec2(0.5), vec2(tile_size - 0.5));
+    vec2 cache_texel = entry.xy * tile_size + offset;
+    return texture(t_Map, cache_texel / vec2(textureSize(t_Map, 0)));
+}
+
+#if FEEDBACK
+// Tile sampled at `uv`, with the id of the texture: the low 8 bits of its
+// column and row in red and green, their high 2 bits and the level in blue,
+// and the id in alpha.
+vec4 feedback(vec2 uv, float id) {
+    float tile_size = v_Layout.z;
+    int levels = int(v_Layout.w);
+    vec2 texel = vec2(uv.x, 1.0 - uv.y) * v_Layout.xy;
+    // a pixel of the feedback target covers several pixels of the frame
+    vec2 footprint = max(abs(dFdx(texel)), abs(dFdy(texel))) / float(FEEDBACK);
+    float lod = log2(max(max(footprint.x, footprint.y), 1.0));
+    int level = clamp(int(lod), 0, levels - 1);
+    // coarser tiles are requested where the columns and rows don't fit
+    while (level < levels - 1 && textureSize(t_NormalMap, level).x > 1024) {
+        level += 1;
+    }
+    ivec2 table_size = textureSize(t_NormalMap, level);
+    ivec2 tile = clamp(ivec2(floor(texel / (tile_size * exp2(float(level))))), ivec2(0), table_size - 1);
+    int high = (tile.x >> 8) | ((tile.y >> 8) << 2) | (level << 4);
+    return vec4(float(tile.x & 255), float(tile.y & 255), float(high), id) / 255.0;
+}
+#endif
+
+void main() {
+    fade(v_Fade);
+#if FEEDBACK
+    section(v_World);
+    // the alpha of the instance color holds the id of the texture
+    Target0 = feedback(v_TexCoord, v_Alpha);
+    return;
+#endif
+    if (section(v_World)) {
+        Target0 = vec4(u_CapColor.rgb, 1.0);
+        return;
+    }
+    Target0 = v_ResultColor;
+    if (v_ShadowCoord[0].w != 0.0) {
+        float shadow = sample_shadow(t_Shadow0, v_ShadowCoord[0]);
+        Target0 += shadow * v_LightEval[0];
+    }
+    if (v_ShadowCoord[1].w != 0.0) {
+        float shadow = sample_shadow(t_Shadow1, v_ShadowCoord[1]);
+        Target0 += shadow * v_LightEval[1];
+    }
+    vec4 color = sample_virtual(v_TexCoord);
+    Target0 = vec4(fog(Target0.rgb * color.rgb, v_World), v_Alpha * color.a);
+}
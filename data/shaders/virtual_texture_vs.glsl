@@ -0,0 +1,69 @@
+#version 150 core
+#include <lights>
+#include <globals>
+
+#define MAX_SHADOWS 2
+
+in vec4 a_Position;
+in vec4 a_Normal;
+in vec2 a_TexCoord;
+out vec4 v_ResultColor;
+out vec4 v_LightEval[MAX_SHADOWS];
+out vec4 v_ShadowCoord[MAX_SHADOWS];
+out vec3 v_World;
+out vec2 v_TexCoord;
+flat out vec4 v_Layout;
+out float v_Alpha;
+flat out float v_Fade;
+
+in vec4 i_World0;
+in vec4 i_World1;
+in vec4 i_World2;
+in vec4 i_Normal0;
+in vec4 i_Normal1;
+in vec4 i_Normal2;
+in vec4 i_MatParams;
+in vec4 i_Color;
+in vec4 i_UvRange;
+in float i_Fade;
+
+void main() {
+    mat4 m_World = transpose(mat4(i_World0, i_World1, i_World2, vec4(0.0, 0.0, 0.0, 1.0)));
+    vec4 world = m_World * a_Position;
+    v_World = world.xyz;
+    vec3 normal = normalize(transpose(mat3(i_Normal0.xyz, i_Normal1.xyz, i_Normal2.xyz)) * a_Normal.xyz);
+    for(int i=0; i<MAX_SHADOWS; ++i) {
+        v_ShadowCoord[i] = vec4(0.0);
+        v_LightEval[i] = vec4(0.0);
+    }
+    v_ResultColor = u_Ambient * i_Color;
+
+    for(uint i=0U; i < min(MAX_LIGHTS, u_NumLights); ++i) {
+        Light light = u_Lights[i];
+        vec3 dir = light.pos.xyz - light.pos.w * world.xyz;
+        // evaluate light color
+        float dot_nl = dot(normal, normalize(dir));
+        vec4 irradiance = light.color;
+        if (dot(light.color_back, light.color_back) > 0.0) {
+            irradiance = mix(light.color_back, light.color, dot_nl*0.5 + 0.5);
+            dot_nl = 0.0;
+        }
+        v_ResultColor += light.intensity.x * i_Color * irradiance; //ambient
+        vec4 color = light.intensity.y * max(0.0, dot_nl) * i_Color * light.color;
+        // compute shadow coordinates
+        int shadow_index = light.shadow_params[0];
+        if (0 <= shadow_index && shadow_index < MAX_SHADOWS) {
+            v_ShadowCoord[shadow_index] = light.projection * world;
+            v_LightEval[shadow_index] = color;
+        } else {
+            v_ResultColor += color;
+        }
+    }
+
+    // the virtual texture spans the whole texture co-ordinates range
+    v_TexCoord = a_TexCoord;
+    v_Layout = i_MatParams;
+    v_Alpha = i_Color.a;
+    v_Fade = i_Fade;
+    gl_Position = u_ViewProj * world;
+}
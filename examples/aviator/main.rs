@@ -47,6 +47,7 @@ fn main() {
         let material = three::material::Lambert {
             color: COLOR_BLUE,
             flat: true,
+            opacity: 1.0,
         };
         win.factory.mesh(geo, material)
     };
@@ -32,6 +32,7 @@ impl AirPlane {
                 three::material::Lambert {
                     color: COLOR_RED,
                     flat: false,
+                    opacity: 1.0,
                 },
             )
         };
@@ -42,6 +43,7 @@ impl AirPlane {
             three::material::Lambert {
                 color: COLOR_WHITE,
                 flat: false,
+                opacity: 1.0,
             },
         );
         engine.set_position([40.0, 0.0, 0.0]);
@@ -52,6 +54,7 @@ impl AirPlane {
             three::material::Lambert {
                 color: COLOR_RED,
                 flat: false,
+                opacity: 1.0,
             },
         );
         tail.set_position([-35.0, 25.0, 0.0]);
@@ -62,6 +65,7 @@ impl AirPlane {
             three::material::Lambert {
                 color: COLOR_RED,
                 flat: false,
+                opacity: 1.0,
             },
         );
         group.add(&wing);
@@ -74,6 +78,7 @@ impl AirPlane {
             three::material::Lambert {
                 color: COLOR_BROWN,
                 flat: false,
+                opacity: 1.0,
             },
         );
         propeller_group.add(&propeller);
@@ -82,6 +87,7 @@ impl AirPlane {
             three::material::Lambert {
                 color: COLOR_BROWN_DARK,
                 flat: false,
+                opacity: 1.0,
             },
         );
         blade.set_position([8.0, 0.0, 0.0]);
@@ -22,6 +22,7 @@ impl Sky {
         let material = three::material::Lambert {
             color: COLOR_WHITE,
             flat: true,
+            opacity: 1.0,
         };
         let template = factory.mesh(geo, material.clone());
         for i in 0i32 .. rng.gen_range(3, 6) {
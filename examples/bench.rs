@@ -0,0 +1,50 @@
+extern crate three;
+
+use std::env;
+use three::bench::{Config, FrameTimes, StressScene};
+use three::Object;
+
+const USAGE: &str = "Usage: bench [--seed S] [--meshes N] [--lights M] [--characters K] [--frames F] [--spinning]";
+
+fn main() {
+    let mut config = Config::default();
+    let mut frames = 600;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--spinning" {
+            config.spinning = true;
+            continue;
+        }
+        let value = args.next().and_then(|value| value.parse::<usize>().ok()).expect(USAGE);
+        match arg.as_str() {
+            "--seed" => config.seed = value as u32,
+            "--meshes" => config.meshes = value,
+            "--lights" => config.lights = value,
+            "--characters" => config.characters = value,
+            "--frames" => frames = value,
+            _ => panic!("{}", USAGE),
+        }
+    }
+
+    let mut win = three::Window::builder("Three-rs benchmark")
+        .vsync(false)
+        .build();
+    let cam = win.factory.perspective_camera(60.0, 0.5 .. 500.0);
+    cam.look_at([0.0, 40.0, 90.0], [0.0, 0.0, 0.0], None);
+
+    let stress = StressScene::generate(&mut win.factory, &config);
+    win.scene.add(&stress.group);
+
+    let mut times = FrameTimes::new(30);
+    while times.len() < frames && win.update() && !win.input.hit(three::KEY_ESCAPE) {
+        times.push(win.input.real_delta_time());
+        stress.update(times.elapsed());
+        win.render(&cam);
+    }
+
+    println!("{:?}", config);
+    match times.report() {
+        Some(report) => println!("{}", report),
+        None => println!("No frames measured"),
+    }
+}
@@ -129,7 +129,7 @@ fn main() {
 
     let materials = LEVELS
         .iter()
-        .map(|l| three::material::Lambert { color: l.color, flat: false })
+        .map(|l| three::material::Lambert { color: l.color, flat: false, opacity: 1.0 })
         .collect::<Vec<_>>();
     let levels = LEVELS
         .iter()
@@ -35,6 +35,7 @@ fn main() {
         let material = three::material::Phong {
             color: 0xffA0A0,
             glossiness: 80.0,
+            ..Default::default()
         };
         win.factory.mesh(geometry, material)
     };
@@ -46,6 +47,7 @@ fn main() {
         let material = three::material::Lambert {
             color: 0xA0ffA0,
             flat: false,
+            opacity: 1.0,
         };
         win.factory.mesh(geometry, material)
     };
@@ -17,18 +17,22 @@ fn main() {
         three::material::Basic {
             color: 0xFFFFFF,
             map: None,
+            opacity: 1.0,
         }.into(),
         three::material::Lambert {
             color: 0xFFFFFF,
             flat: true,
+            opacity: 1.0,
         }.into(),
         three::material::Lambert {
             color: 0xFFFFFF,
             flat: false,
+            opacity: 1.0,
         }.into(),
         three::material::Phong {
             color: 0xFFFFFF,
             glossiness: 80.0,
+            ..Default::default()
         }.into(),
         three::material::Pbr {
             base_color_factor: 0xFFFFFF,
@@ -34,6 +34,22 @@ fn main() {
     msphere.set_position([-3.0, 3.0, 0.0]);
     win.scene.add(&msphere);
 
+    let mcone = {
+        let geometry = three::Geometry::cone(1.0, 2.0, 8);
+        let material = three::material::Wireframe { color: 0x00FFFF };
+        win.factory.mesh(geometry, material)
+    };
+    mcone.set_position([-6.0, 0.0, 0.0]);
+    win.scene.add(&mcone);
+
+    let mtorus = {
+        let geometry = three::Geometry::torus(1.0, 0.3, 8, 16);
+        let material = three::material::Wireframe { color: 0x00FFFF };
+        win.factory.mesh(geometry, material)
+    };
+    mtorus.set_position([0.0, 3.0, 0.0]);
+    win.scene.add(&mtorus);
+
     // test removal from scene
     win.scene.remove(&mcyl);
     win.scene.remove(&mbox);
@@ -46,12 +62,47 @@ fn main() {
             [0.0, 1.0, 0.0].into(),
             [2.0, -1.0, 0.0].into(),
         ]);
-        let material = three::material::Line { color: 0x0000FF };
+        let material = three::material::Line {
+            color: 0x0000FF,
+            width: 3.0,
+            ..Default::default()
+        };
         win.factory.mesh(geometry, material)
     };
     mline.set_position([3.0, 3.0, 0.0]);
     win.scene.add(&mline);
 
+    let mdash = {
+        let mut geometry = three::Geometry::with_vertices(vec![
+            [-1.0, 0.0, 0.0].into(),
+            [1.0, 0.0, 0.0].into(),
+            [0.0, -1.0, 0.0].into(),
+            [0.0, 1.0, 0.0].into(),
+        ]);
+        geometry.compute_line_distances(three::material::LineMode::Segments);
+        let material = three::material::DashedLine {
+            color: 0xFFFF00,
+            width: 2.0,
+            mode: three::material::LineMode::Segments,
+            dash_size: 0.2,
+            gap_size: 0.1,
+        };
+        win.factory.mesh(geometry, material)
+    };
+    win.scene.add(&mdash);
+
+    let mpoints = {
+        let geometry = three::Geometry::uv_sphere(1.0, 12, 12);
+        let material = three::material::Points {
+            color: 0xFF00FF,
+            size: 0.1,
+            ..Default::default()
+        };
+        win.factory.points(geometry, material)
+    };
+    mpoints.set_position([0.0, -3.0, 0.0]);
+    win.scene.add(&mpoints);
+
     let mut angle = cgmath::Rad::zero();
     while win.update() && !win.input.hit(three::KEY_ESCAPE) {
         if let Some(diff) = win.input.timed(three::AXIS_LEFT_RIGHT) {
@@ -60,7 +111,11 @@ fn main() {
             mbox.set_orientation(q);
             mcyl.set_orientation(q);
             msphere.set_orientation(q);
+            mcone.set_orientation(q);
+            mtorus.set_orientation(q);
             mline.set_orientation(q);
+            mdash.set_orientation(q);
+            mpoints.set_orientation(q);
         }
         win.render(&cam);
     }
@@ -0,0 +1,129 @@
+extern crate three;
+
+use three::noise::{Fbm, Noise, Simplex};
+use three::virtual_texture::{self, Layout, Tile};
+use three::Object;
+
+const SIZE: usize = 64;
+const SCALE: f32 = 0.5;
+/// Texels along each side of the virtual texture covering the terrain,
+/// about 1000 per world unit.
+const TEXELS: u32 = 32768;
+const TILE_SIZE: u32 = 128;
+
+/// Colors a tile of the terrain texture, with grass in the valleys, rocks on
+/// the hills and some detail noise showing up close.
+fn make_terrain_tile<N: Noise>(
+    noise: &N,
+    tile: Tile,
+) -> Vec<[u8; 4]> {
+    let span = (1 << tile.level) as f32;
+    let mut texels = Vec::with_capacity((TILE_SIZE * TILE_SIZE) as usize);
+    for y in 0 .. TILE_SIZE {
+        for x in 0 .. TILE_SIZE {
+            // the same grid co-ordinates as the terrain heights
+            let grid = (SIZE - 1) as f32 / TEXELS as f32 * span;
+            let gx = ((tile.x * TILE_SIZE + x) as f32 + 0.5) * grid;
+            let gz = ((tile.y * TILE_SIZE + y) as f32 + 0.5) * grid;
+            let rock = (0.5 + 0.5 * noise.get2(0.05 * gx, 0.05 * gz)).max(0.0).min(1.0);
+            let detail = 0.85 + 0.15 * noise.get2(8.0 * gx, 8.0 * gz);
+            let channel = |grass: f32, stone: f32| ((grass + rock * (stone - grass)) * detail) as u8;
+            texels.push([channel(107.0, 140.0), channel(142.0, 120.0), channel(35.0, 100.0), 255]);
+        }
+    }
+    texels
+}
+
+fn make_terrain_geometry<N: Noise>(noise: &N) -> three::Geometry {
+    let height = |x: usize, z: usize| 4.0 * noise.get2(0.05 * x as f32, 0.05 * z as f32);
+    let offset = 0.5 * SCALE * (SIZE - 1) as f32;
+
+    let mut vertices = Vec::with_capacity(SIZE * SIZE);
+    let mut normals = Vec::with_capacity(SIZE * SIZE);
+    let mut tex_coords = Vec::with_capacity(SIZE * SIZE);
+    for z in 0 .. SIZE {
+        for x in 0 .. SIZE {
+            vertices.push([x as f32 * SCALE - offset, height(x, z), z as f32 * SCALE - offset].into());
+            // the top of the texture is at the lowest z
+            tex_coords.push([x as f32 / (SIZE - 1) as f32, 1.0 - z as f32 / (SIZE - 1) as f32].into());
+            // central differences, clamped at the borders
+            let dx = height((x + 1).min(SIZE - 1), z) - height(x.saturating_sub(1), z);
+            let dz = height(x, (z + 1).min(SIZE - 1)) - height(x, z.saturating_sub(1));
+            let normal = [-dx, 4.0 * SCALE, -dz];
+            let length = (normal[0] * normal[0] + normal[1] * normal[1] + normal[2] * normal[2]).sqrt();
+            normals.push([normal[0] / length, normal[1] / length, normal[2] / length].into());
+        }
+    }
+
+    let mut faces = Vec::with_capacity(2 * (SIZE - 1) * (SIZE - 1));
+    for z in 0 .. SIZE as u32 - 1 {
+        for x in 0 .. SIZE as u32 - 1 {
+            let i = z * SIZE as u32 + x;
+            faces.push([i, i + SIZE as u32, i + 1]);
+            faces.push([i + 1, i + SIZE as u32, i + SIZE as u32 + 1]);
+        }
+    }
+
+    three::Geometry {
+        faces,
+        tex_coords,
+        base: three::Shape {
+            vertices,
+            normals,
+            ..three::Shape::default()
+        },
+        ..three::Geometry::default()
+    }
+}
+
+fn main() {
+    let mut win = three::Window::new("Three-rs terrain example");
+    win.scene.background = three::Background::Color(0xC6F0FF);
+    let cam = win.factory.perspective_camera(60.0, 0.1 .. 100.0);
+    let mut controls = three::controls::Orbit::builder(&cam)
+        .position([0.0, 15.0, -25.0])
+        .target([0.0, 0.0, 0.0])
+        .build();
+
+    let noise = Fbm::new(Simplex::new(7));
+    let layout = Layout {
+        width: TEXELS,
+        height: TEXELS,
+        tile_size: TILE_SIZE,
+    };
+    let mut texture = win.factory.virtual_texture(layout, 32);
+    // generating tiles is slow, spread it over frames
+    texture.set_loads_per_update(4);
+    let mut source = |tile: Tile| Some(make_terrain_tile(&noise, tile));
+    let terrain = {
+        let geometry = make_terrain_geometry(&noise);
+        let material = three::material::Virtual {
+            color: 0xFFFFFF,
+            map: texture.map(),
+        };
+        win.factory.mesh(geometry, material)
+    };
+    win.scene.add(&terrain);
+
+    let ambient = win.factory.ambient_light(0xFFFFFF, 0.3);
+    win.scene.add(&ambient);
+    let sun = win.factory.directional_light(0xFFFFFF, 0.8);
+    sun.look_at([10.0, 20.0, -10.0], [0.0, 0.0, 0.0], None);
+    win.scene.add(&sun);
+
+    let extent = SCALE * (SIZE - 1) as f32;
+    let texels_per_unit = TEXELS as f32 / extent;
+    while win.update() && !win.input.hit(three::KEY_ESCAPE) {
+        controls.update(&win.input);
+        let position = win.scene.sync_guard().resolve_world(&cam).transform.position;
+        let eye = [
+            (position.x + 0.5 * extent) * texels_per_unit,
+            (position.z + 0.5 * extent) * texels_per_unit,
+            position.y.abs() * texels_per_unit,
+        ];
+        let scale = virtual_texture::projection_scale(60.0, win.size().y);
+        texture.request_view(eye.into(), scale);
+        win.factory.update_virtual_texture(&mut texture, &mut source);
+        win.render(&cam);
+    }
+}
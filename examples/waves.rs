@@ -0,0 +1,78 @@
+extern crate three;
+
+use three::Object;
+
+const SIZE: u32 = 128;
+
+// A sum of a few directional waves, with analytic normals.
+const GENERATOR: &str = "
+const int SIZE = 128;
+const vec3 WAVES[3] = vec3[3](
+    // direction angle, wave length, amplitude
+    vec3(0.0, 4.0, 0.12),
+    vec3(0.9, 2.3, 0.06),
+    vec3(-0.6, 1.1, 0.03)
+);
+
+GeneratedVertex generate(int index) {
+    vec2 grid = vec2(index % SIZE, index / SIZE) / float(SIZE - 1);
+    vec2 position = (grid - 0.5) * 20.0;
+    float height = 0.0;
+    vec2 slope = vec2(0.0);
+    for (int i = 0; i < 3; ++i) {
+        vec2 direction = vec2(cos(WAVES[i].x), sin(WAVES[i].x));
+        float k = 6.2831853 / WAVES[i].y;
+        float phase = k * dot(direction, position) - sqrt(9.8 * k) * u_Params.x;
+        height += WAVES[i].z * sin(phase);
+        slope += WAVES[i].z * k * cos(phase) * direction;
+    }
+    GeneratedVertex vertex = generated_vertex();
+    vertex.position = vec3(position.x, height, position.y);
+    vertex.normal = normalize(vec3(-slope.x, 1.0, -slope.y));
+    vertex.uv = grid;
+    return vertex;
+}
+";
+
+fn main() {
+    let mut win = three::Window::new("Three-rs GPU waves example");
+    win.scene.background = three::Background::Color(0xA0C8F0);
+
+    let cam = win.factory.perspective_camera(60.0, 0.1 .. 100.0);
+    let mut controls = three::controls::Orbit::builder(&cam)
+        .position([0.0, 6.0, -12.0])
+        .target([0.0, 0.0, 0.0])
+        .build();
+
+    let ambient = win.factory.ambient_light(0x406080, 0.5);
+    win.scene.add(&ambient);
+    let sun = win.factory.directional_light(0xFFFFFF, 0.8);
+    sun.look_at([10.0, 10.0, 0.0], [0.0, 0.0, 0.0], None);
+    win.scene.add(&sun);
+
+    let generator = win.factory.mesh_generator(GENERATOR).unwrap();
+    let target = win.factory.vertex_target((SIZE * SIZE) as usize);
+    let mut faces = Vec::new();
+    for y in 0 .. SIZE - 1 {
+        for x in 0 .. SIZE - 1 {
+            let i = y * SIZE + x;
+            faces.push([i, i + SIZE, i + 1]);
+            faces.push([i + 1, i + SIZE, i + SIZE + 1]);
+        }
+    }
+    let material = three::material::Phong {
+        color: 0x1E5A8C,
+        glossiness: 80.0,
+        ..Default::default()
+    };
+    let ocean = win.factory.generated_mesh(&target, &faces, material);
+    win.scene.add(&ocean);
+
+    let mut time = 0.0;
+    while win.update() && !win.input.hit(three::KEY_ESCAPE) {
+        time += win.input.delta_time();
+        win.factory.generate_vertices(&target, &generator, [time, 0.0, 0.0, 0.0], None);
+        controls.update(&win.input);
+        win.render(&cam);
+    }
+}
@@ -110,6 +110,7 @@ use mint;
 use object::{Base, Object};
 
 use std::hash::{Hash, Hasher};
+use std::ops;
 use std::sync::mpsc;
 
 
@@ -126,7 +127,17 @@ pub enum Interpolation {
     Linear,
 
     /// Smooth cubic interpolation between keyframe values.
+    ///
+    /// The curve passes through every keyframe, with tangents estimated from
+    /// the neighbouring keyframes (Catmull-Rom).
     Cubic,
+
+    /// Cubic Hermite spline with authored tangents, as in glTF.
+    ///
+    /// The track holds an in-tangent, a value and an out-tangent per
+    /// keyframe, in that order, the tangents being rates of change per
+    /// second.
+    CubicSpline,
 }
 
 /// Describes the looping behaviour of an [`Action`].
@@ -233,6 +244,7 @@ enum Operation {
     Pause,
     Play,
     SetLoopMode(LoopMode),
+    SetTimeScale(f32),
 }
 
 /// Message type sent from `Action` to `Mixer`.
@@ -292,6 +304,9 @@ struct ActionData {
 
     /// Time scaling factor.
     pub local_time_scale: f32,
+
+    /// Whether the clip is currently played backwards, when ping-ponging.
+    pub reversed: bool,
     // Unimplemented properties
     // ------------------------
     // * weight
@@ -319,12 +334,51 @@ pub struct Track {
     pub times: Vec<f32>,
 
     /// The keyframe values.
+    ///
+    /// With `Interpolation::CubicSpline`, each keyframe has three values:
+    /// its in-tangent, its value and its out-tangent.
     pub values: Values,
 
     /// Specifies the interpolation strategy between keyframes.
     pub interpolation: Interpolation,
 }
 
+/// Distance-based update throttling for animation mixers and skeletons.
+///
+/// Objects far away from the camera are updated at a reduced rate, or not at
+/// all, in order to cut CPU cost in scenes with many animated objects.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Lod {
+    /// Pairs of `(distance, interval)`, where `interval` is the minimum time in
+    /// seconds between two updates once the camera is further than `distance`.
+    ///
+    /// Must be sorted by increasing distance.
+    pub levels: Vec<(f32, f32)>,
+
+    /// Distance beyond which updates are paused entirely.
+    pub pause_distance: Option<f32>,
+}
+
+impl Lod {
+    /// Returns the update interval in seconds that applies at `distance` from
+    /// the camera, or `None` if updates are paused at that distance.
+    pub fn interval(
+        &self,
+        distance: f32,
+    ) -> Option<f32> {
+        match self.pause_distance {
+            Some(pause) if distance > pause => return None,
+            _ => {}
+        }
+        let interval = self.levels
+            .iter()
+            .take_while(|&&(threshold, _)| distance > threshold)
+            .last()
+            .map_or(0.0, |&(_, interval)| interval);
+        Some(interval)
+    }
+}
+
 /// Scheduler for the playback of animation actions.
 ///
 /// Use this to update animation actions.
@@ -332,6 +386,8 @@ pub struct Mixer {
     actions: froggy::Storage<ActionData>,
     rx: mpsc::Receiver<Message>,
     tx: mpsc::Sender<Message>,
+    lod: Lod,
+    pending_time: f32,
 }
 
 impl Action {
@@ -371,6 +427,16 @@ impl Action {
     ) -> &mut Self {
         self.send(Operation::SetLoopMode(loop_mode))
     }
+
+    /// Sets the playback speed, e.g. `2.0` to play twice as fast.
+    ///
+    /// Defaults to `1.0`.
+    pub fn set_time_scale(
+        &mut self,
+        time_scale: f32,
+    ) -> &mut Self {
+        self.send(Operation::SetTimeScale(time_scale))
+    }
 }
 
 impl Mixer {
@@ -389,6 +455,7 @@ impl Mixer {
                     action.enabled = true;
                 }
                 Operation::SetLoopMode(loop_mode) => action.loop_mode = loop_mode,
+                Operation::SetTimeScale(time_scale) => action.local_time_scale = time_scale,
             }
         }
     }
@@ -406,7 +473,22 @@ impl Mixer {
     pub fn new() -> Self {
         let actions = froggy::Storage::new();
         let (tx, rx) = mpsc::channel();
-        Mixer { actions, rx, tx }
+        Mixer {
+            actions,
+            rx,
+            tx,
+            lod: Lod::default(),
+            pending_time: 0.0,
+        }
+    }
+
+    /// Sets the distance-based update throttling used by
+    /// [`update_at_distance`](#method.update_at_distance).
+    pub fn set_lod(
+        &mut self,
+        lod: Lod,
+    ) {
+        self.lod = lod;
     }
 
     /// Spawns a new animation [`Action`] to be updated by this mixer.
@@ -430,6 +512,31 @@ impl Mixer {
         self.process_messages();
         self.update_actions(delta_time);
     }
+
+    /// Updates the actions owned by the mixer, throttled according to the
+    /// distance of the animated object from the camera.
+    ///
+    /// Time skipped between throttled updates is accumulated, so animations
+    /// keep their overall speed. While paused by distance, time does not advance.
+    ///
+    /// See [`set_lod`](#method.set_lod).
+    pub fn update_at_distance(
+        &mut self,
+        delta_time: f32,
+        distance: f32,
+    ) {
+        self.process_messages();
+        let interval = match self.lod.interval(distance) {
+            Some(interval) => interval,
+            None => return,
+        };
+        self.pending_time += delta_time;
+        if self.pending_time >= interval {
+            let elapsed = self.pending_time;
+            self.pending_time = 0.0;
+            self.update_actions(elapsed);
+        }
+    }
 }
 
 impl ActionData {
@@ -441,6 +548,7 @@ impl ActionData {
             paused: false,
             local_time: 0.0,
             local_time_scale: 1.0,
+            reversed: false,
         }
     }
 
@@ -453,13 +561,21 @@ impl ActionData {
             return;
         }
 
-        self.local_time += delta_time * self.local_time_scale;
+        let step = delta_time * self.local_time_scale;
+        self.local_time += if self.reversed { -step } else { step };
         let mut finish_count = 0;
         for &(ref track, ref target) in self.clip.tracks.iter() {
             let frame_index = match track.frame_at_time(self.local_time) {
-                FrameRef::Unstarted => continue,
+                FrameRef::Unstarted => {
+                    if self.reversed {
+                        finish_count += 1;
+                    }
+                    continue;
+                }
                 FrameRef::Ended => {
-                    finish_count += 1;
+                    if !self.reversed {
+                        finish_count += 1;
+                    }
                     continue;
                 }
                 FrameRef::InProgress(i) => i,
@@ -470,55 +586,89 @@ impl ActionData {
             // Interpolation constant in range `[0.0, 1.0]` between `frame[i]`
             // and `frame[i + 1]`.
             let s = (self.local_time - frame_start_time) / frame_delta_time;
+            let keys = track.neighbors(frame_index);
+            let indices = track.value_indices(frame_index);
+            let times = [
+                track.times[keys[0]],
+                track.times[keys[1]],
+                track.times[keys[2]],
+                track.times[keys[3]],
+            ];
 
             match (track.binding, &track.values) {
-                (Binding::Orientation, &Values::Euler(ref values)) => {
-                    let frame_start_value = {
-                        let euler = values[frame_index];
-                        cgmath::Quaternion::from(cgmath::Euler::new(
-                            cgmath::Rad(euler.a),
-                            cgmath::Rad(euler.b),
-                            cgmath::Rad(euler.c),
-                        ))
+                (Binding::Orientation, &Values::Euler(ref values))
+                    if track.interpolation == Interpolation::CubicSpline =>
+                {
+                    // the tangents are rates of change of the angles
+                    let angles = |k: usize| {
+                        let euler = values[k];
+                        cgmath::Vector3::new(euler.a, euler.b, euler.c)
                     };
-                    let frame_end_value = {
-                        let euler = values[frame_index + 1];
+                    let points = [
+                        angles(indices[0]),
+                        angles(indices[1]),
+                        angles(indices[2]),
+                        angles(indices[3]),
+                    ];
+                    let euler = track.interpolation.sample(&times, &points, s);
+                    let update = cgmath::Quaternion::from(cgmath::Euler::new(
+                        cgmath::Rad(euler.x),
+                        cgmath::Rad(euler.y),
+                        cgmath::Rad(euler.z),
+                    ));
+                    target.set_orientation(update);
+                }
+                (Binding::Orientation, &Values::Euler(ref values)) => {
+                    let quaternion = |k: usize| {
+                        let euler = values[k];
                         cgmath::Quaternion::from(cgmath::Euler::new(
                             cgmath::Rad(euler.a),
                             cgmath::Rad(euler.b),
                             cgmath::Rad(euler.c),
                         ))
                     };
-                    let update = frame_start_value.slerp(frame_end_value, s);
+                    let quaternions = [
+                        quaternion(indices[0]),
+                        quaternion(indices[1]),
+                        quaternion(indices[2]),
+                        quaternion(indices[3]),
+                    ];
+                    let update = track.sample_orientation(&times, &quaternions, s);
                     target.set_orientation(update);
                 }
                 (Binding::Orientation, &Values::Quaternion(ref values)) => {
-                    let frame_start_value: cgmath::Quaternion<f32> = values[frame_index].into();
-                    let frame_end_value: cgmath::Quaternion<f32> = values[frame_index + 1].into();
-                    let update = frame_start_value.slerp(frame_end_value, s);
+                    let quaternions = [
+                        cgmath::Quaternion::from(values[indices[0]]),
+                        cgmath::Quaternion::from(values[indices[1]]),
+                        cgmath::Quaternion::from(values[indices[2]]),
+                        cgmath::Quaternion::from(values[indices[3]]),
+                    ];
+                    let update = track.sample_orientation(&times, &quaternions, s);
                     target.set_orientation(update);
                 }
                 (Binding::Position, &Values::Vector3(ref values)) => {
-                    use cgmath::{EuclideanSpace, InnerSpace};
-                    let frame_start_value: cgmath::Vector3<f32> = values[frame_index].into();
-                    let frame_end_value: cgmath::Vector3<f32> = values[frame_index + 1].into();
-                    let update = frame_start_value.lerp(frame_end_value, s);
+                    use cgmath::EuclideanSpace;
+                    let points = [
+                        cgmath::Vector3::from(values[indices[0]]),
+                        cgmath::Vector3::from(values[indices[1]]),
+                        cgmath::Vector3::from(values[indices[2]]),
+                        cgmath::Vector3::from(values[indices[3]]),
+                    ];
+                    let update = track.interpolation.sample(&times, &points, s);
                     target.set_position(cgmath::Point3::from_vec(update));
                 }
                 (Binding::Scale, &Values::Scalar(ref values)) => {
-                    let frame_start_value = values[frame_index];
-                    let frame_end_value = values[frame_index + 1];
-                    let update = frame_start_value * (1.0 - s) + frame_end_value * s;
+                    let scalars = [values[indices[0]], values[indices[1]], values[indices[2]], values[indices[3]]];
+                    let update = track.interpolation.sample(&times, &scalars, s);
                     target.set_scale(update);
                 }
                 (Binding::Weights, &Values::Scalar(ref values)) => {
                     // values are: first all scalars for shape[0], then all scalars for shape[1], etc
                     let update = values
-                        .chunks(track.times.len())
+                        .chunks(track.times.len() * track.values_per_key())
                         .map(|chunk| {
-                            let start_value = chunk[frame_index];
-                            let end_value = chunk[frame_index + 1];
-                            start_value * (1.0 - s) + end_value * s
+                            let scalars = [chunk[indices[0]], chunk[indices[1]], chunk[indices[2]], chunk[indices[3]]];
+                            track.interpolation.sample(&times, &scalars, s)
                         })
                         .collect();
                     target.set_weights(update);
@@ -536,16 +686,222 @@ impl ActionData {
                     self.local_time = 0.0;
                     self.loop_mode = LoopMode::Repeat { limit: Some(n - 1) };
                 }
-                LoopMode::PingPong { .. } => {
-                    // TODO
-                    unimplemented!()
+                LoopMode::PingPong { limit } => {
+                    if !self.reversed {
+                        // bounce at the end
+                        self.reversed = true;
+                        self.local_time = self.clip.duration();
+                    } else {
+                        // a round trip is one repetition
+                        match limit {
+                            Some(0) => self.enabled = false,
+                            Some(n) => self.loop_mode = LoopMode::PingPong { limit: Some(n - 1) },
+                            None => {}
+                        }
+                        self.reversed = false;
+                        self.local_time = 0.0;
+                    }
                 }
             }
         }
     }
 }
 
+impl Clip {
+    /// Creates a clip that blends the weight of the blend shape `shape` of
+    /// `target` from 0 to 1 over `duration` seconds, keeping the weights of
+    /// the other `num_shapes - 1` shapes at 0.
+    ///
+    /// Together with [`Geometry::with_morph_target`] this gives a simple shape
+    /// transition, played back like any other clip. Use `LoopMode::PingPong`
+    /// to morph back and forth.
+    ///
+    /// ```rust,no_run
+    /// # let mut window = three::Window::new("");
+    /// use three::animation::{Clip, Interpolation, LoopMode, Mixer};
+    ///
+    /// let cube = three::Geometry::cuboid(1.0, 1.0, 1.0);
+    /// let ball = three::Geometry::uv_sphere(0.5, 12, 12);
+    /// let geometry = cube.with_morph_target(&ball, three::Correspondence::Nearest).unwrap();
+    /// let mesh = window.factory.mesh(geometry, three::material::Pbr::default());
+    ///
+    /// let mut mixer = Mixer::new();
+    /// let clip = Clip::morph(&mesh, 1, 0, 2.0, Interpolation::Linear);
+    /// mixer.action(clip).set_loop_mode(LoopMode::PingPong { limit: None });
+    /// ```
+    ///
+    /// [`Geometry::with_morph_target`]: ../struct.Geometry.html#method.with_morph_target
+    pub fn morph<T: Object>(
+        target: &T,
+        num_shapes: usize,
+        shape: usize,
+        duration: f32,
+        interpolation: Interpolation,
+    ) -> Self {
+        // values are: first all scalars for shape[0], then all scalars for shape[1], etc
+        let values = (0 .. num_shapes)
+            .flat_map(|i| if i == shape { vec![0.0, 1.0] } else { vec![0.0, 0.0] })
+            .collect();
+        let track = Track {
+            binding: Binding::Weights,
+            times: vec![0.0, duration],
+            values: Values::Scalar(values),
+            interpolation,
+        };
+        Clip {
+            name: None,
+            tracks: vec![(track, target.upcast())],
+        }
+    }
+
+    /// Returns the time of the last keyframe of all tracks, in seconds.
+    pub fn duration(&self) -> f32 {
+        self.tracks
+            .iter()
+            .filter_map(|&(ref track, _)| track.times.last().cloned())
+            .fold(0.0, f32::max)
+    }
+}
+
+impl Interpolation {
+    /// Interpolates between `values[1]` and `values[2]` at `s` in `[0, 1]`.
+    ///
+    /// `values[0]` and `values[3]` are the neighbouring keyframes used by
+    /// `Cubic` to estimate tangents, or with `CubicSpline` the out-tangent of
+    /// the start keyframe and the in-tangent of the end keyframe, see
+    /// `Track::value_indices`. `times` are the times of the neighbouring
+    /// keyframes, see `Track::neighbors`.
+    fn sample<V>(
+        &self,
+        times: &[f32; 4],
+        values: &[V; 4],
+        s: f32,
+    ) -> V
+    where
+        V: Copy + ops::Add<Output = V> + ops::Sub<Output = V> + ops::Mul<f32, Output = V>,
+    {
+        match *self {
+            Interpolation::Discrete => values[1],
+            Interpolation::Linear => values[1] * (1.0 - s) + values[2] * s,
+            Interpolation::Cubic => {
+                let dt = times[2] - times[1];
+                let tangent = |a: usize, b: usize| {
+                    let span = times[b] - times[a];
+                    if span > 0.0 {
+                        (values[b] - values[a]) * (dt / span)
+                    } else {
+                        values[1] * 0.0
+                    }
+                };
+                hermite(values[1], tangent(0, 2), values[2], tangent(1, 3), s)
+            }
+            Interpolation::CubicSpline => {
+                let dt = times[2] - times[1];
+                hermite(values[1], values[0] * dt, values[2], values[3] * dt, s)
+            }
+        }
+    }
+}
+
+/// Evaluates the cubic Hermite curve from `p0` to `p1` with the tangents
+/// `m0` and `m1`, scaled to the length of the frame, at `s` in `[0, 1]`.
+fn hermite<V>(
+    p0: V,
+    m0: V,
+    p1: V,
+    m1: V,
+    s: f32,
+) -> V
+where
+    V: Copy + ops::Add<Output = V> + ops::Mul<f32, Output = V>,
+{
+    let (s2, s3) = (s * s, s * s * s);
+    p0 * (2.0 * s3 - 3.0 * s2 + 1.0) + m0 * (s3 - 2.0 * s2 + s) +
+        p1 * (3.0 * s2 - 2.0 * s3) + m1 * (s3 - s2)
+}
+
 impl Track {
+    /// Returns the indices of the keyframes before, at the start of, at the
+    /// end of and after the frame `index`, clamped to the track.
+    fn neighbors(
+        &self,
+        index: usize,
+    ) -> [usize; 4] {
+        let last = self.times.len() - 1;
+        [
+            if index > 0 { index - 1 } else { 0 },
+            index,
+            index + 1,
+            if index + 2 <= last { index + 2 } else { last },
+        ]
+    }
+
+    /// Returns the number of values per keyframe and per blend shape.
+    fn values_per_key(&self) -> usize {
+        match self.interpolation {
+            Interpolation::CubicSpline => 3,
+            _ => 1,
+        }
+    }
+
+    /// Returns the indices of the values sampled for the frame `index`, in
+    /// the order expected by `Interpolation::sample`.
+    fn value_indices(
+        &self,
+        index: usize,
+    ) -> [usize; 4] {
+        match self.interpolation {
+            // out-tangent and value of the start keyframe, value and
+            // in-tangent of the end keyframe
+            Interpolation::CubicSpline => [3 * index + 2, 3 * index + 1, 3 * index + 4, 3 * index + 3],
+            _ => self.neighbors(index),
+        }
+    }
+
+    /// Samples the rotation between `values[1]` and `values[2]`, like
+    /// `Interpolation::sample`.
+    fn sample_orientation(
+        &self,
+        times: &[f32; 4],
+        values: &[cgmath::Quaternion<f32>; 4],
+        s: f32,
+    ) -> cgmath::Quaternion<f32> {
+        use cgmath::InnerSpace;
+        let (start, end) = (values[1], values[2]);
+        match self.interpolation {
+            Interpolation::Discrete => start,
+            Interpolation::Linear => start.slerp(end, s),
+            Interpolation::Cubic => {
+                // blend the components within one hemisphere, then renormalize
+                let aligned = |q: cgmath::Quaternion<f32>| {
+                    let v = cgmath::Vector4::new(q.v.x, q.v.y, q.v.z, q.s);
+                    let reference = cgmath::Vector4::new(start.v.x, start.v.y, start.v.z, start.s);
+                    if v.dot(reference) < 0.0 { -v } else { v }
+                };
+                let components = [
+                    aligned(values[0]),
+                    aligned(values[1]),
+                    aligned(values[2]),
+                    aligned(values[3]),
+                ];
+                let v = self.interpolation.sample(times, &components, s).normalize();
+                cgmath::Quaternion::new(v.w, v.x, v.y, v.z)
+            }
+            Interpolation::CubicSpline => {
+                // the tangents are authored for the components as they are
+                let components = |q: cgmath::Quaternion<f32>| cgmath::Vector4::new(q.v.x, q.v.y, q.v.z, q.s);
+                let components = [
+                    components(values[0]),
+                    components(values[1]),
+                    components(values[2]),
+                    components(values[3]),
+                ];
+                let v = self.interpolation.sample(times, &components, s).normalize();
+                cgmath::Quaternion::new(v.w, v.x, v.y, v.z)
+            }
+        }
+    }
+
     fn frame_at_time(
         &self,
         t: f32,
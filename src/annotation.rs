@@ -0,0 +1,451 @@
+//! Labels attached to points in the scene.
+//!
+//! An [`Annotation`] is anchored to a position in the world or on an object. Each
+//! frame, [`Annotation::update`] places its screen-space label above the anchor,
+//! connected by a leader line, and dispatches mouse clicks on the label.
+//!
+//! ```rust,no_run
+//! # let mut window = three::Window::new("");
+//! # let camera = window.factory.perspective_camera(60.0, 0.1 .. 10.0);
+//! # let mesh = window.factory.mesh(three::Geometry::uv_sphere(1.0, 16, 16), three::material::Basic::default());
+//! use three::annotation::Anchor;
+//!
+//! let font = window.factory.load_font_karla();
+//! let anchor = Anchor::object(&mesh, [0.0, 1.0, 0.0]);
+//! let mut annotation = window.factory.annotation(anchor, "North pole", &font);
+//! annotation.set_on_click(|| println!("clicked"));
+//! window.scene.add(&annotation);
+//! while window.update() {
+//!     annotation.update(&mut window, &camera);
+//!     window.render(&camera);
+//! }
+//! ```
+//!
+//! A [`TextBillboard`] is a label without a leader line, standing at its anchor
+//! like a sign facing the camera. It can keep a constant size on screen or shrink
+//! with distance, and is hidden behind scene meshes.
+//!
+//! [`Annotation`]: struct.Annotation.html
+//! [`Annotation::update`]: struct.Annotation.html#method.update
+//! [`TextBillboard`]: struct.TextBillboard.html
+
+use cgmath::{EuclideanSpace, InnerSpace, Point3, Quaternion, Rotation, Vector3};
+use mint;
+
+use camera::Camera;
+use input::MOUSE_LEFT;
+use mesh::Mesh;
+use object::{Base, Group, Object};
+use raycast::{Ray, Raycaster};
+use scene::{Scene, SyncGuard};
+use text::Text;
+use window::Window;
+
+use std::fmt;
+
+/// The point an [`Annotation`](struct.Annotation.html) is attached to.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Anchor {
+    /// A fixed position in world space.
+    Point(mint::Point3<f32>),
+    /// A position relative to an object, following its transform.
+    Object {
+        /// The object the annotation is attached to.
+        object: Base,
+        /// Position of the anchor in the local space of `object`.
+        offset: mint::Vector3<f32>,
+    },
+}
+
+impl Anchor {
+    /// Creates an anchor fixed in world space.
+    pub fn point<P: Into<mint::Point3<f32>>>(point: P) -> Self {
+        Anchor::Point(point.into())
+    }
+
+    /// Creates an anchor following `object`, at `offset` in its local space.
+    pub fn object<T, V>(
+        object: &T,
+        offset: V,
+    ) -> Self
+    where
+        T: Object,
+        V: Into<mint::Vector3<f32>>,
+    {
+        Anchor::Object {
+            object: object.upcast(),
+            offset: offset.into(),
+        }
+    }
+
+    /// Returns the anchor position in world space.
+    fn resolve(
+        &self,
+        guard: &SyncGuard,
+    ) -> Point3<f32> {
+        match *self {
+            Anchor::Point(point) => Point3::from(point),
+            Anchor::Object { ref object, offset } => {
+                let node = guard.resolve_world(object).transform;
+                let position = Point3::from(node.position);
+                let rotation = Quaternion::from(node.orientation);
+                position + rotation.rotate_vector(Vector3::from(offset)) * node.scale
+            }
+        }
+    }
+}
+
+/// Returns `true` if a visible mesh of `scene` lies between `eye` and `point`.
+fn is_occluded(
+    scene: &Scene,
+    eye: Point3<f32>,
+    point: Point3<f32>,
+) -> bool {
+    let raycaster = Raycaster::new(Ray {
+        origin: eye.into(),
+        direction: (point - eye).into(),
+    });
+    // allow for points lying on a mesh surface
+    raycaster
+        .intersect(scene)
+        .first()
+        .map_or(false, |hit| hit.distance < 0.999)
+}
+
+/// A screen-space label connected to an anchor point by a leader line.
+///
+/// Created with [`Factory::annotation`](../struct.Factory.html#method.annotation).
+pub struct Annotation {
+    group: Group,
+    leader: Mesh,
+    label: Text,
+    anchor: Anchor,
+    leader_length: f32,
+    hit_size: mint::Vector2<f32>,
+    occluded_opacity: f32,
+    screen_position: Option<mint::Point2<f32>>,
+    on_click: Option<Box<FnMut()>>,
+}
+three_object!(Annotation::group);
+
+impl fmt::Debug for Annotation {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter,
+    ) -> fmt::Result {
+        f.debug_struct("Annotation")
+            .field("group", &self.group)
+            .field("leader", &self.leader)
+            .field("label", &self.label)
+            .field("anchor", &self.anchor)
+            .field("leader_length", &self.leader_length)
+            .field("hit_size", &self.hit_size)
+            .field("occluded_opacity", &self.occluded_opacity)
+            .field("screen_position", &self.screen_position)
+            .finish()
+    }
+}
+
+impl Annotation {
+    pub(crate) fn new(
+        group: Group,
+        leader: Mesh,
+        label: Text,
+        anchor: Anchor,
+    ) -> Self {
+        group.add(&leader);
+        group.add(&label);
+        Annotation {
+            group,
+            leader,
+            label,
+            anchor,
+            leader_length: 0.25,
+            hit_size: [120.0, 20.0].into(),
+            occluded_opacity: 0.25,
+            screen_position: None,
+            on_click: None,
+        }
+    }
+
+    /// Moves the annotation to another anchor.
+    pub fn set_anchor(
+        &mut self,
+        anchor: Anchor,
+    ) {
+        self.anchor = anchor;
+    }
+
+    /// Sets the length of the leader line, in world units.
+    ///
+    /// Defaults to `0.25`.
+    pub fn set_leader_length(
+        &mut self,
+        length: f32,
+    ) {
+        self.leader_length = length;
+    }
+
+    /// Sets the size in pixels of the clickable area, starting at the label position.
+    ///
+    /// Defaults to 120x20.
+    pub fn set_hit_size<V: Into<mint::Vector2<f32>>>(
+        &mut self,
+        size: V,
+    ) {
+        self.hit_size = size.into();
+    }
+
+    /// Sets the label opacity used when the anchor is hidden by a scene mesh.
+    ///
+    /// Defaults to `0.25`.
+    pub fn set_occluded_opacity(
+        &mut self,
+        opacity: f32,
+    ) {
+        self.occluded_opacity = opacity;
+    }
+
+    /// Sets the callback invoked when the label is clicked.
+    pub fn set_on_click<F>(
+        &mut self,
+        callback: F,
+    ) where
+        F: 'static + FnMut(),
+    {
+        self.on_click = Some(Box::new(callback));
+    }
+
+    /// Removes the click callback.
+    pub fn clear_on_click(&mut self) {
+        self.on_click = None;
+    }
+
+    /// Returns the label, e.g. to change its text, color or font size.
+    pub fn label(&mut self) -> &mut Text {
+        &mut self.label
+    }
+
+    /// Returns the label position in pixels as of the last update, if the
+    /// annotation was in front of the camera.
+    pub fn screen_position(&self) -> Option<mint::Point2<f32>> {
+        self.screen_position
+    }
+
+    /// Places the label and leader line as seen by `camera`, fades the label
+    /// when the anchor is occluded and invokes the click callback.
+    ///
+    /// Call this each frame before rendering.
+    ///
+    /// The anchor is occluded if a ray cast from the camera hits a visible
+    /// scene mesh before reaching it, see [`Raycaster`].
+    ///
+    /// [`Raycaster`]: ../raycast/struct.Raycaster.html
+    ///
+    /// # Panics
+    ///
+    /// Panics if the anchor object or `camera` isn't in the scene.
+    pub fn update(
+        &mut self,
+        window: &mut Window,
+        camera: &Camera,
+    ) {
+        let (camera_position, camera_orientation, anchor) = {
+            let guard = window.scene.sync_guard();
+            let camera_node = guard.resolve_world(camera).transform;
+            (
+                Point3::from(camera_node.position),
+                Quaternion::from(camera_node.orientation),
+                self.anchor.resolve(&guard),
+            )
+        };
+
+        let up = camera_orientation.rotate_vector(Vector3::unit_y());
+        let top = anchor + up * self.leader_length;
+        self.screen_position = window.world_to_screen(camera, top);
+        let position = match self.screen_position {
+            Some(position) => position,
+            None => {
+                self.label.set_visible(false);
+                self.leader.set_visible(false);
+                return;
+            }
+        };
+
+        let opacity = if is_occluded(&window.scene, camera_position, anchor) {
+            self.occluded_opacity
+        } else {
+            1.0
+        };
+
+        let anchor_point: mint::Point3<f32> = anchor.into();
+        self.leader.set_transform(anchor_point, camera_orientation, 1.0);
+        self.leader.set_nonuniform_scale([1.0, self.leader_length, 1.0]);
+        self.leader.set_visible(true);
+        self.label.set_visible(true);
+        self.label.set_opacity(opacity);
+        self.label.set_pos(position);
+
+        if window.input.hit_count(MOUSE_LEFT) > 0 {
+            let mouse = window.input.mouse_pos();
+            let inside = mouse.x >= position.x && mouse.x <= position.x + self.hit_size.x &&
+                mouse.y >= position.y && mouse.y <= position.y + self.hit_size.y;
+            if inside {
+                if let Some(ref mut callback) = self.on_click {
+                    callback();
+                }
+            }
+        }
+    }
+}
+
+/// How the font size of a [`TextBillboard`](struct.TextBillboard.html) follows
+/// the distance to the camera.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Sizing {
+    /// The text keeps its font size regardless of distance.
+    ConstantScreenSize,
+    /// The text has its font size at `reference_distance` from the camera,
+    /// and shrinks or grows in inverse proportion to the distance.
+    DistanceScaled {
+        /// Distance in world units at which the font size is unchanged.
+        reference_distance: f32,
+    },
+}
+
+/// Text standing at a point in the scene, always facing the camera.
+///
+/// Created with [`Factory::text_billboard`](../struct.Factory.html#method.text_billboard).
+#[derive(Clone, Debug)]
+pub struct TextBillboard {
+    label: Text,
+    anchor: Anchor,
+    font_size: f32,
+    sizing: Sizing,
+    depth_test: bool,
+    screen_position: Option<mint::Point2<f32>>,
+}
+three_object!(TextBillboard::label);
+
+impl TextBillboard {
+    pub(crate) fn new(
+        label: Text,
+        anchor: Anchor,
+    ) -> Self {
+        TextBillboard {
+            label,
+            anchor,
+            font_size: 16.0,
+            sizing: Sizing::ConstantScreenSize,
+            depth_test: true,
+            screen_position: None,
+        }
+    }
+
+    /// Moves the billboard to another anchor.
+    pub fn set_anchor(
+        &mut self,
+        anchor: Anchor,
+    ) {
+        self.anchor = anchor;
+    }
+
+    /// Sets the font size, in pixels at the reference distance when using
+    /// `Sizing::DistanceScaled`.
+    ///
+    /// Defaults to `16.0`.
+    pub fn set_font_size(
+        &mut self,
+        size: f32,
+    ) {
+        self.font_size = size;
+    }
+
+    /// Sets how the font size follows the distance to the camera.
+    ///
+    /// Defaults to `Sizing::ConstantScreenSize`.
+    pub fn set_sizing(
+        &mut self,
+        sizing: Sizing,
+    ) {
+        self.sizing = sizing;
+    }
+
+    /// Sets whether the text is hidden when a mesh is between the camera and
+    /// the anchor.
+    ///
+    /// The test casts a ray against the visible scene meshes on each update,
+    /// which may be slow with many billboards or large meshes.
+    ///
+    /// Defaults to `true`.
+    pub fn set_depth_test(
+        &mut self,
+        enabled: bool,
+    ) {
+        self.depth_test = enabled;
+    }
+
+    /// Returns the label, e.g. to change its text, color or style.
+    pub fn label(&mut self) -> &mut Text {
+        &mut self.label
+    }
+
+    /// Returns the text position in pixels as of the last update, if the
+    /// billboard was visible.
+    pub fn screen_position(&self) -> Option<mint::Point2<f32>> {
+        self.screen_position
+    }
+
+    /// Places and sizes the text as seen by `camera`, hiding it when behind
+    /// the camera or occluded.
+    ///
+    /// Call this each frame before rendering.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the anchor object or `camera` isn't in the scene.
+    pub fn update(
+        &mut self,
+        window: &mut Window,
+        camera: &Camera,
+    ) {
+        let (camera_position, anchor) = {
+            let guard = window.scene.sync_guard();
+            let camera_node = guard.resolve_world(camera).transform;
+            (Point3::from(camera_node.position), self.anchor.resolve(&guard))
+        };
+
+        self.screen_position = window.world_to_screen(camera, anchor);
+        if self.screen_position.is_some() && self.depth_test {
+            if is_occluded(&window.scene, camera_position, anchor) {
+                self.screen_position = None;
+            }
+        }
+        let position = match self.screen_position {
+            Some(position) => position,
+            None => {
+                self.label.set_visible(false);
+                return;
+            }
+        };
+
+        let font_size = match self.sizing {
+            Sizing::ConstantScreenSize => self.font_size,
+            Sizing::DistanceScaled { reference_distance } => {
+                let distance = (anchor - camera_position).magnitude().max(::std::f32::EPSILON);
+                self.font_size * reference_distance / distance
+            }
+        };
+        self.label.set_visible(true);
+        self.label.set_font_size(font_size);
+        self.label.set_pos(position);
+    }
+}
+
+/// Returns the vertices of the unit leader line, along the local Y axis.
+pub(crate) fn unit_leader() -> Vec<mint::Point3<f32>> {
+    vec![
+        Point3::origin().into(),
+        [0.0, 1.0, 0.0].into(),
+    ]
+}
@@ -1,4 +1,10 @@
 //! Primitives for audio playback.
+//!
+//! Playback goes through [`rodio`], which doesn't support every target `three`
+//! runs on. The module is behind the `audio` feature, enabled by default, so
+//! that mobile builds can opt out with `default-features = false`.
+//!
+//! [`rodio`]: https://crates.io/crates/rodio
 
 use hub;
 use object::{Base, ObjectType};
@@ -0,0 +1,376 @@
+//! Baking of static lighting into vertex colors.
+//!
+//! Lighting geometry every frame is wasted work when neither the geometry nor
+//! the lights move. [`bake_vertex_colors`] evaluates the lights of a scene once,
+//! optionally with shadows and ambient occlusion, and stores the result in
+//! [`Geometry::colors`]. Meshes created from the baked geometry with a
+//! [`Basic`] material are drawn without any per-frame lighting, which is a
+//! large saving on mobile-class hardware.
+//!
+//! ```rust,no_run
+//! # let mut window = three::Window::new("");
+//! # let mesh = window.factory.mesh(three::Geometry::uv_sphere(1.0, 16, 16), three::material::Lambert::default());
+//! # window.scene.add(&mesh);
+//! use three::bake::{self, AmbientOcclusion};
+//!
+//! let options = bake::Options {
+//!     ambient_occlusion: Some(AmbientOcclusion::default()),
+//!     .. bake::Options::default()
+//! };
+//! if let Some(baked) = window.factory.bake_mesh(&window.scene, &mesh, &options) {
+//!     window.scene.remove(&mesh);
+//!     window.scene.add(&baked);
+//! }
+//! ```
+//!
+//! [`bake_vertex_colors`]: fn.bake_vertex_colors.html
+//! [`Geometry::colors`]: ../struct.Geometry.html#structfield.colors
+//! [`Basic`]: ../material/struct.Basic.html
+
+use cgmath::{EuclideanSpace, InnerSpace, Matrix, Matrix3, Point3, SquareMatrix, Transform, Vector3, Zero};
+
+use collision::Aabb;
+use color;
+use geometry::Geometry;
+use hub::{SubLight, SubNode};
+use mesh::Mesh;
+use raycast::{self, Pose};
+use scene::Scene;
+
+use std::f32::consts::PI;
+
+/// Ambient occlusion settings for [`bake_vertex_colors`](fn.bake_vertex_colors.html).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AmbientOcclusion {
+    /// Number of rays cast over the hemisphere of each vertex.
+    ///
+    /// Default: `32`.
+    pub samples: usize,
+
+    /// Distance beyond which geometry no longer occludes, in world units.
+    ///
+    /// Default: `1.0`.
+    pub distance: f32,
+}
+
+impl Default for AmbientOcclusion {
+    fn default() -> Self {
+        AmbientOcclusion {
+            samples: 32,
+            distance: 1.0,
+        }
+    }
+}
+
+/// Settings for [`bake_vertex_colors`](fn.bake_vertex_colors.html).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Options {
+    /// Darkens the ambient and hemisphere lighting of vertices surrounded by
+    /// other geometry.
+    ///
+    /// Default: `None`.
+    pub ambient_occlusion: Option<AmbientOcclusion>,
+
+    /// Casts shadows of the visible meshes of the scene from directional and
+    /// point lights.
+    ///
+    /// Default: `true`.
+    pub shadows: bool,
+
+    /// Offset of the shadow and occlusion rays from the surface, in world
+    /// units, to keep vertices from shadowing themselves.
+    ///
+    /// Default: `0.001`.
+    pub bias: f32,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            ambient_occlusion: None,
+            shadows: true,
+            bias: 0.001,
+        }
+    }
+}
+
+enum LightKind {
+    Ambient,
+    Directional(Vector3<f32>),
+    Hemisphere {
+        up: Vector3<f32>,
+        ground: Vector3<f32>,
+    },
+    Point(Point3<f32>),
+}
+
+struct BakedLight {
+    /// Linear color premultiplied by the intensity.
+    color: Vector3<f32>,
+    kind: LightKind,
+}
+
+struct Occluder {
+    bounds: Aabb,
+    triangles: Vec<[Point3<f32>; 3]>,
+}
+
+/// Evaluates the lights of `scene` at the vertices of `mesh` and returns its
+/// geometry with the result stored in [`colors`](../struct.Geometry.html#structfield.colors).
+///
+/// Lights are evaluated like the `Lambert` material does: ambient, directional,
+/// hemisphere and point lights respecting the light mask of the mesh, plus the
+/// ambient light of the scene environment. Specular highlights depend on the
+/// viewer and are not baked. The mesh is baked in its bind pose at its current
+/// world transform, and the colors don't include the material color.
+///
+/// Returns `None` if the mesh isn't in `scene` or wasn't created from a
+/// [`Geometry`](../struct.Geometry.html).
+pub fn bake_vertex_colors(
+    scene: &Scene,
+    mesh: &Mesh,
+    options: &Options,
+) -> Option<Geometry> {
+    let mut hub = scene.hub.lock().unwrap();
+    hub.process_messages();
+
+    let mx_world = match hub.walk_all(&scene.first_child).find(|w| w.node_ptr == mesh.object.node) {
+        Some(w) => w.world_matrix(),
+        None => return None,
+    };
+    let mut lights = Vec::new();
+    let mut occluder_nodes = Vec::new();
+    for w in hub.walk(&scene.first_child) {
+        match w.node.sub_node {
+            SubNode::Light(ref light) => {
+                let rgb = color::to_linear_rgb(light.color);
+                let direction = w.world_transform.rot * Vector3::unit_z();
+                let kind = match light.sub_light {
+                    SubLight::Ambient => LightKind::Ambient,
+                    SubLight::Directional => LightKind::Directional(direction.normalize()),
+                    SubLight::Hemisphere { ground } => LightKind::Hemisphere {
+                        up: direction.normalize(),
+                        ground: Vector3::from(color::to_linear_rgb(ground)) * light.intensity,
+                    },
+                    SubLight::Point => LightKind::Point(Point3::from_vec(w.world_transform.disp)),
+                };
+                lights.push((light.layers, BakedLight {
+                    color: Vector3::from(rgb) * light.intensity,
+                    kind,
+                }));
+            }
+            SubNode::Visual(..) => {
+                occluder_nodes.push((w.node_ptr.clone(), w.world_matrix()));
+            }
+            _ => {}
+        }
+    }
+    let (mut geometry, light_mask) = match hub[mesh].sub_node {
+        SubNode::Visual(_, ref gpu_data, _) => match gpu_data.geometry {
+            Some(ref geometry) => ((**geometry).clone(), gpu_data.light_mask),
+            None => return None,
+        },
+        _ => unreachable!(),
+    };
+    let lights = lights
+        .into_iter()
+        .filter(|&(layers, _)| layers & light_mask != 0)
+        .map(|(_, light)| light)
+        .collect::<Vec<_>>();
+
+    let occluders = if options.shadows || options.ambient_occlusion.is_some() {
+        occluder_nodes
+            .into_iter()
+            .filter_map(|(node_ptr, mx_node)| {
+                let object = hub.upgrade_ptr(node_ptr);
+                raycast::node_triangles(&hub, &scene.first_child, &object, mx_node, Pose::Bind)
+            })
+            .filter_map(|triangles| {
+                Aabb::from_points(triangles.iter().flat_map(|t| t.iter().cloned()))
+                    .map(|bounds| Occluder { bounds, triangles })
+            })
+            .collect()
+    } else {
+        Vec::new()
+    };
+
+    let ambient = {
+        let env = &scene.environment;
+        Vector3::from(color::to_linear_rgb(env.ambient_color)) * env.ambient_intensity
+    };
+    let mx_normal = Matrix3::new(
+        mx_world.x.x, mx_world.x.y, mx_world.x.z,
+        mx_world.y.x, mx_world.y.y, mx_world.y.z,
+        mx_world.z.x, mx_world.z.y, mx_world.z.z,
+    ).invert()
+        .map(|m| m.transpose())
+        .unwrap_or(Matrix3::zero());
+    let normals = if geometry.base.normals.is_empty() {
+        vertex_normals(&geometry)
+    } else {
+        geometry.base.normals.iter().map(|&n| Vector3::from(n)).collect()
+    };
+
+    geometry.colors = geometry
+        .base
+        .vertices
+        .iter()
+        .zip(normals)
+        .map(|(&v, n)| {
+            let position = mx_world.transform_point(Point3::from(v));
+            let mut normal = (mx_normal * n).normalize();
+            if !normal.x.is_finite() {
+                normal = Vector3::zero();
+            }
+            let origin = position + normal * options.bias;
+            let occlusion = match options.ambient_occlusion {
+                Some(ref ao) if normal != Vector3::zero() => {
+                    ambient_occlusion(&occluders, origin, normal, ao)
+                }
+                _ => 1.0,
+            };
+
+            let mut irradiance = ambient * occlusion;
+            for light in &lights {
+                irradiance += match light.kind {
+                    LightKind::Ambient => light.color * occlusion,
+                    LightKind::Hemisphere { up, ground } => {
+                        let t = normal.dot(up) * 0.5 + 0.5;
+                        (ground * (1.0 - t) + light.color * t) * occlusion
+                    }
+                    LightKind::Directional(direction) => {
+                        let dot_nl = normal.dot(direction);
+                        if dot_nl <= 0.0 ||
+                            options.shadows && occluded(&occluders, origin, direction, None)
+                        {
+                            continue;
+                        }
+                        light.color * dot_nl
+                    }
+                    LightKind::Point(point) => {
+                        let offset = point - origin;
+                        let distance = offset.magnitude();
+                        let direction = offset / distance;
+                        let dot_nl = normal.dot(direction);
+                        if dot_nl <= 0.0 ||
+                            options.shadows && occluded(&occluders, origin, direction, Some(distance))
+                        {
+                            continue;
+                        }
+                        light.color * dot_nl
+                    }
+                };
+            }
+            [irradiance.x, irradiance.y, irradiance.z, 1.0]
+        })
+        .collect();
+    Some(geometry)
+}
+
+/// Averages the normals of the faces around each vertex.
+fn vertex_normals(geometry: &Geometry) -> Vec<Vector3<f32>> {
+    let vertices = &geometry.base.vertices;
+    let mut normals = vec![Vector3::zero(); vertices.len()];
+    let faces = if geometry.faces.is_empty() {
+        (0 .. vertices.len() as u32 / 3)
+            .map(|i| [3 * i, 3 * i + 1, 3 * i + 2])
+            .collect()
+    } else {
+        geometry.faces.clone()
+    };
+    for face in &faces {
+        let p = [
+            Point3::from(vertices[face[0] as usize]),
+            Point3::from(vertices[face[1] as usize]),
+            Point3::from(vertices[face[2] as usize]),
+        ];
+        let normal = (p[1] - p[0]).cross(p[2] - p[0]);
+        for &index in face {
+            normals[index as usize] += normal;
+        }
+    }
+    normals
+}
+
+/// Returns the fraction of cosine-weighted hemisphere rays around `normal`
+/// that escape within `ao.distance`.
+fn ambient_occlusion(
+    occluders: &[Occluder],
+    origin: Point3<f32>,
+    normal: Vector3<f32>,
+    ao: &AmbientOcclusion,
+) -> f32 {
+    if ao.samples == 0 {
+        return 1.0;
+    }
+    let helper = if normal.x.abs() < 0.9 {
+        Vector3::unit_x()
+    } else {
+        Vector3::unit_y()
+    };
+    let tangent = helper.cross(normal).normalize();
+    let bitangent = normal.cross(tangent);
+
+    let mut open = 0;
+    for i in 0 .. ao.samples {
+        // Hammersley point set, mapped to a cosine-weighted hemisphere
+        let u = (i as f32 + 0.5) / ao.samples as f32;
+        let v = radical_inverse(i as u32);
+        let (r, phi) = (u.sqrt(), 2.0 * PI * v);
+        let direction = tangent * (r * phi.cos()) + bitangent * (r * phi.sin()) +
+            normal * (1.0 - u).sqrt();
+        if !occluded(occluders, origin, direction, Some(ao.distance)) {
+            open += 1;
+        }
+    }
+    open as f32 / ao.samples as f32
+}
+
+/// Van der Corput radical inverse of `i` in base 2.
+fn radical_inverse(mut i: u32) -> f32 {
+    i = (i << 16) | (i >> 16);
+    i = ((i & 0x5555_5555) << 1) | ((i & 0xAAAA_AAAA) >> 1);
+    i = ((i & 0x3333_3333) << 2) | ((i & 0xCCCC_CCCC) >> 2);
+    i = ((i & 0x0F0F_0F0F) << 4) | ((i & 0xF0F0_F0F0) >> 4);
+    i = ((i & 0x00FF_00FF) << 8) | ((i & 0xFF00_FF00) >> 8);
+    i as f32 / 4_294_967_296.0
+}
+
+/// Returns `true` if the ray from `origin` along `direction` hits any occluder
+/// closer than `max_distance`.
+fn occluded(
+    occluders: &[Occluder],
+    origin: Point3<f32>,
+    direction: Vector3<f32>,
+    max_distance: Option<f32>,
+) -> bool {
+    let max_distance = max_distance.unwrap_or(f32::INFINITY);
+    occluders
+        .iter()
+        .filter(|o| ray_hits_box(&o.bounds, origin, direction, max_distance))
+        .any(|o| {
+            o.triangles.iter().any(|corners| {
+                raycast::intersect_triangle(origin, direction, corners)
+                    .map_or(false, |t| t < max_distance)
+            })
+        })
+}
+
+/// Slab test of the ray against `bounds`.
+fn ray_hits_box(
+    bounds: &Aabb,
+    origin: Point3<f32>,
+    direction: Vector3<f32>,
+    max_distance: f32,
+) -> bool {
+    let (lo, hi) = (Point3::from(bounds.min), Point3::from(bounds.max));
+    let (mut near, mut far) = (0.0f32, max_distance);
+    for axis in 0 .. 3 {
+        let inv = 1.0 / direction[axis];
+        let t0 = (lo[axis] - origin[axis]) * inv;
+        let t1 = (hi[axis] - origin[axis]) * inv;
+        near = near.max(t0.min(t1));
+        far = far.min(t0.max(t1));
+    }
+    near <= far
+}
@@ -0,0 +1,415 @@
+//! Stress scenes and frame statistics for measuring the renderer performance.
+//!
+//! A [`StressScene`] is generated procedurally from a [`Config`]: the same
+//! configuration, seed included, always gives the same scene, so that frame
+//! times can be compared across versions of `three` and across machines.
+//! [`FrameTimes`] collects the time of each frame and summarizes them in a
+//! [`Report`].
+//!
+//! ```rust,no_run
+//! use three::bench::{Config, FrameTimes, StressScene};
+//!
+//! let mut window = three::Window::builder("bench").vsync(false).build();
+//! let camera = window.factory.perspective_camera(60.0, 0.1 .. 200.0);
+//! three::Object::set_position(&camera, [0.0, 20.0, 60.0]);
+//!
+//! let config = Config {
+//!     meshes: 5000,
+//!     ..Config::default()
+//! };
+//! let stress = StressScene::generate(&mut window.factory, &config);
+//! window.scene.add(&stress.group);
+//!
+//! let mut times = FrameTimes::new(10);
+//! while times.len() < 500 && window.update() {
+//!     times.push(window.input.real_delta_time());
+//!     stress.update(times.elapsed());
+//!     window.render(&camera);
+//! }
+//! println!("{}", times.report().unwrap());
+//! ```
+//!
+//! [`Config`]: struct.Config.html
+//! [`FrameTimes`]: struct.FrameTimes.html
+//! [`Report`]: struct.Report.html
+//! [`StressScene`]: struct.StressScene.html
+
+use cgmath::{Matrix4, Quaternion, Rad, Rotation3, SquareMatrix, Vector3};
+use mint;
+
+use factory::Factory;
+use geometry::Geometry;
+use light;
+use material;
+use mesh::Mesh;
+use object::{Group, Object};
+use skeleton::Bone;
+use util::Rng;
+
+use std::f32::consts::PI;
+use std::fmt;
+
+/// Parameters of a [`StressScene`](struct.StressScene.html).
+#[derive(Clone, Debug, PartialEq)]
+pub struct Config {
+    /// Seed of the placement, shapes and colors of the scene contents.
+    ///
+    /// Default: `0`.
+    pub seed: u32,
+
+    /// Number of static meshes, with a mix of shapes and materials.
+    ///
+    /// Default: `1000`.
+    pub meshes: usize,
+
+    /// Number of point lights orbiting above the meshes.
+    ///
+    /// Default: `4`.
+    pub lights: usize,
+
+    /// Number of skinned characters, each swaying with its own skeleton.
+    ///
+    /// Default: `10`.
+    pub characters: usize,
+
+    /// Whether the meshes spin, sending a transform change for each of them
+    /// on every [`update`](struct.StressScene.html#method.update).
+    ///
+    /// Default: `false`.
+    pub spinning: bool,
+
+    /// Half the width of the square area the contents are scattered over,
+    /// around the origin.
+    ///
+    /// Default: `50.0`.
+    pub extent: f32,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            seed: 0,
+            meshes: 1000,
+            lights: 4,
+            characters: 10,
+            spinning: false,
+            extent: 50.0,
+        }
+    }
+}
+
+struct Spinner {
+    mesh: Mesh,
+    speed: f32,
+    phase: f32,
+}
+
+struct Orbit {
+    light: light::Point,
+    center: [f32; 2],
+    radius: f32,
+    height: f32,
+    speed: f32,
+}
+
+struct Character {
+    sway: Bone,
+    speed: f32,
+}
+
+/// A procedurally generated scene for measuring the renderer performance.
+pub struct StressScene {
+    /// Root of the scene contents, to be added to a scene.
+    pub group: Group,
+    meshes: Vec<Spinner>,
+    lights: Vec<Orbit>,
+    characters: Vec<Character>,
+    spinning: bool,
+}
+
+/// Half the height of the character cylinders.
+const CHARACTER_HALF_HEIGHT: f32 = 1.0;
+
+/// Creates a cylinder with two bones, the lower one fixed at its base and
+/// the upper one pivoting at its middle.
+fn character_geometry() -> Geometry {
+    let mut geometry = Geometry::cylinder(0.3, 0.3, 2.0 * CHARACTER_HALF_HEIGHT, 8);
+    let (indices, weights): (Vec<_>, Vec<_>) = geometry
+        .base
+        .vertices
+        .iter()
+        .map(|v| {
+            let t = (v.y / CHARACTER_HALF_HEIGHT + 1.0) * 0.5;
+            ([0, 1, 0, 0], [1.0 - t, t, 0.0, 0.0])
+        })
+        .unzip();
+    geometry.joints.indices = indices;
+    geometry.joints.weights = weights;
+    geometry
+}
+
+impl StressScene {
+    /// Generates the scene described by `config`.
+    pub fn generate(
+        factory: &mut Factory,
+        config: &Config,
+    ) -> Self {
+        let mut rng = Rng::new(config.seed);
+        let group = factory.group();
+        let extent = config.extent;
+
+        // a few shared geometries, as in most scenes
+        let shapes = vec![
+            factory.upload_geometry(Geometry::cuboid(1.0, 1.0, 1.0)),
+            factory.upload_geometry(Geometry::uv_sphere(0.6, 16, 12)),
+            factory.upload_geometry(Geometry::cylinder(0.5, 0.5, 1.0, 12)),
+        ];
+        let mut meshes = Vec::with_capacity(config.meshes);
+        for i in 0 .. config.meshes {
+            let color = rng.color();
+            let material: material::Material = match i % 4 {
+                0 => material::Basic {
+                    color,
+                    ..Default::default()
+                }.into(),
+                1 => material::Lambert {
+                    color,
+                    ..Default::default()
+                }.into(),
+                2 => material::Phong {
+                    color,
+                    ..Default::default()
+                }.into(),
+                _ => material::Pbr {
+                    base_color_factor: color,
+                    metallic_factor: rng.unit(),
+                    roughness_factor: rng.unit(),
+                    ..Default::default()
+                }.into(),
+            };
+            let shape = &shapes[rng.next() as usize % shapes.len()];
+            let mesh = factory.create_instanced_mesh(shape, material);
+            mesh.set_position([
+                rng.range(-extent, extent),
+                rng.range(0.0, 0.2 * extent),
+                rng.range(-extent, extent),
+            ]);
+            let phase = rng.range(0.0, 2.0 * PI);
+            mesh.set_orientation(Quaternion::from_angle_y(Rad(phase)));
+            mesh.set_scale(rng.range(0.5, 2.0));
+            group.add(&mesh);
+            meshes.push(Spinner {
+                mesh,
+                speed: rng.range(-2.0, 2.0),
+                phase,
+            });
+        }
+
+        let ambient = factory.ambient_light(0xFFFFFF, 0.2);
+        group.add(&ambient);
+        let mut lights = Vec::with_capacity(config.lights);
+        for _ in 0 .. config.lights {
+            let light = factory.point_light(rng.color() | 0x40_40_40, 1.0);
+            group.add(&light);
+            lights.push(Orbit {
+                light,
+                center: [rng.range(-extent, extent), rng.range(-extent, extent)],
+                radius: rng.range(0.1, 0.5) * extent,
+                height: rng.range(0.2, 0.4) * extent,
+                speed: rng.range(-1.0, 1.0),
+            });
+        }
+
+        let character = character_geometry();
+        let mut characters = Vec::with_capacity(config.characters);
+        for _ in 0 .. config.characters {
+            let root = factory.group();
+            let mesh = factory.mesh(
+                character.clone(),
+                material::Pbr {
+                    base_color_factor: rng.color(),
+                    ..Default::default()
+                },
+            );
+            let base_bind: mint::ColumnMatrix4<f32> =
+                Matrix4::from_translation(Vector3::new(0.0, CHARACTER_HALF_HEIGHT, 0.0)).into();
+            let base = factory.bone(0, base_bind);
+            base.set_position([0.0, -CHARACTER_HALF_HEIGHT, 0.0]);
+            let sway_bind: mint::ColumnMatrix4<f32> = Matrix4::identity().into();
+            let sway = factory.bone(1, sway_bind);
+            let skeleton = factory.skeleton(vec![base.clone(), sway.clone()]);
+            mesh.set_skeleton(skeleton.clone());
+            // groups list their children in reverse, and the skeleton must
+            // come before its bones
+            root.add(&mesh);
+            root.add(&base);
+            root.add(&sway);
+            root.add(&skeleton);
+            root.set_position([
+                rng.range(-extent, extent),
+                CHARACTER_HALF_HEIGHT,
+                rng.range(-extent, extent),
+            ]);
+            group.add(&root);
+            characters.push(Character {
+                sway,
+                speed: rng.range(1.0, 3.0),
+            });
+        }
+
+        StressScene {
+            group,
+            meshes,
+            lights,
+            characters,
+            spinning: config.spinning,
+        }
+    }
+
+    /// Animates the scene at `time` seconds since its start.
+    ///
+    /// The lights orbit and the characters sway, and the meshes spin if
+    /// [`Config::spinning`](struct.Config.html#structfield.spinning) is set.
+    /// The scene only depends on `time`, so that it can be replayed with
+    /// fixed time steps.
+    pub fn update(
+        &self,
+        time: f32,
+    ) {
+        if self.spinning {
+            for spinner in &self.meshes {
+                let angle = spinner.phase + spinner.speed * time;
+                spinner.mesh.set_orientation(Quaternion::from_angle_y(Rad(angle)));
+            }
+        }
+        for orbit in &self.lights {
+            let angle = orbit.speed * time;
+            orbit.light.set_position([
+                orbit.center[0] + orbit.radius * angle.cos(),
+                orbit.height,
+                orbit.center[1] + orbit.radius * angle.sin(),
+            ]);
+        }
+        for character in &self.characters {
+            let angle = 0.5 * (character.speed * time).sin();
+            character.sway.set_orientation(Quaternion::from_angle_z(Rad(angle)));
+        }
+    }
+}
+
+/// Collects the time of each frame.
+#[derive(Clone, Debug, Default)]
+pub struct FrameTimes {
+    warmup: usize,
+    skipped: usize,
+    times: Vec<f32>,
+    elapsed: f32,
+}
+
+impl FrameTimes {
+    /// Creates an empty collection ignoring the first `warmup` frames, which
+    /// are usually slower while pipelines and buffers are created.
+    pub fn new(warmup: usize) -> Self {
+        FrameTimes {
+            warmup,
+            ..Default::default()
+        }
+    }
+
+    /// Adds the time of a frame, in seconds.
+    pub fn push(
+        &mut self,
+        time: f32,
+    ) {
+        self.elapsed += time;
+        if self.skipped < self.warmup {
+            self.skipped += 1;
+        } else {
+            self.times.push(time);
+        }
+    }
+
+    /// Returns the number of frames collected after the warm-up.
+    pub fn len(&self) -> usize {
+        self.times.len()
+    }
+
+    /// Returns `true` if no frame was collected after the warm-up.
+    pub fn is_empty(&self) -> bool {
+        self.times.is_empty()
+    }
+
+    /// Returns the total time of the frames pushed so far, warm-up included,
+    /// in seconds.
+    pub fn elapsed(&self) -> f32 {
+        self.elapsed
+    }
+
+    /// Summarizes the frame times, or returns `None` if no frame was
+    /// collected after the warm-up.
+    pub fn report(&self) -> Option<Report> {
+        if self.times.is_empty() {
+            return None;
+        }
+        let mut sorted = self.times.clone();
+        sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        let percentile = |p: f32| sorted[(p * (sorted.len() - 1) as f32).round() as usize];
+        let total = sorted.iter().fold(0.0, |sum, time| sum + time);
+        Some(Report {
+            frames: sorted.len(),
+            mean: total / sorted.len() as f32,
+            min: sorted[0],
+            median: percentile(0.5),
+            p95: percentile(0.95),
+            p99: percentile(0.99),
+            max: sorted[sorted.len() - 1],
+        })
+    }
+}
+
+/// Statistics of the frame times collected by
+/// [`FrameTimes`](struct.FrameTimes.html), in seconds.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Report {
+    /// Number of frames.
+    pub frames: usize,
+    /// Average frame time.
+    pub mean: f32,
+    /// Shortest frame time.
+    pub min: f32,
+    /// Median frame time.
+    pub median: f32,
+    /// Frame time exceeded by 5% of the frames.
+    pub p95: f32,
+    /// Frame time exceeded by 1% of the frames.
+    pub p99: f32,
+    /// Longest frame time.
+    pub max: f32,
+}
+
+impl Report {
+    /// Returns the average number of frames per second.
+    pub fn fps(&self) -> f32 {
+        1.0 / self.mean
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter,
+    ) -> fmt::Result {
+        write!(
+            f,
+            "frames={} fps={:.1} mean={:.2}ms min={:.2}ms median={:.2}ms p95={:.2}ms p99={:.2}ms max={:.2}ms",
+            self.frames,
+            self.fps(),
+            1e3 * self.mean,
+            1e3 * self.min,
+            1e3 * self.median,
+            1e3 * self.p95,
+            1e3 * self.p99,
+            1e3 * self.max,
+        )
+    }
+}
@@ -41,7 +41,8 @@
 //! occluded by objects that are closer to the camera.
 //!
 //! [`Orthographic`] projections are created with the
-//! [`Factory::orthographic_camera`] method.
+//! [`Factory::orthographic_camera`] method. Their horizontal extent follows the
+//! aspect ratio of the window.
 //!
 //! ```rust,no_run
 //! # let mut window = three::Window::new("");
@@ -50,10 +51,22 @@
 //! # };
 //! ```
 //!
+//! Orthographic projections with fixed [`OrthographicBounds`] are created with the
+//! [`Factory::orthographic_camera_bounds`] method.
+//!
+//! ```rust,no_run
+//! # let mut window = three::Window::new("");
+//! # let _ = {
+//! window.factory.orthographic_camera_bounds(0.0, 800.0, 0.0, 600.0, -1.0 .. 1.0)
+//! # };
+//! ```
+//!
+//! [`Factory::orthographic_camera_bounds`]: ../factory/struct.Factory.html#method.orthographic_camera_bounds
 //! [`Factory::orthographic_camera`]: ../factory/struct.Factory.html#method.orthographic_camera
 //! [`Factory::perspective_camera`]: ../factory/struct.Factory.html#method.perspective_camera
 //! [`object::Base`]: ../object/struct.Base.html
 //! [`Orthographic`]: struct.Orthographic.html
+//! [`OrthographicBounds`]: struct.OrthographicBounds.html
 //! [`Perspective`]: struct.Perspective.html
 
 use cgmath;
@@ -92,6 +105,8 @@ impl From<ops::RangeFrom<f32>> for ZRange {
 pub enum Projection {
     /// An orthographic projection.
     Orthographic(Orthographic),
+    /// An orthographic projection with fixed bounds.
+    OrthographicBounds(OrthographicBounds),
     /// A perspective projection.
     Perspective(Perspective),
 }
@@ -159,6 +174,24 @@ impl Projection {
         })
     }
 
+    /// Constructs an orthographic projection with fixed bounds, independent of
+    /// the aspect ratio.
+    pub fn orthographic_bounds(
+        left: f32,
+        right: f32,
+        bottom: f32,
+        top: f32,
+        range: ops::Range<f32>,
+    ) -> Self {
+        Projection::OrthographicBounds(OrthographicBounds {
+            left,
+            right,
+            bottom,
+            top,
+            range,
+        })
+    }
+
     /// Constructs a perspective projection.
     pub fn perspective<R>(
         fov_y: f32,
@@ -180,6 +213,7 @@ impl Projection {
     ) -> mint::ColumnMatrix4<f32> {
         match *self {
             Projection::Orthographic(ref x) => x.matrix(aspect_ratio),
+            Projection::OrthographicBounds(ref x) => x.matrix(),
             Projection::Perspective(ref x) => x.matrix(aspect_ratio),
         }
     }
@@ -215,6 +249,39 @@ impl Orthographic {
     }
 }
 
+/// Orthographic projection parameters with fixed bounds.
+///
+/// Unlike [`Orthographic`](struct.Orthographic.html), the bounds don't follow
+/// the aspect ratio of the window, so the image is stretched when it differs
+/// from the aspect ratio of the bounds.
+#[derive(Clone, Debug, PartialEq)]
+pub struct OrthographicBounds {
+    /// The left clipping plane.
+    pub left: f32,
+    /// The right clipping plane.
+    pub right: f32,
+    /// The bottom clipping plane.
+    pub bottom: f32,
+    /// The top clipping plane.
+    pub top: f32,
+    /// Distance to the near and far clipping planes.
+    pub range: ops::Range<f32>,
+}
+
+impl OrthographicBounds {
+    /// Computes the projection matrix representing the camera's projection.
+    pub fn matrix(&self) -> mint::ColumnMatrix4<f32> {
+        cgmath::ortho(
+            self.left,
+            self.right,
+            self.bottom,
+            self.top,
+            self.range.start,
+            self.range.end,
+        ).into()
+    }
+}
+
 /// Perspective projection parameters.
 #[derive(Clone, Debug, PartialEq)]
 pub struct Perspective {
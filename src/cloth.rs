@@ -0,0 +1,412 @@
+//! Position based cloth and soft body simulation for dynamic meshes.
+//!
+//! A [`Cloth`] turns the vertices of a [`Geometry`] into particles kept
+//! together by distance constraints along the triangle edges. Each frame the
+//! simulation is advanced with [`Cloth::step`] and the result is written to a
+//! [`DynamicMesh`] created from the same geometry with
+//! [`Factory::apply_cloth`].
+//!
+//! ```rust,no_run
+//! # let mut window = three::Window::new("");
+//! # let camera = window.factory.perspective_camera(60.0, 0.1 .. 10.0);
+//! use three::cloth::{Cloth, Collider};
+//!
+//! // a jelly ball bouncing on the floor
+//! let geometry = three::Geometry::uv_sphere(0.5, 16, 16);
+//! let mut jelly = Cloth::new(&geometry);
+//! jelly.pressure = 1.0;
+//! jelly.add_collider(Collider::Plane {
+//!     normal: [0.0, 1.0, 0.0].into(),
+//!     distance: -1.0,
+//! });
+//!
+//! let material = three::material::Lambert::default();
+//! let mesh = window.factory.mesh_dynamic(geometry, material);
+//! window.scene.add(&mesh);
+//! while window.update() {
+//!     jelly.step(1.0 / 60.0);
+//!     window.factory.apply_cloth(&mesh, &jelly);
+//!     window.render(&camera);
+//! }
+//! ```
+//!
+//! Cloth such as flags and capes is made of an open surface, with some of its
+//! vertices held in place by [`Cloth::pin`] or [`Cloth::pin_to`].
+//!
+//! The simulation runs in the local space of the mesh: gravity, colliders and
+//! pinned positions are given relative to the mesh, which can be moved and
+//! rotated independently.
+//!
+//! Vertices sharing a position, e.g. along the UV seams of a sphere, are
+//! welded into a single particle so that the surface doesn't tear apart.
+//!
+//! [`Cloth`]: struct.Cloth.html
+//! [`Cloth::pin`]: struct.Cloth.html#method.pin
+//! [`Cloth::pin_to`]: struct.Cloth.html#method.pin_to
+//! [`Cloth::step`]: struct.Cloth.html#method.step
+//! [`DynamicMesh`]: ../struct.DynamicMesh.html
+//! [`Factory::apply_cloth`]: ../struct.Factory.html#method.apply_cloth
+//! [`Geometry`]: ../struct.Geometry.html
+
+use cgmath::{InnerSpace, Vector3, Zero};
+use mint;
+
+use geometry::Geometry;
+
+use std::collections::HashMap;
+
+/// A shape particles are kept out of.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Collider {
+    /// A solid sphere.
+    Sphere {
+        /// Center of the sphere.
+        center: mint::Point3<f32>,
+        /// Radius of the sphere.
+        radius: f32,
+    },
+    /// A half-space bounded by the plane of points `p` where
+    /// `dot(normal, p) == distance`, with the solid side opposite to `normal`.
+    Plane {
+        /// Normal of the plane, pointing out of the solid side.
+        normal: mint::Vector3<f32>,
+        /// Distance of the plane from the origin along `normal`.
+        distance: f32,
+    },
+}
+
+/// A constraint keeping two particles at their rest distance.
+#[derive(Clone, Copy, Debug)]
+struct Link {
+    a: usize,
+    b: usize,
+    rest: f32,
+}
+
+/// Position based dynamics simulation of the vertices of a geometry.
+///
+/// See the [module documentation](index.html) for an example.
+#[derive(Clone, Debug)]
+pub struct Cloth {
+    /// Particle of each vertex of the geometry.
+    particle_of: Vec<usize>,
+    positions: Vec<Vector3<f32>>,
+    previous: Vec<Vector3<f32>>,
+    /// Pinned particles have an inverse mass of zero.
+    inverse_mass: Vec<f32>,
+    stretch: Vec<Link>,
+    bend: Vec<Link>,
+    /// Triangles in particle indices.
+    triangles: Vec<[usize; 3]>,
+    rest_volume: f32,
+    colliders: Vec<Collider>,
+    /// Acceleration applied to all unpinned particles.
+    ///
+    /// Default: `[0.0, -9.81, 0.0]`.
+    pub gravity: mint::Vector3<f32>,
+    /// Fraction of the velocity kept at each step, in `[0, 1]`.
+    ///
+    /// Default: `0.99`.
+    pub damping: f32,
+    /// Number of constraint solver iterations per step. More iterations make
+    /// the surface stiffer at a higher cost.
+    ///
+    /// Default: `8`.
+    pub iterations: usize,
+    /// Stiffness of the edges against stretching, in `[0, 1]`.
+    ///
+    /// Default: `1.0`.
+    pub stiffness: f32,
+    /// Stiffness of neighbouring triangles against folding, in `[0, 1]`.
+    ///
+    /// Default: `0.1`.
+    pub bending: f32,
+    /// Stiffness of the enclosed volume against changes, in `[0, 1]`, for
+    /// soft bodies made of a closed surface. `0.0` disables the constraint.
+    ///
+    /// Default: `0.0`.
+    pub pressure: f32,
+}
+
+impl Cloth {
+    /// Creates a simulation of the vertices of `geometry` at rest in their
+    /// initial positions.
+    pub fn new(geometry: &Geometry) -> Self {
+        let vertices = &geometry.base.vertices;
+
+        // weld vertices sharing a position
+        let mut welded = HashMap::new();
+        let mut positions = Vec::new();
+        let particle_of = vertices
+            .iter()
+            .map(|v| {
+                let key = (v.x.to_bits(), v.y.to_bits(), v.z.to_bits());
+                *welded.entry(key).or_insert_with(|| {
+                    positions.push(Vector3::new(v.x, v.y, v.z));
+                    positions.len() - 1
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let faces = if geometry.faces.is_empty() {
+            (0 .. vertices.len() as u32 / 3)
+                .map(|i| [3 * i, 3 * i + 1, 3 * i + 2])
+                .collect()
+        } else {
+            geometry.faces.clone()
+        };
+        let triangles = faces
+            .iter()
+            .map(|f| [
+                particle_of[f[0] as usize],
+                particle_of[f[1] as usize],
+                particle_of[f[2] as usize],
+            ])
+            .filter(|t| t[0] != t[1] && t[1] != t[2] && t[2] != t[0])
+            .collect::<Vec<_>>();
+
+        // each edge links its end points, and the corners opposite to an
+        // edge shared by two triangles resist bending
+        let mut opposite: HashMap<(usize, usize), usize> = HashMap::new();
+        let mut stretch = Vec::new();
+        let mut bend = Vec::new();
+        for t in &triangles {
+            for k in 0 .. 3 {
+                let (a, b, c) = (t[k], t[(k + 1) % 3], t[(k + 2) % 3]);
+                let edge = if a < b { (a, b) } else { (b, a) };
+                match opposite.get(&edge).cloned() {
+                    Some(other) => {
+                        if other != c {
+                            bend.push(Link {
+                                a: other,
+                                b: c,
+                                rest: (positions[c] - positions[other]).magnitude(),
+                            });
+                        }
+                    }
+                    None => {
+                        opposite.insert(edge, c);
+                        stretch.push(Link {
+                            a: edge.0,
+                            b: edge.1,
+                            rest: (positions[edge.1] - positions[edge.0]).magnitude(),
+                        });
+                    }
+                }
+            }
+        }
+
+        let mut cloth = Cloth {
+            particle_of,
+            previous: positions.clone(),
+            inverse_mass: vec![1.0; positions.len()],
+            positions,
+            stretch,
+            bend,
+            triangles,
+            rest_volume: 0.0,
+            colliders: Vec::new(),
+            gravity: [0.0, -9.81, 0.0].into(),
+            damping: 0.99,
+            iterations: 8,
+            stiffness: 1.0,
+            bending: 0.1,
+            pressure: 0.0,
+        };
+        cloth.rest_volume = cloth.volume();
+        cloth
+    }
+
+    /// Pins the vertex `index` of the geometry at its current position.
+    pub fn pin(
+        &mut self,
+        index: usize,
+    ) {
+        let particle = self.particle_of[index];
+        self.inverse_mass[particle] = 0.0;
+    }
+
+    /// Pins the vertex `index` of the geometry at `position`, e.g. to attach
+    /// a cape to a moving character.
+    pub fn pin_to<P: Into<mint::Point3<f32>>>(
+        &mut self,
+        index: usize,
+        position: P,
+    ) {
+        let particle = self.particle_of[index];
+        let p = position.into();
+        self.inverse_mass[particle] = 0.0;
+        self.positions[particle] = Vector3::new(p.x, p.y, p.z);
+        self.previous[particle] = self.positions[particle];
+    }
+
+    /// Releases the vertex `index` of the geometry.
+    pub fn unpin(
+        &mut self,
+        index: usize,
+    ) {
+        let particle = self.particle_of[index];
+        self.inverse_mass[particle] = 1.0;
+    }
+
+    /// Adds a shape the particles are kept out of.
+    pub fn add_collider(
+        &mut self,
+        collider: Collider,
+    ) {
+        self.colliders.push(collider);
+    }
+
+    /// Removes all the colliders.
+    pub fn clear_colliders(&mut self) {
+        self.colliders.clear();
+    }
+
+    /// Advances the simulation by `dt` seconds.
+    ///
+    /// The simulation is most stable when stepped with a fixed `dt`, e.g.
+    /// several times per frame with a fraction of the frame time.
+    pub fn step(
+        &mut self,
+        dt: f32,
+    ) {
+        let gravity = Vector3::from(self.gravity) * dt * dt;
+        for i in 0 .. self.positions.len() {
+            if self.inverse_mass[i] == 0.0 {
+                continue;
+            }
+            let velocity = (self.positions[i] - self.previous[i]) * self.damping;
+            self.previous[i] = self.positions[i];
+            self.positions[i] += velocity + gravity;
+        }
+
+        for _ in 0 .. self.iterations {
+            let (stiffness, bending) = (self.stiffness, self.bending);
+            for i in 0 .. self.stretch.len() {
+                let link = self.stretch[i];
+                self.solve_link(link, stiffness);
+            }
+            for i in 0 .. self.bend.len() {
+                let link = self.bend[i];
+                self.solve_link(link, bending);
+            }
+            if self.pressure > 0.0 {
+                self.solve_volume();
+            }
+            self.collide();
+        }
+    }
+
+    /// Moves the particles of `link` towards their rest distance.
+    fn solve_link(
+        &mut self,
+        link: Link,
+        stiffness: f32,
+    ) {
+        let (wa, wb) = (self.inverse_mass[link.a], self.inverse_mass[link.b]);
+        if wa + wb == 0.0 {
+            return;
+        }
+        let delta = self.positions[link.b] - self.positions[link.a];
+        let length = delta.magnitude();
+        if length < 1e-6 {
+            return;
+        }
+        let correction = delta * ((length - link.rest) / (length * (wa + wb)) * stiffness);
+        self.positions[link.a] += correction * wa;
+        self.positions[link.b] -= correction * wb;
+    }
+
+    /// Signed volume enclosed by the triangles.
+    fn volume(&self) -> f32 {
+        self.triangles
+            .iter()
+            .map(|t| {
+                let (p0, p1, p2) = (self.positions[t[0]], self.positions[t[1]], self.positions[t[2]]);
+                p0.dot(p1.cross(p2)) / 6.0
+            })
+            .sum()
+    }
+
+    /// Moves the particles towards the rest volume.
+    fn solve_volume(&mut self) {
+        let mut gradients = vec![Vector3::zero(); self.positions.len()];
+        for t in &self.triangles {
+            let (p0, p1, p2) = (self.positions[t[0]], self.positions[t[1]], self.positions[t[2]]);
+            gradients[t[0]] += p1.cross(p2) / 6.0;
+            gradients[t[1]] += p2.cross(p0) / 6.0;
+            gradients[t[2]] += p0.cross(p1) / 6.0;
+        }
+        let weight = gradients
+            .iter()
+            .zip(&self.inverse_mass)
+            .map(|(g, &w)| w * g.magnitude2())
+            .sum::<f32>();
+        if weight < 1e-12 {
+            return;
+        }
+        let lambda = (self.rest_volume - self.volume()) / weight * self.pressure;
+        for (i, g) in gradients.iter().enumerate() {
+            self.positions[i] += *g * (lambda * self.inverse_mass[i]);
+        }
+    }
+
+    /// Pushes the particles out of the colliders.
+    fn collide(&mut self) {
+        for collider in &self.colliders {
+            for (p, &w) in self.positions.iter_mut().zip(&self.inverse_mass) {
+                if w == 0.0 {
+                    continue;
+                }
+                match *collider {
+                    Collider::Sphere { center, radius } => {
+                        let offset = *p - Vector3::new(center.x, center.y, center.z);
+                        let distance = offset.magnitude();
+                        if distance < radius && distance > 1e-6 {
+                            *p += offset * ((radius - distance) / distance);
+                        }
+                    }
+                    Collider::Plane { normal, distance } => {
+                        let normal = Vector3::from(normal).normalize();
+                        let depth = distance - p.dot(normal);
+                        if depth > 0.0 {
+                            *p += normal * depth;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Returns the current position of each vertex of the geometry.
+    pub fn vertices(&self) -> Vec<mint::Point3<f32>> {
+        self.particle_of
+            .iter()
+            .map(|&i| {
+                let p = self.positions[i];
+                [p.x, p.y, p.z].into()
+            })
+            .collect()
+    }
+
+    /// Returns the current smooth normal of each vertex of the geometry.
+    pub fn normals(&self) -> Vec<mint::Vector3<f32>> {
+        let mut normals = vec![Vector3::zero(); self.positions.len()];
+        for t in &self.triangles {
+            let (p0, p1, p2) = (self.positions[t[0]], self.positions[t[1]], self.positions[t[2]]);
+            // area weighted
+            let n = (p1 - p0).cross(p2 - p0);
+            normals[t[0]] += n;
+            normals[t[1]] += n;
+            normals[t[2]] += n;
+        }
+        self.particle_of
+            .iter()
+            .map(|&i| {
+                let n = normals[i];
+                let n = if n.magnitude2() > 0.0 { n.normalize() } else { Vector3::unit_y() };
+                [n.x, n.y, n.z].into()
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,420 @@
+//! Spatial queries against mesh geometry.
+//!
+//! These are simple tests against the triangles of a mesh, suitable for
+//! grounding a character or detecting trigger volumes without a physics engine.
+//! Like [`raycast`](../raycast/index.html), the queries can use either the bind
+//! pose or the animated pose of a mesh. The overlap queries only test the
+//! triangles of a mesh whose bounds in the scene index overlap the volume.
+//!
+//! ```rust,no_run
+//! # let mut window = three::Window::new("");
+//! # let ground = window.factory.mesh(three::Geometry::plane(10.0, 10.0), three::material::Basic::default());
+//! use three::collision::{self, Sphere};
+//! use three::raycast::Pose;
+//!
+//! let feet = Sphere { center: [0.0, 0.2, 0.0].into(), radius: 0.25 };
+//! if collision::overlaps_sphere(&window.scene, &ground, &feet, Pose::Bind) {
+//!     println!("grounded");
+//! }
+//! ```
+
+use cgmath::{EuclideanSpace, InnerSpace, Matrix4, Point3, Transform, Vector3};
+use mint;
+
+use mesh::Mesh;
+use raycast::{self, Pose};
+use scene::Scene;
+use spatial::{Bvh, Candidate};
+
+/// A sphere in world space.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sphere {
+    /// Center of the sphere.
+    pub center: mint::Point3<f32>,
+    /// Radius of the sphere.
+    pub radius: f32,
+}
+
+/// An axis-aligned box in world space.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Aabb {
+    /// Corner with the smallest coordinates.
+    pub min: mint::Point3<f32>,
+    /// Corner with the largest coordinates.
+    pub max: mint::Point3<f32>,
+}
+
+impl Aabb {
+    /// Returns the smallest box containing all `points`, or `None` if there are none.
+    pub(crate) fn from_points<I>(points: I) -> Option<Self>
+    where
+        I: IntoIterator<Item = Point3<f32>>,
+    {
+        let mut points = points.into_iter();
+        let first = match points.next() {
+            Some(point) => point,
+            None => return None,
+        };
+        let (min, max) = points.fold((first, first), |(min, max), p| {
+            (
+                Point3::new(min.x.min(p.x), min.y.min(p.y), min.z.min(p.z)),
+                Point3::new(max.x.max(p.x), max.y.max(p.y), max.z.max(p.z)),
+            )
+        });
+        Some(Aabb {
+            min: min.into(),
+            max: max.into(),
+        })
+    }
+
+    /// Returns the eight corners of the box.
+    pub(crate) fn corners(&self) -> [Point3<f32>; 8] {
+        let (lo, hi) = (self.min, self.max);
+        [
+            Point3::new(lo.x, lo.y, lo.z),
+            Point3::new(hi.x, lo.y, lo.z),
+            Point3::new(lo.x, hi.y, lo.z),
+            Point3::new(hi.x, hi.y, lo.z),
+            Point3::new(lo.x, lo.y, hi.z),
+            Point3::new(hi.x, lo.y, hi.z),
+            Point3::new(lo.x, hi.y, hi.z),
+            Point3::new(hi.x, hi.y, hi.z),
+        ]
+    }
+
+    /// Returns the box containing this one after transformation by `matrix`.
+    pub(crate) fn transformed(
+        &self,
+        matrix: Matrix4<f32>,
+    ) -> Self {
+        let corners = self.corners();
+        Aabb::from_points(corners.iter().map(|&c| matrix.transform_point(c))).unwrap()
+    }
+}
+
+/// The point of a mesh closest to a query point.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Closest {
+    /// Closest point on the mesh surface, in world space.
+    pub point: mint::Point3<f32>,
+    /// Distance between the query point and `point`.
+    pub distance: f32,
+    /// Index of the triangle containing `point`.
+    pub face: usize,
+}
+
+/// Returns the point on the surface of `mesh` closest to `point`.
+///
+/// Returns `None` if the mesh isn't in `scene`, has no triangles or wasn't
+/// created from a [`Geometry`](../struct.Geometry.html).
+pub fn closest_point<P>(
+    scene: &Scene,
+    mesh: &Mesh,
+    point: P,
+    pose: Pose,
+) -> Option<Closest>
+where
+    P: Into<mint::Point3<f32>>,
+{
+    let point = Point3::from(point.into());
+    let triangles = match raycast::world_triangles(scene, mesh, pose) {
+        Some(triangles) => triangles,
+        None => return None,
+    };
+    let mut closest: Option<(Point3<f32>, f32, usize)> = None;
+    for (face, corners) in triangles.iter().enumerate() {
+        let candidate = closest_on_triangle(point, corners);
+        let distance2 = (candidate - point).magnitude2();
+        if closest.map_or(true, |(_, best, _)| distance2 < best) {
+            closest = Some((candidate, distance2, face));
+        }
+    }
+    closest.map(|(candidate, distance2, face)| Closest {
+        point: candidate.into(),
+        distance: distance2.sqrt(),
+        face,
+    })
+}
+
+/// Returns `true` if `sphere` intersects any triangle of `mesh`.
+///
+/// Returns `false` if the mesh isn't in `scene` or wasn't created from a
+/// [`Geometry`](../struct.Geometry.html).
+pub fn overlaps_sphere(
+    scene: &Scene,
+    mesh: &Mesh,
+    sphere: &Sphere,
+    pose: Pose,
+) -> bool {
+    let center = Point3::from(sphere.center);
+    let triangles = candidate_triangles(scene, mesh, pose, |index| index.query_sphere(center, sphere.radius));
+    match triangles {
+        Some(triangles) => triangles_overlap_sphere(&triangles, sphere),
+        None => false,
+    }
+}
+
+/// Returns `true` if `aabb` intersects any triangle of `mesh`.
+///
+/// Returns `false` if the mesh isn't in `scene` or wasn't created from a
+/// [`Geometry`](../struct.Geometry.html).
+pub fn overlaps_aabb(
+    scene: &Scene,
+    mesh: &Mesh,
+    aabb: &Aabb,
+    pose: Pose,
+) -> bool {
+    match candidate_triangles(scene, mesh, pose, |index| index.query_aabb(aabb)) {
+        Some(triangles) => triangles_overlap_aabb(&triangles, aabb),
+        None => false,
+    }
+}
+
+/// Returns the world triangles of `mesh` if it is among the candidates the
+/// broad phase `query` returns from the scene index, or `None` otherwise.
+fn candidate_triangles<F>(
+    scene: &Scene,
+    mesh: &Mesh,
+    pose: Pose,
+    query: F,
+) -> Option<Vec<[Point3<f32>; 3]>>
+where
+    F: FnOnce(&Bvh) -> Vec<Candidate>,
+{
+    let mut hub = scene.hub.lock().unwrap();
+    hub.process_messages();
+    let candidates = {
+        let mut index = scene.index.lock().unwrap();
+        index.refresh(&hub, &scene.first_child);
+        query(&index)
+    };
+    let mx_world = match candidates.into_iter().find(|&(ref node, _)| *node == mesh.object.node) {
+        Some((_, mx_world)) => mx_world,
+        None => return None,
+    };
+    raycast::node_triangles(&hub, &scene.first_child, &mesh.object, mx_world, pose)
+}
+
+/// Returns `true` if `sphere` intersects any of `triangles`.
+fn triangles_overlap_sphere(
+    triangles: &[[Point3<f32>; 3]],
+    sphere: &Sphere,
+) -> bool {
+    let center = Point3::from(sphere.center);
+    let radius2 = sphere.radius * sphere.radius;
+    let bounds = Aabb {
+        min: (center + -Vector3::new(sphere.radius, sphere.radius, sphere.radius)).into(),
+        max: (center + Vector3::new(sphere.radius, sphere.radius, sphere.radius)).into(),
+    };
+    triangles.iter().any(|corners| {
+        overlaps_bounds(&bounds, corners) && (closest_on_triangle(center, corners) - center).magnitude2() <= radius2
+    })
+}
+
+/// Returns `true` if `aabb` intersects any of `triangles`.
+fn triangles_overlap_aabb(
+    triangles: &[[Point3<f32>; 3]],
+    aabb: &Aabb,
+) -> bool {
+    triangles
+        .iter()
+        .any(|corners| overlaps_bounds(aabb, corners) && triangle_overlaps_aabb(aabb, corners))
+}
+
+/// Returns the visible meshes of `scene` whose bounds overlap `aabb`.
+///
+/// This is a broad phase test against the world bounds of the meshes, to pick
+/// the candidates of [`overlaps_aabb`](fn.overlaps_aabb.html). Skinned meshes
+/// and meshes without bounds are always returned.
+pub fn meshes_in_aabb(
+    scene: &Scene,
+    aabb: &Aabb,
+) -> Vec<Mesh> {
+    let mut hub = scene.hub.lock().unwrap();
+    hub.process_messages();
+    let mut index = scene.index.lock().unwrap();
+    index.refresh(&hub, &scene.first_child);
+    index
+        .query_aabb(aabb)
+        .into_iter()
+        .map(|(node, _)| Mesh { object: hub.upgrade_ptr(node) })
+        .collect()
+}
+
+/// Returns the visible meshes of `scene` whose bounds are within `sphere`.
+///
+/// This is a broad phase test against the world bounds of the meshes, to pick
+/// the candidates of [`overlaps_sphere`](fn.overlaps_sphere.html). Skinned
+/// meshes and meshes without bounds are always returned.
+pub fn meshes_in_sphere(
+    scene: &Scene,
+    sphere: &Sphere,
+) -> Vec<Mesh> {
+    let mut hub = scene.hub.lock().unwrap();
+    hub.process_messages();
+    let mut index = scene.index.lock().unwrap();
+    index.refresh(&hub, &scene.first_child);
+    index
+        .query_sphere(Point3::from(sphere.center), sphere.radius)
+        .into_iter()
+        .map(|(node, _)| Mesh { object: hub.upgrade_ptr(node) })
+        .collect()
+}
+
+/// Returns `true` if the bounding box of the triangle overlaps `aabb`.
+fn overlaps_bounds(
+    aabb: &Aabb,
+    corners: &[Point3<f32>; 3],
+) -> bool {
+    let min = |i: usize| corners.iter().map(|c| c[i]).fold(::std::f32::INFINITY, f32::min);
+    let max = |i: usize| corners.iter().map(|c| c[i]).fold(::std::f32::NEG_INFINITY, f32::max);
+    let (lo, hi) = (Point3::from(aabb.min), Point3::from(aabb.max));
+    (0 .. 3).all(|i| min(i) <= hi[i] && max(i) >= lo[i])
+}
+
+/// Separating axis test between a triangle and a box.
+fn triangle_overlaps_aabb(
+    aabb: &Aabb,
+    corners: &[Point3<f32>; 3],
+) -> bool {
+    let (lo, hi) = (Point3::from(aabb.min), Point3::from(aabb.max));
+    let center = lo.midpoint(hi);
+    let extent = (hi - lo) * 0.5;
+    let v = [corners[0] - center, corners[1] - center, corners[2] - center];
+    let edges = [v[1] - v[0], v[2] - v[1], v[0] - v[2]];
+    let axes = [Vector3::unit_x(), Vector3::unit_y(), Vector3::unit_z()];
+
+    let separates = |axis: Vector3<f32>| {
+        if axis.magnitude2() < 1e-12 {
+            return false;
+        }
+        let p = [v[0].dot(axis), v[1].dot(axis), v[2].dot(axis)];
+        let r = extent.x * axis.x.abs() + extent.y * axis.y.abs() + extent.z * axis.z.abs();
+        let min = p[0].min(p[1]).min(p[2]);
+        let max = p[0].max(p[1]).max(p[2]);
+        min > r || max < -r
+    };
+
+    // the box face normals are covered by the bounds test
+    for edge in edges.iter() {
+        for axis in axes.iter() {
+            if separates(axis.cross(*edge)) {
+                return false;
+            }
+        }
+    }
+    !separates(edges[0].cross(edges[1]))
+}
+
+/// Returns the point of the triangle closest to `p`.
+///
+/// See "Real-Time Collision Detection", Christer Ericson, section 5.1.5.
+pub(crate) fn closest_on_triangle(
+    p: Point3<f32>,
+    corners: &[Point3<f32>; 3],
+) -> Point3<f32> {
+    let (a, b, c) = (corners[0], corners[1], corners[2]);
+    let ab = b - a;
+    let ac = c - a;
+    let ap = p - a;
+    let d1 = ab.dot(ap);
+    let d2 = ac.dot(ap);
+    if d1 <= 0.0 && d2 <= 0.0 {
+        return a;
+    }
+    let bp = p - b;
+    let d3 = ab.dot(bp);
+    let d4 = ac.dot(bp);
+    if d3 >= 0.0 && d4 <= d3 {
+        return b;
+    }
+    let vc = d1 * d4 - d3 * d2;
+    if vc <= 0.0 && d1 >= 0.0 && d3 <= 0.0 {
+        return a + ab * (d1 / (d1 - d3));
+    }
+    let cp = p - c;
+    let d5 = ab.dot(cp);
+    let d6 = ac.dot(cp);
+    if d6 >= 0.0 && d5 <= d6 {
+        return c;
+    }
+    let vb = d5 * d2 - d1 * d6;
+    if vb <= 0.0 && d2 >= 0.0 && d6 <= 0.0 {
+        return a + ac * (d2 / (d2 - d6));
+    }
+    let va = d3 * d6 - d5 * d4;
+    if va <= 0.0 && (d4 - d3) >= 0.0 && (d5 - d6) >= 0.0 {
+        return b + (c - b) * ((d4 - d3) / ((d4 - d3) + (d5 - d6)));
+    }
+    let denom = 1.0 / (va + vb + vc);
+    a + ab * (vb * denom) + ac * (vc * denom)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ground() -> Vec<[Point3<f32>; 3]> {
+        vec![
+            [Point3::new(-1.0, 0.0, -1.0), Point3::new(1.0, 0.0, -1.0), Point3::new(1.0, 0.0, 1.0)],
+            [Point3::new(-1.0, 0.0, -1.0), Point3::new(1.0, 0.0, 1.0), Point3::new(-1.0, 0.0, 1.0)],
+        ]
+    }
+
+    #[test]
+    fn sphere_hit() {
+        let sphere = Sphere {
+            center: [0.5, 0.2, 0.5].into(),
+            radius: 0.25,
+        };
+        assert!(triangles_overlap_sphere(&ground(), &sphere));
+    }
+
+    #[test]
+    fn sphere_miss() {
+        let above = Sphere {
+            center: [0.0, 0.3, 0.0].into(),
+            radius: 0.25,
+        };
+        let beside = Sphere {
+            center: [1.2, 0.0, 1.2].into(),
+            radius: 0.25,
+        };
+        assert!(!triangles_overlap_sphere(&ground(), &above));
+        assert!(!triangles_overlap_sphere(&ground(), &beside));
+    }
+
+    #[test]
+    fn aabb_hit() {
+        let aabb = Aabb {
+            min: [-0.1, -0.1, -0.1].into(),
+            max: [0.1, 0.1, 0.1].into(),
+        };
+        assert!(triangles_overlap_aabb(&ground(), &aabb));
+    }
+
+    #[test]
+    fn aabb_miss() {
+        let aabb = Aabb {
+            min: [-0.1, 0.1, -0.1].into(),
+            max: [0.1, 0.3, 0.1].into(),
+        };
+        assert!(!triangles_overlap_aabb(&ground(), &aabb));
+        // inside the bounds of the triangle, but off its diagonal edge
+        let triangle = [[Point3::new(0.0, 0.0, 0.0), Point3::new(1.0, 0.0, 0.0), Point3::new(0.0, 1.0, 0.0)]];
+        let corner = Aabb {
+            min: [0.8, 0.8, -0.1].into(),
+            max: [0.9, 0.9, 0.1].into(),
+        };
+        assert!(!triangles_overlap_aabb(&triangle, &corner));
+    }
+
+    #[test]
+    fn closest_point_on_triangle() {
+        let corners = &ground()[0];
+        let inside = closest_on_triangle(Point3::new(0.5, 1.0, -0.5), corners);
+        assert_eq!(inside, Point3::new(0.5, 0.0, -0.5));
+        let vertex = closest_on_triangle(Point3::new(2.0, 0.0, -2.0), corners);
+        assert_eq!(vertex, Point3::new(1.0, 0.0, -1.0));
+    }
+}
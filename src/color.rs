@@ -1,4 +1,33 @@
 //! sRGB colors.
+//!
+//! Besides the conversions used by the renderer, this module provides helpers
+//! for authoring colors of lights and materials:
+//!
+//! * [`from_hsl`] and [`from_hsv`] build colors from hue, saturation and
+//!   lightness or value, and [`to_hsl`] converts back.
+//! * [`lerp`] blends two colors in linear space.
+//! * [`from_temperature`] gives the color of a black body, e.g. for lamps.
+//! * [`palette`] and [`gradient`] generate sets of related colors.
+//! * [`from_name`] looks up the named CSS colors.
+//!
+//! ```rust
+//! use three::color;
+//!
+//! let orange = color::from_hsl(30.0, 1.0, 0.5);
+//! assert_eq!(color::from_name("orange"), Some(0xFFA500));
+//! let candle = color::from_temperature(1900.0);
+//! let dusk = color::lerp(orange, color::BLUE, 0.5);
+//! # let _ = (candle, dusk);
+//! ```
+//!
+//! [`from_hsl`]: fn.from_hsl.html
+//! [`from_hsv`]: fn.from_hsv.html
+//! [`to_hsl`]: fn.to_hsl.html
+//! [`lerp`]: fn.lerp.html
+//! [`from_temperature`]: fn.from_temperature.html
+//! [`palette`]: fn.palette.html
+//! [`gradient`]: fn.gradient.html
+//! [`from_name`]: fn.from_name.html
 
 /// sRGB color represented by a 4-byte hexadecimal number.
 ///
@@ -55,11 +84,322 @@ pub fn from_linear_rgb(c: [f32; 3]) -> Color {
     let f = |x: f32| -> u32 {
         let y = if x > 0.0031308 {
             let a = 0.055;
-            (1.0 + a) * x.powf(-2.4) - a
+            (1.0 + a) * x.powf(1.0 / 2.4) - a
         } else {
             12.92 * x
         };
-        (y * 255.0).round() as u32
+        (y.max(0.0).min(1.0) * 255.0).round() as u32
     };
     f(c[0]) << 16 | f(c[1]) << 8 | f(c[2])
 }
+
+/// Packs sRGB components in `[0, 1]` into a color, clamping them.
+fn pack(c: [f32; 3]) -> Color {
+    let f = |x: f32| (x.max(0.0).min(1.0) * 255.0).round() as u32;
+    f(c[0]) << 16 | f(c[1]) << 8 | f(c[2])
+}
+
+/// Unpacks a color into sRGB components in `[0, 1]`.
+fn unpack(c: Color) -> [f32; 3] {
+    let f = |x: u32| (x & 0xFF) as f32 / 255.0;
+    [f(c >> 16), f(c >> 8), f(c)]
+}
+
+/// Returns the sRGB components of `hue` in degrees at full saturation, given
+/// the chroma and the minimum component.
+fn hue_to_rgb(
+    hue: f32,
+    chroma: f32,
+    min: f32,
+) -> [f32; 3] {
+    let h = (hue % 360.0 + 360.0) % 360.0 / 60.0;
+    let x = chroma * (1.0 - (h % 2.0 - 1.0).abs());
+    let (r, g, b) = match h as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    [r + min, g + min, b + min]
+}
+
+/// Creates a color from `hue` in degrees, and `saturation` and `lightness`
+/// in `[0, 1]`.
+pub fn from_hsl(
+    hue: f32,
+    saturation: f32,
+    lightness: f32,
+) -> Color {
+    let chroma = (1.0 - (2.0 * lightness - 1.0).abs()) * saturation;
+    pack(hue_to_rgb(hue, chroma, lightness - 0.5 * chroma))
+}
+
+/// Creates a color from `hue` in degrees, and `saturation` and `value` in
+/// `[0, 1]`.
+pub fn from_hsv(
+    hue: f32,
+    saturation: f32,
+    value: f32,
+) -> Color {
+    let chroma = value * saturation;
+    pack(hue_to_rgb(hue, chroma, value - chroma))
+}
+
+/// Returns the hue in degrees, and the saturation and lightness in `[0, 1]`
+/// of `c`.
+pub fn to_hsl(c: Color) -> [f32; 3] {
+    let rgb = unpack(c);
+    let (r, g, b) = (rgb[0], rgb[1], rgb[2]);
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let lightness = 0.5 * (max + min);
+    let chroma = max - min;
+    if chroma == 0.0 {
+        return [0.0, 0.0, lightness];
+    }
+    let saturation = chroma / (1.0 - (2.0 * lightness - 1.0).abs());
+    let hue = if max == r {
+        60.0 * (((g - b) / chroma) % 6.0)
+    } else if max == g {
+        60.0 * ((b - r) / chroma + 2.0)
+    } else {
+        60.0 * ((r - g) / chroma + 4.0)
+    };
+    [(hue + 360.0) % 360.0, saturation, lightness]
+}
+
+/// Blends from `a` at `t = 0` to `b` at `t = 1` in linear space, like the
+/// renderer mixes lights.
+pub fn lerp(
+    a: Color,
+    b: Color,
+    t: f32,
+) -> Color {
+    let (a, b) = (to_linear_rgb(a), to_linear_rgb(b));
+    from_linear_rgb([
+        a[0] + t * (b[0] - a[0]),
+        a[1] + t * (b[1] - a[1]),
+        a[2] + t * (b[2] - a[2]),
+    ])
+}
+
+/// Returns the color of a black body at `kelvin` degrees, from the warm
+/// light of a candle around 1900K to the blue of a clear sky above 10000K.
+///
+/// Daylight is around 6500K. Temperatures are clamped to `[1000, 40000]`.
+/// This is an approximation of the Planckian locus by Tanner Helland.
+pub fn from_temperature(kelvin: f32) -> Color {
+    let t = kelvin.max(1000.0).min(40000.0) / 100.0;
+    let red = if t <= 66.0 {
+        255.0
+    } else {
+        329.698_73 * (t - 60.0).powf(-0.133_204_76)
+    };
+    let green = if t <= 66.0 {
+        99.470_8 * t.ln() - 161.119_57
+    } else {
+        288.122_17 * (t - 60.0).powf(-0.075_514_85)
+    };
+    let blue = if t >= 66.0 {
+        255.0
+    } else if t <= 19.0 {
+        0.0
+    } else {
+        138.517_73 * (t - 10.0).ln() - 305.044_8
+    };
+    pack([red / 255.0, green / 255.0, blue / 255.0])
+}
+
+/// Returns `count` colors with the saturation and lightness of `base` and
+/// hues evenly spread around the color wheel, starting with `base`.
+pub fn palette(
+    base: Color,
+    count: usize,
+) -> Vec<Color> {
+    let hsl = to_hsl(base);
+    let (hue, saturation, lightness) = (hsl[0], hsl[1], hsl[2]);
+    (0 .. count)
+        .map(|i| from_hsl(hue + 360.0 * i as f32 / count as f32, saturation, lightness))
+        .collect()
+}
+
+/// Returns `count` colors blending from `a` to `b` in linear space, both
+/// included.
+pub fn gradient(
+    a: Color,
+    b: Color,
+    count: usize,
+) -> Vec<Color> {
+    match count {
+        0 => Vec::new(),
+        1 => vec![a],
+        _ => (0 .. count)
+            .map(|i| lerp(a, b, i as f32 / (count - 1) as f32))
+            .collect(),
+    }
+}
+
+/// Returns the color of a named CSS color, e.g. `"cornflowerblue"`, ignoring
+/// case.
+pub fn from_name(name: &str) -> Option<Color> {
+    let name = name.to_lowercase();
+    CSS_COLORS
+        .binary_search_by(|&(key, _)| key.cmp(name.as_str()))
+        .ok()
+        .map(|i| CSS_COLORS[i].1)
+}
+
+/// The named CSS colors, sorted by name.
+#[cfg_attr(rustfmt, rustfmt_skip)]
+const CSS_COLORS: [(&'static str, Color); 148] = [
+    ("aliceblue", 0xF0F8FF),
+    ("antiquewhite", 0xFAEBD7),
+    ("aqua", 0x00FFFF),
+    ("aquamarine", 0x7FFFD4),
+    ("azure", 0xF0FFFF),
+    ("beige", 0xF5F5DC),
+    ("bisque", 0xFFE4C4),
+    ("black", 0x000000),
+    ("blanchedalmond", 0xFFEBCD),
+    ("blue", 0x0000FF),
+    ("blueviolet", 0x8A2BE2),
+    ("brown", 0xA52A2A),
+    ("burlywood", 0xDEB887),
+    ("cadetblue", 0x5F9EA0),
+    ("chartreuse", 0x7FFF00),
+    ("chocolate", 0xD2691E),
+    ("coral", 0xFF7F50),
+    ("cornflowerblue", 0x6495ED),
+    ("cornsilk", 0xFFF8DC),
+    ("crimson", 0xDC143C),
+    ("cyan", 0x00FFFF),
+    ("darkblue", 0x00008B),
+    ("darkcyan", 0x008B8B),
+    ("darkgoldenrod", 0xB8860B),
+    ("darkgray", 0xA9A9A9),
+    ("darkgreen", 0x006400),
+    ("darkgrey", 0xA9A9A9),
+    ("darkkhaki", 0xBDB76B),
+    ("darkmagenta", 0x8B008B),
+    ("darkolivegreen", 0x556B2F),
+    ("darkorange", 0xFF8C00),
+    ("darkorchid", 0x9932CC),
+    ("darkred", 0x8B0000),
+    ("darksalmon", 0xE9967A),
+    ("darkseagreen", 0x8FBC8F),
+    ("darkslateblue", 0x483D8B),
+    ("darkslategray", 0x2F4F4F),
+    ("darkslategrey", 0x2F4F4F),
+    ("darkturquoise", 0x00CED1),
+    ("darkviolet", 0x9400D3),
+    ("deeppink", 0xFF1493),
+    ("deepskyblue", 0x00BFFF),
+    ("dimgray", 0x696969),
+    ("dimgrey", 0x696969),
+    ("dodgerblue", 0x1E90FF),
+    ("firebrick", 0xB22222),
+    ("floralwhite", 0xFFFAF0),
+    ("forestgreen", 0x228B22),
+    ("fuchsia", 0xFF00FF),
+    ("gainsboro", 0xDCDCDC),
+    ("ghostwhite", 0xF8F8FF),
+    ("gold", 0xFFD700),
+    ("goldenrod", 0xDAA520),
+    ("gray", 0x808080),
+    ("green", 0x008000),
+    ("greenyellow", 0xADFF2F),
+    ("grey", 0x808080),
+    ("honeydew", 0xF0FFF0),
+    ("hotpink", 0xFF69B4),
+    ("indianred", 0xCD5C5C),
+    ("indigo", 0x4B0082),
+    ("ivory", 0xFFFFF0),
+    ("khaki", 0xF0E68C),
+    ("lavender", 0xE6E6FA),
+    ("lavenderblush", 0xFFF0F5),
+    ("lawngreen", 0x7CFC00),
+    ("lemonchiffon", 0xFFFACD),
+    ("lightblue", 0xADD8E6),
+    ("lightcoral", 0xF08080),
+    ("lightcyan", 0xE0FFFF),
+    ("lightgoldenrodyellow", 0xFAFAD2),
+    ("lightgray", 0xD3D3D3),
+    ("lightgreen", 0x90EE90),
+    ("lightgrey", 0xD3D3D3),
+    ("lightpink", 0xFFB6C1),
+    ("lightsalmon", 0xFFA07A),
+    ("lightseagreen", 0x20B2AA),
+    ("lightskyblue", 0x87CEFA),
+    ("lightslategray", 0x778899),
+    ("lightslategrey", 0x778899),
+    ("lightsteelblue", 0xB0C4DE),
+    ("lightyellow", 0xFFFFE0),
+    ("lime", 0x00FF00),
+    ("limegreen", 0x32CD32),
+    ("linen", 0xFAF0E6),
+    ("magenta", 0xFF00FF),
+    ("maroon", 0x800000),
+    ("mediumaquamarine", 0x66CDAA),
+    ("mediumblue", 0x0000CD),
+    ("mediumorchid", 0xBA55D3),
+    ("mediumpurple", 0x9370DB),
+    ("mediumseagreen", 0x3CB371),
+    ("mediumslateblue", 0x7B68EE),
+    ("mediumspringgreen", 0x00FA9A),
+    ("mediumturquoise", 0x48D1CC),
+    ("mediumvioletred", 0xC71585),
+    ("midnightblue", 0x191970),
+    ("mintcream", 0xF5FFFA),
+    ("mistyrose", 0xFFE4E1),
+    ("moccasin", 0xFFE4B5),
+    ("navajowhite", 0xFFDEAD),
+    ("navy", 0x000080),
+    ("oldlace", 0xFDF5E6),
+    ("olive", 0x808000),
+    ("olivedrab", 0x6B8E23),
+    ("orange", 0xFFA500),
+    ("orangered", 0xFF4500),
+    ("orchid", 0xDA70D6),
+    ("palegoldenrod", 0xEEE8AA),
+    ("palegreen", 0x98FB98),
+    ("paleturquoise", 0xAFEEEE),
+    ("palevioletred", 0xDB7093),
+    ("papayawhip", 0xFFEFD5),
+    ("peachpuff", 0xFFDAB9),
+    ("peru", 0xCD853F),
+    ("pink", 0xFFC0CB),
+    ("plum", 0xDDA0DD),
+    ("powderblue", 0xB0E0E6),
+    ("purple", 0x800080),
+    ("rebeccapurple", 0x663399),
+    ("red", 0xFF0000),
+    ("rosybrown", 0xBC8F8F),
+    ("royalblue", 0x4169E1),
+    ("saddlebrown", 0x8B4513),
+    ("salmon", 0xFA8072),
+    ("sandybrown", 0xF4A460),
+    ("seagreen", 0x2E8B57),
+    ("seashell", 0xFFF5EE),
+    ("sienna", 0xA0522D),
+    ("silver", 0xC0C0C0),
+    ("skyblue", 0x87CEEB),
+    ("slateblue", 0x6A5ACD),
+    ("slategray", 0x708090),
+    ("slategrey", 0x708090),
+    ("snow", 0xFFFAFA),
+    ("springgreen", 0x00FF7F),
+    ("steelblue", 0x4682B4),
+    ("tan", 0xD2B48C),
+    ("teal", 0x008080),
+    ("thistle", 0xD8BFD8),
+    ("tomato", 0xFF6347),
+    ("turquoise", 0x40E0D0),
+    ("violet", 0xEE82EE),
+    ("wheat", 0xF5DEB3),
+    ("white", 0xFFFFFF),
+    ("whitesmoke", 0xF5F5F5),
+    ("yellow", 0xFFFF00),
+    ("yellowgreen", 0x9ACD32),
+];
@@ -18,6 +18,12 @@
 //!  * Uses mouse movement to rotate the object when the right mouse button
 //!    is held down.
 //!
+//! ### Turntable
+//!
+//!  * Spins the object about a fixed axis at a constant speed.
+//!  * Pauses while the user interacts with the window and resumes after
+//!    a period of inactivity.
+//!
 //! [`Object`]: ../object/trait.Object.html
 
 /// First person controls.
@@ -26,13 +32,19 @@ pub mod first_person;
 /// Mouse orbit controls.
 pub mod orbit;
 
+/// Automatic rotation for model viewers.
+pub mod turntable;
+
 #[doc(inline)]
 pub use self::first_person::FirstPerson;
 
 #[doc(inline)]
 pub use self::orbit::Orbit;
 
-pub use input::{axis,
-    Button, Delta, Hit, HitCount, Key, Input, Timer, MouseButton,
+#[doc(inline)]
+pub use self::turntable::Turntable;
+
+pub use input::{axis, record,
+    Button, Delta, Hit, HitCount, Key, Input, Timer, UpdateTimer, MouseButton,
     AXIS_DOWN_UP, AXIS_LEFT_RIGHT, KEY_ESCAPE, KEY_SPACE, MOUSE_LEFT, MOUSE_RIGHT,
 };
@@ -3,6 +3,7 @@ use cgmath::{EuclideanSpace, InnerSpace, Rotation, Rotation3, Transform as Trans
 use mint;
 use object;
 
+use controls::turntable;
 use input::{Button, Input, MOUSE_LEFT};
 use node::TransformInternal;
 use object::Object;
@@ -20,6 +21,9 @@ pub struct Orbit {
     target: Point3<f32>,
     button: Button,
     speed: f32,
+    auto_rotate: Option<f32>,
+    resume_delay: f32,
+    idle_time: f32,
 }
 
 /// Helper struct to construct [`Orbit`](struct.Orbit.html) with desired settings.
@@ -31,6 +35,8 @@ pub struct Builder {
     target: mint::Point3<f32>,
     button: Button,
     speed: f32,
+    auto_rotate: Option<f32>,
+    resume_delay: f32,
 }
 
 impl Builder {
@@ -43,6 +49,8 @@ impl Builder {
             target: [0.0, 0.0, 0.0].into(),
             button: MOUSE_LEFT,
             speed: 1.0,
+            auto_rotate: None,
+            resume_delay: 2.0,
         }
     }
 
@@ -106,6 +114,28 @@ impl Builder {
         self
     }
 
+    /// Setup automatic orbiting around the target while the user is not interacting,
+    /// in radians per second. Default is `None` (no automatic orbiting).
+    pub fn auto_rotate(
+        &mut self,
+        speed: Option<f32>,
+    ) -> &mut Self {
+        self.auto_rotate = speed;
+        self
+    }
+
+    /// Set the time in seconds without user interaction after which automatic
+    /// orbiting resumes.
+    ///
+    /// Defaults to 2.0.
+    pub fn resume_delay(
+        &mut self,
+        delay: f32,
+    ) -> &mut Self {
+        self.resume_delay = delay;
+        self
+    }
+
     /// Finalize builder and create new `OrbitControls`.
     pub fn build(&mut self) -> Orbit {
         let dir = (Point3::from(self.position) - Point3::from(self.target)).normalize();
@@ -126,6 +156,9 @@ impl Builder {
             target: self.target.into(),
             button: self.button,
             speed: self.speed,
+            auto_rotate: self.auto_rotate,
+            resume_delay: self.resume_delay,
+            idle_time: self.resume_delay,
         }
     }
 }
@@ -146,11 +179,20 @@ impl Orbit {
         } else {
             [0.0, 0.0].into()
         };
+        if turntable::is_interacting(input) {
+            self.idle_time = 0.0;
+        } else {
+            self.idle_time += input.delta_time();
+        }
+        let auto_angle = match self.auto_rotate {
+            Some(speed) if self.idle_time >= self.resume_delay => speed * input.delta_time(),
+            _ => 0.0,
+        };
         let pre = Decomposed {
             disp: -self.target.to_vec(),
             ..Decomposed::one()
         };
-        let q_ver = Quaternion::from_angle_y(Rad(self.speed * (mouse_delta.x)));
+        let q_ver = Quaternion::from_angle_y(Rad(self.speed * (mouse_delta.x) + auto_angle));
         let axis = self.transform.rot * Vector3::unit_x();
         let q_hor = Quaternion::from_axis_angle(axis, Rad(self.speed * (mouse_delta.y)));
         let post = Decomposed {
@@ -163,6 +205,23 @@ impl Orbit {
         self.object.set_transform(pf, self.transform.rot, 1.0);
     }
 
+    /// Sets the automatic orbiting speed in radians per second, or `None` to disable it.
+    pub fn set_auto_rotate(
+        &mut self,
+        speed: Option<f32>,
+    ) {
+        self.auto_rotate = speed;
+    }
+
+    /// Sets the time in seconds without user interaction after which automatic
+    /// orbiting resumes.
+    pub fn set_resume_delay(
+        &mut self,
+        delay: f32,
+    ) {
+        self.resume_delay = delay;
+    }
+
     /// Reset the current position and orientation of the controlled object to their initial values.
     pub fn reset(&mut self) {
         self.transform = self.initial_transform;
@@ -0,0 +1,175 @@
+use cgmath::{InnerSpace, Quaternion, Rad, Rotation3, Vector3};
+use mint;
+use object;
+
+use input::{Input, MOUSE_LEFT, MOUSE_RIGHT};
+use object::Object;
+
+/// Automatically spins an object about a fixed axis, as found in product and
+/// model viewers.
+///
+/// Rotation pauses as soon as the user interacts with the window (mouse
+/// buttons, mouse wheel, or keyboard) and resumes after a configurable
+/// period of inactivity.
+#[derive(Clone, Debug)]
+pub struct Turntable {
+    object: object::Base,
+    orientation: Quaternion<f32>,
+    axis: Vector3<f32>,
+    speed: f32,
+    angle: f32,
+    resume_delay: f32,
+    idle_time: f32,
+    paused: bool,
+}
+
+/// Helper struct to construct [`Turntable`](struct.Turntable.html) with desired settings.
+#[derive(Clone, Debug)]
+pub struct Builder {
+    object: object::Base,
+    orientation: mint::Quaternion<f32>,
+    axis: mint::Vector3<f32>,
+    speed: f32,
+    resume_delay: f32,
+}
+
+impl Builder {
+    /// Create new `Builder` with default values.
+    pub fn new<T: Object>(object: &T) -> Self {
+        Builder {
+            object: object.upcast(),
+            orientation: [0.0, 0.0, 0.0, 1.0].into(),
+            axis: [0.0, 1.0, 0.0].into(),
+            speed: 0.5,
+            resume_delay: 2.0,
+        }
+    }
+
+    /// Set the initial orientation of the object, which the rotation is applied on top of.
+    ///
+    /// Defaults to no rotation.
+    pub fn orientation<Q>(
+        &mut self,
+        orientation: Q,
+    ) -> &mut Self
+    where
+        Q: Into<mint::Quaternion<f32>>,
+    {
+        self.orientation = orientation.into();
+        self
+    }
+
+    /// Set the axis of rotation.
+    ///
+    /// Defaults to the unit y axis.
+    pub fn axis<V>(
+        &mut self,
+        axis: V,
+    ) -> &mut Self
+    where
+        V: Into<mint::Vector3<f32>>,
+    {
+        self.axis = axis.into();
+        self
+    }
+
+    /// Set the rotation speed in radians per second.
+    ///
+    /// Defaults to 0.5.
+    pub fn speed(
+        &mut self,
+        speed: f32,
+    ) -> &mut Self {
+        self.speed = speed;
+        self
+    }
+
+    /// Set the time in seconds without user interaction after which rotation resumes.
+    ///
+    /// Defaults to 2.0.
+    pub fn resume_delay(
+        &mut self,
+        delay: f32,
+    ) -> &mut Self {
+        self.resume_delay = delay;
+        self
+    }
+
+    /// Finalize builder and create new `Turntable`.
+    pub fn build(&mut self) -> Turntable {
+        Turntable {
+            object: self.object.clone(),
+            orientation: self.orientation.into(),
+            axis: Vector3::from(self.axis).normalize(),
+            speed: self.speed,
+            angle: 0.0,
+            resume_delay: self.resume_delay,
+            idle_time: self.resume_delay,
+            paused: false,
+        }
+    }
+}
+
+impl Turntable {
+    /// Create new `Builder` with default values.
+    pub fn builder<T: Object>(object: &T) -> Builder {
+        Builder::new(object)
+    }
+
+    /// Create `Turntable` with default parameters.
+    pub fn default<T: Object>(object: &T) -> Self {
+        Self::builder(object).build()
+    }
+
+    /// Sets the rotation speed in radians per second.
+    pub fn set_speed(
+        &mut self,
+        speed: f32,
+    ) -> &mut Self {
+        self.speed = speed;
+        self
+    }
+
+    /// Stops the rotation until [`resume`](#method.resume) is called.
+    pub fn pause(&mut self) -> &mut Self {
+        self.paused = true;
+        self
+    }
+
+    /// Resumes rotation after a call to [`pause`](#method.pause).
+    pub fn resume(&mut self) -> &mut Self {
+        self.paused = false;
+        self
+    }
+
+    /// Returns `true` if the object is currently being rotated.
+    pub fn is_rotating(&self) -> bool {
+        !self.paused && self.idle_time >= self.resume_delay
+    }
+
+    /// Update the orientation of the controlled object according to the last frame input.
+    pub fn update(
+        &mut self,
+        input: &Input,
+    ) {
+        let dt = input.delta_time();
+        if is_interacting(input) {
+            self.idle_time = 0.0;
+        } else {
+            self.idle_time += dt;
+        }
+
+        if !self.is_rotating() {
+            return;
+        }
+
+        self.angle += self.speed * dt;
+        let rotation = Quaternion::from_axis_angle(self.axis, Rad(self.angle));
+        self.object.set_orientation(rotation * self.orientation);
+    }
+}
+
+/// Returns `true` if the user interacted with the window during the last frame.
+pub(crate) fn is_interacting(input: &Input) -> bool {
+    input.hit(MOUSE_LEFT) || input.hit(MOUSE_RIGHT) || input.mouse_wheel() != 0.0 || !input.keys_hit().is_empty()
+}
@@ -11,7 +11,7 @@ use geometry;
 use gltf;
 use material;
 use mint;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use camera::{Orthographic, Perspective, Projection};
 use std::path::Path;
@@ -21,6 +21,7 @@ use geometry::{Geometry, Shape};
 use image::{DynamicImage, ImageBuffer};
 use node::Transform;
 use super::Factory;
+use texture::ColorSpace;
 use template::{
     AnimationTemplate,
     BoneTemplate,
@@ -31,12 +32,48 @@ use template::{
     Template,
 };
 
+/// Textures uploaded for each glTF texture index and the color space it is sampled in.
+type Textures = HashMap<(usize, ColorSpace), Texture<[f32; 4]>>;
+
+/// Returns the color spaces each texture is sampled in by the materials.
+///
+/// Base color and emissive maps hold sRGB colors, while normal,
+/// metallic-roughness and occlusion maps hold linear data.
+fn texture_color_spaces(document: &gltf::Document) -> HashMap<usize, HashSet<ColorSpace>> {
+    let mut spaces = HashMap::new();
+    {
+        let mut add = |index: usize, space: ColorSpace| {
+            spaces.entry(index).or_insert_with(HashSet::new).insert(space);
+        };
+        for mat in document.materials() {
+            let pbr = mat.pbr_metallic_roughness();
+            if let Some(t) = pbr.base_color_texture() {
+                add(t.as_ref().index(), ColorSpace::Srgb);
+            }
+            if let Some(t) = mat.emissive_texture() {
+                add(t.as_ref().index(), ColorSpace::Srgb);
+            }
+            if let Some(t) = pbr.metallic_roughness_texture() {
+                add(t.as_ref().index(), ColorSpace::Linear);
+            }
+            if let Some(t) = mat.normal_texture() {
+                add(t.as_ref().index(), ColorSpace::Linear);
+            }
+            if let Some(t) = mat.occlusion_texture() {
+                add(t.as_ref().index(), ColorSpace::Linear);
+            }
+        }
+    }
+    spaces
+}
+
 fn load_textures(
     factory: &mut Factory,
     document: &gltf::Document,
     images: Vec<gltf::image::Data>,
-) -> Vec<Texture<[f32; 4]>> {
-    let mut textures = Vec::new();
+) -> Textures {
+    let color_spaces = texture_color_spaces(document);
+    let mut textures = HashMap::new();
     for (texture, data) in document.textures().zip(images.into_iter()) {
         let (width, height) = (data.width, data.height);
         let image = match data.format {
@@ -89,35 +126,49 @@ fn load_textures(
             WrappingMode::Repeat => WrapMode::Tile,
         };
         let sampler = factory.sampler(mag_filter, wrap_s, wrap_t);
-        let texture = factory.load_texture_from_memory(width as u16, height as u16, &image, sampler);
-        textures.push(texture);
+        let index = texture.index();
+        // textures not referenced by any material are loaded as colors
+        let spaces = color_spaces
+            .get(&index)
+            .cloned()
+            .unwrap_or_else(|| Some(ColorSpace::Srgb).into_iter().collect());
+        for space in spaces {
+            let texture = factory.load_texture_from_memory_with_color_space(
+                width as u16,
+                height as u16,
+                &image,
+                sampler.clone(),
+                space,
+            );
+            textures.insert((index, space), texture);
+        }
     }
     textures
 }
 
 fn load_material<'a>(
     mat: gltf::Material<'a>,
-    textures: &[Texture<[f32; 4]>],
+    textures: &Textures,
 ) -> Material {
     let pbr = mat.pbr_metallic_roughness();
     let mut is_basic_material = true;
     let base_color_map = pbr.base_color_texture()
-        .map(|t| textures[t.as_ref().index()].clone());
+        .map(|t| textures[&(t.as_ref().index(), ColorSpace::Srgb)].clone());
     let normal_map = mat.normal_texture().map(|t| {
         is_basic_material = false;
-        textures[t.as_ref().index()].clone()
+        textures[&(t.as_ref().index(), ColorSpace::Linear)].clone()
     });
     let emissive_map = mat.emissive_texture().map(|t| {
         is_basic_material = false;
-        textures[t.as_ref().index()].clone()
+        textures[&(t.as_ref().index(), ColorSpace::Srgb)].clone()
     });
     let metallic_roughness_map = pbr.metallic_roughness_texture().map(|t| {
         is_basic_material = false;
-        textures[t.as_ref().index()].clone()
+        textures[&(t.as_ref().index(), ColorSpace::Linear)].clone()
     });
     let occlusion_map = mat.occlusion_texture().map(|t| {
         is_basic_material = false;
-        textures[t.as_ref().index()].clone()
+        textures[&(t.as_ref().index(), ColorSpace::Linear)].clone()
     });
     let (base_color_factor, base_color_alpha) = {
         let x = pbr.base_color_factor();
@@ -128,6 +179,7 @@ fn load_material<'a>(
         material::Basic {
             color: base_color_factor,
             map: base_color_map,
+            opacity: base_color_alpha,
         }.into()
     } else {
         material::Pbr {
@@ -151,11 +203,43 @@ fn load_material<'a>(
     }
 }
 
+/// Geometry uploaded so far, used to share GPU buffers between identical primitives.
+#[derive(Default)]
+struct GeometryCache {
+    /// Uploaded geometries, grouped by content hash.
+    entries: HashMap<u64, Vec<(Geometry, InstancedGeometry)>>,
+    /// Number of primitives loaded.
+    total: usize,
+    /// Number of primitives that reused previously uploaded geometry.
+    reused: usize,
+}
+
+impl GeometryCache {
+    fn upload(
+        &mut self,
+        factory: &mut Factory,
+        geometry: Geometry,
+    ) -> InstancedGeometry {
+        self.total += 1;
+        let entries = self.entries
+            .entry(geometry.content_hash())
+            .or_insert_with(Vec::new);
+        if let Some(&(_, ref instanced)) = entries.iter().find(|&&(ref g, _)| *g == geometry) {
+            self.reused += 1;
+            return instanced.clone();
+        }
+        let instanced = factory.upload_geometry(geometry.clone());
+        entries.push((geometry, instanced.clone()));
+        instanced
+    }
+}
+
 fn load_primitive<'a>(
     factory: &mut Factory,
     primitive: gltf::Primitive<'a>,
     buffers: &[gltf::buffer::Data],
-    textures: &[Texture<[f32; 4]>],
+    textures: &Textures,
+    geometry_cache: &mut GeometryCache,
 ) -> (InstancedGeometry, Material) {
     use itertools::Itertools;
 
@@ -222,6 +306,7 @@ fn load_primitive<'a>(
             tangents,
         },
         tex_coords,
+        colors: Vec::new(),
         faces,
         shapes,
         joints: geometry::Joints {
@@ -230,7 +315,7 @@ fn load_primitive<'a>(
         },
     };
 
-    let geometry = factory.upload_geometry(geometry);
+    let geometry = geometry_cache.upload(factory, geometry);
     let material = load_material(primitive.material(), textures);
     (geometry, material)
 }
@@ -315,9 +400,15 @@ fn load_animation<'a>(
         let interpolation = match sampler.interpolation() {
             Linear => animation::Interpolation::Linear,
             Step => animation::Interpolation::Discrete,
-            CubicSpline => animation::Interpolation::Cubic,
+            CubicSpline => animation::Interpolation::CubicSpline,
             CatmullRomSpline => animation::Interpolation::Cubic,
         };
+        // cubic splines store an in-tangent, a value and an out-tangent per
+        // keyframe, kept as they are
+        let values_per_key = match sampler.interpolation() {
+            CubicSpline => 3,
+            _ => 1,
+        };
         use animation::{Binding, Track, Values};
         let reader = channel.reader(|buffer| Some(&buffers[buffer.index()].0));
         let times: Vec<f32> = reader.read_inputs().unwrap().collect();
@@ -326,7 +417,7 @@ fn load_animation<'a>(
                 let values = iter
                     .map(|v| mint::Vector3::from(v))
                     .collect::<Vec<_>>();
-                assert_eq!(values.len(), times.len());
+                assert_eq!(values.len(), times.len() * values_per_key);
                 (Binding::Position, Values::Vector3(values))
             }
             gltf::animation::util::ReadOutputs::Rotations(rotations) => {
@@ -334,14 +425,14 @@ fn load_animation<'a>(
                     .into_f32()
                     .map(|r| mint::Quaternion::from(r))
                     .collect::<Vec<_>>();
-                assert_eq!(values.len(), times.len());
+                assert_eq!(values.len(), times.len() * values_per_key);
                 (Binding::Orientation, Values::Quaternion(values))
             }
             gltf::animation::util::ReadOutputs::Scales(iter) => {
                 // TODO: Groups do not handle non-uniform scaling, so for now
                 // we'll choose Y to be the scale factor in all directions.
                 let values = iter.map(|s| s[1]).collect::<Vec<_>>();
-                assert_eq!(values.len(), times.len());
+                assert_eq!(values.len(), times.len() * values_per_key);
                 (Binding::Scale, Values::Scalar(values))
             }
             gltf::animation::util::ReadOutputs::MorphTargetWeights(weights) => {
@@ -354,11 +445,12 @@ fn load_animation<'a>(
                     .unwrap()
                     .morph_targets()
                     .len();
-                let mut values = vec![0.0; times.len() * num_targets];
+                let num_values = times.len() * values_per_key;
+                let mut values = vec![0.0; num_values * num_targets];
                 let raw = weights.into_f32().collect::<Vec<_>>();
                 for (i, chunk) in raw.chunks(num_targets).enumerate() {
                     for (j, value) in chunk.iter().enumerate() {
-                        values[j * times.len() + i] = *value;
+                        values[j * num_values + i] = *value;
                     }
                 }
                 (Binding::Weights, Values::Scalar(values))
@@ -563,6 +655,7 @@ impl super::Factory {
         // meshes, and populate `mesh_map` with information on how to lookup meshes in the
         // flattened list given the index in the original glTF document.
         let mut primitives = Vec::new();
+        let mut geometry_cache = GeometryCache::default();
         for gltf_mesh in gltf.meshes() {
             // Save the index within the glTF document so that we can add an entry to the mesh map.
             let gltf_index = gltf_mesh.index();
@@ -572,7 +665,7 @@ impl super::Factory {
             let mut indices = Vec::new();
             let prim_iter = gltf_mesh
                 .primitives()
-                .map(|prim| load_primitive(self, prim, &buffers, &textures));
+                .map(|prim| load_primitive(self, prim, &buffers, &textures, &mut geometry_cache));
             for primitive in prim_iter {
                 indices.push(primitives.len());
                 primitives.push(primitive);
@@ -582,6 +675,20 @@ impl super::Factory {
             mesh_map.insert(gltf_index, indices);
         }
 
+        // Identical materials compare equal, so meshes sharing geometry and material are
+        // rendered as instances of each other.
+        let unique_materials = primitives
+            .iter()
+            .map(|&(_, ref material)| material)
+            .collect::<HashSet<_>>()
+            .len();
+        info!(
+            "\t{} primitives: {} reused uploaded geometry, {} unique materials",
+            geometry_cache.total,
+            geometry_cache.reused,
+            unique_materials,
+        );
+
         // The full list of template nodes created from the glTF file. We know there will be at
         // least as many template nodes as nodes in the original glTF file, but there will likely
         // be many since many things in the glTF format end up as their own template nodes.
@@ -7,8 +7,10 @@ use std::collections::HashSet;
 use std::collections::hash_map::{Entry, HashMap};
 use std::io::Read;
 use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
 
-use cgmath::{Vector3};
+use cgmath::{Deg, InnerSpace, Point3, Quaternion, Rotation3, Vector3};
 use gfx;
 use gfx::format::I8Norm;
 use gfx::traits::{Factory as Factory_, FactoryExt};
@@ -19,35 +21,52 @@ use mint;
 use obj;
 
 use animation;
+use annotation::{self, Anchor, Annotation, TextBillboard};
+#[cfg(feature = "audio")]
 use audio;
+use bake;
 use camera::{Camera, Projection, ZRange};
-use color::{BLACK, Color};
+use cloth::Cloth;
+use collision::Aabb;
+use color::{BLACK, WHITE, Color};
 use geometry::Geometry;
+use helper::{self, LightHelper, LightHelpers};
 use hub::{Hub, HubPtr, LightData, SubLight, SubNode};
 use light::{Ambient, Directional, Hemisphere, Point, ShadowMap};
 use material::{self, Material};
-use mesh::{DynamicMesh, Mesh};
+use measure::{self, Dimension};
+use mesh::{DynamicMesh, Fade, Mesh};
 use object::{self, Group, Object};
-use render::{basic_pipe,
-    BackendFactory, BackendResources, BasicPipelineState, DisplacementContribution,
-    DynamicData, GpuData, Instance, InstanceCacheKey, PipelineCreationError, ShadowFormat, Source, Vertex,
+use particles::{Emitter, ParticleSystem};
+use probe::{ProbeData, ReflectionProbe};
+use render::{basic_pipe, generator_pipe, post_pipe,
+    BackendFactory, BackendResources, BasicPipelineState, ColorFormat, DepthFormat, DisplacementContribution,
+    DynamicData, GeneratorFormat, GeneratorPipelineState, GpuData, Instance, InstanceCacheKey,
+    PipelineCreationError, PostPipelineState, ShadowFormat, Source, TextureUpload, Vertex,
     DEFAULT_VERTEX, VECS_PER_BONE, ZEROED_DISPLACEMENT_CONTRIBUTION,
 };
-use scene::{Background, Scene};
+use render::generator::{self, VertexJob, VertexTarget};
+use scene::{Background, Environment, Scene};
 use sprite::Sprite;
-use skeleton::{Bone, InverseBindMatrix, Skeleton};
+use skeleton::{self, Bone, InverseBindMatrix, Skeleton, SkinningError};
+use spatial::Bvh;
 use template::{
     InstancedGeometry,
     LightTemplate,
     SubLightTemplate,
     Template,
 };
-use text::{Font, Text, TextData};
-use texture::{CubeMap, CubeMapPath, FilterMethod, Sampler, Texture, WrapMode};
+use text::{Align, Font, Layout, Text, TextData};
+use texture::{ColorSpace, CubeMap, CubeMapPath, FilterMethod, RenderTarget, Sampler, Texture, WrapMode};
+use virtual_texture::{self, TileSource, VirtualTexture};
 
 const TANGENT_X: [I8Norm; 4] = [I8Norm(1), I8Norm(0), I8Norm(0), I8Norm(1)];
 const NORMAL_Z: [I8Norm; 4] = [I8Norm(0), I8Norm(0), I8Norm(1), I8Norm(0)];
 
+/// Slope of the surface for a height difference of the full range between
+/// neighbouring texels of a bump map.
+const BUMP_STRENGTH: f32 = 4.0;
+
 const QUAD: [Vertex; 4] = [
     Vertex {
         pos: [-1.0, -1.0, 0.0, 1.0],
@@ -79,8 +98,14 @@ pub struct Factory {
     pub(crate) backend: BackendFactory,
     hub: HubPtr,
     quad_buf: gfx::handle::Buffer<BackendResources, Vertex>,
-    texture_cache: HashMap<PathBuf, Texture<[f32; 4]>>,
+    texture_cache: HashMap<(PathBuf, ColorSpace), Texture<[f32; 4]>>,
+    /// Normal maps converted from the height maps at the given paths.
+    bump_cache: HashMap<PathBuf, Texture<[f32; 4]>>,
     default_sampler: gfx::handle::Sampler<BackendResources>,
+    /// Feedback id of the last virtual texture created.
+    virtual_texture_id: u8,
+    /// Whether new meshes keep a copy of their geometry for CPU-side queries.
+    keep_geometry: bool,
 }
 
 fn f2i(x: f32) -> I8Norm {
@@ -163,9 +188,30 @@ impl Factory {
             pending: None,
             instance_cache_key: None,
             displacement_contributions,
+            render_state: Default::default(),
+            light_mask: !0,
+            fade: Fade::default(),
+            bounds: Self::geometry_bounds(&geometry),
+            geometry: if self.keep_geometry {
+                Some(Arc::new(geometry))
+            } else {
+                None
+            },
         }
     }
 
+    /// Bounds of the base shape, extended by each morph target at full weight.
+    fn geometry_bounds(geometry: &Geometry) -> Option<Aabb> {
+        let base = &geometry.base.vertices;
+        let mut points = base.iter().map(|&v| Point3::from(v)).collect::<Vec<_>>();
+        for shape in &geometry.shapes {
+            for (&v, &d) in base.iter().zip(&shape.vertices) {
+                points.push(Point3::new(v.x + d.x, v.y + d.y, v.z + d.z));
+            }
+        }
+        Aabb::from_points(points)
+    }
+
     pub(crate) fn new(mut backend: BackendFactory) -> Self {
         let quad_buf = backend.create_vertex_buffer(&QUAD);
         let default_sampler = backend.create_sampler_linear();
@@ -174,10 +220,28 @@ impl Factory {
             hub: Hub::new(),
             quad_buf,
             texture_cache: HashMap::new(),
+            bump_cache: HashMap::new(),
             default_sampler: default_sampler,
+            virtual_texture_id: 0,
+            keep_geometry: true,
         }
     }
 
+    /// Sets whether the meshes created from now on keep a CPU-side copy of
+    /// their geometry. Enabled by default.
+    ///
+    /// The copy is needed by [`Mesh::sampled_vertices`], raycasting, collision
+    /// and baking queries, which skip the meshes without one. Disabling it saves
+    /// the memory of the vertices for meshes that are only rendered.
+    ///
+    /// [`Mesh::sampled_vertices`]: struct.Mesh.html#method.sampled_vertices
+    pub fn keep_geometry(
+        &mut self,
+        keep: bool,
+    ) {
+        self.keep_geometry = keep;
+    }
+
     /// Create new empty [`Scene`](struct.Scene.html).
     pub fn scene(&mut self) -> Scene {
         let hub = self.hub.clone();
@@ -186,6 +250,10 @@ impl Factory {
             hub,
             first_child: None,
             background,
+            environment: Environment::default(),
+            section: None,
+            portals: None,
+            index: Mutex::new(Bvh::default()),
         }
     }
 
@@ -398,7 +466,13 @@ impl Factory {
         let gpu_buffer_view = self.backend
             .view_buffer_as_shader_resource(&gpu_buffer)
             .expect("create shader resource view for GPU target buffer");
-        let data = hub::SkeletonData { bones, gpu_buffer, gpu_buffer_view };
+        let data = hub::SkeletonData {
+            bones,
+            gpu_buffer,
+            gpu_buffer_view,
+            lod: Default::default(),
+            last_update: None,
+        };
         let object = self.hub.lock().unwrap().spawn_skeleton(data);
         Skeleton { object }
     }
@@ -432,6 +506,27 @@ impl Factory {
         )
     }
 
+    /// Create new [Orthographic] Camera with fixed bounds.
+    ///
+    /// Unlike [`orthographic_camera`](#method.orthographic_camera), the horizontal
+    /// extent doesn't follow the aspect ratio of the window, e.g. to map the view
+    /// to pixel coordinates for 2D overlays.
+    ///
+    /// [Orthographic]: https://en.wikipedia.org/wiki/Orthographic_projection
+    pub fn orthographic_camera_bounds(
+        &mut self,
+        left: f32,
+        right: f32,
+        bottom: f32,
+        top: f32,
+        range: ops::Range<f32>,
+    ) -> Camera {
+        Camera::new(
+            &mut *self.hub.lock().unwrap(),
+            Projection::orthographic_bounds(left, right, bottom, top, range),
+        )
+    }
+
     /// Create new [Perspective] Camera.
     ///
     /// It's used to render 3D.
@@ -487,13 +582,18 @@ impl Factory {
         } else {
             Either::Right(geometry.tex_coords.iter().map(|uv| [uv.x, uv.y]))
         };
-        let tangent_iter = if geometry.base.tangents.is_empty() {
-            // TODO: Generate tangents if texture coordinates are provided.
-            // (Use mikktspace algorithm or otherwise.)
+        let generated_tangents;
+        let tangents = if geometry.base.tangents.is_empty() {
+            generated_tangents = geometry.generate_tangents();
+            &generated_tangents
+        } else {
+            &geometry.base.tangents
+        };
+        let tangent_iter = if tangents.is_empty() {
             Either::Left(iter::repeat(TANGENT_X))
         } else {
             Either::Right(
-                geometry.base.tangents
+                tangents
                     .iter()
                     .map(|t| [f2i(t.x), f2i(t.y), f2i(t.z), f2i(t.w)]),
             )
@@ -508,6 +608,11 @@ impl Factory {
         } else {
             Either::Right(geometry.joints.weights.iter().cloned())
         };
+        let color_iter = if geometry.colors.is_empty() {
+            Either::Left(iter::repeat([1.0, 1.0, 1.0, 1.0]))
+        } else {
+            Either::Right(geometry.colors.iter().cloned())
+        };
 
         izip!(
             position_iter,
@@ -516,8 +621,9 @@ impl Factory {
             uv_iter,
             joint_indices_iter,
             joint_weights_iter,
+            color_iter,
         )
-            .map(|(pos, normal, tangent, uv, joint_indices, joint_weights)| {
+            .map(|(pos, normal, tangent, uv, joint_indices, joint_weights, color)| {
                 Vertex {
                     pos: [pos.x, pos.y, pos.z, 1.0],
                     normal,
@@ -525,6 +631,7 @@ impl Factory {
                     tangent,
                     joint_indices,
                     joint_weights,
+                    color,
                 }
             })
             .collect()
@@ -556,6 +663,7 @@ impl Factory {
     /// let material = three::material::Basic {
     ///     color: 0xFFFF00,
     ///     map: None,
+    ///     opacity: 1.0,
     /// };
     /// let first = window.factory.create_instanced_mesh(&upload_geometry, material.clone());
     /// let second = window.factory.create_instanced_mesh(&upload_geometry, material.clone());
@@ -588,6 +696,37 @@ impl Factory {
         }
     }
 
+    /// Create a new `Mesh` rendering each vertex of `geometry` as a point,
+    /// e.g. for particles or point clouds.
+    ///
+    /// The faces of `geometry` are ignored. Vertex colors, if any, are
+    /// multiplied with the color of the material.
+    ///
+    /// ```rust,no_run
+    /// # let mut window = three::Window::new("");
+    /// let vertices = (0 .. 1000)
+    ///     .map(|i| {
+    ///         let t = i as f32 * 0.1;
+    ///         [t.cos() * t * 0.1, t.sin() * t * 0.1, 0.0].into()
+    ///     })
+    ///     .collect();
+    /// let material = three::material::Points {
+    ///     color: 0xFFD700,
+    ///     size: 0.05,
+    ///     ..Default::default()
+    /// };
+    /// let spiral = window.factory.points(three::Geometry::with_vertices(vertices), material);
+    /// window.scene.add(&spiral);
+    /// ```
+    pub fn points(
+        &mut self,
+        mut geometry: Geometry,
+        material: material::Points,
+    ) -> Mesh {
+        geometry.faces.clear();
+        self.mesh(geometry, material)
+    }
+
     /// Creates a [`Mesh`] using geometry that has already been loaded to the GPU.
     ///
     /// See the module documentation in [`template`] for information on mesh instancing and
@@ -614,6 +753,7 @@ impl Factory {
     /// let material = three::material::Basic {
     ///     color: 0xFFFF00,
     ///     map: None,
+    ///     opacity: 1.0,
     /// };
     /// let first = window.factory.create_instanced_mesh(&upload_geometry, material.clone());
     /// let second = window.factory.create_instanced_mesh(&upload_geometry, material.clone());
@@ -635,6 +775,7 @@ impl Factory {
         gpu_data.instance_cache_key = Some(InstanceCacheKey {
             geometry: gpu_data.vertices.clone(),
             material: material.clone(),
+            state: gpu_data.render_state,
         });
 
         Mesh {
@@ -690,6 +831,11 @@ impl Factory {
                     pending: None,
                     instance_cache_key: None,
                     displacement_contributions: ZEROED_DISPLACEMENT_CONTRIBUTION.to_vec(),
+                    render_state: Default::default(),
+                    light_mask: !0,
+                    fade: Fade::default(),
+                    geometry: None,
+                    bounds: None,
                 },
                 None,
             ),
@@ -716,6 +862,7 @@ impl Factory {
                     instances,
                     instance_cache_key: Some(InstanceCacheKey {
                         material: mat.clone(),
+                        state: gpu.render_state,
                         geometry: gpu.vertices.clone(),
                     }),
                     ..gpu.clone()
@@ -743,6 +890,7 @@ impl Factory {
                 instances,
                 instance_cache_key: Some(InstanceCacheKey {
                     material: material.clone(),
+                    state: gpu.render_state,
                     geometry: gpu.vertices.clone(),
                 }),
                 ..gpu.clone()
@@ -754,6 +902,60 @@ impl Factory {
         }
     }
 
+    /// Bakes the lighting of `mesh` in `scene` into vertex colors and creates
+    /// an unlit copy of it, drawn with a [`Basic`] material.
+    ///
+    /// The copy takes the color, texture and opacity of the source material and
+    /// the local transform of `mesh`, so it can replace `mesh` in its parent. See
+    /// [`bake`] for details and the baking options.
+    ///
+    /// Returns `None` if the mesh isn't in `scene` or wasn't created from a
+    /// [`Geometry`].
+    ///
+    /// [`Basic`]: material/struct.Basic.html
+    /// [`bake`]: bake/index.html
+    /// [`Geometry`]: struct.Geometry.html
+    pub fn bake_mesh(
+        &mut self,
+        scene: &Scene,
+        mesh: &Mesh,
+        options: &bake::Options,
+    ) -> Option<Mesh> {
+        let geometry = match bake::bake_vertex_colors(scene, mesh, options) {
+            Some(geometry) => geometry,
+            None => return None,
+        };
+        let gpu_data = self.create_gpu_data(geometry);
+        let mut hub = self.hub.lock().unwrap();
+        let (material, transform, nonuniform_scale) = {
+            let node = &hub[mesh];
+            let material = match node.sub_node {
+                SubNode::Visual(Material::Basic(ref params), _, _) => params.clone(),
+                SubNode::Visual(Material::Lambert(ref params), _, _) => material::Basic {
+                    color: params.color,
+                    opacity: params.opacity,
+                    .. material::Basic::default()
+                },
+                SubNode::Visual(Material::Phong(ref params), _, _) => material::Basic {
+                    color: params.color,
+                    map: params.map.clone(),
+                    opacity: params.opacity,
+                },
+                SubNode::Visual(Material::Pbr(ref params), _, _) => material::Basic {
+                    color: params.base_color_factor,
+                    map: params.base_color_map.clone(),
+                    opacity: params.base_color_alpha,
+                },
+                _ => material::Basic::default(),
+            };
+            (material, node.transform, node.nonuniform_scale)
+        };
+        let object = hub.spawn_visual(material.into(), gpu_data, None);
+        hub[&object].transform = transform;
+        hub[&object].nonuniform_scale = nonuniform_scale;
+        Some(Mesh { object })
+    }
+
     /// Create new sprite from `Material`.
     pub fn sprite(
         &mut self,
@@ -773,6 +975,11 @@ impl Factory {
                 pending: None,
                 instance_cache_key: None,
                 displacement_contributions: ZEROED_DISPLACEMENT_CONTRIBUTION.to_vec(),
+                render_state: Default::default(),
+                light_mask: !0,
+                fade: Fade::default(),
+                geometry: None,
+                bounds: None,
             },
             None,
         ))
@@ -792,6 +999,7 @@ impl Factory {
                     instances,
                     instance_cache_key: Some(InstanceCacheKey {
                         material: mat.clone(),
+                        state: gpu.render_state,
                         geometry: self.quad_buf.clone(),
                     }),
                     ..gpu.clone()
@@ -813,6 +1021,7 @@ impl Factory {
             intensity,
             sub_light: SubLight::Ambient,
             shadow: None,
+            layers: !0,
         }))
     }
 
@@ -827,6 +1036,7 @@ impl Factory {
             intensity,
             sub_light: SubLight::Directional,
             shadow: None,
+            layers: !0,
         }))
     }
 
@@ -844,6 +1054,7 @@ impl Factory {
                 ground: ground_color,
             },
             shadow: None,
+            layers: !0,
         }))
     }
 
@@ -858,9 +1069,73 @@ impl Factory {
             intensity,
             sub_light: SubLight::Point,
             shadow: None,
+            layers: !0,
         }))
     }
 
+    /// Create a [`ReflectionProbe`](probe/struct.ReflectionProbe.html) with a
+    /// volume of half size `extent` along its local axes, capturing cube map
+    /// faces of `resolution` pixels.
+    ///
+    /// The probe has no effect until captured with
+    /// [`Renderer::capture_probe`](struct.Renderer.html#method.capture_probe).
+    pub fn reflection_probe<V: Into<mint::Vector3<f32>>>(
+        &mut self,
+        extent: V,
+        resolution: u16,
+    ) -> ReflectionProbe {
+        let data = ProbeData::new(extent.into(), resolution);
+        ReflectionProbe::new(self.hub.lock().unwrap().spawn(SubNode::Probe(data)))
+    }
+
+    /// Create an empty [`LightHelpers`](helper/struct.LightHelpers.html) group.
+    pub fn light_helpers(&mut self) -> LightHelpers {
+        LightHelpers::new(self.group())
+    }
+
+    /// Create a [`LightHelper`](helper/struct.LightHelper.html) visualizing `light`
+    /// with lines in the color of the light.
+    ///
+    /// Directional and hemisphere lights are shown as an arrow of length `size`
+    /// pointing in the direction the light travels. Point lights are shown as three
+    /// circles of radius `size`, marking the range of interest around the light.
+    /// Ambient lights have no position and produce an empty helper.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `light` is not a light source.
+    pub fn light_helper<T: Object>(
+        &mut self,
+        light: &T,
+        size: f32,
+    ) -> LightHelper {
+        let (color, sub_light) = match self.hub.lock().unwrap()[light].sub_node {
+            SubNode::Light(ref data) => (data.color, data.sub_light.clone()),
+            ref sub_node => panic!("Light helper requested for a non-light: {:?}", sub_node),
+        };
+        let material = material::Line {
+            color,
+            ..Default::default()
+        };
+        let shapes = match sub_light {
+            SubLight::Ambient => Vec::new(),
+            SubLight::Directional | SubLight::Hemisphere { .. } => {
+                let geometry = Geometry::with_vertices(helper::arrow(size));
+                vec![self.mesh(geometry, material)]
+            }
+            SubLight::Point => {
+                let geometry = Geometry::with_vertices(helper::circle(size));
+                let xy = self.mesh(geometry, material);
+                let yz = self.mesh_instance(&xy);
+                yz.set_orientation(Quaternion::from_angle_y(Deg(90.0)));
+                let zx = self.mesh_instance(&xy);
+                zx.set_orientation(Quaternion::from_angle_x(Deg(90.0)));
+                vec![xy, yz, zx]
+            }
+        };
+        LightHelper::new(self.group(), light.upcast(), shapes)
+    }
+
     /// Create a `Sampler` with default properties.
     ///
     /// The default sampler has `Clamp` as its horizontal and vertical
@@ -913,9 +1188,39 @@ impl Factory {
         depth_state: gfx::state::Depth,
         stencil_state: gfx::state::Stencil,
     ) -> Result<BasicPipelineState, PipelineCreationError> {
-        use gfx::traits::FactoryExt;
         let vs = Source::user(&dir, name, "vs")?;
         let ps = Source::user(&dir, name, "ps")?;
+        self.basic_pipeline_from_source(
+            &vs,
+            &ps,
+            primitive,
+            rasterizer,
+            color_mask,
+            blend_state,
+            depth_state,
+            stencil_state,
+        )
+    }
+
+    /// Create a basic mesh pipeline from shader code already in memory.
+    ///
+    /// This skips reading and preprocessing shader files, which is useful for
+    /// shipping shaders embedded in the executable. The shaders are still
+    /// compiled and linked when the pipeline is created: the OpenGL backend
+    /// only accepts GLSL, and supports neither SPIR-V nor program binaries,
+    /// so this doesn't reduce the startup time spent in the driver.
+    pub fn basic_pipeline_from_source(
+        &mut self,
+        vs: &Source,
+        ps: &Source,
+        primitive: gfx::Primitive,
+        rasterizer: gfx::state::Rasterizer,
+        color_mask: gfx::state::ColorMask,
+        blend_state: gfx::state::Blend,
+        depth_state: gfx::state::Depth,
+        stencil_state: gfx::state::Stencil,
+    ) -> Result<BasicPipelineState, PipelineCreationError> {
+        use gfx::traits::FactoryExt;
         let shaders = self.backend
             .create_shader_set(vs.0.as_bytes(), ps.0.as_bytes())?;
         let init = basic_pipe::Init {
@@ -928,6 +1233,197 @@ impl Factory {
         Ok(pso)
     }
 
+    /// Create a post-processing pipeline using a custom shader, to be drawn by
+    /// a [`PostEffect`] with [`PostContext::draw`].
+    ///
+    /// The vertex shader usually consists of `#include <fullscreen>`, which
+    /// covers the screen and outputs the texture co-ordinates `v_TexCoord`. The
+    /// pixel shader can `#include <post>` to declare the input texture
+    /// `t_Input`, the second input `t_Extra` of passes combining two images,
+    /// the size of the target in pixels and of a texel in `u_Resolution`, and
+    /// the parameters passed to `draw` in `u_Params`.
+    ///
+    /// [`PostEffect`]: render/post/trait.PostEffect.html
+    /// [`PostContext::draw`]: render/post/struct.PostContext.html#method.draw
+    pub fn post_pipeline<P: AsRef<Path>>(
+        &mut self,
+        dir: P,
+        name: &str,
+    ) -> Result<PostPipelineState, PipelineCreationError> {
+        let vs = Source::user(&dir, name, "vs")?;
+        let ps = Source::user(&dir, name, "ps")?;
+        self.post_pipeline_from_source(&vs, &ps)
+    }
+
+    /// Create a post-processing pipeline from shader code already in memory.
+    ///
+    /// See [`post_pipeline`](#method.post_pipeline) for the shader interface.
+    pub fn post_pipeline_from_source(
+        &mut self,
+        vs: &Source,
+        ps: &Source,
+    ) -> Result<PostPipelineState, PipelineCreationError> {
+        use gfx::traits::FactoryExt;
+        let shaders = self.backend
+            .create_shader_set(vs.0.as_bytes(), ps.0.as_bytes())?;
+        let pso = self.backend.create_pipeline_state(
+            &shaders,
+            gfx::Primitive::TriangleStrip,
+            gfx::state::Rasterizer::new_fill(),
+            post_pipe::new(),
+        )?;
+        Ok(pso)
+    }
+
+    /// Create a pipeline generating vertices on the GPU, to be run with
+    /// [`generate_vertices`](#method.generate_vertices).
+    ///
+    /// `code` is GLSL defining `GeneratedVertex generate(int index)`, which
+    /// returns the vertex at `index`. It's appended to a pixel shader that
+    /// declares the `GeneratedVertex` structure, the `generated_vertex()`
+    /// function returning a vertex with the default attributes, the number of
+    /// vertices in `u_Size.x`, the parameters passed to `generate_vertices`
+    /// in `u_Params` and the optional input texture `t_Input`. See the
+    /// [`generator`](render/generator/index.html) module for an example.
+    pub fn mesh_generator(
+        &mut self,
+        code: &str,
+    ) -> Result<GeneratorPipelineState, PipelineCreationError> {
+        use gfx::traits::FactoryExt;
+        let vs = Source::default("generator", "vs")?;
+        let ps = Source::default("generator", "ps")?;
+        let ps = Source::new(format!("{}\n{}", ps.0, code));
+        let shaders = self.backend
+            .create_shader_set(vs.0.as_bytes(), ps.0.as_bytes())?;
+        let pso = self.backend.create_pipeline_state(
+            &shaders,
+            gfx::Primitive::TriangleStrip,
+            gfx::state::Rasterizer::new_fill(),
+            generator_pipe::new(),
+        )?;
+        Ok(pso)
+    }
+
+    /// Create a [`VertexTarget`] holding `count` vertices generated on the GPU.
+    ///
+    /// The vertices are zeroed until the first call to
+    /// [`generate_vertices`](#method.generate_vertices).
+    ///
+    /// [`VertexTarget`]: render/generator/struct.VertexTarget.html
+    pub fn vertex_target(
+        &mut self,
+        count: usize,
+    ) -> VertexTarget {
+        use gfx::format::{ChannelType, R32_G32_B32_A32};
+        use gfx::texture as t;
+
+        let columns = cmp::max(cmp::min(count, generator::VERTICES_PER_ROW), 1);
+        let rows = cmp::max((count + columns - 1) / columns, 1);
+        let width = columns * generator::TEXELS_PER_VERTEX;
+        let texture = self.backend
+            .create_texture::<R32_G32_B32_A32>(
+                t::Kind::D2(width as t::Size, rows as t::Size, t::AaMode::Single),
+                1,
+                gfx::memory::Bind::RENDER_TARGET | gfx::memory::Bind::TRANSFER_SRC,
+                gfx::memory::Usage::Data,
+                Some(ChannelType::Uint),
+            )
+            .expect("Can't create vertex target texture");
+        let view = self.backend
+            .view_texture_as_render_target::<GeneratorFormat>(&texture, 0, None)
+            .unwrap();
+        let vertices = self.backend
+            .create_buffer::<Vertex>(
+                columns * rows,
+                gfx::buffer::Role::Vertex,
+                gfx::memory::Usage::Data,
+                gfx::memory::Bind::TRANSFER_DST,
+            )
+            .unwrap();
+        let params = self.backend.create_constant_buffer(1);
+        VertexTarget {
+            vertices,
+            texture,
+            view,
+            params,
+            count,
+        }
+    }
+
+    /// Create a new `Mesh` drawing the vertices of `target` with `material`.
+    ///
+    /// `faces` index the vertices of `target`; if empty, the vertices are
+    /// drawn in order, e.g. as a triangle list or as points. The mesh has no
+    /// bounds, so it's never culled.
+    pub fn generated_mesh<M: Into<Material>>(
+        &mut self,
+        target: &VertexTarget,
+        faces: &[[u32; 3]],
+        material: M,
+    ) -> Mesh {
+        let slice = if faces.is_empty() {
+            gfx::Slice {
+                start: 0,
+                end: target.count as u32,
+                base_vertex: 0,
+                instances: Some((1, 0)),
+                buffer: gfx::IndexBuffer::Auto,
+            }
+        } else {
+            let data: &[u32] = gfx::memory::cast_slice(faces);
+            gfx::Slice {
+                start: 0,
+                end: data.len() as u32,
+                base_vertex: 0,
+                instances: Some((1, 0)),
+                buffer: self.backend.create_index_buffer(data),
+            }
+        };
+        let instances = self.create_instance_buffer();
+        Mesh {
+            object: self.hub.lock().unwrap().spawn_visual(
+                material.into(),
+                GpuData {
+                    slice,
+                    vertices: target.vertices.clone(),
+                    instances,
+                    displacements: None,
+                    pending: None,
+                    instance_cache_key: None,
+                    displacement_contributions: ZEROED_DISPLACEMENT_CONTRIBUTION.to_vec(),
+                    render_state: Default::default(),
+                    light_mask: !0,
+                    fade: Fade::default(),
+                    geometry: None,
+                    bounds: None,
+                },
+                None,
+            ),
+        }
+    }
+
+    /// Fill the vertices of `target` with `pipeline`, created by
+    /// [`mesh_generator`](#method.mesh_generator).
+    ///
+    /// `params` are passed to the shader as `u_Params`, and `input` as
+    /// `t_Input`. The pass runs on the next render, before the scene is
+    /// drawn, so that every mesh drawing `target` shows the new vertices.
+    pub fn generate_vertices(
+        &mut self,
+        target: &VertexTarget,
+        pipeline: &GeneratorPipelineState,
+        params: [f32; 4],
+        input: Option<&Texture<[f32; 4]>>,
+    ) {
+        let job = VertexJob {
+            target: target.clone(),
+            pipeline: pipeline.clone(),
+            params,
+            input: input.cloned(),
+        };
+        self.hub.lock().unwrap().vertex_jobs.push(job);
+    }
+
     /// Create new UI (on-screen) text. See [`Text`](struct.Text.html) for default settings.
     pub fn ui_text<S: Into<String>>(
         &mut self,
@@ -939,7 +1435,99 @@ impl Factory {
         Text::with_object(object)
     }
 
+    /// Create an [`Annotation`](annotation/struct.Annotation.html) displaying `text`
+    /// at `anchor`, using `font`.
+    pub fn annotation<S: Into<String>>(
+        &mut self,
+        anchor: Anchor,
+        text: S,
+        font: &Font,
+    ) -> Annotation {
+        let geometry = Geometry::with_vertices(annotation::unit_leader());
+        let leader = self.mesh(geometry, material::Line {
+            color: WHITE,
+            ..Default::default()
+        });
+        let group = self.group();
+        let label = self.ui_text(font, text);
+        Annotation::new(group, leader, label, anchor)
+    }
+
+    /// Create a [`TextBillboard`](annotation/struct.TextBillboard.html) displaying
+    /// `text` centered at `anchor`, using `font`.
+    pub fn text_billboard<S: Into<String>>(
+        &mut self,
+        anchor: Anchor,
+        text: S,
+        font: &Font,
+    ) -> TextBillboard {
+        let mut label = self.ui_text(font, text);
+        label.set_layout(Layout::SingleLine(Align::Center));
+        TextBillboard::new(label, anchor)
+    }
+
+    /// Create a [`Dimension`](measure/struct.Dimension.html) measuring the distance
+    /// between `start` and `end`, labelled using `font`.
+    pub fn distance_dimension<P, Q>(
+        &mut self,
+        start: P,
+        end: Q,
+        font: &Font,
+    ) -> Dimension
+    where
+        P: Into<mint::Point3<f32>>,
+        Q: Into<mint::Point3<f32>>,
+    {
+        let points = vec![Point3::from(start.into()), Point3::from(end.into())];
+        self.dimension(measure::Kind::Distance, points, 1, font)
+    }
+
+    /// Create a [`Dimension`](measure/struct.Dimension.html) measuring the angle at
+    /// `vertex` between the directions to `a` and `b`, labelled using `font`.
+    pub fn angle_dimension<P, Q, R>(
+        &mut self,
+        a: P,
+        vertex: Q,
+        b: R,
+        font: &Font,
+    ) -> Dimension
+    where
+        P: Into<mint::Point3<f32>>,
+        Q: Into<mint::Point3<f32>>,
+        R: Into<mint::Point3<f32>>,
+    {
+        let points = vec![
+            Point3::from(a.into()),
+            Point3::from(vertex.into()),
+            Point3::from(b.into()),
+        ];
+        self.dimension(measure::Kind::Angle, points, 2, font)
+    }
+
+    fn dimension(
+        &mut self,
+        kind: measure::Kind,
+        points: Vec<Point3<f32>>,
+        num_lines: usize,
+        font: &Font,
+    ) -> Dimension {
+        let geometry = Geometry::with_vertices(measure::unit_line());
+        let first = self.mesh(geometry, material::Line {
+            color: WHITE,
+            ..Default::default()
+        });
+        let mut lines = vec![first];
+        for _ in 1 .. num_lines {
+            let line = self.mesh_instance(&lines[0]);
+            lines.push(line);
+        }
+        let group = self.group();
+        let label = self.ui_text(font, "");
+        Dimension::new(group, lines, label, kind, points)
+    }
+
     /// Create new audio source.
+    #[cfg(feature = "audio")]
     pub fn audio_source(&mut self) -> audio::Source {
         let sub = SubNode::Audio(audio::AudioData::new());
         let object = self.hub.lock().unwrap().spawn(sub);
@@ -984,6 +1572,338 @@ impl Factory {
         }
     }
 
+    /// Skins a `DynamicMesh` on the CPU with the current pose of `skeleton` in `scene`.
+    ///
+    /// This is a fallback for GPU skinning with [`Mesh::set_skeleton`], for
+    /// platforms without buffer textures or when the skinned vertices are needed
+    /// on the CPU anyway. The mesh geometry must carry joint indices and weights,
+    /// and the mesh should share the world transform of the skeleton.
+    ///
+    /// Call this each frame after updating the bones; the result is uploaded
+    /// on the next render.
+    ///
+    /// Returns an error, leaving the mesh as it is, if a vertex uses a joint
+    /// the skeleton doesn't have or lacks joint data.
+    ///
+    /// [`Mesh::set_skeleton`]: struct.Mesh.html#method.set_skeleton
+    pub fn skin(
+        &mut self,
+        mesh: &DynamicMesh,
+        skeleton: &Skeleton,
+        scene: &Scene,
+    ) -> Result<(), SkinningError> {
+        let shape = {
+            let mut hub = self.hub.lock().unwrap();
+            hub.process_messages();
+            let matrices = hub.joint_matrices(&scene.first_child, &skeleton.object.node);
+            let shape = skeleton::skin_shape(&mesh.geometry.base, &mesh.geometry.joints, &matrices)?;
+            hub.update_mesh(mesh);
+            shape
+        };
+        let mut mapping = self.backend.write_mapping(&mesh.dynamic.buffer).unwrap();
+        for (i, pos) in shape.vertices.iter().enumerate() {
+            let normal = match shape.normals.get(i) {
+                Some(n) => [f2i(n.x), f2i(n.y), f2i(n.z), I8Norm(0)],
+                None => mapping[i].normal,
+            };
+            mapping[i] = Vertex {
+                pos: [pos.x, pos.y, pos.z, 1.0],
+                normal,
+                .. mapping[i]
+            };
+        }
+        Ok(())
+    }
+
+    /// Writes the current vertices and normals of `cloth` to `mesh`, which
+    /// must be created from the geometry the simulation was created with.
+    ///
+    /// Call this each frame after [`Cloth::step`]; the result is uploaded on
+    /// the next render.
+    ///
+    /// [`Cloth::step`]: cloth/struct.Cloth.html#method.step
+    pub fn apply_cloth(
+        &mut self,
+        mesh: &DynamicMesh,
+        cloth: &Cloth,
+    ) {
+        self.hub.lock().unwrap().update_mesh(mesh);
+        let mut mapping = self.backend.write_mapping(&mesh.dynamic.buffer).unwrap();
+        let vertices = cloth.vertices();
+        let normals = cloth.normals();
+        for (i, (pos, n)) in vertices.iter().zip(&normals).enumerate().take(mapping.len()) {
+            mapping[i] = Vertex {
+                pos: [pos.x, pos.y, pos.z, 1.0],
+                normal: [f2i(n.x), f2i(n.y), f2i(n.z), I8Norm(0)],
+                .. mapping[i]
+            };
+        }
+    }
+
+    /// Create a new [`ParticleSystem`] spawning particles as described by
+    /// `emitter`. See the [`particles`] module for an example.
+    ///
+    /// The vertex buffer holds `emitter.max_particles` particles.
+    ///
+    /// [`ParticleSystem`]: particles/struct.ParticleSystem.html
+    /// [`particles`]: particles/index.html
+    pub fn particle_system(
+        &mut self,
+        emitter: Emitter,
+    ) -> ParticleSystem {
+        // dead particles are left with a zero size, skipped by the shader
+        let capacity = cmp::max(emitter.max_particles, 1);
+        let data = vec![
+            Vertex {
+                pos: [0.0; 4],
+                .. DEFAULT_VERTEX
+            };
+            capacity
+        ];
+        let vertices = self.backend
+            .create_buffer_immutable(&data, gfx::buffer::Role::Vertex, gfx::memory::Bind::TRANSFER_DST)
+            .unwrap();
+        let upload_buf = self.backend.create_upload_buffer(capacity).unwrap();
+        {
+            self.backend
+                .write_mapping(&upload_buf)
+                .unwrap()
+                .copy_from_slice(&data);
+        }
+        let slice = gfx::Slice {
+            start: 0,
+            end: capacity as u32,
+            base_vertex: 0,
+            instances: Some((1, 0)),
+            buffer: gfx::IndexBuffer::Auto,
+        };
+        let material = material::Points {
+            color: WHITE,
+            size: 1.0,
+            map: emitter.map.clone(),
+        };
+        let render_state = material::State {
+            blend: emitter.blend,
+            depth_write: false,
+            ..Default::default()
+        };
+        let instances = self.create_instance_buffer();
+        let object = self.hub.lock().unwrap().spawn_visual(
+            material.into(),
+            GpuData {
+                slice,
+                vertices,
+                instances,
+                displacements: None,
+                pending: None,
+                instance_cache_key: None,
+                displacement_contributions: ZEROED_DISPLACEMENT_CONTRIBUTION.to_vec(),
+                render_state,
+                light_mask: !0,
+                fade: Fade::default(),
+                geometry: None,
+                bounds: None,
+            },
+            None,
+        );
+        let dynamic = DynamicData {
+            num_vertices: capacity,
+            buffer: upload_buf,
+        };
+        ParticleSystem::new(object, dynamic, emitter)
+    }
+
+    /// Writes the live particles of `system` to its vertex buffer.
+    ///
+    /// Call this each frame after [`ParticleSystem::step`]; the result is
+    /// uploaded on the next render.
+    ///
+    /// [`ParticleSystem::step`]: particles/struct.ParticleSystem.html#method.step
+    pub fn apply_particles(
+        &mut self,
+        system: &ParticleSystem,
+    ) {
+        {
+            let mut hub = self.hub.lock().unwrap();
+            if let SubNode::Visual(_, ref mut gpu_data, _) = hub[system].sub_node {
+                gpu_data.pending = Some(system.dynamic.clone());
+            }
+        }
+        let mut mapping = self.backend.write_mapping(&system.dynamic.buffer).unwrap();
+        let sprites = system.sprites();
+        for (i, vertex) in mapping.iter_mut().enumerate() {
+            *vertex = match sprites.get(i) {
+                Some(sprite) => Vertex {
+                    pos: [sprite.position.x, sprite.position.y, sprite.position.z, sprite.size],
+                    color: sprite.color,
+                    .. DEFAULT_VERTEX
+                },
+                None => Vertex {
+                    pos: [0.0; 4],
+                    .. DEFAULT_VERTEX
+                },
+            };
+        }
+    }
+
+    /// Creates a [`VirtualTexture`] with a cache of `pages` by `pages` tiles.
+    ///
+    /// The cache texture is `pages * layout.tile_size` texels wide and high,
+    /// and must fit within the maximum texture size of the driver. At most
+    /// 256 pages fit along each side.
+    ///
+    /// The feedback pass tells 255 virtual textures apart. Further textures
+    /// reuse the ids of the first ones, and the tiles fed back for an id go
+    /// to whichever of its textures is updated first.
+    ///
+    /// [`VirtualTexture`]: virtual_texture/struct.VirtualTexture.html
+    pub fn virtual_texture(
+        &mut self,
+        layout: virtual_texture::Layout,
+        pages: u32,
+    ) -> VirtualTexture {
+        use gfx::format::{ChannelType, Rgba8, Srgba8, Swizzle, R8_G8_B8_A8};
+        use gfx::texture as t;
+
+        let pages = cmp::max(cmp::min(pages, virtual_texture::MAX_PAGES), 1);
+        let bind = gfx::memory::Bind::SHADER_RESOURCE | gfx::memory::Bind::TRANSFER_DST;
+        let cache_size = pages * layout.tile_size;
+        let cache = self.backend
+            .create_texture::<R8_G8_B8_A8>(
+                t::Kind::D2(cache_size as t::Size, cache_size as t::Size, t::AaMode::Single),
+                1,
+                bind,
+                gfx::memory::Usage::Data,
+                Some(ChannelType::Srgb),
+            )
+            .expect("Can't create virtual texture cache");
+        let cache_view = self.backend
+            .view_texture_as_shader_resource::<Srgba8>(&cache, (0, 0), Swizzle::new())
+            .unwrap();
+        let levels = layout.levels();
+        let table_size = layout.table_size();
+        let indirection = self.backend
+            .create_texture::<R8_G8_B8_A8>(
+                t::Kind::D2(table_size as t::Size, table_size as t::Size, t::AaMode::Single),
+                levels as t::Level,
+                bind,
+                gfx::memory::Usage::Data,
+                Some(ChannelType::Unorm),
+            )
+            .expect("Can't create virtual texture indirection table");
+        let indirection_view = self.backend
+            .view_texture_as_shader_resource::<Rgba8>(&indirection, (0, levels as t::Level - 1), Swizzle::new())
+            .unwrap();
+        // `0` marks the feedback pixels without a virtual texture
+        self.virtual_texture_id = self.virtual_texture_id % 255 + 1;
+        let map = virtual_texture::Map {
+            cache: Texture::new(cache_view, self.default_sampler.clone(), [cache_size; 2], ColorSpace::Srgb),
+            indirection: Texture::new(indirection_view, self.default_sampler.clone(), [table_size; 2], ColorSpace::Linear),
+            layout,
+            id: self.virtual_texture_id,
+        };
+        VirtualTexture::new(pages, cache, indirection, map)
+    }
+
+    /// Loads the most needed tiles of `texture` missing from its cache from
+    /// `source`, up to the limit set with
+    /// [`VirtualTexture::set_loads_per_update`], and returns how many were
+    /// loaded.
+    ///
+    /// Call this each frame, after requesting any tiles needed ahead of the
+    /// view; the tiles read back from the feedback pass are requested first.
+    /// The loaded tiles are uploaded on the next render. Tiles not requested
+    /// since the previous update may be evicted to make room.
+    ///
+    /// [`VirtualTexture::set_loads_per_update`]: virtual_texture/struct.VirtualTexture.html#method.set_loads_per_update
+    pub fn update_virtual_texture<S: TileSource>(
+        &mut self,
+        texture: &mut VirtualTexture,
+        source: &mut S,
+    ) -> usize {
+        use gfx::format::{Formatted, Rgba8, Srgba8};
+        use gfx::memory::Typed;
+        use gfx::texture as t;
+
+        let feedback = self.hub.lock().unwrap().virtual_feedback.remove(&texture.id());
+        for tile in feedback.into_iter().flat_map(|tiles| tiles) {
+            texture.request(tile);
+        }
+
+        let tile_size = texture.layout().tile_size;
+        let texels = (tile_size * tile_size) as usize;
+        let mut loaded = Vec::new();
+        for (tile, position) in texture.schedule() {
+            match source.load(tile) {
+                Some(ref data) if data.len() != texels => {
+                    error!("Tile {:?} has {} texels instead of {}", tile, data.len(), texels);
+                    texture.release(position);
+                }
+                Some(data) => {
+                    texture.insert(tile, position);
+                    loaded.push((position, data));
+                }
+                None => texture.release(position),
+            }
+        }
+
+        let mut uploads = Vec::new();
+        if !loaded.is_empty() {
+            let buffer = self.backend
+                .create_upload_buffer::<[u8; 4]>(loaded.len() * texels)
+                .unwrap();
+            {
+                let mut mapping = self.backend.write_mapping(&buffer).unwrap();
+                for (i, &(_, ref data)) in loaded.iter().enumerate() {
+                    mapping[i * texels .. (i + 1) * texels].copy_from_slice(data);
+                }
+            }
+            let info = texture.cache.get_info().to_raw_image_info(Srgba8::get_format().1, 0);
+            for (i, &(position, _)) in loaded.iter().enumerate() {
+                uploads.push(TextureUpload {
+                    buffer: buffer.raw().clone(),
+                    offset: i * texels * 4,
+                    texture: texture.cache.raw().clone(),
+                    info: t::RawImageInfo {
+                        xoffset: (position[0] * tile_size) as t::Size,
+                        yoffset: (position[1] * tile_size) as t::Size,
+                        width: tile_size as t::Size,
+                        height: tile_size as t::Size,
+                        .. info
+                    },
+                });
+            }
+        }
+
+        if let Some(tables) = texture.take_tables() {
+            let total = tables.iter().map(Vec::len).sum();
+            let buffer = self.backend.create_upload_buffer::<[u8; 4]>(total).unwrap();
+            {
+                let mut mapping = self.backend.write_mapping(&buffer).unwrap();
+                let mut offset = 0;
+                for table in &tables {
+                    mapping[offset .. offset + table.len()].copy_from_slice(table);
+                    offset += table.len();
+                }
+            }
+            let mut offset = 0;
+            for (level, table) in tables.iter().enumerate() {
+                uploads.push(TextureUpload {
+                    buffer: buffer.raw().clone(),
+                    offset: offset * 4,
+                    texture: texture.indirection.raw().clone(),
+                    info: texture.indirection.get_info().to_raw_image_info(Rgba8::get_format().1, level as t::Level),
+                });
+                offset += table.len();
+            }
+        }
+
+        if !uploads.is_empty() {
+            self.hub.lock().unwrap().texture_uploads.extend(uploads);
+        }
+        loaded.len()
+    }
+
     /// Load TrueTypeFont (.ttf) from file.
     /// #### Panics
     /// Panics if I/O operations with file fails (e.g. file not found or corrupted)
@@ -1007,6 +1927,25 @@ impl Factory {
         Font::new(buffer, format!("path: {:?}", file_path), self.backend.clone())
     }
 
+    /// Load TrueTypeFont (.ttf) from memory, e.g. a font embedded with `include_bytes!`.
+    ///
+    /// ```rust,no_run
+    /// # let mut window = three::Window::new("");
+    /// static FONT: &[u8] = &[/* include_bytes!("font.ttf") */];
+    /// let font = window.factory.load_font_from_bytes(FONT);
+    /// ```
+    pub fn load_font_from_bytes<B: Into<Cow<'static, [u8]>>>(
+        &mut self,
+        bytes: B,
+    ) -> Font {
+        static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+        let id = format!("bytes: #{}", NEXT_ID.fetch_add(1, Ordering::Relaxed));
+        match bytes.into() {
+            Cow::Borrowed(bytes) => Font::new(bytes, id, self.backend.clone()),
+            Cow::Owned(bytes) => Font::new(bytes, id, self.backend.clone()),
+        }
+    }
+
     /// Load the Karla font
     pub fn load_font_karla(&mut self) -> Font {
         let buffer: &'static [u8] = include_bytes!("../../data/fonts/Karla-Regular.ttf");
@@ -1034,9 +1973,27 @@ impl Factory {
         }
     }
 
+    fn create_texture_view(
+        factory: &mut BackendFactory,
+        kind: gfx::texture::Kind,
+        data: &[&[u8]],
+        color_space: ColorSpace,
+    ) -> Result<gfx::handle::ShaderResourceView<BackendResources, [f32; 4]>, gfx::CombinedError> {
+        use gfx::texture::Mipmap;
+        match color_space {
+            ColorSpace::Srgb => factory
+                .create_texture_immutable_u8::<gfx::format::Srgba8>(kind, Mipmap::Provided, data)
+                .map(|(_, view)| view),
+            ColorSpace::Linear => factory
+                .create_texture_immutable_u8::<gfx::format::Rgba8>(kind, Mipmap::Provided, data)
+                .map(|(_, view)| view),
+        }
+    }
+
     fn load_texture_impl(
         path: &Path,
         sampler: Sampler,
+        color_space: ColorSpace,
         factory: &mut BackendFactory,
     ) -> Texture<[f32; 4]> {
         use gfx::texture as t;
@@ -1049,8 +2006,7 @@ impl Factory {
             .to_rgba();
         let (width, height) = img.dimensions();
         let kind = t::Kind::D2(width as t::Size, height as t::Size, t::AaMode::Single);
-        let (_, view) = factory
-            .create_texture_immutable_u8::<gfx::format::Srgba8>(kind, t::Mipmap::Provided, &[&img])
+        let view = Factory::create_texture_view(factory, kind, &[&img], color_space)
             .unwrap_or_else(|e| {
                 panic!(
                     "Unable to create GPU texture for {}: {:?}",
@@ -1058,7 +2014,7 @@ impl Factory {
                     e
                 )
             });
-        Texture::new(view, sampler.0, [width, height])
+        Texture::new(view, sampler.0, [width, height], color_space)
     }
 
     fn load_cubemap_impl<P: AsRef<Path>>(
@@ -1083,23 +2039,85 @@ impl Factory {
         ];
         let size = images[0].dimensions().0;
         let kind = t::Kind::Cube(size as t::Size);
+        // the mipmap chain is generated with the next render, for rough
+        // materials to sample blurrier reflections
         let (_, view) = factory
-            .create_texture_immutable_u8::<gfx::format::Srgba8>(kind, t::Mipmap::Provided, &data)
+            .create_texture_immutable_u8::<gfx::format::Srgba8>(kind, t::Mipmap::Allocated, &data)
             .unwrap_or_else(|e| {
                 panic!("Unable to create GPU texture for cubemap: {:?}", e);
             });
         CubeMap::new(view, sampler.0)
     }
 
+    /// Loads the height map at `path` as a tangent space normal map.
+    fn load_bump_map_impl(
+        path: &Path,
+        sampler: Sampler,
+        factory: &mut BackendFactory,
+    ) -> Texture<[f32; 4]> {
+        use gfx::texture as t;
+        let format = Factory::parse_texture_format(path);
+        let file = fs::File::open(path).unwrap_or_else(|e| panic!("Unable to open {}: {:?}", path.display(), e));
+        let heights = image::load(io::BufReader::new(file), format)
+            .unwrap_or_else(|e| panic!("Unable to decode {}: {:?}", path.display(), e))
+            .flipv()
+            .to_luma();
+        let (width, height) = heights.dimensions();
+        let sample = |x: i64, y: i64| {
+            let x = cmp::min(cmp::max(x, 0), width as i64 - 1) as u32;
+            let y = cmp::min(cmp::max(y, 0), height as i64 - 1) as u32;
+            heights.get_pixel(x, y).data[0] as f32 / 255.0
+        };
+        let mut pixels = Vec::with_capacity((width * height * 4) as usize);
+        for y in 0 .. height as i64 {
+            for x in 0 .. width as i64 {
+                // central differences, with rows going up after the flip
+                let dx = (sample(x + 1, y) - sample(x - 1, y)) * 0.5 * BUMP_STRENGTH;
+                let dy = (sample(x, y + 1) - sample(x, y - 1)) * 0.5 * BUMP_STRENGTH;
+                let normal = Vector3::new(-dx, -dy, 1.0).normalize();
+                for &c in &[normal.x, normal.y, normal.z] {
+                    pixels.push(((c * 0.5 + 0.5) * 255.0).round() as u8);
+                }
+                pixels.push(0xFF);
+            }
+        }
+        let kind = t::Kind::D2(width as t::Size, height as t::Size, t::AaMode::Single);
+        let view = Factory::create_texture_view(factory, kind, &[&pixels], ColorSpace::Linear)
+            .unwrap_or_else(|e| {
+                panic!(
+                    "Unable to create GPU texture for {}: {:?}",
+                    path.display(),
+                    e
+                )
+            });
+        Texture::new(view, sampler.0, [width, height], ColorSpace::Linear)
+    }
+
+    fn request_bump_map(
+        &mut self,
+        path: &Path,
+        sampler: Sampler,
+    ) -> Texture<[f32; 4]> {
+        match self.bump_cache.entry(path.to_owned()) {
+            Entry::Occupied(e) => e.get().clone(),
+            Entry::Vacant(e) => {
+                let tex = Self::load_bump_map_impl(path, sampler, &mut self.backend);
+                e.insert(tex.clone());
+                tex
+            }
+        }
+    }
+
     fn request_texture<P: AsRef<Path>>(
         &mut self,
         path: P,
         sampler: Sampler,
+        color_space: ColorSpace,
     ) -> Texture<[f32; 4]> {
-        match self.texture_cache.entry(path.as_ref().to_owned()) {
+        match self.texture_cache.entry((path.as_ref().to_owned(), color_space)) {
             Entry::Occupied(e) => e.get().clone(),
             Entry::Vacant(e) => {
-                let tex = Self::load_texture_impl(path.as_ref(), sampler, &mut self.backend);
+                let tex = Self::load_texture_impl(path.as_ref(), sampler, color_space, &mut self.backend);
                 e.insert(tex.clone());
                 tex
             }
@@ -1117,16 +2135,37 @@ impl Factory {
             c.iter()
                 .fold(0, |u, &v| (u << 8) + cmp::min((v * 255.0) as u32, 0xFF))
         };
+        let opacity = mat.d.unwrap_or(1.0);
         match *mat {
             obj::Material {
                 kd: Some(color),
                 ns: Some(glossiness),
+                ks,
+                ref map_kd,
+                ref map_bump,
                 ..
             } if has_normals =>
             {
                 material::Phong {
                     color: cf2u(color),
                     glossiness,
+                    specular: ks.map_or(WHITE, cf2u),
+                    map: match (has_uv, map_kd) {
+                        (true, &Some(ref name)) => {
+                            let sampler = self.default_sampler();
+                            Some(self.request_texture(&concat_path(obj_dir, name), sampler, ColorSpace::Srgb))
+                        },
+                        _ => None,
+                    },
+                    // bump maps hold heights, converted to normals
+                    normal_map: match (has_uv, map_bump) {
+                        (true, &Some(ref name)) => {
+                            let sampler = self.default_sampler();
+                            Some(self.request_bump_map(&concat_path(obj_dir, name), sampler))
+                        },
+                        _ => None,
+                    },
+                    opacity,
                 }.into()
             }
             obj::Material {
@@ -1136,6 +2175,7 @@ impl Factory {
                 material::Lambert {
                     color: cf2u(color),
                     flat: false,
+                    opacity,
                 }.into()
             }
             obj::Material {
@@ -1147,34 +2187,108 @@ impl Factory {
                 map: match (has_uv, map_kd) {
                     (true, &Some(ref name)) => {
                         let sampler = self.default_sampler();
-                        Some(self.request_texture(&concat_path(obj_dir, name), sampler))
+                        Some(self.request_texture(&concat_path(obj_dir, name), sampler, ColorSpace::Srgb))
                     },
                     _ => None,
                 },
+                opacity,
             }.into(),
             _ => material::Basic {
                 color: 0xffffff,
                 map: None,
+                opacity: 1.0,
             }.into(),
         }
     }
 
-    /// Load texture from pre-loaded data.
+    /// Returns the gfx factory of the OpenGL context, to create resources
+    /// shared with code outside of `three`.
+    pub fn backend(&mut self) -> &mut BackendFactory {
+        &mut self.backend
+    }
+
+    /// Wraps a texture created outside of `three` on the same OpenGL context,
+    /// e.g. frames of a video decoder, to be used by materials, sprites or the
+    /// background.
+    ///
+    /// `size` is the size of the texture in texels, and `color_space` the
+    /// encoding of its texels, which must match the format of `view`.
+    ///
+    /// # Synchronization
+    ///
+    /// OpenGL executes the commands of a context in order, so the texture can be
+    /// updated by foreign code between frames without further synchronization.
+    /// It must not be updated while [`Renderer::render`] runs. When the texture
+    /// is written from another context sharing objects with this one, the writer
+    /// must finish its commands (e.g. with `glFinish` or a fence) before the
+    /// frame is rendered.
+    ///
+    /// [`Renderer::render`]: struct.Renderer.html#method.render
+    pub fn texture_from_view(
+        &mut self,
+        view: gfx::handle::ShaderResourceView<BackendResources, [f32; 4]>,
+        sampler: Sampler,
+        size: [u32; 2],
+        color_space: ColorSpace,
+    ) -> Texture<[f32; 4]> {
+        Texture::new(view, sampler.0, size, color_space)
+    }
+
+    /// Creates an offscreen target of `width` by `height` pixels to render a
+    /// scene into with [`Renderer::render_to_target`].
+    ///
+    /// The texture of the target is sampled bilinearly and clamped at the
+    /// edges. Its texels hold the colors as they would be written to the
+    /// window, without conversion.
+    ///
+    /// [`Renderer::render_to_target`]: struct.Renderer.html#method.render_to_target
+    pub fn render_target(
+        &mut self,
+        width: u32,
+        height: u32,
+    ) -> RenderTarget {
+        use gfx::texture as t;
+        let (_, view, color) = self.backend
+            .create_render_target::<ColorFormat>(width as t::Size, height as t::Size)
+            .expect("Can't create render target");
+        let depth = self.backend
+            .create_depth_stencil_view_only::<DepthFormat>(width as t::Size, height as t::Size)
+            .expect("Can't create render target depth");
+        let sampler = self.backend.create_sampler(t::SamplerInfo::new(
+            t::FilterMethod::Bilinear,
+            t::WrapMode::Clamp,
+        ));
+        let texture = Texture::new(view, sampler, [width, height], ColorSpace::Linear);
+        RenderTarget::new(color, depth, texture)
+    }
+
+    /// Load texture from pre-loaded sRGB data.
     pub fn load_texture_from_memory(
         &mut self,
         width: u16,
         height: u16,
         pixels: &[u8],
         sampler: Sampler,
+    ) -> Texture<[f32; 4]> {
+        self.load_texture_from_memory_with_color_space(width, height, pixels, sampler, ColorSpace::Srgb)
+    }
+
+    /// Load texture from pre-loaded data encoded in `color_space`.
+    pub fn load_texture_from_memory_with_color_space(
+        &mut self,
+        width: u16,
+        height: u16,
+        pixels: &[u8],
+        sampler: Sampler,
+        color_space: ColorSpace,
     ) -> Texture<[f32; 4]> {
         use gfx::texture as t;
         let kind = t::Kind::D2(width, height, t::AaMode::Single);
-        let (_, view) = self.backend
-            .create_texture_immutable_u8::<gfx::format::Srgba8>(kind, t::Mipmap::Provided, &[pixels])
+        let view = Factory::create_texture_view(&mut self.backend, kind, &[pixels], color_space)
             .unwrap_or_else(|e| {
                 panic!("Unable to create GPU texture from memory: {:?}", e);
             });
-        Texture::new(view, sampler.0, [width as u32, height as u32])
+        Texture::new(view, sampler.0, [width as u32, height as u32], color_space)
     }
 
     /// Load texture from file, with default `Sampler`.
@@ -1184,7 +2298,7 @@ impl Factory {
         path_str: P,
     ) -> Texture<[f32; 4]> {
         let sampler = self.default_sampler();
-        self.request_texture(path_str, sampler)
+        self.request_texture(path_str, sampler, ColorSpace::Srgb)
     }
 
     /// Load texture from file, with custom `Sampler`.
@@ -1194,7 +2308,20 @@ impl Factory {
         path_str: P,
         sampler: Sampler,
     ) -> Texture<[f32; 4]> {
-        self.request_texture(path_str, sampler)
+        self.request_texture(path_str, sampler, ColorSpace::Srgb)
+    }
+
+    /// Load texture from file, with custom `Sampler` and the color space its
+    /// texels are encoded in. Use `ColorSpace::Linear` for normal maps and
+    /// other textures that don't hold colors.
+    /// Supported file formats are: PNG, JPEG, GIF, WEBP, PPM, TIFF, TGA, BMP, ICO, HDR.
+    pub fn load_texture_with_color_space<P: AsRef<Path>>(
+        &mut self,
+        path_str: P,
+        sampler: Sampler,
+        color_space: ColorSpace,
+    ) -> Texture<[f32; 4]> {
+        self.request_texture(path_str, sampler, color_space)
     }
 
     /// Load cubemap from files.
@@ -1203,10 +2330,16 @@ impl Factory {
         &mut self,
         paths: &CubeMapPath<P>,
     ) -> CubeMap<[f32; 4]> {
-        Factory::load_cubemap_impl(paths, self.default_sampler(), &mut self.backend)
+        use gfx::memory::Typed;
+        let map = Factory::load_cubemap_impl(paths, self.default_sampler(), &mut self.backend);
+        self.hub.lock().unwrap().mipmaps.push(map.to_param().0.raw().clone());
+        map
     }
 
     /// Load mesh from Wavefront Obj format.
+    ///
+    /// Groups made of the same faces share a single vertex buffer, and are
+    /// drawn as instances of each other when their materials match too.
     pub fn load_obj(
         &mut self,
         path_str: &str,
@@ -1225,48 +2358,70 @@ impl Factory {
         let mut meshes = Vec::new();
         let mut vertices = Vec::new();
         let mut indices = Vec::new();
+        // Uploaded vertex data keyed by the OBJ vertices of the triangles,
+        // shared between groups with the same faces.
+        let mut uploaded: HashMap<
+            Vec<obj::IndexTuple>,
+            (gfx::handle::Buffer<BackendResources, Vertex>, gfx::Slice<BackendResources>, usize, usize),
+        > = HashMap::new();
+        let mut num_reused = 0;
 
         for object in &obj.objects {
             let group = object::Group::new(&mut *hub);
             for gr in &object.groups {
-                let (mut num_normals, mut num_uvs) = (0, 0);
-                {
-                    // separate scope for LruIndexer
-                    let f2i = |x: f32| I8Norm(cmp::min(cmp::max((x * 127.) as isize, -128), 127) as i8);
-                    vertices.clear();
-                    let mut lru = LruIndexer::new(10, |_, obj::IndexTuple(ipos, iuv, inor)| {
-                        let p: [f32; 3] = obj.position[ipos];
-                        vertices.push(Vertex {
-                            pos: [p[0], p[1], p[2], 1.0],
-                            uv: match iuv {
-                                Some(i) => {
-                                    num_uvs += 1;
-                                    obj.texture[i]
-                                }
-                                None => [0.0, 0.0],
-                            },
-                            normal: match inor {
-                                Some(id) => {
-                                    num_normals += 1;
-                                    let n: [f32; 3] = obj.normal[id];
-                                    [f2i(n[0]), f2i(n[1]), f2i(n[2]), I8Norm(0)]
-                                }
-                                None => [I8Norm(0), I8Norm(0), I8Norm(0x7f), I8Norm(0)],
-                            },
-                            .. DEFAULT_VERTEX
-                        });
-                    });
-
-                    indices.clear();
-                    indices.extend(
-                        gr.polys
-                            .iter()
-                            .cloned()
-                            .triangulate()
-                            .vertices()
-                            .map(|tuple| lru.index(tuple) as u16),
-                    );
+                let key: Vec<obj::IndexTuple> = gr.polys
+                    .iter()
+                    .cloned()
+                    .triangulate()
+                    .vertices()
+                    .collect();
+                let (vertices, slice, num_normals, num_uvs) = match uploaded.get(&key) {
+                    Some(&(ref vertices, ref slice, num_normals, num_uvs)) => {
+                        num_reused += 1;
+                        (vertices.clone(), slice.clone(), num_normals, num_uvs)
+                    }
+                    None => {
+                        let (mut num_normals, mut num_uvs) = (0, 0);
+                        {
+                            // separate scope for LruIndexer
+                            let f2i = |x: f32| I8Norm(cmp::min(cmp::max((x * 127.) as isize, -128), 127) as i8);
+                            vertices.clear();
+                            let mut lru = LruIndexer::new(10, |_, obj::IndexTuple(ipos, iuv, inor)| {
+                                let p: [f32; 3] = obj.position[ipos];
+                                vertices.push(Vertex {
+                                    pos: [p[0], p[1], p[2], 1.0],
+                                    uv: match iuv {
+                                        Some(i) => {
+                                            num_uvs += 1;
+                                            obj.texture[i]
+                                        }
+                                        None => [0.0, 0.0],
+                                    },
+                                    normal: match inor {
+                                        Some(id) => {
+                                            num_normals += 1;
+                                            let n: [f32; 3] = obj.normal[id];
+                                            [f2i(n[0]), f2i(n[1]), f2i(n[2]), I8Norm(0)]
+                                        }
+                                        None => [I8Norm(0), I8Norm(0), I8Norm(0x7f), I8Norm(0)],
+                                    },
+                                    .. DEFAULT_VERTEX
+                                });
+                            });
+
+                            indices.clear();
+                            indices.extend(key.iter().map(|&tuple| lru.index(tuple) as u16));
+                        };
+                        let (vertices, mut slice) = self.backend
+                            .create_vertex_buffer_with_slice(&vertices, &indices[..]);
+                        slice.instances = Some((1, 0));
+                        (vertices, slice, num_normals, num_uvs)
+                    }
                 };
+                let bounds = Aabb::from_points(key.iter().map(|&obj::IndexTuple(ipos, _, _)| Point3::from(obj.position[ipos])));
+                uploaded
+                    .entry(key)
+                    .or_insert_with(|| (vertices.clone(), slice.clone(), num_normals, num_uvs));
 
                 info!(
                     "\tmaterial {} with {} normals and {} uvs",
@@ -1277,13 +2432,11 @@ impl Factory {
                     None => material::Basic {
                         color: 0xFFFFFF,
                         map: None,
+                        opacity: 1.0,
                     }.into(),
                 };
                 info!("\t{:?}", material);
 
-                let (vertices, mut slice) = self.backend
-                    .create_vertex_buffer_with_slice(&vertices, &indices[..]);
-                slice.instances = Some((1, 0));
                 let instances = self.backend
                     .create_buffer(
                         1,
@@ -1292,6 +2445,12 @@ impl Factory {
                         gfx::memory::Bind::TRANSFER_DST,
                     )
                     .unwrap();
+                // Groups sharing both geometry and material are rendered as instances.
+                let instance_cache_key = InstanceCacheKey {
+                    material: material.clone(),
+                    state: Default::default(),
+                    geometry: vertices.clone(),
+                };
                 let mesh = Mesh {
                     object: hub.spawn_visual(
                         material,
@@ -1301,8 +2460,13 @@ impl Factory {
                             instances,
                             displacements: None,
                             pending: None,
-                            instance_cache_key: None,
+                            instance_cache_key: Some(instance_cache_key),
                             displacement_contributions: ZEROED_DISPLACEMENT_CONTRIBUTION.to_vec(),
+                            render_state: Default::default(),
+                            light_mask: !0,
+                            fade: Fade::default(),
+                            geometry: None,
+                            bounds,
                         },
                         None,
                     ),
@@ -1314,10 +2478,12 @@ impl Factory {
             groups.insert(object.name.clone(), group);
         }
 
+        info!("\t{} of {} groups reused uploaded geometry", num_reused, meshes.len());
         (groups, meshes)
     }
 
     /// Load audio from file. Supported formats are Flac, Vorbis and WAV.
+    #[cfg(feature = "audio")]
     pub fn load_audio<P: AsRef<Path>>(
         &self,
         path: P,
@@ -1,8 +1,15 @@
 //! Structures for creating and storing geometric primitives.
 
+use cgmath::{InnerSpace, Quaternion, Vector3, Zero};
 use genmesh::{EmitTriangles, Triangulate, Vertex as GenVertex};
 use genmesh::generators::{self, IndexedPolygon, SharedVertex};
+use material::LineMode;
 use mint;
+use node::Transform;
+
+use std::borrow::Cow;
+use std::f32::consts::PI;
+use std::iter;
 
 /// A collection of vertices, their normals, and faces that defines the
 /// shape of a polyhedral object.
@@ -43,17 +50,26 @@ use mint;
 /// ```
 /// # Notes
 ///
-/// * If any vertex normals, tangents, or texture co-ordinates are provided,
-///   the number of entries in each array must match the number of entries
-///   in `vertices`.
+/// * If any vertex normals, tangents, texture co-ordinates, or colors are
+///   provided, the number of entries in each array must match the number of
+///   entries in `vertices`.
+/// * Normals can be computed from the faces with
+///   [`compute_normals`](#method.compute_normals).
+/// * Tangents are computed from the normals and texture co-ordinates when
+///   omitted, see [`compute_tangents`](#method.compute_tangents).
 /// * If joints are provided, the number of entries in `joints.indices` must
 ///   match the number of entries in `joints.weights`.
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, PartialEq)]
 pub struct Geometry {
     /// Idle shape of the geometry.
     pub base: Shape,
     /// Texture co-ordinates.
     pub tex_coords: Vec<mint::Point2<f32>>,
+    /// Vertex colors in linear RGBA, multiplied with the material color of
+    /// basic meshes, e.g. lighting baked by [`bake`](../bake/index.html).
+    ///
+    /// When omitted, all vertices are white.
+    pub colors: Vec<[f32; 4]>,
     /// Face indices.
     ///
     /// When omitted, the vertex order `[[0, 1, 2], [3, 4, 5], ...]` is
@@ -66,7 +82,7 @@ pub struct Geometry {
 }
 
 /// A geometry shape.
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, PartialEq)]
 pub struct Shape {
     /// Vertices.
     pub vertices: Vec<mint::Point3<f32>>,
@@ -77,7 +93,7 @@ pub struct Shape {
 }
 
 /// Properties for vertex skinning.
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, PartialEq)]
 pub struct Joints {
     /// Joint indices, encoded as floats.
     pub indices: Vec<[i32; 4]>,
@@ -85,7 +101,93 @@ pub struct Joints {
     pub weights: Vec<[f32; 4]>,
 }
 
+/// Appends `count` values of the attribute `source` to `target`, which
+/// covers `target_count` vertices, padding either one with `default` if only
+/// the other has the attribute.
+fn append<T: Clone>(
+    target: &mut Vec<T>,
+    target_count: usize,
+    source: &[T],
+    count: usize,
+    default: T,
+) {
+    if target.is_empty() && source.is_empty() {
+        return;
+    }
+    target.resize(target_count, default.clone());
+    target.extend(source.iter().cloned().chain(iter::repeat(default)).take(count));
+}
+
+/// How the vertices of two geometries are matched when building a morph
+/// target, see [`Geometry::with_morph_target`].
+///
+/// [`Geometry::with_morph_target`]: struct.Geometry.html#method.with_morph_target
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Correspondence {
+    /// Vertex `i` of one geometry moves to vertex `i` of the other.
+    ///
+    /// Both geometries must have the same number of vertices, which is the
+    /// case for topologically identical meshes exported with the same
+    /// vertex order.
+    Index,
+    /// Every vertex moves to the nearest vertex of the other geometry.
+    ///
+    /// Works with any pair of geometries, but several vertices may collapse
+    /// onto the same target. The search is brute force, so this is meant
+    /// for small meshes or offline preparation.
+    Nearest,
+}
+
 impl Geometry {
+    /// Hashes the contents of the geometry, e.g. to find duplicates in loaders.
+    ///
+    /// Equal geometries have equal hashes, but the converse isn't guaranteed.
+    pub(crate) fn content_hash(&self) -> u64 {
+        use std::collections::hash_map::DefaultHasher;
+        use std::hash::Hasher;
+
+        fn write_floats<'a, I: IntoIterator<Item = &'a f32>>(
+            hasher: &mut DefaultHasher,
+            floats: I,
+        ) {
+            for value in floats {
+                hasher.write_u32(value.to_bits());
+            }
+        }
+
+        let mut hasher = DefaultHasher::new();
+        for shape in Some(&self.base).into_iter().chain(self.shapes.iter()) {
+            hasher.write_usize(shape.vertices.len());
+            for v in &shape.vertices {
+                write_floats(&mut hasher, &[v.x, v.y, v.z]);
+            }
+            for n in &shape.normals {
+                write_floats(&mut hasher, &[n.x, n.y, n.z]);
+            }
+            for t in &shape.tangents {
+                write_floats(&mut hasher, &[t.x, t.y, t.z, t.w]);
+            }
+        }
+        for uv in &self.tex_coords {
+            write_floats(&mut hasher, &[uv.x, uv.y]);
+        }
+        for color in &self.colors {
+            write_floats(&mut hasher, color);
+        }
+        for face in &self.faces {
+            hasher.write_u32(face[0]);
+            hasher.write_u32(face[1]);
+            hasher.write_u32(face[2]);
+        }
+        for (indices, weights) in self.joints.indices.iter().zip(&self.joints.weights) {
+            for &index in indices {
+                hasher.write_i32(index);
+            }
+            write_floats(&mut hasher, weights);
+        }
+        hasher.finish()
+    }
+
     /// Create `Geometry` from vector of vertices.
     ///
     /// # Examples
@@ -110,16 +212,359 @@ impl Geometry {
         }
     }
 
-    fn generate<P, G, Fpos, Fnor>(
+    /// Computes smooth normals of the base shape from its faces, replacing
+    /// any existing ones.
+    ///
+    /// The normal of each vertex averages the normals of the faces around it,
+    /// weighted by their area, so that small faces of a tessellated surface
+    /// don't skew the result. Faces are counter-clockwise seen from the side
+    /// the normals face. Vertices shared by faces meeting at a hard edge are
+    /// smoothed as well; duplicate them to keep the edge sharp.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let mut geometry = three::Geometry {
+    ///     faces: vec![[0, 1, 2]],
+    ///     ..three::Geometry::with_vertices(vec![
+    ///         [0.0, 0.0, 0.0].into(),
+    ///         [1.0, 0.0, 0.0].into(),
+    ///         [0.0, 1.0, 0.0].into(),
+    ///     ])
+    /// };
+    /// geometry.compute_normals();
+    /// let normal = geometry.base.normals[0];
+    /// assert_eq!([normal.x, normal.y, normal.z], [0.0, 0.0, 1.0]);
+    /// ```
+    pub fn compute_normals(&mut self) {
+        self.base.normals = self.generate_normals();
+    }
+
+    /// Computes the tangents of the base shape from its normals and texture
+    /// co-ordinates, replacing any existing ones.
+    ///
+    /// Tangents orient the normal maps of lit materials. They are computed
+    /// automatically when a mesh is created from a geometry without tangents,
+    /// so calling this is only needed to inspect or adjust them. Leaves no
+    /// tangents if the geometry has no normals or no texture co-ordinates,
+    /// see [`compute_normals`](#method.compute_normals).
+    ///
+    /// Like MikkTSpace, the texture space directions of the faces around each
+    /// vertex are projected onto the plane of its normal and weighted by the
+    /// angle of the face at the vertex, so the result doesn't depend on how
+    /// the surface is tessellated. Unlike MikkTSpace, vertices are never
+    /// split, so vertices shared across a mirrored texture seam get a single
+    /// handedness.
+    pub fn compute_tangents(&mut self) {
+        self.base.tangents = self.generate_tangents();
+    }
+
+    /// Stores the distance along the line of each vertex of the base shape in
+    /// the first texture co-ordinate, replacing any existing ones.
+    ///
+    /// Distances follow the line from its first vertex for `LineMode::Strip`
+    /// and start over at each segment for `LineMode::Segments`. They place the
+    /// dashes of [`DashedLine`](material/struct.DashedLine.html) materials.
+    pub fn compute_line_distances(
+        &mut self,
+        mode: LineMode,
+    ) {
+        let vertices = &self.base.vertices;
+        let mut distance = 0.0;
+        self.tex_coords = (0 .. vertices.len())
+            .map(|i| {
+                let start = match mode {
+                    LineMode::Strip => i == 0,
+                    LineMode::Segments => i % 2 == 0,
+                };
+                if start {
+                    distance = 0.0;
+                } else {
+                    let (a, b) = (vertices[i - 1], vertices[i]);
+                    distance += Vector3::new(b.x - a.x, b.y - a.y, b.z - a.z).magnitude();
+                }
+                [distance, 0.0].into()
+            })
+            .collect();
+    }
+
+    /// Bakes `transform` into the vertices, normals and tangents of all
+    /// shapes, e.g. to place a part before [`merge`](#method.merge)ing it
+    /// with others.
+    ///
+    /// A negative scale mirrors the geometry, which also reverses the order
+    /// of the faces so that they keep facing outwards.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let mut geometry = three::Geometry::cuboid(1.0, 1.0, 1.0);
+    /// geometry.transform(&three::Transform {
+    ///     position: [0.0, 0.5, 0.0].into(),
+    ///     scale: 2.0,
+    ///     ..three::Transform::default()
+    /// });
+    /// ```
+    pub fn transform(
+        &mut self,
+        transform: &Transform,
+    ) {
+        let rotation = Quaternion::from(transform.orientation);
+        let p = transform.position;
+        let translation = Vector3::new(p.x, p.y, p.z);
+        let scale = transform.scale;
+        // mirroring flips the normals along with the winding of the faces
+        let sign = if scale < 0.0 { -1.0 } else { 1.0 };
+        let rotate = |v: Vector3<f32>| rotation * v;
+
+        for (index, shape) in Some(&mut self.base).into_iter().chain(self.shapes.iter_mut()).enumerate() {
+            // blend shapes hold displacements, which aren't translated
+            let offset = if index == 0 { translation } else { Vector3::zero() };
+            for v in &mut shape.vertices {
+                let p = rotate(Vector3::new(v.x, v.y, v.z)) * scale + offset;
+                *v = [p.x, p.y, p.z].into();
+            }
+            for n in &mut shape.normals {
+                let r = rotate(Vector3::new(n.x, n.y, n.z)) * sign;
+                *n = r.into();
+            }
+            for t in &mut shape.tangents {
+                let r = rotate(Vector3::new(t.x, t.y, t.z)) * sign;
+                *t = mint::Vector4 {
+                    x: r.x,
+                    y: r.y,
+                    z: r.z,
+                    w: t.w * sign,
+                };
+            }
+        }
+
+        if sign < 0.0 {
+            let faces = self.triangles()
+                .iter()
+                .map(|f| [f[0], f[2], f[1]])
+                .collect();
+            self.faces = faces;
+        }
+    }
+
+    /// Appends the vertices and faces of `other`, offsetting its face indices
+    /// past the existing vertices, so that static parts sharing a material
+    /// can be drawn as a single mesh.
+    ///
+    /// Attributes present in only one of the geometries are filled in for the
+    /// other: normals and tangents are computed as with
+    /// [`compute_normals`](#method.compute_normals) and
+    /// [`compute_tangents`](#method.compute_tangents), while texture
+    /// co-ordinates, colors and joints take the values meshes use when they
+    /// are omitted. Blend shapes are matched by index, and don't move the
+    /// vertices of the geometry lacking them. Geometries without faces are
+    /// appended in vertex order.
+    ///
+    /// # Examples
+    ///
+    /// A row of boxes drawn with a single draw call.
+    ///
+    /// ```rust,no_run
+    /// # let mut window = three::Window::new("");
+    /// let mut row = three::Geometry::default();
+    /// for i in 0 .. 10 {
+    ///     let mut part = three::Geometry::cuboid(1.0, 1.0, 1.0);
+    ///     part.transform(&three::Transform {
+    ///         position: [2.0 * i as f32, 0.0, 0.0].into(),
+    ///         ..three::Transform::default()
+    ///     });
+    ///     row.merge(&part);
+    /// }
+    /// let mesh = window.factory.mesh(row, three::material::Lambert::default());
+    /// window.scene.add(&mesh);
+    /// ```
+    pub fn merge(
+        &mut self,
+        other: &Geometry,
+    ) {
+        let mut other = Cow::Borrowed(other);
+        if self.base.normals.is_empty() != other.base.normals.is_empty() {
+            if self.base.normals.is_empty() {
+                self.compute_normals();
+            } else {
+                other.to_mut().compute_normals();
+            }
+        }
+        if self.base.tangents.is_empty() != other.base.tangents.is_empty() {
+            // stays empty without texture co-ordinates, then filled below
+            if self.base.tangents.is_empty() {
+                self.compute_tangents();
+            } else {
+                other.to_mut().compute_tangents();
+            }
+        }
+
+        let count = self.base.vertices.len();
+        let other_count = other.base.vertices.len();
+        if !self.faces.is_empty() || !other.faces.is_empty() {
+            let mut faces = self.triangles().into_owned();
+            let offset = count as u32;
+            faces.extend(
+                other
+                    .triangles()
+                    .iter()
+                    .map(|f| [f[0] + offset, f[1] + offset, f[2] + offset]),
+            );
+            self.faces = faces;
+        }
+
+        append(&mut self.base.vertices, count, &other.base.vertices, other_count, [0.0; 3].into());
+        append(&mut self.base.normals, count, &other.base.normals, other_count, [0.0, 1.0, 0.0].into());
+        append(&mut self.base.tangents, count, &other.base.tangents, other_count, [1.0, 0.0, 0.0, 1.0].into());
+        append(&mut self.tex_coords, count, &other.tex_coords, other_count, [0.0; 2].into());
+        append(&mut self.colors, count, &other.colors, other_count, [1.0; 4]);
+        append(&mut self.joints.indices, count, &other.joints.indices, other_count, [0; 4]);
+        append(&mut self.joints.weights, count, &other.joints.weights, other_count, [1.0; 4]);
+
+        while self.shapes.len() < other.shapes.len() {
+            self.shapes.push(Shape::default());
+        }
+        let empty = Shape::default();
+        for (index, shape) in self.shapes.iter_mut().enumerate() {
+            let other_shape = other.shapes.get(index).unwrap_or(&empty);
+            append(&mut shape.vertices, count, &other_shape.vertices, other_count, [0.0; 3].into());
+            append(&mut shape.normals, count, &other_shape.normals, other_count, [0.0; 3].into());
+            append(&mut shape.tangents, count, &other_shape.tangents, other_count, [0.0; 4].into());
+        }
+    }
+
+    /// Returns the faces of the geometry, implicit or not.
+    fn triangles(&self) -> Cow<[[u32; 3]]> {
+        if self.faces.is_empty() {
+            let count = self.base.vertices.len() as u32 / 3;
+            Cow::Owned((0 .. count).map(|i| [3 * i, 3 * i + 1, 3 * i + 2]).collect())
+        } else {
+            Cow::Borrowed(&self.faces)
+        }
+    }
+
+    /// Returns the area weighted normals of the base shape.
+    pub(crate) fn generate_normals(&self) -> Vec<mint::Vector3<f32>> {
+        let shape = &self.base;
+        let position = |i: usize| {
+            let p = shape.vertices[i];
+            Vector3::new(p.x, p.y, p.z)
+        };
+
+        // the cross product of two edges is twice the area of the face
+        let mut normals = vec![Vector3::zero(); shape.vertices.len()];
+        for face in self.triangles().iter() {
+            let (a, b, c) = (face[0] as usize, face[1] as usize, face[2] as usize);
+            let normal = (position(b) - position(a)).cross(position(c) - position(a));
+            for &index in &[a, b, c] {
+                normals[index] = normals[index] + normal;
+            }
+        }
+
+        normals
+            .into_iter()
+            .map(|normal| {
+                let normal = if normal.magnitude2() < 1e-24 {
+                    // not part of any face, or only of degenerate ones
+                    Vector3::unit_y()
+                } else {
+                    normal.normalize()
+                };
+                normal.into()
+            })
+            .collect()
+    }
+
+    /// Returns the tangents of the base shape, with the handedness of the
+    /// texture space in `w`, or nothing if they can't be computed.
+    pub(crate) fn generate_tangents(&self) -> Vec<mint::Vector4<f32>> {
+        let shape = &self.base;
+        let count = shape.vertices.len();
+        if shape.normals.len() != count || self.tex_coords.len() != count {
+            return Vec::new();
+        }
+        let position = |i: usize| {
+            let p = shape.vertices[i];
+            Vector3::new(p.x, p.y, p.z)
+        };
+        let normal = |i: usize| {
+            let n = shape.normals[i];
+            Vector3::new(n.x, n.y, n.z)
+        };
+        let orthogonal = |v: Vector3<f32>, n: Vector3<f32>| v - n * n.dot(v);
+
+        // accumulate the texture space directions of the faces around each
+        // vertex, in the plane of its normal and weighted by the corner angle
+        let mut tangents = vec![Vector3::zero(); count];
+        let mut bitangents = vec![Vector3::zero(); count];
+        for face in self.triangles().iter() {
+            let (a, b, c) = (face[0] as usize, face[1] as usize, face[2] as usize);
+            let e1 = position(b) - position(a);
+            let e2 = position(c) - position(a);
+            let (uv0, uv1, uv2) = (self.tex_coords[a], self.tex_coords[b], self.tex_coords[c]);
+            let (du1, dv1) = (uv1.x - uv0.x, uv1.y - uv0.y);
+            let (du2, dv2) = (uv2.x - uv0.x, uv2.y - uv0.y);
+            let det = du1 * dv2 - du2 * dv1;
+            if det.abs() < 1e-12 {
+                continue;
+            }
+            let tangent = (e1 * dv2 - e2 * dv1) / det;
+            let bitangent = (e2 * du1 - e1 * du2) / det;
+            let corners = [(a, b, c), (b, c, a), (c, a, b)];
+            for &(index, next, prev) in &corners {
+                let to_next = position(next) - position(index);
+                let to_prev = position(prev) - position(index);
+                if to_next.magnitude2() < 1e-24 || to_prev.magnitude2() < 1e-24 {
+                    continue;
+                }
+                let cos = to_next.normalize().dot(to_prev.normalize());
+                let angle = cos.max(-1.0).min(1.0).acos();
+                let n = normal(index);
+                let t = orthogonal(tangent, n);
+                if t.magnitude2() > 1e-24 {
+                    tangents[index] = tangents[index] + t.normalize() * angle;
+                }
+                let bt = orthogonal(bitangent, n);
+                if bt.magnitude2() > 1e-24 {
+                    bitangents[index] = bitangents[index] + bt.normalize() * angle;
+                }
+            }
+        }
+
+        (0 .. count)
+            .map(|i| {
+                let n = normal(i);
+                let mut tangent = orthogonal(tangents[i], n);
+                if tangent.magnitude2() < 1e-12 {
+                    // no texture space direction, any vector orthogonal to the normal will do
+                    let axis = if n.x.abs() < 0.9 { Vector3::unit_x() } else { Vector3::unit_y() };
+                    tangent = orthogonal(axis, n);
+                }
+                let tangent = tangent.normalize();
+                let w = if n.cross(tangent).dot(bitangents[i]) < 0.0 { -1.0 } else { 1.0 };
+                mint::Vector4 {
+                    x: tangent.x,
+                    y: tangent.y,
+                    z: tangent.z,
+                    w,
+                }
+            })
+            .collect()
+    }
+
+    fn generate<P, G, Fpos, Fnor, Fuv>(
         gen: G,
         fpos: Fpos,
         fnor: Fnor,
+        fuv: Fuv,
     ) -> Self
     where
         P: EmitTriangles<Vertex = usize>,
         G: IndexedPolygon<P> + SharedVertex<GenVertex>,
         Fpos: Fn(GenVertex) -> mint::Point3<f32>,
         Fnor: Fn(GenVertex) -> mint::Vector3<f32>,
+        Fuv: Fn(GenVertex) -> mint::Point2<f32>,
     {
         Geometry {
             base: Shape {
@@ -127,7 +572,7 @@ impl Geometry {
                 normals: gen.shared_vertex_iter().map(fnor).collect(),
                 .. Shape::default()
             },
-            // TODO: Add similar functions for tangents and texture coords
+            tex_coords: gen.shared_vertex_iter().map(fuv).collect(),
             faces: gen.indexed_polygon_iter()
                 .triangulate()
                 .map(|t| [t.x as u32, t.y as u32, t.z as u32])
@@ -136,6 +581,73 @@ impl Geometry {
         }
     }
 
+    /// Generates a grid of `columns` by `rows` quads, with vertices at both
+    /// ends of the seams so that texture coordinates wrap properly.
+    ///
+    /// `f` maps the texture coordinates of a vertex, in `[0, 1]`, to its
+    /// position and normal. Faces are counter-clockwise when `u` increases
+    /// to the right and `v` upwards, seen from the side the normals face.
+    fn parametric<F>(
+        columns: usize,
+        rows: usize,
+        f: F,
+    ) -> Self
+    where
+        F: Fn(f32, f32) -> ([f32; 3], [f32; 3]),
+    {
+        let mut geometry = Geometry::default();
+        for j in 0 .. rows + 1 {
+            for i in 0 .. columns + 1 {
+                let u = i as f32 / columns as f32;
+                let v = j as f32 / rows as f32;
+                let (position, normal) = f(u, v);
+                geometry.base.vertices.push(position.into());
+                geometry.base.normals.push(normal.into());
+                geometry.tex_coords.push([u, v].into());
+            }
+        }
+        let stride = columns as u32 + 1;
+        for j in 0 .. rows as u32 {
+            for i in 0 .. columns as u32 {
+                let a = j * stride + i;
+                let b = a + 1;
+                let c = a + stride;
+                let d = c + 1;
+                geometry.faces.push([a, b, d]);
+                geometry.faces.push([a, d, c]);
+            }
+        }
+        geometry
+    }
+
+    /// Appends a disc closing a ring of `segments` edges at height `y`.
+    fn add_cap(
+        &mut self,
+        radius: f32,
+        y: f32,
+        segments: usize,
+        up: bool,
+    ) {
+        let normal = [0.0, if up { 1.0 } else { -1.0 }, 0.0];
+        // seen from the side the cap faces, so that textures aren't mirrored
+        let flip = if up { -1.0 } else { 1.0 };
+        let center = self.base.vertices.len() as u32;
+        self.base.vertices.push([0.0, y, 0.0].into());
+        self.base.normals.push(normal.into());
+        self.tex_coords.push([0.5, 0.5].into());
+        for i in 0 .. segments + 1 {
+            let angle = 2.0 * PI * i as f32 / segments as f32;
+            let (sin, cos) = angle.sin_cos();
+            self.base.vertices.push([radius * sin, y, radius * cos].into());
+            self.base.normals.push(normal.into());
+            self.tex_coords.push([0.5 + 0.5 * sin, 0.5 + 0.5 * flip * cos].into());
+        }
+        for i in 0 .. segments as u32 {
+            let (a, b) = (center + 1 + i, center + 2 + i);
+            self.faces.push(if up { [center, a, b] } else { [center, b, a] });
+        }
+    }
+
     /// Creates planar geometry in the XY plane.
     ///
     /// The `width` and `height` parameters specify the total length of the
@@ -160,6 +672,7 @@ impl Geometry {
             generators::Plane::new(),
             |GenVertex { pos, .. }| [pos[0] * 0.5 * width, pos[1] * 0.5 * height, 0.0].into(),
             |v| v.normal.into(),
+            |GenVertex { pos, .. }| [0.5 * (pos[0] + 1.0), 0.5 * (pos[1] + 1.0)].into(),
         )
     }
 
@@ -194,11 +707,27 @@ impl Geometry {
                 ].into()
             },
             |v| v.normal.into(),
+            |GenVertex { pos, normal }| {
+                // each face is unwrapped as seen from outside, upright
+                let (u, v) = if normal[0] != 0.0 {
+                    (-normal[0] * pos[2], pos[1])
+                } else if normal[1] != 0.0 {
+                    (pos[0], -normal[1] * pos[2])
+                } else {
+                    (normal[2] * pos[0], pos[1])
+                };
+                [0.5 * (u + 1.0), 0.5 * (v + 1.0)].into()
+            },
         )
     }
 
     /// Creates cylindrial geometry.
     ///
+    /// The cylinder is centered at the origin with its axis along Y, and is
+    /// closed at both ends, except where a radius is zero. The texture wraps
+    /// once around the side, starting and ending on the +Z axis, and each
+    /// end cap is mapped to its own unit square.
+    ///
     /// # Examples
     ///
     /// Cylinder of unit height and radius, using 12 segments at each end.
@@ -211,14 +740,14 @@ impl Geometry {
     /// # fn main() { let _ = make_cylinder(); }
     /// ```
     ///
-    /// Cone of unit height and unit radius at the bottom.
+    /// Truncated cone of unit height, twice as wide at the bottom.
     ///
     /// ```rust
     /// # extern crate three;
-    /// fn make_cone() -> three::Geometry {
-    ///     three::Geometry::cylinder(0.0, 1.0, 1.0, 12)
+    /// fn make_frustum() -> three::Geometry {
+    ///     three::Geometry::cylinder(0.5, 1.0, 1.0, 12)
     /// }
-    /// # fn main() { let _ = make_cone(); }
+    /// # fn main() { let _ = make_frustum(); }
     /// ```
     pub fn cylinder(
         radius_top: f32,
@@ -226,15 +755,49 @@ impl Geometry {
         height: f32,
         radius_segments: usize,
     ) -> Self {
-        Self::generate(
-            generators::Cylinder::new(radius_segments),
-            //Three.js has height along the Y axis for some reason
-            |GenVertex { pos, .. }| {
-                let scale = (pos[2] + 1.0) * 0.5 * radius_top + (1.0 - pos[2]) * 0.5 * radius_bottom;
-                [pos[1] * scale, pos[2] * 0.5 * height, pos[0] * scale].into()
-            },
-            |GenVertex { normal, .. }| [normal[1], normal[2], normal[0]].into(),
-        )
+        let segments = radius_segments.max(3);
+        //Three.js has height along the Y axis for some reason
+        let mut geometry = Self::parametric(segments, 1, |u, v| {
+            let (sin, cos) = (2.0 * PI * u).sin_cos();
+            let radius = radius_bottom + v * (radius_top - radius_bottom);
+            // the side leans by the difference of radii over the height
+            let normal = Vector3::new(height * sin, radius_bottom - radius_top, height * cos).normalize();
+            (
+                [radius * sin, (v - 0.5) * height, radius * cos],
+                normal.into(),
+            )
+        });
+        if radius_top > 0.0 {
+            geometry.add_cap(radius_top, 0.5 * height, segments, true);
+        }
+        if radius_bottom > 0.0 {
+            geometry.add_cap(radius_bottom, -0.5 * height, segments, false);
+        }
+        geometry
+    }
+
+    /// Creates conical geometry, with its apex up.
+    ///
+    /// This is a [`cylinder`](#method.cylinder) with a top radius of zero:
+    /// the cone is centered at the origin, with its axis along Y.
+    ///
+    /// # Examples
+    ///
+    /// Cone of unit height and unit radius at the bottom.
+    ///
+    /// ```rust
+    /// # extern crate three;
+    /// fn make_cone() -> three::Geometry {
+    ///     three::Geometry::cone(1.0, 1.0, 12)
+    /// }
+    /// # fn main() { let _ = make_cone(); }
+    /// ```
+    pub fn cone(
+        radius: f32,
+        height: f32,
+        radius_segments: usize,
+    ) -> Self {
+        Self::cylinder(0.0, radius, height, radius_segments)
     }
 
     /// Creates geometry for a sphere, using the UV method.
@@ -244,6 +807,10 @@ impl Geometry {
     /// * `meridional_segments` specifies the number of segments around
     ///    the sphere meridian that lies in the YZ plane.
     ///
+    /// The texture is mapped equirectangularly, wrapping once around the
+    /// equator from the +Z axis, with `v` going from the south pole to the
+    /// north pole.
+    ///
     /// ```rust
     /// # extern crate three;
     /// fn make_sphere() -> three::Geometry {
@@ -256,10 +823,159 @@ impl Geometry {
         equatorial_segments: usize,
         meridional_segments: usize,
     ) -> Self {
-        Self::generate(
-            generators::SphereUV::new(equatorial_segments, meridional_segments),
-            |GenVertex { pos, .. }| [pos[0] * radius, pos[1] * radius, pos[2] * radius].into(),
-            |v| v.normal.into(),
-        )
+        Self::parametric(equatorial_segments.max(3), meridional_segments.max(2), |u, v| {
+            let (sin_azimuth, cos_azimuth) = (2.0 * PI * u).sin_cos();
+            let (sin_polar, cos_polar) = (PI * v).sin_cos();
+            let normal = [sin_polar * sin_azimuth, -cos_polar, sin_polar * cos_azimuth];
+            (
+                [radius * normal[0], radius * normal[1], radius * normal[2]],
+                normal,
+            )
+        })
+    }
+
+    /// Creates geometry for a torus lying in the XY plane, centered at the
+    /// origin.
+    ///
+    /// * `radius` is the distance from the center of the torus to the
+    ///    center of the tube.
+    /// * `tube_radius` is the radius of the tube.
+    /// * `radial_segments` specifies the number of segments around the
+    ///    cross-section of the tube.
+    /// * `tubular_segments` specifies the number of segments along the tube.
+    ///
+    /// The texture wraps once along the tube, starting from the +X axis, and
+    /// once around it.
+    ///
+    /// ```rust
+    /// # extern crate three;
+    /// fn make_donut() -> three::Geometry {
+    ///     three::Geometry::torus(1.0, 0.4, 12, 32)
+    /// }
+    /// # fn main() { let _ = make_donut(); }
+    /// ```
+    pub fn torus(
+        radius: f32,
+        tube_radius: f32,
+        radial_segments: usize,
+        tubular_segments: usize,
+    ) -> Self {
+        Self::parametric(tubular_segments.max(3), radial_segments.max(3), |u, v| {
+            let (sin_tubular, cos_tubular) = (2.0 * PI * u).sin_cos();
+            let (sin_radial, cos_radial) = (2.0 * PI * v).sin_cos();
+            let normal = [cos_radial * cos_tubular, cos_radial * sin_tubular, sin_radial];
+            (
+                [
+                    (radius + tube_radius * cos_radial) * cos_tubular,
+                    (radius + tube_radius * cos_radial) * sin_tubular,
+                    tube_radius * sin_radial,
+                ],
+                normal,
+            )
+        })
+    }
+
+    /// Returns a copy of `self` with an extra blend shape morphing it into
+    /// `target`.
+    ///
+    /// The new shape is appended to `shapes`, so its weight is the last one
+    /// of the mesh, see [`Object::set_weights`]. Normals and tangents are
+    /// morphed too when both geometries provide them.
+    ///
+    /// Returns `None` if `target` has no vertices, or if `correspondence` is
+    /// `Index` and the vertex counts differ.
+    ///
+    /// Blend shapes are applied on the GPU by the PBR pipeline only. See
+    /// [`Clip::morph`] to animate the transition.
+    ///
+    /// ```rust
+    /// # extern crate three;
+    /// # fn main() {
+    /// use three::Correspondence;
+    ///
+    /// let cube = three::Geometry::cuboid(1.0, 1.0, 1.0);
+    /// let ball = three::Geometry::uv_sphere(0.5, 12, 12);
+    /// let morph = cube.with_morph_target(&ball, Correspondence::Nearest).unwrap();
+    /// assert_eq!(morph.shapes.len(), 1);
+    /// # }
+    /// ```
+    ///
+    /// [`Object::set_weights`]: ../object/trait.Object.html#method.set_weights
+    /// [`Clip::morph`]: ../animation/struct.Clip.html#method.morph
+    pub fn with_morph_target(
+        &self,
+        target: &Geometry,
+        correspondence: Correspondence,
+    ) -> Option<Geometry> {
+        let source = &self.base;
+        let goal = &target.base;
+        if goal.vertices.is_empty() {
+            return None;
+        }
+        let mapping: Vec<usize> = match correspondence {
+            Correspondence::Index => {
+                if source.vertices.len() != goal.vertices.len() {
+                    return None;
+                }
+                (0 .. source.vertices.len()).collect()
+            }
+            Correspondence::Nearest => source
+                .vertices
+                .iter()
+                .map(|&v| {
+                    let v = Vector3::new(v.x, v.y, v.z);
+                    let mut nearest = (0, ::std::f32::INFINITY);
+                    for (i, &w) in goal.vertices.iter().enumerate() {
+                        let distance = (Vector3::new(w.x, w.y, w.z) - v).magnitude2();
+                        if distance < nearest.1 {
+                            nearest = (i, distance);
+                        }
+                    }
+                    nearest.0
+                })
+                .collect(),
+        };
+
+        let vertices = mapping
+            .iter()
+            .zip(&source.vertices)
+            .map(|(&i, v)| {
+                let w = goal.vertices[i];
+                [w.x - v.x, w.y - v.y, w.z - v.z].into()
+            })
+            .collect();
+        let normals = if !source.normals.is_empty() && goal.normals.len() == goal.vertices.len() {
+            mapping
+                .iter()
+                .zip(&source.normals)
+                .map(|(&i, n)| {
+                    let m = goal.normals[i];
+                    [m.x - n.x, m.y - n.y, m.z - n.z].into()
+                })
+                .collect()
+        } else {
+            Vec::new()
+        };
+        let tangents = if !source.tangents.is_empty() && goal.tangents.len() == goal.vertices.len() {
+            mapping
+                .iter()
+                .zip(&source.tangents)
+                .map(|(&i, t)| {
+                    let u = goal.tangents[i];
+                    // the handedness is kept from the base shape
+                    [u.x - t.x, u.y - t.y, u.z - t.z, 0.0].into()
+                })
+                .collect()
+        } else {
+            Vec::new()
+        };
+
+        let mut geometry = self.clone();
+        geometry.shapes.push(Shape {
+            vertices,
+            normals,
+            tangents,
+        });
+        Some(geometry)
     }
 }
@@ -0,0 +1,281 @@
+//! Golden-image testing of rendered scenes.
+//!
+//! A [`Harness`] renders into a hidden window at a fixed size and compares the
+//! result against a reference PNG. When the images differ by more than the
+//! [`Tolerance`], the rendered image and a diff highlighting the mismatched
+//! pixels are written next to the reference.
+//!
+//! Setting the `THREE_UPDATE_GOLDEN` environment variable overwrites the
+//! references with the rendered images instead, e.g. after an intended change.
+//!
+//! Requires the `golden-image` feature.
+//!
+//! ```rust,no_run
+//! use three::golden::{Harness, Tolerance};
+//!
+//! let mut harness = Harness::new(256, 256);
+//! let camera = harness.window.factory.perspective_camera(60.0, 0.1 .. 10.0);
+//! let mesh = harness.window.factory.mesh(
+//!     three::Geometry::cuboid(1.0, 1.0, 1.0),
+//!     three::material::Basic::default(),
+//! );
+//! harness.window.scene.add(&mesh);
+//! harness.check(&camera, "tests/golden/cube.png", &Tolerance::default()).unwrap();
+//! ```
+//!
+//! [`Harness`]: struct.Harness.html
+//! [`Tolerance`]: struct.Tolerance.html
+
+use image::{self, Rgba, RgbaImage};
+
+use camera::Camera;
+use window::Window;
+
+use std::{env, io};
+use std::path::{Path, PathBuf};
+
+/// Environment variable that makes [`check`](fn.check.html) overwrite the references.
+pub const UPDATE_VAR: &str = "THREE_UPDATE_GOLDEN";
+
+quick_error! {
+    #[doc = "Error encountered when checking an image against its reference."]
+    #[derive(Debug)]
+    pub enum Error {
+        #[doc = "Standard I/O error."]
+        Io(err: io::Error) {
+            from()
+            description("I/O error")
+            display("I/O error: {}", err)
+            cause(err)
+        }
+
+        #[doc = "The reference image couldn't be decoded."]
+        Image(err: image::ImageError) {
+            from()
+            description("image decoding error")
+            display("image decoding error: {}", err)
+            cause(err)
+        }
+
+        #[doc = "The rendered image and the reference have different sizes."]
+        Size(expected: (u32, u32), actual: (u32, u32)) {
+            description("image size mismatch")
+            display("image size mismatch: expected {:?}, rendered {:?}", expected, actual)
+        }
+
+        #[doc = "The rendered image differs from the reference."]
+        Mismatch(comparison: Comparison, diff: PathBuf) {
+            description("image mismatch")
+            display(
+                "{} pixels differ by up to {}, see {}",
+                comparison.pixels, comparison.max_difference, diff.display()
+            )
+        }
+    }
+}
+
+/// How much a rendered image may deviate from its reference.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Tolerance {
+    /// Largest difference of any color channel for two pixels to be considered equal.
+    pub channel: u8,
+    /// Number of pixels allowed to differ by more than `channel`.
+    pub pixels: usize,
+}
+
+impl Default for Tolerance {
+    fn default() -> Self {
+        Tolerance {
+            channel: 2,
+            pixels: 0,
+        }
+    }
+}
+
+/// Result of comparing two images of the same size.
+#[derive(Clone, Debug)]
+pub struct Comparison {
+    /// Number of pixels differing by more than the channel tolerance.
+    pub pixels: usize,
+    /// Largest difference of any color channel.
+    pub max_difference: u8,
+    /// Image showing the differing pixels in red over a faded copy of the reference.
+    pub diff: RgbaImage,
+}
+
+impl Comparison {
+    /// Returns `true` if the comparison is within `tolerance`.
+    pub fn passes(
+        &self,
+        tolerance: &Tolerance,
+    ) -> bool {
+        self.pixels <= tolerance.pixels
+    }
+}
+
+/// Compares `actual` with `expected` pixel by pixel.
+///
+/// # Panics
+///
+/// Panics if the images have different sizes.
+pub fn compare(
+    actual: &RgbaImage,
+    expected: &RgbaImage,
+    tolerance: &Tolerance,
+) -> Comparison {
+    assert_eq!(actual.dimensions(), expected.dimensions());
+    let mut pixels = 0;
+    let mut max_difference = 0;
+    let diff = RgbaImage::from_fn(actual.width(), actual.height(), |x, y| {
+        let a = actual.get_pixel(x, y).data;
+        let e = expected.get_pixel(x, y).data;
+        let difference = (0 .. 4)
+            .map(|i| (a[i] as i16 - e[i] as i16).abs() as u8)
+            .max()
+            .unwrap();
+        max_difference = max_difference.max(difference);
+        if difference > tolerance.channel {
+            pixels += 1;
+            Rgba { data: [0xFF, 0, 0, 0xFF] }
+        } else {
+            let luma = (e[0] as u32 + e[1] as u32 + e[2] as u32) / 12;
+            Rgba { data: [luma as u8, luma as u8, luma as u8, 0xFF] }
+        }
+    });
+    Comparison {
+        pixels,
+        max_difference,
+        diff,
+    }
+}
+
+/// Checks `actual` against the reference PNG at `reference`.
+///
+/// On mismatch, writes `<name>.actual.png` and `<name>.diff.png` next to the
+/// reference. If `THREE_UPDATE_GOLDEN` is set, writes `actual` as the new
+/// reference instead.
+pub fn check<P: AsRef<Path>>(
+    actual: &RgbaImage,
+    reference: P,
+    tolerance: &Tolerance,
+) -> Result<(), Error> {
+    let reference = reference.as_ref();
+    if env::var_os(UPDATE_VAR).is_some() {
+        actual.save(reference)?;
+        return Ok(());
+    }
+
+    let expected = image::open(reference)?.to_rgba();
+    if expected.dimensions() != actual.dimensions() {
+        return Err(Error::Size(expected.dimensions(), actual.dimensions()));
+    }
+    let comparison = compare(actual, &expected, tolerance);
+    if comparison.passes(tolerance) {
+        return Ok(());
+    }
+
+    let diff = reference.with_extension("diff.png");
+    actual.save(reference.with_extension("actual.png"))?;
+    comparison.diff.save(&diff)?;
+    Err(Error::Mismatch(comparison, diff))
+}
+
+/// Renders scenes into a hidden window of fixed size.
+pub struct Harness {
+    /// The hidden window providing the scene and the factory.
+    pub window: Window,
+    width: u32,
+    height: u32,
+}
+
+impl Harness {
+    /// Creates a hidden window rendering images of `width` x `height` pixels.
+    pub fn new(
+        width: u32,
+        height: u32,
+    ) -> Self {
+        let window = Window::builder("three-rs golden image")
+            .dimensions(width, height)
+            .visible(false)
+            .vsync(false)
+            .build();
+        Harness {
+            window,
+            width,
+            height,
+        }
+    }
+
+    /// Renders the scene of the window as seen by `camera`.
+    pub fn render(
+        &mut self,
+        camera: &Camera,
+    ) -> RgbaImage {
+        let window = &mut self.window;
+        window
+            .renderer
+            .render_to_image(&window.scene, camera, self.width, self.height)
+    }
+
+    /// Renders the scene as seen by `camera` and checks it against the
+    /// reference PNG at `reference`. See [`check`](fn.check.html).
+    pub fn check<P: AsRef<Path>>(
+        &mut self,
+        camera: &Camera,
+        reference: P,
+        tolerance: &Tolerance,
+    ) -> Result<(), Error> {
+        let image = self.render(camera);
+        check(&image, reference, tolerance)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn image(color: [u8; 4]) -> RgbaImage {
+        RgbaImage::from_pixel(4, 4, Rgba { data: color })
+    }
+
+    #[test]
+    fn identical() {
+        let expected = image([10, 20, 30, 255]);
+        let comparison = compare(&expected, &expected, &Tolerance::default());
+        assert_eq!(comparison.pixels, 0);
+        assert_eq!(comparison.max_difference, 0);
+        assert!(comparison.passes(&Tolerance::default()));
+    }
+
+    #[test]
+    fn within_tolerance() {
+        let expected = image([10, 20, 30, 255]);
+        let mut actual = expected.clone();
+        actual.put_pixel(1, 2, Rgba { data: [12, 19, 30, 255] });
+        let comparison = compare(&actual, &expected, &Tolerance::default());
+        assert_eq!(comparison.pixels, 0);
+        assert_eq!(comparison.max_difference, 2);
+        assert!(comparison.passes(&Tolerance::default()));
+    }
+
+    #[test]
+    fn over_tolerance() {
+        let expected = image([10, 20, 30, 255]);
+        let mut actual = expected.clone();
+        actual.put_pixel(1, 2, Rgba { data: [10, 20, 40, 255] });
+        let comparison = compare(&actual, &expected, &Tolerance::default());
+        assert_eq!(comparison.pixels, 1);
+        assert_eq!(comparison.max_difference, 10);
+        assert_eq!(comparison.diff.get_pixel(1, 2).data, [0xFF, 0, 0, 0xFF]);
+        assert!(!comparison.passes(&Tolerance::default()));
+        assert!(comparison.passes(&Tolerance { channel: 2, pixels: 1 }));
+    }
+
+    #[test]
+    #[should_panic]
+    fn size_mismatch() {
+        let expected = image([10, 20, 30, 255]);
+        let actual = RgbaImage::from_pixel(4, 2, Rgba { data: [10, 20, 30, 255] });
+        compare(&actual, &expected, &Tolerance::default());
+    }
+}
@@ -0,0 +1,190 @@
+//! Debug helpers visualizing light sources.
+//!
+//! A [`LightHelper`] draws line shapes at the position of a light: an arrow for
+//! directional and hemisphere lights pointing in the direction the light travels,
+//! and three circles for point lights showing the chosen range. Helpers are
+//! collected in a [`LightHelpers`] group so they can be toggled all at once.
+//!
+//! ```rust,no_run
+//! # let mut window = three::Window::new("");
+//! # let camera = window.factory.perspective_camera(60.0, 0.1 .. 10.0);
+//! let light = window.factory.point_light(0xffffff, 1.0);
+//! window.scene.add(&light);
+//! let mut helpers = window.factory.light_helpers();
+//! let helper = window.factory.light_helper(&light, 2.0);
+//! helpers.add(helper);
+//! window.scene.add(&helpers);
+//! while window.update() {
+//!     if window.input.hit(three::Key::H) {
+//!         helpers.toggle();
+//!     }
+//!     helpers.update(&mut window.scene);
+//!     window.render(&camera);
+//! }
+//! ```
+//!
+//! [`LightHelper`]: struct.LightHelper.html
+//! [`LightHelpers`]: struct.LightHelpers.html
+
+use std::f32::consts::PI;
+
+use mint;
+
+use mesh::Mesh;
+use object::{Base, Group, Object};
+use scene::Scene;
+
+/// Number of segments used to draw the circles of a point light helper.
+const CIRCLE_SEGMENTS: usize = 32;
+
+/// Line shapes displaying the position and direction of a light.
+///
+/// Created with [`Factory::light_helper`].
+///
+/// [`Factory::light_helper`]: ../struct.Factory.html#method.light_helper
+#[derive(Clone, Debug)]
+pub struct LightHelper {
+    group: Group,
+    light: Base,
+    shapes: Vec<Mesh>,
+}
+three_object!(LightHelper::group);
+
+impl LightHelper {
+    pub(crate) fn new(
+        group: Group,
+        light: Base,
+        shapes: Vec<Mesh>,
+    ) -> Self {
+        for shape in &shapes {
+            group.add(shape);
+        }
+        LightHelper {
+            group,
+            light,
+            shapes,
+        }
+    }
+
+    /// Returns the light visualized by this helper.
+    pub fn light(&self) -> &Base {
+        &self.light
+    }
+}
+
+/// A group of [`LightHelper`]s that follow their lights.
+///
+/// Hiding the group with [`set_visible`] or [`toggle`] hides all helpers at once.
+///
+/// Created with [`Factory::light_helpers`].
+///
+/// [`LightHelper`]: struct.LightHelper.html
+/// [`set_visible`]: ../object/trait.Object.html#method.set_visible
+/// [`toggle`]: #method.toggle
+/// [`Factory::light_helpers`]: ../struct.Factory.html#method.light_helpers
+#[derive(Clone, Debug)]
+pub struct LightHelpers {
+    group: Group,
+    helpers: Vec<LightHelper>,
+    visible: bool,
+}
+three_object!(LightHelpers::group);
+
+impl LightHelpers {
+    pub(crate) fn new(group: Group) -> Self {
+        LightHelpers {
+            group,
+            helpers: Vec::new(),
+            visible: true,
+        }
+    }
+
+    /// Adds `helper` to the group.
+    pub fn add(
+        &mut self,
+        helper: LightHelper,
+    ) {
+        self.group.add(&helper);
+        self.helpers.push(helper);
+    }
+
+    /// Removes the helpers of `light` from the group.
+    pub fn remove<T: Object>(
+        &mut self,
+        light: &T,
+    ) {
+        let light = light.upcast();
+        let group = &self.group;
+        self.helpers.retain(|helper| {
+            let keep = helper.light != light;
+            if !keep {
+                group.remove(helper);
+            }
+            keep
+        });
+    }
+
+    /// Returns the helpers in the group.
+    pub fn helpers(&self) -> &[LightHelper] {
+        &self.helpers
+    }
+
+    /// Shows the helpers if they are hidden, hides them otherwise.
+    pub fn toggle(&mut self) {
+        self.visible = !self.visible;
+        self.group.set_visible(self.visible);
+    }
+
+    /// Moves each helper to the world transform of its light.
+    ///
+    /// Call this each frame before rendering, or whenever lights have moved.
+    /// The group itself is expected to be a direct child of the scene.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a light isn't part of `scene`.
+    pub fn update(
+        &self,
+        scene: &mut Scene,
+    ) {
+        let transforms = {
+            let guard = scene.sync_guard();
+            self.helpers
+                .iter()
+                .map(|helper| guard.resolve_world(&helper.light).transform)
+                .collect::<Vec<_>>()
+        };
+        for (helper, transform) in self.helpers.iter().zip(transforms) {
+            helper.set_transform(transform.position, transform.orientation, transform.scale);
+        }
+    }
+}
+
+/// Returns the line strip of an arrow of the given `length` pointing along
+/// the local negative Z axis, starting from a square marking the light.
+pub(crate) fn arrow(length: f32) -> Vec<mint::Point3<f32>> {
+    let half = 0.1 * length;
+    let head = 0.15 * length;
+    vec![
+        [-half, -half, 0.0].into(),
+        [half, -half, 0.0].into(),
+        [half, half, 0.0].into(),
+        [-half, half, 0.0].into(),
+        [-half, -half, 0.0].into(),
+        [0.0, 0.0, 0.0].into(),
+        [0.0, 0.0, -length].into(),
+        [-0.5 * head, 0.0, head - length].into(),
+        [0.5 * head, 0.0, head - length].into(),
+        [0.0, 0.0, -length].into(),
+    ]
+}
+
+/// Returns the closed line strip of a circle of the given `radius` in the local XY plane.
+pub(crate) fn circle(radius: f32) -> Vec<mint::Point3<f32>> {
+    (0 .. CIRCLE_SEGMENTS + 1)
+        .map(|i| {
+            let angle = 2.0 * PI * i as f32 / CIRCLE_SEGMENTS as f32;
+            [radius * angle.cos(), radius * angle.sin(), 0.0].into()
+        })
+        .collect()
+}
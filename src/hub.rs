@@ -1,21 +1,30 @@
+use animation::Lod;
+#[cfg(feature = "audio")]
 use audio::{AudioData, Operation as AudioOperation};
 use camera::Projection;
 use color::{self, Color};
 use light::{ShadowMap, ShadowProjection};
-use material::Material;
-use mesh::DynamicMesh;
+use material::{self, Material};
+use mesh::{BeforeRenderCallback, DynamicMesh, Fade};
 use node::{NodeInternal, NodePointer, TransformInternal};
 use object::Base;
-use render::{BackendResources, GpuData};
+use probe::{Operation as ProbeOperation, ProbeData};
+use render::{BackendResources, GpuData, TextureUpload};
+use render::generator::VertexJob;
+use scene::Stats;
 use skeleton::{Bone, Skeleton};
 use text::{Operation as TextOperation, TextData};
+use virtual_texture::Tile;
 
+use cgmath;
 use cgmath::Transform;
 use froggy;
 use gfx;
 use mint;
 
-use std::{mem, ops};
+use std::{mem, ops, time};
+use std::cell::Cell;
+use std::collections::{HashMap, HashSet};
 use std::sync::{Arc, Mutex};
 use std::sync::mpsc;
 
@@ -34,6 +43,7 @@ pub(crate) struct LightData {
     pub intensity: f32,
     pub sub_light: SubLight,
     pub shadow: Option<(ShadowMap, ShadowProjection)>,
+    pub layers: u32,
 }
 
 #[derive(Clone, Debug)]
@@ -41,6 +51,8 @@ pub(crate) struct SkeletonData {
     pub bones: Vec<Bone>,
     pub gpu_buffer_view: gfx::handle::ShaderResourceView<BackendResources, [f32; 4]>,
     pub gpu_buffer: gfx::handle::Buffer<BackendResources, [f32; 4]>,
+    pub lod: Lod,
+    pub last_update: Option<f32>,
 }
 
 #[derive(Clone, Debug)]
@@ -57,6 +69,7 @@ pub(crate) enum SubNode {
     /// Group can be a parent to other objects.
     Group { first_child: Option<NodePointer> },
     /// Audio data.
+    #[cfg(feature = "audio")]
     Audio(AudioData),
     /// Renderable text for 2D user interface.
     UiText(TextData),
@@ -68,6 +81,8 @@ pub(crate) enum SubNode {
     Bone { index: usize, inverse_bind_matrix: mint::ColumnMatrix4<f32> },
     /// Skeleton root.
     Skeleton(SkeletonData),
+    /// Reflection probe volume and its capture.
+    Probe(ProbeData),
 }
 
 pub(crate) type Message = (froggy::WeakPointer<NodeInternal>, Operation);
@@ -76,21 +91,30 @@ pub(crate) type Message = (froggy::WeakPointer<NodeInternal>, Operation);
 pub(crate) enum Operation {
     AddChild(NodePointer),
     RemoveChild(NodePointer),
+    #[cfg(feature = "audio")]
     SetAudio(AudioOperation),
     SetVisible(bool),
+    SetOpacity(f32),
     SetText(TextOperation),
     SetTransform(
         Option<mint::Point3<f32>>,
         Option<mint::Quaternion<f32>>,
         Option<f32>,
     ),
+    SetNonUniformScale(mint::Vector3<f32>),
     SetMaterial(Material),
+    SetRenderState(material::State),
     SetSkeleton(Skeleton),
+    SetSkeletonLod(Lod),
     SetShadow(ShadowMap, ShadowProjection),
+    SetLightMask(u32),
+    SetFade(Fade),
+    SetProbe(ProbeOperation),
     SetTexelRange(mint::Point2<i16>, mint::Vector2<u16>),
     SetWeights(Vec<f32>),
     SetName(String),
     SetProjection(Projection),
+    SetBeforeRender(Option<BeforeRenderCallback>),
 }
 
 pub(crate) type HubPtr = Arc<Mutex<Hub>>;
@@ -99,6 +123,26 @@ pub(crate) struct Hub {
     pub(crate) nodes: froggy::Storage<NodeInternal>,
     pub(crate) message_tx: mpsc::Sender<Message>,
     message_rx: mpsc::Receiver<Message>,
+    /// Counters accumulated since the start of the current frame.
+    stats: Stats,
+    /// Nodes visited by the world transform updates since the start of the
+    /// current frame.
+    walked: Cell<usize>,
+    /// Time spent in the world transform updates since the start of the
+    /// current frame, in seconds.
+    transform_time: Cell<f32>,
+    /// Counters of the last completed frame.
+    frame_stats: Stats,
+    /// Incremented by changes to the nodes seen by a walk of the scene.
+    revision: u64,
+    /// Texture regions to upload with the next render.
+    pub(crate) texture_uploads: Vec<TextureUpload>,
+    /// Generator passes to run with the next render.
+    pub(crate) vertex_jobs: Vec<VertexJob>,
+    /// Textures to generate the mipmap chain of with the next render.
+    pub(crate) mipmaps: Vec<gfx::handle::RawShaderResourceView<BackendResources>>,
+    /// Tiles read back from the feedback pass, by virtual texture id.
+    pub(crate) virtual_feedback: HashMap<u8, HashSet<Tile>>,
 }
 
 impl<T: AsRef<Base>> ops::Index<T> for Hub {
@@ -123,6 +167,15 @@ impl Hub {
             nodes: froggy::Storage::new(),
             message_tx: tx,
             message_rx: rx,
+            stats: Stats::default(),
+            walked: Cell::new(0),
+            transform_time: Cell::new(0.0),
+            frame_stats: Stats::default(),
+            revision: 0,
+            texture_uploads: Vec::new(),
+            vertex_jobs: Vec::new(),
+            mipmaps: Vec::new(),
+            virtual_feedback: HashMap::new(),
         };
         Arc::new(Mutex::new(hub))
     }
@@ -168,13 +221,111 @@ impl Hub {
         }
     }
 
-    pub(crate) fn process_messages(&mut self) {
-        while let Ok((weak_ptr, operation)) = self.message_rx.try_recv() {
+    /// Ends the statistics of the previous frame and starts counting a new one.
+    pub(crate) fn begin_frame(&mut self) {
+        self.frame_stats = Stats {
+            nodes_walked: self.walked.get(),
+            transform_time: self.transform_time.get(),
+            ..self.stats
+        };
+        self.stats = Stats::default();
+        self.walked.set(0);
+        self.transform_time.set(0.0);
+    }
+
+    /// Records an update of the world transforms visiting `nodes` nodes in
+    /// `duration`.
+    pub(crate) fn record_transform_update(
+        &self,
+        nodes: usize,
+        duration: time::Duration,
+    ) {
+        self.walked.set(self.walked.get() + nodes);
+        let seconds = duration.as_secs() as f32 + 1e-9 * duration.subsec_nanos() as f32;
+        self.transform_time.set(self.transform_time.get() + seconds);
+    }
+
+    /// Clears the `rendered` flags of the nodes, at the start of a frame.
+    pub(crate) fn reset_rendered(&mut self) {
+        for node in self.nodes.iter_mut() {
+            node.rendered = false;
+        }
+    }
+
+    /// Returns the counters of the last completed frame.
+    pub(crate) fn frame_stats(&self) -> Stats {
+        self.frame_stats
+    }
+
+    /// Returns the counter of changes to the layout of the nodes.
+    pub(crate) fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    /// Records a change to the nodes seen by a walk of the scene, e.g. their
+    /// opacity.
+    pub(crate) fn touch(&mut self) {
+        self.revision += 1;
+    }
+
+    /// Records a change that may move the world bounds of `ptr` and its
+    /// children.
+    pub(crate) fn touch_node(
+        &mut self,
+        ptr: &NodePointer,
+    ) {
+        self.revision += 1;
+        self.nodes[ptr].revision = self.revision;
+    }
+
+    /// Drains the message queue, dropping operations overwritten by a later
+    /// operation of the same kind on the same node.
+    ///
+    /// Partial transform changes are merged into the latest transform change,
+    /// so that e.g. a position change isn't lost by a subsequent rotation change.
+    fn drain_messages(&mut self) -> Vec<(NodePointer, Operation)> {
+        let mut messages: Vec<Option<(NodePointer, Operation)>> = Vec::new();
+        let mut latest = HashMap::new();
+        while let Ok((weak_ptr, mut operation)) = self.message_rx.try_recv() {
+            self.stats.messages += 1;
             let ptr = match weak_ptr.upgrade() {
                 Ok(ptr) => ptr,
                 Err(_) => continue,
             };
+            let kind = match operation {
+                Operation::SetTransform(..) => 0,
+                Operation::SetNonUniformScale(_) => 1,
+                Operation::SetVisible(_) => 2,
+                Operation::SetMaterial(_) => 3,
+                Operation::SetRenderState(_) => 4,
+                Operation::SetName(_) => 5,
+                Operation::SetOpacity(_) => 6,
+                _ => {
+                    messages.push(Some((ptr, operation)));
+                    continue;
+                }
+            };
+            if let Some(index) = latest.insert((ptr.clone(), kind), messages.len()) {
+                let (_, previous) = messages[index].take().unwrap();
+                if let Operation::SetTransform(pos, rot, scale) = previous {
+                    if let Operation::SetTransform(ref mut new_pos, ref mut new_rot, ref mut new_scale) = operation {
+                        *new_pos = new_pos.or(pos);
+                        *new_rot = new_rot.or(rot);
+                        *new_scale = new_scale.or(scale);
+                    }
+                }
+                self.stats.coalesced += 1;
+            }
+            messages.push(Some((ptr, operation)));
+        }
+        messages.into_iter().filter_map(|message| message).collect()
+    }
+
+    pub(crate) fn process_messages(&mut self) {
+        let start = time::Instant::now();
+        for (ptr, operation) in self.drain_messages() {
             match operation {
+                #[cfg(feature = "audio")]
                 Operation::SetAudio(operation) => {
                     if let SubNode::Audio(ref mut data) = self.nodes[&ptr].sub_node {
                         Hub::process_audio(operation, data);
@@ -182,8 +333,14 @@ impl Hub {
                 },
                 Operation::SetVisible(visible) => {
                     self.nodes[&ptr].visible = visible;
+                    self.touch_node(&ptr);
+                }
+                Operation::SetOpacity(opacity) => {
+                    self.nodes[&ptr].opacity = opacity;
+                    self.touch();
                 }
                 Operation::SetTransform(pos, rot, scale) => {
+                    self.touch_node(&ptr);
                     let transform = &mut self.nodes[&ptr].transform;
                     if let Some(pos) = pos {
 
@@ -208,35 +365,18 @@ impl Hub {
                             child.sub_node, "discarding siblings");
                     }
                     child.next_sibling = sibling;
+                    self.touch_node(&child_ptr);
                 }
                 Operation::RemoveChild(child_ptr) => {
-                    let next_sibling = self.nodes[&child_ptr].next_sibling.clone();
-                    let target_maybe = Some(child_ptr);
-                    let mut cur_ptr = match self.nodes[&ptr].sub_node {
-                        SubNode::Group { ref mut first_child } => {
-                            if *first_child == target_maybe {
-                                *first_child = next_sibling;
-                                continue;
-                            }
-                            first_child.clone()
-                        }
+                    let mut first_child = match self.nodes[&ptr].sub_node {
+                        SubNode::Group { ref mut first_child } => first_child.take(),
                         _ => unreachable!()
                     };
-
-                    //TODO: consolidate the code with `Scene::remove()`
-                    loop {
-                        let node = match cur_ptr.take() {
-                            Some(next_ptr) => &mut self.nodes[&next_ptr],
-                            None => {
-                                error!("Unable to find child for removal");
-                                break;
-                            }
-                        };
-                        if node.next_sibling == target_maybe {
-                            node.next_sibling = next_sibling;
-                            break;
-                        }
-                        cur_ptr = node.next_sibling.clone(); //TODO: avoid clone
+                    if !self.unlink(&mut first_child, &child_ptr) {
+                        error!("Unable to find child for removal");
+                    }
+                    if let SubNode::Group { first_child: ref mut slot } = self.nodes[&ptr].sub_node {
+                        *slot = first_child;
                     }
                 }
                 Operation::SetText(operation) => {
@@ -247,6 +387,10 @@ impl Hub {
                         _ => unreachable!()
                     }
                 }
+                Operation::SetNonUniformScale(scale) => {
+                    self.nodes[&ptr].nonuniform_scale = scale.into();
+                    self.touch_node(&ptr);
+                }
                 Operation::SetMaterial(material) => {
                     match self.nodes[&ptr].sub_node {
                         SubNode::Visual(ref mut mat, _, _) => {
@@ -255,6 +399,17 @@ impl Hub {
                         _ => unreachable!()
                     }
                 }
+                Operation::SetRenderState(state) => {
+                    match self.nodes[&ptr].sub_node {
+                        SubNode::Visual(_, ref mut gpu_data, _) => {
+                            gpu_data.render_state = state;
+                            if let Some(ref mut key) = gpu_data.instance_cache_key {
+                                key.state = state;
+                            }
+                        }
+                        _ => unreachable!()
+                    }
+                }
                 Operation::SetSkeleton(sleketon) => {
                     match self.nodes[&ptr].sub_node {
                         SubNode::Visual(_, _, ref mut skel) => {
@@ -262,6 +417,15 @@ impl Hub {
                         }
                         _ => unreachable!()
                     }
+                    self.touch_node(&ptr);
+                }
+                Operation::SetSkeletonLod(lod) => {
+                    match self.nodes[&ptr].sub_node {
+                        SubNode::Skeleton(ref mut data) => {
+                            data.lod = lod;
+                        }
+                        _ => unreachable!()
+                    }
                 }
                 Operation::SetShadow(map, proj) => {
                     match self.nodes[&ptr].sub_node {
@@ -271,6 +435,30 @@ impl Hub {
                     _ => unreachable!()
                     }
                 }
+                Operation::SetLightMask(mask) => {
+                    match self.nodes[&ptr].sub_node {
+                        SubNode::Light(ref mut data) => {
+                            data.layers = mask;
+                        }
+                        SubNode::Visual(_, ref mut gpu_data, _) => {
+                            gpu_data.light_mask = mask;
+                        }
+                        _ => unreachable!()
+                    }
+                }
+                Operation::SetFade(fade) => {
+                    match self.nodes[&ptr].sub_node {
+                        SubNode::Visual(_, ref mut gpu_data, _) => {
+                            gpu_data.fade = fade;
+                        }
+                        _ => unreachable!()
+                    }
+                }
+                Operation::SetProbe(operation) => {
+                    if let SubNode::Probe(ref mut data) = self.nodes[&ptr].sub_node {
+                        Hub::process_probe(operation, data);
+                    }
+                }
                 Operation::SetTexelRange(base, size) => {
                     match self.nodes[&ptr].sub_node {
                         SubNode::Visual(Material::Sprite(ref mut params), _, _) => {
@@ -312,6 +500,9 @@ impl Hub {
                 Operation::SetName(name) => {
                     self.nodes[&ptr].name = Some(name);
                 }
+                Operation::SetBeforeRender(callback) => {
+                    self.nodes[&ptr].on_before_render = callback;
+                }
                 Operation::SetProjection(projection) => {
                     match self.nodes[&ptr].sub_node {
                         SubNode::Camera(ref mut internal_projection) => {
@@ -324,8 +515,11 @@ impl Hub {
         }
 
         self.nodes.sync_pending();
+        let dt = start.elapsed();
+        self.stats.process_time += dt.as_secs() as f32 + 1e-9 * dt.subsec_nanos() as f32;
     }
 
+    #[cfg(feature = "audio")]
     fn process_audio(
         operation: AudioOperation,
         data: &mut AudioData,
@@ -339,24 +533,81 @@ impl Hub {
         }
     }
 
+    fn process_probe(
+        operation: ProbeOperation,
+        data: &mut ProbeData,
+    ) {
+        match operation {
+            ProbeOperation::Extent(extent) => data.extent = extent,
+            ProbeOperation::BlendDistance(distance) => data.blend_distance = distance,
+            ProbeOperation::Intensity(intensity) => data.intensity = intensity,
+            ProbeOperation::Range(range) => data.range = range,
+        }
+    }
+
     fn process_text(
         operation: TextOperation,
         data: &mut TextData,
     ) {
-        use gfx_glyph::Scale;
+        use gfx_glyph::{OwnedSectionText, Scale};
         match operation {
             TextOperation::Color(color) => {
                 let rgb = color::to_linear_rgb(color);
-                data.section.text[0].color = [rgb[0], rgb[1], rgb[2], 1.0];
+                for text in &mut data.section.text {
+                    text.color = [rgb[0], rgb[1], rgb[2], text.color[3]];
+                }
+            }
+            TextOperation::Font(font) => {
+                data.font = font;
+                data.update_fonts();
+            }
+            TextOperation::Layout(layout) => {
+                data.layout = layout;
+                data.section.layout = layout.into();
+            }
+            TextOperation::Opacity(opacity) => {
+                for text in &mut data.section.text {
+                    text.color[3] = opacity;
+                }
             }
-            TextOperation::Font(font) => data.font = font,
-            TextOperation::Layout(layout) => data.layout = layout,
-            TextOperation::Opacity(opacity) => data.section.text[0].color[3] = opacity,
+            TextOperation::Outline(outline) => data.outline = outline,
             TextOperation::Pos(point) => data.section.screen_position = (point.x, point.y),
-            // TODO: somehow grab window::hdpi_factor and multiply size
-            TextOperation::Scale(scale) => data.section.text[0].scale = Scale::uniform(scale),
+            TextOperation::Scale(scale) => {
+                for text in &mut data.section.text {
+                    text.scale = Scale::uniform(scale);
+                }
+            }
+            TextOperation::Shadow(shadow) => data.shadow = shadow,
             TextOperation::Size(size) => data.section.bounds = (size.x, size.y),
-            TextOperation::Text(text) => data.section.text[0].text = text,
+            TextOperation::Spans(spans) => {
+                data.section.text.clear();
+                data.span_fonts.clear();
+                for span in spans {
+                    let rgb = color::to_linear_rgb(span.color);
+                    data.section.text.push(OwnedSectionText {
+                        text: span.text,
+                        scale: Scale::uniform(span.font_size),
+                        color: [rgb[0], rgb[1], rgb[2], span.opacity],
+                        ..OwnedSectionText::default()
+                    });
+                    data.span_fonts.push(span.font);
+                }
+                data.update_fonts();
+            }
+            TextOperation::WorldScale(scale) => data.world_scale = scale,
+            TextOperation::Text(text) => {
+                data.section.text.truncate(1);
+                data.span_fonts.truncate(1);
+                if data.section.text.is_empty() {
+                    data.section.text.push(OwnedSectionText {
+                        color: [1.0, 1.0, 1.0, 1.0],
+                        ..OwnedSectionText::default()
+                    });
+                    data.span_fonts.push(None);
+                }
+                data.section.text[0].text = text;
+                data.update_fonts();
+            }
         }
     }
 
@@ -370,6 +621,111 @@ impl Hub {
         }
     }
 
+    /// Removes `target` from the list of siblings starting at `first_child`.
+    ///
+    /// Returns `false` if `target` isn't in the list.
+    pub(crate) fn unlink(
+        &mut self,
+        first_child: &mut Option<NodePointer>,
+        target: &NodePointer,
+    ) -> bool {
+        self.touch_node(target);
+        let target_maybe = Some(target.clone());
+        let next_sibling = self.nodes[target].next_sibling.take();
+        if *first_child == target_maybe {
+            *first_child = next_sibling;
+            return true;
+        }
+
+        let mut cur_ptr = first_child.clone();
+        while let Some(ptr) = cur_ptr.take() {
+            let node = &mut self.nodes[&ptr];
+            if node.next_sibling == target_maybe {
+                node.next_sibling = next_sibling;
+                return true;
+            }
+            cur_ptr = node.next_sibling.clone(); //TODO: avoid clone
+        }
+
+        self.nodes[target].next_sibling = next_sibling;
+        false
+    }
+
+    /// Removes `target` from its parent, which is either the scene starting at
+    /// `first_child` or a group within it.
+    ///
+    /// Returns `false` if `target` isn't in the scene.
+    pub(crate) fn detach(
+        &mut self,
+        first_child: &mut Option<NodePointer>,
+        target: &NodePointer,
+    ) -> bool {
+        if self.unlink(first_child, target) {
+            return true;
+        }
+        let groups = self.walk_all(first_child)
+            .filter(|w| match w.node.sub_node {
+                SubNode::Group { .. } => true,
+                _ => false,
+            })
+            .map(|w| w.node_ptr)
+            .collect::<Vec<_>>();
+        for group in groups {
+            let mut children = match self.nodes[&group].sub_node {
+                SubNode::Group { ref mut first_child } => first_child.take(),
+                _ => unreachable!(),
+            };
+            let found = self.unlink(&mut children, target);
+            if let SubNode::Group { ref mut first_child } = self.nodes[&group].sub_node {
+                *first_child = children;
+            }
+            if found {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Computes the skinning matrices of `skeleton`, in bone index order.
+    ///
+    /// Each matrix maps a vertex in bind pose to its current position in the
+    /// local space of the skeleton, matching the palette uploaded for GPU skinning.
+    /// Bones that aren't found under `base` keep an identity matrix.
+    pub(crate) fn joint_matrices(
+        &self,
+        base: &Option<NodePointer>,
+        skeleton: &NodePointer,
+    ) -> Vec<cgmath::Matrix4<f32>> {
+        use cgmath::SquareMatrix;
+
+        let bones = match self.nodes[skeleton].sub_node {
+            SubNode::Skeleton(ref data) => &data.bones,
+            _ => panic!("Not a skeleton"),
+        };
+        let mut inverse_world = None;
+        let mut bone_transforms = vec![None; bones.len()];
+        for w in self.walk_all(base) {
+            if w.node_ptr == *skeleton {
+                inverse_world = w.world_matrix().invert();
+            } else if let SubNode::Bone { index, inverse_bind_matrix } = w.node.sub_node {
+                if bones.get(index).map_or(false, |bone| bone.object.node == w.node_ptr) {
+                    bone_transforms[index] = Some((w.world_matrix(), inverse_bind_matrix));
+                }
+            }
+        }
+
+        let inverse_world = inverse_world.unwrap_or_else(cgmath::Matrix4::identity);
+        bone_transforms
+            .into_iter()
+            .map(|bone| match bone {
+                Some((world, inverse_bind_matrix)) => {
+                    inverse_world * world * cgmath::Matrix4::from(inverse_bind_matrix)
+                }
+                None => cgmath::Matrix4::identity(),
+            })
+            .collect()
+    }
+
     fn walk_impl(
         &self, base: &Option<NodePointer>, only_visible: bool
     ) -> TreeWalker {
@@ -397,7 +753,23 @@ pub(crate) struct WalkedNode<'a> {
     pub(crate) node_ptr: NodePointer,
     pub(crate) node: &'a NodeInternal,
     pub(crate) world_visible: bool,
+    pub(crate) world_opacity: f32,
     pub(crate) world_transform: TransformInternal,
+    /// `world_transform` with the per-axis scales of the node and its ancestors.
+    pub(crate) world_stretched: cgmath::Matrix4<f32>,
+    /// `true` if the node or one of its ancestors has a per-axis scale.
+    pub(crate) stretched: bool,
+    /// Latest revision of the hub at which the node or one of its ancestors
+    /// may have moved.
+    pub(crate) revision: u64,
+}
+
+impl<'a> WalkedNode<'a> {
+    /// World matrix of the node, including the per-axis scales of the node
+    /// and its ancestors.
+    pub(crate) fn world_matrix(&self) -> cgmath::Matrix4<f32> {
+        self.world_stretched
+    }
 }
 
 pub(crate) struct TreeWalker<'a> {
@@ -416,18 +788,30 @@ impl<'a> TreeWalker<'a> {
         let mut node = &self.hub.nodes[ptr];
 
         loop {
+            let scale = node.nonuniform_scale;
+            let local_stretched = scale != cgmath::Vector3::new(1.0, 1.0, 1.0);
+            let local_matrix = cgmath::Matrix4::from(node.transform) *
+                cgmath::Matrix4::from_nonuniform_scale(scale.x, scale.y, scale.z);
             let wn = match self.stack.last() {
                 Some(parent) => WalkedNode {
                     node_ptr: ptr.clone(),
                     node,
                     world_visible: parent.world_visible && node.visible,
+                    world_opacity: parent.world_opacity * node.opacity,
                     world_transform: parent.world_transform.concat(&node.transform),
+                    world_stretched: parent.world_stretched * local_matrix,
+                    stretched: parent.stretched || local_stretched,
+                    revision: parent.revision.max(node.revision),
                 },
                 None => WalkedNode {
                     node_ptr: ptr.clone(),
                     node,
                     world_visible: node.visible,
+                    world_opacity: node.opacity,
                     world_transform: node.transform,
+                    world_stretched: local_matrix,
+                    stretched: local_stretched,
+                    revision: node.revision,
                 },
             };
             self.stack.push(wn);
@@ -7,10 +7,11 @@ use std::time;
 
 mod timer;
 pub mod axis;
+pub mod record;
 
 pub use self::axis::{AXIS_DOWN_UP, AXIS_LEFT_RIGHT};
 
-pub use self::timer::Timer;
+pub use self::timer::{Timer, UpdateTimer};
 
 const PIXELS_PER_LINE: f32 = 38.0;
 
@@ -23,15 +24,24 @@ struct State {
     mouse_pressed: HashSet<MouseButton>,
     mouse_pos: mint::Point2<f32>,
     mouse_pos_ndc: mint::Point2<f32>,
+    time_scale: f32,
+    paused: bool,
+    pending_step: Option<TimerDuration>,
+    fixed_timestep: Option<TimerDuration>,
+    replayed_delta: Option<TimerDuration>,
+    update_time: f64,
 }
 
 struct Diff {
     time_delta: TimerDuration,
+    real_time_delta: TimerDuration,
     keys_hit: Vec<Key>,
     mouse_moves: Vec<mint::Vector2<f32>>,
     mouse_moves_ndc: Vec<mint::Vector2<f32>>,
     axes_raw: Vec<(u8, f32)>,
     mouse_hit: Vec<MouseButton>,
+    mouse_released: Vec<MouseButton>,
+    keys_released: Vec<Key>,
     mouse_wheel: Vec<f32>,
 }
 
@@ -50,14 +60,23 @@ impl Input {
             mouse_pressed: HashSet::new(),
             mouse_pos: [0.0; 2].into(),
             mouse_pos_ndc: [0.0; 2].into(),
+            time_scale: 1.0,
+            paused: false,
+            pending_step: None,
+            fixed_timestep: None,
+            replayed_delta: None,
+            update_time: 0.0,
         };
         let delta = Diff {
             time_delta: 0.0,
+            real_time_delta: 0.0,
             keys_hit: Vec::new(),
             mouse_moves: Vec::new(),
             mouse_moves_ndc: Vec::new(),
             axes_raw: Vec::new(),
             mouse_hit: Vec::new(),
+            mouse_released: Vec::new(),
+            keys_released: Vec::new(),
             mouse_wheel: Vec::new(),
         };
         Input { state, delta }
@@ -74,25 +93,147 @@ impl Input {
         let now = time::Instant::now();
         let dt = now - self.state.time_moment;
         self.state.time_moment = now;
-        self.delta.time_delta = dt.as_secs() as TimerDuration + 1e-9 * dt.subsec_nanos() as TimerDuration;
+        let real_time_delta = match self.state.replayed_delta.take() {
+            Some(delta) => delta,
+            None => dt.as_secs() as TimerDuration + 1e-9 * dt.subsec_nanos() as TimerDuration,
+        };
+        self.delta.real_time_delta = real_time_delta;
+        self.delta.time_delta = if self.state.paused {
+            self.state.pending_step.take().unwrap_or(0.0)
+        } else {
+            let dt = self.state.fixed_timestep.unwrap_or(real_time_delta);
+            dt * self.state.time_scale
+        };
+        self.state.update_time += self.delta.time_delta as f64;
         self.delta.keys_hit.clear();
         self.delta.mouse_moves.clear();
         self.delta.mouse_moves_ndc.clear();
         self.delta.axes_raw.clear();
         self.delta.mouse_hit.clear();
+        self.delta.mouse_released.clear();
+        self.delta.keys_released.clear();
         self.delta.mouse_wheel.clear();
     }
 
     /// Get current delta time (time since previous frame) in seconds.
+    ///
+    /// The delta time is scaled by the [time scale](#method.set_time_scale) and is
+    /// zero while the time is [paused](#method.pause), except for
+    /// [stepped frames](#method.step_frame).
     pub fn delta_time(&self) -> TimerDuration {
         self.delta.time_delta
     }
 
+    /// Get the time since previous frame in seconds, ignoring the time scale and pause.
+    pub fn real_delta_time(&self) -> TimerDuration {
+        self.delta.real_time_delta
+    }
+
+    /// Get the sum of the [delta times](#method.delta_time) of all the frames so far.
+    pub(crate) fn update_time(&self) -> f64 {
+        self.state.update_time
+    }
+
+    /// Returns the factor applied to the delta time.
+    pub fn time_scale(&self) -> f32 {
+        self.state.time_scale
+    }
+
+    /// Sets the factor applied to the delta time, e.g. `0.5` for slow motion.
+    ///
+    /// Defaults to `1.0`.
+    pub fn set_time_scale(
+        &mut self,
+        scale: f32,
+    ) {
+        self.state.time_scale = scale;
+    }
+
+    /// Uses a constant delta time of `timestep` seconds for every frame instead of
+    /// the measured frame time, or goes back to the measured time with `None`.
+    ///
+    /// Together with a fixed [`Emitter::seed`](particles/struct.Emitter.html#structfield.seed)
+    /// of the particle systems and a fixed seed for any other randomness in the
+    /// application, this makes animations, particles and controllers
+    /// reproducible from run to run, e.g. for replays or golden-image tests.
+    ///
+    /// Defaults to `None`.
+    pub fn set_fixed_timestep(
+        &mut self,
+        timestep: Option<TimerDuration>,
+    ) {
+        self.state.fixed_timestep = timestep;
+    }
+
+    /// Freezes the delta time at zero, so that anything driven by it stops.
+    ///
+    /// This includes the built-in controllers; use
+    /// [`real_delta_time`](#method.real_delta_time) for anything that should keep
+    /// running, e.g. an editor camera.
+    pub fn pause(&mut self) {
+        self.state.paused = true;
+    }
+
+    /// Resumes the delta time after a [`pause`](#method.pause).
+    pub fn resume(&mut self) {
+        self.state.paused = false;
+        self.state.pending_step = None;
+    }
+
+    /// Returns `true` if the delta time is paused.
+    pub fn is_paused(&self) -> bool {
+        self.state.paused
+    }
+
+    /// Advances a paused time by `dt` seconds: the delta time of the next frame
+    /// is `dt`, ignoring the time scale.
+    ///
+    /// Using the same `dt` for each step makes single-stepping deterministic.
+    /// Has no effect unless the time is paused.
+    pub fn step_frame(
+        &mut self,
+        dt: TimerDuration,
+    ) {
+        if self.state.paused {
+            self.state.pending_step = Some(self.state.pending_step.unwrap_or(0.0) + dt);
+        }
+    }
+
     /// Get list of all keys hit since the last frame.
     pub fn keys_hit(&self) -> &[Key] {
         &self.delta.keys_hit
     }
 
+    /// Get list of all keys released since the last frame.
+    pub fn keys_released(&self) -> &[Key] {
+        &self.delta.keys_released
+    }
+
+    /// Get list of all mouse buttons pressed since the last frame.
+    pub fn mouse_hit(&self) -> &[MouseButton] {
+        &self.delta.mouse_hit
+    }
+
+    /// Get list of all mouse buttons released since the last frame.
+    pub fn mouse_released(&self) -> &[MouseButton] {
+        &self.delta.mouse_released
+    }
+
+    /// Returns `true` if `button` was released since the last frame.
+    ///
+    /// Together with [`hit`](#method.hit) for held buttons and
+    /// [`hit_count`](#method.hit_count) for presses, this covers the whole
+    /// lifetime of a button press.
+    pub fn released(
+        &self,
+        button: Button,
+    ) -> bool {
+        match button {
+            Button::Key(key) => self.delta.keys_released.contains(&key),
+            Button::Mouse(button) => self.delta.mouse_released.contains(&button),
+        }
+    }
+
     /// Get current mouse pointer position in pixels from top-left.
     pub fn mouse_pos(&self) -> mint::Point2<f32> {
         self.state.mouse_pos
@@ -174,6 +315,15 @@ impl Input {
         self.state.is_focused
     }
 
+    /// Use `delta` as the real time delta on the next reset, instead of
+    /// the time measured since the previous one.
+    pub(crate) fn replay_delta(
+        &mut self,
+        delta: TimerDuration,
+    ) {
+        self.state.replayed_delta = Some(delta);
+    }
+
     pub(crate) fn window_focus(
         &mut self,
         state: bool,
@@ -194,7 +344,9 @@ impl Input {
                 }
             }
             ElementState::Released => {
-                self.state.keys_pressed.remove(&key);
+                if self.state.keys_pressed.remove(&key) {
+                    self.delta.keys_released.push(key);
+                }
             }
         }
     }
@@ -210,7 +362,9 @@ impl Input {
                 self.delta.mouse_hit.push(button);
             }
             ElementState::Released => {
-                self.state.mouse_pressed.remove(&button);
+                if self.state.mouse_pressed.remove(&button) {
+                    self.delta.mouse_released.push(button);
+                }
             }
         }
     }
@@ -0,0 +1,300 @@
+//! Recording and playback of the input events received by a window.
+//!
+//! A recording is a text file with one event per line, preceded by the index
+//! of the frame it was received in and its time in seconds since the start
+//! of the recording:
+//!
+//! ```text
+//! # three input recording
+//! 0 0.000000 frame
+//! 0 0.000512 cursor 412 300
+//! 1 0.016694 frame
+//! 1 0.017003 key pressed W
+//! ```
+//!
+//! See [`Window::record_input`] and [`Window::play_input`].
+//!
+//! [`Window::record_input`]: ../../window/struct.Window.html#method.record_input
+//! [`Window::play_input`]: ../../window/struct.Window.html#method.play_input
+
+use glutin::MouseScrollDelta;
+
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::time::Instant;
+
+use super::{Key, MouseButton, TimerDuration};
+
+const HEADER: &str = "# three input recording";
+
+/// An input event received by a window.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Event {
+    /// Start of a frame, i.e. a call to `Window::update`.
+    Frame,
+    /// The window gained or lost focus.
+    Focus(bool),
+    /// A key was pressed or released.
+    Key {
+        /// The key.
+        key: Key,
+        /// `true` if the key was pressed, `false` if released.
+        pressed: bool,
+    },
+    /// A mouse button was pressed or released.
+    Mouse {
+        /// The button.
+        button: MouseButton,
+        /// `true` if the button was pressed, `false` if released.
+        pressed: bool,
+    },
+    /// The cursor moved to a position in pixels, from the top-left corner
+    /// of the window.
+    Cursor(f32, f32),
+    /// The mouse wheel scrolled by a number of lines.
+    WheelLines(f32, f32),
+    /// The mouse wheel, or a touchpad, scrolled by a number of pixels.
+    WheelPixels(f32, f32),
+    /// A raw axis of an input device moved.
+    Axis(u8, f32),
+    /// The window was closed.
+    Close,
+}
+
+/// An [`Event`](enum.Event.html) with the time it was received.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Record {
+    /// Index of the frame the event was received in.
+    pub frame: u64,
+    /// Time since the start of the recording, in seconds.
+    pub time: TimerDuration,
+    /// The event.
+    pub event: Event,
+}
+
+impl Event {
+    pub(crate) fn wheel(delta: MouseScrollDelta) -> Self {
+        match delta {
+            MouseScrollDelta::LineDelta(x, y) => Event::WheelLines(x, y),
+            MouseScrollDelta::PixelDelta(x, y) => Event::WheelPixels(x, y),
+        }
+    }
+}
+
+/// Writes the events received by a window to a file.
+pub struct Recorder {
+    writer: BufWriter<File>,
+    start: Instant,
+    frames: u64,
+}
+
+impl Recorder {
+    /// Creates the recording file at `path`, overwriting any existing file.
+    pub fn create<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        writeln!(writer, "{}", HEADER)?;
+        Ok(Recorder {
+            writer,
+            start: Instant::now(),
+            frames: 0,
+        })
+    }
+
+    /// Number of frames recorded so far.
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
+
+    /// Starts recording a new frame.
+    pub fn next_frame(&mut self) -> io::Result<()> {
+        self.frames += 1;
+        self.write(Event::Frame)
+    }
+
+    /// Records `event` as received now, in the current frame.
+    pub fn write(
+        &mut self,
+        event: Event,
+    ) -> io::Result<()> {
+        let dt = self.start.elapsed();
+        let time = dt.as_secs() as f64 + 1e-9 * dt.subsec_nanos() as f64;
+        let frame = self.frames.saturating_sub(1);
+        write!(self.writer, "{} {:.6} ", frame, time)?;
+        let pressed = |state: bool| if state { "pressed" } else { "released" };
+        match event {
+            Event::Frame => writeln!(self.writer, "frame"),
+            Event::Focus(state) => writeln!(self.writer, "focus {}", state),
+            Event::Key { key, pressed: state } => writeln!(self.writer, "key {} {:?}", pressed(state), key),
+            Event::Mouse { button: MouseButton::Other(id), pressed: state } => {
+                writeln!(self.writer, "mouse {} Other {}", pressed(state), id)
+            }
+            Event::Mouse { button, pressed: state } => writeln!(self.writer, "mouse {} {:?}", pressed(state), button),
+            Event::Cursor(x, y) => writeln!(self.writer, "cursor {} {}", x, y),
+            Event::WheelLines(x, y) => writeln!(self.writer, "wheel lines {} {}", x, y),
+            Event::WheelPixels(x, y) => writeln!(self.writer, "wheel pixels {} {}", x, y),
+            Event::Axis(axis, value) => writeln!(self.writer, "axis {} {}", axis, value),
+            Event::Close => writeln!(self.writer, "close"),
+        }
+    }
+
+    /// Writes the buffered events to the file.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// Events read from a recording, handed out frame by frame.
+pub struct Playback {
+    records: VecDeque<Record>,
+    frame: u64,
+    last_time: TimerDuration,
+}
+
+impl Playback {
+    /// Reads the recording at `path`.
+    ///
+    /// Keys unknown to this version of `three` are skipped with a warning,
+    /// any other malformed line is an error of kind `InvalidData`.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let reader = BufReader::new(File::open(path)?);
+        let mut records = VecDeque::new();
+        for (number, line) in reader.lines().enumerate() {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            match parse_record(line) {
+                Ok(Some(record)) => records.push_back(record),
+                Ok(None) => warn!("Skipping unknown key on line {} of the input recording: {}", number + 1, line),
+                Err(reason) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("line {}: {}: {}", number + 1, reason, line),
+                    ))
+                }
+            }
+        }
+        Ok(Playback {
+            records,
+            frame: 0,
+            last_time: 0.0,
+        })
+    }
+
+    /// Returns `true` once all the recorded events were played.
+    pub fn is_finished(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    /// Index of the next frame to be played.
+    pub fn frame(&self) -> u64 {
+        self.frame
+    }
+
+    /// Returns the events of the next frame, along with the time elapsed
+    /// since the previous frame when it was recorded.
+    pub fn next_frame(&mut self) -> (TimerDuration, Vec<Event>) {
+        let mut delta = 0.0;
+        let mut events = Vec::new();
+        while self.records.front().map_or(false, |record| record.frame <= self.frame) {
+            let record = self.records.pop_front().unwrap();
+            match record.event {
+                Event::Frame => {
+                    delta = record.time - self.last_time;
+                    self.last_time = record.time;
+                }
+                event => events.push(event),
+            }
+        }
+        self.frame += 1;
+        (delta, events)
+    }
+}
+
+fn parse_record(line: &str) -> Result<Option<Record>, &'static str> {
+    let mut words = line.split_whitespace();
+    let frame = words
+        .next()
+        .and_then(|word| word.parse().ok())
+        .ok_or("invalid frame index")?;
+    let time = words
+        .next()
+        .and_then(|word| word.parse().ok())
+        .ok_or("invalid time")?;
+    let event = match words.next() {
+        Some("frame") => Event::Frame,
+        Some("focus") => Event::Focus(parse(words.next())?),
+        Some("key") => {
+            let pressed = parse_state(words.next())?;
+            match words.next().map(parse_key) {
+                Some(Some(key)) => Event::Key { key, pressed },
+                Some(None) => return Ok(None),
+                None => return Err("missing key"),
+            }
+        }
+        Some("mouse") => {
+            let pressed = parse_state(words.next())?;
+            let button = match words.next() {
+                Some("Left") => MouseButton::Left,
+                Some("Right") => MouseButton::Right,
+                Some("Middle") => MouseButton::Middle,
+                Some("Other") => MouseButton::Other(parse(words.next())?),
+                _ => return Err("invalid mouse button"),
+            };
+            Event::Mouse { button, pressed }
+        }
+        Some("cursor") => Event::Cursor(parse(words.next())?, parse(words.next())?),
+        Some("wheel") => match words.next() {
+            Some("lines") => Event::WheelLines(parse(words.next())?, parse(words.next())?),
+            Some("pixels") => Event::WheelPixels(parse(words.next())?, parse(words.next())?),
+            _ => return Err("invalid wheel unit"),
+        },
+        Some("axis") => Event::Axis(parse(words.next())?, parse(words.next())?),
+        Some("close") => Event::Close,
+        _ => return Err("unknown event"),
+    };
+    Ok(Some(Record { frame, time, event }))
+}
+
+fn parse<T: ::std::str::FromStr>(word: Option<&str>) -> Result<T, &'static str> {
+    word.and_then(|word| word.parse().ok())
+        .ok_or("invalid argument")
+}
+
+fn parse_state(word: Option<&str>) -> Result<bool, &'static str> {
+    match word {
+        Some("pressed") => Ok(true),
+        Some("released") => Ok(false),
+        _ => Err("invalid button state"),
+    }
+}
+
+macro_rules! keys {
+    ($($name:ident)*) => {
+        fn parse_key(name: &str) -> Option<Key> {
+            match name {
+                $(stringify!($name) => Some(Key::$name),)*
+                _ => None,
+            }
+        }
+    };
+}
+
+keys! {
+    Key1 Key2 Key3 Key4 Key5 Key6 Key7 Key8 Key9 Key0
+    A B C D E F G H I J K L M N O P Q R S T U V W X Y Z
+    Escape F1 F2 F3 F4 F5 F6 F7 F8 F9 F10 F11 F12 F13 F14 F15
+    Snapshot Scroll Pause Insert Home Delete End PageDown PageUp
+    Left Up Right Down Back Return Space Compose Numlock
+    Numpad0 Numpad1 Numpad2 Numpad3 Numpad4 Numpad5 Numpad6 Numpad7 Numpad8 Numpad9
+    AbntC1 AbntC2 Add Apostrophe Apps At Ax Backslash Calculator Capital Colon Comma
+    Convert Decimal Divide Equals Grave Kana Kanji LAlt LBracket LControl LMenu LShift LWin
+    Mail MediaSelect MediaStop Minus Multiply Mute MyComputer NavigateForward NavigateBackward
+    NextTrack NoConvert NumpadComma NumpadEnter NumpadEquals OEM102 Period PlayPause Power
+    PrevTrack RAlt RBracket RControl RMenu RShift RWin Semicolon Slash Sleep Stop Subtract
+    Sysrq Tab Underline Unlabeled VolumeDown VolumeUp Wake WebBack WebFavorites WebForward
+    WebHome WebRefresh WebSearch WebStop Yen
+}
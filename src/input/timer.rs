@@ -1,4 +1,4 @@
-use input::TimerDuration;
+use input::{Input, TimerDuration};
 use std::time;
 
 /// Timer can be used to find the time difference between the moment of timer creation and the
@@ -29,3 +29,39 @@ impl Timer {
         dt.as_secs() as f32 + 1e-9 * dt.subsec_nanos() as f32
     }
 }
+
+/// Timer following the [delta time](struct.Input.html#method.delta_time) of the frames
+/// instead of the system time, so that it stops while the time is
+/// [paused](struct.Input.html#method.pause) and follows the time scale.
+///
+/// Unlike [`Timer`](struct.Timer.html), it only advances when the [`Input`](struct.Input.html)
+/// it was created from is updated.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct UpdateTimer {
+    start: f64,
+}
+
+impl UpdateTimer {
+    /// Create new timer based on the current update time of `input`.
+    pub fn new(input: &Input) -> Self {
+        UpdateTimer {
+            start: input.update_time(),
+        }
+    }
+
+    /// Reset time of creation to the current update time of `input`.
+    pub fn reset(
+        &mut self,
+        input: &Input,
+    ) {
+        self.start = input.update_time();
+    }
+
+    /// Get period of update time since timer creation in seconds.
+    pub fn elapsed(
+        &self,
+        input: &Input,
+    ) -> TimerDuration {
+        (input.update_time() - self.start) as TimerDuration
+    }
+}
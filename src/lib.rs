@@ -266,6 +266,7 @@ extern crate obj;
 extern crate phf;
 #[macro_use]
 extern crate quick_error;
+#[cfg(feature = "audio")]
 extern crate rodio;
 extern crate vec_map;
 
@@ -279,30 +280,50 @@ extern crate glutin;
 #[macro_use]
 mod macros;
 
+#[cfg(feature = "audio")]
 pub mod audio;
 pub mod animation;
+pub mod annotation;
+pub mod bake;
+pub mod bench;
 pub mod camera;
+pub mod cloth;
+pub mod collision;
 pub mod color;
 pub mod controls;
 pub mod custom;
 mod data;
 mod factory;
 mod geometry;
+pub mod helper;
+#[cfg(feature = "golden-image")]
+pub mod golden;
 mod hub;
 mod input;
 pub mod light;
 pub mod material;
+pub mod math;
+pub mod measure;
+#[cfg(feature = "navmesh")]
+pub mod navmesh;
 mod mesh;
 mod node;
+pub mod noise;
 pub mod object;
+pub mod particles;
+pub mod portal;
+pub mod probe;
+pub mod raycast;
 pub mod render;
 pub mod scene;
 pub mod skeleton;
+mod spatial;
 mod sprite;
 pub mod template;
 mod text;
 mod texture;
 mod util;
+pub mod virtual_texture;
 
 #[cfg(feature = "opengl")]
 pub mod window;
@@ -314,13 +335,13 @@ pub use color::Color;
 pub use controls::{AXIS_DOWN_UP, AXIS_LEFT_RIGHT, KEY_ESCAPE, KEY_SPACE, MOUSE_LEFT, MOUSE_RIGHT};
 
 #[doc(inline)]
-pub use controls::{Button, MouseButton, Input, Timer};
+pub use controls::{Button, MouseButton, Input, Timer, UpdateTimer};
 
 #[doc(inline)]
 pub use factory::Factory;
 
 #[doc(inline)]
-pub use geometry::{Geometry, Joints, Shape};
+pub use geometry::{Correspondence, Geometry, Joints, Shape};
 
 #[cfg(feature = "opengl")]
 #[doc(inline)]
@@ -333,7 +354,7 @@ pub use glutin::VirtualKeyCode as Key;
 pub use material::Material;
 
 #[doc(inline)]
-pub use mesh::{DynamicMesh, Mesh};
+pub use mesh::{BeforeRender, DynamicMesh, Fade, Mesh};
 
 #[doc(inline)]
 pub use node::{Node, Transform, Local, World};
@@ -342,20 +363,20 @@ pub use node::{Node, Transform, Local, World};
 pub use object::{Group, Object};
 
 #[doc(inline)]
-pub use render::Renderer;
+pub use render::{Budget, GizmoAxis, Renderer};
 
 #[doc(inline)]
-pub use scene::{Background, Scene};
+pub use scene::{Background, Environment, Fog, Scene, Section, Stats, WorldTransforms};
 
 #[doc(inline)]
 pub use sprite::Sprite;
 
 #[doc(inline)]
-pub use text::{Align, Font, Layout, Text};
+pub use text::{Align, DropShadow, Font, Layout, Outline, SizeCategory, Span, Text, UiScale};
 
 #[doc(inline)]
-pub use texture::{CubeMap, CubeMapPath, FilterMethod, Sampler, Texture, WrapMode};
+pub use texture::{ColorSpace, CubeMap, CubeMapPath, FilterMethod, RenderTarget, Sampler, Texture, WrapMode};
 
 #[cfg(feature = "opengl")]
 #[doc(inline)]
-pub use window::{CursorState, Window};
+pub use window::{CursorState, Headless, Window};
@@ -44,6 +44,17 @@ impl Ambient {
     pub(crate) fn new(object: Base) -> Self {
         Ambient { object }
     }
+
+    /// Set the layers of the light.
+    ///
+    /// The light only illuminates meshes whose light mask shares at least one
+    /// bit with `layers`. All bits are set by default.
+    pub fn set_layers(
+        &self,
+        layers: u32,
+    ) {
+        self.object.send(Operation::SetLightMask(layers));
+    }
 }
 
 impl AsRef<Base> for Ambient {
@@ -93,6 +104,17 @@ impl Directional {
         let msg = Operation::SetShadow(map, sp);
         let _ = self.object.tx.send((self.object.node.downgrade(), msg));
     }
+
+    /// Set the layers of the light.
+    ///
+    /// The light only illuminates meshes whose light mask shares at least one
+    /// bit with `layers`. All bits are set by default.
+    pub fn set_layers(
+        &self,
+        layers: u32,
+    ) {
+        self.object.send(Operation::SetLightMask(layers));
+    }
 }
 
 impl AsRef<Base> for Directional {
@@ -130,6 +152,17 @@ impl Hemisphere {
     pub(crate) fn new(object: Base) -> Self {
         Hemisphere { object }
     }
+
+    /// Set the layers of the light.
+    ///
+    /// The light only illuminates meshes whose light mask shares at least one
+    /// bit with `layers`. All bits are set by default.
+    pub fn set_layers(
+        &self,
+        layers: u32,
+    ) {
+        self.object.send(Operation::SetLightMask(layers));
+    }
 }
 
 impl AsRef<Base> for Hemisphere {
@@ -159,6 +192,17 @@ impl Point {
     pub(crate) fn new(object: Base) -> Self {
         Point { object }
     }
+
+    /// Set the layers of the light.
+    ///
+    /// The light only illuminates meshes whose light mask shares at least one
+    /// bit with `layers`. All bits are set by default.
+    pub fn set_layers(
+        &self,
+        layers: u32,
+    ) {
+        self.object.send(Operation::SetLightMask(layers));
+    }
 }
 
 impl AsRef<Base> for Point {
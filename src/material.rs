@@ -6,6 +6,7 @@ use color::Color;
 use render::BasicPipelineState;
 use texture::Texture;
 use util;
+use virtual_texture;
 
 #[doc(inline)]
 pub use self::basic::Basic;
@@ -17,7 +18,8 @@ pub mod basic {
     /// Parameters for a basic solid mesh material.
     ///
     /// Renders triangle meshes with a solid color or texture.
-    #[derive(Clone, Hash, Debug, PartialEq, Eq)]
+    #[derive(Derivative)]
+    #[derivative(Clone, Debug, PartialEq, Hash, Eq)]
     pub struct Basic {
         /// Solid color applied in the absence of `map`.
         ///
@@ -28,6 +30,14 @@ pub mod basic {
         ///
         /// Default: `None`.
         pub map: Option<Texture<[f32; 4]>>,
+
+        /// Opacity in the range [0.0, 1.0], multiplied with the alpha of `map`.
+        ///
+        /// Meshes with an opacity below `1.0` are alpha-blended.
+        ///
+        /// Default: `1.0` (opaque).
+        #[derivative(Hash(hash_with = "util::hash_f32"))]
+        pub opacity: f32,
     }
 
     impl Default for Basic {
@@ -35,6 +45,7 @@ pub mod basic {
             Self {
                 color: color::WHITE,
                 map: None,
+                opacity: 1.0,
             }
         }
     }
@@ -61,7 +72,8 @@ pub mod basic {
 /// Parameters for a Lamberian diffusion reflection model.
 ///
 /// Renders triangle meshes with the Gouraud illumination model.
-#[derive(Clone, Hash, Debug, PartialEq, Eq)]
+#[derive(Derivative)]
+#[derivative(Clone, Debug, PartialEq, Hash, Eq)]
 pub struct Lambert {
     /// Solid color applied in the absense of `map`.
     ///
@@ -72,6 +84,14 @@ pub struct Lambert {
     ///
     /// Default: `false` (lighting is interpolated across faces).
     pub flat: bool,
+
+    /// Opacity in the range [0.0, 1.0].
+    ///
+    /// Meshes with an opacity below `1.0` are alpha-blended.
+    ///
+    /// Default: `1.0` (opaque).
+    #[derivative(Hash(hash_with = "util::hash_f32"))]
+    pub opacity: f32,
 }
 
 impl Default for Lambert {
@@ -79,25 +99,169 @@ impl Default for Lambert {
         Self {
             color: color::WHITE,
             flat: false,
+            opacity: 1.0,
         }
     }
 }
 
+/// How the vertices of a line mesh are joined.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum LineMode {
+    /// Each vertex is joined to the next one, in a single connected line.
+    Strip,
+    /// Vertices are joined by pairs, `[0, 1]`, `[2, 3]` and so on, in
+    /// independent segments.
+    Segments,
+}
+
+impl Default for LineMode {
+    fn default() -> Self {
+        LineMode::Strip
+    }
+}
+
 /// Parameters for a line material.
 ///
-/// Renders line strip meshes with a solid color and unit width.
-#[derive(Clone, Hash, Debug, PartialEq, Eq)]
+/// Renders line meshes with a solid color.
+#[derive(Derivative)]
+#[derivative(Clone, Debug, PartialEq, Hash, Eq)]
 pub struct Line {
     /// Solid line color.
     ///
     /// Default: `0xFFFFFF` (white).
     pub color: Color,
+
+    /// Width of the line in pixels.
+    ///
+    /// Lines wider than a pixel are drawn as screen space quads, since the
+    /// width of OpenGL lines is limited to a pixel on most drivers. Lines up to
+    /// a pixel wide are drawn as OpenGL lines.
+    ///
+    /// Default: `1.0`.
+    #[derivative(Hash(hash_with = "util::hash_f32"))]
+    pub width: f32,
+
+    /// How the vertices are joined.
+    ///
+    /// Default: `LineMode::Strip`.
+    pub mode: LineMode,
 }
 
 impl Default for Line {
     fn default() -> Self {
         Self {
             color: color::WHITE,
+            width: 1.0,
+            mode: LineMode::Strip,
+        }
+    }
+}
+
+/// Parameters for a points material.
+///
+/// Renders each vertex of a mesh as a square facing the camera, e.g. for
+/// particles or point clouds. See
+/// [`Factory::points`](../struct.Factory.html#method.points).
+#[derive(Derivative)]
+#[derivative(Clone, Debug, PartialEq, Hash, Eq)]
+pub struct Points {
+    /// Solid color of the points, multiplied with the vertex colors and
+    /// `map`.
+    ///
+    /// Default: `0xFFFFFF` (white).
+    pub color: Color,
+
+    /// Width and height of the points in world units, so that points get
+    /// smaller with the distance to a perspective camera.
+    ///
+    /// Default: `1.0`.
+    #[derivative(Hash(hash_with = "util::hash_f32"))]
+    pub size: f32,
+
+    /// Texture applied to each point, from its bottom-left corner to its
+    /// top-right corner.
+    ///
+    /// Fully transparent texels are discarded. Other translucent texels are
+    /// only blended with an alpha blending [`State`](struct.State.html).
+    ///
+    /// Default: `None`.
+    pub map: Option<Texture<[f32; 4]>>,
+}
+
+impl Default for Points {
+    fn default() -> Self {
+        Self {
+            color: color::WHITE,
+            size: 1.0,
+            map: None,
+        }
+    }
+}
+
+/// Parameters for a virtual texture material.
+///
+/// Renders triangle meshes with the Gouraud illumination model, colored by
+/// a [`VirtualTexture`] streamed tile by tile, see the [`virtual_texture`]
+/// module.
+///
+/// [`VirtualTexture`]: ../virtual_texture/struct.VirtualTexture.html
+/// [`virtual_texture`]: ../virtual_texture/index.html
+#[derive(Clone, Debug, PartialEq, Hash, Eq)]
+pub struct Virtual {
+    /// Solid color multiplied with the texture.
+    pub color: Color,
+
+    /// The virtual texture, applied using the mesh texture co-ordinates.
+    pub map: virtual_texture::Map,
+}
+
+/// Parameters for a dashed line material.
+///
+/// Renders line meshes with a solid color, alternating dashes and gaps along
+/// the line. The pattern follows the distance along the line stored in the
+/// first texture co-ordinate of the vertices, see
+/// [`Geometry::compute_line_distances`](../struct.Geometry.html#method.compute_line_distances).
+#[derive(Derivative)]
+#[derivative(Clone, Debug, PartialEq, Hash, Eq)]
+pub struct DashedLine {
+    /// Solid line color.
+    ///
+    /// Default: `0xFFFFFF` (white).
+    pub color: Color,
+
+    /// Width of the line in pixels.
+    ///
+    /// Default: `1.0`.
+    #[derivative(Hash(hash_with = "util::hash_f32"))]
+    pub width: f32,
+
+    /// How the vertices are joined.
+    ///
+    /// Default: `LineMode::Strip`.
+    pub mode: LineMode,
+
+    /// Length of the dashes, in the units of the line distances.
+    ///
+    /// Default: `1.0`.
+    #[derivative(Hash(hash_with = "util::hash_f32"))]
+    pub dash_size: f32,
+
+    /// Length of the gaps between the dashes, in the units of the line
+    /// distances.
+    ///
+    /// Default: `1.0`.
+    #[derivative(Hash(hash_with = "util::hash_f32"))]
+    pub gap_size: f32,
+}
+
+impl Default for DashedLine {
+    fn default() -> Self {
+        Self {
+            color: color::WHITE,
+            width: 1.0,
+            mode: LineMode::Strip,
+            dash_size: 1.0,
+            gap_size: 1.0,
         }
     }
 }
@@ -202,7 +366,8 @@ impl Default for Pbr {
 
 /// Parameters for a Phong reflection model.
 ///
-/// Renders triangle meshes with the Phong illumination model.
+/// Renders triangle meshes with the Blinn-Phong illumination model, evaluated
+/// per fragment for the point, directional and hemisphere lights of the scene.
 #[derive(Derivative)]
 #[derivative(Clone, Debug, PartialEq, Hash, Eq)]
 pub struct Phong {
@@ -218,6 +383,30 @@ pub struct Phong {
     /// Default: `30.0`.
     #[derivative(Hash(hash_with = "util::hash_f32"))]
     pub glossiness: f32,
+
+    /// Color of specular highlights.
+    ///
+    /// Default: `WHITE`.
+    pub specular: Color,
+
+    /// Texture applied using the mesh texture co-ordinates.
+    ///
+    /// Default: `None`.
+    pub map: Option<Texture<[f32; 4]>>,
+
+    /// Tangent space normal map perturbing the surface normals, oriented by
+    /// the geometry tangents.
+    ///
+    /// Default: `None`.
+    pub normal_map: Option<Texture<[f32; 4]>>,
+
+    /// Opacity in the range [0.0, 1.0], multiplied with the alpha of `map`.
+    ///
+    /// Meshes with an opacity below `1.0` are alpha-blended.
+    ///
+    /// Default: `1.0` (opaque).
+    #[derivative(Hash(hash_with = "util::hash_f32"))]
+    pub opacity: f32,
 }
 
 impl Default for Phong {
@@ -225,6 +414,10 @@ impl Default for Phong {
         Self {
             color: color::WHITE,
             glossiness: 30.0,
+            specular: color::WHITE,
+            map: None,
+            normal_map: None,
+            opacity: 1.0,
         }
     }
 }
@@ -251,6 +444,78 @@ pub struct Wireframe {
     pub color: Color,
 }
 
+/// Blending applied when writing fragments to the color target.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub enum Blend {
+    /// Fragments replace the existing color.
+    Replace,
+    /// Fragments are blended by their alpha component.
+    Alpha,
+    /// Fragments are added to the existing color.
+    Add,
+    /// Fragments are multiplied with the existing color.
+    Multiply,
+}
+
+/// Triangle faces discarded by the rasterizer.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub enum Cull {
+    /// Both faces are drawn.
+    None,
+    /// Front faces are discarded.
+    Front,
+    /// Back faces are discarded.
+    Back,
+}
+
+/// Pipeline state used together with a [`Material`].
+///
+/// Meshes with identical material kinds and states share the same pipeline,
+/// which is created the first time it is needed. The default state selects the
+/// built-in pipeline of each material, e.g. sprites remain alpha-blended.
+///
+/// [`Material`]: enum.Material.html
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub struct State {
+    /// Color blending mode.
+    ///
+    /// Default: `Replace`.
+    pub blend: Blend,
+
+    /// Face culling mode.
+    ///
+    /// Default: `Back`.
+    pub cull: Cull,
+
+    /// Whether fragments are tested against the depth buffer.
+    ///
+    /// Default: `true`.
+    pub depth_test: bool,
+
+    /// Whether fragments are written to the depth buffer.
+    ///
+    /// Default: `true`.
+    pub depth_write: bool,
+
+    /// Whether the fog of the scene applies, with the basic, Lambert, Phong
+    /// and PBR materials.
+    ///
+    /// Default: `true`.
+    pub fog: bool,
+}
+
+impl Default for State {
+    fn default() -> Self {
+        State {
+            blend: Blend::Replace,
+            cull: Cull::Back,
+            depth_test: true,
+            depth_write: true,
+            fog: true,
+        }
+    }
+}
+
 /// Specifies the appearance of a [`Mesh`](struct.Mesh.html).
 #[derive(Clone, Debug, Hash, PartialEq, Eq)]
 pub enum Material {
@@ -261,9 +526,15 @@ pub enum Material {
     /// its input.
     CustomBasic(basic::Custom),
 
-    /// Renders line strip meshes with a solid color and unit width.
+    /// Renders line meshes with a solid color.
     Line(Line),
 
+    /// Renders line meshes with a solid color and a dash pattern.
+    DashedLine(DashedLine),
+
+    /// Renders the vertices of meshes as camera facing squares.
+    Points(Points),
+
     /// Renders triangle meshes with the Gouraud illumination model.
     Lambert(Lambert),
 
@@ -279,6 +550,10 @@ pub enum Material {
     /// [`Sprite`]: ../sprite/struct.Sprite.html
     Sprite(Sprite),
 
+    /// Renders triangle meshes with the Gouraud illumination model and a
+    /// virtual texture.
+    Virtual(Virtual),
+
     /// Renders the edges of a triangle mesh with a solid color.
     Wireframe(Wireframe),
 }
@@ -307,6 +582,18 @@ impl From<Line> for Material {
     }
 }
 
+impl From<Points> for Material {
+    fn from(params: Points) -> Self {
+        Material::Points(params)
+    }
+}
+
+impl From<DashedLine> for Material {
+    fn from(params: DashedLine) -> Self {
+        Material::DashedLine(params)
+    }
+}
+
 impl From<Phong> for Material {
     fn from(params: Phong) -> Self {
         Material::Phong(params)
@@ -325,6 +612,12 @@ impl From<Sprite> for Material {
     }
 }
 
+impl From<Virtual> for Material {
+    fn from(params: Virtual) -> Self {
+        Material::Virtual(params)
+    }
+}
+
 impl From<Wireframe> for Material {
     fn from(params: Wireframe) -> Self {
         Material::Wireframe(params)
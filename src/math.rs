@@ -0,0 +1,322 @@
+//! Math helpers for interoperating with raw vectors and matrices.
+//!
+//! `three` speaks [`mint`] types in its API, so that any math library can be
+//! used on the application side. The helpers here cover the conventions of
+//! the crate that are easy to get wrong when converting by hand: the
+//! orientation of objects looking in a direction, the split of a matrix into
+//! a [`Transform`] and a per-axis scale, and the planes bounding a camera view.
+//!
+//! ```rust
+//! use three::math;
+//!
+//! // an object at the origin, looking down the X axis with Y up
+//! let orientation = math::look_rotation([1.0, 0.0, 0.0], [0.0, 1.0, 0.0]);
+//! let transform = three::Transform {
+//!     orientation,
+//!     ..three::Transform::default()
+//! };
+//! let matrix = math::compose(&transform, [1.0, 2.0, 1.0]);
+//! let (decomposed, scale) = math::decompose(matrix).unwrap();
+//! assert!((scale.y / scale.x - 2.0).abs() < 1e-5);
+//! # let _ = decomposed;
+//! ```
+//!
+//! [`mint`]: https://crates.io/crates/mint
+//! [`Transform`]: ../struct.Transform.html
+
+use cgmath::{Decomposed, EuclideanSpace, InnerSpace, Matrix3, Matrix4, Point3, Quaternion, Rotation, Vector3, Vector4};
+use mint;
+
+use collision::{Aabb, Sphere};
+use node::Transform;
+
+/// Returns the orientation of an object looking along `forward`, with its
+/// top towards `up`.
+///
+/// Objects, and cameras in particular, look along their negative Z axis, with
+/// their Y axis pointing up. When `forward` and `up` are parallel, any
+/// direction perpendicular to `forward` is used as the up direction.
+pub fn look_rotation<F, U>(
+    forward: F,
+    up: U,
+) -> mint::Quaternion<f32>
+where
+    F: Into<mint::Vector3<f32>>,
+    U: Into<mint::Vector3<f32>>,
+{
+    let forward = Vector3::from(forward.into()).normalize();
+    let mut up = Vector3::from(up.into());
+    if forward.cross(up).magnitude2() < 1e-12 {
+        up = orthonormal_basis(forward)[0].into();
+    }
+    Quaternion::look_at(-forward, up.normalize()).invert().into()
+}
+
+/// Returns two unit vectors perpendicular to `normal` and to each other.
+///
+/// The vectors `[t, b]` complete `normal` into a right-handed basis, that is
+/// `t × b = normal`, and vary continuously with `normal` except when it
+/// points straight down the negative Z axis.
+pub fn orthonormal_basis<N>(normal: N) -> [mint::Vector3<f32>; 2]
+where
+    N: Into<mint::Vector3<f32>>,
+{
+    let n = Vector3::from(normal.into()).normalize();
+    let sign = if n.z >= 0.0 { 1.0 } else { -1.0 };
+    let a = -1.0 / (sign + n.z);
+    let b = n.x * n.y * a;
+    [
+        [1.0 + sign * n.x * n.x * a, sign * b, -sign * n.x].into(),
+        [b, sign + n.y * n.y * a, -n.y].into(),
+    ]
+}
+
+/// Returns the matrix of `transform` followed by a per-axis `scale`, as
+/// applied to an object with [`Object::set_transform`] and
+/// [`Object::set_nonuniform_scale`].
+///
+/// [`Object::set_transform`]: ../object/trait.Object.html#method.set_transform
+/// [`Object::set_nonuniform_scale`]: ../object/trait.Object.html#method.set_nonuniform_scale
+pub fn compose<S>(
+    transform: &Transform,
+    scale: S,
+) -> mint::ColumnMatrix4<f32>
+where
+    S: Into<mint::Vector3<f32>>,
+{
+    let scale = scale.into();
+    let decomposed = Decomposed {
+        disp: Vector3::new(transform.position.x, transform.position.y, transform.position.z),
+        rot: Quaternion::from(transform.orientation),
+        scale: transform.scale,
+    };
+    let matrix = Matrix4::from(decomposed) * Matrix4::from_nonuniform_scale(scale.x, scale.y, scale.z);
+    matrix.into()
+}
+
+/// Splits an affine `matrix` into a transform and a per-axis scale, the
+/// inverse of [`compose`](fn.compose.html).
+///
+/// The uniform `scale` of the transform is the geometric mean of the axis
+/// scales, leaving a per-axis scale of one for matrices without stretch.
+/// Mirroring matrices get a negative X scale. Any shear is dropped, and
+/// `None` is returned if an axis is collapsed.
+pub fn decompose<M>(matrix: M) -> Option<(Transform, mint::Vector3<f32>)>
+where
+    M: Into<mint::ColumnMatrix4<f32>>,
+{
+    let m = Matrix4::from(matrix.into());
+    let columns = [m.x.truncate(), m.y.truncate(), m.z.truncate()];
+    let mut scale = Vector3::new(columns[0].magnitude(), columns[1].magnitude(), columns[2].magnitude());
+    if scale.x < 1e-12 || scale.y < 1e-12 || scale.z < 1e-12 {
+        return None;
+    }
+    if columns[0].cross(columns[1]).dot(columns[2]) < 0.0 {
+        scale.x = -scale.x;
+    }
+
+    // orthonormalize the axes, dropping the shear
+    let x = columns[0] / scale.x;
+    let y = (columns[1] - x * x.dot(columns[1])).normalize();
+    let z = x.cross(y);
+    let orientation = Quaternion::from(Matrix3::from_cols(x, y, z));
+
+    let uniform = (scale.x * scale.y * scale.z).abs().cbrt();
+    let position: mint::Vector3<f32> = m.w.truncate().into();
+    let transform = Transform {
+        position: position.into(),
+        orientation: orientation.into(),
+        scale: uniform,
+    };
+    Some((transform, (scale / uniform).into()))
+}
+
+/// A plane, the set of points `p` with `dot(normal, p) = distance`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Plane {
+    /// Unit normal of the plane, pointing towards its positive side.
+    pub normal: mint::Vector3<f32>,
+    /// Signed distance of the plane from the origin, along `normal`.
+    pub distance: f32,
+}
+
+impl Plane {
+    /// Creates the plane through `point` facing `normal`, which needn't be
+    /// normalized.
+    pub fn from_point_normal<P, N>(
+        point: P,
+        normal: N,
+    ) -> Self
+    where
+        P: Into<mint::Point3<f32>>,
+        N: Into<mint::Vector3<f32>>,
+    {
+        let normal = Vector3::from(normal.into()).normalize();
+        Plane {
+            normal: normal.into(),
+            distance: normal.dot(Point3::from(point.into()).to_vec()),
+        }
+    }
+
+    /// Creates the plane through three points, facing the side they are seen
+    /// counter-clockwise from, or `None` if they are aligned.
+    pub fn from_points<P>(
+        a: P,
+        b: P,
+        c: P,
+    ) -> Option<Self>
+    where
+        P: Into<mint::Point3<f32>>,
+    {
+        let a = Point3::from(a.into());
+        let normal = (Point3::from(b.into()) - a).cross(Point3::from(c.into()) - a);
+        if normal.magnitude2() < 1e-24 {
+            return None;
+        }
+        Some(Self::from_point_normal(a, normal))
+    }
+
+    /// Returns the signed distance of `point` from the plane, positive on the
+    /// side `normal` points to.
+    pub fn signed_distance<P>(
+        &self,
+        point: P,
+    ) -> f32
+    where
+        P: Into<mint::Point3<f32>>,
+    {
+        Vector3::from(self.normal).dot(Point3::from(point.into()).to_vec()) - self.distance
+    }
+
+    /// Returns the point of the plane closest to `point`.
+    pub fn project<P>(
+        &self,
+        point: P,
+    ) -> mint::Point3<f32>
+    where
+        P: Into<mint::Point3<f32>>,
+    {
+        let point = Point3::from(point.into());
+        let distance = self.signed_distance(point);
+        (point + Vector3::from(self.normal) * -distance).into()
+    }
+
+    /// Returns the point where the ray from `origin` along `direction` hits
+    /// the plane, or `None` if it is parallel to the plane or points away.
+    pub fn intersect_ray<P, D>(
+        &self,
+        origin: P,
+        direction: D,
+    ) -> Option<mint::Point3<f32>>
+    where
+        P: Into<mint::Point3<f32>>,
+        D: Into<mint::Vector3<f32>>,
+    {
+        let origin = Point3::from(origin.into());
+        let direction = Vector3::from(direction.into());
+        let denominator = Vector3::from(self.normal).dot(direction);
+        if denominator.abs() < 1e-12 {
+            return None;
+        }
+        let t = -self.signed_distance(origin) / denominator;
+        if t < 0.0 {
+            return None;
+        }
+        Some((origin + direction * t).into())
+    }
+
+    /// Plane from the coefficients `[a, b, c, d]` of `ax + by + cz + d = 0`.
+    ///
+    /// Degenerate coefficients, e.g. of a projection with an infinite far
+    /// plane, give a plane with a zero normal that every point is in front of.
+    fn from_coefficients(v: Vector4<f32>) -> Self {
+        let length = v.truncate().magnitude();
+        if length < 1e-12 {
+            return Plane {
+                normal: [0.0; 3].into(),
+                distance: -::std::f32::INFINITY,
+            };
+        }
+        Plane {
+            normal: (v.truncate() / length).into(),
+            distance: -v.w / length,
+        }
+    }
+}
+
+/// The volume seen by a camera, bounded by six planes facing inwards.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Frustum {
+    /// Left, right, bottom, top, near and far planes, in that order.
+    pub planes: [Plane; 6],
+}
+
+impl Frustum {
+    /// Extracts the frustum of a view-projection matrix, in the space the
+    /// matrix transforms from, e.g. world space for `projection * view`.
+    ///
+    /// The matrix is expected to map the frustum to the OpenGL clip volume,
+    /// like the projections of [`Camera`](../camera/struct.Camera.html).
+    pub fn from_matrix<M>(matrix: M) -> Self
+    where
+        M: Into<mint::ColumnMatrix4<f32>>,
+    {
+        let m = Matrix4::from(matrix.into());
+        let row = |i: usize| Vector4::new(m.x[i], m.y[i], m.z[i], m.w[i]);
+        let (x, y, z, w) = (row(0), row(1), row(2), row(3));
+        Frustum {
+            planes: [
+                Plane::from_coefficients(w + x),
+                Plane::from_coefficients(w - x),
+                Plane::from_coefficients(w + y),
+                Plane::from_coefficients(w - y),
+                Plane::from_coefficients(w + z),
+                Plane::from_coefficients(w - z),
+            ],
+        }
+    }
+
+    /// Returns `true` if `point` is inside the frustum.
+    pub fn contains_point<P>(
+        &self,
+        point: P,
+    ) -> bool
+    where
+        P: Into<mint::Point3<f32>>,
+    {
+        let point = point.into();
+        self.planes.iter().all(|plane| plane.signed_distance(point) >= 0.0)
+    }
+
+    /// Returns `true` if `sphere` may intersect the frustum.
+    ///
+    /// The test is conservative: spheres near the edges of the frustum may be
+    /// reported as intersecting while being just outside.
+    pub fn intersects_sphere(
+        &self,
+        sphere: &Sphere,
+    ) -> bool {
+        self.planes
+            .iter()
+            .all(|plane| plane.signed_distance(sphere.center) >= -sphere.radius)
+    }
+
+    /// Returns `true` if `aabb` may intersect the frustum.
+    ///
+    /// The test is conservative like
+    /// [`intersects_sphere`](#method.intersects_sphere).
+    pub fn intersects_aabb(
+        &self,
+        aabb: &Aabb,
+    ) -> bool {
+        self.planes.iter().all(|plane| {
+            // the corner furthest along the normal
+            let corner = mint::Point3 {
+                x: if plane.normal.x >= 0.0 { aabb.max.x } else { aabb.min.x },
+                y: if plane.normal.y >= 0.0 { aabb.max.y } else { aabb.min.y },
+                z: if plane.normal.z >= 0.0 { aabb.max.z } else { aabb.min.z },
+            };
+            plane.signed_distance(corner) >= 0.0
+        })
+    }
+}
@@ -0,0 +1,233 @@
+//! Measurement helpers for viewer applications.
+//!
+//! ## Dimensions
+//!
+//! A [`Dimension`] displays a measurement between points in the scene: dimension
+//! lines are rendered between the measured points and the measured value is shown
+//! as a screen-space [`Text`] label.
+//!
+//! ```rust,no_run
+//! # let mut window = three::Window::new("");
+//! # let camera = window.factory.perspective_camera(60.0, 0.1 .. 10.0);
+//! let font = window.factory.load_font_karla();
+//! let mut dimension = window.factory.distance_dimension(
+//!     [0.0, 0.0, 0.0],
+//!     [1.0, 2.0, 0.0],
+//!     &font,
+//! );
+//! window.scene.add(&dimension);
+//! while window.update() {
+//!     dimension.update(&mut window, &camera);
+//!     window.render(&camera);
+//! }
+//! ```
+//!
+//! [`Dimension`]: struct.Dimension.html
+//! [`Text`]: ../struct.Text.html
+
+use cgmath::{EuclideanSpace, InnerSpace, Point3, Quaternion, Vector3};
+use mint;
+
+use camera::Camera;
+use mesh::Mesh;
+use object::{Group, Object};
+use text::Text;
+use window::Window;
+
+/// Returns the distance between the points `a` and `b`.
+pub fn distance<P, Q>(
+    a: P,
+    b: Q,
+) -> f32
+where
+    P: Into<mint::Point3<f32>>,
+    Q: Into<mint::Point3<f32>>,
+{
+    (Point3::from(b.into()) - Point3::from(a.into())).magnitude()
+}
+
+/// Returns the angle at `vertex` between the directions to `a` and `b`, in degrees.
+///
+/// Returns `0.0` if either point coincides with `vertex`.
+pub fn angle<P, Q, R>(
+    a: P,
+    vertex: Q,
+    b: R,
+) -> f32
+where
+    P: Into<mint::Point3<f32>>,
+    Q: Into<mint::Point3<f32>>,
+    R: Into<mint::Point3<f32>>,
+{
+    let vertex = Point3::from(vertex.into());
+    let u = Point3::from(a.into()) - vertex;
+    let v = Point3::from(b.into()) - vertex;
+    if u.magnitude2() == 0.0 || v.magnitude2() == 0.0 {
+        return 0.0;
+    }
+    let cos = u.normalize().dot(v.normalize());
+    cos.max(-1.0).min(1.0).acos().to_degrees()
+}
+
+/// The quantity measured by a [`Dimension`](struct.Dimension.html).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Kind {
+    /// Distance between two points.
+    Distance,
+    /// Angle at a vertex between two points.
+    Angle,
+}
+
+/// A measurement displayed with dimension lines and a text label.
+///
+/// Created with [`Factory::distance_dimension`] or [`Factory::angle_dimension`].
+///
+/// [`Factory::distance_dimension`]: ../struct.Factory.html#method.distance_dimension
+/// [`Factory::angle_dimension`]: ../struct.Factory.html#method.angle_dimension
+#[derive(Clone, Debug)]
+pub struct Dimension {
+    group: Group,
+    lines: Vec<Mesh>,
+    label: Text,
+    kind: Kind,
+    points: Vec<Point3<f32>>,
+    precision: usize,
+}
+three_object!(Dimension::group);
+
+impl Dimension {
+    pub(crate) fn new(
+        group: Group,
+        lines: Vec<Mesh>,
+        label: Text,
+        kind: Kind,
+        points: Vec<Point3<f32>>,
+    ) -> Self {
+        for line in &lines {
+            group.add(line);
+        }
+        group.add(&label);
+        let mut dimension = Dimension {
+            group,
+            lines,
+            label,
+            kind,
+            points,
+            precision: 2,
+        };
+        dimension.refresh();
+        dimension
+    }
+
+    /// Returns the quantity measured.
+    pub fn kind(&self) -> Kind {
+        self.kind
+    }
+
+    /// Returns the measured value: a distance in world units or an angle in degrees.
+    pub fn value(&self) -> f32 {
+        match self.kind {
+            Kind::Distance => distance(self.points[0], self.points[1]),
+            Kind::Angle => angle(self.points[0], self.points[1], self.points[2]),
+        }
+    }
+
+    /// Moves the measured point at `index`.
+    ///
+    /// Distances are measured between points `0` and `1`. Angles are measured at
+    /// point `1` between points `0` and `2`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of range for the kind of dimension.
+    pub fn set_point<P>(
+        &mut self,
+        index: usize,
+        point: P,
+    ) where
+        P: Into<mint::Point3<f32>>,
+    {
+        self.points[index] = Point3::from(point.into());
+        self.refresh();
+    }
+
+    /// Sets the number of decimal places displayed in the label.
+    ///
+    /// Defaults to 2.
+    pub fn set_precision(
+        &mut self,
+        precision: usize,
+    ) {
+        self.precision = precision;
+        self.refresh();
+    }
+
+    /// Returns the label displaying the measured value, e.g. to change its color or font size.
+    pub fn label(&mut self) -> &mut Text {
+        &mut self.label
+    }
+
+    /// Moves the label to the screen position of the measurement as seen by `camera`.
+    ///
+    /// Call this each frame before rendering. The label is hidden when the
+    /// measurement isn't in front of the camera.
+    pub fn update(
+        &mut self,
+        window: &mut Window,
+        camera: &Camera,
+    ) {
+        let anchor = match self.kind {
+            Kind::Distance => self.points[0].midpoint(self.points[1]),
+            Kind::Angle => self.points[1],
+        };
+        match window.world_to_screen(camera, anchor) {
+            Some(pos) => {
+                self.label.set_visible(true);
+                self.label.set_pos(pos);
+            }
+            None => self.label.set_visible(false),
+        }
+    }
+
+    /// Updates the dimension lines and the label text after a change.
+    fn refresh(&mut self) {
+        let segments = match self.kind {
+            Kind::Distance => vec![(self.points[0], self.points[1])],
+            Kind::Angle => vec![(self.points[1], self.points[0]), (self.points[1], self.points[2])],
+        };
+        for (line, &(start, end)) in self.lines.iter().zip(segments.iter()) {
+            place_line(line, start, end);
+        }
+        let text = match self.kind {
+            Kind::Distance => format!("{:.*}", self.precision, self.value()),
+            Kind::Angle => format!("{:.*}\u{b0}", self.precision, self.value()),
+        };
+        self.label.set_text(text);
+    }
+}
+
+/// Stretches a line along the local Z axis of unit length between `start` and `end`.
+fn place_line(
+    line: &Mesh,
+    start: Point3<f32>,
+    end: Point3<f32>,
+) {
+    let offset = end - start;
+    let length = offset.magnitude();
+    let rotation = if length > 0.0 {
+        Quaternion::from_arc(Vector3::unit_z(), offset / length, None)
+    } else {
+        Quaternion::new(1.0, 0.0, 0.0, 0.0)
+    };
+    let position: mint::Point3<f32> = start.into();
+    line.set_transform(position, rotation, 1.0);
+    line.set_nonuniform_scale([1.0, 1.0, length]);
+}
+
+/// Returns the vertices of the unit line used for dimension lines.
+pub(crate) fn unit_line() -> Vec<mint::Point3<f32>> {
+    vec![
+        Point3::origin().into(),
+        [0.0, 0.0, 1.0].into(),
+    ]
+}
@@ -1,11 +1,18 @@
-use geometry::Geometry;
-use hub::Operation;
-use material::Material;
+use collision::Aabb;
+use color::Color;
+use geometry::{Geometry, Shape};
+use hub::{Hub, Operation, SubNode};
+use material::{self, Material};
+use node::NodePointer;
 use object::{self, DowncastObject, ObjectType};
 use render::DynamicData;
-use skeleton::Skeleton;
+use scene::Scene;
+use skeleton::{self, Skeleton};
 
+use mint;
+use std::fmt;
 use std::hash::{Hash, Hasher};
+use std::sync::Arc;
 
 /// [`Geometry`](struct.Geometry.html) with some [`Material`](struct.Material.html).
 ///
@@ -22,7 +29,7 @@ use std::hash::{Hash, Hasher};
 ///     [ 0.5, -0.5, 0.0].into(),
 /// ];
 /// let geometry = three::Geometry::with_vertices(vertices);
-/// let red_material = three::material::Basic { color: three::color::RED, map: None };
+/// let red_material = three::material::Basic { color: three::color::RED, map: None, opacity: 1.0 };
 /// let mesh = factory.mesh(geometry, red_material);
 /// # let _ = mesh;
 /// ```
@@ -38,7 +45,7 @@ use std::hash::{Hash, Hasher};
 /// #     [ 0.5, -0.5, 0.0].into(),
 /// # ];
 /// # let geometry = three::Geometry::with_vertices(vertices);
-/// # let red_material = three::material::Basic { color: three::color::RED, map: None };
+/// # let red_material = three::material::Basic { color: three::color::RED, map: None, opacity: 1.0 };
 /// # let mesh = factory.mesh(geometry, red_material);
 /// use three::Object;
 /// let mut duplicate = factory.mesh_instance(&mesh);
@@ -57,7 +64,7 @@ use std::hash::{Hash, Hasher};
 /// #     [ 0.5, -0.5, 0.0].into(),
 /// # ];
 /// # let geometry = three::Geometry::with_vertices(vertices);
-/// # let red_material = three::material::Basic { color: three::color::RED, map: None };
+/// # let red_material = three::material::Basic { color: three::color::RED, map: None, opacity: 1.0 };
 /// # let mesh = factory.mesh(geometry, red_material);
 /// let yellow_material = three::material::Wireframe { color: three::color::YELLOW };
 /// # use three::Object;
@@ -114,6 +121,113 @@ impl Hash for DynamicMesh {
     }
 }
 
+/// Per-draw state passed to a callback registered with
+/// [`Mesh::set_on_before_render`](struct.Mesh.html#method.set_on_before_render).
+///
+/// The `color` and `params` fields may be modified by the callback and are
+/// uploaded as the `i_Color` and `i_MatParams` instance attributes for this draw
+/// only. They have no effect on `Material::Pbr` meshes.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BeforeRender {
+    /// Time in seconds since the renderer was created.
+    pub time: f32,
+    /// World position of the camera.
+    pub camera_position: mint::Point3<f32>,
+    /// View matrix of the camera.
+    pub view: mint::ColumnMatrix4<f32>,
+    /// Projection matrix of the camera.
+    pub projection: mint::ColumnMatrix4<f32>,
+    /// World matrix of the mesh being drawn.
+    pub world: mint::ColumnMatrix4<f32>,
+    /// Instance color, initialized from the material.
+    pub color: Color,
+    /// Instance material parameters, initialized from the material.
+    pub params: [f32; 4],
+}
+
+#[derive(Clone)]
+pub(crate) struct BeforeRenderCallback(pub(crate) Arc<Fn(&mut BeforeRender) + Send + Sync>);
+
+impl fmt::Debug for BeforeRenderCallback {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter,
+    ) -> fmt::Result {
+        write!(f, "BeforeRenderCallback")
+    }
+}
+
+/// Limits beyond which a mesh stops being drawn, so that small or distant
+/// clutter can be skipped.
+///
+/// Set with [`Mesh::set_fade`](struct.Mesh.html#method.set_fade). Skinned meshes
+/// and meshes without known bounds are only faded by distance, measured to
+/// their origin.
+///
+/// The built-in mesh shaders dither the transition, while sprites and custom
+/// shaders are drawn in full until hidden. Hidden meshes still cast shadows.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Fade {
+    /// Distance from the camera to the center of the mesh bounds beyond which
+    /// the mesh is hidden.
+    ///
+    /// Default: infinity.
+    pub max_distance: f32,
+    /// Share of the screen height covered by the bounding sphere of the mesh
+    /// below which the mesh is hidden.
+    ///
+    /// Default: `0.0`.
+    pub min_screen_size: f32,
+    /// Fraction of each limit over which the mesh is dithered out before
+    /// reaching it, instead of disappearing at once.
+    ///
+    /// Default: `0.0`.
+    pub transition: f32,
+}
+
+impl Default for Fade {
+    fn default() -> Self {
+        Fade {
+            max_distance: ::std::f32::INFINITY,
+            min_screen_size: 0.0,
+            transition: 0.0,
+        }
+    }
+}
+
+impl Fade {
+    /// Returns how much of the mesh is drawn, from `0.0` for hidden to `1.0`
+    /// for fully drawn, at `distance` from the camera and covering
+    /// `screen_size` of the screen height.
+    pub(crate) fn amount(
+        &self,
+        distance: f32,
+        screen_size: f32,
+    ) -> f32 {
+        let ramp = |value: f32, limit: f32| {
+            let band = limit * self.transition;
+            if value <= 0.0 {
+                0.0
+            } else if band > 0.0 {
+                (value / band).min(1.0)
+            } else {
+                1.0
+            }
+        };
+        let by_distance = if self.max_distance.is_finite() {
+            ramp(self.max_distance - distance, self.max_distance)
+        } else {
+            1.0
+        };
+        let by_size = if self.min_screen_size > 0.0 {
+            ramp(screen_size - self.min_screen_size, self.min_screen_size)
+        } else {
+            1.0
+        };
+        by_distance.min(by_size)
+    }
+}
+
 impl Mesh {
     /// Set mesh material.
     pub fn set_material<M: Into<Material>>(
@@ -123,6 +237,37 @@ impl Mesh {
         self.as_ref().send(Operation::SetMaterial(material.into()));
     }
 
+    /// Set the pipeline state (blending, culling, depth) used together with the material.
+    ///
+    /// See [`material::State`](material/struct.State.html).
+    pub fn set_render_state(
+        &self,
+        state: material::State,
+    ) {
+        self.as_ref().send(Operation::SetRenderState(state));
+    }
+
+    /// Set the light layers affecting the mesh.
+    ///
+    /// A light only illuminates the mesh if its layers, set with `set_layers`,
+    /// share at least one bit with `mask`. All bits are set by default.
+    pub fn set_light_mask(
+        &self,
+        mask: u32,
+    ) {
+        self.as_ref().send(Operation::SetLightMask(mask));
+    }
+
+    /// Set the distance and screen size limits beyond which the mesh is hidden.
+    ///
+    /// See [`Fade`](struct.Fade.html).
+    pub fn set_fade(
+        &self,
+        fade: Fade,
+    ) {
+        self.as_ref().send(Operation::SetFade(fade));
+    }
+
     /// Bind a skeleton to the mesh.
     pub fn set_skeleton(
         &self,
@@ -130,6 +275,152 @@ impl Mesh {
     ) {
         self.as_ref().send(Operation::SetSkeleton(skeleton));
     }
+
+    /// Computes the current vertex positions of the mesh on the CPU, in its local space.
+    ///
+    /// The positions include the morph target weights and the pose of the bound
+    /// skeleton, as rendered, e.g. for collision or picking against animated meshes.
+    ///
+    /// Returns `None` if the mesh wasn't created from a [`Geometry`], e.g. when
+    /// loaded from an OBJ file, or was created with [`Factory::keep_geometry`]
+    /// disabled. Also returns `None`, logging an error, if the geometry uses
+    /// joints the bound skeleton doesn't have.
+    ///
+    /// [`Geometry`]: struct.Geometry.html
+    /// [`Factory::keep_geometry`]: struct.Factory.html#method.keep_geometry
+    pub fn sampled_vertices(
+        &self,
+        scene: &Scene,
+    ) -> Option<Vec<mint::Point3<f32>>> {
+        let mut hub = scene.hub.lock().unwrap();
+        hub.process_messages();
+        sample_shape(&hub, &scene.first_child, self.as_ref()).map(|(_, shape)| shape.vertices)
+    }
+
+    /// Returns the axis-aligned bounding box of the mesh in world space.
+    ///
+    /// The box encloses the geometry with all morph targets at full weight, but
+    /// not the deformation by a skeleton. Returns `None` if the mesh isn't in
+    /// `scene` or wasn't created from a [`Geometry`].
+    ///
+    /// [`Geometry`]: struct.Geometry.html
+    pub fn bounding_box(
+        &self,
+        scene: &Scene,
+    ) -> Option<Aabb> {
+        let mut hub = scene.hub.lock().unwrap();
+        hub.process_messages();
+        let bounds = match hub[self].sub_node {
+            SubNode::Visual(_, ref gpu_data, _) => gpu_data.bounds,
+            _ => unreachable!(),
+        };
+        let mx_world = hub.walk_all(&scene.first_child)
+            .find(|w| w.node_ptr == self.object.node)
+            .map(|w| w.world_matrix());
+        match (bounds, mx_world) {
+            (Some(bounds), Some(mx_world)) => Some(bounds.transformed(mx_world)),
+            _ => None,
+        }
+    }
+
+    /// Register a callback invoked just before the mesh is drawn each frame.
+    ///
+    /// The callback receives the elapsed time and camera information, and may
+    /// tweak the per-instance uniforms of the mesh for that draw. This is
+    /// mostly useful together with custom pipelines, see
+    /// [`BeforeRender`](struct.BeforeRender.html).
+    ///
+    /// The callback runs while the renderer holds the lock of the scene, so it
+    /// must not call anything that locks the scene again, such as
+    /// [`Scene::add`], [`Scene::sync_guard`] or the object constructors of
+    /// `Factory`: rendering would deadlock. Object
+    /// setters like [`Object::set_position`] only queue a message and are safe
+    /// to call; they take effect on the next frame.
+    ///
+    /// [`Scene::add`]: ../scene/struct.Scene.html#method.add
+    /// [`Scene::sync_guard`]: ../scene/struct.Scene.html#method.sync_guard
+    /// [`Object::set_position`]: ../object/trait.Object.html#method.set_position
+    pub fn set_on_before_render<F>(
+        &self,
+        callback: F,
+    ) where
+        F: 'static + Fn(&mut BeforeRender) + Send + Sync,
+    {
+        let callback = BeforeRenderCallback(Arc::new(callback));
+        self.as_ref().send(Operation::SetBeforeRender(Some(callback)));
+    }
+
+    /// Remove the callback registered with
+    /// [`set_on_before_render`](#method.set_on_before_render), if any.
+    pub fn clear_on_before_render(&self) {
+        self.as_ref().send(Operation::SetBeforeRender(None));
+    }
+}
+
+/// Applies the morph target `weights` to the base shape of `geometry`.
+fn morph(
+    geometry: &Geometry,
+    weights: &[f32],
+) -> Shape {
+    let mut shape = geometry.base.clone();
+    for (target, &weight) in geometry.shapes.iter().zip(weights) {
+        if weight == 0.0 {
+            continue;
+        }
+        for (out, displacement) in shape.vertices.iter_mut().zip(&target.vertices) {
+            out.x += weight * displacement.x;
+            out.y += weight * displacement.y;
+            out.z += weight * displacement.z;
+        }
+        for (out, displacement) in shape.normals.iter_mut().zip(&target.normals) {
+            out.x += weight * displacement.x;
+            out.y += weight * displacement.y;
+            out.z += weight * displacement.z;
+        }
+    }
+    shape
+}
+
+/// Computes the current shape of the visual `object` on the CPU, including the
+/// morph target weights and the pose of its skeleton.
+///
+/// Returns the source geometry along with the shape, or `None` if the visual
+/// has no source geometry or its joint data doesn't match its skeleton.
+pub(crate) fn sample_shape(
+    hub: &Hub,
+    first_child: &Option<NodePointer>,
+    object: &object::Base,
+) -> Option<(Arc<Geometry>, Shape)> {
+    let (geometry, weights, skeleton) = match hub[object].sub_node {
+        SubNode::Visual(_, ref gpu_data, ref skeleton) => {
+            let weights = gpu_data
+                .displacement_contributions
+                .iter()
+                .map(|contribution| contribution.weight)
+                .collect::<Vec<_>>();
+            (gpu_data.geometry.clone(), weights, skeleton.clone())
+        }
+        _ => unreachable!(),
+    };
+    let geometry = match geometry {
+        Some(geometry) => geometry,
+        None => return None,
+    };
+    let shape = morph(&geometry, &weights);
+    let shape = match skeleton {
+        Some(skeleton) => {
+            let matrices = hub.joint_matrices(first_child, &skeleton.object.node);
+            match skeleton::skin_shape(&shape, &geometry.joints, &matrices) {
+                Ok(shape) => shape,
+                Err(err) => {
+                    error!("Failed to skin the mesh: {}", err);
+                    return None;
+                }
+            }
+        }
+        None => shape,
+    };
+    Some((geometry, shape))
 }
 
 impl DynamicMesh {
@@ -145,4 +436,35 @@ impl DynamicMesh {
     ) {
         self.as_ref().send(Operation::SetMaterial(material.into()));
     }
+
+    /// Set the pipeline state (blending, culling, depth) used together with the material.
+    ///
+    /// See [`material::State`](material/struct.State.html).
+    pub fn set_render_state(
+        &mut self,
+        state: material::State,
+    ) {
+        self.as_ref().send(Operation::SetRenderState(state));
+    }
+
+    /// Set the light layers affecting the mesh.
+    ///
+    /// A light only illuminates the mesh if its layers, set with `set_layers`,
+    /// share at least one bit with `mask`. All bits are set by default.
+    pub fn set_light_mask(
+        &mut self,
+        mask: u32,
+    ) {
+        self.as_ref().send(Operation::SetLightMask(mask));
+    }
+
+    /// Set the distance and screen size limits beyond which the mesh is hidden.
+    ///
+    /// See [`Fade`](struct.Fade.html).
+    pub fn set_fade(
+        &mut self,
+        fade: Fade,
+    ) {
+        self.as_ref().send(Operation::SetFade(fade));
+    }
 }
@@ -0,0 +1,281 @@
+//! Navigation meshes for simple path finding.
+//!
+//! A [`NavMesh`] is baked from the walkable triangles of scene meshes: those
+//! facing up within a maximum slope and lying within a height range. Paths are
+//! searched across adjacent triangles with A*.
+//!
+//! Requires the `navmesh` feature.
+//!
+//! ```rust,no_run
+//! # let mut window = three::Window::new("");
+//! # let ground = window.factory.mesh(three::Geometry::plane(10.0, 10.0), three::material::Basic::default());
+//! use three::navmesh::{NavMesh, Settings};
+//!
+//! let navmesh = NavMesh::bake(&window.scene, &[&ground], &Settings::default());
+//! if let Some(path) = navmesh.find_path([-4.0, 0.0, -4.0], [4.0, 0.0, 4.0]) {
+//!     println!("{} waypoints", path.len());
+//! }
+//! ```
+//!
+//! [`NavMesh`]: struct.NavMesh.html
+
+use cgmath::{EuclideanSpace, InnerSpace, Point3};
+use mint;
+
+use collision;
+use mesh::Mesh;
+use raycast::{self, Pose};
+use scene::Scene;
+
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+use std::ops::Range;
+
+/// Size of the grid vertices are snapped to when finding shared edges.
+const WELD_EPSILON: f32 = 1e-4;
+
+/// Filters selecting the walkable triangles when baking a [`NavMesh`](struct.NavMesh.html).
+#[derive(Clone, Debug, PartialEq)]
+pub struct Settings {
+    /// Steepest walkable slope, in degrees.
+    ///
+    /// Default: `45.0`.
+    pub max_slope: f32,
+    /// Range of walkable heights along the Y axis.
+    ///
+    /// Default: unbounded.
+    pub height: Range<f32>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            max_slope: 45.0,
+            height: ::std::f32::NEG_INFINITY .. ::std::f32::INFINITY,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Polygon {
+    corners: [Point3<f32>; 3],
+    /// Adjacent triangles and the shared edge leading to them.
+    neighbors: Vec<(usize, [Point3<f32>; 2])>,
+}
+
+/// Walkable surface baked from scene geometry.
+#[derive(Clone, Debug)]
+pub struct NavMesh {
+    polygons: Vec<Polygon>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Candidate {
+    cost: f32,
+    polygon: usize,
+}
+
+impl Eq for Candidate {}
+
+impl Ord for Candidate {
+    fn cmp(
+        &self,
+        other: &Self,
+    ) -> Ordering {
+        // reversed for a min-heap
+        other
+            .cost
+            .partial_cmp(&self.cost)
+            .unwrap_or(Ordering::Equal)
+    }
+}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(
+        &self,
+        other: &Self,
+    ) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+fn weld_key(point: Point3<f32>) -> [i64; 3] {
+    [
+        (point.x / WELD_EPSILON).round() as i64,
+        (point.y / WELD_EPSILON).round() as i64,
+        (point.z / WELD_EPSILON).round() as i64,
+    ]
+}
+
+impl NavMesh {
+    /// Bakes the walkable triangles of `meshes`, in their current world
+    /// transforms, into a navigation mesh.
+    ///
+    /// Triangles are walkable when their front face points up, within
+    /// `settings.max_slope` of the Y axis, and all their corners lie within
+    /// `settings.height`. Meshes not in `scene` or not created from a
+    /// [`Geometry`](../struct.Geometry.html) are skipped.
+    pub fn bake(
+        scene: &Scene,
+        meshes: &[&Mesh],
+        settings: &Settings,
+    ) -> Self {
+        let min_up = settings.max_slope.to_radians().cos();
+        let mut polygons = Vec::new();
+        for mesh in meshes {
+            let triangles = match raycast::world_triangles(scene, mesh, Pose::Bind) {
+                Some(triangles) => triangles,
+                None => continue,
+            };
+            for corners in triangles {
+                let normal = (corners[1] - corners[0]).cross(corners[2] - corners[0]);
+                if normal.magnitude2() == 0.0 || normal.normalize().y < min_up {
+                    continue;
+                }
+                if !corners.iter().all(|c| c.y >= settings.height.start && c.y <= settings.height.end) {
+                    continue;
+                }
+                polygons.push(Polygon {
+                    corners,
+                    neighbors: Vec::new(),
+                });
+            }
+        }
+
+        let mut edges: HashMap<([i64; 3], [i64; 3]), Vec<usize>> = HashMap::new();
+        for (index, polygon) in polygons.iter().enumerate() {
+            for i in 0 .. 3 {
+                let a = weld_key(polygon.corners[i]);
+                let b = weld_key(polygon.corners[(i + 1) % 3]);
+                let key = if a < b { (a, b) } else { (b, a) };
+                edges.entry(key).or_insert_with(Vec::new).push(index);
+            }
+        }
+        for polygon_index in 0 .. polygons.len() {
+            let corners = polygons[polygon_index].corners;
+            for i in 0 .. 3 {
+                let (pa, pb) = (corners[i], corners[(i + 1) % 3]);
+                let (a, b) = (weld_key(pa), weld_key(pb));
+                let key = if a < b { (a, b) } else { (b, a) };
+                for &other in &edges[&key] {
+                    if other != polygon_index {
+                        polygons[polygon_index].neighbors.push((other, [pa, pb]));
+                    }
+                }
+            }
+        }
+
+        NavMesh { polygons }
+    }
+
+    /// Returns the number of walkable triangles.
+    pub fn len(&self) -> usize {
+        self.polygons.len()
+    }
+
+    /// Returns `true` if no triangle was walkable.
+    pub fn is_empty(&self) -> bool {
+        self.polygons.is_empty()
+    }
+
+    /// Returns the point of the navigation mesh closest to `point`.
+    pub fn closest_point<P: Into<mint::Point3<f32>>>(
+        &self,
+        point: P,
+    ) -> Option<mint::Point3<f32>> {
+        let point = Point3::from(point.into());
+        self.locate(point).map(|(_, p)| p.into())
+    }
+
+    /// Finds the nearest triangle to `point` and the closest point on it.
+    fn locate(
+        &self,
+        point: Point3<f32>,
+    ) -> Option<(usize, Point3<f32>)> {
+        let mut best: Option<(usize, Point3<f32>, f32)> = None;
+        for (index, polygon) in self.polygons.iter().enumerate() {
+            let closest = collision::closest_on_triangle(point, &polygon.corners);
+            let distance2 = (closest - point).magnitude2();
+            if best.map_or(true, |(_, _, d)| distance2 < d) {
+                best = Some((index, closest, distance2));
+            }
+        }
+        best.map(|(index, closest, _)| (index, closest))
+    }
+
+    /// Finds a path between the points of the navigation mesh closest to `start` and `end`.
+    ///
+    /// The path starts and ends at these points and passes through the middle of
+    /// the edges crossed between triangles. Returns `None` if the navigation mesh
+    /// is empty or the points aren't connected.
+    pub fn find_path<P, Q>(
+        &self,
+        start: P,
+        end: Q,
+    ) -> Option<Vec<mint::Point3<f32>>>
+    where
+        P: Into<mint::Point3<f32>>,
+        Q: Into<mint::Point3<f32>>,
+    {
+        let (start_polygon, start) = match self.locate(Point3::from(start.into())) {
+            Some(location) => location,
+            None => return None,
+        };
+        let (end_polygon, end) = match self.locate(Point3::from(end.into())) {
+            Some(location) => location,
+            None => return None,
+        };
+
+        let mut came_from: HashMap<usize, (usize, Point3<f32>)> = HashMap::new();
+        let mut cost = vec![::std::f32::INFINITY; self.polygons.len()];
+        let mut open = BinaryHeap::new();
+        cost[start_polygon] = 0.0;
+        open.push(Candidate {
+            cost: (end - start).magnitude(),
+            polygon: start_polygon,
+        });
+
+        while let Some(Candidate { polygon, .. }) = open.pop() {
+            if polygon == end_polygon {
+                break;
+            }
+            let from = came_from
+                .get(&polygon)
+                .map_or(start, |&(_, portal)| portal);
+            for &(next, edge) in &self.polygons[polygon].neighbors {
+                let portal = edge[0].midpoint(edge[1]);
+                let next_cost = cost[polygon] + (portal - from).magnitude();
+                if next_cost < cost[next] {
+                    cost[next] = next_cost;
+                    came_from.insert(next, (polygon, portal));
+                    open.push(Candidate {
+                        cost: next_cost + (end - portal).magnitude(),
+                        polygon: next,
+                    });
+                }
+            }
+        }
+
+        if start_polygon != end_polygon && !came_from.contains_key(&end_polygon) {
+            return None;
+        }
+        let mut waypoints = vec![end];
+        let mut current = end_polygon;
+        while current != start_polygon {
+            let (previous, portal) = came_from[&current];
+            waypoints.push(portal);
+            current = previous;
+        }
+        waypoints.push(start);
+        waypoints.reverse();
+        Some(waypoints.into_iter().map(|p| p.into()).collect())
+    }
+
+    /// Returns the walkable triangles, e.g. to visualize the navigation mesh.
+    pub fn triangles(&self) -> Vec<[mint::Point3<f32>; 3]> {
+        self.polygons
+            .iter()
+            .map(|p| [p.corners[0].into(), p.corners[1].into(), p.corners[2].into()])
+            .collect()
+    }
+}
@@ -4,6 +4,7 @@ use mint;
 
 use hub::SubNode;
 use material::Material;
+use mesh::BeforeRenderCallback;
 
 use std::marker::PhantomData;
 
@@ -20,6 +21,9 @@ pub(crate) struct NodeInternal {
     /// `true` if this node (and its children) are visible to cameras.
     pub(crate) visible: bool,
 
+    /// Opacity of this node, multiplied with the opacity of its ancestors.
+    pub(crate) opacity: f32,
+
     /// A user-defined name for the node.
     ///
     /// Not used internally to implement functionality. This is used by users to identify nodes
@@ -37,6 +41,19 @@ pub(crate) struct NodeInternal {
 
     /// Context specific-data, for example, `UiText`, `Visual` or `Light`.
     pub(crate) sub_node: SubNode,
+
+    /// Per-axis scale applied on top of `transform`, inherited by children.
+    pub(crate) nonuniform_scale: cgmath::Vector3<f32>,
+
+    /// Callback invoked by the renderer just before drawing a `Visual`.
+    pub(crate) on_before_render: Option<BeforeRenderCallback>,
+
+    /// `true` if this node was drawn by the camera during the last rendered frame.
+    pub(crate) rendered: bool,
+
+    /// Revision of the hub at the last change that may have moved this node
+    /// and its children, see `Hub::touch_node`.
+    pub(crate) revision: u64,
 }
 
 impl NodeInternal {
@@ -44,7 +61,9 @@ impl NodeInternal {
         Node {
             transform: self.transform.into(),
             visible: self.visible,
+            opacity: self.opacity,
             name: self.name.clone(),
+            nonuniform_scale: self.nonuniform_scale.into(),
             material: match self.sub_node {
                 SubNode::Visual(ref mat, _, _) => Some(mat.clone()),
                 _ => None,
@@ -58,11 +77,16 @@ impl From<SubNode> for NodeInternal {
     fn from(sub: SubNode) -> Self {
         NodeInternal {
             visible: true,
+            opacity: 1.0,
             name: None,
             transform: cgmath::Transform::one(),
             world_transform: cgmath::Transform::one(),
             next_sibling: None,
             sub_node: sub,
+            nonuniform_scale: cgmath::Vector3::new(1.0, 1.0, 1.0),
+            on_before_render: None,
+            rendered: false,
+            revision: 0,
         }
     }
 }
@@ -128,6 +152,11 @@ pub struct Node<Space> {
     /// Is `Node` visible by cameras or not?
     pub visible: bool,
 
+    /// Opacity in `[0, 1]`, see [`Object::set_opacity`].
+    ///
+    /// [`Object::set_opacity`]: ../object/trait.Object.html#method.set_opacity
+    pub opacity: f32,
+
     /// The name of the node, if any.
     pub name: Option<String>,
 
@@ -135,6 +164,15 @@ pub struct Node<Space> {
     // NOTE: this really begs for `euclid`-style parametrized math types.
     pub transform: Transform,
 
+    /// Per-axis scale, applied before the orientation of `transform`.
+    ///
+    /// In world space, this is the stretch left over after the uniform scale of
+    /// `transform`, as split by [`math::decompose`]. Any shear caused by a
+    /// rotated child of a stretched parent is dropped.
+    ///
+    /// [`math::decompose`]: ../math/fn.decompose.html
+    pub nonuniform_scale: mint::Vector3<f32>,
+
     /// Material in case this `Node` has it.
     pub material: Option<Material>,
 
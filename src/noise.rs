@@ -0,0 +1,379 @@
+//! Seeded coherent noise for procedural content.
+//!
+//! [`Perlin`] and [`Simplex`] noise vary smoothly in space, and [`Fbm`] sums
+//! several octaves of either to add detail at smaller scales, e.g. for
+//! terrain heights, cloud shapes or particle turbulence.
+//!
+//! ```rust
+//! use three::noise::{Fbm, Noise, Simplex};
+//!
+//! let noise = Fbm::new(Simplex::new(42));
+//! let height = |x: f32, z: f32| 2.0 * noise.get2(0.1 * x, 0.1 * z);
+//! assert_eq!(height(3.0, 4.0), height(3.0, 4.0));
+//! ```
+//!
+//! Noise values are roughly in `[-1, 1]`, and the same seed always gives the
+//! same values.
+//!
+//! [`Fbm`]: struct.Fbm.html
+//! [`Perlin`]: struct.Perlin.html
+//! [`Simplex`]: struct.Simplex.html
+
+use util::Rng;
+
+/// A source of coherent noise in two or three dimensions.
+pub trait Noise {
+    /// Returns the noise value at `(x, y)`.
+    fn get2(
+        &self,
+        x: f32,
+        y: f32,
+    ) -> f32;
+
+    /// Returns the noise value at `(x, y, z)`.
+    fn get3(
+        &self,
+        x: f32,
+        y: f32,
+        z: f32,
+    ) -> f32;
+}
+
+/// Gradient directions of simplex noise: the midpoints of the edges of a cube.
+const GRADIENTS: [[f32; 3]; 12] = [
+    [1.0, 1.0, 0.0],
+    [-1.0, 1.0, 0.0],
+    [1.0, -1.0, 0.0],
+    [-1.0, -1.0, 0.0],
+    [1.0, 0.0, 1.0],
+    [-1.0, 0.0, 1.0],
+    [1.0, 0.0, -1.0],
+    [-1.0, 0.0, -1.0],
+    [0.0, 1.0, 1.0],
+    [0.0, -1.0, 1.0],
+    [0.0, 1.0, -1.0],
+    [0.0, -1.0, -1.0],
+];
+
+/// Returns a random permutation of `0 .. 256`, repeated twice to avoid
+/// wrapping indices.
+fn permutation(seed: u32) -> Vec<usize> {
+    let mut rng = Rng::new(seed);
+    let mut values = (0 .. 256).collect::<Vec<usize>>();
+    for i in (1 .. 256).rev() {
+        let j = rng.next() as usize % (i + 1);
+        values.swap(i, j);
+    }
+    let mut perm = values.clone();
+    perm.extend(values);
+    perm
+}
+
+/// Returns the integer lattice cell of `x`, wrapped to `0 .. 256`.
+fn cell(x: f32) -> usize {
+    (x.floor() as i32 & 255) as usize
+}
+
+fn fade(t: f32) -> f32 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+fn lerp(
+    t: f32,
+    a: f32,
+    b: f32,
+) -> f32 {
+    a + t * (b - a)
+}
+
+/// Gradient noise on a square lattice, after Ken Perlin's improved noise.
+#[derive(Clone, Debug)]
+pub struct Perlin {
+    perm: Vec<usize>,
+}
+
+impl Perlin {
+    /// Creates Perlin noise whose gradients are shuffled by `seed`.
+    pub fn new(seed: u32) -> Self {
+        Perlin {
+            perm: permutation(seed),
+        }
+    }
+
+    fn grad2(
+        hash: usize,
+        x: f32,
+        y: f32,
+    ) -> f32 {
+        match hash & 7 {
+            0 => x + y,
+            1 => -x + y,
+            2 => x - y,
+            3 => -x - y,
+            4 => x,
+            5 => -x,
+            6 => y,
+            _ => -y,
+        }
+    }
+
+    fn grad3(
+        hash: usize,
+        x: f32,
+        y: f32,
+        z: f32,
+    ) -> f32 {
+        let h = hash & 15;
+        let u = if h < 8 { x } else { y };
+        let v = if h < 4 {
+            y
+        } else if h == 12 || h == 14 {
+            x
+        } else {
+            z
+        };
+        (if h & 1 == 0 { u } else { -u }) + (if h & 2 == 0 { v } else { -v })
+    }
+}
+
+impl Noise for Perlin {
+    fn get2(
+        &self,
+        x: f32,
+        y: f32,
+    ) -> f32 {
+        let p = &self.perm;
+        let (xi, yi) = (cell(x), cell(y));
+        let (x, y) = (x - x.floor(), y - y.floor());
+        let (u, v) = (fade(x), fade(y));
+        let (a, b) = (p[xi] + yi, p[xi + 1] + yi);
+        lerp(
+            v,
+            lerp(u, Self::grad2(p[a], x, y), Self::grad2(p[b], x - 1.0, y)),
+            lerp(u, Self::grad2(p[a + 1], x, y - 1.0), Self::grad2(p[b + 1], x - 1.0, y - 1.0)),
+        )
+    }
+
+    fn get3(
+        &self,
+        x: f32,
+        y: f32,
+        z: f32,
+    ) -> f32 {
+        let p = &self.perm;
+        let (xi, yi, zi) = (cell(x), cell(y), cell(z));
+        let (x, y, z) = (x - x.floor(), y - y.floor(), z - z.floor());
+        let (u, v, w) = (fade(x), fade(y), fade(z));
+        let a = p[xi] + yi;
+        let (aa, ab) = (p[a] + zi, p[a + 1] + zi);
+        let b = p[xi + 1] + yi;
+        let (ba, bb) = (p[b] + zi, p[b + 1] + zi);
+        lerp(
+            w,
+            lerp(
+                v,
+                lerp(u, Self::grad3(p[aa], x, y, z), Self::grad3(p[ba], x - 1.0, y, z)),
+                lerp(u, Self::grad3(p[ab], x, y - 1.0, z), Self::grad3(p[bb], x - 1.0, y - 1.0, z)),
+            ),
+            lerp(
+                v,
+                lerp(u, Self::grad3(p[aa + 1], x, y, z - 1.0), Self::grad3(p[ba + 1], x - 1.0, y, z - 1.0)),
+                lerp(
+                    u,
+                    Self::grad3(p[ab + 1], x, y - 1.0, z - 1.0),
+                    Self::grad3(p[bb + 1], x - 1.0, y - 1.0, z - 1.0),
+                ),
+            ),
+        )
+    }
+}
+
+/// Gradient noise on a simplex lattice, after Stefan Gustavson's reference
+/// implementation.
+///
+/// Simplex noise is cheaper than Perlin noise in three dimensions and has
+/// fewer directional artifacts.
+#[derive(Clone, Debug)]
+pub struct Simplex {
+    perm: Vec<usize>,
+}
+
+impl Simplex {
+    /// Creates simplex noise whose gradients are shuffled by `seed`.
+    pub fn new(seed: u32) -> Self {
+        Simplex {
+            perm: permutation(seed),
+        }
+    }
+
+    /// Returns the contribution of a simplex corner with gradient `gradient`
+    /// at the `offset` from the corner.
+    fn corner(
+        falloff: f32,
+        gradient: usize,
+        offset: [f32; 3],
+    ) -> f32 {
+        let t = falloff - offset[0] * offset[0] - offset[1] * offset[1] - offset[2] * offset[2];
+        if t < 0.0 {
+            return 0.0;
+        }
+        let g = GRADIENTS[gradient % 12];
+        let t2 = t * t;
+        t2 * t2 * (g[0] * offset[0] + g[1] * offset[1] + g[2] * offset[2])
+    }
+}
+
+impl Noise for Simplex {
+    fn get2(
+        &self,
+        x: f32,
+        y: f32,
+    ) -> f32 {
+        const F2: f32 = 0.366_025_4; // (sqrt(3) - 1) / 2
+        const G2: f32 = 0.211_324_87; // (3 - sqrt(3)) / 6
+        let p = &self.perm;
+
+        // skew to find the simplex cell, and unskew its origin
+        let s = (x + y) * F2;
+        let (i, j) = ((x + s).floor(), (y + s).floor());
+        let t = (i + j) * G2;
+        let (x0, y0) = (x - (i - t), y - (j - t));
+        let (i1, j1) = if x0 > y0 { (1, 0) } else { (0, 1) };
+        let (x1, y1) = (x0 - i1 as f32 + G2, y0 - j1 as f32 + G2);
+        let (x2, y2) = (x0 - 1.0 + 2.0 * G2, y0 - 1.0 + 2.0 * G2);
+
+        let (ii, jj) = (cell(i), cell(j));
+        let n0 = Self::corner(0.5, p[ii + p[jj]], [x0, y0, 0.0]);
+        let n1 = Self::corner(0.5, p[ii + i1 + p[jj + j1]], [x1, y1, 0.0]);
+        let n2 = Self::corner(0.5, p[ii + 1 + p[jj + 1]], [x2, y2, 0.0]);
+        70.0 * (n0 + n1 + n2)
+    }
+
+    fn get3(
+        &self,
+        x: f32,
+        y: f32,
+        z: f32,
+    ) -> f32 {
+        const F3: f32 = 1.0 / 3.0;
+        const G3: f32 = 1.0 / 6.0;
+        let p = &self.perm;
+
+        // skew to find the simplex cell, and unskew its origin
+        let s = (x + y + z) * F3;
+        let (i, j, k) = ((x + s).floor(), (y + s).floor(), (z + s).floor());
+        let t = (i + j + k) * G3;
+        let (x0, y0, z0) = (x - (i - t), y - (j - t), z - (k - t));
+
+        // the second and third corners of the simplex, by the order of the offsets
+        let (first, second) = if x0 >= y0 {
+            if y0 >= z0 {
+                ([1, 0, 0], [1, 1, 0])
+            } else if x0 >= z0 {
+                ([1, 0, 0], [1, 0, 1])
+            } else {
+                ([0, 0, 1], [1, 0, 1])
+            }
+        } else if y0 < z0 {
+            ([0, 0, 1], [0, 1, 1])
+        } else if x0 < z0 {
+            ([0, 1, 0], [0, 1, 1])
+        } else {
+            ([0, 1, 0], [1, 1, 0])
+        };
+        let offset = |corner: [usize; 3], unskew: f32| {
+            [
+                x0 - corner[0] as f32 + unskew,
+                y0 - corner[1] as f32 + unskew,
+                z0 - corner[2] as f32 + unskew,
+            ]
+        };
+
+        let (ii, jj, kk) = (cell(i), cell(j), cell(k));
+        let hash = |c: [usize; 3]| p[ii + c[0] + p[jj + c[1] + p[kk + c[2]]]];
+        let n0 = Self::corner(0.6, hash([0, 0, 0]), [x0, y0, z0]);
+        let n1 = Self::corner(0.6, hash(first), offset(first, G3));
+        let n2 = Self::corner(0.6, hash(second), offset(second, 2.0 * G3));
+        let n3 = Self::corner(0.6, hash([1, 1, 1]), offset([1, 1, 1], 3.0 * G3));
+        32.0 * (n0 + n1 + n2 + n3)
+    }
+}
+
+/// Fractal Brownian motion: the sum of several octaves of a noise at
+/// increasing frequencies and decreasing amplitudes.
+///
+/// The sum is normalized by the total amplitude, so values stay roughly in
+/// `[-1, 1]`.
+#[derive(Clone, Debug)]
+pub struct Fbm<N> {
+    /// The noise summed at each octave.
+    pub noise: N,
+    /// Number of octaves.
+    ///
+    /// Default: `4`.
+    pub octaves: u32,
+    /// Frequency of the first octave.
+    ///
+    /// Default: `1.0`.
+    pub frequency: f32,
+    /// Frequency multiplier between successive octaves.
+    ///
+    /// Default: `2.0`.
+    pub lacunarity: f32,
+    /// Amplitude multiplier between successive octaves.
+    ///
+    /// Default: `0.5`.
+    pub gain: f32,
+}
+
+impl<N: Noise> Fbm<N> {
+    /// Creates fractal noise with default parameters from `noise`.
+    pub fn new(noise: N) -> Self {
+        Fbm {
+            noise,
+            octaves: 4,
+            frequency: 1.0,
+            lacunarity: 2.0,
+            gain: 0.5,
+        }
+    }
+
+    /// Sums the octaves of `sample`, called with the frequency of each octave.
+    fn sum<F: Fn(f32) -> f32>(
+        &self,
+        sample: F,
+    ) -> f32 {
+        let (mut frequency, mut amplitude) = (self.frequency, 1.0);
+        let (mut total, mut norm) = (0.0, 0.0);
+        for _ in 0 .. self.octaves {
+            total += amplitude * sample(frequency);
+            norm += amplitude;
+            frequency *= self.lacunarity;
+            amplitude *= self.gain;
+        }
+        if norm > 0.0 {
+            total / norm
+        } else {
+            0.0
+        }
+    }
+}
+
+impl<N: Noise> Noise for Fbm<N> {
+    fn get2(
+        &self,
+        x: f32,
+        y: f32,
+    ) -> f32 {
+        self.sum(|f| self.noise.get2(x * f, y * f))
+    }
+
+    fn get3(
+        &self,
+        x: f32,
+        y: f32,
+        z: f32,
+    ) -> f32 {
+        self.sum(|f| self.noise.get3(x * f, y * f, z * f))
+    }
+}
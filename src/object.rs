@@ -6,13 +6,16 @@ use std::sync::mpsc;
 
 use mint;
 
+#[cfg(feature = "audio")]
 use audio;
 use camera::Camera;
 use hub::{Hub, Message, Operation, SubLight, SubNode};
 use light;
+use math;
 use mesh::Mesh;
 use node::NodePointer;
-use scene::SyncGuard;
+use probe::ReflectionProbe;
+use scene::{Scene, SyncGuard};
 use skeleton::{Bone, Skeleton};
 use sprite::Sprite;
 use text::Text;
@@ -66,6 +69,23 @@ pub trait Object: AsRef<Base> {
         self.as_ref().clone()
     }
 
+    /// Detaches the object from its parent, which is either `scene` or a group
+    /// within it.
+    ///
+    /// The object and its children are no longer rendered, and their resources
+    /// are freed once all their handles are dropped. The object may be added to
+    /// a scene or group again.
+    fn detach(
+        &self,
+        scene: &mut Scene,
+    ) {
+        let mut hub = scene.hub.lock().unwrap();
+        hub.process_messages();
+        if !hub.detach(&mut scene.first_child, &self.as_ref().node) {
+            error!("Unable to find object to detach");
+        }
+    }
+
     /// Invisible objects are not rendered by cameras.
     fn set_visible(
         &self,
@@ -74,6 +94,21 @@ pub trait Object: AsRef<Base> {
         self.as_ref().send(Operation::SetVisible(visible));
     }
 
+    /// Sets the opacity of the object and its children, in `[0, 1]`.
+    ///
+    /// The opacity of a visual is multiplied with the opacity of its material
+    /// and of all its ancestors, so fading a group fades everything within it.
+    /// Visuals with an opacity below `1.0` are blended in the transparent pass,
+    /// and visuals with an opacity of `0.0` are not drawn at all.
+    ///
+    /// Default: `1.0`.
+    fn set_opacity(
+        &self,
+        opacity: f32,
+    ) {
+        self.as_ref().send(Operation::SetOpacity(opacity));
+    }
+
     /// Sets the name of the object.
     fn set_name<S: Into<String>>(
         &self,
@@ -126,6 +161,21 @@ pub trait Object: AsRef<Base> {
         self.as_ref().send(Operation::SetTransform(None, None, Some(scale)));
     }
 
+    /// Set a per-axis scale, applied on top of the uniform scale.
+    ///
+    /// Like [`set_scale`](#method.set_scale), the per-axis scale is inherited
+    /// by the children of the object. It stretches the local axes of the
+    /// object, before its orientation is applied.
+    fn set_nonuniform_scale<V>(
+        &self,
+        scale: V,
+    ) where
+        Self: Sized,
+        V: Into<mint::Vector3<f32>>,
+    {
+        self.as_ref().send(Operation::SetNonUniformScale(scale.into()));
+    }
+
     /// Set weights.
     //Note: needed for animations
     fn set_weights(
@@ -146,16 +196,16 @@ pub trait Object: AsRef<Base> {
         E: Into<mint::Point3<f32>>,
         T: Into<mint::Point3<f32>>,
     {
-        use cgmath::{InnerSpace, Point3, Quaternion, Rotation, Vector3};
+        use cgmath::{InnerSpace, Point3, Vector3};
         let p: [mint::Point3<f32>; 2] = [eye.into(), target.into()];
-        let dir = (Point3::from(p[0]) - Point3::from(p[1])).normalize();
+        let forward = (Point3::from(p[1]) - Point3::from(p[0])).normalize();
         let z = Vector3::unit_z();
         let up = match up {
-            Some(v) => Vector3::from(v).normalize(),
-            None if dir.dot(z).abs() < 0.99 => z,
+            Some(v) => Vector3::from(v),
+            None if forward.dot(z).abs() < 0.99 => z,
             None => Vector3::unit_y(),
         };
-        let q = Quaternion::look_at(dir, up).invert();
+        let q = math::look_rotation(forward, up);
         self.set_transform(p[0], q, 1.0);
     }
 }
@@ -218,6 +268,7 @@ impl Object for Base {
                 object: self.clone(),
             }),
 
+            #[cfg(feature = "audio")]
             SubNode::Audio(..) => ObjectType::AudioSource(audio::Source {
                 object: self.clone(),
             }),
@@ -239,6 +290,10 @@ impl Object for Base {
                 object: self.clone(),
             }),
 
+            SubNode::Probe(..) => ObjectType::ReflectionProbe(ReflectionProbe {
+                object: self.clone(),
+            }),
+
             SubNode::Light(light) => match light.sub_light {
                 SubLight::Ambient => ObjectType::AmbientLight(light::Ambient {
                     object: self.clone(),
@@ -270,6 +325,7 @@ impl Object for Base {
 #[derive(Debug, Clone)]
 pub enum ObjectType {
     /// An audio source.
+    #[cfg(feature = "audio")]
     AudioSource(audio::Source),
 
     /// An ambient light.
@@ -304,6 +360,9 @@ pub enum ObjectType {
 
     /// A camera.
     Camera(Camera),
+
+    /// A reflection probe.
+    ReflectionProbe(ReflectionProbe),
 }
 
 /// Marks an object type that can be downcast from a [`Base`].
@@ -0,0 +1,434 @@
+//! Particle systems for effects such as smoke, sparks or fountains.
+//!
+//! A [`ParticleSystem`] spawns particles at the rate of its [`Emitter`] and
+//! moves them under gravity until the end of their life, changing their size,
+//! color and opacity along [`Curve`]s. The particles are simulated on the CPU
+//! with [`ParticleSystem::step`] and written to the GPU with
+//! [`Factory::apply_particles`], to be rendered as quads facing the camera
+//! like the [`Points`] material.
+//!
+//! ```rust,no_run
+//! # let mut window = three::Window::new("");
+//! # let camera = window.factory.perspective_camera(60.0, 0.1 .. 10.0);
+//! use three::particles::{Curve, Emitter};
+//!
+//! let emitter = Emitter {
+//!     rate: 200.0,
+//!     spread: 0.2,
+//!     speed: 4.0 .. 6.0,
+//!     size: Curve::linear(0.1, 0.02),
+//!     color: Curve::linear(0x80C0FF, 0xFFFFFF),
+//!     opacity: Curve::linear(1.0, 0.0),
+//!     ..Default::default()
+//! };
+//! let mut fountain = window.factory.particle_system(emitter);
+//! window.scene.add(&fountain);
+//! while window.update() {
+//!     fountain.step(window.input.delta_time());
+//!     window.factory.apply_particles(&fountain);
+//!     window.render(&camera);
+//! }
+//! ```
+//!
+//! Particles are simulated in the local space of the system: moving the
+//! system moves its live particles along.
+//!
+//! [`Curve`]: struct.Curve.html
+//! [`Emitter`]: struct.Emitter.html
+//! [`Factory::apply_particles`]: ../struct.Factory.html#method.apply_particles
+//! [`ParticleSystem`]: struct.ParticleSystem.html
+//! [`ParticleSystem::step`]: struct.ParticleSystem.html#method.step
+//! [`Points`]: ../material/struct.Points.html
+
+use cgmath::{InnerSpace, Vector3};
+use mint;
+
+use color::{self, Color};
+use material::Blend;
+use math;
+use object;
+use render::DynamicData;
+use texture::Texture;
+use util::Rng;
+
+use std::f32::consts::PI;
+use std::ops::Range;
+
+/// Values which can be interpolated along a [`Curve`](struct.Curve.html).
+pub trait Interpolate: Copy {
+    /// Returns the value at `t` between `self`, at `0.0`, and `other`, at
+    /// `1.0`.
+    fn interpolate(
+        &self,
+        other: &Self,
+        t: f32,
+    ) -> Self;
+}
+
+impl Interpolate for f32 {
+    fn interpolate(
+        &self,
+        other: &Self,
+        t: f32,
+    ) -> Self {
+        *self + t * (*other - *self)
+    }
+}
+
+/// Colors are interpolated per component, in sRGB space.
+impl Interpolate for Color {
+    fn interpolate(
+        &self,
+        other: &Self,
+        t: f32,
+    ) -> Self {
+        let mut result = 0;
+        for &shift in &[0, 8, 16] {
+            let a = ((*self >> shift) & 0xFF) as f32;
+            let b = ((*other >> shift) & 0xFF) as f32;
+            result |= (a.interpolate(&b, t).round() as u32) << shift;
+        }
+        result
+    }
+}
+
+/// A value changing over the life of a particle, from `0.0` at its birth to
+/// `1.0` at its death.
+///
+/// The value is linearly interpolated between keys of increasing times, and
+/// is constant before the first key and after the last one.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Curve<T> {
+    /// Times in `[0, 1]` and values of the keys, ordered by time.
+    pub keys: Vec<(f32, T)>,
+}
+
+impl<T: Interpolate> Curve<T> {
+    /// A value that doesn't change.
+    pub fn constant(value: T) -> Self {
+        Curve {
+            keys: vec![(0.0, value)],
+        }
+    }
+
+    /// A value changing linearly from `start` at birth to `end` at death.
+    pub fn linear(
+        start: T,
+        end: T,
+    ) -> Self {
+        Curve {
+            keys: vec![(0.0, start), (1.0, end)],
+        }
+    }
+
+    /// Returns the value at `t`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the curve has no keys.
+    pub fn sample(
+        &self,
+        t: f32,
+    ) -> T {
+        let next = self.keys.iter().position(|&(time, _)| time > t);
+        match next {
+            Some(0) => self.keys[0].1,
+            Some(i) => {
+                let (t0, ref v0) = self.keys[i - 1];
+                let (t1, ref v1) = self.keys[i];
+                v0.interpolate(v1, (t - t0) / (t1 - t0))
+            }
+            None => self.keys[self.keys.len() - 1].1,
+        }
+    }
+}
+
+/// Parameters of the particles spawned by a
+/// [`ParticleSystem`](struct.ParticleSystem.html).
+#[derive(Clone, Debug)]
+pub struct Emitter {
+    /// Number of particles spawned per second.
+    ///
+    /// Default: `50.0`.
+    pub rate: f32,
+
+    /// Maximum number of live particles. No particle is spawned while the
+    /// system is full.
+    ///
+    /// Default: `1000`.
+    pub max_particles: usize,
+
+    /// Range of the particle lifetimes, in seconds.
+    ///
+    /// Default: `1.0 .. 2.0`.
+    pub lifetime: Range<f32>,
+
+    /// Radius of the sphere around the origin the particles spawn in.
+    ///
+    /// Default: `0.0`.
+    pub radius: f32,
+
+    /// Mean direction of the initial particle velocities.
+    ///
+    /// Default: `[0.0, 1.0, 0.0]` (up).
+    pub direction: mint::Vector3<f32>,
+
+    /// Half angle of the cone around `direction` the initial velocities are
+    /// uniformly distributed in, in radians. `PI` spreads the particles in
+    /// all directions.
+    ///
+    /// Default: `0.3`.
+    pub spread: f32,
+
+    /// Range of the initial particle speeds.
+    ///
+    /// Default: `1.0 .. 2.0`.
+    pub speed: Range<f32>,
+
+    /// Acceleration applied to the particles.
+    ///
+    /// Default: `[0.0, -9.81, 0.0]`.
+    pub gravity: mint::Vector3<f32>,
+
+    /// Width and height of the particles in world units, over their life.
+    ///
+    /// Default: `0.1`.
+    pub size: Curve<f32>,
+
+    /// Color of the particles over their life, multiplied with `map`.
+    ///
+    /// Default: `0xFFFFFF` (white).
+    pub color: Curve<Color>,
+
+    /// Opacity of the particles over their life, in the range `[0.0, 1.0]`.
+    ///
+    /// Default: `1.0`.
+    pub opacity: Curve<f32>,
+
+    /// Texture applied to each particle.
+    ///
+    /// Default: `None`.
+    pub map: Option<Texture<[f32; 4]>>,
+
+    /// Blending of the particles with what lies behind them. Particles
+    /// never write to the depth buffer.
+    ///
+    /// Default: `Blend::Alpha`.
+    pub blend: Blend,
+
+    /// Seed of the random distributions of lifetimes, positions and
+    /// velocities.
+    ///
+    /// Default: `0`.
+    pub seed: u32,
+}
+
+impl Default for Emitter {
+    fn default() -> Self {
+        Emitter {
+            rate: 50.0,
+            max_particles: 1000,
+            lifetime: 1.0 .. 2.0,
+            radius: 0.0,
+            direction: [0.0, 1.0, 0.0].into(),
+            spread: 0.3,
+            speed: 1.0 .. 2.0,
+            gravity: [0.0, -9.81, 0.0].into(),
+            size: Curve::constant(0.1),
+            color: Curve::constant(color::WHITE),
+            opacity: Curve::constant(1.0),
+            map: None,
+            blend: Blend::Alpha,
+            seed: 0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Particle {
+    position: Vector3<f32>,
+    velocity: Vector3<f32>,
+    age: f32,
+    lifetime: f32,
+}
+
+/// The state of a particle, as written to the GPU.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct Sprite {
+    pub position: mint::Point3<f32>,
+    pub size: f32,
+    pub color: [f32; 4],
+}
+
+/// Particles spawned by an [`Emitter`](struct.Emitter.html).
+///
+/// Created with [`Factory::particle_system`](../struct.Factory.html#method.particle_system).
+pub struct ParticleSystem {
+    pub(crate) object: object::Base,
+    pub(crate) dynamic: DynamicData,
+    emitter: Emitter,
+    particles: Vec<Particle>,
+    rng: Rng,
+    pending: f32,
+    emitting: bool,
+}
+three_object!(ParticleSystem::object);
+
+impl ParticleSystem {
+    pub(crate) fn new(
+        object: object::Base,
+        dynamic: DynamicData,
+        emitter: Emitter,
+    ) -> Self {
+        ParticleSystem {
+            object,
+            dynamic,
+            rng: Rng::new(emitter.seed),
+            particles: Vec::with_capacity(emitter.max_particles),
+            emitter,
+            pending: 0.0,
+            emitting: true,
+        }
+    }
+
+    /// Returns the parameters of the spawned particles.
+    pub fn emitter(&self) -> &Emitter {
+        &self.emitter
+    }
+
+    /// Sets the number of particles spawned per second.
+    pub fn set_rate(
+        &mut self,
+        rate: f32,
+    ) {
+        self.emitter.rate = rate;
+    }
+
+    /// Starts or stops spawning particles. The live particles keep moving
+    /// until the end of their life.
+    pub fn set_emitting(
+        &mut self,
+        emitting: bool,
+    ) {
+        self.emitting = emitting;
+        if !emitting {
+            self.pending = 0.0;
+        }
+    }
+
+    /// Returns `true` while particles are spawned.
+    pub fn is_emitting(&self) -> bool {
+        self.emitting
+    }
+
+    /// Returns the number of live particles.
+    pub fn len(&self) -> usize {
+        self.particles.len()
+    }
+
+    /// Returns `true` if there is no live particle.
+    pub fn is_empty(&self) -> bool {
+        self.particles.is_empty()
+    }
+
+    /// Removes all the live particles.
+    pub fn clear(&mut self) {
+        self.particles.clear();
+    }
+
+    /// Spawns `count` particles at once, e.g. for explosions, even if the
+    /// system isn't emitting, as long as it isn't full.
+    pub fn burst(
+        &mut self,
+        count: usize,
+    ) {
+        for _ in 0 .. count {
+            self.spawn();
+        }
+    }
+
+    /// Advances the simulation by `dt` seconds, spawning new particles and
+    /// removing the ones reaching the end of their life.
+    pub fn step(
+        &mut self,
+        dt: f32,
+    ) {
+        let gravity = Vector3::from(self.emitter.gravity);
+        let mut i = 0;
+        while i < self.particles.len() {
+            let alive = {
+                let particle = &mut self.particles[i];
+                particle.age += dt;
+                particle.velocity += gravity * dt;
+                particle.position += particle.velocity * dt;
+                particle.age < particle.lifetime
+            };
+            if alive {
+                i += 1;
+            } else {
+                self.particles.swap_remove(i);
+            }
+        }
+
+        if self.emitting {
+            // spawns beyond the free room would be dropped, don't let them
+            // pile up until particles die
+            let room = self.emitter.max_particles.saturating_sub(self.particles.len());
+            self.pending = (self.pending + self.emitter.rate * dt).min(room as f32);
+            while self.pending >= 1.0 {
+                self.pending -= 1.0;
+                self.spawn();
+            }
+        }
+    }
+
+    fn spawn(&mut self) {
+        if self.particles.len() >= self.emitter.max_particles {
+            return;
+        }
+        let rng = &mut self.rng;
+        let emitter = &self.emitter;
+
+        // uniform in the spawn sphere, by rejection
+        let mut offset = Vector3::new(1.0, 1.0, 1.0);
+        while offset.magnitude2() > 1.0 {
+            offset = Vector3::new(rng.range(-1.0, 1.0), rng.range(-1.0, 1.0), rng.range(-1.0, 1.0));
+        }
+
+        // uniform in the cone of directions
+        let axis = Vector3::from(emitter.direction).normalize();
+        let basis = math::orthonormal_basis(axis);
+        let cos_theta = 1.0 - rng.unit() * (1.0 - emitter.spread.cos());
+        let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+        let phi = 2.0 * PI * rng.unit();
+        let direction = Vector3::from(basis[0]) * sin_theta * phi.cos() +
+            Vector3::from(basis[1]) * sin_theta * phi.sin() + axis * cos_theta;
+
+        let speed = rng.range(emitter.speed.start, emitter.speed.end);
+        // a zero lifetime would make the particle's age relative to it NaN
+        let lifetime = rng.range(emitter.lifetime.start, emitter.lifetime.end).max(f32::EPSILON);
+        self.particles.push(Particle {
+            position: offset * emitter.radius,
+            velocity: direction * speed,
+            age: 0.0,
+            lifetime,
+        });
+    }
+
+    /// Returns the live particles as they are rendered.
+    pub(crate) fn sprites(&self) -> Vec<Sprite> {
+        let emitter = &self.emitter;
+        self.particles
+            .iter()
+            .map(|particle| {
+                let t = particle.age / particle.lifetime;
+                let rgb = color::to_linear_rgb(emitter.color.sample(t));
+                Sprite {
+                    position: [particle.position.x, particle.position.y, particle.position.z].into(),
+                    size: emitter.size.sample(t),
+                    color: [rgb[0], rgb[1], rgb[2], emitter.opacity.sample(t)],
+                }
+            })
+            .collect()
+    }
+}
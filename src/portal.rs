@@ -0,0 +1,282 @@
+//! Cells and portals, culling the rooms of indoor scenes hidden from the camera.
+//!
+//! The scene is split into [`Cell`]s, typically rooms, connected by
+//! [`Portal`]s, such as doors and windows. While the camera is inside a cell,
+//! the renderer only draws the cells seen from it through a chain of portals,
+//! each portal narrowing the part of the screen the cells behind it can cover.
+//! Objects outside all cells are always drawn, and so is everything while the
+//! camera is outside all cells.
+//!
+//! ```rust,no_run
+//! # let mut window = three::Window::new("");
+//! # let hall = window.factory.group();
+//! # let kitchen = window.factory.group();
+//! use three::collision::Aabb;
+//! use three::portal::Cells;
+//!
+//! let mut cells = Cells::new();
+//! let a = cells.add_cell(Aabb { min: [-5.0, 0.0, -5.0].into(), max: [5.0, 3.0, 5.0].into() });
+//! cells.add_object(a, &hall);
+//! let b = cells.add_cell(Aabb { min: [5.0, 0.0, -5.0].into(), max: [15.0, 3.0, 5.0].into() });
+//! cells.add_object(b, &kitchen);
+//! // a door in the wall between them
+//! cells.add_portal(a, b, vec![
+//!     [5.0, 0.0, -1.0].into(),
+//!     [5.0, 2.0, -1.0].into(),
+//!     [5.0, 2.0, 1.0].into(),
+//!     [5.0, 0.0, 1.0].into(),
+//! ]);
+//! window.scene.portals = Some(cells);
+//! ```
+//!
+//! [`Cell`]: struct.Cell.html
+//! [`Portal`]: struct.Portal.html
+
+use cgmath::{Matrix4, Point3};
+use mint;
+
+use collision::Aabb;
+use hub::{Hub, SubNode};
+use node::NodePointer;
+use object::Base;
+
+use std::collections::HashSet;
+
+/// Smallest `w` of a portal corner in clip space still considered in front
+/// of the camera.
+const NEAR_EPSILON: f32 = 1e-5;
+
+/// A region of the scene, such as a room, whose objects are culled together.
+#[derive(Clone, Debug)]
+pub struct Cell {
+    /// Bounds of the cell in world space, used to find the cell of the camera.
+    pub bounds: Aabb,
+    /// Objects drawn only while the cell is visible, with their children.
+    pub objects: Vec<Base>,
+}
+
+/// An opening between two cells, through which each can be seen from the other.
+#[derive(Clone, Debug)]
+pub struct Portal {
+    /// Indices of the connected cells.
+    pub cells: [usize; 2],
+    /// Corners of the opening in world space.
+    pub points: Vec<mint::Point3<f32>>,
+}
+
+/// Rectangle of the screen in normalized device coordinates.
+#[derive(Clone, Copy, Debug)]
+struct Rect {
+    min: [f32; 2],
+    max: [f32; 2],
+}
+
+const FULL_SCREEN: Rect = Rect {
+    min: [-1.0, -1.0],
+    max: [1.0, 1.0],
+};
+
+impl Rect {
+    fn intersect(
+        &self,
+        other: &Rect,
+    ) -> Option<Rect> {
+        let rect = Rect {
+            min: [self.min[0].max(other.min[0]), self.min[1].max(other.min[1])],
+            max: [self.max[0].min(other.max[0]), self.max[1].min(other.max[1])],
+        };
+        if rect.min[0] < rect.max[0] && rect.min[1] < rect.max[1] {
+            Some(rect)
+        } else {
+            None
+        }
+    }
+}
+
+impl Portal {
+    /// Returns the part of the screen covered by the portal, or `None` if it
+    /// is behind the camera.
+    fn screen_rect(
+        &self,
+        mx_vp: Matrix4<f32>,
+    ) -> Option<Rect> {
+        let clip = self.points
+            .iter()
+            .map(|&p| mx_vp * Point3::from(p).to_homogeneous())
+            .collect::<Vec<_>>();
+        let behind = clip.iter().filter(|c| c.w <= NEAR_EPSILON).count();
+        if behind == clip.len() {
+            return None;
+        }
+        if behind > 0 {
+            // the portal crosses the camera plane, e.g. with the camera
+            // standing in a doorway, so it may cover any part of the screen
+            return Some(FULL_SCREEN);
+        }
+        let mut rect = Rect {
+            min: [1.0, 1.0],
+            max: [-1.0, -1.0],
+        };
+        for c in &clip {
+            let (x, y) = (c.x / c.w, c.y / c.w);
+            rect.min = [rect.min[0].min(x), rect.min[1].min(y)];
+            rect.max = [rect.max[0].max(x), rect.max[1].max(y)];
+        }
+        Some(rect)
+    }
+}
+
+/// Cells of a scene and the portals between them.
+///
+/// Set [`Scene::portals`](../scene/struct.Scene.html#structfield.portals) to
+/// have the renderer cull the cells hidden from the camera.
+#[derive(Clone, Debug, Default)]
+pub struct Cells {
+    cells: Vec<Cell>,
+    portals: Vec<Portal>,
+}
+
+impl Cells {
+    /// Creates an empty set of cells.
+    pub fn new() -> Self {
+        Cells::default()
+    }
+
+    /// Adds a cell with the given world space bounds, returning its index.
+    pub fn add_cell(
+        &mut self,
+        bounds: Aabb,
+    ) -> usize {
+        self.cells.push(Cell {
+            bounds,
+            objects: Vec::new(),
+        });
+        self.cells.len() - 1
+    }
+
+    /// Adds `object` and its children to the objects culled with `cell`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `cell` is not the index of a cell.
+    pub fn add_object<T: AsRef<Base>>(
+        &mut self,
+        cell: usize,
+        object: &T,
+    ) {
+        self.cells[cell].objects.push(object.as_ref().clone());
+    }
+
+    /// Adds a portal between cells `a` and `b`, with the corners of the
+    /// opening given in world space.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `a` or `b` is not the index of a cell.
+    pub fn add_portal(
+        &mut self,
+        a: usize,
+        b: usize,
+        points: Vec<mint::Point3<f32>>,
+    ) {
+        assert!(a < self.cells.len() && b < self.cells.len(), "portal between unknown cells");
+        self.portals.push(Portal {
+            cells: [a, b],
+            points,
+        });
+    }
+
+    /// Returns the cells.
+    pub fn cells(&self) -> &[Cell] {
+        &self.cells
+    }
+
+    /// Returns the portals.
+    pub fn portals(&self) -> &[Portal] {
+        &self.portals
+    }
+
+    /// Returns the index of the first cell containing `point`, if any.
+    pub fn cell_at<P: Into<mint::Point3<f32>>>(
+        &self,
+        point: P,
+    ) -> Option<usize> {
+        let p = point.into();
+        self.cells.iter().position(|cell| {
+            let (min, max) = (cell.bounds.min, cell.bounds.max);
+            min.x <= p.x && p.x <= max.x &&
+                min.y <= p.y && p.y <= max.y &&
+                min.z <= p.z && p.z <= max.z
+        })
+    }
+
+    /// Returns which cells are seen from `eye` through the portals, or `None`
+    /// if `eye` is outside all cells.
+    fn visible(
+        &self,
+        eye: Point3<f32>,
+        mx_vp: Matrix4<f32>,
+    ) -> Option<Vec<bool>> {
+        let start = self.cell_at(eye)?;
+        let mut visible = vec![false; self.cells.len()];
+        let mut path = vec![start];
+        self.flood(start, FULL_SCREEN, mx_vp, &mut path, &mut visible);
+        Some(visible)
+    }
+
+    /// Marks `cell` as visible, and the cells seen through its portals within
+    /// `rect`, without going back through the cells of `path`.
+    fn flood(
+        &self,
+        cell: usize,
+        rect: Rect,
+        mx_vp: Matrix4<f32>,
+        path: &mut Vec<usize>,
+        visible: &mut [bool],
+    ) {
+        visible[cell] = true;
+        for portal in &self.portals {
+            let next = if portal.cells[0] == cell {
+                portal.cells[1]
+            } else if portal.cells[1] == cell {
+                portal.cells[0]
+            } else {
+                continue
+            };
+            if path.contains(&next) {
+                continue;
+            }
+            let rect = match portal.screen_rect(mx_vp).and_then(|r| r.intersect(&rect)) {
+                Some(rect) => rect,
+                None => continue,
+            };
+            path.push(next);
+            self.flood(next, rect, mx_vp, path, visible);
+            path.pop();
+        }
+    }
+
+    /// Returns the nodes of the objects in the cells hidden from `eye`, with
+    /// their descendants.
+    pub(crate) fn hidden_nodes(
+        &self,
+        hub: &Hub,
+        eye: Point3<f32>,
+        mx_vp: Matrix4<f32>,
+    ) -> HashSet<NodePointer> {
+        let mut hidden = HashSet::new();
+        let visible = match self.visible(eye, mx_vp) {
+            Some(visible) => visible,
+            None => return hidden,
+        };
+        for (cell, _) in self.cells.iter().zip(visible).filter(|&(_, visible)| !visible) {
+            for object in &cell.objects {
+                hidden.insert(object.node.clone());
+                if let SubNode::Group { ref first_child } = hub[object].sub_node {
+                    hidden.extend(hub.walk_all(first_child).map(|w| w.node_ptr));
+                }
+            }
+        }
+        hidden
+    }
+}
@@ -0,0 +1,124 @@
+//! Reflection probes, capturing the surroundings of a box-shaped volume.
+//!
+//! PBR materials inside the volume of a probe reflect its capture instead of
+//! the scene environment map. Reflections are box-projected, so they line up
+//! with the walls of a room whose bounds match the volume.
+//!
+//! ```rust,no_run
+//! # use three::Object;
+//! # let mut window = three::Window::new("");
+//! # let camera = window.factory.perspective_camera(60.0, 0.1 ..);
+//! let probe = window.factory.reflection_probe([4.0, 1.5, 3.0], 128);
+//! probe.set_position([0.0, 1.5, 0.0]);
+//! window.scene.add(&probe);
+//!
+//! // capture once the scene is set up, and again whenever it changes
+//! window.renderer.capture_probe(&window.scene, &probe);
+//! while window.update() {
+//!     window.render(&camera);
+//! }
+//! ```
+//!
+//! Captures only happen on request, with
+//! [`Renderer::capture_probe`](../render/struct.Renderer.html#method.capture_probe).
+//! Meshes inside several volumes blend the captures of the two smallest ones.
+
+use mint;
+
+use camera::ZRange;
+use hub;
+use object::{Base, ObjectType};
+use texture::CubeMap;
+
+#[derive(Clone, Debug)]
+pub(crate) enum Operation {
+    Extent(mint::Vector3<f32>),
+    BlendDistance(f32),
+    Intensity(f32),
+    Range(ZRange),
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct ProbeData {
+    /// Half size of the volume along the local axes of the probe.
+    pub extent: mint::Vector3<f32>,
+    pub blend_distance: f32,
+    pub intensity: f32,
+    pub range: ZRange,
+    /// Size in pixels of the faces of the capture.
+    pub resolution: u16,
+    /// Cube map of the last capture, if any.
+    pub map: Option<CubeMap<[f32; 4]>>,
+}
+
+impl ProbeData {
+    pub(crate) fn new(
+        extent: mint::Vector3<f32>,
+        resolution: u16,
+    ) -> Self {
+        ProbeData {
+            extent,
+            blend_distance: 0.5,
+            intensity: 1.0,
+            range: ZRange::Finite(0.1 .. 100.0),
+            resolution,
+            map: None,
+        }
+    }
+}
+
+/// Captures its surroundings for the reflections of the PBR materials within
+/// a box-shaped volume centered on it.
+///
+/// Created with [`Factory::reflection_probe`](../struct.Factory.html#method.reflection_probe).
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ReflectionProbe {
+    pub(crate) object: Base,
+}
+three_object!(ReflectionProbe::object);
+derive_DowncastObject!(ReflectionProbe => ObjectType::ReflectionProbe);
+
+impl ReflectionProbe {
+    pub(crate) fn new(object: Base) -> Self {
+        ReflectionProbe { object }
+    }
+
+    /// Sets the half size of the volume along the local axes of the probe.
+    pub fn set_extent<V: Into<mint::Vector3<f32>>>(
+        &self,
+        extent: V,
+    ) {
+        self.object.send(hub::Operation::SetProbe(Operation::Extent(extent.into())));
+    }
+
+    /// Sets the distance from the faces of the volume over which the
+    /// reflections fade out, towards the environment map or another probe.
+    ///
+    /// Default: `0.5`.
+    pub fn set_blend_distance(
+        &self,
+        distance: f32,
+    ) {
+        self.object.send(hub::Operation::SetProbe(Operation::BlendDistance(distance)));
+    }
+
+    /// Sets the multiplier applied to the captured light.
+    ///
+    /// Default: `1.0`.
+    pub fn set_intensity(
+        &self,
+        intensity: f32,
+    ) {
+        self.object.send(hub::Operation::SetProbe(Operation::Intensity(intensity)));
+    }
+
+    /// Sets the near and far planes of the capture.
+    ///
+    /// Default: `0.1 .. 100.0`.
+    pub fn set_range<R: Into<ZRange>>(
+        &self,
+        range: R,
+    ) {
+        self.object.send(hub::Operation::SetProbe(Operation::Range(range.into())));
+    }
+}
@@ -0,0 +1,338 @@
+//! Ray intersection tests against meshes.
+//!
+//! ```rust,no_run
+//! # let mut window = three::Window::new("");
+//! # let camera = window.factory.perspective_camera(60.0, 0.1 .. 10.0);
+//! # let mesh = window.factory.mesh(three::Geometry::uv_sphere(1.0, 16, 16), three::material::Basic::default());
+//! use three::raycast::{self, Pose};
+//!
+//! while window.update() {
+//!     if window.input.hit(three::MOUSE_LEFT) {
+//!         let ray = window.screen_to_ray(&camera, window.input.mouse_pos());
+//!         if let Some(hit) = raycast::intersect_mesh(&window.scene, &mesh, &ray, Pose::Animated) {
+//!             println!("hit at {:?}", hit.point);
+//!         }
+//!     }
+//!     window.render(&camera);
+//! }
+//! ```
+//!
+//! To pick among all the meshes of a scene, use a [`Raycaster`](struct.Raycaster.html):
+//!
+//! ```rust,no_run
+//! # let mut window = three::Window::new("");
+//! # let camera = window.factory.perspective_camera(60.0, 0.1 .. 10.0);
+//! use three::raycast::Raycaster;
+//!
+//! let ndc = window.renderer.map_to_ndc(window.input.mouse_pos());
+//! let aspect_ratio = window.renderer.aspect_ratio();
+//! let raycaster = Raycaster::from_camera(&mut window.scene, &camera, aspect_ratio, ndc);
+//! if let Some(nearest) = raycaster.intersect(&window.scene).first() {
+//!     println!("picked {:?} at {:?}", nearest.object, nearest.point);
+//! }
+//! ```
+
+use cgmath::{Decomposed, EuclideanSpace, InnerSpace, Matrix4, Point3, Quaternion};
+use cgmath::{SquareMatrix, Transform, Vector3, Vector4};
+use mint;
+
+use camera::Camera;
+use hub::{Hub, SubNode};
+use mesh::{self, Mesh};
+use node::NodePointer;
+use object::Base;
+use scene::Scene;
+
+use std::cmp::Ordering;
+
+/// A half-line in world space.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Ray {
+    /// Starting point of the ray.
+    pub origin: mint::Point3<f32>,
+    /// Direction of the ray. Distances along the ray are measured in multiples of its length.
+    pub direction: mint::Vector3<f32>,
+}
+
+/// The intersection of a [`Ray`](struct.Ray.html) with a mesh.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Hit {
+    /// Distance from the ray origin, in multiples of the ray direction.
+    pub distance: f32,
+    /// Intersection point in world space.
+    pub point: mint::Point3<f32>,
+    /// Index of the intersected triangle.
+    pub face: usize,
+}
+
+/// Which vertex positions rays are tested against.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Pose {
+    /// The geometry as it was created, ignoring morph targets and skinning.
+    Bind,
+    /// The geometry as currently rendered, after morph targets and skinning.
+    ///
+    /// This is computed on the CPU for each test, which is slower for large meshes.
+    Animated,
+}
+
+/// The intersection of a [`Raycaster`](struct.Raycaster.html) ray with a scene mesh.
+#[derive(Clone, Debug)]
+pub struct Intersection {
+    /// The intersected mesh.
+    pub object: Mesh,
+    /// Distance from the ray origin, in multiples of the ray direction.
+    pub distance: f32,
+    /// Intersection point in world space.
+    pub point: mint::Point3<f32>,
+    /// Unit normal of the intersected triangle in world space, facing the
+    /// side of its counter-clockwise winding.
+    pub normal: mint::Vector3<f32>,
+    /// Index of the intersected triangle.
+    pub face: usize,
+}
+
+/// Casts a ray against all the visible meshes of a scene.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Raycaster {
+    /// The ray to cast.
+    pub ray: Ray,
+    /// Which vertex positions the ray is tested against.
+    ///
+    /// Default: `Pose::Bind`.
+    pub pose: Pose,
+}
+
+impl Raycaster {
+    /// Creates a raycaster for the given world space ray.
+    pub fn new(ray: Ray) -> Self {
+        Raycaster {
+            ray,
+            pose: Pose::Bind,
+        }
+    }
+
+    /// Creates a raycaster for the ray from `camera` through the point `ndc`,
+    /// given in Normalized Device Coordinates.
+    ///
+    /// The ray starts on the near plane and reaches the far plane at distance
+    /// `1.0`. See [`Renderer::map_to_ndc`] to convert window pixel coordinates
+    /// and [`Renderer::aspect_ratio`] for the aspect ratio of the window.
+    ///
+    /// [`Renderer::map_to_ndc`]: ../struct.Renderer.html#method.map_to_ndc
+    /// [`Renderer::aspect_ratio`]: ../struct.Renderer.html#method.aspect_ratio
+    pub fn from_camera<P: Into<mint::Point2<f32>>>(
+        scene: &mut Scene,
+        camera: &Camera,
+        aspect_ratio: f32,
+        ndc: P,
+    ) -> Self {
+        let (transform, projection) = {
+            let guard = scene.sync_guard();
+            (guard.resolve_world(camera).transform, guard.resolve_data(camera))
+        };
+        let mx_world = Matrix4::from(Decomposed {
+            disp: Point3::from(transform.position).to_vec(),
+            rot: Quaternion::from(transform.orientation),
+            scale: transform.scale,
+        });
+        let mx_proj = Matrix4::from(projection.matrix(aspect_ratio));
+        let mx_inverse = mx_world * mx_proj.invert().unwrap_or(Matrix4::identity());
+        let ndc = ndc.into();
+        let unproject = |z: f32| {
+            let p = mx_inverse * Vector4::new(ndc.x, ndc.y, z, 1.0);
+            Point3::from_homogeneous(p)
+        };
+        let near = unproject(-1.0);
+        let far = unproject(1.0);
+        Raycaster::new(Ray {
+            origin: near.into(),
+            direction: (far - near).into(),
+        })
+    }
+
+    /// Returns the intersections of the ray with the visible meshes of `scene`,
+    /// nearest first.
+    ///
+    /// Each mesh is reported at most once, at its nearest intersection. Meshes
+    /// not created from a [`Geometry`](../struct.Geometry.html) are skipped.
+    pub fn intersect(
+        &self,
+        scene: &Scene,
+    ) -> Vec<Intersection> {
+        let origin = Point3::from(self.ray.origin);
+        let direction = Vector3::from(self.ray.direction);
+        let mut hub = scene.hub.lock().unwrap();
+        hub.process_messages();
+
+        // only the meshes whose bounds the ray hits are tested
+        let visuals = {
+            let mut index = scene.index.lock().unwrap();
+            index.refresh(&hub, &scene.first_child);
+            index.query_ray(origin, direction)
+        };
+
+        let mut intersections = Vec::new();
+        for (node_ptr, mx_world) in visuals {
+            let object = hub.upgrade_ptr(node_ptr);
+            let triangles = match node_triangles(&hub, &scene.first_child, &object, mx_world, self.pose) {
+                Some(triangles) => triangles,
+                None => continue,
+            };
+            let mut nearest: Option<(f32, usize)> = None;
+            for (face, corners) in triangles.iter().enumerate() {
+                if let Some(distance) = intersect_triangle(origin, direction, corners) {
+                    if nearest.map_or(true, |(best, _)| distance < best) {
+                        nearest = Some((distance, face));
+                    }
+                }
+            }
+            if let Some((distance, face)) = nearest {
+                let corners = &triangles[face];
+                let normal = (corners[1] - corners[0]).cross(corners[2] - corners[0]);
+                intersections.push(Intersection {
+                    object: Mesh { object },
+                    distance,
+                    point: (origin + direction * distance).into(),
+                    normal: normal.normalize().into(),
+                    face,
+                });
+            }
+        }
+
+        intersections.sort_by(|a, b| {
+            a.distance
+                .partial_cmp(&b.distance)
+                .unwrap_or(Ordering::Equal)
+        });
+        intersections
+    }
+}
+
+/// Returns the nearest intersection of `ray` with the triangles of `mesh`.
+///
+/// Both sides of the triangles are tested. Returns `None` if the ray misses,
+/// if the mesh isn't in `scene`, or if the mesh wasn't created from a
+/// [`Geometry`](../struct.Geometry.html).
+pub fn intersect_mesh(
+    scene: &Scene,
+    mesh: &Mesh,
+    ray: &Ray,
+    pose: Pose,
+) -> Option<Hit> {
+    let triangles = match world_triangles(scene, mesh, pose) {
+        Some(triangles) => triangles,
+        None => return None,
+    };
+    let origin = Point3::from(ray.origin);
+    let direction = Vector3::from(ray.direction);
+    let mut nearest: Option<(f32, usize)> = None;
+    for (face, corners) in triangles.iter().enumerate() {
+        if let Some(distance) = intersect_triangle(origin, direction, corners) {
+            if nearest.map_or(true, |(best, _)| distance < best) {
+                nearest = Some((distance, face));
+            }
+        }
+    }
+    nearest.map(|(distance, face)| Hit {
+        distance,
+        point: (origin + direction * distance).into(),
+        face,
+    })
+}
+
+/// Returns the triangles of `mesh` in world space, in face order.
+///
+/// Returns `None` if the mesh isn't in `scene` or wasn't created from a `Geometry`.
+pub(crate) fn world_triangles(
+    scene: &Scene,
+    mesh: &Mesh,
+    pose: Pose,
+) -> Option<Vec<[Point3<f32>; 3]>> {
+    let mut hub = scene.hub.lock().unwrap();
+    hub.process_messages();
+    let mx_world = match hub.walk_all(&scene.first_child).find(|w| w.node_ptr == mesh.object.node) {
+        Some(w) => w.world_matrix(),
+        None => return None,
+    };
+    node_triangles(&hub, &scene.first_child, &mesh.object, mx_world, pose)
+}
+
+/// Returns the triangles of the visual node `object`, transformed by `mx_world`.
+pub(crate) fn node_triangles(
+    hub: &Hub,
+    first_child: &Option<NodePointer>,
+    object: &Base,
+    mx_world: Matrix4<f32>,
+    pose: Pose,
+) -> Option<Vec<[Point3<f32>; 3]>> {
+    let (geometry, shape) = match pose {
+        Pose::Bind => match hub[object].sub_node {
+            SubNode::Visual(_, ref gpu_data, _) => match gpu_data.geometry {
+                Some(ref geometry) => (geometry.clone(), None),
+                None => return None,
+            },
+            _ => unreachable!(),
+        },
+        Pose::Animated => match mesh::sample_shape(hub, first_child, object) {
+            Some((geometry, shape)) => (geometry, Some(shape)),
+            None => return None,
+        },
+    };
+    let vertices = match shape {
+        Some(ref shape) => &shape.vertices,
+        None => &geometry.base.vertices,
+    };
+
+    let world = vertices
+        .iter()
+        .map(|&v| mx_world.transform_point(Point3::from(v)))
+        .collect::<Vec<_>>();
+    let triangles = if geometry.faces.is_empty() {
+        world
+            .chunks(3)
+            .filter(|c| c.len() == 3)
+            .map(|c| [c[0], c[1], c[2]])
+            .collect()
+    } else {
+        geometry
+            .faces
+            .iter()
+            .map(|f| [world[f[0] as usize], world[f[1] as usize], world[f[2] as usize]])
+            .collect()
+    };
+    Some(triangles)
+}
+
+/// Möller-Trumbore ray-triangle intersection, returning the ray parameter of the hit.
+pub(crate) fn intersect_triangle(
+    origin: Point3<f32>,
+    direction: Vector3<f32>,
+    corners: &[Point3<f32>; 3],
+) -> Option<f32> {
+    const EPSILON: f32 = 1e-7;
+    let edge1 = corners[1] - corners[0];
+    let edge2 = corners[2] - corners[0];
+    let p = direction.cross(edge2);
+    let det = edge1.dot(p);
+    if det.abs() < EPSILON {
+        return None;
+    }
+    let inv_det = 1.0 / det;
+    let s = origin - corners[0];
+    let u = s.dot(p) * inv_det;
+    if u < 0.0 || u > 1.0 {
+        return None;
+    }
+    let q = s.cross(edge1);
+    let v = direction.dot(q) * inv_det;
+    if v < 0.0 || u + v > 1.0 {
+        return None;
+    }
+    let t = edge2.dot(q) * inv_det;
+    if t >= 0.0 {
+        Some(t)
+    } else {
+        None
+    }
+}
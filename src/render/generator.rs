@@ -0,0 +1,98 @@
+//! Vertex buffers filled on the GPU, e.g. for ocean surfaces or marching
+//! cubes output, without round trips through the CPU.
+//!
+//! A generator is a pixel shader drawn over a [`VertexTarget`], with one
+//! texel of 32-bit unsigned integers per 16 bytes of each vertex. The
+//! texels are then copied as they are into the vertex buffer of the target,
+//! from which any number of meshes can be drawn, see
+//! [`Factory::generated_mesh`]. The pass runs with the next render after
+//! [`Factory::generate_vertices`], before the scene is drawn, and the
+//! vertices stay in the buffer until the next generation.
+//!
+//! Generators only provide the function computing a vertex from its index,
+//! see [`Factory::mesh_generator`] for the shader interface.
+//!
+//! ```rust,no_run
+//! # let mut window = three::Window::new("");
+//! // a 64 by 64 grid of vertices, waving with the time in `u_Params.x`
+//! let generator = window.factory.mesh_generator("
+//!     GeneratedVertex generate(int index) {
+//!         vec2 grid = vec2(index % 64, index / 64) / 63.0;
+//!         float phase = 10.0 * grid.x + u_Params.x;
+//!         GeneratedVertex vertex = generated_vertex();
+//!         vertex.position = vec3(grid.x - 0.5, 0.05 * sin(phase), grid.y - 0.5);
+//!         vertex.normal = normalize(vec3(-0.5 * cos(phase), 1.0, 0.0));
+//!         vertex.uv = grid;
+//!         return vertex;
+//!     }
+//! ").unwrap();
+//!
+//! let target = window.factory.vertex_target(64 * 64);
+//! let mut faces = Vec::new();
+//! for y in 0 .. 63 {
+//!     for x in 0 .. 63 {
+//!         let i = y * 64 + x;
+//!         faces.push([i, i + 64, i + 1]);
+//!         faces.push([i + 1, i + 64, i + 65]);
+//!     }
+//! }
+//! let material = three::material::Lambert::default();
+//! let surface = window.factory.generated_mesh(&target, &faces, material);
+//! window.scene.add(&surface);
+//!
+//! # let camera = window.factory.perspective_camera(60.0, 0.1 .. 10.0);
+//! let mut time = 0.0;
+//! while window.update() {
+//!     time += window.input.delta_time();
+//!     window.factory.generate_vertices(&target, &generator, [time, 0.0, 0.0, 0.0], None);
+//!     window.render(&camera);
+//! }
+//! ```
+//!
+//! [`VertexTarget`]: struct.VertexTarget.html
+//! [`Factory::generated_mesh`]: ../../struct.Factory.html#method.generated_mesh
+//! [`Factory::generate_vertices`]: ../../struct.Factory.html#method.generate_vertices
+//! [`Factory::mesh_generator`]: ../../struct.Factory.html#method.mesh_generator
+
+use gfx::format::R32_G32_B32_A32;
+use gfx::handle as h;
+
+use super::{back, GeneratorFormat, GeneratorParams, GeneratorPipelineState, Vertex};
+use texture::Texture;
+
+/// Number of 16 byte texels per vertex.
+pub(crate) const TEXELS_PER_VERTEX: usize = 5;
+
+/// Maximum number of vertices along each row of a target.
+pub(crate) const VERTICES_PER_ROW: usize = 512;
+
+/// A vertex buffer filled by generator passes, created with
+/// [`Factory::vertex_target`](../../struct.Factory.html#method.vertex_target).
+#[derive(Clone, Debug)]
+pub struct VertexTarget {
+    pub(crate) vertices: h::Buffer<back::Resources, Vertex>,
+    pub(crate) texture: h::Texture<back::Resources, R32_G32_B32_A32>,
+    pub(crate) view: h::RenderTargetView<back::Resources, GeneratorFormat>,
+    pub(crate) params: h::Buffer<back::Resources, GeneratorParams>,
+    pub(crate) count: usize,
+}
+
+impl VertexTarget {
+    /// Returns the number of vertices generated into the target.
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    /// Returns `true` if the target holds no vertices.
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+}
+
+/// A generator pass queued for the next render.
+pub(crate) struct VertexJob {
+    pub target: VertexTarget,
+    pub pipeline: GeneratorPipelineState,
+    pub params: [f32; 4],
+    pub input: Option<Texture<[f32; 4]>>,
+}
@@ -0,0 +1,176 @@
+//! Orientation gizmo drawn in a corner of the viewport.
+
+use cgmath::{self, Matrix4, Quaternion, Rotation, Vector3, Vector4};
+use gfx::handle as h;
+use mint;
+
+use color::{self, Color};
+use math;
+use super::{back, Vertex, DEFAULT_VERTEX};
+
+/// Length of the gizmo axes, relative to the half size of the gizmo.
+const AXIS_LENGTH: f32 = 0.8;
+
+/// Pick radius around the axis ends, relative to the gizmo size.
+const PICK_RADIUS: f32 = 0.15;
+
+/// An axis of the orientation gizmo.
+///
+/// See [`Renderer::pick_gizmo`](struct.Renderer.html#method.pick_gizmo).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum GizmoAxis {
+    /// The positive X axis.
+    PositiveX,
+    /// The negative X axis.
+    NegativeX,
+    /// The positive Y axis.
+    PositiveY,
+    /// The negative Y axis.
+    NegativeY,
+    /// The positive Z axis.
+    PositiveZ,
+    /// The negative Z axis.
+    NegativeZ,
+}
+
+/// All gizmo axes, in drawing order.
+pub(crate) const AXES: [GizmoAxis; 6] = [
+    GizmoAxis::NegativeX,
+    GizmoAxis::NegativeY,
+    GizmoAxis::NegativeZ,
+    GizmoAxis::PositiveX,
+    GizmoAxis::PositiveY,
+    GizmoAxis::PositiveZ,
+];
+
+impl GizmoAxis {
+    /// Returns the unit vector pointing along the axis.
+    pub fn direction(&self) -> mint::Vector3<f32> {
+        let v = match *self {
+            GizmoAxis::PositiveX => [1.0, 0.0, 0.0],
+            GizmoAxis::NegativeX => [-1.0, 0.0, 0.0],
+            GizmoAxis::PositiveY => [0.0, 1.0, 0.0],
+            GizmoAxis::NegativeY => [0.0, -1.0, 0.0],
+            GizmoAxis::PositiveZ => [0.0, 0.0, 1.0],
+            GizmoAxis::NegativeZ => [0.0, 0.0, -1.0],
+        };
+        v.into()
+    }
+
+    /// Returns the orientation of a camera placed on this axis and looking
+    /// towards the origin, using the same up vector as
+    /// [`Object::look_at`](trait.Object.html#method.look_at).
+    ///
+    /// Passing the result to `Object::set_orientation` snaps the camera to the
+    /// axis-aligned view.
+    pub fn view_orientation(&self) -> mint::Quaternion<f32> {
+        let up = match *self {
+            GizmoAxis::PositiveZ | GizmoAxis::NegativeZ => Vector3::unit_y(),
+            _ => Vector3::unit_z(),
+        };
+        math::look_rotation(-Vector3::from(self.direction()), up)
+    }
+
+    pub(crate) fn color(&self) -> Color {
+        match *self {
+            GizmoAxis::PositiveX => color::RED,
+            GizmoAxis::PositiveY => color::GREEN,
+            GizmoAxis::PositiveZ => color::BLUE,
+            GizmoAxis::NegativeX => 0x400000,
+            GizmoAxis::NegativeY => 0x004000,
+            GizmoAxis::NegativeZ => 0x000040,
+        }
+    }
+}
+
+/// Orientation gizmo state kept by the renderer.
+pub(crate) struct Gizmo {
+    /// Position in pixels; negative values are relative to the right and bottom edges.
+    pub pos: [i32; 2],
+    /// Size in pixels.
+    pub size: i32,
+    /// Line vertices, two per axis in `AXES` order.
+    pub vertices: h::Buffer<back::Resources, Vertex>,
+    /// View-projection matrix used during the last frame.
+    pub mx_vp: Option<Matrix4<f32>>,
+}
+
+/// Returns the line vertices of the gizmo axes.
+pub(crate) fn vertices() -> Vec<Vertex> {
+    let mut vertices = Vec::with_capacity(AXES.len() * 2);
+    for axis in AXES.iter() {
+        let end = Vector3::from(axis.direction()) * AXIS_LENGTH;
+        vertices.push(DEFAULT_VERTEX);
+        vertices.push(Vertex {
+            pos: [end.x, end.y, end.z, 1.0],
+            ..DEFAULT_VERTEX
+        });
+    }
+    vertices
+}
+
+impl Gizmo {
+    /// Returns the top-left corner of the gizmo in pixels.
+    fn origin(
+        &self,
+        screen: (u32, u32),
+    ) -> [i32; 2] {
+        let resolve = |pos: i32, extent: u32| {
+            if pos >= 0 {
+                pos
+            } else {
+                extent as i32 + pos - self.size
+            }
+        };
+        [resolve(self.pos[0], screen.0), resolve(self.pos[1], screen.1)]
+    }
+
+    /// Computes the view-projection matrix placing the gizmo in its corner,
+    /// rotated by the camera `orientation`.
+    pub fn view_projection(
+        &self,
+        screen: (u32, u32),
+        orientation: Quaternion<f32>,
+    ) -> Matrix4<f32> {
+        let origin = self.origin(screen);
+        let half = self.size as f32 * 0.5;
+        let center = [
+            2.0 * (origin[0] as f32 + half) / screen.0 as f32 - 1.0,
+            1.0 - 2.0 * (origin[1] as f32 + half) / screen.1 as f32,
+        ];
+        let scale = [
+            self.size as f32 / screen.0 as f32,
+            self.size as f32 / screen.1 as f32,
+        ];
+        let mx_corner = Matrix4::from_translation(Vector3::new(center[0], center[1], 0.0)) *
+            Matrix4::from_nonuniform_scale(scale[0], scale[1], 1.0);
+        let mx_ortho = cgmath::ortho(-1.0, 1.0, -1.0, 1.0, -1.0, 1.0);
+        mx_corner * mx_ortho * Matrix4::from(orientation.invert())
+    }
+
+    /// Returns the axis whose end lies under `point`, given in pixels.
+    pub fn pick(
+        &self,
+        screen: (u32, u32),
+        point: mint::Point2<f32>,
+    ) -> Option<GizmoAxis> {
+        let mx_vp = match self.mx_vp {
+            Some(mx) => mx,
+            None => return None,
+        };
+        let radius = PICK_RADIUS * self.size as f32;
+        let mut best = None;
+        for axis in AXES.iter() {
+            let end = Vector3::from(axis.direction()) * AXIS_LENGTH;
+            let ndc: Vector4<f32> = mx_vp * end.extend(1.0);
+            let x = (ndc.x + 1.0) * 0.5 * screen.0 as f32;
+            let y = (1.0 - ndc.y) * 0.5 * screen.1 as f32;
+            let distance = ((x - point.x).powi(2) + (y - point.y).powi(2)).sqrt();
+            // prefer the axis closest to the viewer when ends overlap
+            if distance <= radius && best.map_or(true, |(_, depth)| ndc.z < depth) {
+                best = Some((*axis, ndc.z));
+            }
+        }
+        best.map(|(axis, _)| axis)
+    }
+}
@@ -0,0 +1,347 @@
+//! Frame graph used to schedule the render passes.
+//!
+//! Each pass declares the resources it reads and writes. The graph derives the
+//! execution order from these declarations, so that new passes can be added
+//! without hand-ordering the whole frame.
+//!
+//! Intermediate targets are declared as transient resources. The graph
+//! assigns them to slots, sharing a slot between transients of the same
+//! description whose uses don't overlap, and the renderer backs each slot
+//! with a target recycled from frame to frame.
+
+use gfx::texture::Size;
+
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+
+/// A resource read or written by a render pass.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Resource {
+    /// Shadow map of the shadow request with the given index.
+    ShadowMap(usize),
+    /// Main color target.
+    Color,
+    /// Main depth-stencil target.
+    Depth,
+    /// Transient target with the given index, see `FrameGraph::add_transient`.
+    Transient(usize),
+    /// Virtual texture feedback target, read back by the next frame.
+    Feedback,
+}
+
+/// Contents of a transient target.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TargetFormat {
+    /// Color with 8-bit channels.
+    Color,
+    /// Color with 16-bit float channels.
+    HdrColor,
+    /// Depth and stencil.
+    Depth,
+}
+
+/// Description of a transient target, which targets must match to be shared.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TargetDesc {
+    /// Size in pixels.
+    pub size: (Size, Size),
+    /// Samples per pixel, `0` without multisampling.
+    pub samples: u8,
+    /// Contents of the target.
+    pub format: TargetFormat,
+}
+
+/// A render pass of the frame.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Pass {
+    /// Renders the shadow map of the shadow request with the given index.
+    Shadow(usize),
+    /// Clears the main targets and renders the scene meshes.
+    Scene,
+    /// Renders the virtual texture tiles sampled by the scene meshes into
+    /// the feedback target.
+    Feedback,
+    /// Renders the scene background.
+    Background,
+    /// Renders the text drawn in world space.
+    WorldText,
+    /// Renders the diagnostic view of the scene meshes.
+    Debug,
+    /// Resolves the multisampled color of the frame.
+    Resolve,
+    /// Runs the post-processing effect with the given index.
+    Post(usize),
+    /// Renders the orientation gizmo.
+    Gizmo,
+    /// Renders the UI text.
+    Text,
+    /// Renders the debug quads.
+    DebugQuads,
+}
+
+quick_error! {
+    #[doc = "Error encountered when compiling a frame graph."]
+    #[derive(Debug)]
+    pub enum FrameGraphError {
+        #[doc = "The pass dependencies contain a cycle."]
+        Cycle(pass: Pass) {
+            description("cyclic render pass dependency")
+            display("cyclic render pass dependency involving {:?}", pass)
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct Node {
+    pass: Pass,
+    reads: Vec<Resource>,
+    writes: Vec<Resource>,
+}
+
+/// Set of render passes with their resource dependencies.
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct FrameGraph {
+    nodes: Vec<Node>,
+    transients: Vec<TargetDesc>,
+}
+
+/// Execution order of the passes of a frame graph, with the slot of each
+/// transient resource.
+#[derive(Debug, Default)]
+pub(crate) struct Schedule {
+    /// Passes in execution order.
+    pub passes: Vec<Pass>,
+    /// Slot backing each transient resource, by index.
+    pub slots: Vec<usize>,
+    /// Description of the target of each slot.
+    pub targets: Vec<TargetDesc>,
+}
+
+impl FrameGraph {
+    /// Creates an empty frame graph.
+    pub(crate) fn new() -> Self {
+        FrameGraph::default()
+    }
+
+    /// Declares a transient target matching `desc`, allocated for the passes
+    /// using it.
+    pub(crate) fn add_transient(
+        &mut self,
+        desc: TargetDesc,
+    ) -> Resource {
+        self.transients.push(desc);
+        Resource::Transient(self.transients.len() - 1)
+    }
+
+    /// Adds a pass reading and writing the given resources.
+    ///
+    /// Passes writing the same resource are executed in the order they were added.
+    /// A pass reading a resource it doesn't write is executed after all of its writers.
+    pub(crate) fn add_pass(
+        &mut self,
+        pass: Pass,
+        reads: &[Resource],
+        writes: &[Resource],
+    ) {
+        self.nodes.push(Node {
+            pass,
+            reads: reads.to_vec(),
+            writes: writes.to_vec(),
+        });
+    }
+
+    /// Computes the execution order of the passes and the slots of the
+    /// transient resources.
+    ///
+    /// The order of insertion is preserved between independent passes.
+    pub(crate) fn compile(&self) -> Result<Schedule, FrameGraphError> {
+        let count = self.nodes.len();
+
+        // writers of each resource in insertion order, then its other readers
+        let mut users = HashMap::<Resource, (Vec<usize>, Vec<usize>)>::new();
+        for (index, node) in self.nodes.iter().enumerate() {
+            for &resource in &node.writes {
+                users.entry(resource).or_insert_with(Default::default).0.push(index);
+            }
+        }
+        for (index, node) in self.nodes.iter().enumerate() {
+            for resource in node.reads.iter().filter(|resource| !node.writes.contains(resource)) {
+                if let Some(&mut (_, ref mut readers)) = users.get_mut(resource) {
+                    readers.push(index);
+                }
+            }
+        }
+
+        // each writer follows the previous one, and readers follow the last
+        let mut successors = vec![Vec::new(); count];
+        let mut predecessors = vec![0; count];
+        for &(ref writers, ref readers) in users.values() {
+            for pair in writers.windows(2) {
+                successors[pair[0]].push(pair[1]);
+                predecessors[pair[1]] += 1;
+            }
+            if let Some(&last) = writers.last() {
+                for &reader in readers {
+                    successors[last].push(reader);
+                    predecessors[reader] += 1;
+                }
+            }
+        }
+
+        // pick the first pass in insertion order among the ready ones
+        let mut ready = (0 .. count)
+            .filter(|&index| predecessors[index] == 0)
+            .map(Reverse)
+            .collect::<BinaryHeap<_>>();
+        let mut order = Vec::with_capacity(count);
+        while let Some(Reverse(index)) = ready.pop() {
+            order.push(index);
+            for &next in &successors[index] {
+                predecessors[next] -= 1;
+                if predecessors[next] == 0 {
+                    ready.push(Reverse(next));
+                }
+            }
+        }
+        if let Some(index) = (0 .. count).find(|&index| predecessors[index] != 0) {
+            return Err(FrameGraphError::Cycle(self.nodes[index].pass));
+        }
+
+        let (slots, targets) = self.allocate(&order);
+        Ok(Schedule {
+            passes: order.into_iter().map(|index| self.nodes[index].pass).collect(),
+            slots,
+            targets,
+        })
+    }
+
+    /// Assigns the transient resources to slots, given the execution order
+    /// of the nodes. A slot is shared by transients of the same description
+    /// when the last pass using one comes before the first pass using the
+    /// next.
+    fn allocate(
+        &self,
+        order: &[usize],
+    ) -> (Vec<usize>, Vec<TargetDesc>) {
+        // first and last position of each transient in the execution order,
+        // unused transients being given the start of the frame
+        let mut spans = vec![None; self.transients.len()];
+        for (position, &index) in order.iter().enumerate() {
+            let node = &self.nodes[index];
+            for resource in node.reads.iter().chain(&node.writes) {
+                if let Resource::Transient(transient) = *resource {
+                    let span = spans[transient].get_or_insert((position, position));
+                    span.1 = position;
+                }
+            }
+        }
+        let spans = spans
+            .into_iter()
+            .map(|span| span.unwrap_or((0, 0)))
+            .collect::<Vec<_>>();
+
+        let mut by_start = (0 .. self.transients.len()).collect::<Vec<_>>();
+        by_start.sort_by_key(|&transient| spans[transient].0);
+        let mut slots = vec![0; self.transients.len()];
+        // description of each slot, with the last position it's used at
+        let mut targets = Vec::<(TargetDesc, usize)>::new();
+        for transient in by_start {
+            let (start, end) = spans[transient];
+            let desc = self.transients[transient];
+            let free = targets
+                .iter()
+                .position(|&(slot_desc, slot_end)| slot_desc == desc && slot_end < start);
+            slots[transient] = match free {
+                Some(slot) => {
+                    targets[slot].1 = end;
+                    slot
+                }
+                None => {
+                    targets.push((desc, end));
+                    targets.len() - 1
+                }
+            };
+        }
+        (slots, targets.into_iter().map(|(desc, _)| desc).collect())
+    }
+}
+
+/// Schedule of the last compiled frame graph, reused as long as the passes
+/// and their resources don't change, which is the case for most frames.
+#[derive(Debug, Default)]
+pub(crate) struct CompiledGraph {
+    graph: FrameGraph,
+    schedule: Schedule,
+}
+
+impl CompiledGraph {
+    /// Returns the schedule of `graph`, compiling it only if it differs from
+    /// the previous one.
+    pub(crate) fn schedule(
+        &mut self,
+        graph: FrameGraph,
+    ) -> Result<&Schedule, FrameGraphError> {
+        if graph != self.graph {
+            self.schedule = graph.compile()?;
+            self.graph = graph;
+        }
+        Ok(&self.schedule)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn desc(format: TargetFormat) -> TargetDesc {
+        TargetDesc {
+            size: (64, 64),
+            samples: 0,
+            format,
+        }
+    }
+
+    #[test]
+    fn readers_follow_writers() {
+        let mut graph = FrameGraph::new();
+        graph.add_pass(Pass::Scene, &[Resource::ShadowMap(0)], &[Resource::Color, Resource::Depth]);
+        graph.add_pass(Pass::Text, &[], &[Resource::Color]);
+        graph.add_pass(Pass::Shadow(0), &[], &[Resource::ShadowMap(0)]);
+        let schedule = graph.compile().unwrap();
+        assert_eq!(schedule.passes, vec![Pass::Shadow(0), Pass::Scene, Pass::Text]);
+    }
+
+    #[test]
+    fn cycle() {
+        let mut graph = FrameGraph::new();
+        graph.add_pass(Pass::Scene, &[Resource::ShadowMap(0)], &[Resource::Color]);
+        graph.add_pass(Pass::Shadow(0), &[Resource::Color], &[Resource::ShadowMap(0)]);
+        assert!(graph.compile().is_err());
+    }
+
+    #[test]
+    fn transients_share_slots() {
+        let mut graph = FrameGraph::new();
+        let first = graph.add_transient(desc(TargetFormat::Color));
+        let second = graph.add_transient(desc(TargetFormat::Color));
+        let depth = graph.add_transient(desc(TargetFormat::Depth));
+        graph.add_pass(Pass::Scene, &[], &[first, depth]);
+        graph.add_pass(Pass::Background, &[first, depth], &[second]);
+        graph.add_pass(Pass::Text, &[second], &[Resource::Color]);
+        let schedule = graph.compile().unwrap();
+        // `second` is written while `first` is read, so they can't share
+        assert_eq!(schedule.slots, vec![0, 2, 1]);
+        assert_eq!(schedule.targets.len(), 3);
+
+        let mut graph = FrameGraph::new();
+        let first = graph.add_transient(desc(TargetFormat::Color));
+        let second = graph.add_transient(desc(TargetFormat::Color));
+        graph.add_pass(Pass::Scene, &[], &[first]);
+        graph.add_pass(Pass::Background, &[first], &[Resource::Color]);
+        graph.add_pass(Pass::Text, &[], &[second]);
+        graph.add_pass(Pass::DebugQuads, &[second], &[Resource::Color]);
+        let schedule = graph.compile().unwrap();
+        assert_eq!(schedule.slots, vec![0, 0]);
+        assert_eq!(schedule.targets, vec![desc(TargetFormat::Color)]);
+    }
+}
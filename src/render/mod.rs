@@ -1,6 +1,7 @@
 //! The renderer.
 
-use cgmath::{Matrix as Matrix_, Matrix4, SquareMatrix, Transform as Transform_, Vector3};
+use cgmath::{EuclideanSpace, InnerSpace, Matrix as Matrix_, Matrix3, Matrix4, MetricSpace, Point3};
+use cgmath::{SquareMatrix, Transform as Transform_, Vector3};
 use froggy;
 use gfx;
 use gfx::format::I8Norm;
@@ -13,30 +14,54 @@ use gfx_device_gl as back;
 use gfx_window_glutin;
 #[cfg(feature = "opengl")]
 use glutin;
+use image;
 use mint;
 
+pub mod generator;
+pub mod post;
 pub mod source;
+mod gizmo;
+mod graph;
+mod pso_cache;
 mod pso_data;
+mod watchdog;
 
 use color;
 
-use std::{io, str};
-use std::collections::HashMap;
+use std::{cmp, io, iter, mem, str};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::sync::Arc;
 
 pub use self::back::CommandBuffer as BackendCommandBuffer;
 pub use self::back::Factory as BackendFactory;
 pub use self::back::Resources as BackendResources;
-pub use self::source::Source;
+pub use self::gizmo::GizmoAxis;
+pub use self::source::{Profile, Source};
+pub use self::watchdog::Budget;
 
+use self::generator::VertexJob;
+use self::gizmo::{Gizmo, AXES as GIZMO_AXES};
+use self::graph::{CompiledGraph, FrameGraph, Pass, Resource, TargetDesc, TargetFormat};
+use self::post::{PostContext, PostEffect, ToneMapOperator, ToneMapping};
+use self::pso_cache::{Features, PsoCache, Shaders};
 use self::pso_data::{PbrFlags, PsoData};
-use camera::Camera;
+use self::watchdog::{Phase, Watchdog};
+use camera::{Camera, Projection};
+use collision::Aabb;
 use factory::Factory;
-use hub::{SubLight, SubNode};
+use geometry::Geometry;
+use hub::{Hub, SubLight, SubNode};
+use input::Timer;
 use light::{ShadowMap, ShadowProjection};
-use material::Material;
-use scene::{Background, Scene};
-use text::Font;
-use texture::Texture;
+use material::{self, Material};
+use math::{self, Frustum};
+use mesh::{BeforeRender, Fade};
+use probe::ReflectionProbe;
+use scene::{Background, Fog, Scene};
+use text::{Font, UiScale};
+use texture::{ColorSpace, CubeMap, RenderTarget, Texture};
+use virtual_texture::Tile;
 
 /// The format of the back buffer color requested from the windowing system.
 pub type ColorFormat = gfx::format::Rgba8;
@@ -46,10 +71,39 @@ pub type DepthFormat = gfx::format::DepthStencil;
 pub type ShadowFormat = gfx::format::Depth32F;
 /// The concrete type of a basic pipeline.
 pub type BasicPipelineState = gfx::PipelineState<back::Resources, basic_pipe::Meta>;
+/// The concrete type of a post-processing pipeline.
+pub type PostPipelineState = gfx::PipelineState<back::Resources, post_pipe::Meta>;
+/// The format of the targets of vertex generator passes, each texel holding
+/// 16 bytes of a vertex.
+pub type GeneratorFormat = (gfx::format::R32_G32_B32_A32, gfx::format::Uint);
+/// The concrete type of a vertex generator pipeline.
+pub type GeneratorPipelineState = gfx::PipelineState<back::Resources, generator_pipe::Meta>;
 
-pub(crate) const MAX_LIGHTS: usize = 4;
+/// Number of lights affecting each mesh, unless configured otherwise with
+/// [`window::Builder::max_lights`](../window/struct.Builder.html#method.max_lights).
+pub const DEFAULT_MAX_LIGHTS: usize = 4;
 pub(crate) const MAX_TARGETS: usize = 8;
 pub(crate) const VECS_PER_BONE: usize = 3;
+/// Ratio of the size of the frame to the size of the virtual texture
+/// feedback target.
+pub(crate) const FEEDBACK_SCALE: u32 = 8;
+
+/// Section plane that keeps all geometry.
+const NO_CLIP_PLANE: [f32; 4] = [0.0, 0.0, 0.0, 1.0];
+
+/// Normalized device co-ordinates of the whole output, as `[left, bottom,
+/// right, top]`.
+const FULL_NDC_RECT: [f32; 4] = [-1.0, -1.0, 1.0, 1.0];
+
+/// Probe parameters of meshes outside all reflection probes.
+const NO_PROBES: ProbeParams = ProbeParams {
+    min0: [0.0; 4],
+    max0: [0.0; 4],
+    center0: [0.0; 4],
+    min1: [0.0; 4],
+    max1: [0.0; 4],
+    center1: [0.0; 4],
+};
 
 const STENCIL_SIDE: gfx::state::StencilSide = gfx::state::StencilSide {
     fun: gfx::state::Comparison::Always,
@@ -99,6 +153,7 @@ pub const DEFAULT_VERTEX: Vertex = Vertex {
     tangent: [I8Norm(127), I8Norm(0), I8Norm(0), I8Norm(0)],
     joint_indices: [0, 0, 0, 0],
     joint_weights: [1.0, 1.0, 1.0, 1.0],
+    color: [1.0, 1.0, 1.0, 1.0],
 };
 
 impl Default for Vertex {
@@ -129,15 +184,20 @@ gfx_defines! {
         tangent: [gfx::format::I8Norm; 4] = "a_Tangent",
         joint_indices: [i32; 4] = "a_JointIndices",
         joint_weights: [f32; 4] = "a_JointWeights",
+        color: [f32; 4] = "a_Color",
     }
 
     vertex Instance {
         world0: [f32; 4] = "i_World0",
         world1: [f32; 4] = "i_World1",
         world2: [f32; 4] = "i_World2",
+        normal0: [f32; 4] = "i_Normal0",
+        normal1: [f32; 4] = "i_Normal1",
+        normal2: [f32; 4] = "i_Normal2",
         color: [f32; 4] = "i_Color",
         mat_params: [f32; 4] = "i_MatParams",
         uv_range: [f32; 4] = "i_UvRange",
+        fade: f32 = "i_Fade",
     }
 
     constant LightParam {
@@ -155,6 +215,14 @@ gfx_defines! {
         mx_vp: [[f32; 4]; 4] = "u_ViewProj",
         mx_inv_proj: [[f32; 4]; 4] = "u_InverseProj",
         mx_view: [[f32; 4]; 4] = "u_View",
+        env_params: [f32; 4] = "u_EnvParams",
+        ambient: [f32; 4] = "u_Ambient",
+        clip_plane: [f32; 4] = "u_ClipPlane",
+        cap_color: [f32; 4] = "u_CapColor",
+        shadow_params: [f32; 4] = "u_ShadowParams",
+        fog_color: [f32; 4] = "u_FogColor",
+        fog_params: [f32; 4] = "u_FogParams",
+        resolution: [f32; 4] = "u_Resolution",
         num_lights: u32 = "u_NumLights",
     }
 
@@ -164,14 +232,18 @@ gfx_defines! {
         cb_lights: gfx::ConstantBuffer<LightParam> = "b_Lights",
         cb_globals: gfx::ConstantBuffer<Globals> = "b_Globals",
         tex_map: gfx::TextureSampler<[f32; 4]> = "t_Map",
+        tex_normal_map: gfx::TextureSampler<[f32; 4]> = "t_NormalMap",
         shadow_map0: gfx::TextureSampler<f32> = "t_Shadow0",
         shadow_map1: gfx::TextureSampler<f32> = "t_Shadow1",
+        displacement_contributions: gfx::ConstantBuffer<DisplacementContribution> = "b_DisplacementContributions",
+        displacements: gfx::TextureSampler<[f32; 4]> = "u_Displacements",
         out_color: gfx::BlendTarget<ColorFormat> =
             ("Target0", gfx::state::ColorMask::all(), gfx::preset::blend::REPLACE),
         out_depth: gfx::DepthStencilTarget<DepthFormat> =
             (gfx::preset::depth::LESS_EQUAL_WRITE, gfx::state::Stencil {
                 front: STENCIL_SIDE, back: STENCIL_SIDE,
             }),
+        scissor: gfx::Scissor = (),
     }
 
     pipeline shadow_pipe {
@@ -195,6 +267,51 @@ gfx_defines! {
         target: gfx::RenderTarget<ColorFormat> = "Target0",
         depth_target: gfx::DepthTarget<DepthFormat> =
             gfx::preset::depth::LESS_EQUAL_TEST,
+        scissor: gfx::Scissor = (),
+    }
+
+    pipeline resolve_pipe {
+        samples: gfx::Global<i32> = "u_Samples",
+        input: gfx::ShaderResource<[f32; 4]> = "t_Input",
+        target: gfx::RenderTarget<ColorFormat> = "Target0",
+    }
+
+    constant PostParams {
+        resolution: [f32; 4] = "u_Resolution",
+        params: [f32; 4] = "u_Params",
+    }
+
+    pipeline post_pipe {
+        params: gfx::ConstantBuffer<PostParams> = "b_PostParams",
+        input: gfx::TextureSampler<[f32; 4]> = "t_Input",
+        extra: gfx::TextureSampler<[f32; 4]> = "t_Extra",
+        target: gfx::RenderTarget<ColorFormat> = "Target0",
+    }
+
+    constant GeneratorParams {
+        size: [u32; 4] = "u_Size",
+        params: [f32; 4] = "u_Params",
+    }
+
+    pipeline generator_pipe {
+        params: gfx::ConstantBuffer<GeneratorParams> = "b_GeneratorParams",
+        input: gfx::TextureSampler<[f32; 4]> = "t_Input",
+        target: gfx::RenderTarget<GeneratorFormat> = "Target0",
+    }
+
+    constant SsaoParams {
+        projection: [[f32; 4]; 4] = "u_Projection",
+        inv_projection: [[f32; 4]; 4] = "u_InverseProjection",
+        resolution: [f32; 4] = "u_Resolution",
+        params: [f32; 4] = "u_Params",
+    }
+
+    pipeline ssao_pipe {
+        params: gfx::ConstantBuffer<SsaoParams> = "b_SsaoParams",
+        input: gfx::TextureSampler<[f32; 4]> = "t_Input",
+        depth: gfx::TextureSampler<f32> = "t_Depth",
+        occlusion: gfx::TextureSampler<[f32; 4]> = "t_Occlusion",
+        target: gfx::RenderTarget<ColorFormat> = "Target0",
     }
 
     constant PbrParams {
@@ -209,6 +326,15 @@ gfx_defines! {
         pbr_flags: i32 = "u_PbrFlags",
     }
 
+    constant ProbeParams {
+        min0: [f32; 4] = "u_ProbeMin0",
+        max0: [f32; 4] = "u_ProbeMax0",
+        center0: [f32; 4] = "u_ProbeCenter0",
+        min1: [f32; 4] = "u_ProbeMin1",
+        max1: [f32; 4] = "u_ProbeMax1",
+        center1: [f32; 4] = "u_ProbeCenter1",
+    }
+
     constant DisplacementContribution {
         position: f32 = "position",
         normal: f32 = "normal",
@@ -236,48 +362,80 @@ gfx_defines! {
 
         occlusion_map: gfx::TextureSampler<[f32; 4]> = "u_OcclusionSampler",
 
-        color_target: gfx::RenderTarget<ColorFormat> = "Target0",
+        environment_map: gfx::TextureSampler<[f32; 4]> = "u_EnvironmentSampler",
+
+        probes: gfx::ConstantBuffer<ProbeParams> = "b_Probes",
+        probe_map0: gfx::TextureSampler<[f32; 4]> = "u_ProbeSampler0",
+        probe_map1: gfx::TextureSampler<[f32; 4]> = "u_ProbeSampler1",
+
+        color_target: gfx::BlendTarget<ColorFormat> =
+            ("Target0", gfx::state::ColorMask::all(), gfx::preset::blend::REPLACE),
         depth_target: gfx::DepthTarget<DepthFormat> = gfx::preset::depth::LESS_EQUAL_WRITE,
+        scissor: gfx::Scissor = (),
     }
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub(crate) struct InstanceCacheKey {
     pub(crate) material: Material,
+    pub(crate) state: material::State,
     pub(crate) geometry: h::Buffer<back::Resources, Vertex>,
 }
 
+/// Computes the rows of the normal matrix, i.e. the inverse-transpose of the
+/// upper 3x3 part of the world matrix.
+fn normal_matrix(mx_world: &mint::RowMatrix4<f32>) -> [[f32; 4]; 3] {
+    let m = Matrix3::new(
+        mx_world.x.x, mx_world.y.x, mx_world.z.x,
+        mx_world.x.y, mx_world.y.y, mx_world.z.y,
+        mx_world.x.z, mx_world.y.z, mx_world.z.z,
+    );
+    // Rows of the inverse-transpose are the columns of the inverse.
+    let inv = m.invert().unwrap_or(Matrix3::identity());
+    [inv.x.extend(0.0).into(), inv.y.extend(0.0).into(), inv.z.extend(0.0).into()]
+}
+
 impl Instance {
     #[inline]
     fn basic(
         mx_world: mint::RowMatrix4<f32>,
         color: u32,
+        opacity: f32,
         uv_range: [f32; 4],
-        param: f32,
+        mat_params: [f32; 4],
     ) -> Self {
+        let normal = normal_matrix(&mx_world);
         Instance {
             world0: mx_world.x.into(),
             world1: mx_world.y.into(),
             world2: mx_world.z.into(),
+            normal0: normal[0],
+            normal1: normal[1],
+            normal2: normal[2],
             color: {
-                // TODO: add alpha parameter for `to_linear_rgb`
                 let rgb = color::to_linear_rgb(color);
-                [rgb[0], rgb[1], rgb[2], 0.0]
+                [rgb[0], rgb[1], rgb[2], opacity]
             },
-            mat_params: [param, 0.0, 0.0, 0.0],
+            mat_params,
             uv_range,
+            fade: 1.0,
         }
     }
 
     #[inline]
     fn pbr(mx_world: mint::RowMatrix4<f32>) -> Self {
+        let normal = normal_matrix(&mx_world);
         Instance {
             world0: mx_world.x.into(),
             world1: mx_world.y.into(),
             world2: mx_world.z.into(),
+            normal0: normal[0],
+            normal1: normal[1],
+            normal2: normal[2],
             color: [0.0; 4],
             mat_params: [0.0; 4],
             uv_range: [0.0; 4],
+            fade: 1.0,
         }
     }
 }
@@ -301,6 +459,15 @@ pub(crate) struct GpuData {
     pub pending: Option<DynamicData>,
     pub instance_cache_key: Option<InstanceCacheKey>,
     pub displacement_contributions: Vec<DisplacementContribution>,
+    pub render_state: material::State,
+    /// Lights affect this mesh only if their layers intersect this mask.
+    pub light_mask: u32,
+    /// Limits beyond which the mesh is faded out.
+    pub fade: Fade,
+    /// Source geometry kept for CPU-side queries, if available.
+    pub geometry: Option<Arc<Geometry>>,
+    /// Local space bounds of the geometry and its morph targets, if known.
+    pub bounds: Option<Aabb>,
 }
 
 #[derive(Debug)]
@@ -308,6 +475,7 @@ struct InstanceData {
     slice: gfx::Slice<back::Resources>,
     vertices: h::Buffer<back::Resources, Vertex>,
     material: Material,
+    state: material::State,
     list: Vec<Instance>,
 }
 
@@ -317,6 +485,17 @@ pub(crate) struct DynamicData {
     pub buffer: h::Buffer<back::Resources, Vertex>,
 }
 
+/// A copy from an upload buffer to a region of a texture, applied with the
+/// next render.
+#[derive(Clone, Debug)]
+pub(crate) struct TextureUpload {
+    pub buffer: h::RawBuffer<back::Resources>,
+    /// Offset of the texels in `buffer`, in bytes.
+    pub offset: usize,
+    pub texture: h::RawTexture<back::Resources>,
+    pub info: gfx::texture::RawImageInfo,
+}
+
 /// Shadow type is used to specify shadow's rendering algorithm.
 pub enum ShadowType {
     /// Force no shadows.
@@ -327,6 +506,259 @@ pub enum ShadowType {
     Pcf,
 }
 
+/// Settings of an offscreen capture, see
+/// [`Renderer::capture`](struct.Renderer.html#method.capture).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Capture {
+    /// Width of the image in pixels.
+    pub width: u32,
+    /// Height of the image in pixels.
+    pub height: u32,
+    /// Number of samples per pixel along each axis. The scene is rendered at
+    /// `supersampling` times the image size and averaged down, which
+    /// anti-aliases edges and fine details.
+    ///
+    /// Default: `2`.
+    pub supersampling: u32,
+    /// Whether the background is left out, so that only the scene objects
+    /// are opaque in the image.
+    ///
+    /// Default: `false`.
+    pub transparent: bool,
+}
+
+impl Capture {
+    /// Creates the settings of a `width` x `height` capture.
+    pub fn new(
+        width: u32,
+        height: u32,
+    ) -> Self {
+        Capture {
+            width,
+            height,
+            supersampling: 2,
+            transparent: false,
+        }
+    }
+}
+
+/// Region of the window a frame is rendered into, in pixels from the top
+/// left corner, see
+/// [`Renderer::set_viewport`](struct.Renderer.html#method.set_viewport).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Viewport {
+    /// Distance of the left edge from the left of the window.
+    pub x: u32,
+    /// Distance of the top edge from the top of the window.
+    pub y: u32,
+    /// Width of the region.
+    pub width: u32,
+    /// Height of the region.
+    pub height: u32,
+}
+
+/// Diagnostic display of the scene meshes, selected with
+/// [`Renderer::debug_view`](struct.Renderer.html#structfield.debug_view).
+///
+/// Line and sprite materials are left out of the diagnostic views.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum DebugView {
+    /// Regular shading.
+    Off,
+    /// Heatmap of the number of mesh fragments drawn to each pixel, brighter
+    /// where surfaces are drawn over each other.
+    Overdraw,
+    /// Mesh edges drawn over the regular shading.
+    Wireframe,
+    /// Mipmap level sampled from the texture map of each mesh, from blue
+    /// (level 0, or magnified) through cyan, green and yellow to red (level 4
+    /// and above).
+    MipLevel,
+    /// World space normals mapped to colors.
+    Normals,
+    /// Checkerboard of the mesh texture co-ordinates, tinted by their value.
+    UvChecker,
+}
+
+impl Default for DebugView {
+    fn default() -> Self {
+        DebugView::Off
+    }
+}
+
+impl DebugView {
+    /// Returns the mode of the diagnostic shader, if used by this view.
+    fn shader_mode(&self) -> Option<f32> {
+        match *self {
+            DebugView::Off | DebugView::Wireframe => None,
+            DebugView::Overdraw => Some(0.0),
+            DebugView::MipLevel => Some(1.0),
+            DebugView::Normals => Some(2.0),
+            DebugView::UvChecker => Some(3.0),
+        }
+    }
+}
+
+/// Contribution of a light source, used to choose the lights of each mesh when
+/// there are more than `Renderer::max_lights` or when light masks exclude some.
+#[derive(Clone, Copy, Debug)]
+struct LightPriority {
+    /// Intensity scaled by the brightest color channel.
+    brightness: f32,
+    /// World position of point lights, the others lighting the scene evenly.
+    position: Option<Point3<f32>>,
+    /// Layers of the light, tested against the light mask of each mesh.
+    layers: u32,
+}
+
+impl LightPriority {
+    fn score(
+        &self,
+        point: Point3<f32>,
+    ) -> f32 {
+        match self.position {
+            Some(position) => self.brightness / (1.0 + position.distance2(point)),
+            None => self.brightness,
+        }
+    }
+}
+
+/// Returns the `count` lights matching `mask` that contribute the most at `point`.
+///
+/// The list is padded with unlit entries up to the number of lights given to the
+/// shaders, so that it doesn't depend on the mask.
+fn select_lights(
+    lights: &[LightParam],
+    priorities: &[LightPriority],
+    point: Point3<f32>,
+    count: usize,
+    mask: u32,
+) -> Vec<LightParam> {
+    let mut order = (0 .. lights.len())
+        .filter(|&index| priorities[index].layers & mask != 0)
+        .collect::<Vec<_>>();
+    order.sort_by(|&a, &b| {
+        let (a, b) = (priorities[a].score(point), priorities[b].score(point));
+        b.partial_cmp(&a).unwrap_or(cmp::Ordering::Equal)
+    });
+    order.truncate(count);
+    order.sort();
+    let mut selected = order.into_iter().map(|index| lights[index]).collect::<Vec<_>>();
+    let unlit = LightParam {
+        projection: [[0.0; 4]; 4],
+        // a directional light, keeping the shader math finite
+        pos: [0.0, 0.0, 1.0, 0.0],
+        dir: [0.0, 0.0, 1.0, 0.0],
+        focus: [0.0; 4],
+        color: [0.0; 4],
+        color_back: [0.0; 4],
+        intensity: [0.0; 4],
+        shadow_params: [-1, 0, 0, 0],
+    };
+    let total = cmp::min(lights.len(), count);
+    selected.resize(total, unlit);
+    selected
+}
+
+/// Returns the matrix squeezing clip space into `rect`, given in normalized
+/// device co-ordinates as `[left, bottom, right, top]`.
+fn ndc_rect_matrix(rect: [f32; 4]) -> Matrix4<f32> {
+    let scale = [0.5 * (rect[2] - rect[0]), 0.5 * (rect[3] - rect[1])];
+    let offset = [0.5 * (rect[2] + rect[0]), 0.5 * (rect[3] + rect[1])];
+    Matrix4::new(
+        scale[0], 0.0, 0.0, 0.0,
+        0.0, scale[1], 0.0, 0.0,
+        0.0, 0.0, 1.0, 0.0,
+        offset[0], offset[1], 0.0, 1.0,
+    )
+}
+
+/// Maps `quad`, given in the normalized device co-ordinates of the whole
+/// output, into `rect`, both as `[left, bottom, right, top]`.
+fn fit_rect(
+    quad: [f32; 4],
+    rect: [f32; 4],
+) -> [f32; 4] {
+    let x = |value: f32| rect[0] + 0.5 * (value + 1.0) * (rect[2] - rect[0]);
+    let y = |value: f32| rect[1] + 0.5 * (value + 1.0) * (rect[3] - rect[1]);
+    [x(quad[0]), y(quad[1]), x(quad[2]), y(quad[3])]
+}
+
+/// Returns `true` if a visual is blended with what lies behind it, and so
+/// is drawn after the opaque ones.
+fn is_transparent(
+    pso_data: &PsoData,
+    state: &material::State,
+) -> bool {
+    let opacity = match *pso_data {
+        PsoData::Basic { opacity, .. } => opacity,
+        PsoData::Pbr { ref params, .. } => params.base_color_factor[3],
+    };
+    opacity < 1.0 || state.blend != material::Blend::Replace
+}
+
+/// A captured reflection probe, with its volume in world space.
+struct ProbeInstance {
+    bounds: Aabb,
+    /// World position the probe was captured from.
+    center: Point3<f32>,
+    blend_distance: f32,
+    intensity: f32,
+    map: (h::ShaderResourceView<back::Resources, [f32; 4]>, h::Sampler<back::Resources>),
+}
+
+impl ProbeInstance {
+    fn volume(&self) -> f32 {
+        (self.bounds.max.x - self.bounds.min.x) *
+            (self.bounds.max.y - self.bounds.min.y) *
+            (self.bounds.max.z - self.bounds.min.z)
+    }
+
+    fn contains(
+        &self,
+        point: Point3<f32>,
+    ) -> bool {
+        let (min, max) = (self.bounds.min, self.bounds.max);
+        min.x <= point.x && point.x <= max.x &&
+            min.y <= point.y && point.y <= max.y &&
+            min.z <= point.z && point.z <= max.z
+    }
+}
+
+/// Returns the parameters and maps of the first two `probes` containing `point`,
+/// the others being disabled and bound to `default`.
+///
+/// Probes are expected from the smallest to the largest, the first one taking
+/// precedence where they overlap.
+fn select_probes(
+    probes: &[ProbeInstance],
+    point: Point3<f32>,
+    default: &(h::ShaderResourceView<back::Resources, [f32; 4]>, h::Sampler<back::Resources>),
+) -> (ProbeParams, [(h::ShaderResourceView<back::Resources, [f32; 4]>, h::Sampler<back::Resources>); 2]) {
+    let mut params = NO_PROBES;
+    let mut maps = [default.clone(), default.clone()];
+    for (index, probe) in probes.iter().filter(|probe| probe.contains(point)).take(2).enumerate() {
+        let (min, max, center) = (probe.bounds.min, probe.bounds.max, probe.center);
+        let min = [min.x, min.y, min.z, 1.0];
+        let max = [max.x, max.y, max.z, probe.blend_distance];
+        let center = [center.x, center.y, center.z, probe.intensity];
+        if index == 0 {
+            params.min0 = min;
+            params.max0 = max;
+            params.center0 = center;
+        } else {
+            params.min1 = min;
+            params.max1 = max;
+            params.center1 = center;
+        }
+        maps[index] = probe.map.clone();
+    }
+    (params, maps)
+}
+
+/// Size in pixels of the shadow maps displayed by `Renderer::debug_shadows`.
+const DEBUG_SHADOW_SIZE: i32 = 192;
+
 struct DebugQuad {
     resource: h::RawShaderResourceView<back::Resources>,
     pos: [i32; 2],
@@ -338,7 +770,7 @@ pub struct PipelineStates<R: gfx::Resources> {
     /// Corresponds to `Material::Basic`.
     mesh_basic_fill: gfx::PipelineState<R, basic_pipe::Meta>,
 
-    /// Corresponds to `Material::Line`.
+    /// Corresponds to `Material::Line` with the default width and mode.
     line_basic: gfx::PipelineState<R, basic_pipe::Meta>,
 
     /// Corresponds to `Material::Wireframe`.
@@ -364,6 +796,31 @@ pub struct PipelineStates<R: gfx::Resources> {
 
     /// Used internally for rendering `Background::Skybox`.
     skybox: gfx::PipelineState<R, quad_pipe::Meta>,
+
+    /// Used internally to resolve multisampled targets.
+    resolve: gfx::PipelineState<R, resolve_pipe::Meta>,
+
+    /// Used by `post::Bloom`.
+    bloom: gfx::PipelineState<R, post_pipe::Meta>,
+
+    /// Used by `post::Fxaa`.
+    fxaa: gfx::PipelineState<R, post_pipe::Meta>,
+
+    /// Used by `post::Ssao`.
+    ssao: gfx::PipelineState<R, ssao_pipe::Meta>,
+
+    /// Used by `post::ToneMapping`.
+    tone_mapping: gfx::PipelineState<R, post_pipe::Meta>,
+
+    /// Shader programs used to create pipeline variants.
+    shaders: Shaders<R>,
+
+    /// Pipeline variants created for non-default material states.
+    cache: PsoCache<R>,
+
+    /// Whether pipelines with a geometry shader can be created, which isn't
+    /// the case with OpenGL ES contexts.
+    geometry_shaders: bool,
 }
 
 impl PipelineStates<back::Resources> {
@@ -375,20 +832,109 @@ impl PipelineStates<back::Resources> {
         Self::init(src, &mut factory.backend)
     }
 
+    /// Returns the built-in basic pipeline for `material`, or `None` if its
+    /// pipelines are only created on first use, e.g. for points.
     pub(crate) fn pso_by_material<'a>(
         &'a self,
         material: &'a Material,
-    ) -> &'a BasicPipelineState {
+    ) -> Option<&'a BasicPipelineState> {
         match *material {
-            Material::Basic(_) => &self.mesh_basic_fill,
-            Material::CustomBasic(ref b) => &b.pipeline,
-            Material::Line(_) => &self.line_basic,
-            Material::Wireframe(_) => &self.mesh_basic_wireframe,
-            Material::Lambert(_) => &self.mesh_gouraud,
-            Material::Phong(_) => &self.mesh_phong,
-            Material::Sprite(_) => &self.sprite,
-            _ => unreachable!(),
+            Material::Basic(_) => Some(&self.mesh_basic_fill),
+            Material::CustomBasic(ref b) => Some(&b.pipeline),
+            Material::Line(_) | Material::DashedLine(_) => Some(&self.line_basic),
+            Material::Wireframe(_) => Some(&self.mesh_basic_wireframe),
+            Material::Lambert(_) => Some(&self.mesh_gouraud),
+            Material::Phong(_) => Some(&self.mesh_phong),
+            Material::Sprite(_) => Some(&self.sprite),
+            _ => None,
+        }
+    }
+
+    /// Returns the basic pipeline for `material` with the given `state` and
+    /// `features`, creating it if necessary.
+    ///
+    /// Falls back to the built-in pipeline for `material` if the variant fails
+    /// to compile, or returns `None` if there is none. Without geometry shaders,
+    /// wide lines are drawn as thin ones and points aren't drawn.
+    pub(crate) fn basic_variant<'a>(
+        &'a mut self,
+        backend: &mut back::Factory,
+        material: &'a Material,
+        state: &material::State,
+        features: Features,
+    ) -> Option<&'a BasicPipelineState> {
+        let kind = match pso_cache::Kind::of(material) {
+            Some(kind) if self.geometry_shaders => kind,
+            Some(kind) => kind.without_geometry_shader()?,
+            None => return self.pso_by_material(material),
+        };
+        let features = kind.features(features);
+        if *state == material::State::default() && features == Features::BASIC && kind.is_builtin() {
+            return self.pso_by_material(material);
+        }
+        let state = kind.state(state);
+        let key = (kind, state, features);
+        if !self.cache.basic.contains_key(&key) {
+            match pso_cache::create_basic(&self.shaders, backend, kind, &state, features) {
+                Ok(pso) => {
+                    self.cache.basic.insert(key, pso);
+                }
+                Err(err) => {
+                    error!("Failed to create pipeline variant: {}", err);
+                    return self.pso_by_material(material);
+                }
+            }
+        }
+        Some(&self.cache.basic[&key])
+    }
+
+    /// Returns the basic pipeline of `kind` with the given `state`, creating it
+    /// if necessary, or `None` if it fails to compile.
+    pub(crate) fn kind_variant(
+        &mut self,
+        backend: &mut back::Factory,
+        kind: pso_cache::Kind,
+        state: &material::State,
+    ) -> Option<&BasicPipelineState> {
+        let key = (kind, *state, Features::BASIC);
+        if !self.cache.basic.contains_key(&key) {
+            match pso_cache::create_basic(&self.shaders, backend, kind, state, Features::BASIC) {
+                Ok(pso) => {
+                    self.cache.basic.insert(key, pso);
+                }
+                Err(err) => {
+                    error!("Failed to create pipeline variant: {}", err);
+                    return None;
+                }
+            }
+        }
+        Some(&self.cache.basic[&key])
+    }
+
+    /// Returns the PBR pipeline with the given `state` and `features`, creating
+    /// it if necessary.
+    pub(crate) fn pbr_variant(
+        &mut self,
+        backend: &mut back::Factory,
+        state: &material::State,
+        features: Features,
+    ) -> &gfx::PipelineState<back::Resources, pbr_pipe::Meta> {
+        if *state == material::State::default() && features == Features::ALL {
+            return &self.pbr;
+        }
+        let key = (*state, features);
+        if !self.cache.pbr.contains_key(&key) {
+            match pso_cache::create_pbr(&self.shaders, backend, state, features) {
+                Ok(pso) => {
+                    self.cache.pbr.insert(key, pso);
+                }
+                Err(err) => {
+                    error!("Failed to create pipeline variant: {}", err);
+                    return &self.pbr;
+                }
+            }
         }
+        &self.cache.pbr[&key]
     }
 }
 
@@ -399,6 +945,7 @@ impl<R: gfx::Resources> PipelineStates<R> {
         backend: &mut F,
     ) -> Result<Self, PipelineCreationError> {
         let basic = backend.create_shader_set(&src.basic.vs, &src.basic.ps)?;
+        let diagnostic = backend.create_shader_set(&src.diagnostic.vs, &src.diagnostic.ps)?;
         let gouraud = backend.create_shader_set(&src.gouraud.vs, &src.gouraud.ps)?;
         let phong = backend.create_shader_set(&src.phong.vs, &src.phong.ps)?;
         let sprite = backend.create_shader_set(&src.sprite.vs, &src.sprite.ps)?;
@@ -406,6 +953,11 @@ impl<R: gfx::Resources> PipelineStates<R> {
         let quad = backend.create_shader_set(&src.quad.vs, &src.quad.ps)?;
         let pbr = backend.create_shader_set(&src.pbr.vs, &src.pbr.ps)?;
         let skybox = backend.create_shader_set(&src.skybox.vs, &src.skybox.ps)?;
+        let resolve = backend.create_shader_set(&src.resolve.vs, &src.resolve.ps)?;
+        let bloom = backend.create_shader_set(&src.bloom.vs, &src.bloom.ps)?;
+        let fxaa = backend.create_shader_set(&src.fxaa.vs, &src.fxaa.ps)?;
+        let ssao = backend.create_shader_set(&src.ssao.vs, &src.ssao.ps)?;
+        let tone_mapping = backend.create_shader_set(&src.tone_mapping.vs, &src.tone_mapping.ps)?;
 
         let rast_quad = gfx::state::Rasterizer {
             samples: Some(gfx::state::MultiSample),
@@ -484,6 +1036,36 @@ impl<R: gfx::Resources> PipelineStates<R> {
             rast_fill,
             pbr_pipe::new(),
         )?;
+        let pso_resolve = backend.create_pipeline_state(
+            &resolve,
+            gfx::Primitive::TriangleStrip,
+            gfx::state::Rasterizer::new_fill(),
+            resolve_pipe::new(),
+        )?;
+        let pso_bloom = backend.create_pipeline_state(
+            &bloom,
+            gfx::Primitive::TriangleStrip,
+            gfx::state::Rasterizer::new_fill(),
+            post_pipe::new(),
+        )?;
+        let pso_fxaa = backend.create_pipeline_state(
+            &fxaa,
+            gfx::Primitive::TriangleStrip,
+            gfx::state::Rasterizer::new_fill(),
+            post_pipe::new(),
+        )?;
+        let pso_ssao = backend.create_pipeline_state(
+            &ssao,
+            gfx::Primitive::TriangleStrip,
+            gfx::state::Rasterizer::new_fill(),
+            ssao_pipe::new(),
+        )?;
+        let pso_tone_mapping = backend.create_pipeline_state(
+            &tone_mapping,
+            gfx::Primitive::TriangleStrip,
+            gfx::state::Rasterizer::new_fill(),
+            post_pipe::new(),
+        )?;
 
         Ok(PipelineStates {
             mesh_basic_fill: pso_mesh_basic_fill,
@@ -496,10 +1078,317 @@ impl<R: gfx::Resources> PipelineStates<R> {
             quad: pso_quad,
             pbr: pso_pbr,
             skybox: pso_skybox,
+            resolve: pso_resolve,
+            bloom: pso_bloom,
+            fxaa: pso_fxaa,
+            ssao: pso_ssao,
+            tone_mapping: pso_tone_mapping,
+            shaders: Shaders {
+                basic,
+                diagnostic,
+                gouraud,
+                phong,
+                sprite,
+                pbr,
+                source: src.clone(),
+            },
+            cache: PsoCache::new(),
+            geometry_shaders: true,
         })
     }
 }
 
+/// Creates a color texture of the given kind that can be rendered to and
+/// sampled, with 16-bit float channels if `hdr` is set.
+fn color_target(
+    factory: &mut back::Factory,
+    kind: gfx::texture::Kind,
+    hdr: bool,
+) -> Result<(
+    h::RenderTargetView<back::Resources, ColorFormat>,
+    h::ShaderResourceView<back::Resources, [f32; 4]>,
+), gfx::CombinedError> {
+    use gfx::format::{ChannelTyped, Formatted, Rgba16F};
+
+    let bind = gfx::memory::Bind::RENDER_TARGET | gfx::memory::Bind::SHADER_RESOURCE;
+    if hdr {
+        let texture = factory.create_texture::<<Rgba16F as Formatted>::Surface>(
+            kind,
+            1,
+            bind,
+            gfx::memory::Usage::Data,
+            Some(<<Rgba16F as Formatted>::Channel as ChannelTyped>::get_channel_type()),
+        )?;
+        let target = factory.view_texture_as_render_target::<Rgba16F>(&texture, 0, None)?;
+        let resource = factory.view_texture_as_shader_resource::<Rgba16F>(
+            &texture,
+            (0, 0),
+            gfx::format::Swizzle::new(),
+        )?;
+        // the pipelines are typed with `ColorFormat`, but GL only cares about
+        // the float channels of the attachment
+        Ok((Typed::new(target.raw().clone()), resource))
+    } else {
+        let texture = factory.create_texture::<<ColorFormat as Formatted>::Surface>(
+            kind,
+            1,
+            bind,
+            gfx::memory::Usage::Data,
+            Some(<<ColorFormat as Formatted>::Channel as ChannelTyped>::get_channel_type()),
+        )?;
+        let target = factory.view_texture_as_render_target(&texture, 0, None)?;
+        let resource = factory.view_texture_as_shader_resource::<ColorFormat>(
+            &texture,
+            (0, 0),
+            gfx::format::Swizzle::new(),
+        )?;
+        Ok((target, resource))
+    }
+}
+
+/// A full-screen pass run after the scene.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum PostStep {
+    /// The tone mapping of HDR rendering.
+    ToneMapping,
+    /// The post effect at the given index.
+    Effect(usize),
+}
+
+/// Target backing a slot of the frame graph.
+#[derive(Clone, Debug)]
+enum Target {
+    Color {
+        view: h::RenderTargetView<back::Resources, ColorFormat>,
+        resource: h::ShaderResourceView<back::Resources, [f32; 4]>,
+    },
+    Depth {
+        view: h::DepthStencilView<back::Resources, DepthFormat>,
+        /// Sampled depth, or `None` if multisampled.
+        resource: Option<h::ShaderResourceView<back::Resources, f32>>,
+    },
+}
+
+impl Target {
+    fn new(
+        factory: &mut back::Factory,
+        desc: &TargetDesc,
+    ) -> Result<Self, gfx::CombinedError> {
+        use gfx::format::{ChannelTyped, Formatted};
+        use gfx::texture as t;
+
+        let aa = if desc.samples > 1 {
+            t::AaMode::Multi(desc.samples)
+        } else {
+            t::AaMode::Single
+        };
+        let kind = t::Kind::D2(desc.size.0, desc.size.1, aa);
+        match desc.format {
+            TargetFormat::Color | TargetFormat::HdrColor => {
+                let (view, resource) = color_target(factory, kind, desc.format == TargetFormat::HdrColor)?;
+                Ok(Target::Color { view, resource })
+            }
+            TargetFormat::Depth if desc.samples > 1 => {
+                let texture = factory.create_texture::<<DepthFormat as Formatted>::Surface>(
+                    kind,
+                    1,
+                    gfx::memory::Bind::DEPTH_STENCIL,
+                    gfx::memory::Usage::Data,
+                    Some(<<DepthFormat as Formatted>::Channel as ChannelTyped>::get_channel_type()),
+                )?;
+                Ok(Target::Depth {
+                    view: factory.view_texture_as_depth_stencil_trivial(&texture)?,
+                    resource: None,
+                })
+            }
+            TargetFormat::Depth => {
+                let (_, resource, view) = factory.create_depth_stencil::<DepthFormat>(desc.size.0, desc.size.1)?;
+                Ok(Target::Depth {
+                    view,
+                    resource: Some(resource),
+                })
+            }
+        }
+    }
+
+    fn color(&self) -> (
+        h::RenderTargetView<back::Resources, ColorFormat>,
+        h::ShaderResourceView<back::Resources, [f32; 4]>,
+    ) {
+        match *self {
+            Target::Color { ref view, ref resource } => (view.clone(), resource.clone()),
+            Target::Depth { .. } => panic!("Depth target used as a color target"),
+        }
+    }
+
+    fn depth(&self) -> (
+        h::DepthStencilView<back::Resources, DepthFormat>,
+        Option<h::ShaderResourceView<back::Resources, f32>>,
+    ) {
+        match *self {
+            Target::Depth { ref view, ref resource } => (view.clone(), resource.clone()),
+            Target::Color { .. } => panic!("Color target used as a depth target"),
+        }
+    }
+}
+
+/// Targets backing the slots of the last frame graph, recycled by the
+/// following frames.
+#[derive(Default)]
+struct TargetPool {
+    targets: Vec<(TargetDesc, Target)>,
+}
+
+impl TargetPool {
+    /// Returns a target for each of `descs`, reusing the targets of the
+    /// previous call with a matching description and releasing the others.
+    fn acquire(
+        &mut self,
+        factory: &mut back::Factory,
+        descs: &[TargetDesc],
+    ) -> Result<Vec<Target>, gfx::CombinedError> {
+        let mut previous = mem::replace(&mut self.targets, Vec::with_capacity(descs.len()));
+        for desc in descs {
+            let target = match previous.iter().position(|&(ref other, _)| other == desc) {
+                Some(index) => previous.swap_remove(index).1,
+                None => Target::new(factory, desc)?,
+            };
+            self.targets.push((*desc, target));
+        }
+        Ok(self.targets.iter().map(|&(_, ref target)| target.clone()).collect())
+    }
+}
+
+/// Passes of a frame, with the views of the targets they use.
+struct FramePlan {
+    passes: Vec<Pass>,
+    /// Targets the scene is rendered into, if not the output ones.
+    scene: Option<(
+        h::RenderTargetView<back::Resources, ColorFormat>,
+        h::DepthStencilView<back::Resources, DepthFormat>,
+    )>,
+    /// Sampled depth of the scene, if available to the post effects.
+    depth: Option<h::ShaderResourceView<back::Resources, f32>>,
+    /// Samples per pixel, input and output of the multisample resolve,
+    /// `None` writing to the output target.
+    resolve: Option<(
+        u8,
+        h::ShaderResourceView<back::Resources, [f32; 4]>,
+        Option<h::RenderTargetView<back::Resources, ColorFormat>>,
+    )>,
+    /// Input and output of each post step, `None` writing to the output
+    /// target.
+    post: Vec<(
+        h::ShaderResourceView<back::Resources, [f32; 4]>,
+        Option<h::RenderTargetView<back::Resources, ColorFormat>>,
+    )>,
+}
+
+/// Float cube map a reflection probe is captured into, with a render target
+/// for each face and a depth target shared by the faces.
+struct ProbeTargets {
+    faces: Vec<h::RenderTargetView<back::Resources, ColorFormat>>,
+    resource: h::ShaderResourceView<back::Resources, [f32; 4]>,
+    depth: h::DepthStencilView<back::Resources, DepthFormat>,
+}
+
+impl ProbeTargets {
+    fn new(
+        factory: &mut back::Factory,
+        resolution: gfx::texture::Size,
+    ) -> Result<Self, gfx::CombinedError> {
+        use gfx::format::{ChannelTyped, Formatted, Rgba16F};
+        use gfx::texture as t;
+
+        // a full mipmap chain, sampled by rough materials
+        let levels = 32 - (resolution as u32).leading_zeros();
+        let texture = factory.create_texture::<<Rgba16F as Formatted>::Surface>(
+            t::Kind::Cube(resolution),
+            levels as t::Level,
+            gfx::memory::Bind::RENDER_TARGET | gfx::memory::Bind::SHADER_RESOURCE,
+            gfx::memory::Usage::Data,
+            Some(<<Rgba16F as Formatted>::Channel as ChannelTyped>::get_channel_type()),
+        )?;
+        let mut faces = Vec::with_capacity(t::CUBE_FACES.len());
+        for face in 0 .. t::CUBE_FACES.len() {
+            let target = factory.view_texture_as_render_target::<Rgba16F>(&texture, 0, Some(face as t::Layer))?;
+            // see `color_target` about the format
+            faces.push(Typed::new(target.raw().clone()));
+        }
+        let resource = factory.view_texture_as_shader_resource::<Rgba16F>(
+            &texture,
+            (0, levels as t::Level - 1),
+            gfx::format::Swizzle::new(),
+        )?;
+        Ok(ProbeTargets {
+            faces,
+            resource,
+            depth: factory.create_depth_stencil_view_only(resolution, resolution)?,
+        })
+    }
+}
+
+/// Target of the virtual texture feedback pass, with the buffer it's read
+/// back through by the next frame.
+struct FeedbackTarget {
+    texture: h::Texture<back::Resources, <ColorFormat as gfx::format::Formatted>::Surface>,
+    color: h::RenderTargetView<back::Resources, ColorFormat>,
+    depth: h::DepthStencilView<back::Resources, DepthFormat>,
+    globals: h::Buffer<back::Resources, Globals>,
+    download: h::Buffer<back::Resources, [u8; 4]>,
+    /// Whether `download` holds feedback not read back yet.
+    pending: bool,
+}
+
+impl FeedbackTarget {
+    fn new(
+        factory: &mut back::Factory,
+        width: gfx::texture::Size,
+        height: gfx::texture::Size,
+    ) -> Result<Self, gfx::CombinedError> {
+        use gfx::format::{ChannelTyped, Formatted};
+        use gfx::texture as t;
+
+        let texture = factory.create_texture::<<ColorFormat as Formatted>::Surface>(
+            t::Kind::D2(width, height, t::AaMode::Single),
+            1,
+            gfx::memory::Bind::RENDER_TARGET | gfx::memory::Bind::TRANSFER_SRC,
+            gfx::memory::Usage::Data,
+            Some(<<ColorFormat as Formatted>::Channel as ChannelTyped>::get_channel_type()),
+        )?;
+        Ok(FeedbackTarget {
+            color: factory.view_texture_as_render_target::<ColorFormat>(&texture, 0, None)?,
+            depth: factory.create_depth_stencil_view_only::<DepthFormat>(width, height)?,
+            texture,
+            globals: factory.create_constant_buffer(1),
+            download: factory
+                .create_download_buffer::<[u8; 4]>(width as usize * height as usize)
+                .expect("Can't create the feedback download buffer"),
+            pending: false,
+        })
+    }
+
+    fn size(&self) -> (gfx::texture::Size, gfx::texture::Size) {
+        let (width, height, _, _) = self.texture.get_info().kind.get_dimensions();
+        (width, height)
+    }
+}
+
+/// Targets of a frame: the output it ends up in. Intermediate targets are
+/// allocated by the frame graph.
+struct FrameTargets {
+    output: (
+        h::RenderTargetView<back::Resources, ColorFormat>,
+        h::DepthStencilView<back::Resources, DepthFormat>,
+    ),
+    /// Whether the frame is a face of a reflection probe capture, rendered
+    /// without reflection probes, overlays and UI.
+    capture: bool,
+    /// Region of the output the frame is restricted to, rendered without
+    /// multisampling, post effects, overlays and UI.
+    viewport: Option<Viewport>,
+}
+
 /// Handle for additional viewport to render some relevant debug information.
 /// See [`Renderer::debug_shadow_quad`](struct.Renderer.html#method.debug_shadow_quad).
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
@@ -517,20 +1406,84 @@ pub struct Renderer {
     inst_buf: h::Buffer<back::Resources, Instance>,
     light_buf: h::Buffer<back::Resources, LightParam>,
     pbr_buf: h::Buffer<back::Resources, PbrParams>,
+    probe_buf: h::Buffer<back::Resources, ProbeParams>,
     out_color: h::RenderTargetView<back::Resources, ColorFormat>,
     out_depth: h::DepthStencilView<back::Resources, DepthFormat>,
+    /// Texture behind `out_color` for headless renderers, read back by
+    /// `read_pixels`.
+    frame: Option<h::Texture<back::Resources, <ColorFormat as gfx::format::Formatted>::Surface>>,
     displacement_contributions_buf: gfx::handle::Buffer<back::Resources, DisplacementContribution>,
     default_joint_buffer_view: gfx::handle::ShaderResourceView<back::Resources, [f32; 4]>,
     default_displacement_buffer_view: gfx::handle::ShaderResourceView<back::Resources, [f32; 4]>,
     pso: PipelineStates<back::Resources>,
     map_default: Texture<[f32; 4]>,
+    /// Flat normal map, for materials without one.
+    normal_default: Texture<[f32; 4]>,
     shadow_default: Texture<f32>,
+    environment_default: CubeMap<[f32; 4]>,
+    max_lights: usize,
+    /// Requested number of samples per pixel, `0` or `1` without multisampling.
+    multisampling: u16,
+    /// Highest number of samples per pixel supported by the driver.
+    max_samples: u16,
+    post_effects: Vec<Box<PostEffect>>,
+    frame_graph: CompiledGraph,
+    /// Transient targets of the frame graph.
+    targets: TargetPool,
+    /// Whether the frame is rendered with float colors, tone mapped before
+    /// the post effects.
+    hdr: bool,
+    tone_mapping: ToneMapping,
+    post_buf: h::Buffer<back::Resources, PostParams>,
+    ssao_buf: h::Buffer<back::Resources, SsaoParams>,
+    post_sampler: h::Sampler<back::Resources>,
     debug_quads: froggy::Storage<DebugQuad>,
     size: (u32, u32),
+    dpi_factor: f32,
+    ui_scale: UiScale,
     font_cache: HashMap<String, Font>,
     instance_cache: HashMap<InstanceCacheKey, InstanceData>,
+    timer: Timer,
+    gizmo: Option<Gizmo>,
+    /// Texture of the last `Background::Gradient`, with its top and bottom colors.
+    gradient: Option<(color::Color, color::Color, Texture<[f32; 4]>)>,
+    /// Whether the background is skipped and the frame cleared to transparent
+    /// black, for `capture`.
+    transparent_background: bool,
+    /// Frame time budget checks, see `set_budget`.
+    watchdog: Option<Watchdog>,
+    /// Region of the window the frames are rendered into, see `set_viewport`.
+    viewport: Option<Viewport>,
+    /// Target of the virtual texture feedback, created on first use.
+    feedback: Option<FeedbackTarget>,
     /// `ShadowType` of this `Renderer`.
     pub shadow: ShadowType,
+    /// Depth bias applied when sampling shadow maps, to avoid shadow acne.
+    ///
+    /// Default: `0.0`.
+    pub shadow_bias: f32,
+    /// Width in texels of the filter kernel of `ShadowType::Pcf`.
+    ///
+    /// The kernel is centered on the shadow map sample, so even widths are
+    /// rounded down to the odd width below.
+    ///
+    /// Default: `3`.
+    pub pcf_kernel_size: u32,
+    /// Whether meshes outside the camera view frustum are skipped.
+    ///
+    /// Skinned meshes and meshes without known bounds are never culled.
+    ///
+    /// Default: `true`.
+    pub frustum_culling: bool,
+    /// Whether the shadow maps rendered in a frame are displayed in a row at the
+    /// bottom left of the screen, to help tuning `shadow_bias` and shadow projections.
+    ///
+    /// Default: `false`.
+    pub debug_shadows: bool,
+    /// Diagnostic display of the scene meshes, switchable between frames.
+    ///
+    /// Default: `DebugView::Off`.
+    pub debug_view: DebugView,
 }
 
 impl Renderer {
@@ -540,16 +1493,169 @@ impl Renderer {
         context: glutin::ContextBuilder,
         event_loop: &glutin::EventsLoop,
         source: &source::Set,
+        max_lights: usize,
     ) -> (Self, glutin::GlWindow, Factory) {
+        let (window, device, gl_factory, out_color, out_depth) = gfx_window_glutin::init(builder, context, event_loop);
+        let size = window.get_inner_size().unwrap();
+        let (renderer, factory) = Self::with_device(
+            device,
+            gl_factory,
+            out_color,
+            out_depth,
+            size,
+            window.hidpi_factor(),
+            source,
+            max_lights,
+        );
+        (renderer, window, factory)
+    }
+
+    /// Creates a renderer and a factory drawing into the default framebuffer of
+    /// an OpenGL context owned by the application, e.g. the surface of a GTK,
+    /// Qt or SDL2 window.
+    ///
+    /// `load` resolves OpenGL function names, like `SDL_GL_GetProcAddress` or
+    /// `glutin::GlContext::get_proc_address`. `size` is the framebuffer size in
+    /// pixels, to be updated with [`resize_to`](#method.resize_to). `max_lights`
+    /// is the number of lights affecting each mesh, usually [`DEFAULT_MAX_LIGHTS`].
+    ///
+    /// The application stays in charge of its event loop: it calls
+    /// [`render`](#method.render) each frame, then presents the frame by
+    /// swapping the buffers of its context. Use [`Factory::scene`] to create
+    /// the scene to render.
+    ///
+    /// # Safety
+    ///
+    /// The context must be current on the calling thread whenever the
+    /// renderer or the factory are used, and must have an 8-bit sRGB color
+    /// buffer and a 24-bit depth buffer with 8-bit stencil.
+    ///
+    /// [`Factory::scene`]: struct.Factory.html#method.scene
+    /// [`DEFAULT_MAX_LIGHTS`]: constant.DEFAULT_MAX_LIGHTS.html
+    #[cfg(feature = "opengl")]
+    pub unsafe fn from_gl_context<F>(
+        load: F,
+        size: (u32, u32),
+        source: &source::Set,
+        max_lights: usize,
+    ) -> (Self, Factory)
+    where
+        F: FnMut(&str) -> *const ::std::os::raw::c_void,
+    {
+        let (device, gl_factory) = back::create(load);
+        let (out_color, out_depth) = Self::main_targets(size);
+        Self::with_device(device, gl_factory, out_color, out_depth, size, 1.0, source, max_lights)
+    }
+
+    /// Creates a renderer and a factory drawing into an offscreen texture of
+    /// `size` pixels with the current headless OpenGL context, see
+    /// [`Builder::build_headless`](window/struct.Builder.html#method.build_headless).
+    #[cfg(feature = "opengl")]
+    pub(crate) fn headless(
+        context: &glutin::HeadlessContext,
+        size: (u32, u32),
+        source: &source::Set,
+        max_lights: usize,
+    ) -> (Self, Factory) {
+        use gfx::format::{Formatted, ChannelTyped};
+        use gfx::texture as t;
+        use glutin::GlContext;
+
+        let (device, mut gl_factory) = back::create(|name| context.get_proc_address(name) as *const _);
+        let texture = gl_factory
+            .create_texture::<<ColorFormat as Formatted>::Surface>(
+                t::Kind::D2(size.0 as t::Size, size.1 as t::Size, t::AaMode::Single),
+                1,
+                gfx::memory::Bind::RENDER_TARGET | gfx::memory::Bind::TRANSFER_SRC,
+                gfx::memory::Usage::Data,
+                Some(<<ColorFormat as Formatted>::Channel as ChannelTyped>::get_channel_type()),
+            )
+            .expect("Can't create offscreen color target");
+        let out_color = gl_factory
+            .view_texture_as_render_target::<ColorFormat>(&texture, 0, None)
+            .unwrap();
+        let out_depth = gl_factory
+            .create_depth_stencil_view_only::<DepthFormat>(size.0 as t::Size, size.1 as t::Size)
+            .expect("Can't create offscreen depth target");
+        let (mut renderer, factory) = Self::with_device(device, gl_factory, out_color, out_depth, size, 1.0, source, max_lights);
+        renderer.frame = Some(texture);
+        (renderer, factory)
+    }
+
+    /// Updates the framebuffer size of a renderer created with
+    /// [`from_gl_context`](#method.from_gl_context), e.g. when the host window
+    /// is resized.
+    ///
+    /// `dpi_factor` is used by [`UiScale::Auto`](enum.UiScale.html#variant.Auto).
+    #[cfg(feature = "opengl")]
+    pub fn resize_to(
+        &mut self,
+        size: (u32, u32),
+        dpi_factor: f32,
+    ) {
+        if size.0 == 0 || size.1 == 0 {
+            return;
+        }
+        let (out_color, out_depth) = Self::main_targets(size);
+        self.out_color = out_color;
+        self.out_depth = out_depth;
+        self.size = size;
+        self.dpi_factor = dpi_factor;
+    }
+
+    /// Returns views of the default framebuffer with the given size.
+    #[cfg(feature = "opengl")]
+    fn main_targets(size: (u32, u32)) -> (
+        h::RenderTargetView<back::Resources, ColorFormat>,
+        h::DepthStencilView<back::Resources, DepthFormat>,
+    ) {
+        use gfx::format::Formatted;
+
+        let dim = (size.0 as u16, size.1 as u16, 1, gfx::texture::AaMode::Single);
+        let (color, depth) = back::create_main_targets_raw(
+            dim,
+            ColorFormat::get_format().0,
+            DepthFormat::get_format().0,
+        );
+        (Typed::new(color), Typed::new(depth))
+    }
+
+    #[cfg(feature = "opengl")]
+    fn with_device(
+        mut device: back::Device,
+        mut gl_factory: back::Factory,
+        out_color: h::RenderTargetView<back::Resources, ColorFormat>,
+        out_depth: h::DepthStencilView<back::Resources, DepthFormat>,
+        size: (u32, u32),
+        dpi_factor: f32,
+        source: &source::Set,
+        max_lights: usize,
+    ) -> (Self, Factory) {
         use gfx::texture as t;
 
-        let (window, device, mut gl_factory, out_color, out_depth) = gfx_window_glutin::init(builder, context, event_loop);
+        let max_lights = cmp::max(max_lights, 1);
+        let mut defines = source::Defines::new();
+        defines.define("MAX_LIGHTS", format!("{}U", max_lights));
+        let source = &source.with_defines(&defines);
+
         let (_, srv_white) = gl_factory
             .create_texture_immutable::<gfx::format::Rgba8>(
                 t::Kind::D2(1, 1, t::AaMode::Single),
                 t::Mipmap::Provided,
                 &[&[[0xFF; 4]]]
             ).unwrap();
+        let (_, srv_normal) = gl_factory
+            .create_texture_immutable::<gfx::format::Rgba8>(
+                t::Kind::D2(1, 1, t::AaMode::Single),
+                t::Mipmap::Provided,
+                &[&[[0x80, 0x80, 0xFF, 0xFF]]]
+            ).unwrap();
+        let (_, srv_environment) = gl_factory
+            .create_texture_immutable::<gfx::format::Rgba8>(
+                t::Kind::Cube(1),
+                t::Mipmap::Provided,
+                &[&[[0u8; 4]][..]; 6],
+            ).unwrap();
         let (_, srv_shadow) = gl_factory
             .create_texture_immutable::<(gfx::format::R32, gfx::format::Float)>(
                 t::Kind::D2(1, 1, t::AaMode::Single),
@@ -599,8 +1705,11 @@ impl Renderer {
         let encoder = gl_factory.create_command_buffer().into();
         let const_buf = gl_factory.create_constant_buffer(1);
         let quad_buf = gl_factory.create_constant_buffer(1);
-        let light_buf = gl_factory.create_constant_buffer(MAX_LIGHTS);
+        let light_buf = gl_factory.create_constant_buffer(max_lights);
         let pbr_buf = gl_factory.create_constant_buffer(1);
+        let probe_buf = gl_factory.create_constant_buffer(1);
+        let post_buf = gl_factory.create_constant_buffer(1);
+        let ssao_buf = gl_factory.create_constant_buffer(1);
         let inst_buf = gl_factory
             .create_buffer(
                 1,
@@ -610,7 +1719,13 @@ impl Renderer {
             )
             .unwrap();
         let displacement_contributions_buf = gl_factory.create_constant_buffer(MAX_TARGETS);
-        let pso = PipelineStates::init(source, &mut gl_factory).unwrap();
+        let mut pso = PipelineStates::init(source, &mut gl_factory).unwrap();
+        pso.geometry_shaders = !device.get_info().version.is_embedded;
+        let mut max_samples = 0;
+        unsafe {
+            // GL_MAX_SAMPLES
+            device.with_gl(|gl| gl.GetIntegerv(0x8D57, &mut max_samples));
+        }
 
         let renderer = Renderer {
             device,
@@ -621,25 +1736,59 @@ impl Renderer {
             light_buf,
             inst_buf,
             pbr_buf,
+            probe_buf,
             displacement_contributions_buf,
             out_color,
             out_depth,
+            frame: None,
             pso,
             default_joint_buffer_view,
             default_displacement_buffer_view,
-            map_default: Texture::new(srv_white, sampler, [1, 1]),
-            shadow_default: Texture::new(srv_shadow, sampler_shadow, [1, 1]),
+            map_default: Texture::new(srv_white, sampler.clone(), [1, 1], ColorSpace::Linear),
+            normal_default: Texture::new(srv_normal, sampler.clone(), [1, 1], ColorSpace::Linear),
+            shadow_default: Texture::new(srv_shadow, sampler_shadow, [1, 1], ColorSpace::Linear),
+            environment_default: CubeMap::new(srv_environment, sampler.clone()),
+            max_lights,
+            multisampling: 0,
+            max_samples: cmp::min(cmp::max(max_samples, 0), u8::max_value() as i32) as u16,
+            post_effects: Vec::new(),
+            frame_graph: CompiledGraph::default(),
+            targets: TargetPool::default(),
+            hdr: false,
+            tone_mapping: ToneMapping::default(),
+            post_buf,
+            ssao_buf,
+            post_sampler: sampler,
             instance_cache: HashMap::new(),
+            timer: Timer::new(),
+            gizmo: None,
+            gradient: None,
+            transparent_background: false,
+            watchdog: None,
+            viewport: None,
+            feedback: None,
             shadow: ShadowType::Basic,
+            shadow_bias: 0.0,
+            pcf_kernel_size: 3,
+            frustum_culling: true,
+            debug_shadows: false,
+            debug_view: DebugView::Off,
             debug_quads: froggy::Storage::new(),
             font_cache: HashMap::new(),
-            size: window.get_inner_size().unwrap(),
+            size,
+            dpi_factor,
+            ui_scale: UiScale::default(),
         };
         let factory = Factory::new(gl_factory);
-        (renderer, window, factory)
+        (renderer, factory)
     }
 
     /// Reloads the shaders.
+    ///
+    /// The shaders of `pipeline_states` must be compiled for the same number of
+    /// lights as the renderer, by defining `MAX_LIGHTS` with
+    /// [`Set::with_defines`](source/struct.Set.html#method.with_defines) when
+    /// it differs from [`DEFAULT_MAX_LIGHTS`](constant.DEFAULT_MAX_LIGHTS.html).
     pub fn reload(
         &mut self,
         pipeline_states: PipelineStates<back::Resources>,
@@ -647,11 +1796,163 @@ impl Renderer {
         self.pso = pipeline_states;
     }
 
-    pub(crate) fn resize(
+    /// Returns the maximum number of lights affecting each mesh.
+    ///
+    /// When the scene has more lights, each mesh is lit by the lights that
+    /// contribute the most at its position: the brightest ones, with point
+    /// lights attenuated by their squared distance to the mesh.
+    pub fn max_lights(&self) -> usize {
+        self.max_lights
+    }
+
+    /// Sets the number of samples per pixel used to anti-alias the frame.
+    /// `0` and `1` disable multisampling. Defaults to `0`, or to the value
+    /// passed to [`window::Builder::multisampling`].
+    ///
+    /// The frame is rendered into multisampled targets, which are resolved
+    /// before the post effects, or into the output target once the scene is
+    /// done. Counts above the
+    /// highest one returned by [`supported_multisampling`] are lowered to it.
+    ///
+    /// [`window::Builder::multisampling`]: window/struct.Builder.html#method.multisampling
+    /// [`supported_multisampling`]: #method.supported_multisampling
+    pub fn set_multisampling(
         &mut self,
-        window: &glutin::GlWindow,
+        samples: u16,
     ) {
-        let size = window.get_inner_size().unwrap();
+        let supported = self.supported_multisampling();
+        let samples = match supported.iter().rev().find(|&&count| count <= samples) {
+            Some(&count) => count,
+            None => 0,
+        };
+        self.multisampling = samples;
+    }
+
+    /// Returns the number of samples per pixel used to anti-alias the frame,
+    /// `0` or `1` without multisampling.
+    pub fn multisampling(&self) -> u16 {
+        self.multisampling
+    }
+
+    /// Returns the sample counts accepted by [`set_multisampling`] with an
+    /// effect, in increasing order. The list only contains `1` if the driver
+    /// doesn't support multisampled render targets.
+    ///
+    /// [`set_multisampling`]: #method.set_multisampling
+    pub fn supported_multisampling(&self) -> Vec<u16> {
+        let mut counts = vec![1];
+        while counts[counts.len() - 1] * 2 <= self.max_samples {
+            let next = counts[counts.len() - 1] * 2;
+            counts.push(next);
+        }
+        counts
+    }
+
+    /// Adds a full-screen effect applied to the frame after the scene, after
+    /// the effects added before. See the [`post`](post/index.html) module.
+    pub fn add_post_effect(
+        &mut self,
+        effect: Box<PostEffect>,
+    ) {
+        self.post_effects.push(effect);
+    }
+
+    /// Removes all the effects added with [`add_post_effect`](#method.add_post_effect).
+    pub fn clear_post_effects(&mut self) {
+        self.post_effects.clear();
+    }
+
+    /// Enables or disables HDR rendering. Defaults to `false`.
+    ///
+    /// With HDR rendering, the scene is rendered into targets with 16-bit float
+    /// channels, so bright lights and emissive materials don't clip, and tone
+    /// mapped to the displayable range before the effects added with
+    /// [`add_post_effect`](#method.add_post_effect), except those working on
+    /// unclamped colors, see
+    /// [`PostEffect::before_tone_mapping`](post/trait.PostEffect.html#method.before_tone_mapping).
+    pub fn set_hdr(
+        &mut self,
+        enable: bool,
+    ) {
+        self.hdr = enable;
+    }
+
+    /// Returns `true` if HDR rendering is enabled.
+    pub fn hdr(&self) -> bool {
+        self.hdr
+    }
+
+    /// Sets the multiplier applied to the colors of the frame before tone
+    /// mapping, with HDR rendering. Defaults to `1.0`.
+    pub fn set_exposure(
+        &mut self,
+        exposure: f32,
+    ) {
+        self.tone_mapping.exposure = exposure;
+    }
+
+    /// Returns the exposure set with [`set_exposure`](#method.set_exposure).
+    pub fn exposure(&self) -> f32 {
+        self.tone_mapping.exposure
+    }
+
+    /// Sets the curve mapping the colors of the frame to the displayable range,
+    /// with HDR rendering. Defaults to `ToneMapOperator::Aces`.
+    pub fn set_tone_map_operator(
+        &mut self,
+        operator: ToneMapOperator,
+    ) {
+        self.tone_mapping.operator = operator;
+    }
+
+    /// Returns the number of full-screen passes run after the scene: the tone
+    /// mapping of HDR rendering, if enabled, and the post effects.
+    fn post_count(&self) -> usize {
+        self.hdr as usize + self.post_effects.len()
+    }
+
+    /// Returns the full-screen passes run after the scene, in order: with HDR
+    /// rendering, the effects working on unclamped colors come before the
+    /// tone mapping and the others after it.
+    fn post_steps(&self) -> Vec<PostStep> {
+        let (before, after): (Vec<usize>, Vec<usize>) = (0 .. self.post_effects.len())
+            .partition(|&index| self.hdr && self.post_effects[index].before_tone_mapping());
+        let tone_mapping = if self.hdr { Some(PostStep::ToneMapping) } else { None };
+        before
+            .into_iter()
+            .map(PostStep::Effect)
+            .chain(tone_mapping)
+            .chain(after.into_iter().map(PostStep::Effect))
+            .collect()
+    }
+
+    /// Resolves the multisampled color `input` into `target`.
+    fn resolve(
+        &mut self,
+        samples: u8,
+        input: h::ShaderResourceView<back::Resources, [f32; 4]>,
+        target: h::RenderTargetView<back::Resources, ColorFormat>,
+    ) {
+        let slice = gfx::Slice {
+            start: 0,
+            end: 4,
+            base_vertex: 0,
+            instances: None,
+            buffer: gfx::IndexBuffer::Auto,
+        };
+        let data = resolve_pipe::Data {
+            samples: samples as i32,
+            input,
+            target,
+        };
+        self.encoder.draw(&slice, &self.pso.resolve, &data);
+    }
+
+    pub(crate) fn resize(
+        &mut self,
+        window: &glutin::GlWindow,
+    ) {
+        let size = window.get_inner_size().unwrap();
 
         // skip updating view and self size if some
         // of the sides equals to zero (fixes crash on minimize on Windows machines)
@@ -660,9 +1961,124 @@ impl Renderer {
         }
 
         self.size = size;
+        self.dpi_factor = window.hidpi_factor();
         gfx_window_glutin::update_views(window, &mut self.out_color, &mut self.out_depth);
     }
 
+    /// Sets the scaling applied to the font size and bounds of all UI text,
+    /// along with their outline widths and shadow offsets. Positions are left
+    /// as is.
+    ///
+    /// Default: `UiScale::Custom(1.0)`.
+    pub fn set_ui_scale(
+        &mut self,
+        ui_scale: UiScale,
+    ) {
+        self.ui_scale = ui_scale;
+    }
+
+    /// Sets the time budgets of a frame, or `None` to disable the checks.
+    ///
+    /// Every frame rendered by `render` is timed phase by phase: message
+    /// processing, updates preceding the passes, each render pass and the final
+    /// submission. A warning with the time of each phase is logged for the
+    /// frames exceeding their budget, to help spot which part of the frame
+    /// regressed. Each call to `render`, `render_to_views` or `capture_probe`
+    /// is checked as a frame of its own.
+    ///
+    /// Default: `None`.
+    pub fn set_budget(
+        &mut self,
+        budget: Option<Budget>,
+    ) {
+        match budget {
+            Some(budget) => match self.watchdog {
+                Some(ref mut watchdog) => watchdog.budget = budget,
+                None => self.watchdog = Some(Watchdog::new(budget, &self.device)),
+            },
+            None => if let Some(mut watchdog) = self.watchdog.take() {
+                watchdog.release(&mut self.device);
+            },
+        }
+    }
+
+    /// Returns the current time budgets of a frame, if checked.
+    pub fn budget(&self) -> Option<Budget> {
+        self.watchdog.as_ref().map(|watchdog| watchdog.budget)
+    }
+
+    /// Restricts the following frames to a region of the window, or lets them
+    /// cover the whole window with `None`.
+    ///
+    /// Rendering several cameras into different viewports of the same frame
+    /// gives split-screen or picture-in-picture views. The aspect ratio of the
+    /// cameras is the one of their viewport. The rest of the window is left
+    /// untouched, so the viewports should either cover the window or be
+    /// rendered over a full window frame.
+    ///
+    /// Viewport frames are drawn straight into their region of the window,
+    /// clipped by a scissor rectangle, with the camera projection fitted to
+    /// the region. They skip multisampling and post effects, and leave out UI
+    /// text, the gizmo and debug quads, which are drawn by the frames covering
+    /// the whole window. Frames rendered with `render_to_target` and
+    /// `render_to_views` ignore the viewport.
+    ///
+    /// ```rust,no_run
+    /// # let mut window = three::Window::new("");
+    /// # let left = window.factory.perspective_camera(60.0, 0.1 .. 10.0);
+    /// # let right = window.factory.perspective_camera(60.0, 0.1 .. 10.0);
+    /// while window.update() {
+    ///     let size = window.size();
+    ///     let (width, height) = (size.x as u32, size.y as u32);
+    ///     let half = width / 2;
+    ///     window.renderer.set_viewport(Some(three::render::Viewport {
+    ///         x: 0,
+    ///         y: 0,
+    ///         width: half,
+    ///         height,
+    ///     }));
+    ///     window.render(&left);
+    ///     window.renderer.set_viewport(Some(three::render::Viewport {
+    ///         x: half,
+    ///         y: 0,
+    ///         width: width - half,
+    ///         height,
+    ///     }));
+    ///     window.render(&right);
+    /// }
+    /// ```
+    ///
+    /// Default: `None`.
+    pub fn set_viewport(
+        &mut self,
+        viewport: Option<Viewport>,
+    ) {
+        self.viewport = viewport;
+    }
+
+    /// Returns the region of the window the frames are rendered into, if
+    /// restricted.
+    pub fn viewport(&self) -> Option<Viewport> {
+        self.viewport
+    }
+
+    /// Ends the current phase of the frame timed by the watchdog, if any.
+    fn end_phase(
+        &mut self,
+        phase: Phase,
+    ) {
+        if let Some(ref mut watchdog) = self.watchdog {
+            watchdog.end_phase(phase, &mut self.device, &mut self.encoder);
+        }
+    }
+
+    /// Returns the current UI text scale factor, accounting for the window
+    /// size and DPI factor when using `UiScale::Auto`.
+    pub fn ui_scale_factor(&self) -> f32 {
+        let size = [self.size.0 as f32, self.size.1 as f32];
+        self.ui_scale.factor(size.into(), self.dpi_factor)
+    }
+
     /// Returns current viewport aspect ratio, i.e. width / height.
     pub fn aspect_ratio(&self) -> f32 {
         self.size.0 as f32 / self.size.1 as f32
@@ -687,6 +2103,123 @@ impl Renderer {
         &mut self,
         scene: &Scene,
         camera: &Camera,
+    ) {
+        if let Some(ref mut watchdog) = self.watchdog {
+            watchdog.begin_frame();
+        }
+        let frame = FrameTargets {
+            output: (self.out_color.clone(), self.out_depth.clone()),
+            capture: false,
+            viewport: self.viewport,
+        };
+        self.render_frame(scene, camera, &frame);
+        self.encoder.flush(&mut self.device);
+        self.end_phase(Phase::Submit);
+        if let Some(ref mut watchdog) = self.watchdog {
+            watchdog.end_frame(&mut self.device);
+        }
+    }
+
+    /// Captures the surroundings of `probe` into its cube map, for the
+    /// reflections of the PBR materials within its volume.
+    ///
+    /// The probe must be in `scene`. The scene is rendered once per face of the
+    /// cube map, from the world position of the probe, without reflection probes,
+    /// UI and debug overlays. Captures are kept until the next call, so this is
+    /// typically called once the surroundings are set up and whenever they change.
+    pub fn capture_probe(
+        &mut self,
+        scene: &Scene,
+        probe: &ReflectionProbe,
+    ) {
+        use cgmath::Decomposed;
+
+        // looking direction and up vector of the cube map faces, in GL order
+        const FACES: [([f32; 3], [f32; 3]); 6] = [
+            ([1.0, 0.0, 0.0], [0.0, -1.0, 0.0]),
+            ([-1.0, 0.0, 0.0], [0.0, -1.0, 0.0]),
+            ([0.0, 1.0, 0.0], [0.0, 0.0, 1.0]),
+            ([0.0, -1.0, 0.0], [0.0, 0.0, -1.0]),
+            ([0.0, 0.0, 1.0], [0.0, -1.0, 0.0]),
+            ([0.0, 0.0, -1.0], [0.0, -1.0, 0.0]),
+        ];
+
+        let (camera, position, resolution) = {
+            let mut hub = scene.hub.lock().unwrap();
+            hub.process_messages();
+            let (range, resolution) = match hub[probe].sub_node {
+                SubNode::Probe(ref data) => (data.range.clone(), data.resolution),
+                _ => unreachable!(),
+            };
+            let position = {
+                let probe_node = &hub[probe] as *const _;
+                hub.walk(&scene.first_child)
+                    .find(|w| w.node as *const _ == probe_node)
+                    .map(|w| w.world_transform.disp)
+            };
+            let position = match position {
+                Some(position) => position,
+                None => {
+                    error!("Reflection probe is not in the scene, skipping the capture");
+                    return;
+                }
+            };
+            let camera = Camera::new(&mut hub, Projection::perspective(90.0, range));
+            (camera, position, resolution)
+        };
+        let targets = match ProbeTargets::new(&mut self.factory, resolution) {
+            Ok(targets) => targets,
+            Err(err) => {
+                error!("Failed to create reflection probe targets: {:?}", err);
+                return;
+            }
+        };
+
+        if let Some(ref mut watchdog) = self.watchdog {
+            watchdog.begin_frame();
+        }
+        let (size, out_color, out_depth) = (self.size, self.out_color.clone(), self.out_depth.clone());
+        self.size = (resolution as u32, resolution as u32);
+        for (face, &(forward, up)) in targets.faces.iter().zip(FACES.iter()) {
+            // the camera isn't in the scene, so its local transform is used as is
+            scene.hub.lock().unwrap()[&camera].transform = Decomposed {
+                scale: 1.0,
+                rot: math::look_rotation(forward, up).into(),
+                disp: position,
+            };
+            self.out_color = face.clone();
+            self.out_depth = targets.depth.clone();
+            let frame = FrameTargets {
+                output: (face.clone(), targets.depth.clone()),
+                capture: true,
+                viewport: None,
+            };
+            self.render_frame(scene, &camera, &frame);
+        }
+        self.size = size;
+        self.out_color = out_color;
+        self.out_depth = out_depth;
+
+        self.encoder.generate_mipmap_raw(targets.resource.raw());
+        self.encoder.flush(&mut self.device);
+        self.end_phase(Phase::Submit);
+        if let Some(ref mut watchdog) = self.watchdog {
+            watchdog.end_frame(&mut self.device);
+        }
+
+        let map = CubeMap::new(targets.resource, self.environment_default.to_param().1);
+        if let SubNode::Probe(ref mut data) = scene.hub.lock().unwrap()[probe].sub_node {
+            data.map = Some(map);
+        }
+    }
+
+    /// Records the passes of a frame into the output targets, running the post
+    /// effects from the targets of `frame`.
+    fn render_frame(
+        &mut self,
+        scene: &Scene,
+        camera: &Camera,
+        frame: &FrameTargets,
     ) {
         {
             use gfx::Device;
@@ -694,30 +2227,61 @@ impl Renderer {
         }
 
         let mut hub = scene.hub.lock().unwrap();
+        // UI and overlays are drawn by the frames covering the whole output
+        let overlays = !frame.capture && frame.viewport.is_none();
+        // captures count towards the frame they happen in
+        if !frame.capture {
+            hub.begin_frame();
+        }
         hub.process_messages();
+        if !frame.capture {
+            self.read_feedback(&mut hub);
+            self.end_phase(Phase::Messages);
+        }
         // update joint transforms of skeletons
         {
-            use node::TransformInternal;
+            use node::NodePointer;
 
             struct SkeletonTemp {
-                inverse_world_transform: TransformInternal,
+                node_ptr: NodePointer,
+                update: bool,
+                mx_inverse_world: Matrix4<f32>,
                 cpu_buffer: Vec<[f32; 4]>,
                 gpu_buffer: gfx::handle::Buffer<BackendResources, [f32; 4]>,
             }
 
+            let now = self.timer.elapsed();
+            let camera_position = {
+                let camera_node = &hub[&camera] as *const _;
+                hub.walk(&scene.first_child)
+                    .find(|w| w.node as *const _ == camera_node)
+                    .map_or(hub[&camera].transform.disp, |w| w.world_transform.disp)
+            };
             let mut skeletons = Vec::new();
             for w in hub.walk(&scene.first_child) {
                 match w.node.sub_node {
                     SubNode::Skeleton(ref skeleton) => {
+                        let distance = (w.world_transform.disp - camera_position).magnitude();
+                        let update = match skeleton.lod.interval(distance) {
+                            Some(interval) => skeleton.last_update.map_or(true, |time| now - time >= interval),
+                            None => false,
+                        };
                         skeletons.push(SkeletonTemp {
-                            inverse_world_transform: w.world_transform.inverse_transform().unwrap(),
+                            node_ptr: w.node_ptr.clone(),
+                            update,
+                            mx_inverse_world: w.world_matrix().invert().unwrap_or_else(Matrix4::identity),
                             cpu_buffer: vec![[0.0; 4]; skeleton.bones.len() * VECS_PER_BONE],
                             gpu_buffer: skeleton.gpu_buffer.clone(),
                         });
                     }
                     SubNode::Bone { index, inverse_bind_matrix } => {
                         let skel = skeletons.last_mut().unwrap();
-                        let mx_base = Matrix4::from(skel.inverse_world_transform.concat(&w.world_transform));
+                        if !skel.update {
+                            continue;
+                        }
+                        // per-axis scales of the bones and their ancestors are
+                        // part of the pose
+                        let mx_base = skel.mx_inverse_world * w.world_matrix();
                         let mx = (mx_base * Matrix4::from(inverse_bind_matrix)).transpose();
                         let buf = &mut skel.cpu_buffer[index * VECS_PER_BONE .. (index + 1) * VECS_PER_BONE];
                         buf[0] = mx.x.into();
@@ -729,6 +2293,12 @@ impl Renderer {
             }
 
             for skel in skeletons {
+                if !skel.update {
+                    continue;
+                }
+                if let SubNode::Skeleton(ref mut data) = hub.nodes[&skel.node_ptr].sub_node {
+                    data.last_update = Some(now);
+                }
                 self.encoder
                     .update_buffer(
                         &skel.gpu_buffer,
@@ -739,14 +2309,36 @@ impl Renderer {
             }
         }
 
+        // upload streamed texture regions
+        for upload in hub.texture_uploads.drain(..) {
+            self.encoder
+                .copy_buffer_to_texture_raw(&upload.buffer, upload.offset, &upload.texture, None, upload.info)
+                .expect("upload to GPU target texture");
+        }
+
+        // fill the vertex buffers generated on the GPU
+        for job in hub.vertex_jobs.drain(..) {
+            self.run_vertex_job(job);
+        }
+
+        // generate the mipmaps of loaded cube maps
+        for view in hub.mipmaps.drain(..) {
+            self.encoder.generate_mipmap_raw(&view);
+        }
+
         // update dynamic meshes
         // Note: mutable node access here
+        let ui_scale = self.ui_scale_factor();
+        let mut feedback = false;
         for node in hub.nodes.iter_mut() {
             if !node.visible {
                 continue;
             }
             match node.sub_node {
-                SubNode::Visual(_, ref mut gpu_data, _) => {
+                SubNode::Visual(ref material, ref mut gpu_data, _) => {
+                    if let Material::Virtual(_) = *material {
+                        feedback = true;
+                    }
                     if let Some(dynamic) = gpu_data.pending.take() {
                         self.encoder
                             .copy_buffer(
@@ -761,11 +2353,11 @@ impl Renderer {
                 }
                 // Note: UI text currently applies to all the scenes.
                 // We may want to make it scene-dependent at some point.
-                SubNode::UiText(ref text) => {
-                    text.font.queue(&text.section);
-                    if !self.font_cache.contains_key(&text.font.id) {
+                SubNode::UiText(ref text) if overlays && text.world_scale.is_none() => {
+                    text.queue(ui_scale);
+                    if !self.font_cache.contains_key(&text.brush_font.id) {
                         self.font_cache
-                            .insert(text.font.id.clone(), text.font.clone());
+                            .insert(text.brush_font.id.clone(), text.brush_font.clone());
                     }
                 }
                 _ => {}
@@ -780,6 +2372,7 @@ impl Renderer {
             mx_proj: Matrix4<f32>,
         }
         let mut lights = Vec::new();
+        let mut light_priorities = Vec::new();
         let mut shadow_requests = Vec::new();
         let mut mx_camera_transform = hub[&camera].transform;
 
@@ -792,12 +2385,11 @@ impl Renderer {
                 SubNode::Light(ref light) => light,
                 _ => continue,
             };
-            if lights.len() == MAX_LIGHTS {
-                error!("Max number of lights ({}) reached", MAX_LIGHTS);
-                break;
-            }
-
-            let shadow_index = if let Some((ref map, ref projection)) = light.shadow {
+            let shadow = match self.shadow {
+                ShadowType::Off => None,
+                _ => light.shadow.as_ref(),
+            };
+            let shadow_index = if let Some(&(ref map, ref projection)) = shadow {
                 let target = map.to_target();
                 let dim = target.get_dimensions();
                 let aspect = dim.0 as f32 / dim.1 as f32;
@@ -856,40 +2448,17 @@ impl Renderer {
                 intensity,
                 shadow_params: [shadow_index, 0, 0, 0],
             });
-        }
-
-        // render shadow maps
-        for request in &shadow_requests {
-            self.encoder.clear_depth(&request.target, 1.0);
-            let mx_vp = request.mx_proj * request.mx_view;
-            self.encoder.update_constant_buffer(
-                &self.const_buf,
-                &Globals {
-                    mx_vp: mx_vp.into(),
-                    mx_view: request.mx_view.into(),
-                    mx_inv_proj: request.mx_proj.into(),
-                    num_lights: 0,
+            light_priorities.push(LightPriority {
+                brightness: light.intensity * color::to_linear_rgb(light.color)
+                    .iter()
+                    .cloned()
+                    .fold(0.0, f32::max),
+                position: match light.sub_light {
+                    SubLight::Point => Some(Point3::from_vec(w.world_transform.disp)),
+                    _ => None,
                 },
-            );
-
-            for w in hub.walk(&scene.first_child) {
-                let gpu_data = match w.node.sub_node {
-                    SubNode::Visual(_, ref data, _) => data,
-                    _ => continue,
-                };
-                let mx_world: mint::ColumnMatrix4<_> = Matrix4::from(w.world_transform).into();
-                self.encoder
-                    .update_buffer(&gpu_data.instances, &[Instance::pbr(mx_world.into())], 0)
-                    .unwrap();
-                //TODO: avoid excessive cloning
-                let data = shadow_pipe::Data {
-                    vbuf: gpu_data.vertices.clone(),
-                    inst_buf: gpu_data.instances.clone(),
-                    cb_globals: self.const_buf.clone(),
-                    target: request.target.clone(),
-                };
-                self.encoder.draw(&gpu_data.slice, &self.pso.shadow, &data);
-            }
+                layers: light.layers,
+            });
         }
 
         // prepare target and globals
@@ -898,253 +2467,1534 @@ impl Renderer {
             SubNode::Camera(ref projection) => projection.clone(),
             _ => panic!("Camera had incorrect sub node")
         };
-        let mx_proj = Matrix4::from(projection.matrix(self.aspect_ratio()));
-        self.encoder.update_constant_buffer(
-            &self.const_buf,
-            &Globals {
-                mx_vp: (mx_proj * mx_view).into(),
-                mx_view: mx_view.into(),
-                mx_inv_proj: mx_proj.invert().unwrap().into(),
-                num_lights: lights.len() as u32,
-            },
-        );
-        self.encoder
-            .update_buffer(&self.light_buf, &lights, 0)
-            .unwrap();
-
-        self.encoder.clear_depth(&self.out_depth, 1.0);
-        self.encoder.clear_stencil(&self.out_depth, 0);
-
-        if let Background::Color(color) = scene.background {
-            let rgb = color::to_linear_rgb(color);
-            self.encoder
-                .clear(&self.out_color, [rgb[0], rgb[1], rgb[2], 0.0]);
-        }
+        // the projection of a viewport frame has the aspect ratio of the
+        // viewport, and its clip space is squeezed into the viewport region
+        // of the output, out of which the scissor rectangle clips everything
+        let (aspect, ndc_rect, scissor) = match frame.viewport {
+            Some(viewport) => {
+                let p0 = self.map_to_ndc([viewport.x as f32, viewport.y as f32]);
+                let p1 = self.map_to_ndc([
+                    (viewport.x + viewport.width) as f32,
+                    (viewport.y + viewport.height) as f32,
+                ]);
+                // the scissor is clamped to the target, and its origin is at
+                // the bottom left corner, unlike the viewport's
+                let left = cmp::min(viewport.x, self.size.0);
+                let right = cmp::min(viewport.x.saturating_add(viewport.width), self.size.0);
+                let top = cmp::min(viewport.y, self.size.1);
+                let bottom = cmp::min(viewport.y.saturating_add(viewport.height), self.size.1);
+                let scissor = gfx::Rect {
+                    x: left as u16,
+                    y: (self.size.1 - bottom) as u16,
+                    w: (right - left) as u16,
+                    h: (bottom - top) as u16,
+                };
+                let aspect = viewport.width as f32 / cmp::max(viewport.height, 1) as f32;
+                (aspect, [p0.x, p1.y, p1.x, p0.y], scissor)
+            }
+            None => {
+                let scissor = gfx::Rect {
+                    x: 0,
+                    y: 0,
+                    w: self.size.0 as u16,
+                    h: self.size.1 as u16,
+                };
+                (self.aspect_ratio(), FULL_NDC_RECT, scissor)
+            }
+        };
+        let mx_proj = Matrix4::from(projection.matrix(aspect));
+        // projection of the scene passes into the output
+        let mx_clip = ndc_rect_matrix(ndc_rect) * mx_proj;
 
-        // render everything
-        let (shadow_default, shadow_sampler) = self.shadow_default.to_param();
-        let shadow0 = match shadow_requests.get(0) {
-            Some(ref request) => request.resource.clone(),
-            None => shadow_default.clone(),
+        let quad_slice = gfx::Slice {
+            start: 0,
+            end: 4,
+            base_vertex: 0,
+            instances: None,
+            buffer: gfx::IndexBuffer::Auto,
         };
-        let shadow1 = match shadow_requests.get(1) {
-            Some(ref request) => request.resource.clone(),
-            None => shadow_default.clone(),
+
+        let post_steps = self.post_steps();
+        // multisampling and post effects only apply to frames covering the
+        // whole output
+        // virtual textures are fed back from the frames covering the whole output
+        let feedback = feedback && overlays;
+        let plan = match self.plan_frame(frame, shadow_requests.len(), overlays, overlays, feedback) {
+            Some(plan) => plan,
+            None => return,
         };
+        if let Some((ref color, ref depth)) = plan.scene {
+            self.out_color = color.clone();
+            self.out_depth = depth.clone();
+        }
 
-        // clear instance cache
-        for instances in self.instance_cache.values_mut() {
-            instances.list.clear();
+        if !frame.capture {
+            self.end_phase(Phase::Setup);
         }
+        // meshes drawn by the scene pass of this frame
+        let mut rendered = HashSet::new();
+        for pass in plan.passes.clone() {
+            match pass {
+                Pass::Shadow(index) => {
+                    let request = &shadow_requests[index];
+                    self.encoder.clear_depth(&request.target, 1.0);
+                    let mx_vp = request.mx_proj * request.mx_view;
+                    self.encoder.update_constant_buffer(
+                        &self.const_buf,
+                        &Globals {
+                            mx_vp: mx_vp.into(),
+                            mx_view: request.mx_view.into(),
+                            mx_inv_proj: request.mx_proj.into(),
+                            env_params: [0.0; 4],
+                            ambient: [0.0; 4],
+                            clip_plane: NO_CLIP_PLANE,
+                            cap_color: [0.0; 4],
+                            shadow_params: [0.0; 4],
+                            fog_color: [0.0; 4],
+                            fog_params: [0.0; 4],
+                            resolution: [0.0; 4],
+                            num_lights: 0,
+                        },
+                    );
 
-        for w in hub.walk(&scene.first_child) {
-            let (material, gpu_data, skeleton) = match w.node.sub_node {
-                SubNode::Visual(ref material, ref gpu_data, ref skeleton) => {
-                    (material, gpu_data, skeleton)
+                    for w in hub.walk(&scene.first_child) {
+                        let gpu_data = match w.node.sub_node {
+                            // points are expanded by a geometry shader
+                            SubNode::Visual(Material::Points(_), _, _) => continue,
+                            SubNode::Visual(_, ref data, _) => data,
+                            _ => continue,
+                        };
+                        let mx_world: mint::ColumnMatrix4<_> = w.world_matrix().into();
+                        self.encoder
+                            .update_buffer(&gpu_data.instances, &[Instance::pbr(mx_world.into())], 0)
+                            .unwrap();
+                        //TODO: avoid excessive cloning
+                        let data = shadow_pipe::Data {
+                            vbuf: gpu_data.vertices.clone(),
+                            inst_buf: gpu_data.instances.clone(),
+                            cb_globals: self.const_buf.clone(),
+                            target: request.target.clone(),
+                        };
+                        self.encoder.draw(&gpu_data.slice, &self.pso.shadow, &data);
+                    }
                 }
-                _ => continue,
-            };
+                Pass::Scene => {
+                    let env = &scene.environment;
+                    // with too many lights, meshes are lit by a selection of them,
+                    // chosen at the camera position for instanced meshes
+                    let overflow = lights.len() > self.max_lights;
+                    let camera_lights = select_lights(
+                        &lights,
+                        &light_priorities,
+                        Point3::from_vec(mx_camera_transform.disp),
+                        self.max_lights,
+                        !0,
+                    );
+                    let mut lights_dirty = false;
+                    let ambient = color::to_linear_rgb(env.ambient_color);
+                    let pcf_radius = match self.shadow {
+                        ShadowType::Pcf => ((self.pcf_kernel_size.max(1) - 1) / 2) as f32,
+                        _ => 0.0,
+                    };
+                    let (clip_plane, cap_color) = match scene.section {
+                        Some(ref section) => {
+                            let normal = section.plane.normal;
+                            let cap_color = match section.cap {
+                                Some(color) => {
+                                    let rgb = color::to_linear_rgb(color);
+                                    [rgb[0], rgb[1], rgb[2], 1.0]
+                                }
+                                None => [0.0; 4],
+                            };
+                            ([normal.x, normal.y, normal.z, -section.plane.distance], cap_color)
+                        }
+                        None => (NO_CLIP_PLANE, [0.0; 4]),
+                    };
+                    let (fog_color, fog_params) = match env.fog {
+                        Some(Fog::Linear { near, far, color }) => {
+                            let rgb = color::to_linear_rgb(color);
+                            ([rgb[0], rgb[1], rgb[2], 1.0], [1.0, near, far, 0.0])
+                        }
+                        Some(Fog::Exp2 { density, color }) => {
+                            let rgb = color::to_linear_rgb(color);
+                            ([rgb[0], rgb[1], rgb[2], 1.0], [2.0, density, 0.0, 0.0])
+                        }
+                        None => ([0.0; 4], [0.0; 4]),
+                    };
+                    self.encoder.update_constant_buffer(
+                        &self.const_buf,
+                        &Globals {
+                            mx_vp: (mx_clip * mx_view).into(),
+                            mx_view: mx_view.into(),
+                            mx_inv_proj: mx_clip.invert().unwrap().into(),
+                            env_params: [
+                                env.intensity,
+                                env.rotation,
+                                if env.map.is_some() { 1.0 } else { 0.0 },
+                                0.0,
+                            ],
+                            ambient: [
+                                ambient[0] * env.ambient_intensity,
+                                ambient[1] * env.ambient_intensity,
+                                ambient[2] * env.ambient_intensity,
+                                0.0,
+                            ],
+                            clip_plane,
+                            cap_color,
+                            shadow_params: [self.shadow_bias, pcf_radius, 0.0, 0.0],
+                            fog_color,
+                            fog_params,
+                            resolution: [self.size.0 as f32, self.size.1 as f32, 0.0, 0.0],
+                            num_lights: cmp::min(lights.len(), self.max_lights) as u32,
+                        },
+                    );
+                    self.encoder
+                        .update_buffer(&self.light_buf, &camera_lights, 0)
+                        .unwrap();
 
-            let mx_world: mint::ColumnMatrix4<_> = Matrix4::from(w.world_transform).into();
-            let pso_data = material.to_pso_data();
+                    self.encoder.clear_depth(&self.out_depth, 1.0);
+                    self.encoder.clear_stencil(&self.out_depth, 0);
 
-            let instance = match pso_data {
-                PsoData::Basic { color, map, param0 } => {
-                    let uv_range = match map {
-                        Some(ref map) => map.uv_range(),
-                        None => [0.0; 4],
+                    // clears cover the whole output, so viewport frames draw
+                    // their background color in the background pass instead
+                    if self.transparent_background {
+                        self.encoder.clear(&self.out_color, [0.0; 4]);
+                    } else if let Background::Color(color) = scene.background {
+                        if frame.viewport.is_none() {
+                            let rgb = color::to_linear_rgb(color);
+                            self.encoder
+                                .clear(&self.out_color, [rgb[0], rgb[1], rgb[2], 0.0]);
+                        }
+                    }
+
+                    // render everything
+                    let environment = match scene.environment.map {
+                        Some(ref map) => map.to_param(),
+                        None => self.environment_default.to_param(),
+                    };
+                    let (shadow_default, shadow_sampler) = self.shadow_default.to_param();
+                    let shadow0 = match shadow_requests.get(0) {
+                        Some(ref request) => request.resource.clone(),
+                        None => shadow_default.clone(),
                     };
-                    if let Some(ref key) = gpu_data.instance_cache_key {
-                        let data = self.instance_cache
-                            .entry(key.clone())
-                            .or_insert_with(|| InstanceData {
-                                slice: gpu_data.slice.clone(),
-                                vertices: gpu_data.vertices.clone(),
-                                material: material.clone(),
-                                list: Vec::new(),
+                    let shadow1 = match shadow_requests.get(1) {
+                        Some(ref request) => request.resource.clone(),
+                        None => shadow_default.clone(),
+                    };
+
+                    // captured reflection probes, from the smallest volume to the largest
+                    let probe_default = self.environment_default.to_param();
+                    let no_probe_maps = [probe_default.clone(), probe_default.clone()];
+                    let mut probes = Vec::new();
+                    if !frame.capture {
+                        for w in hub.walk(&scene.first_child) {
+                            let probe = match w.node.sub_node {
+                                SubNode::Probe(ref probe) => probe,
+                                _ => continue,
+                            };
+                            let map = match probe.map {
+                                Some(ref map) => map.to_param(),
+                                None => continue,
+                            };
+                            let extent = Vector3::from(probe.extent);
+                            let volume = Aabb {
+                                min: Point3::from_vec(-extent).into(),
+                                max: Point3::from_vec(extent).into(),
+                            };
+                            probes.push(ProbeInstance {
+                                bounds: volume.transformed(w.world_matrix()),
+                                center: Point3::from_vec(w.world_transform.disp),
+                                blend_distance: probe.blend_distance,
+                                intensity: probe.intensity,
+                                map,
                             });
-                        data.list.push(Instance::basic(mx_world.into(), color, uv_range, param0));
-                        // Create a new instance and defer the draw call.
-                        continue;
+                        }
+                        probes.sort_by(|a, b| a.volume().partial_cmp(&b.volume()).unwrap_or(cmp::Ordering::Equal));
+                    }
+
+                    // clear instance cache
+                    for instances in self.instance_cache.values_mut() {
+                        instances.list.clear();
+                    }
+                    // instanced meshes are drawn in the order they are first met in the
+                    // scene, so that the frame doesn't depend on the hash map ordering
+                    let mut instance_order = Vec::new();
+
+                    // objects of the cells hidden from the camera by the portals
+                    let hidden = match scene.portals {
+                        Some(ref cells) => {
+                            let eye = Point3::from_vec(mx_camera_transform.disp);
+                            cells.hidden_nodes(&hub, eye, mx_proj * mx_view)
+                        }
+                        None => HashSet::new(),
+                    };
+
+                    // meshes whose bounds may be in view, found in the spatial index
+                    // in scene order, or all the meshes without culling
+                    let walked = {
+                        let mut index = scene.index.lock().unwrap();
+                        index.refresh(&hub, &scene.first_child);
+                        let entries = if self.frustum_culling {
+                            index.query_frustum(&Frustum::from_matrix(mx_proj * mx_view))
+                        } else {
+                            index.query_all()
+                        };
+                        entries
+                            .iter()
+                            .map(|entry| entry.walked(&hub))
+                            .collect::<Vec<_>>()
+                    };
+
+                    // meshes partly faded out by their distance or screen size
+                    let eye = Point3::from_vec(mx_camera_transform.disp);
+                    let mut fades = HashMap::new();
+
+                    // opaque visuals are drawn in scene order, transparent ones
+                    // from back to front once the opaque and instanced ones are done
+                    let mut opaque = Vec::new();
+                    let mut transparent = Vec::new();
+                    for w in walked {
+                        let (material, gpu_data, skeleton) = match w.node.sub_node {
+                            SubNode::Visual(ref material, ref gpu_data, ref skeleton) => {
+                                (material, gpu_data, skeleton)
+                            }
+                            _ => continue,
+                        };
+                        if hidden.contains(&w.node_ptr) {
+                            continue;
+                        }
+                        if gpu_data.fade != Fade::default() {
+                            let (center, radius) = match gpu_data.bounds {
+                                Some(bounds) if skeleton.is_none() => {
+                                    let bounds = bounds.transformed(w.world_matrix());
+                                    let (min, max) = (Point3::from(bounds.min), Point3::from(bounds.max));
+                                    (min.midpoint(max), 0.5 * min.distance(max))
+                                }
+                                _ => (Point3::from_vec(w.world_transform.disp), 0.0),
+                            };
+                            // share of the screen height covered by the bounding sphere
+                            let screen_size = if radius > 0.0 {
+                                let depth = (mx_proj * mx_view * center.to_homogeneous()).w;
+                                radius * mx_proj.y.y / depth.max(1e-5)
+                            } else {
+                                ::std::f32::INFINITY
+                            };
+                            let amount = gpu_data.fade.amount(eye.distance(center), screen_size);
+                            if amount <= 0.0 {
+                                continue;
+                            }
+                            if amount < 1.0 {
+                                fades.insert(w.node_ptr.clone(), amount);
+                            }
+                        }
+                        if w.world_opacity <= 0.0 {
+                            continue;
+                        }
+                        let mut pso_data = material.to_pso_data();
+                        if w.world_opacity < 1.0 {
+                            match pso_data {
+                                PsoData::Basic { ref mut opacity, .. } => *opacity *= w.world_opacity,
+                                PsoData::Pbr { ref mut params, .. } => params.base_color_factor[3] *= w.world_opacity,
+                            }
+                        }
+                        if is_transparent(&pso_data, &gpu_data.render_state) {
+                            let center = match gpu_data.bounds {
+                                Some(Aabb { min, max }) => Point3::new(
+                                    0.5 * (min.x + max.x),
+                                    0.5 * (min.y + max.y),
+                                    0.5 * (min.z + max.z),
+                                ),
+                                None => Point3::origin(),
+                            };
+                            let depth = (mx_view * w.world_matrix()).transform_point(center).z;
+                            transparent.push((depth, w, pso_data));
+                        } else {
+                            opaque.push((w, pso_data));
+                        }
+                    }
+                    // the camera looks along negative Z, so the farthest come first
+                    transparent.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(cmp::Ordering::Equal));
+                    rendered.extend(
+                        opaque
+                            .iter()
+                            .map(|&(ref w, _)| w.node_ptr.clone())
+                            .chain(transparent.iter().map(|&(_, ref w, _)| w.node_ptr.clone())),
+                    );
+
+                    // `None` marks the point where the instanced meshes are drawn
+                    let visuals = opaque
+                        .into_iter()
+                        .map(|(w, pso_data)| Some((w, pso_data, false)))
+                        .chain(iter::once(None))
+                        .chain(transparent.into_iter().map(|(_, w, pso_data)| Some((w, pso_data, true))));
+                    for visual in visuals {
+                        let (w, pso_data, blended) = match visual {
+                            Some(visual) => visual,
+                            None => {
+                                // render instanced meshes
+                                if lights_dirty {
+                                    self.encoder.update_buffer(&self.light_buf, &camera_lights, 0).unwrap();
+                                    lights_dirty = false;
+                                }
+                                for key in &instance_order {
+                                    let data = &self.instance_cache[key];
+                                    if data.list.len() > self.inst_buf.len() {
+                                        self.inst_buf = self.factory
+                                            .create_buffer(
+                                                data.list.len(),
+                                                gfx::buffer::Role::Vertex,
+                                                gfx::memory::Usage::Dynamic,
+                                                gfx::memory::Bind::TRANSFER_DST,
+                                            )
+                                            // TODO: Better error handling
+                                            .unwrap();
+                                    }
+                                    Self::render_mesh(
+                                        &mut self.encoder,
+                                        self.const_buf.clone(),
+                                        self.inst_buf.clone(),
+                                        self.light_buf.clone(),
+                                        self.pbr_buf.clone(),
+                                        self.displacement_contributions_buf.clone(),
+                                        self.out_color.clone(),
+                                        self.out_depth.clone(),
+                                        &mut self.pso,
+                                        &mut self.factory,
+                                        &self.map_default,
+                                        &self.normal_default,
+                                        environment.clone(),
+                                        self.probe_buf.clone(),
+                                        &NO_PROBES,
+                                        &no_probe_maps,
+                                        &data.list,
+                                        data.vertices.clone(),
+                                        data.slice.clone(),
+                                        &data.material,
+                                        &data.state,
+                                        &shadow_sampler,
+                                        &shadow0,
+                                        &shadow1,
+                                        &ZEROED_DISPLACEMENT_CONTRIBUTION,
+                                        (self.default_displacement_buffer_view.clone(), self.map_default.to_param().1),
+                                        self.default_joint_buffer_view.clone(),
+                                        false,
+                                        false,
+                                        !shadow_requests.is_empty(),
+                                        scissor,
+                                    );
+                                }
+                                continue;
+                            }
+                        };
+                        let (material, gpu_data, skeleton) = match w.node.sub_node {
+                            SubNode::Visual(ref material, ref gpu_data, ref skeleton) => {
+                                (material, gpu_data, skeleton)
+                            }
+                            _ => unreachable!(),
+                        };
+                        // transparent visuals are alpha-blended unless they ask
+                        // for another blending, and leave the depth buffer untouched
+                        let state = if blended {
+                            material::State {
+                                blend: match gpu_data.render_state.blend {
+                                    material::Blend::Replace => material::Blend::Alpha,
+                                    blend => blend,
+                                },
+                                depth_write: false,
+                                ..gpu_data.render_state
+                            }
+                        } else {
+                            gpu_data.render_state
+                        };
+
+                        let mx_world: mint::ColumnMatrix4<_> = w.world_matrix().into();
+
+                        let (mut instance, base_color) = match pso_data {
+                            PsoData::Basic { color, opacity, ref map, params, .. } => {
+                                let uv_range = match *map {
+                                    Some(ref map) => map.uv_range(),
+                                    None => [0.0; 4],
+                                };
+                                (Instance::basic(mx_world.into(), color, opacity, uv_range, params), color)
+                            }
+                            PsoData::Pbr { .. } => {
+                                (Instance::pbr(mx_world.into()), color::BLACK)
+                            }
+                        };
+                        if let Some(&amount) = fades.get(&w.node_ptr) {
+                            instance.fade = amount;
+                        }
+                        if let Some(ref callback) = w.node.on_before_render {
+                            let mut context = BeforeRender {
+                                time: self.timer.elapsed(),
+                                camera_position: Point3::from_vec(mx_camera_transform.disp).into(),
+                                view: mx_view.into(),
+                                projection: mx_proj.into(),
+                                world: mx_world,
+                                color: base_color,
+                                params: instance.mat_params,
+                            };
+                            (callback.0)(&mut context);
+                            if let PsoData::Basic { .. } = pso_data {
+                                let rgb = color::to_linear_rgb(context.color);
+                                instance.color = [rgb[0], rgb[1], rgb[2], instance.color[3]];
+                                instance.mat_params = context.params;
+                            }
+                        }
+                        if let PsoData::Basic { .. } = pso_data {
+                            let key = match gpu_data.instance_cache_key {
+                                Some(ref key) if gpu_data.light_mask == !0 && !blended => Some(key),
+                                _ => None,
+                            };
+                            if let Some(key) = key {
+                                let data = self.instance_cache
+                                    .entry(key.clone())
+                                    .or_insert_with(|| InstanceData {
+                                        slice: gpu_data.slice.clone(),
+                                        vertices: gpu_data.vertices.clone(),
+                                        material: material.clone(),
+                                        state: gpu_data.render_state,
+                                        list: Vec::new(),
+                                    });
+                                if data.list.is_empty() {
+                                    instance_order.push(key.clone());
+                                }
+                                data.list.push(instance);
+                                // Create a new instance and defer the draw call.
+                                continue;
+                            }
+                        }
+                        let joint_buffer_view = if let Some(ref ptr) = *skeleton {
+                            match hub[ptr].sub_node {
+                                SubNode::Skeleton(ref skeleton_data) => {
+                                    skeleton_data.gpu_buffer_view.clone()
+                                }
+                                _ => unreachable!()
+                            }
+                        } else {
+                            self.default_joint_buffer_view.clone()
+                        };
+                        let displacement_view = match gpu_data.displacements {
+                            Some((_, ref view)) => view.clone(),
+                            None => self.default_displacement_buffer_view.clone(),
+                        };
+                        // PBR meshes are reflected by the probes around their center
+                        let (probe_params, probe_maps) = match pso_data {
+                            PsoData::Pbr { .. } if !probes.is_empty() => {
+                                let center = match gpu_data.bounds {
+                                    Some(Aabb { min, max }) => w.world_matrix().transform_point(Point3::new(
+                                        0.5 * (min.x + max.x),
+                                        0.5 * (min.y + max.y),
+                                        0.5 * (min.z + max.z),
+                                    )),
+                                    None => Point3::from_vec(w.world_transform.disp),
+                                };
+                                select_probes(&probes, center, &probe_default)
+                            }
+                            _ => (NO_PROBES, no_probe_maps.clone()),
+                        };
+                        let mask = gpu_data.light_mask;
+                        if overflow || light_priorities.iter().any(|p| p.layers & mask == 0) {
+                            let position = Point3::from_vec(w.world_transform.disp);
+                            let selected = select_lights(&lights, &light_priorities, position, self.max_lights, mask);
+                            self.encoder.update_buffer(&self.light_buf, &selected, 0).unwrap();
+                            lights_dirty = true;
+                        } else if lights_dirty {
+                            self.encoder.update_buffer(&self.light_buf, &camera_lights, 0).unwrap();
+                            lights_dirty = false;
+                        }
+
+                        Self::render_mesh(
+                            &mut self.encoder,
+                            self.const_buf.clone(),
+                            gpu_data.instances.clone(),
+                            self.light_buf.clone(),
+                            self.pbr_buf.clone(),
+                            self.displacement_contributions_buf.clone(),
+                            self.out_color.clone(),
+                            self.out_depth.clone(),
+                            &mut self.pso,
+                            &mut self.factory,
+                            &self.map_default,
+                            &self.normal_default,
+                            environment.clone(),
+                            self.probe_buf.clone(),
+                            &probe_params,
+                            &probe_maps,
+                            &[instance],
+                            gpu_data.vertices.clone(),
+                            gpu_data.slice.clone(),
+                            &material,
+                            &state,
+                            &shadow_sampler,
+                            &shadow0,
+                            &shadow1,
+                            &gpu_data.displacement_contributions,
+                            (displacement_view, self.map_default.to_param().1),
+                            joint_buffer_view,
+                            skeleton.is_some(),
+                            gpu_data.displacements.is_some(),
+                            !shadow_requests.is_empty(),
+                            scissor,
+                        );
+                    }
+
+                    if !frame.capture {
+                        for ptr in &rendered {
+                            hub.nodes[ptr].rendered = true;
+                        }
                     }
-                    Instance::basic(mx_world.into(), color, uv_range, param0)
                 }
-                PsoData::Pbr { .. } => {
-                    Instance::pbr(mx_world.into())
+                Pass::Feedback => {
+                    let size = (
+                        cmp::max(self.size.0 / FEEDBACK_SCALE, 1) as gfx::texture::Size,
+                        cmp::max(self.size.1 / FEEDBACK_SCALE, 1) as gfx::texture::Size,
+                    );
+                    if self.feedback.as_ref().map_or(true, |target| target.size() != size) {
+                        match FeedbackTarget::new(&mut self.factory, size.0, size.1) {
+                            Ok(target) => self.feedback = Some(target),
+                            Err(err) => {
+                                error!("Failed to create the virtual texture feedback target: {:?}", err);
+                                self.feedback = None;
+                                continue;
+                            }
+                        }
+                    }
+                    let target = self.feedback.as_mut().unwrap();
+                    let clip_plane = match scene.section {
+                        Some(ref section) => {
+                            let normal = section.plane.normal;
+                            [normal.x, normal.y, normal.z, -section.plane.distance]
+                        }
+                        None => NO_CLIP_PLANE,
+                    };
+                    self.encoder.update_constant_buffer(
+                        &target.globals,
+                        &Globals {
+                            mx_vp: (mx_clip * mx_view).into(),
+                            mx_view: mx_view.into(),
+                            mx_inv_proj: mx_clip.invert().unwrap().into(),
+                            env_params: [0.0; 4],
+                            ambient: [0.0; 4],
+                            clip_plane,
+                            cap_color: [0.0; 4],
+                            shadow_params: [0.0; 4],
+                            fog_color: [0.0; 4],
+                            fog_params: [0.0; 4],
+                            resolution: [size.0 as f32, size.1 as f32, 0.0, 0.0],
+                            num_lights: 0,
+                        },
+                    );
+                    self.encoder.clear(&target.color, [0.0; 4]);
+                    self.encoder.clear_depth(&target.depth, 1.0);
+                    let (shadow_default, shadow_sampler) = self.shadow_default.to_param();
+                    let scissor = gfx::Rect {
+                        x: 0,
+                        y: 0,
+                        w: size.0,
+                        h: size.1,
+                    };
+                    for w in hub.walk(&scene.first_child) {
+                        let (map, gpu_data) = match w.node.sub_node {
+                            SubNode::Visual(Material::Virtual(ref params), ref gpu_data, _) if w.world_opacity > 0.0 => {
+                                (&params.map, gpu_data)
+                            }
+                            _ => continue,
+                        };
+                        let state = material::State {
+                            blend: material::Blend::Replace,
+                            depth_test: true,
+                            depth_write: true,
+                            ..gpu_data.render_state
+                        };
+                        let mx_world: mint::ColumnMatrix4<_> = w.world_matrix().into();
+                        // the opacity carries the id of the texture
+                        let instance = Instance::basic(mx_world.into(), color::WHITE, map.id as f32, [0.0; 4], map.params());
+                        self.encoder.update_buffer(&gpu_data.instances, &[instance], 0).unwrap();
+                        let pso = match self.pso.kind_variant(&mut self.factory, pso_cache::Kind::Feedback, &state) {
+                            Some(pso) => pso,
+                            None => break,
+                        };
+                        let data = basic_pipe::Data {
+                            vbuf: gpu_data.vertices.clone(),
+                            inst_buf: gpu_data.instances.clone(),
+                            cb_lights: self.light_buf.clone(),
+                            cb_globals: target.globals.clone(),
+                            tex_map: map.cache.to_param(),
+                            tex_normal_map: map.indirection.to_param(),
+                            shadow_map0: (shadow_default.clone(), shadow_sampler.clone()),
+                            shadow_map1: (shadow_default.clone(), shadow_sampler.clone()),
+                            displacement_contributions: self.displacement_contributions_buf.clone(),
+                            displacements: (self.default_displacement_buffer_view.clone(), self.map_default.to_param().1),
+                            out_color: target.color.clone(),
+                            out_depth: (target.depth.clone(), (0, 0)),
+                            scissor,
+                        };
+                        self.encoder.draw(&gpu_data.slice, pso, &data);
+                    }
+                    {
+                        use gfx::format::Formatted;
+
+                        let info = target.texture.get_info().to_raw_image_info(ColorFormat::get_format().1, 0);
+                        self.encoder
+                            .copy_texture_to_buffer_raw(target.texture.raw(), None, info, target.download.raw(), 0)
+                            .expect("copy the feedback target to the download buffer");
+                    }
+                    target.pending = true;
                 }
-            };
-            let joint_buffer_view = if let Some(ref ptr) = *skeleton {
-                match hub[ptr].sub_node {
-                    SubNode::Skeleton(ref skeleton_data) => {
-                        skeleton_data.gpu_buffer_view.clone()
+                Pass::Debug => {
+                    let view = self.debug_view;
+                    if view == DebugView::Overdraw {
+                        self.encoder.clear(&self.out_color, [0.0, 0.0, 0.0, 0.0]);
+                    }
+                    let (shadow_default, shadow_sampler) = self.shadow_default.to_param();
+                    for w in hub.walk(&scene.first_child) {
+                        let (material, gpu_data) = match w.node.sub_node {
+                            SubNode::Visual(ref material, ref gpu_data, _) if rendered.contains(&w.node_ptr) => {
+                                (material, gpu_data)
+                            }
+                            _ => continue,
+                        };
+                        let map = match *material {
+                            Material::Line(_) | Material::DashedLine(_) | Material::Points(_) | Material::Sprite(_) => continue,
+                            Material::Basic(ref params) => params.map.clone(),
+                            Material::CustomBasic(ref params) => params.map.clone(),
+                            Material::Phong(ref params) => params.map.clone(),
+                            Material::Pbr(ref params) => params.base_color_map.clone(),
+                            Material::Lambert(_) | Material::Virtual(_) | Material::Wireframe(_) => None,
+                        };
+                        let uv_range = match map {
+                            Some(ref map) => map.uv_range(),
+                            None => [0.0; 4],
+                        };
+                        // overdraw counts every fragment, the other views are drawn
+                        // over the surfaces left in the depth buffer by the scene pass
+                        let (kind, state, mode) = match view.shader_mode() {
+                            Some(mode) if view == DebugView::Overdraw => {
+                                let state = material::State {
+                                    blend: material::Blend::Add,
+                                    cull: gpu_data.render_state.cull,
+                                    depth_test: false,
+                                    depth_write: false,
+                                    fog: true,
+                                };
+                                (pso_cache::Kind::Diagnostic, state, mode)
+                            }
+                            Some(mode) => {
+                                let state = material::State {
+                                    blend: material::Blend::Replace,
+                                    depth_test: true,
+                                    depth_write: false,
+                                    ..gpu_data.render_state
+                                };
+                                (pso_cache::Kind::Diagnostic, state, mode)
+                            }
+                            None => {
+                                let state = material::State {
+                                    blend: material::Blend::Replace,
+                                    depth_test: true,
+                                    depth_write: false,
+                                    ..gpu_data.render_state
+                                };
+                                (pso_cache::Kind::Wireframe, state, 0.0)
+                            }
+                        };
+                        let mx_world: mint::ColumnMatrix4<_> = w.world_matrix().into();
+                        let instance = Instance::basic(
+                            mx_world.into(),
+                            color::BLACK,
+                            1.0,
+                            uv_range,
+                            [mode, 0.0, 0.0, 0.0],
+                        );
+                        self.encoder.update_buffer(&gpu_data.instances, &[instance], 0).unwrap();
+                        let pso = match self.pso.kind_variant(&mut self.factory, kind, &state) {
+                            Some(pso) => pso,
+                            None => break,
+                        };
+                        let data = basic_pipe::Data {
+                            vbuf: gpu_data.vertices.clone(),
+                            inst_buf: gpu_data.instances.clone(),
+                            cb_lights: self.light_buf.clone(),
+                            cb_globals: self.const_buf.clone(),
+                            tex_map: map.unwrap_or(self.map_default.clone()).to_param(),
+                            tex_normal_map: self.normal_default.to_param(),
+                            shadow_map0: (shadow_default.clone(), shadow_sampler.clone()),
+                            shadow_map1: (shadow_default.clone(), shadow_sampler.clone()),
+                            displacement_contributions: self.displacement_contributions_buf.clone(),
+                            displacements: (self.default_displacement_buffer_view.clone(), self.map_default.to_param().1),
+                            out_color: self.out_color.clone(),
+                            out_depth: (self.out_depth.clone(), (0, 0)),
+                            scissor,
+                        };
+                        self.encoder.draw(&gpu_data.slice, pso, &data);
+                    }
+                }
+                Pass::Background if self.transparent_background => {}
+                Pass::Background => {
+                    // draw background (if any)
+                    match scene.background {
+                        Background::Texture(ref texture) => {
+                            // TODO: Reduce code duplication (see drawing debug quads)
+                            self.encoder.update_constant_buffer(
+                                &self.quad_buf,
+                                &QuadParams {
+                                    rect: ndc_rect,
+                                    depth: 1.0,
+                                },
+                            );
+                            let data = quad_pipe::Data {
+                                params: self.quad_buf.clone(),
+                                globals: self.const_buf.clone(),
+                                resource: texture.to_param().0.raw().clone(),
+                                sampler: texture.to_param().1,
+                                target: self.out_color.clone(),
+                                depth_target: self.out_depth.clone(),
+                                scissor,
+                            };
+                            self.encoder.draw(&quad_slice, &self.pso.quad, &data);
+                        }
+                        Background::Skybox(ref cubemap) => {
+                            self.encoder.update_constant_buffer(
+                                &self.quad_buf,
+                                &QuadParams {
+                                    rect: ndc_rect,
+                                    depth: 1.0,
+                                },
+                            );
+                            let data = quad_pipe::Data {
+                                params: self.quad_buf.clone(),
+                                resource: cubemap.to_param().0.raw().clone(),
+                                sampler: cubemap.to_param().1,
+                                globals: self.const_buf.clone(),
+                                target: self.out_color.clone(),
+                                depth_target: self.out_depth.clone(),
+                                scissor,
+                            };
+                            self.encoder.draw(&quad_slice, &self.pso.skybox, &data);
+                        }
+                        Background::Gradient { top, bottom } => {
+                            let texture = self.gradient_texture(top, bottom);
+                            // the texel centers are a quarter of the quad height from
+                            // its edges, so stretch it for the filtered colors to reach
+                            // the screen edges exactly
+                            self.encoder.update_constant_buffer(
+                                &self.quad_buf,
+                                &QuadParams {
+                                    rect: fit_rect([-1.0, -2.0, 1.0, 2.0], ndc_rect),
+                                    depth: 1.0,
+                                },
+                            );
+                            let data = quad_pipe::Data {
+                                params: self.quad_buf.clone(),
+                                globals: self.const_buf.clone(),
+                                resource: texture.to_param().0.raw().clone(),
+                                sampler: texture.to_param().1,
+                                target: self.out_color.clone(),
+                                depth_target: self.out_depth.clone(),
+                                scissor,
+                            };
+                            self.encoder.draw(&quad_slice, &self.pso.quad, &data);
+                        }
+                        Background::Color(color) if frame.viewport.is_some() => {
+                            // a gradient between the same colors is uniform
+                            let texture = self.gradient_texture(color, color);
+                            self.encoder.update_constant_buffer(
+                                &self.quad_buf,
+                                &QuadParams {
+                                    rect: ndc_rect,
+                                    depth: 1.0,
+                                },
+                            );
+                            let data = quad_pipe::Data {
+                                params: self.quad_buf.clone(),
+                                globals: self.const_buf.clone(),
+                                resource: texture.to_param().0.raw().clone(),
+                                sampler: texture.to_param().1,
+                                target: self.out_color.clone(),
+                                depth_target: self.out_depth.clone(),
+                                scissor,
+                            };
+                            self.encoder.draw(&quad_slice, &self.pso.quad, &data);
+                        }
+                        Background::Color(_) => {}
+                    }
+                }
+                Pass::WorldText => {
+                    let (width, height, _, _) = self.out_color.get_dimensions();
+                    let (width, height) = (width as f32, height as f32);
+                    for w in hub.walk(&scene.first_child) {
+                        let (text, scale) = match w.node.sub_node {
+                            SubNode::UiText(ref text) => match text.world_scale {
+                                Some(scale) => (text, scale),
+                                None => continue,
+                            },
+                            _ => continue,
+                        };
+                        // from the clip space the glyphs are laid out in, for the
+                        // size of the target, to pixels in the XY plane of the node
+                        let mx_layout = Matrix4::new(
+                            0.5 * scale * width, 0.0, 0.0, 0.0,
+                            0.0, 0.5 * scale * height, 0.0, 0.0,
+                            0.0, 0.0, 0.0, 0.0,
+                            0.5 * scale * width, -0.5 * scale * height, 0.0, 1.0,
+                        );
+                        let transform = mx_clip * mx_view * w.world_matrix() * mx_layout;
+                        text.draw_in_world(transform.into(), &mut self.encoder, &self.out_color, &self.out_depth);
+                    }
+                }
+                Pass::Gizmo => {
+                    let gizmo = self.gizmo.as_mut().unwrap();
+                    let mx_vp = gizmo.view_projection(self.size, mx_camera_transform.rot);
+                    gizmo.mx_vp = Some(mx_vp);
+                    self.encoder.update_constant_buffer(
+                        &self.const_buf,
+                        &Globals {
+                            mx_vp: mx_vp.into(),
+                            mx_view: Matrix4::from(mx_camera_transform.rot).transpose().into(),
+                            mx_inv_proj: Matrix4::identity().into(),
+                            env_params: [0.0; 4],
+                            ambient: [0.0; 4],
+                            clip_plane: NO_CLIP_PLANE,
+                            cap_color: [0.0; 4],
+                            shadow_params: [0.0; 4],
+                            fog_color: [0.0; 4],
+                            fog_params: [0.0; 4],
+                            resolution: [self.size.0 as f32, self.size.1 as f32, 0.0, 0.0],
+                            num_lights: 0,
+                        },
+                    );
+                    let (shadow_default, shadow_sampler) = self.shadow_default.to_param();
+                    let mx_world: mint::ColumnMatrix4<_> = Matrix4::identity().into();
+                    let state = material::State {
+                        depth_test: false,
+                        depth_write: false,
+                        ..material::State::default()
+                    };
+                    for (index, axis) in GIZMO_AXES.iter().enumerate() {
+                        let material = Material::from(material::Line {
+                            color: axis.color(),
+                            ..Default::default()
+                        });
+                        let instance = Instance::basic(mx_world.into(), axis.color(), 1.0, [0.0; 4], [0.0; 4]);
+                        let slice = gfx::Slice {
+                            start: 2 * index as u32,
+                            end: 2 * index as u32 + 2,
+                            base_vertex: 0,
+                            instances: None,
+                            buffer: gfx::IndexBuffer::Auto,
+                        };
+                        Self::render_mesh(
+                            &mut self.encoder,
+                            self.const_buf.clone(),
+                            self.inst_buf.clone(),
+                            self.light_buf.clone(),
+                            self.pbr_buf.clone(),
+                            self.displacement_contributions_buf.clone(),
+                            self.out_color.clone(),
+                            self.out_depth.clone(),
+                            &mut self.pso,
+                            &mut self.factory,
+                            &self.map_default,
+                            &self.normal_default,
+                            self.environment_default.to_param(),
+                            self.probe_buf.clone(),
+                            &NO_PROBES,
+                            &[self.environment_default.to_param(), self.environment_default.to_param()],
+                            &[instance],
+                            gizmo.vertices.clone(),
+                            slice,
+                            &material,
+                            &state,
+                            &shadow_sampler,
+                            &shadow_default,
+                            &shadow_default,
+                            &ZEROED_DISPLACEMENT_CONTRIBUTION,
+                            (self.default_displacement_buffer_view.clone(), self.map_default.to_param().1),
+                            self.default_joint_buffer_view.clone(),
+                            false,
+                            false,
+                            false,
+                            scissor,
+                        );
+                    }
+                }
+                Pass::Resolve => {
+                    let (samples, ref input, ref output) = *plan.resolve.as_ref().unwrap();
+                    let target = output.clone().unwrap_or_else(|| frame.output.0.clone());
+                    self.resolve(samples, input.clone(), target);
+                    if output.is_none() {
+                        self.use_output(frame);
+                    }
+                }
+                Pass::Post(index) => {
+                    let (ref input, ref output) = plan.post[index];
+                    {
+                        let size = frame.output.0.get_dimensions();
+                        let mut context = PostContext {
+                            encoder: &mut self.encoder,
+                            factory: &mut self.factory,
+                            input: input.clone(),
+                            output: output.clone().unwrap_or_else(|| frame.output.0.clone()),
+                            size: (size.0 as u32, size.1 as u32),
+                            depth: plan.depth.clone(),
+                            projection: mx_proj.into(),
+                            pso: &self.pso,
+                            params_buf: self.post_buf.clone(),
+                            ssao_buf: self.ssao_buf.clone(),
+                            sampler: self.post_sampler.clone(),
+                        };
+                        match post_steps[index] {
+                            PostStep::ToneMapping => self.tone_mapping.render(&mut context),
+                            PostStep::Effect(effect) => self.post_effects[effect].render(&mut context),
+                        }
+                    }
+                    if output.is_none() {
+                        self.use_output(frame);
                     }
-                    _ => unreachable!()
                 }
+                Pass::Text => {
+                    // draw ui text
+                    let mut fonts = self.font_cache.iter().collect::<Vec<_>>();
+                    fonts.sort_by(|a, b| a.0.cmp(b.0));
+                    for (_, font) in fonts {
+                        font.draw(&mut self.encoder, &self.out_color, &self.out_depth);
+                    }
+                }
+                Pass::DebugQuads => {
+                    // draw debug quads
+                    self.debug_quads.sync_pending();
+                    let mut quads = self.debug_quads
+                        .iter()
+                        .map(|quad| (quad.resource.clone(), quad.pos, quad.size))
+                        .collect::<Vec<_>>();
+                    if self.debug_shadows {
+                        let size = DEBUG_SHADOW_SIZE;
+                        quads.extend(shadow_requests.iter().enumerate().map(|(i, request)| {
+                            let pos = [10 + i as i32 * (size + 10), -10];
+                            (request.resource.raw().clone(), pos, [size, size])
+                        }));
+                    }
+                    for (resource, quad_pos, quad_size) in quads {
+                        let pos = [
+                            if quad_pos[0] >= 0 {
+                                quad_pos[0]
+                            } else {
+                                self.size.0 as i32 + quad_pos[0] - quad_size[0]
+                            },
+                            if quad_pos[1] >= 0 {
+                                quad_pos[1]
+                            } else {
+                                self.size.1 as i32 + quad_pos[1] - quad_size[1]
+                            },
+                        ];
+                        let p0 = self.map_to_ndc([pos[0] as f32, pos[1] as f32]);
+                        let p1 = self.map_to_ndc([
+                            (pos[0] + quad_size[0]) as f32,
+                            (pos[1] + quad_size[1]) as f32,
+                        ]);
+                        self.encoder.update_constant_buffer(
+                            &self.quad_buf,
+                            &QuadParams {
+                                rect: [p0.x, p0.y, p1.x, p1.y],
+                                depth: -1.0,
+                            },
+                        );
+                        let data = quad_pipe::Data {
+                            params: self.quad_buf.clone(),
+                            globals: self.const_buf.clone(),
+                            resource,
+                            sampler: self.map_default.to_param().1,
+                            target: self.out_color.clone(),
+                            depth_target: self.out_depth.clone(),
+                            scissor,
+                        };
+                        self.encoder.draw(&quad_slice, &self.pso.quad, &data);
+                    }
+                }
+            }
+            if !frame.capture {
+                self.end_phase(Phase::Pass(pass));
+            }
+        }
+        self.out_color = frame.output.0.clone();
+        self.out_depth = frame.output.1.clone();
+    }
+
+    /// Switches to the output targets once the scene is done, for the UI to
+    /// be drawn over the processed frame.
+    fn use_output(
+        &mut self,
+        frame: &FrameTargets,
+    ) {
+        self.out_color = frame.output.0.clone();
+        self.out_depth = frame.output.1.clone();
+        self.encoder.clear_depth(&self.out_depth, 1.0);
+        self.encoder.clear_stencil(&self.out_depth, 0);
+    }
+
+    /// Declares the passes of `frame` in a frame graph and allocates the
+    /// transient targets they use, recycling the ones of the previous frames.
+    ///
+    /// If the targets can't be created, the frame is planned again without
+    /// multisampling and post effects.
+    fn plan_frame(
+        &mut self,
+        frame: &FrameTargets,
+        shadow_count: usize,
+        overlays: bool,
+        effects: bool,
+        feedback: bool,
+    ) -> Option<FramePlan> {
+        let post_count = if effects { self.post_count() } else { 0 };
+        let samples = if effects && self.multisampling > 1 {
+            self.multisampling as u8
+        } else {
+            0
+        };
+        let (width, height, _, _) = frame.output.0.get_dimensions();
+        let desc = |format, samples| TargetDesc {
+            size: (width, height),
+            samples,
+            format,
+        };
+
+        let mut graph = FrameGraph::new();
+        // post effects need a scene color that can be sampled, in HDR if
+        // enabled, and multisampled targets are resolved before them
+        let (color, depth) = if post_count > 0 || samples > 0 {
+            let format = if post_count > 0 {
+                TargetFormat::HdrColor
             } else {
-                self.default_joint_buffer_view.clone()
+                TargetFormat::Color
             };
-            let displacement_view = match gpu_data.displacements {
-                Some((_, ref view)) => view.clone(),
-                None => self.default_displacement_buffer_view.clone(),
+            (
+                graph.add_transient(desc(format, samples)),
+                graph.add_transient(desc(TargetFormat::Depth, samples)),
+            )
+        } else {
+            (Resource::Color, Resource::Depth)
+        };
+        for index in 0 .. shadow_count {
+            graph.add_pass(Pass::Shadow(index), &[], &[Resource::ShadowMap(index)]);
+        }
+        let shadow_maps = (0 .. shadow_count)
+            .map(Resource::ShadowMap)
+            .collect::<Vec<_>>();
+        graph.add_pass(Pass::Scene, &shadow_maps, &[color, depth]);
+        if feedback {
+            graph.add_pass(Pass::Feedback, &[], &[Resource::Feedback]);
+        }
+        graph.add_pass(Pass::Background, &[depth], &[color]);
+        // the glyph pipeline has no scissor to restrict it to a viewport
+        if !frame.capture && frame.viewport.is_none() {
+            graph.add_pass(Pass::WorldText, &[depth], &[color]);
+        }
+        if !frame.capture {
+            if self.debug_view != DebugView::Off {
+                graph.add_pass(Pass::Debug, &[depth], &[color]);
+            }
+        }
+        let mut resolve = None;
+        let mut input = color;
+        if samples > 0 {
+            let output = if post_count > 0 {
+                graph.add_transient(desc(TargetFormat::HdrColor, 0))
+            } else {
+                Resource::Color
             };
+            graph.add_pass(Pass::Resolve, &[color], &[output]);
+            resolve = Some((color, output));
+            input = output;
+        }
+        // the effects alternate between transient targets, aliased by the
+        // graph, and the last one writes to the output
+        let mut post = Vec::with_capacity(post_count);
+        for index in 0 .. post_count {
+            let output = if index + 1 == post_count {
+                Resource::Color
+            } else {
+                graph.add_transient(desc(TargetFormat::HdrColor, 0))
+            };
+            if samples > 0 {
+                graph.add_pass(Pass::Post(index), &[input], &[output]);
+            } else {
+                graph.add_pass(Pass::Post(index), &[input, depth], &[output]);
+            }
+            post.push((input, output));
+            input = output;
+        }
+        if overlays {
+            // drawn over the effects, like the other overlays
+            if self.gizmo.is_some() {
+                graph.add_pass(Pass::Gizmo, &[], &[Resource::Color, Resource::Depth]);
+            }
+            graph.add_pass(Pass::Text, &[], &[Resource::Color, Resource::Depth]);
+            graph.add_pass(Pass::DebugQuads, &[], &[Resource::Color, Resource::Depth]);
+        }
 
-            Self::render_mesh(
-                &mut self.encoder,
-                self.const_buf.clone(),
-                gpu_data.instances.clone(),
-                self.light_buf.clone(),
-                self.pbr_buf.clone(),
-                self.displacement_contributions_buf.clone(),
-                self.out_color.clone(),
-                self.out_depth.clone(),
-                &self.pso,
-                &self.map_default,
-                &[instance],
-                gpu_data.vertices.clone(),
-                gpu_data.slice.clone(),
-                &material,
-                &shadow_sampler,
-                &shadow0,
-                &shadow1,
-                &gpu_data.displacement_contributions,
-                (displacement_view, self.map_default.to_param().1),
-                joint_buffer_view,
-                gpu_data.displacements.is_some(),
-            );
+        let (passes, slots, descs) = match self.frame_graph.schedule(graph) {
+            Ok(schedule) => (
+                schedule.passes.clone(),
+                schedule.slots.clone(),
+                schedule.targets.clone(),
+            ),
+            Err(err) => {
+                error!("Failed to build the frame graph: {}", err);
+                return None;
+            }
+        };
+        let targets = match self.targets.acquire(&mut self.factory, &descs) {
+            Ok(targets) => targets,
+            Err(err) => {
+                error!("Failed to create the frame targets: {:?}", err);
+                if !effects {
+                    return None;
+                }
+                if samples > 0 {
+                    error!("Disabling multisampling");
+                    self.multisampling = 0;
+                }
+                return self.plan_frame(frame, shadow_count, overlays, false, feedback);
+            }
+        };
+        let target = |resource| match resource {
+            Resource::Transient(index) => Some(&targets[slots[index]]),
+            _ => None,
+        };
+
+        let scene_depth = target(depth).map(Target::depth);
+        Some(FramePlan {
+            passes,
+            scene: target(color).map(|t| t.color().0).map(|color| {
+                (color, scene_depth.clone().unwrap().0)
+            }),
+            depth: scene_depth.and_then(|(_, resource)| resource),
+            resolve: resolve.map(|(input, output)| {
+                (
+                    samples,
+                    target(input).unwrap().color().1,
+                    target(output).map(|t| t.color().0),
+                )
+            }),
+            post: post.into_iter()
+                .map(|(input, output)| {
+                    (target(input).unwrap().color().1, target(output).map(|t| t.color().0))
+                })
+                .collect(),
+        })
+    }
+
+    /// Returns the two-texel texture of a vertical gradient, reusing the
+    /// previous one if the colors didn't change.
+    fn gradient_texture(
+        &mut self,
+        top: color::Color,
+        bottom: color::Color,
+    ) -> Texture<[f32; 4]> {
+        use gfx::texture as t;
+
+        if let Some((cached_top, cached_bottom, ref texture)) = self.gradient {
+            if cached_top == top && cached_bottom == bottom {
+                return texture.clone();
+            }
         }
+        let texel = |color: color::Color| {
+            [(color >> 16) as u8, (color >> 8) as u8, color as u8, 0xFF]
+        };
+        // the first row is at the bottom of the quad
+        let (lo, hi) = (texel(bottom), texel(top));
+        let data = [lo[0], lo[1], lo[2], lo[3], hi[0], hi[1], hi[2], hi[3]];
+        let (_, view) = self.factory
+            .create_texture_immutable_u8::<gfx::format::Srgba8>(
+                t::Kind::D2(1, 2, t::AaMode::Single),
+                t::Mipmap::Provided,
+                &[&data],
+            )
+            .unwrap();
+        let sampler = self.factory.create_sampler(t::SamplerInfo::new(
+            t::FilterMethod::Bilinear,
+            t::WrapMode::Clamp,
+        ));
+        let texture = Texture::new(view, sampler, [1, 2], ColorSpace::Srgb);
+        self.gradient = Some((top, bottom, texture.clone()));
+        texture
+    }
 
-        // render instanced meshes
-        for data in self.instance_cache.values() {
-            if data.list.len() > self.inst_buf.len() {
-                self.inst_buf = self.factory
-                    .create_buffer(
-                        data.list.len(),
-                        gfx::buffer::Role::Vertex,
-                        gfx::memory::Usage::Dynamic,
-                        gfx::memory::Bind::TRANSFER_DST,
-                    )
-                    // TODO: Better error handling
-                    .unwrap();
+    /// Renders the scene into `target` instead of the window, e.g. the view
+    /// of a mirror or a mini-map, before rendering the main frame.
+    ///
+    /// Post effects and MSAA apply as they do for the window. A mesh
+    /// using the texture of `target` must not be visible from `camera`, since
+    /// a texture can't be sampled while it's being rendered into.
+    ///
+    /// ```rust,no_run
+    /// # let mut window = three::Window::new("");
+    /// # let camera = window.factory.perspective_camera(60.0, 0.1 .. 10.0);
+    /// # let map_camera = window.factory.orthographic_camera([0.0, 0.0], 10.0, -10.0 .. 10.0);
+    /// let target = window.factory.render_target(256, 256);
+    /// let material = three::material::Basic {
+    ///     map: Some(target.texture()),
+    ///     .. Default::default()
+    /// };
+    /// # let _ = material;
+    /// while window.update() {
+    ///     window.renderer.render_to_target(&window.scene, &map_camera, &target);
+    ///     window.render(&camera);
+    /// }
+    /// ```
+    pub fn render_to_target(
+        &mut self,
+        scene: &Scene,
+        camera: &Camera,
+        target: &RenderTarget,
+    ) {
+        self.render_to_views(scene, camera, &target.color, &target.depth);
+    }
+
+    /// Renders the scene into `color` and `depth` instead of the window, e.g.
+    /// into a texture read by code outside of `three`.
+    ///
+    /// The targets can be created with the gfx factory returned by
+    /// [`Factory::backend`]. The rendering commands are submitted to OpenGL
+    /// before returning, so later commands on the same context, foreign or not,
+    /// see the finished frame. Readers on another context sharing objects with
+    /// this one must wait for the commands to complete, e.g. with `glFinish`
+    /// or a fence.
+    ///
+    /// [`Factory::backend`]: struct.Factory.html#method.backend
+    pub fn render_to_views(
+        &mut self,
+        scene: &Scene,
+        camera: &Camera,
+        color: &h::RenderTargetView<back::Resources, ColorFormat>,
+        depth: &h::DepthStencilView<back::Resources, DepthFormat>,
+    ) {
+        let (width, height, _, _) = color.get_dimensions();
+        let out_color = mem::replace(&mut self.out_color, color.clone());
+        let out_depth = mem::replace(&mut self.out_depth, depth.clone());
+        let size = mem::replace(&mut self.size, (width as u32, height as u32));
+        let viewport = self.viewport.take();
+        self.render(scene, camera);
+        self.out_color = out_color;
+        self.out_depth = out_depth;
+        self.size = size;
+        self.viewport = viewport;
+    }
+
+    /// Renders the scene into an offscreen target of `width` x `height` pixels
+    /// and reads the result back, top row first.
+    pub(crate) fn render_to_image(
+        &mut self,
+        scene: &Scene,
+        camera: &Camera,
+        width: u32,
+        height: u32,
+    ) -> image::RgbaImage {
+        use gfx::format::Formatted;
+        use gfx::texture as t;
+
+        let texture = self.factory
+            .create_texture::<<ColorFormat as Formatted>::Surface>(
+                t::Kind::D2(width as t::Size, height as t::Size, t::AaMode::Single),
+                1,
+                gfx::memory::Bind::RENDER_TARGET | gfx::memory::Bind::TRANSFER_SRC,
+                gfx::memory::Usage::Data,
+                Some(<<ColorFormat as Formatted>::Channel as gfx::format::ChannelTyped>::get_channel_type()),
+            )
+            .expect("Can't create offscreen color target");
+        let color = self.factory
+            .view_texture_as_render_target::<ColorFormat>(&texture, 0, None)
+            .unwrap();
+        let depth = self.factory
+            .create_depth_stencil_view_only::<DepthFormat>(width as t::Size, height as t::Size)
+            .unwrap();
+
+        self.render_to_views(scene, camera, &color, &depth);
+
+        let pixels = self.download(&texture);
+        image::RgbaImage::from_raw(width, height, pixels).unwrap()
+    }
+
+    /// Renders the scene as seen by `camera` into an offscreen image, e.g. for
+    /// high quality stills of a viewer application.
+    ///
+    /// Transparent captures have straight, non-premultiplied alpha. Post
+    /// effects may overwrite the alpha channel, so they are best disabled for
+    /// transparent captures. The supersampled size must not exceed the
+    /// maximum texture size of the driver.
+    ///
+    /// ```rust,no_run
+    /// # let mut window = three::Window::new("");
+    /// # let camera = window.factory.perspective_camera(60.0, 0.1 .. 10.0);
+    /// let capture = three::render::Capture {
+    ///     supersampling: 4,
+    ///     transparent: true,
+    ///     .. three::render::Capture::new(1920, 1080)
+    /// };
+    /// let image = window.renderer.capture(&window.scene, &camera, &capture);
+    /// image.save("still.png").unwrap();
+    /// ```
+    pub fn capture(
+        &mut self,
+        scene: &Scene,
+        camera: &Camera,
+        capture: &Capture,
+    ) -> image::RgbaImage {
+        let factor = cmp::max(capture.supersampling, 1);
+        let (width, height) = (capture.width, capture.height);
+        let transparent = mem::replace(&mut self.transparent_background, capture.transparent);
+        let image = self.render_to_image(scene, camera, width * factor, height * factor);
+        self.transparent_background = transparent;
+
+        // blended colors over the cleared background are premultiplied, so
+        // the samples are averaged before dividing by the alpha
+        let samples = (factor * factor) as f32;
+        let mut pixels = Vec::with_capacity((width * height * 4) as usize);
+        for y in 0 .. height {
+            for x in 0 .. width {
+                let mut sum = [0.0f32; 4];
+                for sy in 0 .. factor {
+                    for sx in 0 .. factor {
+                        let texel = image.get_pixel(x * factor + sx, y * factor + sy);
+                        for c in 0 .. 4 {
+                            sum[c] += texel.data[c] as f32;
+                        }
+                    }
+                }
+                let alpha = if capture.transparent { sum[3] / samples } else { 255.0 };
+                let scale = if capture.transparent && alpha > 0.0 { 255.0 / alpha } else { 1.0 };
+                let channel = |value: f32| (value / samples * scale).round().min(255.0) as u8;
+                pixels.extend_from_slice(&[channel(sum[0]), channel(sum[1]), channel(sum[2]), alpha.round() as u8]);
             }
-            Self::render_mesh(
-                &mut self.encoder,
-                self.const_buf.clone(),
-                self.inst_buf.clone(),
-                self.light_buf.clone(),
-                self.pbr_buf.clone(),
-                self.displacement_contributions_buf.clone(),
-                self.out_color.clone(),
-                self.out_depth.clone(),
-                &self.pso,
-                &self.map_default,
-                &data.list,
-                data.vertices.clone(),
-                data.slice.clone(),
-                &data.material,
-                &shadow_sampler,
-                &shadow0,
-                &shadow1,
-                &ZEROED_DISPLACEMENT_CONTRIBUTION,
-                (self.default_displacement_buffer_view.clone(), self.map_default.to_param().1),
-                self.default_joint_buffer_view.clone(),
-                false,
-            );
         }
+        image::RgbaImage::from_raw(width, height, pixels).unwrap()
+    }
 
-        let quad_slice = gfx::Slice {
+    /// Draws the generator pass of `job` and copies its output to the vertex
+    /// buffer of the target.
+    fn run_vertex_job(
+        &mut self,
+        job: VertexJob,
+    ) {
+        use gfx::format::Formatted;
+
+        let target = &job.target;
+        let info = target.texture.get_info().to_raw_image_info(GeneratorFormat::get_format().1, 0);
+        self.encoder.update_constant_buffer(
+            &target.params,
+            &GeneratorParams {
+                size: [target.count as u32, info.width as u32, 0, 0],
+                params: job.params,
+            },
+        );
+        let slice = gfx::Slice {
             start: 0,
             end: 4,
             base_vertex: 0,
             instances: None,
             buffer: gfx::IndexBuffer::Auto,
         };
+        let data = generator_pipe::Data {
+            params: target.params.clone(),
+            input: job.input.as_ref().unwrap_or(&self.map_default).to_param(),
+            target: target.view.clone(),
+        };
+        self.encoder.draw(&slice, &job.pipeline, &data);
+        self.encoder
+            .copy_texture_to_buffer_raw(target.texture.raw(), None, info, target.vertices.raw(), 0)
+            .expect("copy generated vertices to the vertex buffer");
+    }
 
-        // draw background (if any)
-        match scene.background {
-            Background::Texture(ref texture) => {
-                // TODO: Reduce code duplication (see drawing debug quads)
-                self.encoder.update_constant_buffer(
-                    &self.quad_buf,
-                    &QuadParams {
-                        rect: [-1.0, -1.0, 1.0, 1.0],
-                        depth: 1.0,
-                    },
-                );
-                let data = quad_pipe::Data {
-                    params: self.quad_buf.clone(),
-                    globals: self.const_buf.clone(),
-                    resource: texture.to_param().0.raw().clone(),
-                    sampler: texture.to_param().1,
-                    target: self.out_color.clone(),
-                    depth_target: self.out_depth.clone(),
-                };
-                self.encoder.draw(&quad_slice, &self.pso.quad, &data);
-            }
-            Background::Skybox(ref cubemap) => {
-                self.encoder.update_constant_buffer(
-                    &self.quad_buf,
-                    &QuadParams {
-                        rect: [-1.0, -1.0, 1.0, 1.0],
-                        depth: 1.0,
-                    },
-                );
-                let data = quad_pipe::Data {
-                    params: self.quad_buf.clone(),
-                    resource: cubemap.to_param().0.raw().clone(),
-                    sampler: cubemap.to_param().1,
-                    globals: self.const_buf.clone(),
-                    target: self.out_color.clone(),
-                    depth_target: self.out_depth.clone(),
-                };
-                self.encoder.draw(&quad_slice, &self.pso.skybox, &data);
-            }
-            Background::Color(_) => {}
+    /// Requests the virtual texture tiles recorded by the feedback pass of
+    /// the previous frame, see `Factory::update_virtual_texture`.
+    fn read_feedback(
+        &mut self,
+        hub: &mut Hub,
+    ) {
+        let target = match self.feedback {
+            Some(ref mut target) if target.pending => target,
+            _ => return,
+        };
+        target.pending = false;
+        let reader = self.factory.read_mapping(&target.download).unwrap();
+        for texel in reader.iter().filter(|texel| texel[3] != 0) {
+            // see `feedback` in the virtual texture shader
+            let high = texel[2] as u32;
+            let tile = Tile {
+                level: high >> 4,
+                x: texel[0] as u32 | (high & 3) << 8,
+                y: texel[1] as u32 | (high >> 2 & 3) << 8,
+            };
+            hub.virtual_feedback
+                .entry(texel[3])
+                .or_insert_with(HashSet::new)
+                .insert(tile);
         }
+    }
 
-        // draw ui text
-        for (_, font) in &self.font_cache {
-            font.draw(&mut self.encoder, &self.out_color, &self.out_depth);
-        }
+    /// Copies the texels of `texture` to the CPU, as RGBA rows from top to bottom.
+    fn download(
+        &mut self,
+        texture: &h::Texture<back::Resources, <ColorFormat as gfx::format::Formatted>::Surface>,
+    ) -> Vec<u8> {
+        use gfx::format::Formatted;
 
-        // draw debug quads
-        self.debug_quads.sync_pending();
-        for quad in self.debug_quads.iter() {
-            let pos = [
-                if quad.pos[0] >= 0 {
-                    quad.pos[0]
-                } else {
-                    self.size.0 as i32 + quad.pos[0] - quad.size[0]
-                },
-                if quad.pos[1] >= 0 {
-                    quad.pos[1]
-                } else {
-                    self.size.1 as i32 + quad.pos[1] - quad.size[1]
-                },
-            ];
-            let p0 = self.map_to_ndc([pos[0] as f32, pos[1] as f32]);
-            let p1 = self.map_to_ndc([
-                (pos[0] + quad.size[0]) as f32,
-                (pos[1] + quad.size[1]) as f32,
-            ]);
-            self.encoder.update_constant_buffer(
-                &self.quad_buf,
-                &QuadParams {
-                    rect: [p0.x, p0.y, p1.x, p1.y],
-                    depth: -1.0,
-                },
-            );
-            let data = quad_pipe::Data {
-                params: self.quad_buf.clone(),
-                globals: self.const_buf.clone(),
-                resource: quad.resource.clone(),
-                sampler: self.map_default.to_param().1,
-                target: self.out_color.clone(),
-                depth_target: self.out_depth.clone(),
-            };
-            self.encoder.draw(&quad_slice, &self.pso.quad, &data);
+        let info = texture.get_info().to_raw_image_info(ColorFormat::get_format().1, 0);
+        let (width, height) = (info.width as usize, info.height as usize);
+        let download = self.factory
+            .create_download_buffer::<[u8; 4]>(width * height)
+            .unwrap();
+        self.encoder
+            .copy_texture_to_buffer_raw(texture.raw(), None, info, download.raw(), 0)
+            .unwrap();
+        self.encoder.flush(&mut self.device);
+
+        let reader = self.factory.read_mapping(&download).unwrap();
+        let mut pixels = Vec::with_capacity(reader.len() * 4);
+        // GL stores the bottom row first
+        for y in (0 .. height).rev() {
+            for texel in &reader[y * width .. (y + 1) * width] {
+                pixels.extend_from_slice(texel);
+            }
         }
+        pixels
+    }
 
-        self.encoder.flush(&mut self.device);
+    /// Returns the last frame rendered by a headless renderer, as 8-bit RGBA
+    /// pixels in rows from top to bottom.
+    ///
+    /// Returns `None` for renderers drawing into a window or a framebuffer of
+    /// the application, whose contents can't be read back. Render those into
+    /// a [`RenderTarget`] first, or use [`Window::builder`] with
+    /// [`build_headless`].
+    ///
+    /// [`RenderTarget`]: struct.RenderTarget.html
+    /// [`Window::builder`]: window/struct.Window.html#method.builder
+    /// [`build_headless`]: window/struct.Builder.html#method.build_headless
+    pub fn read_pixels(&mut self) -> Option<Vec<u8>> {
+        let texture = match self.frame {
+            Some(ref texture) => texture.clone(),
+            None => return None,
+        };
+        Some(self.download(&texture))
+    }
+
+    /// Saves the last frame rendered by a headless renderer to an image file,
+    /// whose format is deduced from the extension of `path`.
+    ///
+    /// Fails with `io::ErrorKind::Other` when the frame can't be read back, see
+    /// [`read_pixels`](#method.read_pixels).
+    pub fn save_screenshot<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+    ) -> io::Result<()> {
+        let pixels = match self.read_pixels() {
+            Some(pixels) => pixels,
+            None => return Err(io::Error::new(io::ErrorKind::Other, "the frame of this renderer can't be read back")),
+        };
+        let image = image::RgbaImage::from_raw(self.size.0, self.size.1, pixels).unwrap();
+        image.save(path)
     }
 
     //TODO: make it generic over `gfx::Resources`
@@ -1158,40 +4008,58 @@ impl Renderer {
         displacement_contributions_buf: h::Buffer<back::Resources, DisplacementContribution>,
         out_color: h::RenderTargetView<back::Resources, ColorFormat>,
         out_depth: h::DepthStencilView<back::Resources, DepthFormat>,
-        pso: &PipelineStates<back::Resources>,
+        pso: &mut PipelineStates<back::Resources>,
+        factory: &mut back::Factory,
         map_default: &Texture<[f32; 4]>,
+        normal_default: &Texture<[f32; 4]>,
+        environment: (h::ShaderResourceView<back::Resources, [f32; 4]>, h::Sampler<back::Resources>),
+        probe_buf: h::Buffer<back::Resources, ProbeParams>,
+        probes: &ProbeParams,
+        probe_maps: &[(h::ShaderResourceView<back::Resources, [f32; 4]>, h::Sampler<back::Resources>); 2],
         instances: &[Instance],
         vertex_buf: h::Buffer<back::Resources, Vertex>,
         mut slice: gfx::Slice<back::Resources>,
         material: &Material,
+        state: &material::State,
         shadow_sampler: &h::Sampler<back::Resources>,
         shadow0: &h::ShaderResourceView<back::Resources, f32>,
         shadow1: &h::ShaderResourceView<back::Resources, f32>,
         displacement_contributions: &[DisplacementContribution],
         displacements: (h::ShaderResourceView<back::Resources, [f32; 4]>, h::Sampler<back::Resources>),
         joint_transform_buffer_view: h::ShaderResourceView<back::Resources, [f32; 4]>,
+        skinning: bool,
         displace: bool,
+        shadows: bool,
+        scissor: gfx::Rect,
     ) {
         encoder.update_buffer(&inst_buf, instances, 0).unwrap();
 
         if instances.len() > 1 {
             slice.instances = Some((instances.len() as u32, 0));
         }
+        if displace {
+            let data = if displacement_contributions.len() > MAX_TARGETS {
+                error!("Too many mesh targets ({})!", displacement_contributions.len());
+                &displacement_contributions[.. MAX_TARGETS]
+            } else {
+                displacement_contributions
+            };
+            encoder.update_buffer(&displacement_contributions_buf, data, 0).unwrap();
+        }
+        let features = Features {
+            skinning,
+            displacement: displace,
+            shadows,
+        };
 
         //TODO: batch per PSO
         match material.to_pso_data() {
             PsoData::Pbr { maps, mut params } => {
                 if displace {
-                    let data = if displacement_contributions.len() > MAX_TARGETS {
-                        error!("Too many mesh targets ({})!", displacement_contributions.len());
-                        &displacement_contributions[.. MAX_TARGETS]
-                    } else {
-                        displacement_contributions
-                    };
-                    encoder.update_buffer(&displacement_contributions_buf, data, 0).unwrap();
                     params.pbr_flags |= PbrFlags::DISPLACEMENT_BUFFER.bits();
                 }
                 encoder.update_constant_buffer(&pbr_buf, &params);
+                encoder.update_constant_buffer(&probe_buf, probes);
                 let map_params = maps.into_params(map_default);
                 let data = pbr_pipe::Data {
                     vbuf: vertex_buf,
@@ -1204,15 +4072,21 @@ impl Renderer {
                     emissive_map: map_params.emissive,
                     metallic_roughness_map: map_params.metallic_roughness,
                     occlusion_map: map_params.occlusion,
+                    environment_map: environment,
+                    probes: probe_buf,
+                    probe_map0: probe_maps[0].clone(),
+                    probe_map1: probe_maps[1].clone(),
                     color_target: out_color,
                     depth_target: out_depth,
                     displacement_contributions: displacement_contributions_buf,
                     displacements,
                     joint_transforms: joint_transform_buffer_view,
+                    scissor,
                 };
-                encoder.draw(&slice, &pso.pbr, &data);
+                let features = pso_cache::Kind::Pbr.features(features);
+                encoder.draw(&slice, pso.pbr_variant(factory, state, features), &data);
             }
-            PsoData::Basic { map, .. } => {
+            PsoData::Basic { map, normal_map, .. } => {
                 //TODO: avoid excessive cloning
                 let data = basic_pipe::Data {
                     vbuf: vertex_buf,
@@ -1220,16 +4094,64 @@ impl Renderer {
                     cb_lights: light_buf,
                     cb_globals: const_buf.clone(),
                     tex_map: map.unwrap_or(map_default.clone()).to_param(),
+                    tex_normal_map: normal_map.unwrap_or(normal_default.clone()).to_param(),
                     shadow_map0: (shadow0.clone(), shadow_sampler.clone()),
                     shadow_map1: (shadow1.clone(), shadow_sampler.clone()),
+                    displacement_contributions: displacement_contributions_buf,
+                    displacements,
                     out_color,
                     out_depth: (out_depth, (0, 0)),
+                    scissor,
                 };
-                encoder.draw(&slice, pso.pso_by_material(&material), &data);
+                match pso.basic_variant(factory, material, state, features) {
+                    Some(pso) => encoder.draw(&slice, pso, &data),
+                    None => error!("No pipeline for the material, skipping the draw"),
+                }
             }
         }
     }
 
+    /// Shows the orientation gizmo, drawing the world axes as seen by the camera
+    /// in a square of `size` pixels.
+    ///
+    /// Negative `pos` coordinates are relative to the right and bottom edges of the
+    /// window, as in [`debug_shadow_quad`](#method.debug_shadow_quad).
+    pub fn show_gizmo(
+        &mut self,
+        pos: [i16; 2],
+        size: u16,
+    ) {
+        let vertices = match self.gizmo.take() {
+            Some(gizmo) => gizmo.vertices,
+            None => self.factory.create_vertex_buffer(&gizmo::vertices()),
+        };
+        self.gizmo = Some(Gizmo {
+            pos: [pos[0] as i32, pos[1] as i32],
+            size: size as i32,
+            vertices,
+            mx_vp: None,
+        });
+    }
+
+    /// Hides the orientation gizmo.
+    pub fn hide_gizmo(&mut self) {
+        self.gizmo = None;
+    }
+
+    /// Returns the gizmo axis under `point`, given in screen pixel coordinates,
+    /// as of the last rendered frame.
+    ///
+    /// Snap the camera to the returned axis with
+    /// [`GizmoAxis::view_orientation`](enum.GizmoAxis.html#method.view_orientation).
+    pub fn pick_gizmo<P: Into<mint::Point2<f32>>>(
+        &self,
+        point: P,
+    ) -> Option<GizmoAxis> {
+        self.gizmo
+            .as_ref()
+            .and_then(|gizmo| gizmo.pick(self.size, point.into()))
+    }
+
     /// Draw [`ShadowMap`](struct.ShadowMap.html) for debug purposes.
     pub fn debug_shadow_quad(
         &mut self,
@@ -0,0 +1,534 @@
+//! Full-screen effects applied to the frame after the scene is rendered.
+//!
+//! Effects are registered with [`Renderer::add_post_effect`] and run in the
+//! order they were added, each one reading the output of the previous one.
+//! UI text and debug quads are drawn afterwards, so they are not affected.
+//!
+//! ```rust,no_run
+//! # let mut window = three::Window::new("");
+//! use three::render::post::{Bloom, Fxaa, ToneMapping};
+//!
+//! window.renderer.add_post_effect(Box::new(Bloom::default()));
+//! window.renderer.add_post_effect(Box::new(ToneMapping::default()));
+//! window.renderer.add_post_effect(Box::new(Fxaa));
+//! ```
+//!
+//! While effects are registered, the scene is rendered into targets with
+//! 16-bit float channels, so lighting brighter than white reaches the effects
+//! unclamped until the last effect writes to the output. With
+//! [`Renderer::set_hdr`], a [`ToneMapping`] pass runs between the effects
+//! working on unclamped colors, like [`Bloom`], and the others, see
+//! [`PostEffect::before_tone_mapping`].
+//! The depth of the frame is also available to the effects, e.g. for the
+//! ambient occlusion of [`Ssao`], unless the frame is multisampled.
+//!
+//! Custom effects implement [`PostEffect`], usually with a pipeline created
+//! by [`Factory::post_pipeline`].
+//!
+//! [`Renderer::add_post_effect`]: ../struct.Renderer.html#method.add_post_effect
+//! [`Renderer::set_hdr`]: ../struct.Renderer.html#method.set_hdr
+//! [`ToneMapping`]: struct.ToneMapping.html
+//! [`Bloom`]: struct.Bloom.html
+//! [`PostEffect::before_tone_mapping`]: trait.PostEffect.html#method.before_tone_mapping
+//! [`Ssao`]: struct.Ssao.html
+//! [`PostEffect`]: trait.PostEffect.html
+//! [`Factory::post_pipeline`]: ../../struct.Factory.html#method.post_pipeline
+
+use cgmath::{Matrix4, SquareMatrix};
+use gfx;
+use gfx::handle as h;
+use mint;
+
+use std::cmp;
+
+use super::{back, color_target, post_pipe, ssao_pipe, ColorFormat, PipelineStates};
+use super::{PostParams, PostPipelineState, SsaoParams};
+
+/// Resources available to a [`PostEffect`](trait.PostEffect.html) while it
+/// records its passes.
+pub struct PostContext<'a> {
+    /// Encoder recording the commands of the frame.
+    pub encoder: &'a mut gfx::Encoder<back::Resources, back::CommandBuffer>,
+    /// Factory to create pipelines and intermediate targets, e.g. on first use.
+    pub factory: &'a mut back::Factory,
+    /// Color of the frame before the effect.
+    pub input: h::ShaderResourceView<back::Resources, [f32; 4]>,
+    /// Target receiving the color of the frame after the effect.
+    pub output: h::RenderTargetView<back::Resources, ColorFormat>,
+    /// Size of `input` and `output` in pixels.
+    pub size: (u32, u32),
+    /// Depth of the frame, or `None` if it can't be sampled, e.g. with
+    /// multisampling.
+    pub depth: Option<h::ShaderResourceView<back::Resources, f32>>,
+    /// Projection matrix of the camera the frame was rendered with.
+    pub projection: mint::ColumnMatrix4<f32>,
+    pub(crate) pso: &'a PipelineStates<back::Resources>,
+    pub(crate) params_buf: h::Buffer<back::Resources, PostParams>,
+    pub(crate) ssao_buf: h::Buffer<back::Resources, SsaoParams>,
+    pub(crate) sampler: h::Sampler<back::Resources>,
+}
+
+impl<'a> PostContext<'a> {
+    /// Draws `input` into `output` through `pipeline`, a full-screen pass
+    /// with the given effect parameters in `u_Params`.
+    ///
+    /// See [`Factory::post_pipeline`](../../struct.Factory.html#method.post_pipeline)
+    /// for the shader interface.
+    pub fn draw(
+        &mut self,
+        pipeline: &PostPipelineState,
+        params: [f32; 4],
+    ) {
+        let (input, output, size) = (self.input.clone(), self.output.clone(), self.size);
+        self.draw_pass(pipeline, params, input.clone(), input, output, size);
+    }
+
+    /// Draws `input` and `extra` into `target` of `size` pixels through
+    /// `pipeline`, for effects with several passes or intermediate targets.
+    ///
+    /// `u_Resolution` holds the size of `target`.
+    pub fn draw_pass(
+        &mut self,
+        pipeline: &PostPipelineState,
+        params: [f32; 4],
+        input: h::ShaderResourceView<back::Resources, [f32; 4]>,
+        extra: h::ShaderResourceView<back::Resources, [f32; 4]>,
+        target: h::RenderTargetView<back::Resources, ColorFormat>,
+        size: (u32, u32),
+    ) {
+        let (width, height) = (size.0 as f32, size.1 as f32);
+        self.encoder.update_constant_buffer(
+            &self.params_buf,
+            &PostParams {
+                resolution: [width, height, 1.0 / width, 1.0 / height],
+                params,
+            },
+        );
+        let slice = gfx::Slice {
+            start: 0,
+            end: 4,
+            base_vertex: 0,
+            instances: None,
+            buffer: gfx::IndexBuffer::Auto,
+        };
+        let data = post_pipe::Data {
+            params: self.params_buf.clone(),
+            input: (input, self.sampler.clone()),
+            extra: (extra, self.sampler.clone()),
+            target,
+        };
+        self.encoder.draw(&slice, pipeline, &data);
+    }
+}
+
+/// A full-screen pass run after the scene is rendered.
+pub trait PostEffect {
+    /// Records the commands drawing `context.input` into `context.output`.
+    ///
+    /// The effect must write every pixel of the output.
+    fn render(
+        &mut self,
+        context: &mut PostContext,
+    );
+
+    /// Returns `true` if the effect works on the unclamped colors of the
+    /// frame, so that it runs before the tone mapping of
+    /// [`Renderer::set_hdr`](../struct.Renderer.html#method.set_hdr), after
+    /// the other effects returning `true`. Defaults to `false`.
+    fn before_tone_mapping(&self) -> bool {
+        false
+    }
+}
+
+/// Fast approximate anti-aliasing, smoothing the edges of the frame.
+///
+/// Meant to run after tone mapping, on colors in the displayable range.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Fxaa;
+
+impl PostEffect for Fxaa {
+    fn render(
+        &mut self,
+        context: &mut PostContext,
+    ) {
+        let pso = context.pso;
+        context.draw(&pso.fxaa, [0.0; 4]);
+    }
+}
+
+/// Curve mapping unbounded scene colors to the displayable range.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ToneMapOperator {
+    /// `color / (1 + color)`, keeping the hue of bright colors.
+    Reinhard,
+    /// Fit of the ACES filmic curve, with more contrast and saturation.
+    Aces,
+}
+
+/// Maps the colors of the frame to the displayable range.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ToneMapping {
+    /// Multiplier applied to the colors before the curve.
+    ///
+    /// Default: `1.0`.
+    pub exposure: f32,
+
+    /// Curve applied to the colors.
+    ///
+    /// Default: `ToneMapOperator::Aces`.
+    pub operator: ToneMapOperator,
+}
+
+impl Default for ToneMapping {
+    fn default() -> Self {
+        ToneMapping {
+            exposure: 1.0,
+            operator: ToneMapOperator::Aces,
+        }
+    }
+}
+
+impl PostEffect for ToneMapping {
+    fn render(
+        &mut self,
+        context: &mut PostContext,
+    ) {
+        let operator = match self.operator {
+            ToneMapOperator::Reinhard => 0.0,
+            ToneMapOperator::Aces => 1.0,
+        };
+        let pso = context.pso;
+        context.draw(&pso.tone_mapping, [self.exposure, operator, 0.0, 0.0]);
+    }
+}
+
+/// Intermediate targets of [`Bloom`](struct.Bloom.html), at half the size of
+/// the frame.
+#[derive(Clone, Debug)]
+struct BloomTargets {
+    size: (u32, u32),
+    color: [h::RenderTargetView<back::Resources, ColorFormat>; 2],
+    resource: [h::ShaderResourceView<back::Resources, [f32; 4]>; 2],
+}
+
+/// Glow around the bright parts of the frame.
+///
+/// Colors brighter than `threshold` are blurred at half resolution and added
+/// back to the frame. Works on unclamped colors, so with
+/// [`Renderer::set_hdr`](../struct.Renderer.html#method.set_hdr) it runs
+/// before the tone mapping, whatever the order it was added in.
+#[derive(Clone, Debug)]
+pub struct Bloom {
+    /// Brightest color channel above which pixels glow.
+    ///
+    /// Default: `1.0`.
+    pub threshold: f32,
+
+    /// Multiplier applied to the glow added to the frame.
+    ///
+    /// Default: `0.5`.
+    pub intensity: f32,
+
+    targets: Option<BloomTargets>,
+}
+
+impl Bloom {
+    /// Creates a bloom effect with the given `threshold` and `intensity`.
+    pub fn new(
+        threshold: f32,
+        intensity: f32,
+    ) -> Self {
+        Bloom {
+            threshold,
+            intensity,
+            targets: None,
+        }
+    }
+
+    /// Returns the intermediate targets for a frame of `size` pixels,
+    /// creating them if needed.
+    fn targets(
+        &mut self,
+        factory: &mut back::Factory,
+        size: (u32, u32),
+    ) -> Option<BloomTargets> {
+        use gfx::texture as t;
+
+        let size = (cmp::max(size.0 / 2, 1), cmp::max(size.1 / 2, 1));
+        match self.targets {
+            Some(ref targets) if targets.size == size => return Some(targets.clone()),
+            _ => {}
+        }
+        let kind = t::Kind::D2(size.0 as t::Size, size.1 as t::Size, t::AaMode::Single);
+        let targets = color_target(factory, kind, true).and_then(|(color0, resource0)| {
+            let (color1, resource1) = color_target(factory, kind, true)?;
+            Ok(BloomTargets {
+                size,
+                color: [color0, color1],
+                resource: [resource0, resource1],
+            })
+        });
+        match targets {
+            Ok(targets) => {
+                self.targets = Some(targets.clone());
+                Some(targets)
+            }
+            Err(err) => {
+                error!("Failed to create bloom targets, skipping bloom: {:?}", err);
+                None
+            }
+        }
+    }
+}
+
+impl Default for Bloom {
+    fn default() -> Self {
+        Bloom::new(1.0, 0.5)
+    }
+}
+
+impl PostEffect for Bloom {
+    fn render(
+        &mut self,
+        context: &mut PostContext,
+    ) {
+        let pso = context.pso;
+        let (input, output, size) = (context.input.clone(), context.output.clone(), context.size);
+        let targets = match self.targets(context.factory, size) {
+            Some(targets) => targets,
+            None => {
+                // a composite without glow, still writing the output
+                context.draw_pass(&pso.bloom, [0.0, 0.0, 0.0, 2.0], input.clone(), input, output, size);
+                return;
+            }
+        };
+        let (color, resource) = (&targets.color, &targets.resource);
+        context.draw_pass(
+            &pso.bloom,
+            [self.threshold, 0.0, 0.0, 0.0],
+            input.clone(),
+            input.clone(),
+            color[0].clone(),
+            targets.size,
+        );
+        context.draw_pass(
+            &pso.bloom,
+            [1.0, 0.0, 0.0, 1.0],
+            resource[0].clone(),
+            resource[0].clone(),
+            color[1].clone(),
+            targets.size,
+        );
+        context.draw_pass(
+            &pso.bloom,
+            [0.0, 1.0, 0.0, 1.0],
+            resource[1].clone(),
+            resource[1].clone(),
+            color[0].clone(),
+            targets.size,
+        );
+        context.draw_pass(
+            &pso.bloom,
+            [self.intensity, 0.0, 0.0, 2.0],
+            input,
+            resource[0].clone(),
+            output,
+            size,
+        );
+    }
+
+    fn before_tone_mapping(&self) -> bool {
+        true
+    }
+}
+
+/// Intermediate targets of [`Ssao`](struct.Ssao.html), holding the occlusion
+/// of the frame and its blurred copy.
+#[derive(Clone, Debug)]
+struct SsaoTargets {
+    size: (u32, u32),
+    color: [h::RenderTargetView<back::Resources, ColorFormat>; 2],
+    resource: [h::ShaderResourceView<back::Resources, [f32; 4]>; 2],
+}
+
+/// Screen space ambient occlusion, darkening the creases and contact areas of
+/// the frame.
+///
+/// The occlusion is estimated from the depth of the frame, blurred and
+/// multiplied with the colors. The frame is left unchanged when its depth
+/// can't be sampled, which is the case with multisampling. Meant to run first,
+/// before effects that don't preserve the layout of the frame.
+#[derive(Clone, Debug)]
+pub struct Ssao {
+    /// Distance in world units around a point within which surfaces occlude it.
+    ///
+    /// Default: `0.5`.
+    pub radius: f32,
+
+    /// Strength of the darkening, where `0.0` leaves the frame unchanged.
+    ///
+    /// Default: `1.0`.
+    pub intensity: f32,
+
+    /// Depth difference below which surfaces don't occlude each other, to
+    /// avoid flat surfaces darkening themselves.
+    ///
+    /// Default: `0.025`.
+    pub bias: f32,
+
+    /// Spacing in pixels of the taps of the blur smoothing the occlusion,
+    /// where `0.0` disables the blur.
+    ///
+    /// Default: `1.0`.
+    pub blur: f32,
+
+    targets: Option<SsaoTargets>,
+}
+
+impl Ssao {
+    /// Creates an ambient occlusion effect with the given `radius` and
+    /// `intensity`.
+    pub fn new(
+        radius: f32,
+        intensity: f32,
+    ) -> Self {
+        Ssao {
+            radius,
+            intensity,
+            bias: 0.025,
+            blur: 1.0,
+            targets: None,
+        }
+    }
+
+    /// Returns the intermediate targets for a frame of `size` pixels,
+    /// creating them if needed.
+    fn targets(
+        &mut self,
+        factory: &mut back::Factory,
+        size: (u32, u32),
+    ) -> Option<SsaoTargets> {
+        use gfx::texture as t;
+
+        match self.targets {
+            Some(ref targets) if targets.size == size => return Some(targets.clone()),
+            _ => {}
+        }
+        let kind = t::Kind::D2(size.0 as t::Size, size.1 as t::Size, t::AaMode::Single);
+        let targets = color_target(factory, kind, false).and_then(|(color0, resource0)| {
+            let (color1, resource1) = color_target(factory, kind, false)?;
+            Ok(SsaoTargets {
+                size,
+                color: [color0, color1],
+                resource: [resource0, resource1],
+            })
+        });
+        match targets {
+            Ok(targets) => {
+                self.targets = Some(targets.clone());
+                Some(targets)
+            }
+            Err(err) => {
+                error!("Failed to create ambient occlusion targets, skipping it: {:?}", err);
+                None
+            }
+        }
+    }
+
+    /// Draws a pass of the ambient occlusion shader into `target`.
+    fn draw_pass(
+        context: &mut PostContext,
+        params: [f32; 4],
+        input: h::ShaderResourceView<back::Resources, [f32; 4]>,
+        depth: h::ShaderResourceView<back::Resources, f32>,
+        occlusion: h::ShaderResourceView<back::Resources, [f32; 4]>,
+        target: h::RenderTargetView<back::Resources, ColorFormat>,
+    ) {
+        let (width, height) = (context.size.0 as f32, context.size.1 as f32);
+        let projection = Matrix4::from(context.projection);
+        let inv_projection = projection.invert().unwrap_or(Matrix4::identity());
+        context.encoder.update_constant_buffer(
+            &context.ssao_buf,
+            &SsaoParams {
+                projection: projection.into(),
+                inv_projection: inv_projection.into(),
+                resolution: [width, height, 1.0 / width, 1.0 / height],
+                params,
+            },
+        );
+        let slice = gfx::Slice {
+            start: 0,
+            end: 4,
+            base_vertex: 0,
+            instances: None,
+            buffer: gfx::IndexBuffer::Auto,
+        };
+        let data = ssao_pipe::Data {
+            params: context.ssao_buf.clone(),
+            input: (input, context.sampler.clone()),
+            depth: (depth, context.sampler.clone()),
+            occlusion: (occlusion, context.sampler.clone()),
+            target,
+        };
+        context.encoder.draw(&slice, &context.pso.ssao, &data);
+    }
+}
+
+impl Default for Ssao {
+    fn default() -> Self {
+        Ssao::new(0.5, 1.0)
+    }
+}
+
+impl PostEffect for Ssao {
+    fn render(
+        &mut self,
+        context: &mut PostContext,
+    ) {
+        let pso = context.pso;
+        let (input, output, size) = (context.input.clone(), context.output.clone(), context.size);
+        let (depth, targets) = match (context.depth.clone(), self.targets(context.factory, size)) {
+            (Some(depth), Some(targets)) => (depth, targets),
+            _ => {
+                // nothing to darken with, the frame is copied as is
+                context.draw_pass(&pso.bloom, [0.0, 0.0, 0.0, 2.0], input.clone(), input, output, size);
+                return;
+            }
+        };
+        let (color, resource) = (&targets.color, &targets.resource);
+        Ssao::draw_pass(
+            context,
+            [self.radius, self.bias, 0.0, 0.0],
+            input.clone(),
+            depth.clone(),
+            input.clone(),
+            color[0].clone(),
+        );
+        if self.blur > 0.0 {
+            context.draw_pass(
+                &pso.bloom,
+                [self.blur, 0.0, 0.0, 1.0],
+                resource[0].clone(),
+                resource[0].clone(),
+                color[1].clone(),
+                size,
+            );
+            context.draw_pass(
+                &pso.bloom,
+                [0.0, self.blur, 0.0, 1.0],
+                resource[1].clone(),
+                resource[1].clone(),
+                color[0].clone(),
+                size,
+            );
+        }
+        Ssao::draw_pass(
+            context,
+            [self.intensity, 0.0, 0.0, 1.0],
+            input,
+            depth,
+            resource[0].clone(),
+            output,
+        );
+    }
+}
@@ -0,0 +1,365 @@
+use gfx;
+use gfx::traits::FactoryExt;
+use material::{Blend, Cull, LineMode, Material, State};
+use render::{basic_pipe, pbr_pipe, PipelineCreationError, FEEDBACK_SCALE};
+use render::source::{self, Defines};
+
+use std::collections::HashMap;
+
+/// The shader programs a material can be rendered with.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub(crate) enum Kind {
+    Basic,
+    /// Lines joined as a strip or as independent `segments`, drawn as OpenGL
+    /// lines if `thin` and expanded into quads by the line program otherwise.
+    Line { segments: bool, thin: bool },
+    /// Points expanded into quads by the points program.
+    Points,
+    Wireframe,
+    Lambert,
+    Phong,
+    Sprite,
+    /// Lambert lighting with a virtual texture.
+    Virtual,
+    /// Virtual texture tiles sampled by the meshes with a `Virtual`
+    /// material, drawn by the feedback pass.
+    Feedback,
+    Pbr,
+    /// Diagnostic views of `Renderer::debug_view`, not used by any material.
+    Diagnostic,
+}
+
+impl Kind {
+    /// Returns the kind of the built-in pipeline of `material`, if any.
+    pub(crate) fn of(material: &Material) -> Option<Self> {
+        match *material {
+            Material::Basic(_) => Some(Kind::Basic),
+            Material::CustomBasic(_) => None,
+            Material::Line(ref params) => Some(Kind::Line {
+                segments: params.mode == LineMode::Segments,
+                // OpenGL can't draw lines thinner than a pixel either
+                thin: params.width <= 1.0,
+            }),
+            Material::DashedLine(ref params) => Some(Kind::Line {
+                segments: params.mode == LineMode::Segments,
+                thin: false,
+            }),
+            Material::Points(_) => Some(Kind::Points),
+            Material::Wireframe(_) => Some(Kind::Wireframe),
+            Material::Lambert(_) => Some(Kind::Lambert),
+            Material::Phong(_) => Some(Kind::Phong),
+            Material::Sprite(_) => Some(Kind::Sprite),
+            Material::Virtual(_) => Some(Kind::Virtual),
+            Material::Pbr(_) => Some(Kind::Pbr),
+        }
+    }
+
+    /// Returns `true` if the pipeline of this kind with the default state is
+    /// created with the renderer, the others being created on first use.
+    pub(crate) fn is_builtin(&self) -> bool {
+        match *self {
+            Kind::Line { segments, thin } => !segments && thin,
+            Kind::Points | Kind::Virtual | Kind::Feedback => false,
+            _ => true,
+        }
+    }
+
+    /// Returns the part of `features` the programs of this kind are compiled
+    /// with, so that unsupported features don't create variants.
+    pub(crate) fn features(
+        &self,
+        features: Features,
+    ) -> Features {
+        match *self {
+            Kind::Basic | Kind::Wireframe => Features {
+                displacement: features.displacement,
+                ..Features::BASIC
+            },
+            Kind::Lambert | Kind::Phong => Features {
+                displacement: features.displacement,
+                shadows: features.shadows,
+                ..Features::BASIC
+            },
+            // the PBR program doesn't sample the shadow maps
+            Kind::Pbr => Features {
+                shadows: true,
+                ..features
+            },
+            _ => Features::BASIC,
+        }
+    }
+
+    /// Returns the kind drawing this one without a geometry shader, i.e. thin
+    /// lines for wide ones, or `None` for points.
+    pub(crate) fn without_geometry_shader(&self) -> Option<Self> {
+        match *self {
+            Kind::Line { segments, .. } => Some(Kind::Line { segments, thin: true }),
+            Kind::Points => None,
+            kind => Some(kind),
+        }
+    }
+
+    /// Returns `state` with the blending the programs of this kind require
+    /// merged in, so that sprites keep their alpha blending.
+    pub(crate) fn state(
+        &self,
+        state: &State,
+    ) -> State {
+        match *self {
+            Kind::Sprite if state.blend == Blend::Replace => State {
+                blend: Blend::Alpha,
+                ..*state
+            },
+            _ => *state,
+        }
+    }
+
+    fn primitive(&self) -> gfx::Primitive {
+        match *self {
+            Kind::Line { segments: true, .. } => gfx::Primitive::LineList,
+            Kind::Line { .. } => gfx::Primitive::LineStrip,
+            Kind::Points => gfx::Primitive::PointList,
+            Kind::Sprite => gfx::Primitive::TriangleStrip,
+            _ => gfx::Primitive::TriangleList,
+        }
+    }
+}
+
+/// Optional shader features of the mesh pipelines.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub(crate) struct Features {
+    /// Vertex skinning with a joint buffer.
+    pub skinning: bool,
+    /// Morph target displacements.
+    pub displacement: bool,
+    /// Shadow map sampling.
+    pub shadows: bool,
+}
+
+impl Features {
+    /// All features enabled, matching the built-in PBR pipeline.
+    pub(crate) const ALL: Features = Features {
+        skinning: true,
+        displacement: true,
+        shadows: true,
+    };
+
+    /// Features of the built-in basic pipelines.
+    pub(crate) const BASIC: Features = Features {
+        skinning: false,
+        displacement: false,
+        shadows: true,
+    };
+
+    fn defines(&self) -> Defines {
+        let mut defines = Defines::new();
+        defines
+            .define("SKINNING", self.skinning as u32)
+            .define("DISPLACEMENT", self.displacement as u32)
+            .define("SHADOWS", self.shadows as u32);
+        defines
+    }
+}
+
+/// Shader programs kept around to create pipeline variants on demand.
+pub(crate) struct Shaders<R: gfx::Resources> {
+    pub basic: gfx::ShaderSet<R>,
+    pub diagnostic: gfx::ShaderSet<R>,
+    pub gouraud: gfx::ShaderSet<R>,
+    pub phong: gfx::ShaderSet<R>,
+    pub sprite: gfx::ShaderSet<R>,
+    pub pbr: gfx::ShaderSet<R>,
+    /// Shader templates, compiled lazily for feature variants.
+    pub source: source::Set,
+}
+
+/// Pipeline states created on demand, keyed by material kind, state and
+/// shader features.
+pub(crate) struct PsoCache<R: gfx::Resources> {
+    pub basic: HashMap<(Kind, State, Features), gfx::PipelineState<R, basic_pipe::Meta>>,
+    pub pbr: HashMap<(State, Features), gfx::PipelineState<R, pbr_pipe::Meta>>,
+}
+
+impl<R: gfx::Resources> PsoCache<R> {
+    pub(crate) fn new() -> Self {
+        PsoCache {
+            basic: HashMap::new(),
+            pbr: HashMap::new(),
+        }
+    }
+}
+
+fn rasterizer(
+    kind: Kind,
+    state: &State,
+) -> gfx::state::Rasterizer {
+    let fill = gfx::state::Rasterizer {
+        samples: Some(gfx::state::MultiSample),
+        ..gfx::state::Rasterizer::new_fill()
+    };
+    let culled = match state.cull {
+        Cull::None => fill,
+        Cull::Front => gfx::state::Rasterizer {
+            cull_face: gfx::state::CullFace::Front,
+            ..fill
+        },
+        Cull::Back => fill.with_cull_back(),
+    };
+    match kind {
+        Kind::Wireframe => gfx::state::Rasterizer {
+            method: gfx::state::RasterMethod::Line(1),
+            ..culled
+        },
+        // the quads of wide lines and points face either way
+        Kind::Line { thin: false, .. } | Kind::Points => fill,
+        _ => culled,
+    }
+}
+
+fn blend(state: &State) -> gfx::state::Blend {
+    match state.blend {
+        Blend::Replace => gfx::preset::blend::REPLACE,
+        Blend::Alpha => gfx::preset::blend::ALPHA,
+        Blend::Add => gfx::preset::blend::ADD,
+        Blend::Multiply => gfx::preset::blend::MULTIPLY,
+    }
+}
+
+fn depth(state: &State) -> gfx::state::Depth {
+    gfx::state::Depth {
+        fun: if state.depth_test {
+            gfx::state::Comparison::LessEqual
+        } else {
+            gfx::state::Comparison::Always
+        },
+        write: state.depth_write,
+    }
+}
+
+/// Returns the definitions of the shader variant for `state`.
+fn fog_defines(state: &State) -> Defines {
+    let mut defines = Defines::new();
+    defines.define("FOG", state.fog as u32);
+    defines
+}
+
+/// Creates a basic pipeline variant for `kind` with the given `state` and
+/// the `features` supported by the kind.
+///
+/// Feature variants are compiled from the shader templates on first use.
+pub(crate) fn create_basic<R, F>(
+    shaders: &Shaders<R>,
+    backend: &mut F,
+    kind: Kind,
+    state: &State,
+    features: Features,
+) -> Result<gfx::PipelineState<R, basic_pipe::Meta>, PipelineCreationError>
+where
+    R: gfx::Resources,
+    F: gfx::Factory<R>,
+{
+    let src = &shaders.source;
+    let variant;
+    let program = match kind {
+        // compiled on first use, since geometry shaders aren't available
+        // with all OpenGL ES drivers
+        Kind::Line { thin: false, .. } => {
+            let defines = fog_defines(state);
+            variant = backend.create_shader_set_geometry(
+                &src.line.vs.with_defines(&defines),
+                &src.line.gs.with_defines(&defines),
+                &src.line.ps.with_defines(&defines),
+            )?;
+            &variant
+        }
+        Kind::Points => {
+            let defines = fog_defines(state);
+            variant = backend.create_shader_set_geometry(
+                &src.points.vs.with_defines(&defines),
+                &src.points.gs.with_defines(&defines),
+                &src.points.ps.with_defines(&defines),
+            )?;
+            &variant
+        }
+        // compiled on first use, since few applications need it
+        Kind::Virtual | Kind::Feedback => {
+            let mut defines = fog_defines(state);
+            if kind == Kind::Feedback {
+                defines.define("FEEDBACK", FEEDBACK_SCALE);
+            }
+            variant = backend.create_shader_set(
+                &src.virtual_texture.vs.with_defines(&defines),
+                &src.virtual_texture.ps.with_defines(&defines),
+            )?;
+            &variant
+        }
+        _ => {
+            let (program, vs, ps) = match kind {
+                Kind::Basic | Kind::Line { .. } | Kind::Wireframe => (&shaders.basic, &src.basic.vs, &src.basic.ps),
+                Kind::Lambert => (&shaders.gouraud, &src.gouraud.vs, &src.gouraud.ps),
+                Kind::Phong => (&shaders.phong, &src.phong.vs, &src.phong.ps),
+                Kind::Sprite => (&shaders.sprite, &src.sprite.vs, &src.sprite.ps),
+                Kind::Diagnostic => (&shaders.diagnostic, &src.diagnostic.vs, &src.diagnostic.ps),
+                Kind::Points | Kind::Virtual | Kind::Feedback | Kind::Pbr => unreachable!(),
+            };
+            // meshes opting out of the fog, or with other features than the
+            // built-in pipeline, are compiled from the templates
+            if state.fog && kind.features(features) == Features::BASIC {
+                program
+            } else {
+                let mut defines = kind.features(features).defines();
+                defines.define("FOG", state.fog as u32);
+                variant = backend.create_shader_set(&vs.with_defines(&defines), &ps.with_defines(&defines))?;
+                &variant
+            }
+        }
+    };
+    let default = basic_pipe::new();
+    let init = basic_pipe::Init {
+        out_color: ("Target0", gfx::state::ColorMask::all(), blend(state)),
+        out_depth: (depth(state), (default.out_depth).1),
+        ..default
+    };
+    let pso = backend.create_pipeline_state(program, kind.primitive(), rasterizer(kind, state), init)?;
+    Ok(pso)
+}
+
+/// Creates a PBR pipeline variant with the given `state` and `features`.
+///
+/// Feature variants are compiled from the shader templates on first use.
+pub(crate) fn create_pbr<R, F>(
+    shaders: &Shaders<R>,
+    backend: &mut F,
+    state: &State,
+    features: Features,
+) -> Result<gfx::PipelineState<R, pbr_pipe::Meta>, PipelineCreationError>
+where
+    R: gfx::Resources,
+    F: gfx::Factory<R>,
+{
+    let variant;
+    let program = if features == Features::ALL && state.fog {
+        &shaders.pbr
+    } else {
+        let mut defines = features.defines();
+        if !state.fog {
+            defines.define("FOG", 0);
+        }
+        let vs = shaders.source.pbr.vs.with_defines(&defines);
+        let ps = shaders.source.pbr.ps.with_defines(&defines);
+        variant = backend.create_shader_set(&vs, &ps)?;
+        &variant
+    };
+    let init = pbr_pipe::Init {
+        color_target: ("Target0", gfx::state::ColorMask::all(), blend(state)),
+        depth_target: depth(state),
+        ..pbr_pipe::new()
+    };
+    let pso = backend.create_pipeline_state(
+        program,
+        Kind::Pbr.primitive(),
+        rasterizer(Kind::Pbr, state),
+        init,
+    )?;
+    Ok(pso)
+}
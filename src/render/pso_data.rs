@@ -65,8 +65,10 @@ pub(crate) enum PsoData {
     },
     Basic {
         color: u32,
-        param0: f32,
+        opacity: f32,
+        params: [f32; 4],
         map: Option<Texture<[f32; 4]>>,
+        normal_map: Option<Texture<[f32; 4]>>,
     },
 }
 
@@ -116,38 +118,76 @@ impl Material {
             }
             Material::Basic(ref params) => PsoData::Basic {
                 color: params.color,
+                opacity: params.opacity,
                 map: params.map.clone(),
-                param0: 0.0,
+                normal_map: None,
+                params: [0.0; 4],
             },
             Material::CustomBasic(ref params) => PsoData::Basic {
                 color: params.color,
+                opacity: 1.0,
                 map: params.map.clone(),
-                param0: 0.0,
+                normal_map: None,
+                params: [0.0; 4],
             },
             Material::Line(ref params) => PsoData::Basic {
                 color: params.color,
+                opacity: 1.0,
                 map: None,
-                param0: 0.0,
+                normal_map: None,
+                params: [params.width, 0.0, 0.0, 0.0],
+            },
+            Material::DashedLine(ref params) => PsoData::Basic {
+                color: params.color,
+                opacity: 1.0,
+                map: None,
+                normal_map: None,
+                params: [params.width, params.dash_size, params.gap_size, 1.0],
+            },
+            Material::Points(ref params) => PsoData::Basic {
+                color: params.color,
+                opacity: 1.0,
+                map: params.map.clone(),
+                normal_map: None,
+                params: [params.size, 0.0, 0.0, 0.0],
             },
             Material::Wireframe(ref params) => PsoData::Basic {
                 color: params.color,
+                opacity: 1.0,
                 map: None,
-                param0: 0.0,
+                normal_map: None,
+                params: [0.0; 4],
             },
             Material::Lambert(ref params) => PsoData::Basic {
                 color: params.color,
+                opacity: params.opacity,
                 map: None,
-                param0: if params.flat { 0.0 } else { 1.0 },
+                normal_map: None,
+                params: [if params.flat { 0.0 } else { 1.0 }, 0.0, 0.0, 0.0],
             },
-            Material::Phong(ref params) => PsoData::Basic {
+            Material::Phong(ref params) => {
+                let specular = color::to_linear_rgb(params.specular);
+                PsoData::Basic {
+                    color: params.color,
+                    opacity: params.opacity,
+                    map: params.map.clone(),
+                    normal_map: params.normal_map.clone(),
+                    params: [params.glossiness, specular[0], specular[1], specular[2]],
+                }
+            }
+            Material::Virtual(ref params) => PsoData::Basic {
                 color: params.color,
-                map: None,
-                param0: params.glossiness,
+                opacity: 1.0,
+                map: Some(params.map.cache.clone()),
+                normal_map: Some(params.map.indirection.clone()),
+                params: params.map.params(),
             },
             Material::Sprite(ref params) => PsoData::Basic {
                 color: !0,
+                opacity: 1.0,
                 map: Some(params.map.clone()),
-                param0: 0.0,
+                normal_map: None,
+                params: [0.0; 4],
             },
         }
     }
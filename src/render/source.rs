@@ -8,9 +8,82 @@ use std::borrow::Borrow;
 use std::path::Path;
 
 /// Source code for a single GLSL shader.
+///
+/// Shaders are always compiled from GLSL when a pipeline is created. SPIR-V and
+/// cached program binaries are not supported: the OpenGL backend of gfx can't
+/// create a pipeline from a program linked outside of it.
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct Source(pub(crate) String);
 
+/// OpenGL flavour the shaders are compiled for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Profile {
+    /// Desktop OpenGL 3.2 core, GLSL 1.50.
+    Core,
+    /// OpenGL ES 3.1, GLSL ES 3.10, e.g. for the Raspberry Pi and other
+    /// embedded devices.
+    ///
+    /// Skinned meshes additionally require texture buffers, available with
+    /// OpenGL ES 3.2 or the `GL_EXT_texture_buffer` extension. Geometry shaders
+    /// aren't used, so wide and dashed lines are drawn as thin lines and
+    /// `Material::Points` isn't drawn. OpenGL ES 2 isn't supported.
+    Es,
+}
+
+impl Default for Profile {
+    fn default() -> Self {
+        Profile::Core
+    }
+}
+
+/// Declarations following the `#version` directive of GLSL ES shaders.
+const ES_PROLOGUE: &str = "\
+#extension GL_EXT_texture_buffer : enable
+precision highp float;
+precision highp int;
+precision highp sampler2D;
+precision highp sampler2DShadow;
+precision highp samplerCube;
+precision highp sampler2DMS;
+#if defined(GL_EXT_texture_buffer)
+precision highp samplerBuffer;
+#endif
+";
+
+/// Preprocessor definitions selecting a variant of a shader template.
+///
+/// Shader templates guard optional features with `#if`/`#ifdef` blocks, so that
+/// feature combinations are compiled from a single file instead of handwritten
+/// copies.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Defines(Vec<(String, String)>);
+
+impl Defines {
+    /// Create an empty set of definitions.
+    pub fn new() -> Self {
+        Defines(Vec::new())
+    }
+
+    /// Define `name` as `value`, replacing any previous definition of `name`.
+    pub fn define<N, V>(
+        &mut self,
+        name: N,
+        value: V,
+    ) -> &mut Self
+    where
+        N: Into<String>,
+        V: ToString,
+    {
+        let name = name.into();
+        let value = value.to_string();
+        match self.0.iter().position(|&(ref n, _)| *n == name) {
+            Some(index) => self.0[index].1 = value,
+            None => self.0.push((name, value)),
+        }
+        self
+    }
+}
+
 impl ops::Deref for Source {
     type Target = [u8];
     fn deref(&self) -> &Self::Target {
@@ -53,6 +126,68 @@ impl Source {
         Ok(new_code)
     }
 
+    /// Wraps ready-to-compile GLSL code, e.g. code preprocessed offline or embedded
+    /// with `include_str!`.
+    ///
+    /// No preprocessing is applied, so `#include` directives are not resolved.
+    pub fn new<S: Into<String>>(code: S) -> Self {
+        Source(code.into())
+    }
+
+    /// Returns a copy of this source with `defines` inserted after the `#version` directive.
+    pub fn with_defines(
+        &self,
+        defines: &Defines,
+    ) -> Self {
+        let mut code = String::with_capacity(self.0.len());
+        let mut inserted = false;
+        let insert = |code: &mut String| {
+            for &(ref name, ref value) in &defines.0 {
+                *code += &format!("#define {} {}\n", name, value);
+            }
+        };
+        for line in self.0.lines() {
+            let is_version = line.trim_left().starts_with("#version");
+            if !inserted && !is_version {
+                insert(&mut code);
+                inserted = true;
+            }
+            code.push_str(line);
+            code.push('\n');
+        }
+        if !inserted {
+            insert(&mut code);
+        }
+        Source(code)
+    }
+
+    /// Returns a copy of this source converted from GLSL 1.50 to `profile`.
+    ///
+    /// For `Profile::Es`, the `#version` directive is replaced and default
+    /// precision qualifiers are declared. Other constructs are left as they are,
+    /// so user shaders must stay within the common subset of both languages.
+    pub fn for_profile(
+        &self,
+        profile: Profile,
+    ) -> Self {
+        match profile {
+            Profile::Core => self.clone(),
+            Profile::Es => {
+                let mut code = String::with_capacity(self.0.len() + ES_PROLOGUE.len());
+                for line in self.0.lines() {
+                    if line.trim_left().starts_with("#version") {
+                        code += "#version 310 es\n";
+                        code += ES_PROLOGUE;
+                    } else {
+                        code.push_str(line);
+                        code.push('\n');
+                    }
+                }
+                Source(code)
+            }
+        }
+    }
+
     /// Load the named shader from the default set of shaders.
     pub fn default(
         name: &str,
@@ -79,8 +214,12 @@ impl Source {
 }
 
 macro_rules! decl_shaders {
-    { $(($pso:ident, $doc:ident, $ty:ident),)* } => {
+    {
+        $(($pso:ident, $doc:ident, $ty:ident),)*
+        geometry: $(($gpso:ident, $gdoc:ident, $gty:ident),)*
+    } => {
         $( decl_shaders!($pso, $doc, $ty); )*
+        $( decl_shaders!(geometry $gpso, $gdoc, $gty); )*
 
         /// The set of shaders needed by the `three` renderer.
         #[derive(Clone, Debug, Default)]
@@ -89,6 +228,60 @@ macro_rules! decl_shaders {
                 #[allow(missing_docs)]
                 pub $pso: $ty,
             )*
+            $(
+                #[allow(missing_docs)]
+                pub $gpso: $gty,
+            )*
+        }
+
+        impl Set {
+            /// Returns a copy of the shaders converted to `profile`.
+            ///
+            /// See [`Source::for_profile`](struct.Source.html#method.for_profile).
+            pub fn for_profile(
+                &self,
+                profile: Profile,
+            ) -> Self {
+                Set {
+                    $(
+                        $pso: $ty {
+                            vs: self.$pso.vs.for_profile(profile),
+                            ps: self.$pso.ps.for_profile(profile),
+                        },
+                    )*
+                    $(
+                        $gpso: $gty {
+                            vs: self.$gpso.vs.for_profile(profile),
+                            gs: self.$gpso.gs.for_profile(profile),
+                            ps: self.$gpso.ps.for_profile(profile),
+                        },
+                    )*
+                }
+            }
+
+            /// Returns a copy of the shaders with `defines` inserted.
+            ///
+            /// See [`Source::with_defines`](struct.Source.html#method.with_defines).
+            pub fn with_defines(
+                &self,
+                defines: &Defines,
+            ) -> Self {
+                Set {
+                    $(
+                        $pso: $ty {
+                            vs: self.$pso.vs.with_defines(defines),
+                            ps: self.$pso.ps.with_defines(defines),
+                        },
+                    )*
+                    $(
+                        $gpso: $gty {
+                            vs: self.$gpso.vs.with_defines(defines),
+                            gs: self.$gpso.gs.with_defines(defines),
+                            ps: self.$gpso.ps.with_defines(defines),
+                        },
+                    )*
+                }
+            }
         }
     };
 
@@ -122,15 +315,61 @@ macro_rules! decl_shaders {
             }
         }
     };
+
+    (geometry $pso:ident, $doc:ident, $ty:ident) => {
+        #[allow(missing_docs)]
+        #[derive(Clone, Debug)]
+        pub struct $ty {
+            /// Vertex shader code.
+            pub(crate) vs: Source,
+
+            /// Geometry shader code.
+            pub(crate) gs: Source,
+
+            /// Pixel/fragment shader code.
+            pub(crate) ps: Source,
+        }
+
+        impl $ty {
+            /// Loads user shader code.
+            pub fn user<P: AsRef<Path>>(root: P) -> io::Result<Self> {
+                Ok(Self {
+                    vs: Source::user(&root, stringify!($pso), "vs")?,
+                    gs: Source::user(&root, stringify!($pso), "gs")?,
+                    ps: Source::user(&root, stringify!($pso), "ps")?,
+                })
+            }
+        }
+
+        impl Default for $ty {
+            fn default() -> Self {
+                Self {
+                    vs: Source::default(stringify!($pso), "vs").unwrap(),
+                    gs: Source::default(stringify!($pso), "gs").unwrap(),
+                    ps: Source::default(stringify!($pso), "ps").unwrap(),
+                }
+            }
+        }
+    };
 }
 
 decl_shaders! {
     (basic, basic, Basic),
+    (bloom, bloom, Bloom),
+    (diagnostic, diagnostic, Diagnostic),
+    (fxaa, FXAA, Fxaa),
     (gouraud, Gouraud, Gouraud),
     (pbr, PBR, Pbr),
     (phong, Phong, Phong),
     (quad, quad, Quad),
+    (resolve, resolve, Resolve),
     (shadow, shadow, Shadow),
     (skybox, skybox, Skybox),
     (sprite, sprite, Sprite),
+    (ssao, SSAO, Ssao),
+    (tone_mapping, tone_mapping, ToneMapping),
+    (virtual_texture, virtual_texture, VirtualTexture),
+    geometry:
+    (line, line, Line),
+    (points, points, Points),
 }
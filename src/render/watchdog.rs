@@ -0,0 +1,253 @@
+//! Frame time budget checks.
+
+use gfx;
+use gfx_device_gl as back;
+
+use std::collections::VecDeque;
+use std::time::Instant;
+
+use super::graph::Pass;
+
+// `GL_TIME_ELAPSED`, `GL_QUERY_RESULT` and `GL_QUERY_RESULT_AVAILABLE`.
+const TIME_ELAPSED: u32 = 0x88BF;
+const QUERY_RESULT: u32 = 0x8866;
+const QUERY_RESULT_AVAILABLE: u32 = 0x8867;
+
+/// Number of frames whose GPU timings may be in flight before the oldest
+/// ones are dropped.
+const MAX_PENDING_FRAMES: usize = 4;
+
+/// Time budgets of a frame, checked by the watchdog of a renderer.
+///
+/// See [`Renderer::set_budget`](struct.Renderer.html#method.set_budget).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Budget {
+    /// Maximum CPU time spent rendering a frame, in seconds, or `None`
+    /// to leave the CPU time unchecked.
+    ///
+    /// Default: `Some(1.0 / 60.0)`.
+    pub cpu: Option<f32>,
+    /// Maximum GPU time spent executing a frame, in seconds, or `None`
+    /// to leave the GPU time unchecked.
+    ///
+    /// Measuring the GPU time submits the passes of the frame one by one,
+    /// each wrapped in a timer query whose result is only available a few
+    /// frames later, so warnings are delayed accordingly. Timer queries need
+    /// OpenGL 3.3, `GL_ARB_timer_query` or `GL_EXT_disjoint_timer_query`,
+    /// without which the GPU time is left unchecked.
+    ///
+    /// Default: `None`.
+    pub gpu: Option<f32>,
+}
+
+impl Default for Budget {
+    fn default() -> Self {
+        Budget {
+            cpu: Some(1.0 / 60.0),
+            gpu: None,
+        }
+    }
+}
+
+/// Phase of a frame timed by the watchdog.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub(crate) enum Phase {
+    /// Processing of the scene messages.
+    Messages,
+    /// Skeleton, dynamic mesh and light updates preceding the passes.
+    Setup,
+    /// A pass of the frame graph.
+    Pass(Pass),
+    /// Submission of the remaining commands.
+    Submit,
+}
+
+/// Times the phases of each frame and logs a warning for the frames
+/// exceeding their budget.
+pub(crate) struct Watchdog {
+    pub budget: Budget,
+    /// Whether the context supports `GL_TIME_ELAPSED` queries.
+    timer_queries: bool,
+    /// Whether a frame is being timed, phases outside of frames are ignored.
+    timing: bool,
+    frame: u64,
+    phase_start: Instant,
+    cpu_times: Vec<(Phase, f32)>,
+    queries: Vec<(Phase, u32)>,
+    pending: VecDeque<(u64, Vec<(Phase, u32)>)>,
+    free_queries: Vec<u32>,
+}
+
+fn seconds(start: Instant) -> f32 {
+    let dt = start.elapsed();
+    dt.as_secs() as f32 + 1e-9 * dt.subsec_nanos() as f32
+}
+
+/// Logs a warning with the time of each phase if `times` add up to more
+/// than `limit`.
+fn check(
+    kind: &str,
+    frame: u64,
+    times: &[(Phase, f32)],
+    limit: f32,
+) {
+    let total = times.iter().fold(0.0, |sum, &(_, time)| sum + time);
+    if total <= limit {
+        return;
+    }
+    let worst = times
+        .iter()
+        .fold(None, |worst: Option<(Phase, f32)>, &(phase, time)| match worst {
+            Some((_, worst_time)) if worst_time >= time => worst,
+            _ => Some((phase, time)),
+        });
+    let breakdown = times
+        .iter()
+        .map(|&(phase, time)| format!("{:?}={:.2}ms", phase, 1e3 * time))
+        .collect::<Vec<_>>()
+        .join(", ");
+    warn!(
+        "Frame {} exceeded the {} budget: total={:.2}ms budget={:.2}ms worst={:?} breakdown=[{}]",
+        frame,
+        kind,
+        1e3 * total,
+        1e3 * limit,
+        worst.map(|(phase, _)| phase),
+        breakdown,
+    );
+}
+
+impl Watchdog {
+    pub fn new(
+        budget: Budget,
+        device: &back::Device,
+    ) -> Self {
+        let info = device.get_info();
+        let timer_queries = info.is_version_or_extension_supported(3, 3, "GL_ARB_timer_query") ||
+            info.is_extension_supported("GL_EXT_disjoint_timer_query");
+        if budget.gpu.is_some() && !timer_queries {
+            warn!("Timer queries aren't supported, the GPU budget is left unchecked");
+        }
+        Watchdog {
+            budget,
+            timer_queries,
+            timing: false,
+            frame: 0,
+            phase_start: Instant::now(),
+            cpu_times: Vec::new(),
+            queries: Vec::new(),
+            pending: VecDeque::new(),
+            free_queries: Vec::new(),
+        }
+    }
+
+    /// Starts timing a new frame.
+    pub fn begin_frame(&mut self) {
+        self.frame += 1;
+        self.timing = true;
+        self.cpu_times.clear();
+        self.phase_start = Instant::now();
+    }
+
+    /// Ends the current phase. When the GPU time is checked, the commands
+    /// recorded during the phase are submitted inside a timer query.
+    pub fn end_phase(
+        &mut self,
+        phase: Phase,
+        device: &mut back::Device,
+        encoder: &mut gfx::Encoder<back::Resources, back::CommandBuffer>,
+    ) {
+        if !self.timing {
+            return;
+        }
+        if self.budget.gpu.is_some() && self.timer_queries {
+            let query = match self.free_queries.pop() {
+                Some(query) => query,
+                None => {
+                    let mut query: u32 = 0;
+                    unsafe {
+                        device.with_gl(|gl| gl.GenQueries(1, &mut query));
+                    }
+                    query
+                }
+            };
+            unsafe {
+                device.with_gl(|gl| gl.BeginQuery(TIME_ELAPSED, query));
+            }
+            encoder.flush(device);
+            unsafe {
+                device.with_gl(|gl| gl.EndQuery(TIME_ELAPSED));
+            }
+            self.queries.push((phase, query));
+        }
+        self.cpu_times.push((phase, seconds(self.phase_start)));
+        self.phase_start = Instant::now();
+    }
+
+    /// Checks the CPU time of the current frame and the GPU time of the
+    /// previous frames whose timer queries are done.
+    pub fn end_frame(
+        &mut self,
+        device: &mut back::Device,
+    ) {
+        self.timing = false;
+        if let Some(limit) = self.budget.cpu {
+            check("CPU", self.frame, &self.cpu_times, limit);
+        }
+        if !self.queries.is_empty() {
+            let queries = self.queries.drain(..).collect();
+            self.pending.push_back((self.frame, queries));
+        }
+        while self.pending.len() > MAX_PENDING_FRAMES {
+            let (_, queries) = self.pending.pop_front().unwrap();
+            self.free_queries.extend(queries.into_iter().map(|(_, query)| query));
+        }
+
+        loop {
+            let available = match self.pending.front() {
+                Some(&(_, ref queries)) => {
+                    let last = queries.last().unwrap().1;
+                    let mut available: i32 = 0;
+                    unsafe {
+                        device.with_gl(|gl| gl.GetQueryObjectiv(last, QUERY_RESULT_AVAILABLE, &mut available));
+                    }
+                    available != 0
+                }
+                None => false,
+            };
+            if !available {
+                break;
+            }
+            let (frame, queries) = self.pending.pop_front().unwrap();
+            let mut times = Vec::with_capacity(queries.len());
+            for &(phase, query) in &queries {
+                let mut nanos: u64 = 0;
+                unsafe {
+                    device.with_gl(|gl| gl.GetQueryObjectui64v(query, QUERY_RESULT, &mut nanos));
+                }
+                times.push((phase, 1e-9 * nanos as f32));
+            }
+            if let Some(limit) = self.budget.gpu {
+                check("GPU", frame, &times, limit);
+            }
+            self.free_queries.extend(queries.into_iter().map(|(_, query)| query));
+        }
+    }
+
+    /// Deletes the timer queries.
+    pub fn release(
+        &mut self,
+        device: &mut back::Device,
+    ) {
+        let mut queries = self.free_queries.drain(..).collect::<Vec<_>>();
+        queries.extend(self.queries.drain(..).map(|(_, query)| query));
+        for (_, pending) in self.pending.drain(..) {
+            queries.extend(pending.into_iter().map(|(_, query)| query));
+        }
+        if !queries.is_empty() {
+            unsafe {
+                device.with_gl(|gl| gl.DeleteQueries(queries.len() as i32, queries.as_ptr()));
+            }
+        }
+    }
+}
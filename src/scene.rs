@@ -1,14 +1,20 @@
 //! `Scene` and `SyncGuard` structures.
 
+use cgmath;
+use mint;
 use node;
-use color::Color;
+use color::{self, Color};
 use hub::{Hub, HubPtr, SubNode};
+use math::{self, Plane};
 use object::{Base, DowncastObject, Group, Object};
+use portal::Cells;
+use spatial::Bvh;
 use texture::{CubeMap, Texture};
 
+use std::collections::HashMap;
 use std::mem;
 use std::marker::PhantomData;
-use std::sync::MutexGuard;
+use std::sync::{Mutex, MutexGuard};
 
 
 /// Background type.
@@ -21,6 +27,192 @@ pub enum Background {
     Texture(Texture<[f32; 4]>),
     /// Skybox
     Skybox(CubeMap<[f32; 4]>),
+    /// Vertical gradient covering the whole screen.
+    Gradient {
+        /// Color at the top edge of the screen.
+        top: Color,
+        /// Color at the bottom edge of the screen.
+        bottom: Color,
+    },
+}
+
+/// Scene-level lighting and environment settings.
+///
+/// The environment map settings are applied to the [`Background::Skybox`] and to
+/// the image-based lighting of PBR materials by [`map`], so that rotating or
+/// dimming the environment relights the scene consistently. The background itself is chosen
+/// with [`Scene::background`].
+///
+/// [`Background::Skybox`]: enum.Background.html#variant.Skybox
+/// [`Scene::background`]: struct.Scene.html#structfield.background
+/// [`map`]: #structfield.map
+#[derive(Clone, Debug, PartialEq)]
+pub struct Environment {
+    /// Color of the ambient light applied uniformly to all lit materials.
+    ///
+    /// Default: `WHITE`.
+    pub ambient_color: Color,
+
+    /// Intensity of the ambient light.
+    ///
+    /// Default: `0.0`.
+    pub ambient_intensity: f32,
+
+    /// Brightness multiplier applied to the environment map.
+    ///
+    /// Default: `1.0`.
+    pub intensity: f32,
+
+    /// Rotation of the environment map about the world Y axis, in radians.
+    ///
+    /// Default: `0.0`.
+    pub rotation: f32,
+
+    /// Environment map lighting PBR materials with reflections and diffuse
+    /// ambient light, usually the cube map of the skybox.
+    ///
+    /// Rougher materials sample lower mipmap levels of the map, down to its
+    /// last level, so a map without mipmaps gives sharp reflections regardless
+    /// of roughness. Maps loaded with [`Factory::load_cubemap`] get a full
+    /// mipmap chain.
+    ///
+    /// [`Factory::load_cubemap`]: ../factory/struct.Factory.html#method.load_cubemap
+    ///
+    /// Default: `None`.
+    pub map: Option<CubeMap<[f32; 4]>>,
+
+    /// Fog applied to the meshes of the scene, if any.
+    ///
+    /// The fog usually matches the background color, so that distant geometry
+    /// fades into it.
+    ///
+    /// Default: `None`.
+    pub fog: Option<Fog>,
+}
+
+impl Default for Environment {
+    fn default() -> Self {
+        Environment {
+            ambient_color: color::WHITE,
+            ambient_intensity: 0.0,
+            intensity: 1.0,
+            rotation: 0.0,
+            map: None,
+            fog: None,
+        }
+    }
+}
+
+/// A plane cutting through the scene, used to look inside solid objects.
+///
+/// Geometry behind the plane, i.e. on the opposite side from where its
+/// normal points, is not rendered.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Section {
+    /// Plane of the cut, facing the geometry that is kept, e.g. created with
+    /// [`Plane::from_point_normal`](../math/struct.Plane.html#method.from_point_normal).
+    pub plane: Plane,
+
+    /// Color of the caps filling the cut, if any.
+    ///
+    /// Caps are drawn where back faces of a mesh are seen through the cut, so
+    /// they are only visible on closed meshes rendered without back face
+    /// culling, see [`Mesh::set_render_state`].
+    ///
+    /// [`Mesh::set_render_state`]: ../struct.Mesh.html#method.set_render_state
+    pub cap: Option<Color>,
+}
+
+/// Fog blending distant geometry into a color.
+///
+/// Set with the [`fog`](struct.Environment.html#structfield.fog) of the scene
+/// environment. Meshes opt out with the `fog` field of their [`material::State`].
+///
+/// [`material::State`]: ../material/struct.State.html
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Fog {
+    /// Fog growing linearly from no fog at `near` to full fog at `far`, in
+    /// distances from the camera.
+    Linear {
+        /// Distance where the fog starts.
+        near: f32,
+        /// Distance beyond which geometry has the color of the fog.
+        far: f32,
+        /// Color of the fog.
+        color: Color,
+    },
+    /// Fog growing exponentially with the square of the distance from the
+    /// camera, multiplied by `density`.
+    Exp2 {
+        /// Density of the fog.
+        density: f32,
+        /// Color of the fog.
+        color: Color,
+    },
+}
+
+/// World transforms of all objects in a scene, captured at a point in time.
+///
+/// A snapshot doesn't borrow or lock the scene, so it can be sent to other threads,
+/// e.g. for audio, physics or networking. See [`Scene::world_transforms`].
+///
+/// [`Scene::world_transforms`]: struct.Scene.html#method.world_transforms
+#[derive(Clone, Debug, Default)]
+pub struct WorldTransforms {
+    matrices: HashMap<Base, mint::ColumnMatrix4<f32>>,
+}
+
+impl WorldTransforms {
+    /// Returns the world matrix of `object`, if it was in the scene when the
+    /// snapshot was taken.
+    pub fn get<T: Object>(
+        &self,
+        object: &T,
+    ) -> Option<mint::ColumnMatrix4<f32>> {
+        self.matrices.get(object.as_ref()).cloned()
+    }
+
+    /// Returns an iterator over the objects and their world matrices.
+    pub fn iter<'a>(&'a self) -> impl Iterator<Item = (&'a Base, &'a mint::ColumnMatrix4<f32>)> + 'a {
+        self.matrices.iter()
+    }
+
+    /// Returns the number of objects in the snapshot.
+    pub fn len(&self) -> usize {
+        self.matrices.len()
+    }
+
+    /// Returns `true` if the snapshot contains no objects.
+    pub fn is_empty(&self) -> bool {
+        self.matrices.is_empty()
+    }
+}
+
+/// Scene graph counters of the last rendered frame.
+///
+/// Counters cover the period between the starts of two consecutive calls to
+/// [`Window::render`], so that applications can tell whether the messages they
+/// send to the scene are the bottleneck.
+///
+/// [`Window::render`]: ../window/struct.Window.html#method.render
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Stats {
+    /// Number of queued messages processed, e.g. transform or material changes.
+    pub messages: usize,
+
+    /// Number of messages skipped because a later message to the same object
+    /// overwrote their effect, e.g. repeated transform changes.
+    pub coalesced: usize,
+
+    /// Time spent processing messages, in seconds.
+    pub process_time: f32,
+
+    /// Number of nodes visited while updating the world transforms of the
+    /// scene graph.
+    pub nodes_walked: usize,
+
+    /// Time spent updating the world transforms, in seconds.
+    pub transform_time: f32,
 }
 
 /// The root node of a tree of game objects that may be rendered by a [`Camera`].
@@ -31,9 +223,24 @@ pub struct Scene {
     pub(crate) first_child: Option<node::NodePointer>,
     /// See [`Background`](struct.Background.html).
     pub background: Background,
+    /// See [`Environment`](struct.Environment.html).
+    pub environment: Environment,
+    /// Section plane clipping the scene meshes, if any.
+    /// See [`Section`](struct.Section.html).
+    pub section: Option<Section>,
+    /// Cells and portals culling the parts of the scene hidden from the camera,
+    /// if any. See the [`portal`](../portal/index.html) module.
+    pub portals: Option<Cells>,
+    /// Bounding volume hierarchy over the world bounds of the visible meshes.
+    pub(crate) index: Mutex<Bvh>,
 }
 
 impl Scene {
+    /// Returns the scene graph counters of the last rendered frame.
+    pub fn stats(&self) -> Stats {
+        self.hub.lock().unwrap().frame_stats()
+    }
+
     /// Add new [`Base`](struct.Base.html) to the scene.
     pub fn add<P>(
         &mut self,
@@ -43,6 +250,7 @@ impl Scene {
     {
         let mut hub = self.hub.lock().unwrap();
         let node_ptr = child_base.as_ref().node.clone();
+        hub.touch_node(&node_ptr);
         let child = &mut hub[child_base];
 
         if child.next_sibling.is_some() {
@@ -60,26 +268,10 @@ impl Scene {
     ) where
         P: AsRef<Base>,
     {
-        let target_maybe = Some(child_base.as_ref().node.clone());
         let mut hub = self.hub.lock().unwrap();
-        let next_sibling = hub[child_base].next_sibling.clone();
-
-        if self.first_child == target_maybe {
-            self.first_child = next_sibling;
-            return;
+        if !hub.unlink(&mut self.first_child, &child_base.as_ref().node) {
+            error!("Unable to find child for removal");
         }
-
-        let mut cur_ptr = self.first_child.clone();
-        while let Some(ptr) = cur_ptr.take() {
-            let node = &mut hub.nodes[&ptr];
-            if node.next_sibling == target_maybe {
-                node.next_sibling = next_sibling;
-                return;
-            }
-            cur_ptr = node.next_sibling.clone(); //TODO: avoid clone
-        }
-
-        error!("Unable to find child for removal");
     }
 }
 
@@ -122,7 +314,7 @@ impl Scene {
 /// # use three::Object;
 /// # let mut win = three::Window::new("SyncGuard example");
 /// # let geometry = three::Geometry::default();
-/// # let material = three::material::Basic { color: three::color::RED, map: None };
+/// # let material = three::material::Basic { color: three::color::RED, map: None, opacity: 1.0 };
 /// # let mesh = win.factory.mesh(geometry, material);
 /// # let enemy = Enemy { mesh, is_visible: true };
 /// # win.scene.add(&enemy);
@@ -179,10 +371,18 @@ impl<'a> SyncGuard<'a> {
             .walk_all(&self.scene.first_child)
             .find(|wn| wn.node as *const _ == internal)
             .expect("Unable to find objects for world resolve!");
+        let unstretched = (wn.world_transform.into(), [1.0, 1.0, 1.0].into());
+        let (transform, nonuniform_scale) = if wn.stretched {
+            math::decompose(wn.world_matrix()).unwrap_or(unstretched)
+        } else {
+            unstretched
+        };
         node::Node {
             visible: wn.world_visible,
+            opacity: wn.world_opacity,
             name: wn.node.name.clone(),
-            transform: wn.world_transform.into(),
+            transform,
+            nonuniform_scale,
             material: match wn.node.sub_node {
                 SubNode::Visual(ref mat, _, _) => Some(mat.clone()),
                 _ => None,
@@ -230,6 +430,35 @@ impl<'a> SyncGuard<'a> {
         object.resolve_data(self)
     }
 
+    /// Returns `true` if `object` was drawn by a call to [`Window::render`]
+    /// since the last call to [`Window::update`].
+    ///
+    /// Objects that are hidden, detached from the scene, or not visual (such as groups
+    /// and lights) always return `false`.
+    ///
+    /// [`Window::render`]: ../window/struct.Window.html#method.render
+    /// [`Window::update`]: ../window/struct.Window.html#method.update
+    pub fn was_rendered<T: 'a + Object>(
+        &self,
+        object: &T,
+    ) -> bool {
+        self.hub[object].rendered
+    }
+
+    /// Returns an iterator of all the objects in the scene that were drawn by a call
+    /// to [`Window::render`] since the last call to [`Window::update`].
+    ///
+    /// [`Window::render`]: ../window/struct.Window.html#method.render
+    /// [`Window::update`]: ../window/struct.Window.html#method.update
+    pub fn visible_objects(&'a self) -> impl Iterator<Item = Base> + 'a {
+        let guard = &*self;
+        self
+            .hub
+            .walk(&self.scene.first_child)
+            .filter(|walked| walked.node.rendered)
+            .map(move |walked| guard.hub.upgrade_ptr(walked.node_ptr.clone()))
+    }
+
     /// Returns an iterator that walks all the objects in `root`'s hierarchy.
     ///
     /// Walks the children of `root`, recursively walking the children of any [`Group`] objects
@@ -369,4 +598,62 @@ impl Scene {
         hub.process_messages();
         SyncGuard { scene: self, hub }
     }
+
+    /// Captures the world transforms of all objects in the scene, including
+    /// invisible ones.
+    ///
+    /// Pending messages are processed first, so the snapshot reflects all changes
+    /// made so far.
+    pub fn world_transforms(&mut self) -> WorldTransforms {
+        let mut hub = self.hub.lock().unwrap();
+        hub.process_messages();
+        let matrices = hub
+            .walk_all(&self.first_child)
+            .map(|w| (w.node_ptr.clone(), w.world_matrix().into()))
+            .collect::<Vec<_>>();
+        WorldTransforms {
+            matrices: matrices
+                .into_iter()
+                .map(|(ptr, matrix)| (hub.upgrade_ptr(ptr), matrix))
+                .collect(),
+        }
+    }
+
+    /// Sets the local transforms of many objects at once.
+    ///
+    /// The transforms are written directly under a single lock of the scene, instead
+    /// of queuing one message per object as [`Object::set_transform`] does. This is
+    /// intended for applications synchronizing a large number of objects every frame,
+    /// e.g. from an ECS or a physics engine.
+    ///
+    /// Messages queued before this call are processed first, so that they don't
+    /// override the new transforms.
+    ///
+    /// # Panics
+    /// Panics if an object was created by another `Scene`'s factory.
+    ///
+    /// [`Object::set_transform`]: ../object/trait.Object.html#method.set_transform
+    pub fn set_transforms<'a, T, I>(
+        &mut self,
+        transforms: I,
+    ) where
+        T: 'a + Object,
+        I: IntoIterator<Item = (&'a T, node::Transform)>,
+    {
+        let mut hub = self.hub.lock().unwrap();
+        hub.process_messages();
+        for (object, transform) in transforms {
+            let position: mint::Vector3<f32> = [
+                transform.position.x,
+                transform.position.y,
+                transform.position.z,
+            ].into();
+            hub[object].transform = cgmath::Decomposed {
+                disp: position.into(),
+                rot: transform.orientation.into(),
+                scale: transform.scale,
+            };
+            hub.touch_node(&object.as_ref().node);
+        }
+    }
 }
@@ -1,8 +1,30 @@
 //! Mesh skinning.
 
+use animation::Lod;
+use cgmath::{InnerSpace, Matrix4, Vector4, Zero};
+use geometry::{Joints, Shape};
+use hub::Operation;
 use mint;
 use object::{self, ObjectType};
 
+quick_error! {
+    #[doc = "Error encountered when skinning a mesh on the CPU."]
+    #[derive(Debug)]
+    pub enum SkinningError {
+        #[doc = "A vertex is weighted by a joint the skeleton doesn't have."]
+        JointIndex(vertex: usize, joint: i32, bones: usize) {
+            description("joint index out of range")
+            display("vertex {} uses joint {}, but the skeleton has {} bones", vertex, joint, bones)
+        }
+
+        #[doc = "The joint data covers fewer vertices than the shape."]
+        MissingJoints(vertices: usize, joints: usize) {
+            description("missing joint data")
+            display("the shape has {} vertices, but only {} have joint data", vertices, joints)
+        }
+    }
+}
+
 /// Contains array of bones.
 #[derive(Clone, Debug)]
 pub struct Skeleton {
@@ -11,6 +33,21 @@ pub struct Skeleton {
 three_object!(Skeleton::object);
 derive_DowncastObject!(Skeleton => ObjectType::Skeleton);
 
+impl Skeleton {
+    /// Sets the distance-based throttling of the bone palette upload.
+    ///
+    /// Skeletons further away from the camera have their bone matrices
+    /// recomputed less often, or not at all. See [`Lod`].
+    ///
+    /// [`Lod`]: ../animation/struct.Lod.html
+    pub fn set_lod(
+        &self,
+        lod: Lod,
+    ) {
+        self.as_ref().send(Operation::SetSkeletonLod(lod));
+    }
+}
+
 /// A single bone that forms one component of a [`Skeleton`].
 ///
 /// [`Skeleton`]: struct.Skeleton.html
@@ -23,3 +60,46 @@ derive_DowncastObject!(Bone => ObjectType::Bone);
 
 /// A matrix defining how bind mesh nodes to a bone.
 pub type InverseBindMatrix = mint::ColumnMatrix4<f32>;
+
+/// Applies linear blend skinning to `shape` with the skinning matrices of the
+/// skeleton, given in bone index order.
+///
+/// Shapes without joint data are left unchanged.
+pub(crate) fn skin_shape(
+    shape: &Shape,
+    joints: &Joints,
+    matrices: &[Matrix4<f32>],
+) -> Result<Shape, SkinningError> {
+    if joints.indices.is_empty() || joints.weights.is_empty() {
+        return Ok(shape.clone());
+    }
+    let covered = joints.indices.len().min(joints.weights.len());
+    if covered < shape.vertices.len() {
+        return Err(SkinningError::MissingJoints(shape.vertices.len(), covered));
+    }
+    let mut skinned = Shape {
+        vertices: Vec::with_capacity(shape.vertices.len()),
+        normals: Vec::with_capacity(shape.normals.len()),
+        tangents: shape.tangents.clone(),
+    };
+    for (i, vertex) in shape.vertices.iter().enumerate() {
+        let mut mx = Matrix4::zero();
+        for (&index, &weight) in joints.indices[i].iter().zip(joints.weights[i].iter()) {
+            if weight == 0.0 {
+                continue;
+            }
+            match matrices.get(index as usize) {
+                Some(matrix) if index >= 0 => mx = mx + matrix * weight,
+                _ => return Err(SkinningError::JointIndex(i, index, matrices.len())),
+            }
+        }
+        let pos = mx * Vector4::new(vertex.x, vertex.y, vertex.z, 1.0);
+        skinned.vertices.push([pos.x, pos.y, pos.z].into());
+        if let Some(normal) = shape.normals.get(i) {
+            let n = (mx * Vector4::new(normal.x, normal.y, normal.z, 0.0)).truncate();
+            let n = if n.magnitude2() > 0.0 { n.normalize() } else { n };
+            skinned.normals.push(n.into());
+        }
+    }
+    Ok(skinned)
+}
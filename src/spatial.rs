@@ -0,0 +1,514 @@
+//! Dynamic bounding volume hierarchy over the world bounds of scene meshes.
+//!
+//! The tree stores bounds enlarged by a margin, so that objects moving a
+//! little don't require updating it. It is refreshed lazily, only when the
+//! hub reports changes, and only the leaves of the meshes that may have moved
+//! since the last refresh are refitted. It is shared by frustum culling,
+//! picking and proximity queries.
+
+use cgmath::{InnerSpace, Matrix4, Point3, Vector3};
+
+use collision::Aabb;
+use hub::{Hub, SubNode, WalkedNode};
+use math::Frustum;
+use node::{NodePointer, TransformInternal};
+
+use std::collections::{HashMap, HashSet};
+use std::time;
+
+/// Distance the bounds stored in the tree extend beyond the bounds of a mesh.
+const MARGIN: f32 = 0.1;
+
+#[derive(Clone, Copy, Debug)]
+struct Bounds {
+    min: Point3<f32>,
+    max: Point3<f32>,
+}
+
+impl Bounds {
+    fn union(
+        &self,
+        other: &Bounds,
+    ) -> Bounds {
+        Bounds {
+            min: Point3::new(self.min.x.min(other.min.x), self.min.y.min(other.min.y), self.min.z.min(other.min.z)),
+            max: Point3::new(self.max.x.max(other.max.x), self.max.y.max(other.max.y), self.max.z.max(other.max.z)),
+        }
+    }
+
+    fn contains(
+        &self,
+        other: &Bounds,
+    ) -> bool {
+        (0 .. 3).all(|i| self.min[i] <= other.min[i] && other.max[i] <= self.max[i])
+    }
+
+    fn overlaps(
+        &self,
+        other: &Bounds,
+    ) -> bool {
+        (0 .. 3).all(|i| self.min[i] <= other.max[i] && other.min[i] <= self.max[i])
+    }
+
+    /// Half the surface area, the cost minimized when inserting into the tree.
+    fn area(&self) -> f32 {
+        let d = self.max - self.min;
+        d.x * d.y + d.y * d.z + d.z * d.x
+    }
+
+    fn enlarged(
+        &self,
+        margin: f32,
+    ) -> Bounds {
+        let margin = Vector3::new(margin, margin, margin);
+        Bounds {
+            min: self.min + -margin,
+            max: self.max + margin,
+        }
+    }
+
+    fn distance2(
+        &self,
+        point: Point3<f32>,
+    ) -> f32 {
+        let closest = Point3::new(
+            point.x.max(self.min.x).min(self.max.x),
+            point.y.max(self.min.y).min(self.max.y),
+            point.z.max(self.min.z).min(self.max.z),
+        );
+        (closest - point).magnitude2()
+    }
+
+    /// Slab test of the half-line from `origin` along `direction`.
+    fn hit_by_ray(
+        &self,
+        origin: Point3<f32>,
+        direction: Vector3<f32>,
+    ) -> bool {
+        let mut near = 0.0f32;
+        let mut far = ::std::f32::INFINITY;
+        for i in 0 .. 3 {
+            if direction[i].abs() < 1e-12 {
+                if origin[i] < self.min[i] || origin[i] > self.max[i] {
+                    return false;
+                }
+                continue;
+            }
+            let t0 = (self.min[i] - origin[i]) / direction[i];
+            let t1 = (self.max[i] - origin[i]) / direction[i];
+            near = near.max(t0.min(t1));
+            far = far.min(t0.max(t1));
+            if near > far {
+                return false;
+            }
+        }
+        true
+    }
+
+    fn to_aabb(&self) -> Aabb {
+        Aabb {
+            min: self.min.into(),
+            max: self.max.into(),
+        }
+    }
+}
+
+impl From<Aabb> for Bounds {
+    fn from(aabb: Aabb) -> Self {
+        Bounds {
+            min: aabb.min.into(),
+            max: aabb.max.into(),
+        }
+    }
+}
+
+/// A mesh stored in the tree, with its world state at the last refresh.
+#[derive(Clone, Debug)]
+pub(crate) struct Entry {
+    pub(crate) node: NodePointer,
+    /// Position of the mesh in the walk of the scene.
+    order: usize,
+    world_opacity: f32,
+    world_transform: TransformInternal,
+    world_stretched: Matrix4<f32>,
+    stretched: bool,
+}
+
+impl Entry {
+    fn new(
+        order: usize,
+        w: &WalkedNode,
+    ) -> Self {
+        Entry {
+            node: w.node_ptr.clone(),
+            order,
+            world_opacity: w.world_opacity,
+            world_transform: w.world_transform,
+            world_stretched: w.world_stretched,
+            stretched: w.stretched,
+        }
+    }
+
+    /// Returns the mesh as a node walked in `hub`.
+    pub(crate) fn walked<'a>(
+        &self,
+        hub: &'a Hub,
+    ) -> WalkedNode<'a> {
+        let node = &hub.nodes[&self.node];
+        WalkedNode {
+            node_ptr: self.node.clone(),
+            node,
+            world_visible: true,
+            world_opacity: self.world_opacity,
+            world_transform: self.world_transform,
+            world_stretched: self.world_stretched,
+            stretched: self.stretched,
+            revision: node.revision,
+        }
+    }
+
+    fn candidate(&self) -> Candidate {
+        (self.node.clone(), self.world_stretched)
+    }
+}
+
+#[derive(Debug)]
+enum Kind {
+    Leaf {
+        entry: Entry,
+        /// Exact world bounds of the mesh.
+        bounds: Bounds,
+    },
+    Branch([usize; 2]),
+    /// Unused slot, kept for reuse.
+    Free,
+}
+
+#[derive(Debug)]
+struct TreeNode {
+    /// Bounds containing the children, or the enlarged bounds of a leaf.
+    bounds: Bounds,
+    parent: Option<usize>,
+    kind: Kind,
+}
+
+/// A mesh returned by a query, with its world matrix.
+pub(crate) type Candidate = (NodePointer, Matrix4<f32>);
+
+/// Bounding volume hierarchy over the visible meshes of a scene.
+#[derive(Debug, Default)]
+pub(crate) struct Bvh {
+    nodes: Vec<TreeNode>,
+    free: Vec<usize>,
+    root: Option<usize>,
+    leaves: HashMap<NodePointer, usize>,
+    /// Meshes without bounds or with a skeleton, returned by all queries.
+    unbounded: Vec<Entry>,
+    /// Revision of the hub the tree was last refreshed at.
+    revision: Option<u64>,
+}
+
+impl Bvh {
+    /// Brings the tree up to date with the visible meshes under `first_child`,
+    /// unless nothing changed since the last refresh.
+    ///
+    /// Only the meshes that may have moved since the last refresh get their
+    /// world bounds recomputed, the others keep their leaves as they are.
+    pub(crate) fn refresh(
+        &mut self,
+        hub: &Hub,
+        first_child: &Option<NodePointer>,
+    ) {
+        let refreshed = self.revision;
+        if refreshed == Some(hub.revision()) {
+            return;
+        }
+        self.revision = Some(hub.revision());
+        self.unbounded.clear();
+        let start = time::Instant::now();
+        let mut walked = 0;
+        let mut seen = HashSet::new();
+        for (order, w) in hub.walk(first_child).enumerate() {
+            walked += 1;
+            let (gpu_data, skeleton) = match w.node.sub_node {
+                SubNode::Visual(_, ref gpu_data, ref skeleton) => (gpu_data, skeleton),
+                _ => continue,
+            };
+            let entry = Entry::new(order, &w);
+            match gpu_data.bounds {
+                Some(bounds) if skeleton.is_none() => {
+                    seen.insert(w.node_ptr.clone());
+                    let moved = refreshed.map_or(true, |revision| w.revision > revision);
+                    match self.leaves.get(&w.node_ptr) {
+                        Some(&leaf) if !moved => {
+                            if let Kind::Leaf { entry: ref mut old, .. } = self.nodes[leaf].kind {
+                                *old = entry;
+                            }
+                        }
+                        _ => {
+                            let bounds = Bounds::from(bounds.transformed(w.world_matrix()));
+                            self.update(entry, bounds);
+                        }
+                    }
+                }
+                _ => self.unbounded.push(entry),
+            }
+        }
+        let stale = self.leaves
+            .keys()
+            .filter(|node| !seen.contains(*node))
+            .cloned()
+            .collect::<Vec<_>>();
+        for node in stale {
+            let leaf = self.leaves.remove(&node).unwrap();
+            self.remove_leaf(leaf);
+            self.release(leaf);
+        }
+        hub.record_transform_update(walked, start.elapsed());
+    }
+
+    /// Returns the meshes that may be inside `frustum`, in scene order.
+    pub(crate) fn query_frustum(
+        &self,
+        frustum: &Frustum,
+    ) -> Vec<Entry> {
+        let mut found = self.query(|bounds| frustum.intersects_aabb(&bounds.to_aabb()));
+        found.sort_by_key(|entry| entry.order);
+        found
+    }
+
+    /// Returns all the meshes, in scene order.
+    pub(crate) fn query_all(&self) -> Vec<Entry> {
+        let mut found = self.query(|_| true);
+        found.sort_by_key(|entry| entry.order);
+        found
+    }
+
+    /// Returns the meshes whose bounds are hit by the half-line from `origin`
+    /// along `direction`.
+    pub(crate) fn query_ray(
+        &self,
+        origin: Point3<f32>,
+        direction: Vector3<f32>,
+    ) -> Vec<Candidate> {
+        self.query(|bounds| bounds.hit_by_ray(origin, direction))
+            .iter()
+            .map(Entry::candidate)
+            .collect()
+    }
+
+    /// Returns the meshes whose bounds overlap `aabb`.
+    pub(crate) fn query_aabb(
+        &self,
+        aabb: &Aabb,
+    ) -> Vec<Candidate> {
+        let query = Bounds::from(*aabb);
+        self.query(|bounds| bounds.overlaps(&query))
+            .iter()
+            .map(Entry::candidate)
+            .collect()
+    }
+
+    /// Returns the meshes whose bounds are within `radius` of `center`.
+    pub(crate) fn query_sphere(
+        &self,
+        center: Point3<f32>,
+        radius: f32,
+    ) -> Vec<Candidate> {
+        self.query(|bounds| bounds.distance2(center) <= radius * radius)
+            .iter()
+            .map(Entry::candidate)
+            .collect()
+    }
+
+    fn query<F>(
+        &self,
+        test: F,
+    ) -> Vec<Entry>
+    where
+        F: Fn(&Bounds) -> bool,
+    {
+        let mut found = self.unbounded.clone();
+        let mut stack = self.root.into_iter().collect::<Vec<_>>();
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+            if !test(&node.bounds) {
+                continue;
+            }
+            match node.kind {
+                Kind::Leaf { ref entry, ref bounds } => {
+                    if test(bounds) {
+                        found.push(entry.clone());
+                    }
+                }
+                Kind::Branch(children) => stack.extend_from_slice(&children),
+                Kind::Free => {}
+            }
+        }
+        found
+    }
+
+    /// Stores the exact world `bounds` of the mesh of `entry`, moving its leaf
+    /// only if they leave the enlarged bounds of the leaf.
+    fn update(
+        &mut self,
+        entry: Entry,
+        bounds: Bounds,
+    ) {
+        if let Some(&leaf) = self.leaves.get(&entry.node) {
+            let moved = !self.nodes[leaf].bounds.contains(&bounds);
+            if moved {
+                self.remove_leaf(leaf);
+                self.nodes[leaf].bounds = bounds.enlarged(MARGIN);
+            }
+            self.nodes[leaf].kind = Kind::Leaf { entry, bounds };
+            if moved {
+                self.insert_leaf(leaf);
+            }
+            return;
+        }
+        let node = entry.node.clone();
+        let tree_node = TreeNode {
+            bounds: bounds.enlarged(MARGIN),
+            parent: None,
+            kind: Kind::Leaf { entry, bounds },
+        };
+        let leaf = self.allocate(tree_node);
+        self.leaves.insert(node, leaf);
+        self.insert_leaf(leaf);
+    }
+
+    fn allocate(
+        &mut self,
+        node: TreeNode,
+    ) -> usize {
+        match self.free.pop() {
+            Some(index) => {
+                self.nodes[index] = node;
+                index
+            }
+            None => {
+                self.nodes.push(node);
+                self.nodes.len() - 1
+            }
+        }
+    }
+
+    /// Inserts `leaf` next to the node where it increases the area of the
+    /// tree the least.
+    fn insert_leaf(
+        &mut self,
+        leaf: usize,
+    ) {
+        let bounds = self.nodes[leaf].bounds;
+        let mut index = match self.root {
+            Some(root) => root,
+            None => {
+                self.nodes[leaf].parent = None;
+                self.root = Some(leaf);
+                return;
+            }
+        };
+        while let Kind::Branch(children) = self.nodes[index].kind {
+            let area = self.nodes[index].bounds.area();
+            let combined = self.nodes[index].bounds.union(&bounds).area();
+            // cost of a new parent for this node and the leaf, and the
+            // increase of area it causes to the ancestors
+            let cost = 2.0 * combined;
+            let inheritance = 2.0 * (combined - area);
+            let child_cost = |child: usize| {
+                let node = &self.nodes[child];
+                let union = node.bounds.union(&bounds).area();
+                match node.kind {
+                    Kind::Leaf { .. } => union + inheritance,
+                    _ => union - node.bounds.area() + inheritance,
+                }
+            };
+            let (cost0, cost1) = (child_cost(children[0]), child_cost(children[1]));
+            if cost < cost0 && cost < cost1 {
+                break;
+            }
+            index = if cost0 < cost1 { children[0] } else { children[1] };
+        }
+
+        let sibling = index;
+        let old_parent = self.nodes[sibling].parent;
+        let new_parent = self.allocate(TreeNode {
+            bounds: self.nodes[sibling].bounds.union(&bounds),
+            parent: old_parent,
+            kind: Kind::Branch([sibling, leaf]),
+        });
+        self.nodes[sibling].parent = Some(new_parent);
+        self.nodes[leaf].parent = Some(new_parent);
+        match old_parent {
+            Some(parent) => {
+                self.replace_child(parent, sibling, new_parent);
+                self.refit(Some(parent));
+            }
+            None => self.root = Some(new_parent),
+        }
+    }
+
+    /// Detaches `leaf` from the tree, freeing its parent.
+    fn remove_leaf(
+        &mut self,
+        leaf: usize,
+    ) {
+        if self.root == Some(leaf) {
+            self.root = None;
+            return;
+        }
+        let parent = self.nodes[leaf].parent.unwrap();
+        let sibling = match self.nodes[parent].kind {
+            Kind::Branch(children) => if children[0] == leaf { children[1] } else { children[0] },
+            _ => unreachable!(),
+        };
+        let grandparent = self.nodes[parent].parent;
+        self.nodes[sibling].parent = grandparent;
+        match grandparent {
+            Some(grandparent) => {
+                self.replace_child(grandparent, parent, sibling);
+                self.refit(Some(grandparent));
+            }
+            None => self.root = Some(sibling),
+        }
+        self.release(parent);
+    }
+
+    fn release(
+        &mut self,
+        index: usize,
+    ) {
+        self.nodes[index].kind = Kind::Free;
+        self.free.push(index);
+    }
+
+    fn replace_child(
+        &mut self,
+        parent: usize,
+        old: usize,
+        new: usize,
+    ) {
+        if let Kind::Branch(ref mut children) = self.nodes[parent].kind {
+            for child in children.iter_mut() {
+                if *child == old {
+                    *child = new;
+                }
+            }
+        }
+    }
+
+    /// Recomputes the bounds of `index` and its ancestors.
+    fn refit(
+        &mut self,
+        mut index: Option<usize>,
+    ) {
+        while let Some(i) = index {
+            if let Kind::Branch(children) = self.nodes[i].kind {
+                self.nodes[i].bounds = self.nodes[children[0]].bounds.union(&self.nodes[children[1]].bounds);
+            }
+            index = self.nodes[i].parent;
+        }
+    }
+}
+
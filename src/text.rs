@@ -1,20 +1,23 @@
 use std::cell::RefCell;
+use std::collections::HashMap;
 use std::fmt;
 use std::rc::Rc;
 
+use gfx;
 use gfx::Encoder;
 use gfx::handle::{DepthStencilView, RenderTargetView};
 use gfx_glyph as g;
 use mint;
 use object;
 
-use color::Color;
+use color::{self, Color};
 use hub::Operation as HubOperation;
 use render::{BackendCommandBuffer, BackendFactory, BackendResources, ColorFormat, DepthFormat};
 
 #[derive(Debug)]
 pub(crate) enum Operation {
     Text(String),
+    Spans(Vec<Span>),
     Font(Font),
     Scale(f32),
     Pos(mint::Point2<f32>),
@@ -22,6 +25,9 @@ pub(crate) enum Operation {
     Color(Color),
     Opacity(f32),
     Layout(Layout),
+    Outline(Option<Outline>),
+    Shadow(Option<DropShadow>),
+    WorldScale(Option<f32>),
 }
 
 /// Describes horizontal alignment preference for positioning & bounds.
@@ -47,7 +53,12 @@ pub enum Layout {
     /// Renders a single line from left-to-right according to the inner alignment.
     SingleLine(Align),
     /// Renders multiple lines from left-to-right according to the inner alignment.
+    ///
+    /// Lines are broken at newlines, and between words when exceeding the bounds.
     Wrap(Align),
+    /// Like `Wrap`, but lines may also be broken between any two characters,
+    /// e.g. for long identifiers or text without spaces.
+    WrapAnyChar(Align),
 }
 
 impl Default for Layout {
@@ -74,6 +85,11 @@ impl From<Layout> for g::Layout<g::BuiltInLineBreaker> {
                 h_align: a.into(),
                 v_align: g::VerticalAlign::Top,
             },
+            Layout::WrapAnyChar(a) => g::Layout::Wrap {
+                line_breaker: g::BuiltInLineBreaker::AnyCharLineBreaker,
+                h_align: a.into(),
+                v_align: g::VerticalAlign::Top,
+            },
             Layout::SingleLine(a) => g::Layout::SingleLine {
                 line_breaker: g::BuiltInLineBreaker::UnicodeLineBreaker,
                 h_align: a.into(),
@@ -83,11 +99,182 @@ impl From<Layout> for g::Layout<g::BuiltInLineBreaker> {
     }
 }
 
+/// A run of text with its own style, within a [`Text`](struct.Text.html).
+#[derive(Clone, Debug, PartialEq)]
+pub struct Span {
+    /// The text of the span.
+    pub text: String,
+    /// Color of the span.
+    ///
+    /// Default: white (`0xFFFFFF`).
+    pub color: Color,
+    /// Opacity of the span, from `0.0` to `1.0`.
+    ///
+    /// Default: `1.0`.
+    pub opacity: f32,
+    /// Font size (scale) of the span.
+    ///
+    /// Default: `16.0`.
+    pub font_size: f32,
+    /// Font of the span, or `None` for the font of the text.
+    ///
+    /// Default: `None`.
+    pub font: Option<Font>,
+}
+
+impl Span {
+    /// Creates a span with the default style.
+    pub fn new<S: Into<String>>(text: S) -> Self {
+        Span {
+            text: text.into(),
+            color: 0xFFFFFF,
+            opacity: 1.0,
+            font_size: 16.0,
+            font: None,
+        }
+    }
+
+    /// Sets the color of the span.
+    pub fn color(
+        mut self,
+        color: Color,
+    ) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Sets the opacity of the span.
+    pub fn opacity(
+        mut self,
+        opacity: f32,
+    ) -> Self {
+        self.opacity = opacity;
+        self
+    }
+
+    /// Sets the font size of the span.
+    pub fn font_size(
+        mut self,
+        font_size: f32,
+    ) -> Self {
+        self.font_size = font_size;
+        self
+    }
+
+    /// Sets the font of the span.
+    pub fn font(
+        mut self,
+        font: &Font,
+    ) -> Self {
+        self.font = Some(font.clone());
+        self
+    }
+}
+
+/// Outline drawn around the glyphs of a [`Text`](struct.Text.html).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Outline {
+    /// Color of the outline.
+    pub color: Color,
+    /// Width of the outline in pixels, before UI scaling.
+    pub width: f32,
+}
+
+/// Drop shadow drawn behind the glyphs of a [`Text`](struct.Text.html).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DropShadow {
+    /// Color of the shadow.
+    pub color: Color,
+    /// Offset of the shadow in pixels from the text, before UI scaling.
+    /// Positive values go right and down.
+    pub offset: mint::Vector2<f32>,
+}
+
+/// Size category of a window, based on its height in logical pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SizeCategory {
+    /// Less than 900 logical pixels high, e.g. 720p.
+    Small,
+    /// Between 900 and 1800 logical pixels high, e.g. 1080p.
+    Normal,
+    /// At least 1800 logical pixels high, e.g. 4K without DPI scaling.
+    Large,
+}
+
+impl SizeCategory {
+    /// Returns the category of a window `height` logical pixels high.
+    pub fn from_height(height: f32) -> Self {
+        if height < 900.0 {
+            SizeCategory::Small
+        } else if height < 1800.0 {
+            SizeCategory::Normal
+        } else {
+            SizeCategory::Large
+        }
+    }
+
+    /// Returns the text scale factor of this category.
+    pub fn factor(&self) -> f32 {
+        match *self {
+            SizeCategory::Small => 0.75,
+            SizeCategory::Normal => 1.0,
+            SizeCategory::Large => 2.0,
+        }
+    }
+}
+
+/// Scaling applied to the font size and bounds of all UI text. Positions are
+/// left as is.
+///
+/// Set with [`Renderer::set_ui_scale`](struct.Renderer.html#method.set_ui_scale).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UiScale {
+    /// Scale by the factor of the given category, regardless of the window.
+    Preset(SizeCategory),
+    /// Scale by a fixed factor.
+    Custom(f32),
+    /// Scale by the window DPI factor, then by the factor of the window
+    /// size category.
+    Auto,
+}
+
+impl Default for UiScale {
+    fn default() -> Self {
+        UiScale::Custom(1.0)
+    }
+}
+
+impl UiScale {
+    /// Returns the scale factor for a window of the given physical `size`
+    /// and DPI factor.
+    pub fn factor(
+        &self,
+        size: mint::Vector2<f32>,
+        dpi_factor: f32,
+    ) -> f32 {
+        match *self {
+            UiScale::Preset(category) => category.factor(),
+            UiScale::Custom(factor) => factor,
+            UiScale::Auto => {
+                let category = SizeCategory::from_height(size.y / dpi_factor);
+                dpi_factor * category.factor()
+            }
+        }
+    }
+}
+
 /// Smart pointer containing a font to draw text.
 #[derive(Clone)]
 pub struct Font {
     brush: Rc<RefCell<g::GlyphBrush<'static, BackendResources, BackendFactory>>>,
+    /// Brush of the text drawn in world space, depth tested against the scene.
+    world_brush: Rc<RefCell<g::GlyphBrush<'static, BackendResources, BackendFactory>>>,
     pub(crate) id: String,
+    data: g::SharedBytes<'static>,
+    factory: BackendFactory,
+    /// Fonts drawing this one along with the fonts of text spans, by the
+    /// ids of the span fonts.
+    combined: Rc<RefCell<HashMap<Vec<String>, Font>>>,
 }
 
 impl Font {
@@ -96,20 +283,80 @@ impl Font {
         id: String,
         factory: BackendFactory,
     ) -> Font {
+        let data = buf.into();
+        Font::with_faces(id, data.clone(), vec![data], factory)
+    }
+
+    /// Creates a font whose brushes have all of `faces`, referenced by their
+    /// index as `g::FontId`.
+    fn with_faces(
+        id: String,
+        data: g::SharedBytes<'static>,
+        faces: Vec<g::SharedBytes<'static>>,
+        factory: BackendFactory,
+    ) -> Font {
+        // each world text is drawn with its own transform, so the vertices
+        // of the previous draw can't be reused
+        let world_brush = g::GlyphBrushBuilder::using_fonts_bytes(faces.clone())
+            .depth_test(gfx::preset::depth::LESS_EQUAL_TEST)
+            .cache_glyph_drawing(false)
+            .build(factory.clone());
         Font {
             brush: Rc::new(RefCell::new(
-                g::GlyphBrushBuilder::using_font_bytes(buf).build(factory),
+                g::GlyphBrushBuilder::using_fonts_bytes(faces).build(factory.clone()),
             )),
+            world_brush: Rc::new(RefCell::new(world_brush)),
             id: id,
+            data,
+            factory,
+            combined: Rc::new(RefCell::new(HashMap::new())),
         }
     }
 
+    /// Returns a font drawing this one as `g::FontId(0)` and `spans`, other
+    /// fonts, as the following ids.
+    fn combined(
+        &self,
+        spans: &[Font],
+    ) -> Font {
+        if spans.is_empty() {
+            return self.clone();
+        }
+        let key = spans.iter().map(|font| font.id.clone()).collect::<Vec<_>>();
+        self.combined
+            .borrow_mut()
+            .entry(key)
+            .or_insert_with(|| {
+                let mut id = self.id.clone();
+                let mut faces = vec![self.data.clone()];
+                for font in spans {
+                    id += " + ";
+                    id += &font.id;
+                    faces.push(font.data.clone());
+                }
+                Font::with_faces(id, self.data.clone(), faces, self.factory.clone())
+            })
+            .clone()
+    }
+
     pub(crate) fn queue(
         &self,
         section: &g::OwnedVariedSection,
+        scale: f32,
     ) {
         let mut brush = self.brush.borrow_mut();
-        brush.queue(section);
+        if scale == 1.0 {
+            brush.queue(section);
+        } else {
+            let mut section = section.clone();
+            section.bounds.0 *= scale;
+            section.bounds.1 *= scale;
+            for text in &mut section.text {
+                text.scale.x *= scale;
+                text.scale.y *= scale;
+            }
+            brush.queue(&section);
+        }
     }
 
     pub(crate) fn draw(
@@ -123,6 +370,25 @@ impl Font {
             .draw_queued(encoder, out, depth)
             .expect("Error while drawing text");
     }
+
+    /// Draws `sections` at once with `transform` from the screen coordinates
+    /// of their layout to clip space, testing them against the scene depth.
+    pub(crate) fn draw_in_world(
+        &self,
+        sections: &[g::OwnedVariedSection],
+        transform: [[f32; 4]; 4],
+        encoder: &mut Encoder<BackendResources, BackendCommandBuffer>,
+        out: &RenderTargetView<BackendResources, ColorFormat>,
+        depth: &DepthStencilView<BackendResources, DepthFormat>,
+    ) {
+        let mut brush = self.world_brush.borrow_mut();
+        for section in sections {
+            brush.queue(section);
+        }
+        brush
+            .draw_queued_with_transform(transform, encoder, out, depth)
+            .expect("Error while drawing text");
+    }
 }
 
 impl fmt::Debug for Font {
@@ -134,11 +400,30 @@ impl fmt::Debug for Font {
     }
 }
 
+impl PartialEq for Font {
+    fn eq(
+        &self,
+        other: &Font,
+    ) -> bool {
+        self.id == other.id
+    }
+}
+
 #[derive(Debug, Clone)]
 pub(crate) struct TextData {
     pub(crate) section: g::OwnedVariedSection,
     pub(crate) layout: Layout,
+    /// Font of the text.
     pub(crate) font: Font,
+    /// Font of each span of the section, `None` for the font of the text.
+    pub(crate) span_fonts: Vec<Option<Font>>,
+    /// Font drawing the text, with the font of the text and of the spans.
+    pub(crate) brush_font: Font,
+    pub(crate) outline: Option<Outline>,
+    pub(crate) shadow: Option<DropShadow>,
+    /// World units per pixel of the layout if the text is drawn in world
+    /// space.
+    pub(crate) world_scale: Option<f32>,
 }
 
 impl TextData {
@@ -159,7 +444,103 @@ impl TextData {
             },
             layout: Default::default(),
             font: font.clone(),
+            span_fonts: vec![None],
+            brush_font: font.clone(),
+            outline: None,
+            shadow: None,
+            world_scale: None,
+        }
+    }
+
+    /// Updates the font drawing the text and the font ids of the spans, after
+    /// the font of the text or the spans changed.
+    pub(crate) fn update_fonts(&mut self) {
+        let mut others: Vec<Font> = Vec::new();
+        for font in self.span_fonts.iter().filter_map(Option::as_ref) {
+            if *font != self.font && !others.contains(font) {
+                others.push(font.clone());
+            }
+        }
+        for (text, font) in self.section.text.iter_mut().zip(&self.span_fonts) {
+            text.font_id = match others.iter().position(|other| Some(other) == font.as_ref()) {
+                Some(index) => g::FontId(index + 1),
+                None => g::FontId(0),
+            };
+        }
+        self.brush_font = self.font.combined(&others);
+    }
+
+    /// Queues the shadow, outline and glyphs of the text for drawing.
+    pub(crate) fn queue(
+        &self,
+        scale: f32,
+    ) {
+        for section in self.styled_sections(scale) {
+            self.brush_font.queue(&section, scale);
+        }
+    }
+
+    /// Draws the shadow, outline and glyphs of the text in world space, with
+    /// `transform` from the screen coordinates of the layout to clip space.
+    pub(crate) fn draw_in_world(
+        &self,
+        transform: [[f32; 4]; 4],
+        encoder: &mut Encoder<BackendResources, BackendCommandBuffer>,
+        out: &RenderTargetView<BackendResources, ColorFormat>,
+        depth: &DepthStencilView<BackendResources, DepthFormat>,
+    ) {
+        let sections = self.styled_sections(1.0);
+        self.brush_font.draw_in_world(&sections, transform, encoder, out, depth);
+    }
+
+    /// Returns the sections of the shadow, outline and glyphs of the text in
+    /// drawing order, with the offsets of the shadow and outline multiplied by
+    /// `scale`.
+    fn styled_sections(
+        &self,
+        scale: f32,
+    ) -> Vec<g::OwnedVariedSection> {
+        let mut sections = Vec::new();
+        if let Some(shadow) = self.shadow {
+            sections.push(self.recolored(shadow.color, shadow.offset.x * scale, shadow.offset.y * scale));
         }
+        if let Some(outline) = self.outline {
+            // stamp the glyphs in eight directions around the text
+            let width = outline.width * scale;
+            let diagonal = width * 0.5f32.sqrt();
+            let offsets = [
+                (width, 0.0),
+                (-width, 0.0),
+                (0.0, width),
+                (0.0, -width),
+                (diagonal, diagonal),
+                (-diagonal, diagonal),
+                (diagonal, -diagonal),
+                (-diagonal, -diagonal),
+            ];
+            for &(x, y) in offsets.iter() {
+                sections.push(self.recolored(outline.color, x, y));
+            }
+        }
+        sections.push(self.section.clone());
+        sections
+    }
+
+    /// Returns a copy of the section in a single color, moved by `(x, y)` pixels.
+    fn recolored(
+        &self,
+        color: Color,
+        x: f32,
+        y: f32,
+    ) -> g::OwnedVariedSection {
+        let rgb = color::to_linear_rgb(color);
+        let mut section = self.section.clone();
+        section.screen_position.0 += x;
+        section.screen_position.1 += y;
+        for text in &mut section.text {
+            text.color = [rgb[0], rgb[1], rgb[2], text.color[3]];
+        }
+        section
     }
 }
 
@@ -187,6 +568,19 @@ impl Text {
         let _ = self.object.tx.send((self.object.node.downgrade(), msg));
     }
 
+    /// Replace the text with styled spans, laid out one after another.
+    ///
+    /// Spans without a font are drawn with the font of this text. `set_color`,
+    /// `set_opacity` and `set_font_size` restyle every span, and `set_text`
+    /// replaces them with a single span in the style of the first one.
+    pub fn set_spans(
+        &mut self,
+        spans: Vec<Span>,
+    ) {
+        let msg = HubOperation::SetText(Operation::Spans(spans));
+        let _ = self.object.tx.send((self.object.node.downgrade(), msg));
+    }
+
     /// Change font.
     pub fn set_font(
         &mut self,
@@ -248,6 +642,49 @@ impl Text {
         let _ = self.object.tx.send((self.object.node.downgrade(), msg));
     }
 
+    /// Change the outline drawn around the glyphs, or remove it with `None`.
+    /// Defaults to `None`.
+    ///
+    /// The outline is made of eight copies of the glyphs offset around them,
+    /// so outlines wider than a few pixels show gaps at sharp corners. Glyphs
+    /// are rasterized at their font size rather than drawn from a distance
+    /// field, which also holds for text drawn in world space.
+    pub fn set_outline(
+        &mut self,
+        outline: Option<Outline>,
+    ) {
+        let msg = HubOperation::SetText(Operation::Outline(outline));
+        let _ = self.object.tx.send((self.object.node.downgrade(), msg));
+    }
+
+    /// Change the drop shadow drawn behind the text, or remove it with `None`.
+    /// Defaults to `None`.
+    pub fn set_shadow(
+        &mut self,
+        shadow: Option<DropShadow>,
+    ) {
+        let msg = HubOperation::SetText(Operation::Shadow(shadow));
+        let _ = self.object.tx.send((self.object.node.downgrade(), msg));
+    }
+
+    /// Draws the text in the XY plane of its node instead of over the screen,
+    /// with `scale` world units per pixel of the layout, or back over the
+    /// screen with `None`. Defaults to `None`.
+    ///
+    /// The layout starts at the node origin offset by the text position,
+    /// with X to the right and Y up. The text is hidden by the scene meshes in
+    /// front of it and goes through the post effects, but isn't drawn in
+    /// viewports, reflection probe captures and at the UI scale. As the
+    /// glyphs are rasterized at the font size, the font size should match the
+    /// size the text is seen at to keep it sharp.
+    pub fn set_world_scale(
+        &mut self,
+        scale: Option<f32>,
+    ) {
+        let msg = HubOperation::SetText(Operation::WorldScale(scale));
+        let _ = self.object.tx.send((self.object.node.downgrade(), msg));
+    }
+
     /// Change text layout.
     /// Defaults to `Layout::SingleLine(Align::Left)`.
     pub fn set_layout(
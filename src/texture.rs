@@ -3,11 +3,31 @@ use std::path::Path;
 use gfx::handle as h;
 use mint;
 
-use render::BackendResources;
+use render::{BackendResources, ColorFormat, DepthFormat};
 use util;
 
 pub use gfx::texture::{FilterMethod, WrapMode};
 
+/// How the texels of a `Texture` are encoded.
+///
+/// Images holding colors, such as base color and emissive maps, are usually
+/// stored in sRGB and converted to linear values when sampled. Images holding
+/// data, such as normal, metallic-roughness and occlusion maps, must be
+/// sampled as they are.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ColorSpace {
+    /// Texels are sRGB-encoded and decoded to linear values by the sampler.
+    Srgb,
+    /// Texels are sampled without conversion.
+    Linear,
+}
+
+impl Default for ColorSpace {
+    fn default() -> Self {
+        ColorSpace::Srgb
+    }
+}
+
 /// The sampling properties for a `Texture`.
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct Sampler(pub h::Sampler<BackendResources>);
@@ -19,6 +39,7 @@ pub struct Texture<T> {
     view: h::ShaderResourceView<BackendResources, T>,
     sampler: h::Sampler<BackendResources>,
     total_size: [u32; 2],
+    color_space: ColorSpace,
     #[derivative(Hash(hash_with = "util::hash_f32_slice"))] tex0: [f32; 2],
     #[derivative(Hash(hash_with = "util::hash_f32_slice"))] tex1: [f32; 2],
 }
@@ -28,11 +49,13 @@ impl<T> Texture<T> {
         view: h::ShaderResourceView<BackendResources, T>,
         sampler: h::Sampler<BackendResources>,
         total_size: [u32; 2],
+        color_space: ColorSpace,
     ) -> Self {
         Texture {
             view,
             sampler,
             total_size,
+            color_space,
             tex0: [0.0; 2],
             tex1: [total_size[0] as f32, total_size[1] as f32],
         }
@@ -47,6 +70,26 @@ impl<T> Texture<T> {
         (self.view.clone(), self.sampler.clone())
     }
 
+    /// Returns the underlying gfx shader resource view, e.g. to sample the
+    /// texture from pipelines outside of `three`.
+    ///
+    /// See [`Factory::texture_from_view`] for synchronization.
+    ///
+    /// [`Factory::texture_from_view`]: struct.Factory.html#method.texture_from_view
+    pub fn raw_view(&self) -> &h::ShaderResourceView<BackendResources, T> {
+        &self.view
+    }
+
+    /// Returns the size of the whole texture in texels.
+    pub fn size(&self) -> [u32; 2] {
+        self.total_size
+    }
+
+    /// Returns the color space the texels were uploaded in.
+    pub fn color_space(&self) -> ColorSpace {
+        self.color_space
+    }
+
     /// See [`Sprite::set_texel_range`](struct.Sprite.html#method.set_texel_range).
     pub fn set_texel_range(
         &mut self,
@@ -74,6 +117,46 @@ impl<T> Texture<T> {
     }
 }
 
+/// An offscreen color and depth target that a scene can be rendered into,
+/// e.g. for mirrors, portals or mini-maps.
+///
+/// Created with [`Factory::render_target`] and filled with
+/// [`Renderer::render_to_target`]. Its [`texture`](#method.texture) can be
+/// used as a material map or sprite like any other texture.
+///
+/// [`Factory::render_target`]: struct.Factory.html#method.render_target
+/// [`Renderer::render_to_target`]: struct.Renderer.html#method.render_to_target
+#[derive(Clone, Debug)]
+pub struct RenderTarget {
+    pub(crate) color: h::RenderTargetView<BackendResources, ColorFormat>,
+    pub(crate) depth: h::DepthStencilView<BackendResources, DepthFormat>,
+    texture: Texture<[f32; 4]>,
+}
+
+impl RenderTarget {
+    pub(crate) fn new(
+        color: h::RenderTargetView<BackendResources, ColorFormat>,
+        depth: h::DepthStencilView<BackendResources, DepthFormat>,
+        texture: Texture<[f32; 4]>,
+    ) -> Self {
+        RenderTarget {
+            color,
+            depth,
+            texture,
+        }
+    }
+
+    /// Returns the texture holding the rendered colors.
+    pub fn texture(&self) -> Texture<[f32; 4]> {
+        self.texture.clone()
+    }
+
+    /// Returns the size of the target in pixels.
+    pub fn size(&self) -> [u32; 2] {
+        self.texture.total_size
+    }
+}
+
 /// Represents paths to cube map texture, useful for loading
 /// [`CubeMap`](struct.CubeMap.html).
 #[derive(Clone, Debug)]
@@ -1,5 +1,7 @@
 //! Internal utility functions.
 
+use color::Color;
+
 use std::{fs, io, path};
 use std::hash::{Hash, Hasher};
 
@@ -30,3 +32,40 @@ pub fn hash_f32_slice<H: Hasher>(
         element.to_bits().hash(state);
     }
 }
+
+/// Seeded xorshift generator, for procedural content that must not depend
+/// on external crates.
+pub struct Rng(u32);
+
+impl Rng {
+    pub fn new(seed: u32) -> Self {
+        // xorshift must not start from zero
+        let state = seed ^ 0x9E37_79B9;
+        Rng(if state == 0 { 1 } else { state })
+    }
+
+    pub fn next(&mut self) -> u32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 17;
+        self.0 ^= self.0 << 5;
+        self.0
+    }
+
+    /// Returns a number in `[0, 1)`.
+    pub fn unit(&mut self) -> f32 {
+        (self.next() >> 8) as f32 / (1 << 24) as f32
+    }
+
+    /// Returns a number in `[min, max)`.
+    pub fn range(
+        &mut self,
+        min: f32,
+        max: f32,
+    ) -> f32 {
+        min + (max - min) * self.unit()
+    }
+
+    pub fn color(&mut self) -> Color {
+        self.next() & 0xFF_FF_FF
+    }
+}
@@ -0,0 +1,512 @@
+//! Virtual texturing, for textures too large to fit in GPU memory at once,
+//! e.g. the color map of a large terrain or a scanned surface.
+//!
+//! A virtual texture is split into square tiles, at each level of its mip
+//! chain. Only the tiles needed to render the current view are kept on the
+//! GPU, in a cache texture of a fixed number of pages, and an indirection
+//! table tells the shader which page holds the tile covering each part of
+//! the texture. Where the needed tile isn't resident yet, the shader falls
+//! back to the finest resident tile of a coarser level, so the texture
+//! sharpens as tiles are streamed in.
+//!
+//! The needed tiles are found by a feedback pass: each frame, the meshes
+//! sampling a virtual texture are also drawn into a small target, each pixel
+//! recording the tile it samples, and the target is read back with the next
+//! frame. More tiles can be requested ahead of the view from the position of
+//! the eye relative to the textured surface with
+//! [`VirtualTexture::request_view`], or tile by tile with
+//! [`VirtualTexture::request`]. Each call to
+//! [`Factory::update_virtual_texture`] then loads a few of the missing tiles
+//! from a [`TileSource`], evicting the least recently requested pages of the
+//! cache, and uploads them with the next render.
+//!
+//! Meshes sample the texture with the [`Virtual`] material, using their
+//! texture co-ordinates.
+//!
+//! ```rust,no_run
+//! # let mut window = three::Window::new("");
+//! # use three::Object;
+//! # let camera = window.factory.perspective_camera(60.0, 1.0 .. 1000.0);
+//! use three::virtual_texture::{self, Layout, Tile};
+//!
+//! // a 64k by 64k texels checkerboard, tinted by level
+//! let layout = Layout {
+//!     width: 65536,
+//!     height: 65536,
+//!     tile_size: 128,
+//! };
+//! let mut source = |tile: Tile| {
+//!     let shade = 255 - 20 * tile.level as u8;
+//!     let color = if (tile.x + tile.y) % 2 == 0 { [shade, shade, shade, 255] } else { [shade, 0, 0, 255] };
+//!     Some(vec![color; 128 * 128])
+//! };
+//! let mut texture = window.factory.virtual_texture(layout, 32);
+//!
+//! let material = three::material::Virtual {
+//!     color: three::color::WHITE,
+//!     map: texture.map(),
+//! };
+//! let terrain = window.factory.mesh(three::Geometry::plane(1000.0, 1000.0), material);
+//! window.scene.add(&terrain);
+//!
+//! let scale = virtual_texture::projection_scale(60.0, window.size().y);
+//! let mut position = [0.0f32, -400.0, 50.0];
+//! while window.update() {
+//!     position[1] += 0.5;
+//!     camera.set_position(position);
+//!     // the plane spans the whole texture, at 65.536 texels per world unit
+//!     let texels = 65.536;
+//!     let eye = [
+//!         (position[0] + 500.0) * texels,
+//!         (500.0 - position[1]) * texels,
+//!         position[2] * texels,
+//!     ];
+//!     texture.request_view(eye.into(), scale);
+//!     window.factory.update_virtual_texture(&mut texture, &mut source);
+//!     window.render(&camera);
+//! }
+//! ```
+//!
+//! [`VirtualTexture::request_view`]: struct.VirtualTexture.html#method.request_view
+//! [`VirtualTexture::request`]: struct.VirtualTexture.html#method.request
+//! [`Factory::update_virtual_texture`]: ../struct.Factory.html#method.update_virtual_texture
+//! [`TileSource`]: trait.TileSource.html
+//! [`Virtual`]: ../material/struct.Virtual.html
+
+use gfx::format::R8_G8_B8_A8;
+use gfx::handle as h;
+use mint;
+use render::BackendResources;
+use texture::Texture;
+
+use std::cmp;
+use std::collections::{HashMap, HashSet};
+
+/// Maximum number of pages along each side of the cache, so that page
+/// positions fit in the 8 bit channels of the indirection table.
+pub(crate) const MAX_PAGES: u32 = 256;
+
+pub(crate) type TileTexture = h::Texture<BackendResources, R8_G8_B8_A8>;
+
+/// Size of a virtual texture and of its tiles.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Layout {
+    /// Width of the finest level, in texels.
+    pub width: u32,
+    /// Height of the finest level, in texels.
+    pub height: u32,
+    /// Width and height of the tiles, in texels.
+    pub tile_size: u32,
+}
+
+impl Layout {
+    /// Returns the number of tiles along the width and height of `level`.
+    pub fn tiles(
+        &self,
+        level: u32,
+    ) -> [u32; 2] {
+        let span = (self.tile_size as u64) << level;
+        [
+            ((self.width as u64 + span - 1) / span) as u32,
+            ((self.height as u64 + span - 1) / span) as u32,
+        ]
+    }
+
+    /// Returns the number of levels of the mip chain, the coarsest level
+    /// fitting in a single tile.
+    pub fn levels(&self) -> u32 {
+        let tiles = self.tiles(0);
+        cmp::max(tiles[0], tiles[1]).next_power_of_two().trailing_zeros() + 1
+    }
+
+    /// Returns `true` if `tile` covers part of the texture.
+    pub fn contains(
+        &self,
+        tile: Tile,
+    ) -> bool {
+        let tiles = self.tiles(tile.level);
+        tile.level < self.levels() && tile.x < tiles[0] && tile.y < tiles[1]
+    }
+
+    /// Number of entries along each side of the finest level of the
+    /// indirection table, which is square with a full mip chain.
+    pub(crate) fn table_size(&self) -> u32 {
+        1 << (self.levels() - 1)
+    }
+}
+
+/// A tile of a virtual texture.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Tile {
+    /// Mip level, 0 being the finest.
+    pub level: u32,
+    /// Column of the tile, from the left edge of the texture.
+    pub x: u32,
+    /// Row of the tile, from the top edge of the texture.
+    pub y: u32,
+}
+
+impl Tile {
+    /// Returns the tile of the next coarser level covering this one.
+    pub fn parent(&self) -> Tile {
+        Tile {
+            level: self.level + 1,
+            x: self.x / 2,
+            y: self.y / 2,
+        }
+    }
+}
+
+/// Provides the texels of the tiles of a virtual texture, e.g. by reading
+/// them from disk or generating them.
+///
+/// Implemented for closures taking a [`Tile`](struct.Tile.html).
+pub trait TileSource {
+    /// Returns the `tile_size * tile_size` texels of `tile`, as sRGB colors
+    /// with alpha in rows from top to bottom, or `None` if the tile isn't
+    /// available yet.
+    ///
+    /// Each update clears the requests, so a tile that isn't available is
+    /// only loaded again once it's requested again, e.g. by the feedback of
+    /// a later frame.
+    ///
+    /// Tiles on the right and bottom edges of the texture may extend past
+    /// it; the texels outside of the texture are never sampled.
+    fn load(
+        &mut self,
+        tile: Tile,
+    ) -> Option<Vec<[u8; 4]>>;
+}
+
+impl<F: FnMut(Tile) -> Option<Vec<[u8; 4]>>> TileSource for F {
+    fn load(
+        &mut self,
+        tile: Tile,
+    ) -> Option<Vec<[u8; 4]>> {
+        self(tile)
+    }
+}
+
+/// The GPU side of a virtual texture, as sampled by the
+/// [`Virtual`](../material/struct.Virtual.html) material.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Map {
+    pub(crate) cache: Texture<[f32; 4]>,
+    pub(crate) indirection: Texture<[f32; 4]>,
+    pub(crate) layout: Layout,
+    /// Id of the texture in the feedback target, never `0`.
+    pub(crate) id: u8,
+}
+
+impl Map {
+    /// Returns the layout of the virtual texture.
+    pub fn layout(&self) -> Layout {
+        self.layout
+    }
+
+    /// Material parameters of the virtual texture shader.
+    pub(crate) fn params(&self) -> [f32; 4] {
+        [
+            self.layout.width as f32,
+            self.layout.height as f32,
+            self.layout.tile_size as f32,
+            self.layout.levels() as f32,
+        ]
+    }
+}
+
+/// Returns the number of pixels covered by one radian of the vertical field
+/// of view `fov_y` (in degrees) of a perspective camera, for a viewport
+/// `height` pixels high.
+///
+/// See [`VirtualTexture::request_view`](struct.VirtualTexture.html#method.request_view).
+pub fn projection_scale(
+    fov_y: f32,
+    height: f32,
+) -> f32 {
+    0.5 * height / (0.5 * fov_y.to_radians()).tan()
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Page {
+    position: [u32; 2],
+    last_used: u64,
+}
+
+/// A texture streamed to the GPU tile by tile.
+///
+/// Created with [`Factory::virtual_texture`], see the
+/// [module documentation](index.html).
+///
+/// [`Factory::virtual_texture`]: ../struct.Factory.html#method.virtual_texture
+pub struct VirtualTexture {
+    layout: Layout,
+    pages: u32,
+    pub(crate) cache: TileTexture,
+    pub(crate) indirection: TileTexture,
+    map: Map,
+    resident: HashMap<Tile, Page>,
+    free: Vec<[u32; 2]>,
+    requested: HashSet<Tile>,
+    frame: u64,
+    loads_per_update: usize,
+    dirty: bool,
+}
+
+impl VirtualTexture {
+    pub(crate) fn new(
+        pages: u32,
+        cache: TileTexture,
+        indirection: TileTexture,
+        map: Map,
+    ) -> Self {
+        let free = (0 .. pages * pages)
+            .rev()
+            .map(|i| [i % pages, i / pages])
+            .collect();
+        VirtualTexture {
+            layout: map.layout,
+            pages,
+            cache,
+            indirection,
+            map,
+            resident: HashMap::new(),
+            free,
+            requested: HashSet::new(),
+            frame: 0,
+            loads_per_update: 16,
+            dirty: true,
+        }
+    }
+
+    /// Returns the layout of the texture.
+    pub fn layout(&self) -> Layout {
+        self.layout
+    }
+
+    /// Returns the GPU side of the texture, to be sampled by a
+    /// [`Virtual`](../material/struct.Virtual.html) material.
+    pub fn map(&self) -> Map {
+        self.map.clone()
+    }
+
+    /// Id of the texture in the feedback target.
+    pub(crate) fn id(&self) -> u8 {
+        self.map.id
+    }
+
+    /// Returns the number of tiles the cache can hold.
+    pub fn capacity(&self) -> usize {
+        (self.pages * self.pages) as usize
+    }
+
+    /// Returns the number of tiles in the cache.
+    pub fn len(&self) -> usize {
+        self.resident.len()
+    }
+
+    /// Returns `true` if no tile is in the cache.
+    pub fn is_empty(&self) -> bool {
+        self.resident.is_empty()
+    }
+
+    /// Returns `true` if `tile` is in the cache.
+    pub fn is_resident(
+        &self,
+        tile: Tile,
+    ) -> bool {
+        self.resident.contains_key(&tile)
+    }
+
+    /// Sets the maximum number of tiles loaded by each update, to bound the
+    /// time spent loading and uploading tiles per frame.
+    ///
+    /// Default: `16`.
+    pub fn set_loads_per_update(
+        &mut self,
+        count: usize,
+    ) {
+        self.loads_per_update = count;
+    }
+
+    /// Requests `tile`, along with the coarser tiles covering it, to be
+    /// loaded by the next update, and to stay in the cache.
+    ///
+    /// Tiles outside of the texture are ignored.
+    pub fn request(
+        &mut self,
+        tile: Tile,
+    ) {
+        if !self.layout.contains(tile) {
+            return;
+        }
+        let levels = self.layout.levels();
+        let mut tile = tile;
+        // ancestors requested before are already in the set with theirs
+        while self.requested.insert(tile) && tile.level + 1 < levels {
+            tile = tile.parent();
+        }
+    }
+
+    /// Requests the tiles needed to render the texture mapped on a flat
+    /// surface, seen from `eye`.
+    ///
+    /// `eye` is in texels of the finest level: `x` and `y` from the top-left
+    /// corner of the texture, and `z` the height above the surface. `scale`
+    /// is the number of pixels covered by one radian around the view
+    /// direction, see [`projection_scale`](fn.projection_scale.html). Each
+    /// part of the texture is requested at the level where a texel covers
+    /// about one pixel, whether it's in view or not.
+    pub fn request_view(
+        &mut self,
+        eye: mint::Point3<f32>,
+        scale: f32,
+    ) {
+        let top = Tile {
+            level: self.layout.levels() - 1,
+            x: 0,
+            y: 0,
+        };
+        let mut stack = vec![top];
+        while let Some(tile) = stack.pop() {
+            if !self.layout.contains(tile) {
+                continue;
+            }
+            let span = ((self.layout.tile_size as u64) << tile.level) as f32;
+            let distance = {
+                let min = [tile.x as f32 * span, tile.y as f32 * span];
+                let dx = (min[0] - eye.x).max(eye.x - min[0] - span).max(0.0);
+                let dy = (min[1] - eye.y).max(eye.y - min[1] - span).max(0.0);
+                (dx * dx + dy * dy + eye.z * eye.z).sqrt()
+            };
+            // a texel of this level covers more than a pixel
+            let texel = (1u32 << tile.level) as f32;
+            if tile.level > 0 && distance < scale * texel {
+                for &(x, y) in &[(0, 0), (1, 0), (0, 1), (1, 1)] {
+                    stack.push(Tile {
+                        level: tile.level - 1,
+                        x: 2 * tile.x + x,
+                        y: 2 * tile.y + y,
+                    });
+                }
+            } else {
+                self.request(tile);
+            }
+        }
+    }
+
+    /// Starts an update: picks the missing tiles to load, most important
+    /// first, and the pages to load them into.
+    pub(crate) fn schedule(&mut self) -> Vec<(Tile, [u32; 2])> {
+        self.frame += 1;
+        // the coarsest tile is the fallback for the whole texture
+        let top = Tile {
+            level: self.layout.levels() - 1,
+            x: 0,
+            y: 0,
+        };
+        self.request(top);
+
+        let mut missing = Vec::new();
+        for tile in self.requested.drain() {
+            match self.resident.get_mut(&tile) {
+                Some(page) => page.last_used = self.frame,
+                None => missing.push(tile),
+            }
+        }
+        // coarse tiles first, so that fallbacks are available early
+        missing.sort_by(|a, b| {
+            b.level
+                .cmp(&a.level)
+                .then(a.y.cmp(&b.y))
+                .then(a.x.cmp(&b.x))
+        });
+
+        let mut schedule = Vec::new();
+        for tile in missing.into_iter().take(self.loads_per_update) {
+            let position = match self.free.pop() {
+                Some(position) => position,
+                None => match self.evict() {
+                    Some(position) => position,
+                    // the cache is full of tiles needed by this view
+                    None => break,
+                },
+            };
+            schedule.push((tile, position));
+        }
+        schedule
+    }
+
+    /// Removes the least recently requested tile not needed by the current
+    /// update from the cache, returning its page.
+    fn evict(&mut self) -> Option<[u32; 2]> {
+        let frame = self.frame;
+        let victim = self.resident
+            .iter()
+            .filter(|&(_, page)| page.last_used < frame)
+            // finer tiles go first, the coarser ones cover more of the texture
+            .min_by_key(|&(tile, page)| (page.last_used, cmp::Reverse(tile.level)))
+            .map(|(tile, _)| *tile);
+        victim.and_then(|tile| self.resident.remove(&tile)).map(|page| {
+            self.dirty = true;
+            page.position
+        })
+    }
+
+    /// Records `tile` as loaded into the page at `position`.
+    pub(crate) fn insert(
+        &mut self,
+        tile: Tile,
+        position: [u32; 2],
+    ) {
+        let page = Page {
+            position,
+            last_used: self.frame,
+        };
+        self.resident.insert(tile, page);
+        self.dirty = true;
+    }
+
+    /// Returns the page at `position` to the free list, after its tile
+    /// failed to load.
+    pub(crate) fn release(
+        &mut self,
+        position: [u32; 2],
+    ) {
+        self.free.push(position);
+    }
+
+    /// Returns the levels of the indirection table, finest first, if they
+    /// changed since the last call.
+    ///
+    /// Each entry holds the page position and the level of the finest
+    /// resident tile covering it, and a non-zero alpha if there is one.
+    pub(crate) fn take_tables(&mut self) -> Option<Vec<Vec<[u8; 4]>>> {
+        if !self.dirty {
+            return None;
+        }
+        self.dirty = false;
+        let levels = self.layout.levels();
+        let side = self.layout.table_size();
+        let mut tables = (0 .. levels)
+            .map(|level| vec![[0u8; 4]; ((side >> level) * (side >> level)) as usize])
+            .collect::<Vec<_>>();
+        for level in (0 .. levels).rev() {
+            let size = side >> level;
+            for y in 0 .. size {
+                for x in 0 .. size {
+                    let tile = Tile { level, x, y };
+                    let entry = match self.resident.get(&tile) {
+                        Some(page) => [page.position[0] as u8, page.position[1] as u8, level as u8, 255],
+                        None if level + 1 < levels => {
+                            tables[level as usize + 1][((y / 2) * (size / 2) + x / 2) as usize]
+                        }
+                        None => [0; 4],
+                    };
+                    tables[level as usize][(y * size + x) as usize] = entry;
+                }
+            }
+        }
+        Some(tables)
+    }
+}
@@ -8,12 +8,24 @@ use render;
 use camera::Camera;
 use factory::Factory;
 use input::Input;
+use input::record::{Event, Playback, Recorder};
+use raycast::{Ray, Raycaster};
 use render::Renderer;
+use render::Profile;
 use scene::Scene;
-use std::path::PathBuf;
+use std::io;
+use std::path::{Path, PathBuf};
 
 pub use glutin::CursorState;
 
+fn element_state(pressed: bool) -> glutin::ElementState {
+    if pressed {
+        glutin::ElementState::Pressed
+    } else {
+        glutin::ElementState::Released
+    }
+}
+
 /// `Window` is the core entity of every `three-rs` application.
 ///
 /// It provides [user input](struct.Window.html#method.update),
@@ -33,6 +45,45 @@ pub struct Window {
     ///
     /// Defaults to `true`.
     pub reset_input: bool,
+    suspended: bool,
+    recorder: Option<Recorder>,
+    playback: Option<Playback>,
+}
+
+/// A renderer without a window, created by
+/// [`Builder::build_headless`](struct.Builder.html#method.build_headless).
+///
+/// ```rust,no_run
+/// # let camera: three::camera::Camera = unimplemented!();
+/// let mut headless = three::Window::builder("thumbnail")
+///     .dimensions(256, 256)
+///     .build_headless();
+/// // populate `headless.scene` with `headless.factory`
+/// headless.render(&camera);
+/// headless.renderer.save_screenshot("thumbnail.png").unwrap();
+/// ```
+pub struct Headless {
+    /// Keeps the OpenGL context alive.
+    _context: glutin::HeadlessContext,
+    /// See [`Renderer`](struct.Renderer.html).
+    pub renderer: Renderer,
+    /// See [`Factory`](struct.Factory.html).
+    pub factory: Factory,
+    /// See [`Scene`](struct.Scene.html).
+    pub scene: Scene,
+}
+
+impl Headless {
+    /// Renders the scene as seen by `camera` into the offscreen frame, to be
+    /// read with [`Renderer::read_pixels`].
+    ///
+    /// [`Renderer::read_pixels`]: struct.Renderer.html#method.read_pixels
+    pub fn render(
+        &mut self,
+        camera: &Camera,
+    ) {
+        self.renderer.render(&self.scene, camera);
+    }
 }
 
 /// Builder for creating new [`Window`](struct.Window.html) with desired parameters.
@@ -40,9 +91,12 @@ pub struct Window {
 pub struct Builder {
     dimensions: (u32, u32),
     fullscreen: bool,
+    max_lights: usize,
     multisampling: u16,
+    profile: Profile,
     shader_directory: Option<PathBuf>,
     title: String,
+    visible: bool,
     vsync: bool,
 }
 
@@ -66,8 +120,28 @@ impl Builder {
         self
     }
 
-    /// Sets the multisampling level to request. A value of `0` indicates that multisampling must
-    /// not be enabled. Must be the power of 2. Defaults to `0`.
+    /// Sets the maximum number of lights affecting each mesh. Defaults to
+    /// [`DEFAULT_MAX_LIGHTS`](../render/constant.DEFAULT_MAX_LIGHTS.html).
+    ///
+    /// When the scene has more lights, each mesh is lit by the ones contributing
+    /// the most at its position, see [`Renderer::max_lights`]. Phong materials
+    /// use two interpolated vectors per light, which drivers limit to about
+    /// 8 lights.
+    ///
+    /// [`Renderer::max_lights`]: ../struct.Renderer.html#method.max_lights
+    pub fn max_lights(
+        &mut self,
+        max_lights: usize,
+    ) -> &mut Self {
+        self.max_lights = max_lights;
+        self
+    }
+
+    /// Sets the number of samples per pixel used to anti-alias the frame. A value of `0`
+    /// indicates that multisampling must not be enabled. Defaults to `0`.
+    ///
+    /// Unsupported counts are lowered to the closest supported one, see
+    /// [`Renderer::set_multisampling`](../struct.Renderer.html#method.set_multisampling).
     pub fn multisampling(
         &mut self,
         option: u16,
@@ -76,6 +150,20 @@ impl Builder {
         self
     }
 
+    /// Sets the OpenGL flavour to request and compile the shaders for.
+    /// Defaults to `Profile::Es` on Android and iOS, and `Profile::Core` elsewhere.
+    ///
+    /// User shaders from the [`shader_directory`](#method.shader_directory) are
+    /// written in GLSL 1.50 and converted like the default ones, see
+    /// [`Source::for_profile`](render/struct.Source.html#method.for_profile).
+    pub fn profile(
+        &mut self,
+        profile: Profile,
+    ) -> &mut Self {
+        self.profile = profile;
+        self
+    }
+
     /// Specifies the user shader directory.
     pub fn shader_directory<P: Into<PathBuf>>(
         &mut self,
@@ -85,6 +173,15 @@ impl Builder {
         self
     }
 
+    /// Whether to show the window or not, e.g. to render offscreen. Defaults to `true`.
+    pub fn visible(
+        &mut self,
+        option: bool,
+    ) -> &mut Self {
+        self.visible = option;
+        self
+    }
+
     /// Whether to enable vertical synchronization or not. Defaults to `true`.
     pub fn vsync(
         &mut self,
@@ -94,24 +191,8 @@ impl Builder {
         self
     }
 
-    /// Create new `Window` with desired parameters.
-    pub fn build(&mut self) -> Window {
-        let event_loop = glutin::EventsLoop::new();
-        let monitor_id = if self.fullscreen {
-            Some(event_loop.get_primary_monitor())
-        } else {
-            None
-        };
-
-        let builder = glutin::WindowBuilder::new()
-            .with_fullscreen(monitor_id)
-            .with_dimensions(self.dimensions.0, self.dimensions.1)
-            .with_title(self.title.clone());
-
-        let context = glutin::ContextBuilder::new()
-            .with_vsync(self.vsync)
-            .with_multisampling(self.multisampling);
-
+    /// Returns the shader sources, with the overrides of the user shader directory.
+    fn source_set(&self) -> render::source::Set {
         let mut source_set = render::source::Set::default();
         if let Some(path) = self.shader_directory.as_ref() {
             let path = path.to_str().unwrap();
@@ -142,10 +223,54 @@ impl Builder {
                     $( try_override!($name); )*
                 };
             }
-            try_override!(basic, gouraud, pbr, phong, quad, shadow, skybox, sprite,);
+            macro_rules! try_override_gs {
+                ( $($name:ident,)* ) => {
+                    $(
+                        match render::Source::user(path, stringify!($name), "gs") {
+                            Ok(src) => {
+                                info!("Overriding {}_gs.glsl", stringify!($name));
+                                source_set.$name.gs = src;
+                            }
+                            Err(err) => {
+                                error!("{:#?}", err);
+                                info!("Using default {}_gs.glsl", stringify!($name));
+                            }
+                        }
+                    )*
+                };
+            }
+            try_override!(basic, bloom, fxaa, gouraud, line, pbr, phong, points, quad, resolve, shadow, skybox, sprite, ssao, tone_mapping, virtual_texture,);
+            try_override_gs!(line, points,);
         }
+        source_set.for_profile(self.profile)
+    }
+
+    /// Create new `Window` with desired parameters.
+    pub fn build(&mut self) -> Window {
+        let event_loop = glutin::EventsLoop::new();
+        let monitor_id = if self.fullscreen {
+            Some(event_loop.get_primary_monitor())
+        } else {
+            None
+        };
+
+        let builder = glutin::WindowBuilder::new()
+            .with_fullscreen(monitor_id)
+            .with_dimensions(self.dimensions.0, self.dimensions.1)
+            .with_title(self.title.clone())
+            .with_visibility(self.visible);
+
+        let context = glutin::ContextBuilder::new()
+            .with_vsync(self.vsync);
+        let context = match self.profile {
+            Profile::Core => context,
+            Profile::Es => context.with_gl(glutin::GlRequest::Specific(glutin::Api::OpenGlEs, (3, 1))),
+        };
+
+        let source_set = self.source_set();
 
-        let (renderer, window, mut factory) = Renderer::new(builder, context, &event_loop, &source_set);
+        let (mut renderer, window, mut factory) = Renderer::new(builder, context, &event_loop, &source_set, self.max_lights);
+        renderer.set_multisampling(self.multisampling);
         let scene = factory.scene();
         Window {
             event_loop,
@@ -155,6 +280,41 @@ impl Builder {
             factory,
             scene,
             reset_input: true,
+            suspended: false,
+            recorder: None,
+            playback: None,
+        }
+    }
+
+    /// Creates a renderer without a window, drawing into an offscreen frame
+    /// of the [`dimensions`](#method.dimensions) set on the builder, e.g. to
+    /// render thumbnails on a server or in tests without a display.
+    ///
+    /// The title, fullscreen, visibility and vsync settings are ignored.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the platform can't create a headless OpenGL context.
+    pub fn build_headless(&mut self) -> Headless {
+        let builder = glutin::HeadlessRendererBuilder::new(self.dimensions.0, self.dimensions.1);
+        let builder = match self.profile {
+            Profile::Core => builder,
+            Profile::Es => builder.with_gl(glutin::GlRequest::Specific(glutin::Api::OpenGlEs, (3, 1))),
+        };
+        let context = builder.build().expect("Can't create a headless OpenGL context");
+        unsafe {
+            context.make_current().expect("Can't make the headless OpenGL context current");
+        }
+
+        let source_set = self.source_set();
+        let (mut renderer, mut factory) = Renderer::headless(&context, self.dimensions, &source_set, self.max_lights);
+        renderer.set_multisampling(self.multisampling);
+        let scene = factory.scene();
+        Headless {
+            _context: context,
+            renderer,
+            factory,
+            scene,
         }
     }
 }
@@ -170,53 +330,96 @@ impl Window {
         Builder {
             dimensions: (1024, 768),
             fullscreen: false,
+            max_lights: render::DEFAULT_MAX_LIGHTS,
             multisampling: 0,
+            profile: if cfg!(any(target_os = "android", target_os = "ios")) {
+                Profile::Es
+            } else {
+                Profile::Core
+            },
             shader_directory: None,
             title: title.into(),
+            visible: true,
             vsync: true,
         }
     }
 
     /// `update` method returns `false` if the window was closed.
+    ///
+    /// While [playing back](#method.play_input) a recording, the input events
+    /// of the window are ignored and replaced by the recorded ones.
     pub fn update(&mut self) -> bool {
         let mut running = true;
+        let mut replayed = None;
+        if let Some(ref mut playback) = self.playback {
+            let (delta, events) = playback.next_frame();
+            self.input.replay_delta(delta);
+            replayed = Some(events);
+        }
+        if self.playback.as_ref().map_or(false, |playback| playback.is_finished()) {
+            info!("Input playback finished");
+            self.playback = None;
+        }
+        if let Some(Err(err)) = self.recorder.as_mut().map(|recorder| recorder.next_frame()) {
+            warn!("Stopping the input recording: {}", err);
+            self.recorder = None;
+        }
+
+        // the draws of all the renders of a frame are reported until the next one
+        self.scene.hub.lock().unwrap().reset_rendered();
+
         let renderer = &mut self.renderer;
         let input = &mut self.input;
         if self.reset_input {
             input.reset();
         }
 
-        self.window.swap_buffers().unwrap();
+        // the surface may be destroyed while the application is suspended
+        if !self.suspended {
+            self.window.swap_buffers().unwrap();
+        }
         let window = &self.window;
+        let suspended = &mut self.suspended;
+        let mut events = Vec::new();
 
         self.event_loop.poll_events(|event| {
             use glutin::WindowEvent::{Closed, Focused, KeyboardInput, MouseInput, CursorMoved, MouseWheel, Resized};
             match event {
                 glutin::Event::WindowEvent { event, .. } => match event {
                     Resized(..) => renderer.resize(window),
-                    Focused(state) => input.window_focus(state),
-                    Closed => running = false,
+                    Focused(state) => events.push(Event::Focus(state)),
+                    Closed => events.push(Event::Close),
                     KeyboardInput {
                         input: glutin::KeyboardInput {
                             state,
-                            virtual_keycode: Some(keycode),
+                            virtual_keycode: Some(key),
                             ..
                         },
                         ..
-                    } => input.keyboard_input(state, keycode),
-                    MouseInput { state, button, .. } => input.mouse_input(state, button),
+                    } => events.push(Event::Key {
+                        key,
+                        pressed: state == glutin::ElementState::Pressed,
+                    }),
+                    MouseInput { state, button, .. } => events.push(Event::Mouse {
+                        button,
+                        pressed: state == glutin::ElementState::Pressed,
+                    }),
                     CursorMoved {
                         position: (x, y), ..
-                    } => input.mouse_moved(
-                        [x as f32, y as f32].into(),
-                        renderer.map_to_ndc([x as f32, y as f32]),
-                    ),
-                    MouseWheel { delta, .. } => input.mouse_wheel_input(delta),
+                    } => events.push(Event::Cursor(x as f32, y as f32)),
+                    MouseWheel { delta, .. } => events.push(Event::wheel(delta)),
                     _ => {}
                 },
+                glutin::Event::Suspended(state) => {
+                    *suspended = state;
+                    if !state {
+                        // mobile platforms recreate the surface on resume
+                        renderer.resize(window);
+                    }
+                }
                 glutin::Event::DeviceEvent { event, .. } => match event {
                     glutin::DeviceEvent::Motion { axis, value } => {
-                        input.axis_moved_raw(axis as u8, value as f32);
+                        events.push(Event::Axis(axis as u8, value as f32));
                     }
                     _ => {}
                 },
@@ -224,15 +427,122 @@ impl Window {
             }
         });
 
+        let events = match replayed {
+            Some(replayed) => {
+                // closing the window still works during playback
+                if events.iter().any(|event| *event == Event::Close) {
+                    running = false;
+                }
+                replayed
+            }
+            None => events,
+        };
+        for event in events {
+            if let Some(Err(err)) = self.recorder.as_mut().map(|recorder| recorder.write(event)) {
+                warn!("Stopping the input recording: {}", err);
+                self.recorder = None;
+            }
+            match event {
+                Event::Frame => {}
+                Event::Focus(state) => input.window_focus(state),
+                Event::Key { key, pressed } => input.keyboard_input(element_state(pressed), key),
+                Event::Mouse { button, pressed } => input.mouse_input(element_state(pressed), button),
+                Event::Cursor(x, y) => input.mouse_moved([x, y].into(), renderer.map_to_ndc([x, y])),
+                Event::WheelLines(x, y) => input.mouse_wheel_input(glutin::MouseScrollDelta::LineDelta(x, y)),
+                Event::WheelPixels(x, y) => input.mouse_wheel_input(glutin::MouseScrollDelta::PixelDelta(x, y)),
+                Event::Axis(axis, value) => input.axis_moved_raw(axis, value),
+                Event::Close => running = false,
+            }
+        }
+
         running
     }
 
+    /// Starts recording the input events of the window to the file at `path`,
+    /// replacing any recording in progress.
+    ///
+    /// Each event is written with the index of the frame, i.e. of the call to
+    /// [`update`](#method.update), it was received in and its time, so that it
+    /// can be replayed with [`play_input`](#method.play_input). While playing
+    /// back, the replayed events are recorded.
+    ///
+    /// ```rust,no_run
+    /// # let mut window = three::Window::new("");
+    /// window.record_input("session.txt").expect("Can't create the recording");
+    /// while window.update() && !window.input.hit(three::KEY_ESCAPE) {
+    ///     // ...
+    /// }
+    /// window.stop_recording_input().expect("Can't write the recording");
+    /// ```
+    pub fn record_input<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+    ) -> io::Result<()> {
+        self.recorder = Some(Recorder::create(path)?);
+        Ok(())
+    }
+
+    /// Stops recording the input events and writes the remaining ones to the
+    /// file. Does nothing if no recording is in progress.
+    pub fn stop_recording_input(&mut self) -> io::Result<()> {
+        match self.recorder.take() {
+            Some(mut recorder) => recorder.flush(),
+            None => Ok(()),
+        }
+    }
+
+    /// Returns `true` while the input events are being recorded.
+    pub fn is_recording_input(&self) -> bool {
+        self.recorder.is_some()
+    }
+
+    /// Replays the input events recorded to the file at `path` by
+    /// [`record_input`](#method.record_input), starting with the next
+    /// [`update`](#method.update).
+    ///
+    /// The recorded events are fed to [`input`](#structfield.input) at the
+    /// frame they were received in, and the recorded time between frames is
+    /// used as the delta time, so that the application sees the same input
+    /// as when recording regardless of the speed of the machine. Input events
+    /// of the window are ignored until the end of the recording, except for
+    /// closing the window.
+    pub fn play_input<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+    ) -> io::Result<()> {
+        self.playback = Some(Playback::open(path)?);
+        Ok(())
+    }
+
+    /// Stops replaying recorded input events, giving control back to the user.
+    pub fn stop_playing_input(&mut self) {
+        self.playback = None;
+    }
+
+    /// Returns `true` while recorded input events are being replayed.
+    pub fn is_playing_input(&self) -> bool {
+        self.playback.is_some()
+    }
+
     /// Render the current scene with specific [`Camera`](struct.Camera.html).
+    ///
+    /// Does nothing while the application is [suspended](#method.is_suspended).
     pub fn render(
         &mut self,
         camera: &Camera,
     ) {
-        self.renderer.render(&self.scene, camera);
+        if !self.suspended {
+            self.renderer.render(&self.scene, camera);
+        }
+    }
+
+    /// Returns `true` while the application is suspended, e.g. in the
+    /// background on Android or iOS.
+    ///
+    /// The rendering surface doesn't exist while suspended, so `render` skips
+    /// drawing. The surface is recreated when the application resumes.
+    pub fn is_suspended(&self) -> bool {
+        self.suspended
     }
 
     /// Get current window size in pixels.
@@ -243,6 +553,62 @@ impl Window {
         [size.0 as f32, size.1 as f32].into()
     }
 
+    /// Projects a world space `point` to window pixel coordinates as seen by `camera`,
+    /// measured from the top-left corner.
+    ///
+    /// Returns `None` if the point is behind the camera or outside of its clipping range.
+    pub fn world_to_screen<P: Into<mint::Point3<f32>>>(
+        &mut self,
+        camera: &Camera,
+        point: P,
+    ) -> Option<mint::Point2<f32>> {
+        use cgmath::{Decomposed, EuclideanSpace, Matrix4, Point3, Quaternion, SquareMatrix};
+
+        let (transform, projection) = {
+            let guard = self.scene.sync_guard();
+            (guard.resolve_world(camera).transform, guard.resolve_data(camera))
+        };
+        let mx_world = Matrix4::from(Decomposed {
+            disp: Point3::from(transform.position).to_vec(),
+            rot: Quaternion::from(transform.orientation),
+            scale: transform.scale,
+        });
+        let mx_view = match mx_world.invert() {
+            Some(mx) => mx,
+            None => return None,
+        };
+        let mx_proj = Matrix4::from(projection.matrix(self.renderer.aspect_ratio()));
+        let point = Point3::from(point.into());
+        let clip = mx_proj * mx_view * point.to_homogeneous();
+        if clip.w <= 0.0 {
+            return None;
+        }
+        let ndc = clip.truncate() / clip.w;
+        if ndc.z < -1.0 || ndc.z > 1.0 {
+            return None;
+        }
+        let size = self.size();
+        Some(mint::Point2 {
+            x: (ndc.x + 1.0) * 0.5 * size.x,
+            y: (1.0 - ndc.y) * 0.5 * size.y,
+        })
+    }
+
+    /// Returns the ray from `camera` through the window pixel `point`, measured
+    /// from the top-left corner, e.g. to pick objects under the mouse cursor.
+    ///
+    /// The ray starts on the near plane and reaches the far plane at distance `1.0`.
+    /// See [`raycast`](raycast/index.html).
+    pub fn screen_to_ray<P: Into<mint::Point2<f32>>>(
+        &mut self,
+        camera: &Camera,
+        point: P,
+    ) -> Ray {
+        let aspect_ratio = self.renderer.aspect_ratio();
+        let ndc = self.renderer.map_to_ndc(point);
+        Raycaster::from_camera(&mut self.scene, camera, aspect_ratio, ndc).ray
+    }
+
     /// Sets how the cursor should be handled.
     ///
     /// See the documentation for [`CursorState`] for the possible cursor states.